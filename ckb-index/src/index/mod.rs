@@ -1,17 +1,22 @@
 mod key;
 mod types;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt;
 use std::io;
+use std::time::Instant;
 
-use ckb_sdk::{Address, GenesisInfo, NetworkType};
+use ckb_sdk::{
+    trace::{self, TraceKind},
+    Address, GenesisInfo, NetworkType,
+};
 use ckb_types::{
     core::{BlockView, HeaderView},
     packed::{Byte32, Header, OutPoint, Script},
     prelude::*,
+    H256,
 };
-use rocksdb::{ColumnFamily, DB};
+use rocksdb::{ops::CompactRangeCF, ColumnFamily, DB};
 
 use crate::{KVReader, KVTxn, RocksReader, RocksTxn};
 pub use key::{Key, KeyMetrics, KeyType};
@@ -31,6 +36,10 @@ pub struct IndexDatabase<'a> {
     init_block_buf: Vec<BlockView>,
     // Disable record tx info by default
     enable_explorer: bool,
+    // Only index cells of these locks (see: WatchList), None means all
+    watch_list: Option<HashSet<H256>>,
+    // Live cells with less confirmations are ignored by queries
+    min_confirmations: u64,
 }
 
 impl<'a> IndexDatabase<'a> {
@@ -88,6 +97,8 @@ impl<'a> IndexDatabase<'a> {
             tip_header: genesis_header,
             init_block_buf: Vec::new(),
             enable_explorer,
+            watch_list: None,
+            min_confirmations: 0,
         })
     }
 
@@ -144,6 +155,18 @@ impl<'a> IndexDatabase<'a> {
         }
     }
 
+    /// The watch list must not change for an existing database, cells of
+    /// newly watched locks in indexed blocks are missing.
+    /// Live cells with less confirmations (counted to the last indexed block)
+    /// are ignored by queries of this database, 0 (the default) disables it
+    pub fn set_min_confirmations(&mut self, confirmations: u64) {
+        self.min_confirmations = confirmations;
+    }
+
+    pub fn set_watch_list(&mut self, watch_list: Option<HashSet<H256>>) {
+        self.watch_list = watch_list;
+    }
+
     pub fn update_tip(&mut self, header: HeaderView) {
         self.tip_header = header
     }
@@ -160,6 +183,15 @@ impl<'a> IndexDatabase<'a> {
         self.last_number().map(|number| number + 1)
     }
 
+    // Cells from this block number on have less than min confirmations
+    fn unconfirmed_from(&self) -> Option<u64> {
+        if self.min_confirmations == 0 {
+            return None;
+        }
+        let last_number = self.last_number().unwrap_or(0);
+        Some((last_number + 2).saturating_sub(self.min_confirmations))
+    }
+
     fn get_address_inner(&self, reader: &RocksReader, lock_hash: Byte32) -> Option<Address> {
         reader
             .get(&Key::LockScript(lock_hash.unpack()).to_bytes())
@@ -170,14 +202,28 @@ impl<'a> IndexDatabase<'a> {
     }
 
     pub fn get_capacity(&self, lock_hash: Byte32) -> Option<u64> {
+        let start = Instant::now();
         let reader = RocksReader::new(self.db, self.cf);
-        reader
+        let capacity_opt = reader
             .get(&Key::LockTotalCapacity(lock_hash.unpack()).to_bytes())
             .map(|bytes| {
                 let mut data = [0u8; 8];
                 data.copy_from_slice(&bytes[..8]);
                 u64::from_le_bytes(data)
-            })
+            });
+        trace::record(TraceKind::Db, "get_capacity", start.elapsed(), 1);
+        if let (Some(capacity), Some(from_number)) = (capacity_opt, self.unconfirmed_from()) {
+            // Young cells are at the end of the lock's cells (ordered by block number)
+            let key_prefix = Key::LockLiveCellIndexPrefix(lock_hash.clone().unpack(), None);
+            let key_start = Key::LockLiveCellIndexPrefix(lock_hash.unpack(), Some(from_number));
+            let unconfirmed = self
+                .live_cell_infos(key_prefix, key_start, |_, _| (false, true), false)
+                .iter()
+                .map(|info| info.capacity)
+                .sum::<u64>();
+            return Some(capacity.saturating_sub(unconfirmed));
+        }
+        capacity_opt
     }
 
     pub fn get_lock_hash_by_address(&self, address: Address) -> Option<Byte32> {
@@ -233,10 +279,20 @@ impl<'a> IndexDatabase<'a> {
     }
 
     pub fn get_live_cell_infos<F: FnMut(usize, &LiveCellInfo) -> (bool, bool)>(
+        &self,
+        key_prefix: Key,
+        key_start: Key,
+        terminator: F,
+    ) -> Vec<LiveCellInfo> {
+        self.live_cell_infos(key_prefix, key_start, terminator, true)
+    }
+
+    fn live_cell_infos<F: FnMut(usize, &LiveCellInfo) -> (bool, bool)>(
         &self,
         key_prefix: Key,
         key_start: Key,
         mut terminator: F,
+        confirmed_only: bool,
     ) -> Vec<LiveCellInfo> {
         fn get_live_cell_info(reader: &RocksReader, out_point: OutPoint) -> Option<LiveCellInfo> {
             reader
@@ -244,9 +300,11 @@ impl<'a> IndexDatabase<'a> {
                 .map(|bytes| bincode::deserialize(&bytes).unwrap())
         }
 
+        let start = Instant::now();
         let reader = RocksReader::new(self.db, self.cf);
         let key_prefix = key_prefix.to_bytes();
         let key_start = key_start.to_bytes();
+        let unconfirmed_from = self.unconfirmed_from().filter(|_| confirmed_only);
 
         let mut infos = Vec::new();
         for (idx, (key_bytes, value_bytes)) in reader.iter_from(&key_start).enumerate() {
@@ -256,6 +314,12 @@ impl<'a> IndexDatabase<'a> {
             }
             let out_point = OutPoint::new_unchecked(value_bytes.into());
             let live_cell_info = get_live_cell_info(&reader, out_point).unwrap();
+            if unconfirmed_from
+                .map(|number| live_cell_info.number >= number)
+                .unwrap_or(false)
+            {
+                continue;
+            }
             let (stop, push_info) = terminator(idx, &live_cell_info);
             if push_info {
                 infos.push(live_cell_info);
@@ -265,10 +329,17 @@ impl<'a> IndexDatabase<'a> {
                 break;
             }
         }
+        trace::record(
+            TraceKind::Db,
+            "get_live_cell_infos",
+            start.elapsed(),
+            infos.len(),
+        );
         infos
     }
 
     pub fn get_top_n(&self, n: usize) -> Vec<(Byte32, Option<Address>, u64)> {
+        let start = Instant::now();
         let reader = RocksReader::new(self.db, self.cf);
         let key_prefix: Vec<u8> = KeyType::LockTotalCapacityIndex.to_bytes();
 
@@ -288,6 +359,7 @@ impl<'a> IndexDatabase<'a> {
                 break;
             }
         }
+        trace::record(TraceKind::Db, "get_top_n", start.elapsed(), pairs.len());
         pairs
     }
 
@@ -315,8 +387,13 @@ impl<'a> IndexDatabase<'a> {
         let secp_type_hash = self.genesis_info.secp_type_hash();
         let mut txn = RocksTxn::new(self.db, self.cf);
         for block in blocks {
-            let block_delta_info =
-                BlockDeltaInfo::from_block(&block, &txn, secp_data_hash, secp_type_hash);
+            let block_delta_info = BlockDeltaInfo::from_block(
+                &block,
+                &txn,
+                secp_data_hash,
+                secp_type_hash,
+                self.watch_list.as_ref(),
+            );
             let number = block_delta_info.number();
             let hash = block_delta_info.hash();
             let result = block_delta_info.apply(&mut txn, self.enable_explorer);
@@ -339,11 +416,15 @@ impl<'a> IndexDatabase<'a> {
         if let Some(key_type) = key_type_opt {
             key_types.insert(key_type, KeyMetrics::default());
         } else {
-            let mut types = vec![
+            // History types are listed even if explorer is disabled, they may
+            // be left by an old index and can be removed by `prune`
+            let types = vec![
                 KeyType::GenesisHash,
                 KeyType::Network,
                 KeyType::LastHeader,
                 KeyType::TotalCapacity,
+                KeyType::GlobalHash,
+                KeyType::TxMap,
                 KeyType::SecpAddrLock,
                 KeyType::RecentHeader,
                 KeyType::BlockDelta,
@@ -353,12 +434,10 @@ impl<'a> IndexDatabase<'a> {
                 KeyType::LockTotalCapacity,
                 KeyType::LockTotalCapacityIndex,
                 KeyType::LockLiveCellIndex,
+                KeyType::LockTx,
                 KeyType::TypeLiveCellIndex,
                 KeyType::CodeLiveCellIndex,
             ];
-            if self.enable_explorer {
-                types.extend(vec![KeyType::TxMap, KeyType::LockTx, KeyType::GlobalHash]);
-            }
             for key_type in types {
                 key_types.insert(key_type, KeyMetrics::default());
             }
@@ -376,8 +455,86 @@ impl<'a> IndexDatabase<'a> {
         }
         key_types
     }
+
+    /// Remove history data: transactions (TxMap, LockTx and their GlobalHash)
+    /// of blocks before `keep_from_number`. If it is None all history is
+    /// removed, scripts of locks without live cells are also removed. Live
+    /// cells and rollback data are kept. The database is compacted afterwards
+    /// to release disk space. Returns the count of removed keys by type.
+    pub fn prune(
+        &self,
+        keep_from_number: Option<u64>,
+    ) -> Result<BTreeMap<KeyType, usize>, IndexError> {
+        let start = Instant::now();
+        let before_number = keep_from_number.unwrap_or(std::u64::MAX);
+        let mut removed_keys: Vec<Vec<u8>> = Vec::new();
+        let mut removed = BTreeMap::default();
+        {
+            let reader = RocksReader::new(self.db, self.cf);
+            let mut remove = |key_type: KeyType, key_bytes: Vec<u8>| {
+                *removed.entry(key_type).or_insert(0) += 1;
+                removed_keys.push(key_bytes);
+            };
+            let key_prefix = KeyType::TxMap.to_bytes();
+            for (key_bytes, value_bytes) in reader.iter_from(&key_prefix) {
+                if key_bytes[..key_prefix.len()] != key_prefix[..] {
+                    break;
+                }
+                let tx_info: TxInfo = bincode::deserialize(&value_bytes).unwrap();
+                if tx_info.block_number < before_number {
+                    let global_key = Key::GlobalHash(tx_info.tx_hash).to_bytes();
+                    if reader.get(&global_key).is_some() {
+                        remove(KeyType::GlobalHash, global_key);
+                    }
+                    remove(KeyType::TxMap, key_bytes);
+                }
+            }
+            let key_prefix = KeyType::LockTx.to_bytes();
+            for (key_bytes, _) in reader.iter_from(&key_prefix) {
+                if key_bytes[..key_prefix.len()] != key_prefix[..] {
+                    break;
+                }
+                if let Key::LockTx(_, number, _) = Key::from_bytes(&key_bytes) {
+                    if number < before_number {
+                        remove(KeyType::LockTx, key_bytes);
+                    }
+                }
+            }
+            if keep_from_number.is_none() {
+                let key_prefix = KeyType::LockScript.to_bytes();
+                for (key_bytes, _) in reader.iter_from(&key_prefix) {
+                    if key_bytes[..key_prefix.len()] != key_prefix[..] {
+                        break;
+                    }
+                    if let Key::LockScript(lock_hash) = Key::from_bytes(&key_bytes) {
+                        if reader
+                            .get(&Key::LockTotalCapacity(lock_hash).to_bytes())
+                            .is_none()
+                        {
+                            remove(KeyType::LockScript, key_bytes);
+                        }
+                    }
+                }
+            }
+        }
+        for keys in removed_keys.chunks(PRUNE_BATCH_SIZE) {
+            let mut txn = RocksTxn::new(self.db, self.cf);
+            for key in keys {
+                txn.remove_ok(key.clone());
+            }
+            txn.commit();
+        }
+        self.db
+            .compact_range_cf(self.cf, None, None)
+            .map_err(|err| IndexError::IoError(err.to_string()))?;
+        trace::record(TraceKind::Db, "prune", start.elapsed(), removed_keys.len());
+        Ok(removed)
+    }
 }
 
+// Keys removed in one write batch by `prune`
+const PRUNE_BATCH_SIZE: usize = 10_000;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum IndexError {
     BlockImmature(u64),
@@ -67,6 +67,7 @@ impl BlockDeltaInfo {
         reader: &'r T,
         _secp_data_hash: &Byte32,
         secp_type_hash: &Byte32,
+        watch_list: Option<&HashSet<H256>>,
     ) -> BlockDeltaInfo {
         let block_header: HeaderView = block.header().clone();
         let block_number = block_header.number();
@@ -121,13 +122,18 @@ impl BlockDeltaInfo {
                     .map(|input| input.previous_output())
                     .filter(|input| !input.is_null())
                 {
-                    let live_cell_info: LiveCellInfo =
-                        live_cell_infos.get(&out_point).cloned().unwrap_or_else(|| {
+                    let live_cell_info_opt: Option<LiveCellInfo> =
+                        live_cell_infos.get(&out_point).cloned().or_else(|| {
                             reader
                                 .get(&Key::LiveCellMap(out_point.clone()).to_bytes())
                                 .map(|bytes| bincode::deserialize(&bytes).unwrap())
-                                .unwrap()
                         });
+                    let live_cell_info = match live_cell_info_opt {
+                        Some(live_cell_info) => live_cell_info,
+                        // Cell of a lock not in the watch list
+                        None if watch_list.is_some() => continue,
+                        None => panic!("Live cell not found: {}", out_point),
+                    };
                     let lock_hash = live_cell_info.lock_hash.clone();
                     let capacity = live_cell_info.capacity;
                     inputs.push(live_cell_info);
@@ -156,6 +162,11 @@ impl BlockDeltaInfo {
                 {
                     let lock: Script = output.lock().clone();
                     let lock_hash = lock.calc_script_hash();
+                    if let Some(watch_list) = watch_list {
+                        if !watch_list.contains(&lock_hash.unpack()) {
+                            continue;
+                        }
+                    }
                     let capacity: Capacity = output.capacity().unpack();
                     let capacity = capacity.as_u64();
                     let cell_index = CellIndex::new(tx_index as u32, output_index as u32);
@@ -208,6 +219,7 @@ impl BlockDeltaInfo {
                     outputs,
                 }
             })
+            .filter(|tx| watch_list.is_none() || !tx.inputs.is_empty() || !tx.outputs.is_empty())
             .collect::<Vec<_>>();
 
         let locks_old_total: u64 = locks.values().map(|info| info.old_total_capacity).sum();
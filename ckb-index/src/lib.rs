@@ -2,6 +2,7 @@ mod error;
 mod index;
 mod kvdb;
 mod util;
+mod watch;
 
 pub use error::Error;
 pub use index::{
@@ -10,5 +11,6 @@ pub use index::{
 };
 pub use kvdb::{KVReader, KVTxn, RocksReader, RocksTxn};
 pub use util::{with_index_db, with_rocksdb};
+pub use watch::WatchList;
 
 const ROCKSDB_COL_INDEX_DB: &str = "index-db";
@@ -5,10 +5,10 @@ use std::time::{Duration, Instant};
 use ckb_types::H256;
 use rocksdb::{
     ops::{GetColumnFamilys, OpenCF},
-    ColumnFamily, Options, DB,
+    BlockBasedOptions, ColumnFamily, ColumnFamilyDescriptor, Options, DB,
 };
 
-use crate::{Error, ROCKSDB_COL_INDEX_DB};
+use crate::{Error, WatchList, ROCKSDB_COL_INDEX_DB};
 
 pub fn with_rocksdb<P, T, F>(path: P, timeout: Option<Duration>, func: F) -> Result<T, Error>
 where
@@ -22,9 +22,17 @@ where
     options.create_if_missing(true);
     options.create_missing_column_families(true);
     options.set_keep_log_file_num(32);
-    let columns = vec![ROCKSDB_COL_INDEX_DB];
+    // Flush and compaction run in background threads, so they do not block
+    // the interactive commands reading the database.
+    options.increase_parallelism(BACKGROUND_THREADS);
+    options.set_max_background_compactions(BACKGROUND_THREADS - 1);
+    options.set_max_background_flushes(1);
     loop {
-        match DB::open_cf(&options, &path, &columns) {
+        let columns = vec![ColumnFamilyDescriptor::new(
+            ROCKSDB_COL_INDEX_DB,
+            index_cf_options(),
+        )];
+        match DB::open_cf_descriptors(&options, &path, columns) {
             Ok(db) => break func(&db),
             Err(err) => {
                 if start.elapsed() >= timeout {
@@ -42,13 +50,31 @@ where
     }
 }
 
+const BACKGROUND_THREADS: i32 = 4;
+// Bits per key of the bloom filter
+const BLOOM_FILTER_BITS: i32 = 10;
+const BLOCK_CACHE_SIZE: usize = 32 * 1024 * 1024;
+
+// Listing live cells is a prefix scan over the lock/type/code index followed by
+// a point lookup of every cell (LiveCellMap), the bloom filter and block cache
+// make those lookups cheap.
+fn index_cf_options() -> Options {
+    let mut block_options = BlockBasedOptions::default();
+    block_options.set_bloom_filter(BLOOM_FILTER_BITS, false);
+    block_options.set_lru_cache(BLOCK_CACHE_SIZE);
+    let mut options = Options::default();
+    options.set_block_based_table_factory(&block_options);
+    options
+}
+
 pub fn with_index_db<P, T, F>(path: P, genesis_hash: H256, func: F) -> Result<T, Error>
 where
     P: AsRef<Path>,
     F: FnOnce(&DB, &ColumnFamily) -> Result<T, Error>,
 {
+    let watch_list = WatchList::load(&path)?;
     let mut directory = path.as_ref().to_path_buf();
-    directory.push(format!("{:#x}", genesis_hash));
+    directory.push(watch_list.db_dir_name(&genesis_hash));
     std::fs::create_dir_all(&directory)?;
     with_rocksdb(directory, None, |db| {
         let cf = db
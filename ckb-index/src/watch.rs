@@ -0,0 +1,87 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_hash::blake2b_256;
+use ckb_types::H256;
+
+use crate::Error;
+
+/// Lock scripts (by lock hash) to index, saved in `<index-dir>/watch-list.json`.
+/// When the list is empty every lock is indexed.
+///
+/// Each watch list has its own database directory, so the index is synced
+/// from genesis again after the list changed.
+pub struct WatchList {
+    path: PathBuf,
+    lock_hashes: BTreeSet<H256>,
+}
+
+impl WatchList {
+    pub fn load<P: AsRef<Path>>(index_dir: P) -> Result<WatchList, Error> {
+        let path = index_dir.as_ref().join("watch-list.json");
+        let lock_hashes = if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            serde_json::from_str(&content).map_err(|err| {
+                Error::Other(format!("Invalid watch list file {:?}: {}", path, err))
+            })?
+        } else {
+            BTreeSet::new()
+        };
+        Ok(WatchList { path, lock_hashes })
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(&self.lock_hashes)
+            .map_err(|err| Error::Other(err.to_string()))?;
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    pub fn lock_hashes(&self) -> &BTreeSet<H256> {
+        &self.lock_hashes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock_hashes.is_empty()
+    }
+
+    /// Returns false if the lock hash is already watched
+    pub fn add(&mut self, lock_hash: H256) -> bool {
+        self.lock_hashes.insert(lock_hash)
+    }
+
+    /// Returns false if the lock hash is not watched
+    pub fn remove(&mut self, lock_hash: &H256) -> bool {
+        self.lock_hashes.remove(lock_hash)
+    }
+
+    /// Lock hashes to filter blocks with, None means index every lock
+    pub fn filter(&self) -> Option<HashSet<H256>> {
+        if self.lock_hashes.is_empty() {
+            None
+        } else {
+            Some(self.lock_hashes.iter().cloned().collect())
+        }
+    }
+
+    /// Database directory name of the index under this watch list
+    pub fn db_dir_name(&self, genesis_hash: &H256) -> String {
+        if self.lock_hashes.is_empty() {
+            return format!("{:#x}", genesis_hash);
+        }
+        let mut data = Vec::with_capacity(self.lock_hashes.len() * 32);
+        for lock_hash in &self.lock_hashes {
+            data.extend_from_slice(lock_hash.as_bytes());
+        }
+        let hash = blake2b_256(&data);
+        let short_hash = hash[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        format!("{:#x}-watch-{}", genesis_hash, short_hash)
+    }
+}
@@ -11,6 +11,11 @@ use ckb_types::{
 };
 use serde_derive::{Deserialize, Serialize};
 
+use crate::network::{
+    activate_custom_network, active_custom_prefix, find_custom_network_by_name,
+    find_custom_network_by_prefix,
+};
+
 pub use old_addr::{Address as OldAddress, AddressFormat as OldAddressFormat};
 
 const PREFIX_MAINNET: &str = "ckb";
@@ -38,24 +43,37 @@ impl NetworkType {
         match value {
             PREFIX_MAINNET => Some(NetworkType::MainNet),
             PREFIX_TESTNET => Some(NetworkType::TestNet),
-            _ => None,
+            _ if active_custom_prefix() == Some(value) => Some(NetworkType::Dev),
+            // Any registered network, activated if the node is not known yet
+            _ => find_custom_network_by_prefix(value).map(|network| {
+                if active_custom_prefix().is_none() {
+                    activate_custom_network(&network);
+                }
+                NetworkType::Dev
+            }),
         }
     }
 
+    /// Dev chains use the prefix of the active custom network if any
     pub fn to_prefix(self) -> &'static str {
         match self {
             NetworkType::MainNet => PREFIX_MAINNET,
             NetworkType::TestNet => PREFIX_TESTNET,
-            NetworkType::Dev => PREFIX_TESTNET,
+            NetworkType::Dev => active_custom_prefix().unwrap_or(PREFIX_TESTNET),
         }
     }
 
+    /// Custom networks (see: `set_custom_networks`) are dev chains, the
+    /// matched one is activated
     pub fn from_raw_str(value: &str) -> Option<NetworkType> {
         match value {
             "ckb" => Some(NetworkType::MainNet),
             "ckb_testnet" => Some(NetworkType::TestNet),
             "ckb_dev" => Some(NetworkType::Dev),
-            _ => None,
+            _ => find_custom_network_by_name(value).map(|network| {
+                activate_custom_network(&network);
+                NetworkType::Dev
+            }),
         }
     }
 
@@ -288,4 +306,28 @@ mod test {
             "ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p"
         );
     }
+
+    #[test]
+    fn test_custom_prefix_address() {
+        crate::set_custom_networks(vec![crate::CustomNetwork {
+            name: "ckb_consortium".to_owned(),
+            genesis_hash: H256::default(),
+            address_prefix: "ckc".to_owned(),
+            secp_dep: None,
+            dao_dep: None,
+        }]);
+        let hash = h160!("0x13e41d6F9292555916f17B4882a5477C01270142");
+        let mut data = [0; 22];
+        data[0] = AddressType::Default as u8;
+        data[1] = CodeHashIndex::Default as u8;
+        data[2..22].copy_from_slice(hash.as_bytes());
+        let input = Bech32::new("ckc".to_owned(), data.to_base32())
+            .unwrap()
+            .to_string();
+        let (network, address) = Address::from_input(&input).unwrap();
+        assert_eq!(network, NetworkType::Dev);
+        assert_eq!(address.hash(), &hash);
+        assert_eq!(address.to_string(NetworkType::Dev), input);
+        assert!(Address::from_input(&input.replacen("ckc", "ckd", 1)).is_err());
+    }
 }
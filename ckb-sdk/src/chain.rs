@@ -1,3 +1,4 @@
+use crate::network::{activate_custom_network, find_custom_network_by_genesis, CustomNetwork};
 use crate::Address;
 use ckb_crypto::secp::SECP256K1;
 use ckb_hash::new_blake2b;
@@ -37,6 +38,8 @@ const SECP_GROUP_TRANSACTION_INDEX: usize = 1;
 const SECP_GROUP_OUTPUT_INDEX: usize = 0;
 const DAO_TRANSACTION_INDEX: usize = 0;
 const DAO_OUTPUT_INDEX: usize = 2;
+const MULTISIG_TRANSACTION_INDEX: usize = 0;
+const MULTISIG_OUTPUT_INDEX: usize = 4;
 
 #[derive(Debug, Clone)]
 pub struct GenesisInfo {
@@ -46,6 +49,11 @@ pub struct GenesisInfo {
     secp_type_hash: Byte32,
     dao_data_hash: Byte32,
     dao_type_hash: Byte32,
+    // Not deployed in genesis of old chains
+    multisig_type_hash: Option<Byte32>,
+    // Deps of a custom network deployed out of genesis
+    secp_dep_override: Option<OutPoint>,
+    dao_dep_override: Option<OutPoint>,
 }
 
 impl GenesisInfo {
@@ -62,6 +70,7 @@ impl GenesisInfo {
         let mut secp_type_hash = None;
         let mut dao_data_hash = None;
         let mut dao_type_hash = None;
+        let mut multisig_type_hash = None;
         let out_points = genesis_block
             .transactions()
             .iter()
@@ -102,6 +111,13 @@ impl GenesisInfo {
                             }
                             dao_data_hash = Some(data_hash);
                         }
+                        if tx_index == MULTISIG_TRANSACTION_INDEX && index == MULTISIG_OUTPUT_INDEX
+                        {
+                            multisig_type_hash = output
+                                .type_()
+                                .to_opt()
+                                .map(|script| script.calc_script_hash());
+                        }
                         OutPoint::new(tx.hash(), index as u32)
                     })
                     .collect::<Vec<_>>()
@@ -116,6 +132,10 @@ impl GenesisInfo {
             dao_data_hash.ok_or_else(|| "No data hash(dao) found in txs[0][2]".to_owned())?;
         let dao_type_hash =
             dao_type_hash.ok_or_else(|| "No type hash(dao) found in txs[0][2]".to_owned())?;
+        let custom_network = find_custom_network_by_genesis(&header.hash().unpack());
+        if let Some(network) = custom_network.as_ref() {
+            activate_custom_network(network);
+        }
         Ok(GenesisInfo {
             header,
             out_points,
@@ -123,6 +143,13 @@ impl GenesisInfo {
             secp_type_hash,
             dao_data_hash,
             dao_type_hash,
+            multisig_type_hash,
+            secp_dep_override: custom_network
+                .as_ref()
+                .and_then(CustomNetwork::secp_dep_out_point),
+            dao_dep_override: custom_network
+                .as_ref()
+                .and_then(CustomNetwork::dao_dep_out_point),
         })
     }
 
@@ -146,19 +173,27 @@ impl GenesisInfo {
         &self.dao_type_hash
     }
 
+    /// Type hash of secp256k1_blake160_multisig_all
+    pub fn multisig_type_hash(&self) -> Option<&Byte32> {
+        self.multisig_type_hash.as_ref()
+    }
+
     pub fn secp_dep(&self) -> CellDep {
+        let out_point = self.secp_dep_override.clone().unwrap_or_else(|| {
+            self.out_points[SECP_GROUP_TRANSACTION_INDEX][SECP_GROUP_OUTPUT_INDEX].clone()
+        });
         CellDep::new_builder()
-            .out_point(
-                self.out_points[SECP_GROUP_TRANSACTION_INDEX][SECP_GROUP_OUTPUT_INDEX].clone(),
-            )
+            .out_point(out_point)
             .dep_type(DepType::DepGroup.into())
             .build()
     }
 
     pub fn dao_dep(&self) -> CellDep {
-        CellDep::new_builder()
-            .out_point(self.out_points[DAO_TRANSACTION_INDEX][DAO_OUTPUT_INDEX].clone())
-            .build()
+        let out_point = self
+            .dao_dep_override
+            .clone()
+            .unwrap_or_else(|| self.out_points[DAO_TRANSACTION_INDEX][DAO_OUTPUT_INDEX].clone());
+        CellDep::new_builder().out_point(out_point).build()
     }
 }
 
@@ -173,10 +208,16 @@ pub struct TransferTransactionBuilder<'a> {
     tx_fee: u64,
 
     inputs: Vec<CellInput>,
+    // Replace the single output to `to_address` if set
+    custom_outputs: Option<Vec<(CellOutput, Bytes)>>,
+    // Send the change to this address instead of `from_address` if set
+    change_address: Option<&'a Address>,
     outputs: Vec<(CellOutput, Bytes)>,
     changes: Vec<(CellOutput, Bytes)>,
     cell_deps: Vec<CellDep>,
     header_deps: Vec<Byte32>,
+    // Header deps given by user, put after the ones required by NervosDAO
+    extra_header_deps: Vec<Byte32>,
     witnesses: Vec<Bytes>,
 }
 
@@ -205,13 +246,40 @@ impl<'a> TransferTransactionBuilder<'a> {
             inputs,
             witnesses,
 
+            custom_outputs: None,
+            change_address: None,
             outputs: Vec::new(),
             changes: Vec::new(),
             cell_deps: Vec::new(),
             header_deps: Vec::new(),
+            extra_header_deps: Vec::new(),
         }
     }
 
+    /// Send to many outputs (with their own lock, type and data) instead of
+    /// `to_address`, total capacity of the outputs must equal to `to_capacity`.
+    pub fn set_outputs(&mut self, outputs: Vec<(CellOutput, Bytes)>) {
+        self.custom_outputs = Some(outputs);
+    }
+
+    /// Send the change to another address of the sender (e.g. a HD change
+    /// address)
+    pub fn set_change_address(&mut self, change_address: &'a Address) {
+        self.change_address = Some(change_address);
+    }
+
+    /// Cell deps of scripts in the outputs (secp256k1 dep is always added)
+    pub fn add_cell_deps(&mut self, cell_deps: Vec<CellDep>) {
+        self.cell_deps.extend(cell_deps);
+    }
+
+    /// Header deps required by scripts (e.g. loading headers for since by
+    /// epoch checks), duplicated ones are skipped
+    pub fn add_header_deps(&mut self, header_deps: Vec<H256>) {
+        self.extra_header_deps
+            .extend(header_deps.into_iter().map(|hash| hash.pack()));
+    }
+
     pub fn transfer<F>(
         &mut self,
         genesis_info: &GenesisInfo,
@@ -314,6 +382,10 @@ impl<'a> TransferTransactionBuilder<'a> {
     }
 
     fn build_outputs(&mut self, genesis_info: &GenesisInfo) {
+        if let Some(outputs) = self.custom_outputs.as_ref() {
+            self.outputs.extend(outputs.iter().cloned());
+            return;
+        }
         let output = CellOutput::new_builder()
             .capacity(Capacity::shannons(self.to_capacity).pack())
             .lock(
@@ -333,7 +405,8 @@ impl<'a> TransferTransactionBuilder<'a> {
             let change = CellOutput::new_builder()
                 .capacity(Capacity::shannons(rest_capacity).pack())
                 .lock(
-                    self.from_address
+                    self.change_address
+                        .unwrap_or(self.from_address)
                         .lock_script(genesis_info.secp_type_hash.to_owned()),
                 )
                 .build();
@@ -370,6 +443,23 @@ impl<'a> TransferTransactionBuilder<'a> {
             .outputs_data(changes_data.iter().map(Pack::pack))
             .cell_deps(self.cell_deps.clone())
             .header_deps(self.header_deps.clone())
+            .header_deps(
+                self.extra_header_deps
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, hash)| {
+                        let duplicated = self
+                            .header_deps
+                            .iter()
+                            .chain(self.extra_header_deps[..index].iter())
+                            .any(|dep| dep.as_slice() == hash.as_slice());
+                        if duplicated {
+                            None
+                        } else {
+                            Some(hash.clone())
+                        }
+                    }),
+            )
             .witnesses(self.witnesses.pack())
             .build()
     }
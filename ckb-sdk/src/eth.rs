@@ -0,0 +1,50 @@
+use ckb_types::{H160, H256};
+
+const PERSONAL_MESSAGE_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n32";
+
+/// Ethereum address: last 20 bytes of keccak256 of the uncompressed pubkey
+pub fn eth_address(pubkey: &secp256k1::PublicKey) -> H160 {
+    let hash = tiny_keccak::keccak256(&pubkey.serialize_uncompressed()[1..]);
+    H160::from_slice(&hash[12..32]).expect("Convert to H160 failed")
+}
+
+/// Hash signed by ethereum `personal_sign` for a 32 bytes message
+pub fn eth_personal_message(message: &H256) -> H256 {
+    let mut data = PERSONAL_MESSAGE_PREFIX.to_vec();
+    data.extend_from_slice(message.as_bytes());
+    H256::from(tiny_keccak::keccak256(&data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_crypto::secp::SECP256K1;
+    use ckb_types::h160;
+
+    #[test]
+    fn test_eth() {
+        let mut privkey_bytes = [0u8; 32];
+        privkey_bytes[31] = 1;
+        let privkey = secp256k1::SecretKey::from_slice(&privkey_bytes).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+        assert_eq!(
+            eth_address(&pubkey),
+            h160!("0x7e5f4552091a69125d5dfcb7b8c2659029395bdf")
+        );
+
+        let message = H256::from([1u8; 32]);
+        let hash = eth_personal_message(&message);
+        assert_ne!(hash, message);
+        let signature = SECP256K1.sign_recoverable(
+            &secp256k1::Message::from_slice(hash.as_bytes()).unwrap(),
+            &privkey,
+        );
+        let recovered = SECP256K1
+            .recover(
+                &secp256k1::Message::from_slice(hash.as_bytes()).unwrap(),
+                &signature,
+            )
+            .unwrap();
+        assert_eq!(eth_address(&recovered), eth_address(&pubkey));
+    }
+}
@@ -0,0 +1,378 @@
+use std::convert::TryInto;
+
+use bech32::convert_bits;
+use ckb_types::{bytes::Bytes, core::ScriptHashType, h256, packed::Script, prelude::*, H160, H256};
+use failure::Error as FailureError;
+
+use crate::basic::{Address, NetworkType};
+
+/// Type hash of secp256k1_blake160_sighash_all on mainnet and testnet (Aggron)
+pub const SECP_TYPE_HASH: H256 =
+    h256!("0x9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8");
+
+// See: https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0021-ckb-address-format/0021-ckb-address-format.md
+const FORMAT_FULL: u8 = 0x00;
+const FORMAT_SHORT: u8 = 0x01;
+const FORMAT_FULL_DATA: u8 = 0x02;
+const FORMAT_FULL_TYPE: u8 = 0x04;
+const SHORT_CODE_HASH_INDEX_SIGHASH: u8 = 0x00;
+
+/// Payload format of an encoded address
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AddressPayloadFormat {
+    // Deprecated, code hash index + args
+    Short,
+    // Deprecated, data code hash + args
+    FullData,
+    // Deprecated, type code hash + args
+    FullType,
+    // CKB2021 full format (bech32m), code hash + hash type + args
+    Full,
+}
+
+impl AddressPayloadFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            AddressPayloadFormat::Short => "short",
+            AddressPayloadFormat::FullData => "full-data",
+            AddressPayloadFormat::FullType => "full-type",
+            AddressPayloadFormat::Full => "full",
+        }
+    }
+
+    pub fn is_deprecated(self) -> bool {
+        self != AddressPayloadFormat::Full
+    }
+}
+
+/// Address of any lock script
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FullAddress {
+    code_hash: H256,
+    hash_type: ScriptHashType,
+    args: Bytes,
+}
+
+impl FullAddress {
+    pub fn new(code_hash: H256, hash_type: ScriptHashType, args: Bytes) -> FullAddress {
+        FullAddress {
+            code_hash,
+            hash_type,
+            args,
+        }
+    }
+
+    pub fn from_script(script: &Script) -> Result<FullAddress, String> {
+        let hash_type = script
+            .hash_type()
+            .try_into()
+            .map_err(|err: FailureError| err.to_string())?;
+        Ok(FullAddress::new(
+            script.code_hash().unpack(),
+            hash_type,
+            script.args().raw_data(),
+        ))
+    }
+
+    /// Full address of the default secp256k1 lock
+    pub fn from_short(address: &Address, secp_type_hash: &H256) -> FullAddress {
+        FullAddress::new(
+            secp_type_hash.clone(),
+            ScriptHashType::Type,
+            Bytes::from(address.hash().as_bytes()),
+        )
+    }
+
+    pub fn code_hash(&self) -> &H256 {
+        &self.code_hash
+    }
+
+    pub fn hash_type(&self) -> ScriptHashType {
+        self.hash_type
+    }
+
+    pub fn args(&self) -> &Bytes {
+        &self.args
+    }
+
+    pub fn script(&self) -> Script {
+        Script::new_builder()
+            .code_hash(self.code_hash.pack())
+            .hash_type(self.hash_type.into())
+            .args(self.args.pack())
+            .build()
+    }
+
+    /// The short address, only when this is the default secp256k1 lock
+    pub fn to_short(&self, secp_type_hash: &H256) -> Option<Address> {
+        if &self.code_hash == secp_type_hash
+            && self.hash_type == ScriptHashType::Type
+            && self.args.len() == 20
+        {
+            Address::from_lock_arg(&self.args).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Encode in the CKB2021 full format
+    pub fn to_string(&self, network: NetworkType) -> String {
+        let mut data = vec![FORMAT_FULL];
+        data.extend_from_slice(self.code_hash.as_bytes());
+        data.push(self.hash_type as u8);
+        data.extend_from_slice(&self.args);
+        encode(network.to_prefix(), &data, Variant::Bech32m)
+    }
+
+    /// Encode in the deprecated full-data/full-type format
+    pub fn to_deprecated_string(&self, network: NetworkType) -> String {
+        let format = match self.hash_type {
+            ScriptHashType::Data => FORMAT_FULL_DATA,
+            ScriptHashType::Type => FORMAT_FULL_TYPE,
+        };
+        let mut data = vec![format];
+        data.extend_from_slice(self.code_hash.as_bytes());
+        data.extend_from_slice(&self.args);
+        encode(network.to_prefix(), &data, Variant::Bech32)
+    }
+
+    /// Decode address in any format, `secp_type_hash` is the code hash of short addresses
+    pub fn from_input(
+        input: &str,
+        secp_type_hash: &H256,
+    ) -> Result<(NetworkType, FullAddress, AddressPayloadFormat), String> {
+        let (hrp, data, variant) = decode(input)?;
+        let network =
+            NetworkType::from_prefix(&hrp).ok_or_else(|| format!("Invalid hrp: {}", hrp))?;
+        if data.is_empty() {
+            return Err("Empty address payload".to_owned());
+        }
+        let expected_variant = if data[0] == FORMAT_FULL {
+            Variant::Bech32m
+        } else {
+            Variant::Bech32
+        };
+        if variant != expected_variant {
+            return Err(format!(
+                "Invalid checksum variant for address format {:#04x}",
+                data[0]
+            ));
+        }
+        let code_hash_at = |offset: usize| {
+            if data.len() < offset + 32 {
+                return Err(format!("Invalid input data length {}", data.len()));
+            }
+            H256::from_slice(&data[offset..offset + 32]).map_err(|err| err.to_string())
+        };
+        let (address, format) = match data[0] {
+            FORMAT_SHORT => {
+                if data.len() != 22 {
+                    return Err(format!("Invalid input data length {}", data.len()));
+                }
+                if data[1] != SHORT_CODE_HASH_INDEX_SIGHASH {
+                    return Err(format!("Unsupported code hash index: {:#04x}", data[1]));
+                }
+                let address = Address::new_default(
+                    H160::from_slice(&data[2..22]).map_err(|err| err.to_string())?,
+                );
+                (
+                    FullAddress::from_short(&address, secp_type_hash),
+                    AddressPayloadFormat::Short,
+                )
+            }
+            FORMAT_FULL_DATA | FORMAT_FULL_TYPE => {
+                let code_hash = code_hash_at(1)?;
+                let (hash_type, format) = if data[0] == FORMAT_FULL_DATA {
+                    (ScriptHashType::Data, AddressPayloadFormat::FullData)
+                } else {
+                    (ScriptHashType::Type, AddressPayloadFormat::FullType)
+                };
+                let args = Bytes::from(&data[33..]);
+                (FullAddress::new(code_hash, hash_type, args), format)
+            }
+            FORMAT_FULL => {
+                let code_hash = code_hash_at(1)?;
+                let hash_type = match data.get(33) {
+                    Some(0) => ScriptHashType::Data,
+                    Some(1) => ScriptHashType::Type,
+                    Some(value) => {
+                        return Err(format!(
+                            "Unsupported script hash type in address: {}",
+                            value
+                        ))
+                    }
+                    None => return Err(format!("Invalid input data length {}", data.len())),
+                };
+                let args = Bytes::from(&data[34..]);
+                (
+                    FullAddress::new(code_hash, hash_type, args),
+                    AddressPayloadFormat::Full,
+                )
+            }
+            value => return Err(format!("Invalid address format: {:#04x}", value)),
+        };
+        Ok((network, address, format))
+    }
+}
+
+// Bech32 (BIP173) and bech32m (BIP350), the `bech32` crate does not support
+// bech32m and limits the length to 90 characters which full addresses exceed.
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => BECH32_CONST,
+            Variant::Bech32m => BECH32M_CONST,
+        }
+    }
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(*value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut values = hrp.bytes().map(|byte| byte >> 5).collect::<Vec<_>>();
+    values.push(0);
+    values.extend(hrp.bytes().map(|byte| byte & 0x1f));
+    values
+}
+
+fn encode(hrp: &str, data: &[u8], variant: Variant) -> String {
+    let data = convert_bits(data, 8, 5, true).expect("convert bits with padding");
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    values.extend_from_slice(&[0; 6]);
+    let checksum = polymod(&values) ^ variant.constant();
+    let mut output = format!("{}1", hrp);
+    output.extend(
+        data.iter()
+            .cloned()
+            .chain((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 0x1f) as u8))
+            .map(|value| char::from(CHARSET[value as usize])),
+    );
+    output
+}
+
+fn decode(input: &str) -> Result<(String, Vec<u8>, Variant), String> {
+    let lowercase = input.to_lowercase();
+    if lowercase != input && input.to_uppercase() != input {
+        return Err("Mixed case address".to_owned());
+    }
+    let sep = lowercase
+        .rfind('1')
+        .ok_or_else(|| "Missing address separator".to_owned())?;
+    let (hrp, data) = (&lowercase[..sep], &lowercase[sep + 1..]);
+    if hrp.is_empty() || data.len() < 6 {
+        return Err("Invalid address length".to_owned());
+    }
+    let values = data
+        .bytes()
+        .map(|byte| {
+            CHARSET
+                .iter()
+                .position(|c| *c == byte)
+                .map(|value| value as u8)
+                .ok_or_else(|| format!("Invalid address character: {}", char::from(byte)))
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    let mut checked = hrp_expand(hrp);
+    checked.extend_from_slice(&values);
+    let variant = match polymod(&checked) {
+        BECH32_CONST => Variant::Bech32,
+        BECH32M_CONST => Variant::Bech32m,
+        _ => return Err("Invalid address checksum".to_owned()),
+    };
+    let payload = convert_bits(&values[..values.len() - 6], 5, 8, false)
+        .map_err(|err| format!("Invalid address payload: {}", err))?;
+    Ok((hrp.to_owned(), payload, variant))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::h160;
+
+    #[test]
+    fn test_full_address() {
+        // BIP350 test vector
+        assert_eq!(
+            decode("a1lqfn3a"),
+            Ok(("a".to_owned(), Vec::new(), Variant::Bech32m))
+        );
+
+        let short = "ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p";
+        let (network, address, format) = FullAddress::from_input(short, &SECP_TYPE_HASH).unwrap();
+        assert_eq!(network, NetworkType::MainNet);
+        assert_eq!(format, AddressPayloadFormat::Short);
+        assert_eq!(
+            address.to_short(&SECP_TYPE_HASH),
+            Some(Address::new_default(h160!(
+                "0x13e41d6F9292555916f17B4882a5477C01270142"
+            )))
+        );
+
+        let full = address.to_string(NetworkType::MainNet);
+        assert!(full.starts_with("ckb1q"));
+        assert_eq!(
+            FullAddress::from_input(&full, &SECP_TYPE_HASH),
+            Ok((
+                NetworkType::MainNet,
+                address.clone(),
+                AddressPayloadFormat::Full
+            ))
+        );
+        let deprecated = address.to_deprecated_string(NetworkType::TestNet);
+        assert_eq!(
+            FullAddress::from_input(&deprecated, &SECP_TYPE_HASH),
+            Ok((
+                NetworkType::TestNet,
+                address.clone(),
+                AddressPayloadFormat::FullType
+            ))
+        );
+
+        // Arbitrary code hash and args
+        let other = FullAddress::new(
+            h256!("0x1"),
+            ScriptHashType::Data,
+            Bytes::from(vec![1u8; 40]),
+        );
+        let encoded = other.to_string(NetworkType::TestNet);
+        let (_, decoded, _) = FullAddress::from_input(&encoded, &SECP_TYPE_HASH).unwrap();
+        assert_eq!(decoded, other);
+        assert_eq!(decoded.to_short(&SECP_TYPE_HASH), None);
+
+        // Broken checksum
+        let mut broken = encoded.clone();
+        broken.pop();
+        broken.push(if encoded.ends_with('q') { 'p' } else { 'q' });
+        assert!(FullAddress::from_input(&broken, &SECP_TYPE_HASH).is_err());
+    }
+}
@@ -1,9 +1,15 @@
 mod basic;
 mod chain;
 mod error;
+mod eth;
+mod full_address;
+mod merkle;
+mod network;
+mod omnilock;
 mod rpc;
 mod transaction;
 
+pub mod trace;
 pub mod wallet;
 
 pub use basic::{Address, NetworkType, OldAddress, OldAddressFormat};
@@ -12,10 +18,27 @@ pub use chain::{
     TransferTransactionBuilder, MIN_SECP_CELL_CAPACITY, ONE_CKB,
 };
 pub use error::Error;
-pub use rpc::HttpRpcClient;
+pub use eth::{eth_address, eth_personal_message};
+pub use full_address::{AddressPayloadFormat, FullAddress, SECP_TYPE_HASH};
+pub use merkle::{cbmt_proof_root, transactions_root_from_proof};
+pub use network::{
+    activate_custom_network, custom_networks, find_custom_network_by_genesis,
+    find_custom_network_by_name, set_custom_networks, CustomNetwork,
+};
+pub use omnilock::{
+    is_omnilock, omnilock_type_hash, omnilock_witness_template, omnilock_witness_templates,
+    OmniAuth, OMNILOCK_MAINNET_TYPE_HASH, OMNILOCK_TESTNET_TYPE_HASH,
+};
+pub use rpc::{
+    http_client, is_offline, is_unsupported_method, rpc_cache_stats, rpc_client_options,
+    set_offline, set_rpc_cache_size, set_rpc_client_options, HttpRpcClient, MerkleProof,
+    RpcClientOptions, TransactionProof, DEFAULT_RPC_CACHE_SIZE,
+};
 pub use transaction::{
-    MockCellDep, MockInfo, MockInput, MockResourceLoader, MockTransaction, MockTransactionHelper,
-    ReprMockCellDep, ReprMockInfo, ReprMockInput, ReprMockTransaction,
+    multisig_config, multisig_lock_size, sign_in_parallel, FeeEstimate, MockCellDep, MockInfo,
+    MockInput, MockResourceLoader, MockTransaction, MockTransactionHelper, ReprMockCellDep,
+    ReprMockInfo, ReprMockInput, ReprMockTransaction, ResignGroup, Severity, SignProgress,
+    TxFinding, WitnessEstimate, WitnessField, WitnessTemplate,
 };
 
 pub use ckb_crypto::secp::SECP256K1;
@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+
+use ckb_hash::new_blake2b;
+use ckb_types::H256;
+
+use crate::rpc::TransactionProof;
+
+fn merge(left: &H256, right: &H256) -> H256 {
+    let mut blake2b = new_blake2b();
+    blake2b.update(left.as_bytes());
+    blake2b.update(right.as_bytes());
+    let mut digest = [0u8; 32];
+    blake2b.finalize(&mut digest);
+    H256::from_slice(&digest).expect("converting digest of [u8; 32] to H256 should be ok")
+}
+
+fn sibling(index: u32) -> u32 {
+    ((index + 1) ^ 1) - 1
+}
+
+fn parent(index: u32) -> u32 {
+    (index - 1) >> 1
+}
+
+fn is_left(index: u32) -> bool {
+    index & 1 == 1
+}
+
+/// Calculate the root of a CBMT (complete binary merkle tree) proof
+///
+/// `indices` are the tree node indices of `leaves`, `lemmas` are the sibling
+/// nodes required to calculate the root.
+pub fn cbmt_proof_root(indices: &[u32], lemmas: &[H256], leaves: &[H256]) -> Option<H256> {
+    if leaves.is_empty() || leaves.len() != indices.len() {
+        return None;
+    }
+    let mut nodes: Vec<(u32, H256)> = indices
+        .iter()
+        .cloned()
+        .zip(leaves.iter().cloned())
+        .collect();
+    nodes.sort_by(|a, b| b.0.cmp(&a.0));
+    let mut lemmas_iter = lemmas.iter();
+    let mut queue: VecDeque<(u32, H256)> = nodes.into();
+    while let Some((index, node)) = queue.pop_front() {
+        if index == 0 {
+            // All lemmas and nodes must be consumed
+            if lemmas_iter.next().is_none() && queue.is_empty() {
+                return Some(node);
+            } else {
+                return None;
+            }
+        }
+        let sibling_node = match queue.front() {
+            Some((front, _)) if *front == sibling(index) => queue.pop_front().map(|item| item.1),
+            _ => lemmas_iter.next().cloned(),
+        }?;
+        let parent_node = if is_left(index) {
+            merge(&node, &sibling_node)
+        } else {
+            merge(&sibling_node, &node)
+        };
+        queue.push_back((parent(index), parent_node));
+    }
+    None
+}
+
+/// Calculate the transactions root in block header from a single transaction
+/// proof, the result should equal to `header.transactions_root`.
+pub fn transactions_root_from_proof(proof: &TransactionProof, tx_hash: &H256) -> Option<H256> {
+    let indices = proof
+        .proof
+        .indices
+        .iter()
+        .map(|index| index.value())
+        .collect::<Vec<_>>();
+    cbmt_proof_root(&indices, &proof.proof.lemmas, &[tx_hash.clone()])
+        .map(|raw_transactions_root| merge(&raw_transactions_root, &proof.witnesses_root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::h256;
+
+    #[test]
+    fn test_cbmt_proof_root() {
+        let leaves = vec![h256!("0x1"), h256!("0x2"), h256!("0x3")];
+        // Tree nodes: [root, n1, leaf0, leaf1, leaf2]
+        let n1 = merge(&leaves[1], &leaves[2]);
+        let root = merge(&n1, &leaves[0]);
+
+        assert_eq!(
+            cbmt_proof_root(&[2], &[n1.clone()], &[leaves[0].clone()]),
+            Some(root.clone())
+        );
+        assert_eq!(
+            cbmt_proof_root(
+                &[3],
+                &[leaves[2].clone(), leaves[0].clone()],
+                &[leaves[1].clone()]
+            ),
+            Some(root.clone())
+        );
+        assert_eq!(
+            cbmt_proof_root(
+                &[4, 3],
+                &[leaves[0].clone()],
+                &[leaves[2].clone(), leaves[1].clone()]
+            ),
+            Some(root)
+        );
+        // Lemmas not consumed
+        assert_eq!(
+            cbmt_proof_root(&[2], &[n1.clone(), n1], &[leaves[0].clone()]),
+            None
+        );
+        assert_eq!(cbmt_proof_root(&[2], &[], &[]), None);
+    }
+}
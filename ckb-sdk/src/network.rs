@@ -0,0 +1,143 @@
+use std::sync::RwLock;
+
+use ckb_types::{packed::OutPoint, H256};
+use lazy_static::lazy_static;
+use serde_derive::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref CUSTOM_NETWORKS: RwLock<Vec<CustomNetwork>> = RwLock::new(Vec::new());
+    // Address prefix of the custom network the node is on, used by
+    // `NetworkType::Dev`
+    static ref ACTIVE_PREFIX: RwLock<Option<&'static str>> = RwLock::new(None);
+}
+
+/// A chain other than mainnet/testnet (e.g. a private consortium chain),
+/// matched by the chain name the node reports or by its genesis hash.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct CustomNetwork {
+    // Chain name of the node (`get_blockchain_info`)
+    pub name: String,
+    pub genesis_hash: H256,
+    pub address_prefix: String,
+    // Dep group of secp256k1_blake160_sighash_all, default: the one in genesis
+    #[serde(default)]
+    pub secp_dep: Option<ckb_jsonrpc_types::OutPoint>,
+    // Nervos DAO cell, default: the one in genesis
+    #[serde(default)]
+    pub dao_dep: Option<ckb_jsonrpc_types::OutPoint>,
+}
+
+impl CustomNetwork {
+    pub fn check(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("Network name is empty".to_owned());
+        }
+        if ["ckb", "ckb_testnet", "ckb_dev"].contains(&self.name.as_str()) {
+            return Err(format!("Network name {} is reserved", self.name));
+        }
+        let valid_prefix = !self.address_prefix.is_empty()
+            && self
+                .address_prefix
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+        if !valid_prefix {
+            return Err(format!(
+                "Invalid address prefix (lowercase letters and digits only): {}",
+                self.address_prefix
+            ));
+        }
+        if ["ckb", "ckt"].contains(&self.address_prefix.as_str()) {
+            return Err(format!(
+                "Address prefix {} is reserved",
+                self.address_prefix
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn secp_dep_out_point(&self) -> Option<OutPoint> {
+        self.secp_dep.clone().map(Into::into)
+    }
+
+    pub fn dao_dep_out_point(&self) -> Option<OutPoint> {
+        self.dao_dep.clone().map(Into::into)
+    }
+}
+
+/// Register custom networks, used by every network lookup after this call
+pub fn set_custom_networks(networks: Vec<CustomNetwork>) {
+    *CUSTOM_NETWORKS.write().unwrap() = networks;
+}
+
+pub fn custom_networks() -> Vec<CustomNetwork> {
+    CUSTOM_NETWORKS.read().unwrap().clone()
+}
+
+pub fn find_custom_network_by_name(name: &str) -> Option<CustomNetwork> {
+    CUSTOM_NETWORKS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|network| network.name == name)
+        .cloned()
+}
+
+pub fn find_custom_network_by_genesis(genesis_hash: &H256) -> Option<CustomNetwork> {
+    CUSTOM_NETWORKS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|network| &network.genesis_hash == genesis_hash)
+        .cloned()
+}
+
+pub fn find_custom_network_by_prefix(address_prefix: &str) -> Option<CustomNetwork> {
+    CUSTOM_NETWORKS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|network| network.address_prefix == address_prefix)
+        .cloned()
+}
+
+/// Addresses of `NetworkType::Dev` use the prefix of this network from now on
+pub fn activate_custom_network(network: &CustomNetwork) {
+    let mut active = ACTIVE_PREFIX.write().unwrap();
+    if active.map(|prefix| prefix != network.address_prefix) != Some(false) {
+        // Activated at most once per network in a process
+        *active = Some(Box::leak(network.address_prefix.clone().into_boxed_str()));
+    }
+}
+
+pub fn active_custom_prefix() -> Option<&'static str> {
+    *ACTIVE_PREFIX.read().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_custom_network() {
+        let network = CustomNetwork {
+            name: "ckb_consortium".to_owned(),
+            genesis_hash: H256::default(),
+            address_prefix: "ckc".to_owned(),
+            secp_dep: None,
+            dao_dep: None,
+        };
+        assert!(network.check().is_ok());
+        let reserved_name = CustomNetwork {
+            name: "ckb_testnet".to_owned(),
+            ..network.clone()
+        };
+        assert!(reserved_name.check().is_err());
+        for prefix in &["ckt", "", "CKC", "ck-c"] {
+            let invalid_prefix = CustomNetwork {
+                address_prefix: (*prefix).to_owned(),
+                ..network.clone()
+            };
+            assert!(invalid_prefix.check().is_err());
+        }
+    }
+}
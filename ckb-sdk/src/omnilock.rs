@@ -0,0 +1,160 @@
+use ckb_jsonrpc_types::JsonBytes;
+use ckb_types::{bytes::Bytes, core::ScriptHashType, h256, packed::Script, prelude::*, H160, H256};
+
+use crate::basic::NetworkType;
+use crate::transaction::{WitnessField, WitnessTemplate};
+
+// See: https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0042-omnilock/0042-omnilock.md
+pub const OMNILOCK_MAINNET_TYPE_HASH: H256 =
+    h256!("0x9b819793a64463aed77c615d6cb226eea5487ccfc0783043a587254cda2b6f26");
+pub const OMNILOCK_TESTNET_TYPE_HASH: H256 =
+    h256!("0xf329effd1c475a2978453c8600e1eaf0bc2087ee093c3ee64cc96ec6847752cb");
+
+const AUTH_FLAG_SECP256K1: u8 = 0x00;
+const AUTH_FLAG_ETHEREUM: u8 = 0x01;
+// Size of OmniLockWitnessLock with only the signature field set
+const WITNESS_LOCK_SIZE: usize = 85;
+// Molecule header of the table: total size, 3 field offsets, then length of
+// the signature bytes
+const WITNESS_LOCK_HEADER_SIZE: usize = 20;
+
+/// Identity of an omnilock, the 21 bytes auth at start of lock args
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum OmniAuth {
+    // blake160 of compressed secp256k1 pubkey
+    Secp256k1(H160),
+    // Ethereum address: keccak256 of uncompressed pubkey, last 20 bytes
+    Ethereum(H160),
+}
+
+impl OmniAuth {
+    pub fn from_args(args: &[u8]) -> Option<OmniAuth> {
+        if args.len() < 22 {
+            return None;
+        }
+        let content = H160::from_slice(&args[1..21]).ok()?;
+        match args[0] {
+            AUTH_FLAG_SECP256K1 => Some(OmniAuth::Secp256k1(content)),
+            AUTH_FLAG_ETHEREUM => Some(OmniAuth::Ethereum(content)),
+            _ => None,
+        }
+    }
+
+    pub fn flag(&self) -> u8 {
+        match self {
+            OmniAuth::Secp256k1(_) => AUTH_FLAG_SECP256K1,
+            OmniAuth::Ethereum(_) => AUTH_FLAG_ETHEREUM,
+        }
+    }
+
+    pub fn content(&self) -> &H160 {
+        match self {
+            OmniAuth::Secp256k1(content) | OmniAuth::Ethereum(content) => content,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OmniAuth::Secp256k1(_) => "secp256k1",
+            OmniAuth::Ethereum(_) => "ethereum",
+        }
+    }
+
+    /// Lock args: auth (21 bytes) + omnilock flags (no extra modes)
+    pub fn args(&self) -> Bytes {
+        let mut args = Vec::with_capacity(22);
+        args.push(self.flag());
+        args.extend_from_slice(self.content().as_bytes());
+        args.push(0);
+        Bytes::from(args)
+    }
+
+    pub fn script(&self, network: NetworkType) -> Option<Script> {
+        omnilock_type_hash(network).map(|code_hash| {
+            Script::new_builder()
+                .code_hash(code_hash.pack())
+                .hash_type(ScriptHashType::Type.into())
+                .args(self.args().pack())
+                .build()
+        })
+    }
+}
+
+/// Omnilock is deployed on mainnet and testnet only
+pub fn omnilock_type_hash(network: NetworkType) -> Option<H256> {
+    match network {
+        NetworkType::MainNet => Some(OMNILOCK_MAINNET_TYPE_HASH),
+        NetworkType::TestNet => Some(OMNILOCK_TESTNET_TYPE_HASH),
+        _ => None,
+    }
+}
+
+pub fn is_omnilock(code_hash: &H256) -> bool {
+    code_hash == &OMNILOCK_MAINNET_TYPE_HASH || code_hash == &OMNILOCK_TESTNET_TYPE_HASH
+}
+
+/// Witness template of omnilock: WitnessArgs.lock is an OmniLockWitnessLock
+/// with only `signature` set, the key is the auth content in lock args.
+pub fn omnilock_witness_template(code_hash: H256) -> WitnessTemplate {
+    let mut prefix = Vec::with_capacity(WITNESS_LOCK_HEADER_SIZE);
+    for value in &[
+        WITNESS_LOCK_SIZE,
+        16,
+        WITNESS_LOCK_SIZE,
+        WITNESS_LOCK_SIZE,
+        65,
+    ] {
+        prefix.extend_from_slice(&(*value as u32).to_le_bytes());
+    }
+    WitnessTemplate {
+        code_hash,
+        lock_size: WITNESS_LOCK_SIZE,
+        input_type_size: None,
+        output_type_size: None,
+        signature_field: WitnessField::Lock,
+        signature_offset: WITNESS_LOCK_HEADER_SIZE,
+        lock_arg_offset: 1,
+        signature_prefix: Some(JsonBytes::from_bytes(Bytes::from(prefix))),
+    }
+}
+
+/// Witness templates of omnilock on mainnet and testnet
+pub fn omnilock_witness_templates() -> Vec<WitnessTemplate> {
+    vec![
+        omnilock_witness_template(OMNILOCK_MAINNET_TYPE_HASH),
+        omnilock_witness_template(OMNILOCK_TESTNET_TYPE_HASH),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_omnilock() {
+        let auth = OmniAuth::Secp256k1(H160::from_slice(&[0x12; 20]).unwrap());
+        let args = auth.args();
+        assert_eq!(args.len(), 22);
+        assert_eq!(OmniAuth::from_args(&args), Some(auth.clone()));
+        assert!(auth.script(NetworkType::Dev).is_none());
+        let script = auth.script(NetworkType::TestNet).unwrap();
+        assert!(is_omnilock(&script.code_hash().unpack()));
+
+        let template = omnilock_witness_template(OMNILOCK_TESTNET_TYPE_HASH);
+        assert!(template.validate().is_ok());
+        assert_eq!(template.lock_arg(&args), Some(auth.content().clone()));
+        let lock = template
+            .with_signature(&[7u8; 65])
+            .lock()
+            .to_opt()
+            .unwrap()
+            .raw_data();
+        assert_eq!(lock.len(), WITNESS_LOCK_SIZE);
+        assert_eq!(&lock[0..4], &85u32.to_le_bytes()[..]);
+        assert_eq!(&lock[4..8], &16u32.to_le_bytes()[..]);
+        assert_eq!(&lock[16..20], &65u32.to_le_bytes()[..]);
+        assert_eq!(&lock[20..], &[7u8; 65][..]);
+        let placeholder = template.placeholder().lock().to_opt().unwrap().raw_data();
+        assert_eq!(placeholder, Bytes::from(vec![0u8; WITNESS_LOCK_SIZE]));
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+pub const DEFAULT_RPC_CACHE_SIZE: usize = 1024;
+// About one block interval, cells may be spent even if no new tip is observed
+const VOLATILE_TTL: Duration = Duration::from_secs(8);
+
+lazy_static! {
+    static ref RPC_CACHE: Mutex<RpcCache> = Mutex::new(RpcCache::new(DEFAULT_RPC_CACHE_SIZE));
+}
+
+/// Set max number of cached RPC responses, 0 disables the cache
+pub fn set_rpc_cache_size(size: usize) {
+    let mut cache = RPC_CACHE.lock().unwrap();
+    *cache = RpcCache::new(size);
+}
+
+/// (hits, misses) of the RPC cache
+pub fn rpc_cache_stats() -> (u64, u64) {
+    let cache = RPC_CACHE.lock().unwrap();
+    (cache.hits, cache.misses)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Cacheable {
+    // Content addressed, never changes (header/block by hash)
+    Immutable,
+    // Changes when the chain grows (cell status, transaction status)
+    Volatile,
+}
+
+fn cacheable(method: &str) -> Option<Cacheable> {
+    match method {
+        "get_header" | "get_block" => Some(Cacheable::Immutable),
+        "get_live_cell" | "get_transaction" | "get_header_by_number" => Some(Cacheable::Volatile),
+        _ => None,
+    }
+}
+
+struct Entry {
+    tick: u64,
+    response: Vec<u8>,
+    volatile: bool,
+    created_at: Instant,
+}
+
+/// LRU cache of RPC responses, volatile entries are dropped when a new tip is
+/// observed (from get_tip_header/get_tip_block_number responses).
+pub struct RpcCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<String, Entry>,
+    // tick => key, the smallest tick is the least recently used
+    order: BTreeMap<u64, String>,
+    tip_number: Option<u64>,
+    hits: u64,
+    misses: u64,
+}
+
+impl RpcCache {
+    fn new(capacity: usize) -> RpcCache {
+        RpcCache {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tip_number: None,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, key: &str) -> Option<Vec<u8>> {
+        let expired = match self.entries.get(key) {
+            Some(entry) => entry.volatile && entry.created_at.elapsed() >= VOLATILE_TTL,
+            None => {
+                self.misses += 1;
+                return None;
+            }
+        };
+        if expired {
+            self.remove(key);
+            self.misses += 1;
+            return None;
+        }
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(key).unwrap();
+        self.order.remove(&entry.tick);
+        self.order.insert(tick, key.to_owned());
+        entry.tick = tick;
+        self.hits += 1;
+        Some(entry.response.clone())
+    }
+
+    fn insert(&mut self, key: String, response: Vec<u8>, volatile: bool) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(&key);
+        while self.entries.len() >= self.capacity {
+            let oldest = match self.order.keys().next() {
+                Some(tick) => *tick,
+                None => break,
+            };
+            if let Some(old_key) = self.order.remove(&oldest) {
+                self.entries.remove(&old_key);
+            }
+        }
+        let tick = self.next_tick();
+        self.order.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                tick,
+                response,
+                volatile,
+                created_at: Instant::now(),
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.tick);
+        }
+    }
+
+    fn observe_tip(&mut self, tip_number: u64) {
+        if self.tip_number == Some(tip_number) {
+            return;
+        }
+        self.tip_number = Some(tip_number);
+        let volatile_keys = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.volatile)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in volatile_keys {
+            self.remove(&key);
+        }
+    }
+}
+
+/// A cacheable request: method and the cache key
+pub(crate) struct CacheRequest {
+    id: serde_json::Value,
+    method: String,
+    key: Option<String>,
+}
+
+impl CacheRequest {
+    pub(crate) fn parse(json_data: &[u8]) -> Option<CacheRequest> {
+        if RPC_CACHE.lock().unwrap().capacity == 0 {
+            return None;
+        }
+        let request: serde_json::Value = serde_json::from_slice(json_data).ok()?;
+        let method = request["method"].as_str()?.to_owned();
+        let key = cacheable(&method).map(|_| format!("{}:{}", method, request["params"]));
+        Some(CacheRequest {
+            id: request["id"].clone(),
+            method,
+            key,
+        })
+    }
+
+    /// Cached response, with id replaced by id of this request
+    pub(crate) fn lookup(&self) -> Option<Vec<u8>> {
+        let key = self.key.as_ref()?;
+        let cached = RPC_CACHE.lock().unwrap().get(key)?;
+        let mut response: serde_json::Value = serde_json::from_slice(&cached).ok()?;
+        response["id"] = self.id.clone();
+        serde_json::to_vec(&response).ok()
+    }
+
+    pub(crate) fn store(&self, response_data: &[u8]) {
+        let response: serde_json::Value = match serde_json::from_slice(response_data) {
+            Ok(response) => response,
+            Err(_) => return,
+        };
+        let result = &response["result"];
+        let mut cache = RPC_CACHE.lock().unwrap();
+        let tip_number = match self.method.as_str() {
+            "get_tip_header" => result["number"].as_str(),
+            "get_tip_block_number" => result.as_str(),
+            _ => None,
+        };
+        if let Some(tip_number) = tip_number.and_then(parse_hex_u64) {
+            cache.observe_tip(tip_number);
+        }
+        // Errors and null results (not found yet) are not cached
+        if let (Some(key), false) = (self.key.as_ref(), result.is_null()) {
+            let volatile = cacheable(&self.method) == Some(Cacheable::Volatile);
+            cache.insert(key.clone(), response_data.to_vec(), volatile);
+        }
+    }
+}
+
+fn parse_hex_u64(value: &str) -> Option<u64> {
+    let value = value.trim_start_matches("0x");
+    u64::from_str_radix(value, 16).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rpc_cache() {
+        let mut cache = RpcCache::new(2);
+        cache.insert("a".to_owned(), vec![1], false);
+        cache.insert("b".to_owned(), vec![2], true);
+        assert_eq!(cache.get("a"), Some(vec![1]));
+        // "b" is the least recently used
+        cache.insert("c".to_owned(), vec![3], false);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(vec![1]));
+        assert_eq!(cache.get("c"), Some(vec![3]));
+
+        cache.insert("d".to_owned(), vec![4], true);
+        cache.observe_tip(10);
+        assert_eq!(cache.get("d"), None);
+        assert_eq!(cache.get("c"), Some(vec![3]));
+        assert_eq!((cache.hits, cache.misses), (4, 2));
+
+        let mut disabled = RpcCache::new(0);
+        disabled.insert("a".to_owned(), vec![1], false);
+        assert_eq!(disabled.get("a"), None);
+        assert_eq!(parse_hex_u64("0x10"), Some(16));
+    }
+}
@@ -1,14 +1,24 @@
 use ckb_jsonrpc_types::{
-    BannedAddr, BlockNumber, BlockReward, BlockView, CellOutputWithOutPoint, CellTransaction,
-    CellWithStatus, ChainInfo, EpochNumber, EpochView, HeaderView, LiveCell, LockHashIndexState,
-    Node, OutPoint, PeerState, Timestamp, Transaction, TransactionWithStatus, TxPoolInfo, Uint64,
+    BannedAddr, Block, BlockNumber, BlockReward, BlockTemplate, BlockView, CellOutputWithOutPoint,
+    CellTransaction, CellWithStatus, ChainInfo, EpochNumber, EpochView, HeaderView, LiveCell,
+    LockHashIndexState, Node, OutPoint, PeerState, Timestamp, Transaction, TransactionWithStatus,
+    TxPoolInfo, Uint32, Uint64,
 };
-use jsonrpc_client_core::{expand_params, jsonrpc_client};
-use jsonrpc_client_http::{HttpHandle, HttpTransport};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use futures::{future, Future};
+use jsonrpc_client_core::{expand_params, jsonrpc_client, Transport};
 use serde_derive::{Deserialize, Serialize};
 
 use ckb_types::H256;
 
+use super::cache::CacheRequest;
+use super::compat::{adapt_response, check_method, NodeCompat, NodeVersion};
+use super::options::{rpc_client_options, HttpTransport};
+use crate::trace::{self, is_trace, TraceKind};
+
 #[derive(Serialize, Deserialize)]
 pub struct Nodes(pub Vec<Node>);
 
@@ -45,6 +55,24 @@ pub struct LiveCells(pub Vec<LiveCell>);
 #[derive(Serialize, Deserialize)]
 pub struct CellTransactions(pub Vec<CellTransaction>);
 
+#[derive(Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub indices: Vec<Uint32>,
+    pub lemmas: Vec<H256>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EstimateCycles {
+    pub cycles: Uint64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TransactionProof {
+    pub block_hash: H256,
+    pub witnesses_root: H256,
+    pub proof: MerkleProof,
+}
+
 jsonrpc_client!(pub struct RpcClient {
     // Chain
     pub fn get_block(&mut self, hash: H256) -> RpcRequest<OptionBlockView>;
@@ -60,6 +88,8 @@ jsonrpc_client!(pub struct RpcClient {
     pub fn get_tip_block_number(&mut self) -> RpcRequest<BlockNumber>;
     pub fn get_tip_header(&mut self) -> RpcRequest<HeaderView>;
     pub fn get_transaction(&mut self, hash: H256) -> RpcRequest<OptionTransactionWithStatus>;
+    pub fn get_transaction_proof(&mut self, tx_hashes: Vec<H256>, block_hash: Option<H256>) -> RpcRequest<TransactionProof>;
+    pub fn estimate_cycles(&mut self, tx: Transaction) -> RpcRequest<EstimateCycles>;
 
     // Indexer
     pub fn deindex_lock_hash(&mut self, lock_hash: H256) -> RpcRequest<()>;
@@ -83,6 +113,15 @@ jsonrpc_client!(pub struct RpcClient {
         index_from: Option<BlockNumber>
     ) -> RpcRequest<LockHashIndexState>;
 
+    // Miner
+    pub fn get_block_template(
+        &mut self,
+        bytes_limit: Option<Uint64>,
+        proposals_limit: Option<Uint64>,
+        max_version: Option<Uint32>
+    ) -> RpcRequest<BlockTemplate>;
+    pub fn submit_block(&mut self, work_id: String, data: Block) -> RpcRequest<OptionH256>;
+
     // Net
     pub fn get_banned_addresses(&mut self) -> RpcRequest<BannedAddres>;
     pub fn get_peers(&mut self) -> RpcRequest<Nodes>;
@@ -110,12 +149,176 @@ jsonrpc_client!(pub struct RpcClient {
     pub fn broadcast_transaction(&mut self, tx: Transaction) -> RpcRequest<H256>;
 });
 
-impl RpcClient<HttpHandle> {
-    pub fn from_uri(server: &str) -> RpcClient<HttpHandle> {
-        let transport = HttpTransport::new().standalone().unwrap();
-        let transport_handle = transport.handle(server).unwrap();
-        RpcClient::new(transport_handle)
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Forbid all network access, every rpc client created after this call fails immediately
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::SeqCst);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::SeqCst)
+}
+
+#[derive(Clone)]
+pub enum RpcTransport {
+    // HTTP(S) with pooled keep-alive connections, proxy or basic auth (see:
+    // set_rpc_client_options)
+    Http(HttpTransport, NodeCompat),
+    // Invalid client options, every request fails with the error
+    Invalid(String),
+    Offline,
+}
+
+#[derive(Debug)]
+pub enum RpcTransportError {
+    Http(String),
+    // Method or response not supported by the connected node version
+    Compat(String),
+    Offline,
+}
+
+impl fmt::Display for RpcTransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RpcTransportError::Http(err) => write!(f, "{}", err),
+            RpcTransportError::Compat(err) => write!(f, "{}", err),
+            RpcTransportError::Offline => {
+                write!(f, "Network access is forbidden in offline mode")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RpcTransportError {}
+
+/// The request failed because the connected node does not support the method
+/// (or its version is too old), callers may fall back to a local
+/// implementation.
+pub fn is_unsupported_method(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(RpcTransportError::Compat(_)) = err.downcast_ref::<RpcTransportError>() {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+impl Transport for RpcTransport {
+    type Future = Box<dyn Future<Item = Vec<u8>, Error = RpcTransportError> + Send>;
+    type Error = RpcTransportError;
+
+    fn get_next_id(&mut self) -> u64 {
+        match self {
+            RpcTransport::Http(transport, _) => transport.get_next_id(),
+            RpcTransport::Invalid(_) | RpcTransport::Offline => 0,
+        }
+    }
+
+    fn send(&self, json_data: Vec<u8>) -> Self::Future {
+        match self {
+            RpcTransport::Http(_, compat) => {
+                let method = request_method(&json_data);
+                let version = compat.version(|| self.detect_version());
+                if let Some(version) = version {
+                    if let Err(err) = check_method(&method, version) {
+                        return Box::new(future::err(RpcTransportError::Compat(err)));
+                    }
+                }
+                let cache_request = CacheRequest::parse(&json_data);
+                if let Some(response) = cache_request.as_ref().and_then(CacheRequest::lookup) {
+                    return Box::new(future::ok(response));
+                }
+                let response: <RpcTransport as Transport>::Future =
+                    Box::new(self.send_http(json_data).and_then(move |response_data| {
+                        adapt_response(&method, version, response_data)
+                            .map_err(RpcTransportError::Compat)
+                    }));
+                match cache_request {
+                    Some(cache_request) => Box::new(response.map(move |response_data| {
+                        cache_request.store(&response_data);
+                        response_data
+                    })),
+                    None => response,
+                }
+            }
+            RpcTransport::Invalid(err) => {
+                Box::new(future::err(RpcTransportError::Http(err.clone())))
+            }
+            RpcTransport::Offline => Box::new(future::err(RpcTransportError::Offline)),
+        }
+    }
+}
+
+impl RpcTransport {
+    fn send_raw(&self, json_data: Vec<u8>) -> <RpcTransport as Transport>::Future {
+        match self {
+            RpcTransport::Http(transport, _) => Box::new(future::result(
+                transport.send(json_data).map_err(RpcTransportError::Http),
+            )),
+            RpcTransport::Invalid(err) => {
+                Box::new(future::err(RpcTransportError::Http(err.clone())))
+            }
+            RpcTransport::Offline => Box::new(future::err(RpcTransportError::Offline)),
+        }
+    }
+
+    fn send_http(&self, json_data: Vec<u8>) -> <RpcTransport as Transport>::Future {
+        if !is_trace() {
+            return self.send_raw(json_data);
+        }
+        let method = request_method(&json_data);
+        let request_size = json_data.len();
+        let start = Instant::now();
+        Box::new(self.send_raw(json_data).then(move |result| {
+            let response_size = result.as_ref().map(Vec::len).unwrap_or(0);
+            trace::record(
+                TraceKind::Rpc,
+                &method,
+                start.elapsed(),
+                request_size + response_size,
+            );
+            result
+        }))
+    }
+
+    // Ask the node its version, None if the node is unreachable or the
+    // version is unknown
+    fn detect_version(&self) -> Option<NodeVersion> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "local_node_info",
+            "params": [],
+        });
+        let response_data = self
+            .send_raw(serde_json::to_vec(&request).ok()?)
+            .wait()
+            .ok()?;
+        let response: serde_json::Value = serde_json::from_slice(&response_data).ok()?;
+        NodeVersion::parse(response["result"]["version"].as_str()?)
+    }
+}
+
+fn request_method(json_data: &[u8]) -> String {
+    serde_json::from_slice::<serde_json::Value>(json_data)
+        .ok()
+        .and_then(|request| request["method"].as_str().map(ToOwned::to_owned))
+        .unwrap_or_else(|| "<unknown>".to_owned())
+}
+
+impl RpcClient<RpcTransport> {
+    pub fn from_uri(server: &str) -> RpcClient<RpcTransport> {
+        if is_offline() {
+            return RpcClient::new(RpcTransport::Offline);
+        }
+        match HttpTransport::new(server, rpc_client_options()) {
+            Ok(transport) => RpcClient::new(RpcTransport::Http(transport, NodeCompat::default())),
+            Err(err) => RpcClient::new(RpcTransport::Invalid(err)),
+        }
     }
 }
 
-pub type HttpRpcClient = RpcClient<HttpHandle>;
+pub type HttpRpcClient = RpcClient<RpcTransport>;
@@ -0,0 +1,203 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Version of the connected node, parsed from `local_node_info`
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub struct NodeVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl NodeVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> NodeVersion {
+        NodeVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parse "0.24.0 (rylai-v5 abcdef 2019-11-01)" or "v0.24.0"
+    pub fn parse(version: &str) -> Option<NodeVersion> {
+        let version = version.split_whitespace().next()?.trim_start_matches('v');
+        // Drop pre-release suffix: 0.25.0-pre
+        let version = version.split('-').next()?;
+        let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+        let major = parts.next()??;
+        let minor = parts.next().unwrap_or(Some(0))?;
+        let patch = parts.next().unwrap_or(Some(0))?;
+        Some(NodeVersion::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for NodeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+// Methods added after the oldest supported node version
+const METHOD_SINCE: &[(&str, NodeVersion)] = &[
+    ("get_transaction_proof", NodeVersion::new(0, 24, 0)),
+    ("estimate_cycles", NodeVersion::new(0, 105, 0)),
+];
+
+// (methods, field name in node response, field name expected by ckb-cli,
+// node version since the field is renamed)
+const FIELD_RENAMES: &[(&[&str], &str, &str, NodeVersion)] = &[(
+    &[
+        "get_tip_header",
+        "get_header",
+        "get_header_by_number",
+        "get_block",
+        "get_block_by_number",
+    ],
+    "extra_hash",
+    "uncles_hash",
+    NodeVersion::new(0, 100, 0),
+)];
+
+// JSON-RPC error code of unknown method
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The requested method is not available on the connected node
+pub fn check_method(method: &str, version: NodeVersion) -> Result<(), String> {
+    match METHOD_SINCE.iter().find(|(name, _)| *name == method) {
+        Some((_, since)) if version < *since => Err(format!(
+            "RPC method {} requires node version >= {}, connected node is {}",
+            method, since, version
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Rewrite the response of a node with different version into the format
+/// ckb-cli understands: rename fields and explain unknown methods.
+pub fn adapt_response(
+    method: &str,
+    version: Option<NodeVersion>,
+    response_data: Vec<u8>,
+) -> Result<Vec<u8>, String> {
+    let mut response: serde_json::Value = match serde_json::from_slice(&response_data) {
+        Ok(response) => response,
+        Err(_) => return Ok(response_data),
+    };
+    if response["error"]["code"].as_i64() == Some(METHOD_NOT_FOUND) {
+        return Err(format!(
+            "RPC method {} is not supported by the connected node (version: {})",
+            method,
+            version
+                .map(|version| version.to_string())
+                .unwrap_or_else(|| "unknown".to_owned())
+        ));
+    }
+    let version = match version {
+        Some(version) => version,
+        None => return Ok(response_data),
+    };
+    let mut changed = false;
+    for (methods, from, to, since) in FIELD_RENAMES {
+        if version >= *since && methods.contains(&method) {
+            changed |= rename_field(&mut response["result"], from, to);
+        }
+    }
+    if changed {
+        serde_json::to_vec(&response).map_err(|err| err.to_string())
+    } else {
+        Ok(response_data)
+    }
+}
+
+fn rename_field(value: &mut serde_json::Value, from: &str, to: &str) -> bool {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut changed = false;
+            if let Some(field) = map.remove(from) {
+                map.insert(to.to_owned(), field);
+                changed = true;
+            }
+            for field in map.values_mut() {
+                changed |= rename_field(field, from, to);
+            }
+            changed
+        }
+        serde_json::Value::Array(items) => items.iter_mut().fold(false, |changed, item| {
+            rename_field(item, from, to) || changed
+        }),
+        _ => false,
+    }
+}
+
+/// Node version of one transport, detected on first request
+#[derive(Clone, Default)]
+pub struct NodeCompat {
+    // None: not detected yet, Some(None): detection failed
+    version: Arc<Mutex<Option<Option<NodeVersion>>>>,
+}
+
+impl NodeCompat {
+    pub fn version<F>(&self, detect: F) -> Option<NodeVersion>
+    where
+        F: FnOnce() -> Option<NodeVersion>,
+    {
+        let mut version = self.version.lock().unwrap();
+        if version.is_none() {
+            *version = Some(detect());
+        }
+        version.unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_node_version() {
+        assert_eq!(
+            NodeVersion::parse("0.24.0 (rylai-v5 abcdef 2019-11-01)"),
+            Some(NodeVersion::new(0, 24, 0))
+        );
+        assert_eq!(
+            NodeVersion::parse("v0.25.0-pre"),
+            Some(NodeVersion::new(0, 25, 0))
+        );
+        assert_eq!(
+            NodeVersion::parse("0.100"),
+            Some(NodeVersion::new(0, 100, 0))
+        );
+        assert_eq!(NodeVersion::parse("unknown"), None);
+
+        assert!(check_method("get_transaction_proof", NodeVersion::new(0, 23, 2)).is_err());
+        assert!(check_method("get_transaction_proof", NodeVersion::new(0, 24, 0)).is_ok());
+        assert!(check_method("estimate_cycles", NodeVersion::new(0, 104, 1)).is_err());
+        assert!(check_method("get_tip_header", NodeVersion::new(0, 1, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_adapt_response() {
+        let response = br#"{"jsonrpc":"2.0","id":0,"result":{"inner":{"extra_hash":"0x00"}}}"#;
+        let adapted = adapt_response(
+            "get_tip_header",
+            Some(NodeVersion::new(0, 100, 0)),
+            response.to_vec(),
+        )
+        .unwrap();
+        let adapted: serde_json::Value = serde_json::from_slice(&adapted).unwrap();
+        assert_eq!(adapted["result"]["inner"]["uncles_hash"], "0x00");
+        assert!(adapted["result"]["inner"]["extra_hash"].is_null());
+
+        let unchanged = adapt_response(
+            "get_tip_header",
+            Some(NodeVersion::new(0, 24, 0)),
+            response.to_vec(),
+        )
+        .unwrap();
+        assert_eq!(unchanged, response.to_vec());
+
+        let not_found =
+            br#"{"jsonrpc":"2.0","id":0,"error":{"code":-32601,"message":"Method not found"}}"#;
+        assert!(adapt_response("get_peers_state", None, not_found.to_vec()).is_err());
+    }
+}
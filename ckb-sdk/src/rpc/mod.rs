@@ -1,10 +1,19 @@
+mod cache;
 mod client;
+mod compat;
+mod options;
 
+pub use cache::{rpc_cache_stats, set_rpc_cache_size, DEFAULT_RPC_CACHE_SIZE};
 pub use ckb_jsonrpc_types::{
     BlockNumber, BlockView, CellOutputWithOutPoint, CellWithStatus, ChainInfo, EpochNumber,
     EpochView, HeaderView, Node, OutPoint, Transaction, TransactionWithStatus, TxPoolInfo,
 };
 pub use client::{
-    CellOutputWithOutPoints, HttpRpcClient, Nodes, OptionBlockView, OptionEpochView, OptionH256,
-    OptionTransactionWithStatus, RpcClient,
+    is_offline, is_unsupported_method, set_offline, CellOutputWithOutPoints, EstimateCycles,
+    HttpRpcClient, MerkleProof, Nodes, OptionBlockView, OptionEpochView, OptionH256,
+    OptionTransactionWithStatus, RpcClient, RpcTransport, RpcTransportError, TransactionProof,
+};
+pub use compat::NodeVersion;
+pub use options::{
+    http_client, rpc_client_options, set_rpc_client_options, HttpTransport, RpcClientOptions,
 };
@@ -0,0 +1,244 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use reqwest::header::CONTENT_TYPE;
+use serde_derive::{Deserialize, Serialize};
+
+use super::is_offline;
+
+lazy_static! {
+    static ref RPC_CLIENT_OPTIONS: RwLock<RpcClientOptions> =
+        RwLock::new(RpcClientOptions::default());
+    // Shared by all transports with the same options, so connections to the
+    // node are kept alive and reused across rpc clients
+    static ref SHARED_CLIENT: Mutex<Option<(RpcClientOptions, reqwest::Client)>> =
+        Mutex::new(None);
+    // Same for third-party requests, which never carry the node credentials
+    static ref SHARED_HTTP_CLIENT: Mutex<Option<(RpcClientOptions, reqwest::Client)>> =
+        Mutex::new(None);
+}
+
+/// Set TLS, proxy, authentication and pool options, used by every rpc client
+/// created after this call
+pub fn set_rpc_client_options(options: RpcClientOptions) {
+    *RPC_CLIENT_OPTIONS.write().unwrap() = options;
+}
+
+pub fn rpc_client_options() -> RpcClientOptions {
+    RPC_CLIENT_OPTIONS.read().unwrap().clone()
+}
+
+/// Connection options for remote nodes (e.g. behind TLS terminating reverse
+/// proxies) and the connection pool
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RpcClientOptions {
+    // Extra trusted CA certificate (PEM format)
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+    // Client certificate and key (PKCS#12 format)
+    #[serde(default)]
+    pub client_cert: Option<PathBuf>,
+    #[serde(default)]
+    pub client_cert_password: Option<String>,
+    // HTTP(S) proxy url, e.g. http://127.0.0.1:3128
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    // Max idle (keep-alive) connections per host, 0 disables keep-alive
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<usize>,
+}
+
+impl RpcClientOptions {
+    pub fn is_empty(&self) -> bool {
+        self == &RpcClientOptions::default()
+    }
+
+    // Options for requests to others than the node: the node's client
+    // certificate and basic auth are dropped, CA, proxy and pool are kept
+    fn without_credentials(&self) -> RpcClientOptions {
+        RpcClientOptions {
+            client_cert: None,
+            client_cert_password: None,
+            username: None,
+            password: None,
+            ..self.clone()
+        }
+    }
+
+    fn build_client(&self) -> Result<reqwest::Client, String> {
+        self.client_builder()?
+            .build()
+            .map_err(|err| err.to_string())
+    }
+
+    fn client_builder(&self) -> Result<reqwest::ClientBuilder, String> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(path) = self.ca_cert.as_ref() {
+            let pem = read_file(path)?;
+            let cert = reqwest::Certificate::from_pem(&pem)
+                .map_err(|err| format!("Invalid CA certificate {:?}: {}", path, err))?;
+            builder = builder.add_root_certificate(cert);
+        }
+        if let Some(path) = self.client_cert.as_ref() {
+            let der = read_file(path)?;
+            let password = self
+                .client_cert_password
+                .as_ref()
+                .map(String::as_str)
+                .unwrap_or("");
+            let identity = reqwest::Identity::from_pkcs12_der(&der, password)
+                .map_err(|err| format!("Invalid client certificate {:?}: {}", path, err))?;
+            builder = builder.identity(identity);
+        }
+        if let Some(proxy) = self.proxy.as_ref() {
+            let proxy = reqwest::Proxy::all(proxy.as_str())
+                .map_err(|err| format!("Invalid proxy {}: {}", proxy, err))?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder = builder.max_idle_per_host(max_idle);
+        }
+        Ok(builder)
+    }
+
+    fn shared_client(&self) -> Result<reqwest::Client, String> {
+        self.shared_client_in(&SHARED_CLIENT)
+    }
+
+    fn shared_client_in(
+        &self,
+        slot: &Mutex<Option<(RpcClientOptions, reqwest::Client)>>,
+    ) -> Result<reqwest::Client, String> {
+        let mut shared = slot.lock().unwrap();
+        if let Some((options, client)) = shared.as_ref() {
+            if options == self {
+                return Ok(client.clone());
+            }
+        }
+        let client = self.build_client()?;
+        *shared = Some((self.clone(), client.clone()));
+        Ok(client)
+    }
+}
+
+/// Client for other http requests (token lists, price feeds, faucets,
+/// webhooks) with the CA, proxy and pool options of rpc clients. Credentials
+/// of the node (client certificate, basic auth) are not included, they are
+/// only sent to the node. Refused in offline mode.
+pub fn http_client(timeout: Option<Duration>) -> Result<reqwest::Client, String> {
+    if is_offline() {
+        return Err("Network access is forbidden in offline mode".to_owned());
+    }
+    let options = rpc_client_options().without_credentials();
+    match timeout {
+        Some(timeout) => options
+            .client_builder()?
+            .timeout(timeout)
+            .build()
+            .map_err(|err| err.to_string()),
+        None => options.shared_client_in(&SHARED_HTTP_CLIENT),
+    }
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+    let mut content = Vec::new();
+    fs::File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut content))
+        .map_err(|err| format!("Read {:?} failed: {}", path, err))?;
+    Ok(content)
+}
+
+/// JSON-RPC over HTTP(S), optionally through a proxy or with basic auth
+#[derive(Clone)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    options: RpcClientOptions,
+    next_id: Arc<AtomicUsize>,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str, options: RpcClientOptions) -> Result<HttpTransport, String> {
+        Ok(HttpTransport {
+            client: options.shared_client()?,
+            url: url.to_owned(),
+            options,
+            next_id: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn get_next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::SeqCst) as u64
+    }
+
+    pub fn send(&self, json_data: Vec<u8>) -> Result<Vec<u8>, String> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(json_data);
+        if let Some(username) = self.options.username.as_ref() {
+            request = request.basic_auth(username, self.options.password.as_ref());
+        }
+        let mut response = request
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| err.to_string())?;
+        let mut content = Vec::new();
+        response
+            .copy_to(&mut content)
+            .map_err(|err| err.to_string())?;
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rpc_client_options() {
+        let options: RpcClientOptions = serde_json::from_str("{}").unwrap();
+        assert!(options.is_empty());
+        let options: RpcClientOptions =
+            serde_json::from_str(r#"{"proxy": "http://127.0.0.1:3128", "username": "ckb"}"#)
+                .unwrap();
+        assert!(!options.is_empty());
+        assert_eq!(options.username, Some("ckb".to_owned()));
+        assert!(options.build_client().is_ok());
+        let options: RpcClientOptions =
+            serde_json::from_str(r#"{"pool_max_idle_per_host": 0}"#).unwrap();
+        assert!(!options.is_empty());
+        assert!(options.shared_client().is_ok());
+
+        let invalid = RpcClientOptions {
+            ca_cert: Some(PathBuf::from("/not/exists.pem")),
+            ..Default::default()
+        };
+        assert!(invalid.build_client().is_err());
+
+        let node = RpcClientOptions {
+            client_cert: Some(PathBuf::from("/not/exists.p12")),
+            client_cert_password: Some("secret".to_owned()),
+            username: Some("ckb".to_owned()),
+            password: Some("secret".to_owned()),
+            proxy: Some("http://127.0.0.1:3128".to_owned()),
+            ..Default::default()
+        };
+        assert!(node.build_client().is_err());
+        let others = node.without_credentials();
+        assert_eq!(others.client_cert, None);
+        assert_eq!(others.username, None);
+        assert_eq!(others.proxy, node.proxy);
+        assert!(others.build_client().is_ok());
+    }
+}
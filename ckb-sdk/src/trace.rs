@@ -0,0 +1,100 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// Tracing is per thread, so background threads (like the index thread)
+// won't mix their requests into the command being traced.
+thread_local! {
+    static TRACE: Cell<bool> = Cell::new(false);
+    static RECORDS: RefCell<Vec<TraceRecord>> = RefCell::new(Vec::new());
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum TraceKind {
+    // size: request + response bytes
+    Rpc,
+    // size: number of items read
+    Db,
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceRecord {
+    pub kind: TraceKind,
+    pub name: String,
+    pub duration: Duration,
+    pub size: usize,
+}
+
+impl TraceRecord {
+    fn size_string(&self) -> String {
+        match self.kind {
+            TraceKind::Rpc => format!("{} bytes", self.size),
+            TraceKind::Db => format!("{} items", self.size),
+        }
+    }
+}
+
+/// Enable/disable tracing of RPC calls and index database operations in current thread
+pub fn set_trace(enable: bool) {
+    TRACE.with(|trace| trace.set(enable));
+}
+
+pub fn is_trace() -> bool {
+    TRACE.with(Cell::get)
+}
+
+/// Print the record to stderr and keep it for the summary
+pub fn record(kind: TraceKind, name: &str, duration: Duration, size: usize) {
+    if !is_trace() {
+        return;
+    }
+    let record = TraceRecord {
+        kind,
+        name: name.to_owned(),
+        duration,
+        size,
+    };
+    eprintln!(
+        "[TRACE] {:?} {} {:.3}ms {}",
+        record.kind,
+        record.name,
+        duration_ms(record.duration),
+        record.size_string()
+    );
+    RECORDS.with(|records| records.borrow_mut().push(record));
+}
+
+/// Copy of the records kept since last take
+pub fn records() -> Vec<TraceRecord> {
+    RECORDS.with(|records| records.borrow().clone())
+}
+
+/// Take all records since last call
+pub fn take_records() -> Vec<TraceRecord> {
+    RECORDS.with(|records| records.replace(Vec::new()))
+}
+
+/// Summary grouped by (kind, name): count, total duration, total size
+pub fn summary(records: &[TraceRecord]) -> Vec<(TraceKind, String, usize, Duration, usize)> {
+    let mut groups: BTreeMap<(TraceKind, String), (usize, Duration, usize)> = BTreeMap::new();
+    for record in records {
+        let group =
+            groups
+                .entry((record.kind, record.name.clone()))
+                .or_insert((0, Duration::default(), 0));
+        group.0 += 1;
+        group.1 += record.duration;
+        group.2 += record.size;
+    }
+    let mut items = groups
+        .into_iter()
+        .map(|((kind, name), (count, duration, size))| (kind, name, count, duration, size))
+        .collect::<Vec<_>>();
+    // Slowest first
+    items.sort_by(|a, b| b.3.cmp(&a.3));
+    items
+}
+
+pub fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1_000_000.0
+}
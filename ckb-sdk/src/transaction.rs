@@ -1,27 +1,258 @@
-use ckb_hash::new_blake2b;
+use ckb_hash::{blake2b_256, new_blake2b};
+use ckb_jsonrpc_types::JsonBytes;
 use ckb_script::TransactionScriptsVerifier;
 use ckb_types::{
     bytes::Bytes,
     core::{cell::resolve_transaction, Capacity, Cycle, ScriptHashType},
-    packed::{Byte32, CellInput, CellOutput, OutPoint, Script, WitnessArgs},
+    packed::{self, Byte32, CellInput, CellOutput, OutPoint, Script, WitnessArgs},
     prelude::*,
     H160, H256,
 };
 use failure::Error as FailureError;
 use fnv::FnvHashSet;
+use serde_derive::{Deserialize, Serialize};
+use std::cmp;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
-use crate::{GenesisInfo, MIN_SECP_CELL_CAPACITY};
+use crate::{omnilock::is_omnilock, GenesisInfo, MIN_SECP_CELL_CAPACITY};
 
 pub use ckb_sdk_types::transaction::{
     MockCellDep, MockInfo, MockInput, MockResourceLoader, MockTransaction, ReprMockCellDep,
     ReprMockInfo, ReprMockInput, ReprMockTransaction, Resource,
 };
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WitnessField {
+    Lock,
+    InputType,
+    OutputType,
+}
+
+/// Placeholder witness of a non-standard lock (by lock code hash). The
+/// signature (65 bytes, signed by the key of 20 bytes of lock args at
+/// `lock_arg_offset`) is put into `signature_field` at `signature_offset`,
+/// after `signature_prefix` if any, other bytes are zero.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct WitnessTemplate {
+    pub code_hash: H256,
+    pub lock_size: usize,
+    #[serde(default)]
+    pub input_type_size: Option<usize>,
+    #[serde(default)]
+    pub output_type_size: Option<usize>,
+    pub signature_field: WitnessField,
+    #[serde(default)]
+    pub signature_offset: usize,
+    #[serde(default)]
+    pub lock_arg_offset: usize,
+    // Bytes at start of signature field in the signed witness (such as a
+    // molecule header), the placeholder for signing message is all zero.
+    #[serde(default)]
+    pub signature_prefix: Option<JsonBytes>,
+}
+
+impl WitnessTemplate {
+    fn field_size(&self, field: WitnessField) -> Option<usize> {
+        match field {
+            WitnessField::Lock => Some(self.lock_size),
+            WitnessField::InputType => self.input_type_size,
+            WitnessField::OutputType => self.output_type_size,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let size = self
+            .field_size(self.signature_field)
+            .ok_or_else(|| format!("Signature field {:?} has no size", self.signature_field))?;
+        if self.signature_offset + 65 > size {
+            return Err(format!(
+                "Signature (offset: {}, length: 65) exceeds {:?} field size: {}",
+                self.signature_offset, self.signature_field, size
+            ));
+        }
+        let prefix_len = self.prefix().len();
+        if prefix_len > self.signature_offset {
+            return Err(format!(
+                "Signature prefix (length: {}) overlaps signature (offset: {})",
+                prefix_len, self.signature_offset
+            ));
+        }
+        Ok(())
+    }
+
+    fn prefix(&self) -> Bytes {
+        self.signature_prefix
+            .as_ref()
+            .map(|prefix| prefix.clone().into_bytes())
+            .unwrap_or_default()
+    }
+
+    fn build(&self, signature: &[u8], with_prefix: bool) -> WitnessArgs {
+        let prefix = if with_prefix {
+            self.prefix()
+        } else {
+            Bytes::new()
+        };
+        let field_data = |field: WitnessField| {
+            self.field_size(field).map(|size| {
+                let mut data = vec![0u8; size];
+                if field == self.signature_field {
+                    data[..prefix.len()].copy_from_slice(&prefix);
+                    data[self.signature_offset..self.signature_offset + signature.len()]
+                        .copy_from_slice(signature);
+                }
+                Bytes::from(data)
+            })
+        };
+        WitnessArgs::new_builder()
+            .lock(field_data(WitnessField::Lock).pack())
+            .input_type(field_data(WitnessField::InputType).pack())
+            .output_type(field_data(WitnessField::OutputType).pack())
+            .build()
+    }
+
+    /// Zero filled witness, used for signing message and fee estimation
+    pub fn placeholder(&self) -> WitnessArgs {
+        self.build(&[0u8; 65], false)
+    }
+
+    pub fn with_signature(&self, signature: &[u8; 65]) -> WitnessArgs {
+        self.build(&signature[..], true)
+    }
+
+    /// Key identifier (blake160 of pubkey) in lock args
+    pub fn lock_arg(&self, args: &[u8]) -> Option<H160> {
+        args.get(self.lock_arg_offset..self.lock_arg_offset + 20)
+            .map(|bytes| H160::from_slice(bytes).expect("Convert to H160 failed"))
+    }
+}
+
+// Multisig config header: require first n, threshold, pubkeys count (S, R, M, N)
+const MULTISIG_CONFIG_HEADER_SIZE: usize = 4;
+
+/// Lock field size of secp256k1_blake160_multisig_all: the multisig config
+/// (header and pubkey hashes) then `threshold` signatures
+pub fn multisig_lock_size(threshold: usize, pubkeys: usize) -> usize {
+    MULTISIG_CONFIG_HEADER_SIZE + 20 * pubkeys + 65 * threshold
+}
+
+/// Multisig config of secp256k1_blake160_multisig_all, lock args of the
+/// multisig address is blake160 of it
+pub fn multisig_config(
+    require_first_n: u8,
+    threshold: u8,
+    pubkey_hashes: &[H160],
+) -> Result<Bytes, String> {
+    if pubkey_hashes.is_empty() || pubkey_hashes.len() > 255 {
+        return Err(format!(
+            "Invalid multisig pubkeys count: {}, expected 1 to 255",
+            pubkey_hashes.len()
+        ));
+    }
+    if threshold == 0 || threshold as usize > pubkey_hashes.len() {
+        return Err(format!(
+            "Invalid multisig threshold: {}, expected 1 to {}",
+            threshold,
+            pubkey_hashes.len()
+        ));
+    }
+    if require_first_n > threshold {
+        return Err(format!(
+            "Multisig require first n {} is more than threshold {}",
+            require_first_n, threshold
+        ));
+    }
+    let mut config = vec![0, require_first_n, threshold, pubkey_hashes.len() as u8];
+    for pubkey_hash in pubkey_hashes {
+        config.extend_from_slice(pubkey_hash.as_bytes());
+    }
+    Ok(Bytes::from(config))
+}
+
+/// Estimated final witness of a lock group, carried by the first input of
+/// the group
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct WitnessEstimate {
+    pub lock_hash: H256,
+    // sighash, multisig, template or unknown (the existing witness is kept)
+    pub kind: String,
+    pub inputs: usize,
+    pub lock_size: Option<usize>,
+    pub witness_size: usize,
+}
+
+/// A lock group whose signatures were cleared by an edit of the transaction,
+/// `signers` are the keys (blake160 of pubkey) which must sign again, for
+/// multisig they are all members of the multisig config.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ResignGroup {
+    pub lock_hash: H256,
+    // sighash, multisig, template or unknown (the witness is kept)
+    pub kind: String,
+    pub first_input: usize,
+    pub signers: Vec<H160>,
+}
+
+/// Fee paid by the change output of `complete_tx_with_fee`
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct FeeEstimate {
+    pub tx_size: usize,
+    // Unit: shannons/KB
+    pub fee_rate: u64,
+    pub fee: u64,
+    pub witnesses: Vec<WitnessEstimate>,
+}
+
+/// Called with (signed, total) after each signature
+pub type SignProgress = Box<dyn Fn(usize, usize) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in a transaction. `code` is a stable kebab-case name of
+/// the check, `location` is the field path in the transaction (example:
+/// `inputs[1]`, `witnesses`, `tx` for the whole transaction).
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct TxFinding {
+    pub severity: Severity,
+    pub code: String,
+    pub location: String,
+    pub message: String,
+}
+
+impl TxFinding {
+    pub fn error(code: &str, location: String, message: String) -> TxFinding {
+        TxFinding {
+            severity: Severity::Error,
+            code: code.to_owned(),
+            location,
+            message,
+        }
+    }
+
+    pub fn warning(code: &str, location: String, message: String) -> TxFinding {
+        TxFinding {
+            severity: Severity::Warning,
+            code: code.to_owned(),
+            location,
+            message,
+        }
+    }
+}
+
 pub struct MockTransactionHelper<'a> {
     pub mock_tx: &'a mut MockTransaction,
     live_cell_cache: HashMap<OutPoint, (CellOutput, Bytes)>,
+    witness_templates: HashMap<Byte32, WitnessTemplate>,
+    sign_progress: Option<SignProgress>,
 }
 
 impl<'a> MockTransactionHelper<'a> {
@@ -29,9 +260,23 @@ impl<'a> MockTransactionHelper<'a> {
         MockTransactionHelper {
             mock_tx,
             live_cell_cache: HashMap::default(),
+            witness_templates: HashMap::default(),
+            sign_progress: None,
         }
     }
 
+    pub fn set_sign_progress(&mut self, progress: SignProgress) {
+        self.sign_progress = Some(progress);
+    }
+
+    /// Inputs locked by these code hashes are signed by `fill_witnesses` too
+    pub fn set_witness_templates(&mut self, templates: Vec<WitnessTemplate>) {
+        self.witness_templates = templates
+            .into_iter()
+            .map(|template| (template.code_hash.pack(), template))
+            .collect();
+    }
+
     fn get_input_cell<C>(
         &mut self,
         input: &CellInput,
@@ -180,6 +425,10 @@ impl<'a> MockTransactionHelper<'a> {
                 .try_into()
                 .map_err(|err: FailureError| err.to_string())?;
             insert_dep(hash_type, &lock.code_hash())?;
+            // Omnilock loads secp256k1 data from cell deps
+            if is_omnilock(&lock.code_hash().unpack()) {
+                cell_deps.insert(genesis_info.secp_dep());
+            }
         }
         for output in tx.outputs().into_iter() {
             if let Some(script) = output.type_().to_opt() {
@@ -209,7 +458,8 @@ impl<'a> MockTransactionHelper<'a> {
         Ok(())
     }
 
-    /// Compute transaction hash and set witnesses for inputs (search by lock scripts)
+    /// Compute transaction hash and set witnesses for inputs (search by lock
+    /// scripts), lock groups are signed in parallel
     pub fn fill_witnesses<S, C>(
         &mut self,
         genesis_info: &GenesisInfo,
@@ -217,7 +467,7 @@ impl<'a> MockTransactionHelper<'a> {
         mut live_cell_getter: C,
     ) -> Result<(), String>
     where
-        S: Fn(&H160, &H256) -> Result<[u8; 65], String>,
+        S: Fn(&H160, &H256) -> Result<[u8; 65], String> + Sync,
         C: FnMut(OutPoint) -> Result<Option<(CellOutput, Bytes)>, String>,
     {
         let tx = self.mock_tx.core_transaction();
@@ -225,46 +475,161 @@ impl<'a> MockTransactionHelper<'a> {
         while witnesses.len() < tx.inputs().len() {
             witnesses.push(Bytes::new().pack());
         }
-        let mut input_group: HashMap<H160, Vec<usize>> = HashMap::default();
+        // Group by lock script: (lock_arg, witness template, input indexes)
+        let mut input_group: HashMap<Byte32, (H160, Option<WitnessTemplate>, Vec<usize>)> =
+            HashMap::default();
         for (idx, input) in tx.inputs().into_iter().enumerate() {
             let lock = self.get_input_cell(&input, &mut live_cell_getter)?.0.lock();
-            if &lock.code_hash() == genesis_info.secp_type_hash()
+            let args = lock.args().raw_data();
+            let (lock_arg, template_opt) = if &lock.code_hash() == genesis_info.secp_type_hash()
                 && lock.hash_type() == ScriptHashType::Type.into()
-                && lock.args().raw_data().len() == 20
+                && args.len() == 20
             {
-                let lock_arg =
-                    H160::from_slice(&lock.args().raw_data()).expect("Convert to H160 failed");
-                input_group
-                    .entry(lock_arg)
-                    .or_insert_with(Vec::new)
-                    .push(idx);
-            }
+                (
+                    H160::from_slice(&args).expect("Convert to H160 failed"),
+                    None,
+                )
+            } else if let Some(template) = self.witness_templates.get(&lock.code_hash()) {
+                let lock_arg = template.lock_arg(&args).ok_or_else(|| {
+                    format!(
+                        "Lock args of input #{} is shorter than {} bytes, can not sign with witness template",
+                        idx,
+                        template.lock_arg_offset + 20
+                    )
+                })?;
+                (lock_arg, Some(template.clone()))
+            } else {
+                continue;
+            };
+            input_group
+                .entry(lock.calc_script_hash())
+                .or_insert_with(|| (lock_arg, template_opt, Vec::new()))
+                .2
+                .push(idx);
         }
 
-        for (lock_arg, idxs) in input_group.into_iter() {
-            let init_witness = WitnessArgs::new_builder()
-                .lock(Some(Bytes::from(vec![0u8; 65])).pack())
-                .build();
-            let mut blake2b = new_blake2b();
-            blake2b.update(tx.hash().as_slice());
-            blake2b.update(&(init_witness.as_bytes().len() as u64).to_le_bytes());
-            blake2b.update(&init_witness.as_bytes());
-            for idx in idxs.iter().skip(1).cloned() {
-                let other_witness = &witnesses[idx];
-                blake2b.update(&(other_witness.len() as u64).to_le_bytes());
-                blake2b.update(other_witness.as_slice());
+        let groups = input_group.values().collect::<Vec<_>>();
+        let mut messages = Vec::with_capacity(groups.len());
+        for (lock_arg, template_opt, idxs) in groups.iter().cloned() {
+            let init_witness = match template_opt {
+                Some(template) => template.placeholder(),
+                None => WitnessArgs::new_builder()
+                    .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+                    .build(),
+            };
+            let message = group_message(&tx.hash(), &init_witness, &witnesses, idxs);
+            messages.push((lock_arg.clone(), message));
+        }
+
+        let signatures = sign_in_parallel(
+            messages.len(),
+            |index| signer(&messages[index].0, &messages[index].1),
+            self.sign_progress.as_ref(),
+        )?;
+        for ((_, template_opt, idxs), sig) in groups.into_iter().zip(signatures) {
+            let witness = match template_opt {
+                Some(template) => template.with_signature(&sig),
+                None => WitnessArgs::new_builder()
+                    .lock(Some(Bytes::from(sig.as_ref())).pack())
+                    .build(),
+            };
+            witnesses[idxs[0]] = witness.as_bytes().pack();
+        }
+
+        self.mock_tx.tx = self
+            .mock_tx
+            .tx
+            .as_advanced_builder()
+            .set_witnesses(witnesses)
+            .build()
+            .data();
+        Ok(())
+    }
+
+    /// Lock hash and input indexes of the multisig lock group of `config`,
+    /// whose lock args start with blake160 of the config (28 bytes lock args
+    /// also have a since)
+    pub fn multisig_group<C>(
+        &mut self,
+        config: &Bytes,
+        mut live_cell_getter: C,
+    ) -> Result<(Byte32, Vec<usize>), String>
+    where
+        C: FnMut(OutPoint) -> Result<Option<(CellOutput, Bytes)>, String>,
+    {
+        check_multisig_config(config)?;
+        let config_hash = blake2b_256(config);
+        let tx = self.mock_tx.core_transaction();
+        let mut group: Option<(Byte32, Vec<usize>)> = None;
+        for (idx, input) in tx.inputs().into_iter().enumerate() {
+            let lock = self.get_input_cell(&input, &mut live_cell_getter)?.0.lock();
+            let args = lock.args().raw_data();
+            if lock.hash_type() != ScriptHashType::Type.into()
+                || (args.len() != 20 && args.len() != 28)
+                || args[0..20] != config_hash[0..20]
+            {
+                continue;
             }
-            let mut message = [0u8; 32];
-            blake2b.finalize(&mut message);
-            let message = H256::from(message);
-            let sig = signer(&lock_arg, &message).map(|data| Bytes::from(data.as_ref()))?;
-            witnesses[idxs[0]] = WitnessArgs::new_builder()
-                .lock(Some(sig).pack())
-                .build()
-                .as_bytes()
-                .pack();
+            let lock_hash = lock.calc_script_hash();
+            if let Some((group_hash, idxs)) = group.as_mut() {
+                if *group_hash != lock_hash {
+                    return Err(
+                        "Inputs of more than one lock script use the multisig config".to_owned(),
+                    );
+                }
+                idxs.push(idx);
+            } else {
+                group = Some((lock_hash, vec![idx]));
+            }
+        }
+        group.ok_or_else(|| "No input is locked by the multisig config".to_owned())
+    }
+
+    /// Signing message of a secp256k1_blake160_multisig_all lock group (see:
+    /// `multisig_group`), the witness lock is the multisig config then zero
+    /// filled signatures while signing
+    pub fn multisig_message(&self, config: &Bytes, idxs: &[usize]) -> Result<H256, String> {
+        check_multisig_config(config)?;
+        let threshold = config[2] as usize;
+        let tx = self.mock_tx.core_transaction();
+        let mut witnesses: Vec<_> = tx.witnesses().into_iter().collect();
+        while witnesses.len() < tx.inputs().len() {
+            witnesses.push(Bytes::new().pack());
         }
+        let placeholder = multisig_witness(&witnesses[idxs[0]], config, &vec![0u8; 65 * threshold]);
+        Ok(group_message(&tx.hash(), &placeholder, &witnesses, idxs))
+    }
 
+    /// Put the multisig config and `threshold` signatures (in the order of
+    /// their keys in the config) into the witness of the multisig lock group,
+    /// other fields of the witness are kept
+    pub fn fill_multisig_witness(
+        &mut self,
+        config: &Bytes,
+        signatures: &[[u8; 65]],
+        idxs: &[usize],
+    ) -> Result<(), String> {
+        check_multisig_config(config)?;
+        let threshold = config[2] as usize;
+        if signatures.len() != threshold {
+            return Err(format!(
+                "Multisig needs {} signatures, got {}",
+                threshold,
+                signatures.len()
+            ));
+        }
+        let tx = self.mock_tx.core_transaction();
+        let mut witnesses: Vec<_> = tx.witnesses().into_iter().collect();
+        while witnesses.len() < tx.inputs().len() {
+            witnesses.push(Bytes::new().pack());
+        }
+        let signatures = signatures
+            .iter()
+            .flat_map(|signature| signature.iter().cloned())
+            .collect::<Vec<_>>();
+        witnesses[idxs[0]] = multisig_witness(&witnesses[idxs[0]], config, &signatures)
+            .as_bytes()
+            .pack();
         self.mock_tx.tx = self
             .mock_tx
             .tx
@@ -283,7 +648,7 @@ impl<'a> MockTransactionHelper<'a> {
         mut live_cell_getter: C,
     ) -> Result<(), String>
     where
-        S: Fn(&H160, &H256) -> Result<[u8; 65], String>,
+        S: Fn(&H160, &H256) -> Result<[u8; 65], String> + Sync,
         C: FnMut(OutPoint) -> Result<Option<(CellOutput, Bytes)>, String>,
     {
         self.add_change_output(target_lock, &mut live_cell_getter)?;
@@ -291,6 +656,514 @@ impl<'a> MockTransactionHelper<'a> {
         self.fill_witnesses(genesis_info, signer, &mut live_cell_getter)
     }
 
+    /// Same as `complete_tx`, the fee for `fee_rate` (shannons/KB) is paid by
+    /// the change output, the transaction size is estimated with the final
+    /// witness size of every lock group.
+    pub fn complete_tx_with_fee<S, C>(
+        &mut self,
+        target_lock: Option<Script>,
+        fee_rate: u64,
+        genesis_info: &GenesisInfo,
+        signer: S,
+        mut live_cell_getter: C,
+    ) -> Result<FeeEstimate, String>
+    where
+        S: Fn(&H160, &H256) -> Result<[u8; 65], String> + Sync,
+        C: FnMut(OutPoint) -> Result<Option<(CellOutput, Bytes)>, String>,
+    {
+        let outputs_count = self.mock_tx.core_transaction().outputs().len();
+        self.add_change_output(target_lock, &mut live_cell_getter)?;
+        self.fill_deps(genesis_info, &mut live_cell_getter)?;
+        let (tx_size, witnesses) = self.estimate_tx_size(genesis_info, &mut live_cell_getter)?;
+        let fee = (fee_rate * tx_size as u64 + 999) / 1000;
+        let tx = self.mock_tx.core_transaction();
+        if tx.outputs().len() > outputs_count {
+            let change = tx
+                .outputs()
+                .get(outputs_count)
+                .expect("change output exists");
+            let capacity: u64 = change.capacity().unpack();
+            let change = change
+                .as_builder()
+                .capacity(Capacity::shannons(capacity.saturating_sub(fee)).pack())
+                .build();
+            let occupied = change
+                .occupied_capacity(Capacity::zero())
+                .map_err(|err| err.to_string())?
+                .as_u64();
+            if capacity < fee + occupied {
+                return Err(format!(
+                    "Change capacity {} can not pay fee {} (occupied capacity: {})",
+                    capacity, fee, occupied
+                ));
+            }
+            let mut outputs: Vec<_> = tx.outputs().into_iter().collect();
+            outputs[outputs_count] = change;
+            self.mock_tx.tx = self
+                .mock_tx
+                .tx
+                .as_advanced_builder()
+                .set_outputs(outputs)
+                .build()
+                .data();
+        } else {
+            let input_total = self.input_total(&mut live_cell_getter)?;
+            let output_total: u64 = tx
+                .outputs()
+                .into_iter()
+                .map(|output| Unpack::<u64>::unpack(&output.capacity()))
+                .sum();
+            if input_total.saturating_sub(output_total) < fee {
+                return Err(format!(
+                    "input total({}) - output total({}) can not pay fee {}",
+                    input_total, output_total, fee
+                ));
+            }
+        }
+        self.fill_witnesses(genesis_info, signer, &mut live_cell_getter)?;
+        Ok(FeeEstimate {
+            tx_size,
+            fee_rate,
+            fee,
+            witnesses,
+        })
+    }
+
+    fn input_total<C>(&mut self, mut live_cell_getter: C) -> Result<u64, String>
+    where
+        C: FnMut(OutPoint) -> Result<Option<(CellOutput, Bytes)>, String>,
+    {
+        let mut input_total = 0;
+        for input in self.mock_tx.core_transaction().inputs().into_iter() {
+            let (output, _) = self.get_input_cell(&input, &mut live_cell_getter)?;
+            input_total += Unpack::<u64>::unpack(&output.capacity());
+        }
+        Ok(input_total)
+    }
+
+    /// Serialized size (in block) of the transaction after signing, the
+    /// signatures are zero filled placeholders of the final size. A multisig
+    /// witness must already contain the multisig config to know its size.
+    pub fn estimate_tx_size<C>(
+        &mut self,
+        genesis_info: &GenesisInfo,
+        mut live_cell_getter: C,
+    ) -> Result<(usize, Vec<WitnessEstimate>), String>
+    where
+        C: FnMut(OutPoint) -> Result<Option<(CellOutput, Bytes)>, String>,
+    {
+        let tx = self.mock_tx.core_transaction();
+        let mut witnesses: Vec<_> = tx.witnesses().into_iter().collect();
+        while witnesses.len() < tx.inputs().len() {
+            witnesses.push(Bytes::new().pack());
+        }
+        // Lock groups in order of their first input
+        let mut group_indexes: HashMap<Byte32, usize> = HashMap::default();
+        let mut estimates: Vec<WitnessEstimate> = Vec::new();
+        for (idx, input) in tx.inputs().into_iter().enumerate() {
+            let lock = self.get_input_cell(&input, &mut live_cell_getter)?.0.lock();
+            let lock_hash = lock.calc_script_hash();
+            if let Some(group_index) = group_indexes.get(&lock_hash) {
+                estimates[*group_index].inputs += 1;
+                continue;
+            }
+            let is_type = lock.hash_type() == ScriptHashType::Type.into();
+            let (kind, witness) = if is_type && &lock.code_hash() == genesis_info.secp_type_hash() {
+                let witness = WitnessArgs::new_builder()
+                    .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+                    .build();
+                ("sighash", Some(witness))
+            } else if is_type && Some(&lock.code_hash()) == genesis_info.multisig_type_hash() {
+                let config = WitnessArgs::from_slice(witnesses[idx].as_slice())
+                    .ok()
+                    .and_then(|witness| witness.lock().to_opt())
+                    .map(|lock| lock.raw_data())
+                    .filter(|lock| lock.len() >= MULTISIG_CONFIG_HEADER_SIZE)
+                    .ok_or_else(|| {
+                        format!(
+                            "Witness of multisig input #{} has no multisig config, can not estimate its size",
+                            idx
+                        )
+                    })?;
+                let threshold = config[2] as usize;
+                let pubkeys = config[3] as usize;
+                let witness = WitnessArgs::new_builder()
+                    .lock(
+                        Some(Bytes::from(vec![
+                            0u8;
+                            multisig_lock_size(threshold, pubkeys)
+                        ]))
+                        .pack(),
+                    )
+                    .build();
+                ("multisig", Some(witness))
+            } else if let Some(template) = self.witness_templates.get(&lock.code_hash()) {
+                ("template", Some(template.placeholder()))
+            } else {
+                ("unknown", None)
+            };
+            if let Some(witness) = witness.as_ref() {
+                witnesses[idx] = witness.as_bytes().pack();
+            }
+            group_indexes.insert(lock_hash.clone(), estimates.len());
+            estimates.push(WitnessEstimate {
+                lock_hash: lock_hash.unpack(),
+                kind: kind.to_owned(),
+                inputs: 1,
+                lock_size: witness
+                    .as_ref()
+                    .and_then(|witness| witness.lock().to_opt())
+                    .map(|lock| lock.raw_data().len()),
+                witness_size: witnesses[idx].raw_data().len(),
+            });
+        }
+        let tx = tx.as_advanced_builder().set_witnesses(witnesses).build();
+        // 4 bytes for the offset of transaction in block
+        Ok((tx.data().as_slice().len() + 4, estimates))
+    }
+
+    /// Structural checks without any resource loading (no RPC), return all
+    /// found problems: duplicated inputs/deps, outputs data count, witnesses
+    /// count, occupied capacity and capacity balance (when all input cells
+    /// are in mock info).
+    pub fn check_structure(&self) -> Vec<TxFinding> {
+        let tx = self.mock_tx.core_transaction();
+        let mut findings = Vec::new();
+
+        let mut seen_inputs = HashSet::new();
+        for (input_index, input) in tx.inputs().into_iter().enumerate() {
+            let out_point = input.previous_output();
+            if !seen_inputs.insert(out_point.as_slice().to_vec()) {
+                let index: u32 = out_point.index().unpack();
+                findings.push(TxFinding::error(
+                    "duplicated-input",
+                    format!("inputs[{}]", input_index),
+                    format!("Duplicated input: {:#x}-{}", out_point.tx_hash(), index),
+                ));
+            }
+        }
+        let mut seen_cell_deps = HashSet::new();
+        for (dep_index, cell_dep) in tx.cell_deps().into_iter().enumerate() {
+            if !seen_cell_deps.insert(cell_dep.as_slice().to_vec()) {
+                let out_point = cell_dep.out_point();
+                let index: u32 = out_point.index().unpack();
+                findings.push(TxFinding::error(
+                    "duplicated-cell-dep",
+                    format!("cell_deps[{}]", dep_index),
+                    format!("Duplicated cell dep: {:#x}-{}", out_point.tx_hash(), index),
+                ));
+            }
+        }
+        let mut seen_header_deps = HashSet::new();
+        for (dep_index, header_dep) in tx.header_deps().into_iter().enumerate() {
+            if !seen_header_deps.insert(header_dep.as_slice().to_vec()) {
+                findings.push(TxFinding::error(
+                    "duplicated-header-dep",
+                    format!("header_deps[{}]", dep_index),
+                    format!("Duplicated header dep: {:#x}", header_dep),
+                ));
+            }
+        }
+
+        if tx.outputs().len() != tx.outputs_data().len() {
+            findings.push(TxFinding::error(
+                "outputs-data-count",
+                "outputs_data".to_owned(),
+                format!(
+                    "Outputs count {} not match outputs data count {}",
+                    tx.outputs().len(),
+                    tx.outputs_data().len()
+                ),
+            ));
+        }
+        if tx.witnesses().len() < tx.inputs().len() {
+            findings.push(TxFinding::error(
+                "witnesses-count",
+                "witnesses".to_owned(),
+                format!(
+                    "Witnesses count {} less than inputs count {}",
+                    tx.witnesses().len(),
+                    tx.inputs().len()
+                ),
+            ));
+        }
+
+        let outputs_capacity = tx
+            .outputs()
+            .into_iter()
+            .map(|output| Unpack::<u64>::unpack(&output.capacity()))
+            .fold(0u64, u64::saturating_add);
+        for (index, (output, data)) in tx
+            .outputs()
+            .into_iter()
+            .zip(tx.outputs_data().into_iter())
+            .enumerate()
+        {
+            let capacity: u64 = output.capacity().unpack();
+            let location = format!("outputs[{}]", index);
+            match Capacity::bytes(data.raw_data().len())
+                .and_then(|data_capacity| output.occupied_capacity(data_capacity))
+            {
+                Ok(occupied) if occupied.as_u64() > capacity => findings.push(TxFinding::error(
+                    "occupied-capacity",
+                    location,
+                    format!(
+                        "Output #{} occupied capacity {} > declared capacity {}",
+                        index,
+                        occupied.as_u64(),
+                        capacity
+                    ),
+                )),
+                Ok(_) => {}
+                Err(err) => findings.push(TxFinding::error(
+                    "capacity-overflow",
+                    location,
+                    format!("Output #{} capacity error: {:?}", index, err),
+                )),
+            }
+        }
+
+        let inputs_capacity = tx
+            .inputs()
+            .into_iter()
+            .map(|input| {
+                self.mock_tx
+                    .mock_info
+                    .inputs
+                    .iter()
+                    .find(|mock_input| {
+                        mock_input.input.previous_output().as_slice()
+                            == input.previous_output().as_slice()
+                    })
+                    .map(|mock_input| Unpack::<u64>::unpack(&mock_input.output.capacity()))
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|capacities| capacities.into_iter().try_fold(0u64, u64::checked_add));
+        match inputs_capacity {
+            Some(Some(inputs_capacity)) if inputs_capacity < outputs_capacity => {
+                findings.push(TxFinding::error(
+                    "capacity-balance",
+                    "outputs".to_owned(),
+                    format!(
+                        "Inputs capacity {} < outputs capacity {}",
+                        inputs_capacity, outputs_capacity
+                    ),
+                ));
+            }
+            Some(None) => findings.push(TxFinding::error(
+                "capacity-overflow",
+                "inputs".to_owned(),
+                "Sum of inputs capacity overflows".to_owned(),
+            )),
+            _ => {}
+        }
+        findings
+    }
+
+    /// Remove signatures from the witnesses of all signed lock groups, any edit
+    /// of the raw transaction invalidates them. Other fields of the witness
+    /// are kept, a multisig witness keeps its multisig config.
+    pub fn clear_signatures<C>(
+        &mut self,
+        genesis_info: &GenesisInfo,
+        mut live_cell_getter: C,
+    ) -> Result<Vec<ResignGroup>, String>
+    where
+        C: FnMut(OutPoint) -> Result<Option<(CellOutput, Bytes)>, String>,
+    {
+        let tx = self.mock_tx.core_transaction();
+        let mut witnesses: Vec<_> = tx.witnesses().into_iter().collect();
+        let mut seen_locks: HashSet<Byte32> = HashSet::default();
+        let mut groups = Vec::new();
+        for (idx, input) in tx.inputs().into_iter().enumerate() {
+            let lock = self.get_input_cell(&input, &mut live_cell_getter)?.0.lock();
+            let lock_hash = lock.calc_script_hash();
+            if !seen_locks.insert(lock_hash.clone()) {
+                continue;
+            }
+            let witness = match witnesses
+                .get(idx)
+                .filter(|witness| !witness.raw_data().is_empty())
+            {
+                Some(witness) => witness.clone(),
+                None => continue,
+            };
+            let witness_args = WitnessArgs::from_slice(witness.raw_data().as_ref()).ok();
+            let witness_lock = witness_args
+                .as_ref()
+                .and_then(|witness| witness.lock().to_opt())
+                .map(|lock| lock.raw_data());
+            let args = lock.args().raw_data();
+            let is_type = lock.hash_type() == ScriptHashType::Type.into();
+            let (kind, signers, new_witness) = if is_type
+                && &lock.code_hash() == genesis_info.secp_type_hash()
+                && args.len() == 20
+            {
+                if witness_lock.is_none() {
+                    continue;
+                }
+                let cleared = witness_args
+                    .unwrap()
+                    .as_builder()
+                    .lock(None::<Bytes>.pack())
+                    .build();
+                let signer = H160::from_slice(&args).expect("Convert to H160 failed");
+                ("sighash", vec![signer], Some(cleared))
+            } else if is_type && Some(&lock.code_hash()) == genesis_info.multisig_type_hash() {
+                let config =
+                    match witness_lock.filter(|lock| lock.len() >= MULTISIG_CONFIG_HEADER_SIZE) {
+                        Some(config) => config,
+                        None => continue,
+                    };
+                let pubkeys = config[3] as usize;
+                let config_size = MULTISIG_CONFIG_HEADER_SIZE + 20 * pubkeys;
+                if config.len() <= config_size {
+                    // Not signed yet
+                    continue;
+                }
+                let signers = config[MULTISIG_CONFIG_HEADER_SIZE..config_size]
+                    .chunks(20)
+                    .map(|hash| H160::from_slice(hash).expect("Convert to H160 failed"))
+                    .collect();
+                let cleared = witness_args
+                    .unwrap()
+                    .as_builder()
+                    .lock(Some(config.slice(0, config_size)).pack())
+                    .build();
+                ("multisig", signers, Some(cleared))
+            } else if let Some(template) = self.witness_templates.get(&lock.code_hash()) {
+                let signer = template.lock_arg(&args).into_iter().collect();
+                ("template", signer, Some(template.placeholder()))
+            } else {
+                ("unknown", Vec::new(), None)
+            };
+            if let Some(new_witness) = new_witness {
+                witnesses[idx] = new_witness.as_bytes().pack();
+            }
+            groups.push(ResignGroup {
+                lock_hash: lock_hash.unpack(),
+                kind: kind.to_owned(),
+                first_input: idx,
+                signers,
+            });
+        }
+        self.mock_tx.tx = self
+            .mock_tx
+            .tx
+            .as_advanced_builder()
+            .set_witnesses(witnesses)
+            .build()
+            .data();
+        Ok(groups)
+    }
+
+    /// Content not covered by the signatures, which a co-signer could alter
+    /// without invalidating existing signatures: mock cells which are missing,
+    /// not referenced or not matching the transaction (they tell signers what
+    /// is spent), and witnesses after the inputs.
+    pub fn check_malleability(&self) -> Vec<TxFinding> {
+        let tx = self.mock_tx.core_transaction();
+        let mock_info = &self.mock_tx.mock_info;
+        let mut findings = Vec::new();
+
+        for (index, input) in tx.inputs().into_iter().enumerate() {
+            let out_point = input.previous_output();
+            match mock_info.inputs.iter().find(|mock_input| {
+                mock_input.input.previous_output().as_slice() == out_point.as_slice()
+            }) {
+                None => findings.push(TxFinding::error(
+                    "missing-mock-input",
+                    format!("inputs[{}]", index),
+                    "Input cell is not in mock info, signers can not see what is spent".to_owned(),
+                )),
+                Some(mock_input) if mock_input.input.as_slice() != input.as_slice() => findings
+                    .push(TxFinding::error(
+                        "mock-input-mismatch",
+                        format!("inputs[{}]", index),
+                        "Since of the mock input is different from the transaction input"
+                            .to_owned(),
+                    )),
+                Some(_) => {}
+            }
+        }
+        for (index, mock_input) in mock_info.inputs.iter().enumerate() {
+            let out_point = mock_input.input.previous_output();
+            if tx
+                .inputs()
+                .into_iter()
+                .all(|input| input.previous_output().as_slice() != out_point.as_slice())
+            {
+                findings.push(TxFinding::warning(
+                    "floating-mock-input",
+                    format!("mock_info.inputs[{}]", index),
+                    "Mock input is not spent by the transaction".to_owned(),
+                ));
+            }
+        }
+        for (index, cell_dep) in tx.cell_deps().into_iter().enumerate() {
+            if mock_info
+                .cell_deps
+                .iter()
+                .all(|mock_dep| mock_dep.cell_dep.as_slice() != cell_dep.as_slice())
+            {
+                findings.push(TxFinding::error(
+                    "missing-mock-cell-dep",
+                    format!("cell_deps[{}]", index),
+                    "Cell dep is not in mock info".to_owned(),
+                ));
+            }
+        }
+        for (index, mock_dep) in mock_info.cell_deps.iter().enumerate() {
+            if tx
+                .cell_deps()
+                .into_iter()
+                .all(|cell_dep| cell_dep.as_slice() != mock_dep.cell_dep.as_slice())
+            {
+                findings.push(TxFinding::warning(
+                    "floating-mock-cell-dep",
+                    format!("mock_info.cell_deps[{}]", index),
+                    "Mock cell dep is not used by the transaction".to_owned(),
+                ));
+            }
+        }
+        for index in tx.inputs().len()..tx.witnesses().len() {
+            findings.push(TxFinding::error(
+                "floating-witness",
+                format!("witnesses[{}]", index),
+                "Witness after the inputs is not covered by any signature".to_owned(),
+            ));
+        }
+        findings
+    }
+
+    /// Digest of all signable content: the raw transaction (without
+    /// witnesses) and the mock cells and headers. Signers compare it to make
+    /// sure they sign the same content.
+    pub fn content_digest(&self) -> H256 {
+        let tx = self.mock_tx.core_transaction();
+        let mock_info = &self.mock_tx.mock_info;
+        let mut blake2b = new_blake2b();
+        blake2b.update(tx.data().raw().as_slice());
+        for mock_input in &mock_info.inputs {
+            blake2b.update(mock_input.input.as_slice());
+            blake2b.update(mock_input.output.as_slice());
+            blake2b.update(&(mock_input.data.len() as u64).to_le_bytes());
+            blake2b.update(&mock_input.data);
+        }
+        for mock_dep in &mock_info.cell_deps {
+            blake2b.update(mock_dep.cell_dep.as_slice());
+            blake2b.update(mock_dep.output.as_slice());
+            blake2b.update(&(mock_dep.data.len() as u64).to_le_bytes());
+            blake2b.update(&mock_dep.data);
+        }
+        for header in &mock_info.header_deps {
+            blake2b.update(header.data().as_slice());
+        }
+        let mut digest = [0u8; 32];
+        blake2b.finalize(&mut digest);
+        H256::from(digest)
+    }
+
     /// Verify the transaction by local ScriptVerifier
     pub fn verify<L: MockResourceLoader>(
         &mut self,
@@ -315,6 +1188,102 @@ impl<'a> MockTransactionHelper<'a> {
     }
 }
 
+// Signing message of a lock group: the transaction hash, then the first
+// witness of the group (with placeholder lock), then other witnesses of it
+fn group_message(
+    tx_hash: &Byte32,
+    init_witness: &WitnessArgs,
+    witnesses: &[packed::Bytes],
+    idxs: &[usize],
+) -> H256 {
+    let mut blake2b = new_blake2b();
+    blake2b.update(tx_hash.as_slice());
+    blake2b.update(&(init_witness.as_bytes().len() as u64).to_le_bytes());
+    blake2b.update(&init_witness.as_bytes());
+    for idx in idxs.iter().skip(1).cloned() {
+        let other_witness = &witnesses[idx];
+        blake2b.update(&(other_witness.len() as u64).to_le_bytes());
+        blake2b.update(other_witness.as_slice());
+    }
+    let mut message = [0u8; 32];
+    blake2b.finalize(&mut message);
+    H256::from(message)
+}
+
+fn check_multisig_config(config: &Bytes) -> Result<(), String> {
+    if config.len() < MULTISIG_CONFIG_HEADER_SIZE
+        || config.len() != MULTISIG_CONFIG_HEADER_SIZE + 20 * config[3] as usize
+    {
+        return Err(format!("Invalid multisig config length: {}", config.len()));
+    }
+    Ok(())
+}
+
+// Witness lock is the multisig config then signatures, other fields of the
+// current witness are kept
+fn multisig_witness(witness: &packed::Bytes, config: &Bytes, signatures: &[u8]) -> WitnessArgs {
+    let mut lock = config.to_vec();
+    lock.extend_from_slice(signatures);
+    WitnessArgs::from_slice(witness.raw_data().as_ref())
+        .unwrap_or_default()
+        .as_builder()
+        .lock(Some(Bytes::from(lock)).pack())
+        .build()
+}
+
+/// Call `sign` with every index in `0..total` over all cores, results are in
+/// index order. Signing with a keystore account runs scrypt for every
+/// signature, which takes seconds.
+pub fn sign_in_parallel<R, S>(
+    total: usize,
+    sign: S,
+    progress: Option<&SignProgress>,
+) -> Result<Vec<R>, String>
+where
+    R: Send,
+    S: Fn(usize) -> Result<R, String> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let signed = AtomicUsize::new(0);
+    let results = (0..total)
+        .map(|_| Mutex::new(None))
+        .collect::<Vec<Mutex<Option<Result<R, String>>>>>();
+    let threads = cmp::max(cmp::min(num_cpus::get(), total), 1);
+    crossbeam_utils::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|_| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                if index >= total {
+                    break;
+                }
+                let result = sign(index);
+                let failed = result.is_err();
+                *results[index].lock().unwrap() = Some(result);
+                if failed {
+                    // Stop other threads taking new indexes
+                    next.store(total, Ordering::SeqCst);
+                    break;
+                }
+                let count = signed.fetch_add(1, Ordering::SeqCst) + 1;
+                if let Some(progress) = progress {
+                    progress(count, total);
+                }
+            });
+        }
+    })
+    .map_err(|_| "Signing thread panicked".to_owned())?;
+    // Indexes not taken (None) only exist when some signing failed
+    let mut signatures = Vec::with_capacity(total);
+    for result in results {
+        match result.into_inner().unwrap() {
+            Some(Ok(signature)) => signatures.push(signature),
+            Some(Err(err)) => return Err(err),
+            None => {}
+        }
+    }
+    Ok(signatures)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -323,7 +1292,7 @@ mod test {
     use ckb_jsonrpc_types as json_types;
     use ckb_types::{
         core::{capacity_bytes, BlockView, Capacity, HeaderView},
-        h256,
+        h160, h256,
         packed::CellDep,
     };
     use rand::Rng;
@@ -342,6 +1311,45 @@ mod test {
         panic!("Can not find a random private key in 1000 times");
     }
 
+    #[test]
+    fn test_witness_template() {
+        let template = WitnessTemplate {
+            code_hash: h256!("0x1"),
+            lock_size: 85,
+            input_type_size: None,
+            output_type_size: Some(4),
+            signature_field: WitnessField::Lock,
+            signature_offset: 20,
+            lock_arg_offset: 0,
+            signature_prefix: None,
+        };
+        assert!(template.validate().is_ok());
+        let witness = template.with_signature(&[1u8; 65]);
+        let lock = witness.lock().to_opt().unwrap().raw_data();
+        assert_eq!(&lock[0..20], &[0u8; 20][..]);
+        assert_eq!(&lock[20..85], &[1u8; 65][..]);
+        assert!(witness.input_type().to_opt().is_none());
+        assert_eq!(
+            witness.output_type().to_opt().unwrap().raw_data(),
+            Bytes::from(vec![0u8; 4])
+        );
+        assert_eq!(
+            template.placeholder().as_bytes().len(),
+            witness.as_bytes().len()
+        );
+
+        let invalid = WitnessTemplate {
+            signature_field: WitnessField::InputType,
+            ..template.clone()
+        };
+        assert!(invalid.validate().is_err());
+        let invalid = WitnessTemplate {
+            signature_offset: 21,
+            ..template
+        };
+        assert!(invalid.validate().is_err());
+    }
+
     #[test]
     fn test_verify() {
         let genesis_block: json_types::BlockView = serde_json::from_str(GENESIS_JSON).unwrap();
@@ -443,6 +1451,7 @@ mod test {
                 ))
             }
         }
+        let mut fee_mock_tx = mock_tx.clone();
         let mut helper = MockTransactionHelper::new(&mut mock_tx);
         helper
             .complete_tx(None, &genesis_info, signer, |out_point| {
@@ -465,5 +1474,339 @@ mod test {
         helper
             .verify(u64::max_value(), Loader)
             .expect("Verify mock tx failed");
+
+        let mut helper = MockTransactionHelper::new(&mut fee_mock_tx);
+        let estimate = helper
+            .complete_tx_with_fee(None, 1000, &genesis_info, signer, |out_point| {
+                Loader.get_live_cell(out_point)
+            })
+            .expect("Complete mock tx with fee failed");
+        assert_eq!(estimate.witnesses.len(), 1);
+        assert_eq!(estimate.witnesses[0].kind, "sighash");
+        assert_eq!(estimate.witnesses[0].lock_size, Some(65));
+        let tx = helper.mock_tx.core_transaction();
+        assert_eq!(estimate.tx_size, tx.data().as_slice().len() + 4);
+        assert_eq!(estimate.fee, (estimate.tx_size as u64 * 1000 + 999) / 1000);
+        assert_eq!(
+            Unpack::<u64>::unpack(&tx.outputs().get(1).unwrap().capacity()),
+            capacity_bytes!(80).as_u64() - estimate.fee,
+        );
+        helper
+            .verify(u64::max_value(), Loader)
+            .expect("Verify mock tx with fee failed");
+        assert_eq!(multisig_lock_size(2, 3), 4 + 60 + 130);
+    }
+
+    #[test]
+    fn test_multisig_config() {
+        let hashes = vec![H160::from([1u8; 20]), H160::from([2u8; 20])];
+        let config = multisig_config(0, 2, &hashes).unwrap();
+        assert_eq!(config.len(), MULTISIG_CONFIG_HEADER_SIZE + 40);
+        assert_eq!(&config[..MULTISIG_CONFIG_HEADER_SIZE], &[0, 0, 2, 2]);
+        assert_eq!(&config[24..], hashes[1].as_bytes());
+        assert!(multisig_config(0, 3, &hashes).is_err());
+        assert!(multisig_config(0, 0, &hashes).is_err());
+        assert!(multisig_config(2, 1, &hashes).is_err());
+        assert!(multisig_config(0, 1, &[]).is_err());
+    }
+
+    #[test]
+    fn test_check_structure() {
+        let lock_script = Script::new_builder()
+            .args(Bytes::from(H160::default().as_bytes()).pack())
+            .build();
+        let input = CellInput::new(OutPoint::new(h256!("0xff01").pack(), 0), 0);
+        let mut mock_tx = MockTransaction::default();
+        mock_tx.mock_info.inputs.push(MockInput {
+            input: input.clone(),
+            output: CellOutput::new_builder()
+                .capacity(capacity_bytes!(100).pack())
+                .lock(lock_script.clone())
+                .build(),
+            data: Bytes::default(),
+        });
+        let output = CellOutput::new_builder()
+            .capacity(capacity_bytes!(70).pack())
+            .lock(lock_script)
+            .build();
+        mock_tx.tx = mock_tx
+            .tx
+            .as_advanced_builder()
+            .input(input.clone())
+            .input(input)
+            .output(output.clone())
+            .output(output.clone())
+            .output(output)
+            .output_data(Default::default())
+            .output_data(Bytes::from(vec![0u8; 20]).pack())
+            .witness(Bytes::default().pack())
+            .build()
+            .data();
+
+        let helper = MockTransactionHelper::new(&mut mock_tx);
+        let findings = helper.check_structure();
+        // Duplicated input, outputs data count, witnesses count,
+        // occupied capacity of output #1, capacity balance
+        let codes = findings
+            .iter()
+            .map(|finding| finding.code.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            vec![
+                "duplicated-input",
+                "outputs-data-count",
+                "witnesses-count",
+                "occupied-capacity",
+                "capacity-balance",
+            ],
+            "{:?}",
+            findings
+        );
+        assert_eq!(findings[0].location, "inputs[1]");
+        assert_eq!(findings[3].location, "outputs[1]");
+    }
+
+    #[test]
+    fn test_check_structure_inputs_overflow() {
+        let lock_script = Script::new_builder()
+            .args(Bytes::from(H160::default().as_bytes()).pack())
+            .build();
+        let mut mock_tx = MockTransaction::default();
+        let mut builder = mock_tx.tx.as_advanced_builder();
+        for index in 0..2 {
+            let input = CellInput::new(OutPoint::new(h256!("0xff01").pack(), index), 0);
+            mock_tx.mock_info.inputs.push(MockInput {
+                input: input.clone(),
+                output: CellOutput::new_builder()
+                    .capacity(Capacity::shannons(u64::max_value()).pack())
+                    .lock(lock_script.clone())
+                    .build(),
+                data: Bytes::default(),
+            });
+            builder = builder.input(input).witness(Bytes::default().pack());
+        }
+        mock_tx.tx = builder
+            .output(
+                CellOutput::new_builder()
+                    .capacity(capacity_bytes!(100).pack())
+                    .lock(lock_script)
+                    .build(),
+            )
+            .output_data(Default::default())
+            .build()
+            .data();
+
+        let helper = MockTransactionHelper::new(&mut mock_tx);
+        let findings = helper.check_structure();
+        assert_eq!(findings.len(), 1, "{:?}", findings);
+        assert_eq!(findings[0].code, "capacity-overflow");
+        assert_eq!(findings[0].location, "inputs");
+    }
+
+    #[test]
+    fn test_clear_signatures() {
+        let genesis_block: json_types::BlockView = serde_json::from_str(GENESIS_JSON).unwrap();
+        let genesis_block: BlockView = genesis_block.into();
+        let genesis_info = GenesisInfo::from_block(&genesis_block).unwrap();
+        let lock_arg = h160!("0x13e41d6f9292555916f17b4882a5477c01270142");
+        let sighash_lock = |arg: &H160| {
+            Script::new_builder()
+                .code_hash(genesis_info.secp_type_hash().clone())
+                .hash_type(ScriptHashType::Type.into())
+                .args(Bytes::from(arg.as_bytes()).pack())
+                .build()
+        };
+        let mut mock_tx = MockTransaction::default();
+        let mut builder = mock_tx.tx.as_advanced_builder();
+        // Input 0 signed, input 1 of another lock not signed
+        for (index, lock) in vec![sighash_lock(&lock_arg), sighash_lock(&H160::default())]
+            .into_iter()
+            .enumerate()
+        {
+            let input = CellInput::new(OutPoint::new(h256!("0xff01").pack(), index as u32), 0);
+            mock_tx.mock_info.inputs.push(MockInput {
+                input: input.clone(),
+                output: CellOutput::new_builder()
+                    .capacity(capacity_bytes!(100).pack())
+                    .lock(lock)
+                    .build(),
+                data: Bytes::default(),
+            });
+            builder = builder.input(input);
+        }
+        let signed = WitnessArgs::new_builder()
+            .lock(Some(Bytes::from(vec![1u8; 65])).pack())
+            .input_type(Some(Bytes::from(vec![2u8])).pack())
+            .build();
+        mock_tx.tx = builder
+            .witness(signed.as_bytes().pack())
+            .witness(Bytes::default().pack())
+            .build()
+            .data();
+
+        let mut helper = MockTransactionHelper::new(&mut mock_tx);
+        let groups = helper
+            .clear_signatures(&genesis_info, |_| unreachable!())
+            .unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].kind, "sighash");
+        assert_eq!(groups[0].first_input, 0);
+        assert_eq!(groups[0].signers, vec![lock_arg]);
+        let witness = helper
+            .mock_tx
+            .core_transaction()
+            .witnesses()
+            .get(0)
+            .unwrap();
+        let witness = WitnessArgs::from_slice(witness.raw_data().as_ref()).unwrap();
+        assert!(witness.lock().to_opt().is_none());
+        assert!(witness.input_type().to_opt().is_some());
+    }
+
+    #[test]
+    fn test_multisig_witness() {
+        let privkeys = vec![random_privkey(), random_privkey()];
+        let pubkey_hashes = privkeys
+            .iter()
+            .map(|privkey| {
+                let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, privkey);
+                H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20]).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let config = multisig_config(0, 2, &pubkey_hashes).unwrap();
+        let multisig_lock = Script::new_builder()
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(&blake2b_256(&config)[0..20]).pack())
+            .build();
+        let mut mock_tx = MockTransaction::default();
+        let mut builder = mock_tx.tx.as_advanced_builder();
+        for (index, lock) in vec![multisig_lock.clone(), Script::default(), multisig_lock]
+            .into_iter()
+            .enumerate()
+        {
+            let input = CellInput::new(OutPoint::new(h256!("0xff01").pack(), index as u32), 0);
+            mock_tx.mock_info.inputs.push(MockInput {
+                input: input.clone(),
+                output: CellOutput::new_builder()
+                    .capacity(capacity_bytes!(100).pack())
+                    .lock(lock)
+                    .build(),
+                data: Bytes::default(),
+            });
+            builder = builder.input(input);
+        }
+        mock_tx.tx = builder.build().data();
+
+        let mut helper = MockTransactionHelper::new(&mut mock_tx);
+        let (_, idxs) = helper.multisig_group(&config, |_| unreachable!()).unwrap();
+        assert_eq!(idxs, vec![0, 2]);
+        let other_config = multisig_config(0, 1, &pubkey_hashes).unwrap();
+        assert!(helper
+            .multisig_group(&other_config, |_| unreachable!())
+            .is_err());
+
+        let message = helper.multisig_message(&config, &idxs).unwrap();
+        let signatures = privkeys
+            .iter()
+            .map(|privkey| {
+                let signature = SECP256K1.sign_recoverable(
+                    &secp256k1::Message::from_slice(message.as_bytes()).unwrap(),
+                    privkey,
+                );
+                let (recov_id, data) = signature.serialize_compact();
+                let mut signature_bytes = [0u8; 65];
+                signature_bytes[0..64].copy_from_slice(&data[0..64]);
+                signature_bytes[64] = recov_id.to_i32() as u8;
+                signature_bytes
+            })
+            .collect::<Vec<_>>();
+        assert!(helper
+            .fill_multisig_witness(&config, &signatures[0..1], &idxs)
+            .is_err());
+        helper
+            .fill_multisig_witness(&config, &signatures, &idxs)
+            .unwrap();
+        // Signatures are not part of the signing message
+        assert_eq!(helper.multisig_message(&config, &idxs).unwrap(), message);
+        let witness = helper
+            .mock_tx
+            .core_transaction()
+            .witnesses()
+            .get(0)
+            .unwrap();
+        let lock = WitnessArgs::from_slice(witness.raw_data().as_ref())
+            .unwrap()
+            .lock()
+            .to_opt()
+            .unwrap()
+            .raw_data();
+        assert_eq!(lock.len(), multisig_lock_size(2, 2));
+        assert_eq!(lock.slice(0, config.len()), config);
+        assert_eq!(&lock[config.len() + 65..], &signatures[1][..]);
+    }
+
+    #[test]
+    fn test_check_malleability() {
+        let input = CellInput::new(OutPoint::new(h256!("0xff01").pack(), 0), 0);
+        let floating = CellInput::new(OutPoint::new(h256!("0xff02").pack(), 0), 0);
+        let mut mock_tx = MockTransaction::default();
+        for mock_input in vec![input.clone(), floating] {
+            mock_tx.mock_info.inputs.push(MockInput {
+                input: mock_input,
+                output: CellOutput::new_builder()
+                    .capacity(capacity_bytes!(100).pack())
+                    .build(),
+                data: Bytes::default(),
+            });
+        }
+        mock_tx.tx = mock_tx
+            .tx
+            .as_advanced_builder()
+            .input(input)
+            .input(CellInput::new(OutPoint::new(h256!("0xff03").pack(), 0), 0))
+            .witness(Bytes::default().pack())
+            .witness(Bytes::default().pack())
+            .witness(Bytes::from(vec![1u8]).pack())
+            .build()
+            .data();
+
+        let helper = MockTransactionHelper::new(&mut mock_tx);
+        let codes = helper
+            .check_malleability()
+            .into_iter()
+            .map(|finding| (finding.code, finding.location))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            vec![
+                ("missing-mock-input".to_owned(), "inputs[1]".to_owned()),
+                (
+                    "floating-mock-input".to_owned(),
+                    "mock_info.inputs[1]".to_owned()
+                ),
+                ("floating-witness".to_owned(), "witnesses[2]".to_owned()),
+            ]
+        );
+
+        // Witnesses are not signable content
+        let digest = helper.content_digest();
+        let witnesses_changed = {
+            let mut other = helper.mock_tx.clone();
+            other.tx = other
+                .tx
+                .as_advanced_builder()
+                .set_witnesses(Vec::new())
+                .build()
+                .data();
+            MockTransactionHelper::new(&mut other).content_digest()
+        };
+        assert_eq!(digest, witnesses_changed);
+        let mock_changed = {
+            let mut other = helper.mock_tx.clone();
+            other.mock_info.inputs[0].data = Bytes::from(vec![1u8]);
+            MockTransactionHelper::new(&mut other).content_digest()
+        };
+        assert_ne!(digest, mock_changed);
     }
 }
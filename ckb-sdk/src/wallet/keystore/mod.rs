@@ -68,6 +68,9 @@ impl KeyStore {
         self.files.insert(address.clone(), abs_path);
         Ok(address)
     }
+    pub fn keys_dir(&self) -> &Path {
+        &self.keys_dir
+    }
     pub fn get_accounts(&mut self) -> &HashMap<H160, PathBuf> {
         self.refresh_dir().ok();
         &self.files
@@ -307,7 +310,15 @@ impl PassphraseKeyStore {
     ) -> Result<PathBuf, Error> {
         let filepath = self.join_path(filename);
         let json_value = key.to_json(password, self.scrypt_type);
-        let mut file = fs::File::create(&filepath)?;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        // Only readable by current user
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&filepath)?;
         serde_json::to_writer(&mut file, &json_value).map_err(|err| Error::Io(err.to_string()))?;
         Ok(filepath)
     }
@@ -417,6 +428,10 @@ impl Key {
         &self.address
     }
 
+    pub fn extended_pubkey(&self, path: Option<&DerivationPath>) -> Result<ExtendedPubKey, String> {
+        self.master_privkey.extended_pubkey(path)
+    }
+
     pub fn filename(&self) -> String {
         let utc_now = Utc::now();
         let date = utc_now.date();
@@ -468,6 +483,51 @@ impl Key {
         })
     }
 
+    /// Load key from a keystore v3 (Web3 Secret Storage) file of other
+    /// wallets. Neuron encrypts the extended private key (private key and
+    /// chain code, 64 bytes), Ethereum style keystore encrypts only the
+    /// private key (32 bytes), a random chain code is generated for it.
+    /// Returns the key and whether the chain code is from the file.
+    pub fn from_keystore_v3(
+        data: &serde_json::Value,
+        password: &[u8],
+    ) -> Result<(Key, bool), Error> {
+        let version = util::get_u64(data, "version")?;
+        if version != u64::from(KEYSTORE_VERSION) {
+            return Err(Error::ParseJsonFailed(format!(
+                "Unsupported keystore version: {}",
+                version
+            )));
+        }
+        // Some wallets (MyEtherWallet) use "Crypto"
+        let crypto = util::get_value(data, "crypto")
+            .or_else(|_| util::get_value(data, "Crypto"))
+            .and_then(|value| Crypto::from_json(value))?;
+        let mut key_vec = crypto.decrypt(password)?;
+        let result = match key_vec.len() {
+            64 => {
+                let mut key_bytes = [0u8; 64];
+                key_bytes.copy_from_slice(&key_vec);
+                let master_privkey = MasterPrivKey::from_bytes(key_bytes);
+                zeroize_slice(&mut key_bytes);
+                master_privkey.map(|master_privkey| (Key::new(master_privkey), true))
+            }
+            32 => secp256k1::SecretKey::from_slice(&key_vec)
+                .map(|mut secret_key| {
+                    let master_privkey = MasterPrivKey::from_secp_key(&secret_key);
+                    zeroize_privkey(&mut secret_key);
+                    (Key::new(master_privkey), false)
+                })
+                .map_err(|_| Error::InvalidSecpSecret),
+            len => Err(Error::ParseJsonFailed(format!(
+                "Invalid ciphertext length: {}, expected: 32 or 64",
+                len
+            ))),
+        };
+        zeroize_slice(&mut key_vec);
+        result
+    }
+
     pub fn to_json(&self, password: &[u8], scrypt_type: ScryptType) -> serde_json::Value {
         let mut buf = Uuid::encode_buffer();
         let id_str = self.id.to_hyphenated().encode_lower(&mut buf);
@@ -545,13 +605,7 @@ impl MasterPrivKey {
     }
 
     pub fn extended_pubkey(&self, path: Option<&DerivationPath>) -> Result<ExtendedPubKey, String> {
-        let sk = ExtendedPrivKey {
-            depth: 0,
-            parent_fingerprint: Default::default(),
-            child_number: ChildNumber::Normal { index: 0 },
-            private_key: self.secp_secret_key,
-            chain_code: ChainCode(self.chain_code),
-        };
+        let sk = self.extended_privkey();
         let sub_sk = if let Some(path) = path {
             sk.derive_priv(&SECP256K1, path)
                 .map_err(|err| err.to_string())?
@@ -561,6 +615,24 @@ impl MasterPrivKey {
         Ok(ExtendedPubKey::from_private(&SECP256K1, &sub_sk))
     }
 
+    /// Secp256k1 private key of the child key at `path`
+    pub fn derive_privkey(&self, path: &DerivationPath) -> Result<secp256k1::SecretKey, String> {
+        self.extended_privkey()
+            .derive_priv(&SECP256K1, path)
+            .map(|sub_sk| sub_sk.private_key)
+            .map_err(|err| err.to_string())
+    }
+
+    fn extended_privkey(&self) -> ExtendedPrivKey {
+        ExtendedPrivKey {
+            depth: 0,
+            parent_fingerprint: Default::default(),
+            child_number: ChildNumber::Normal { index: 0 },
+            private_key: self.secp_secret_key,
+            chain_code: ChainCode(self.chain_code),
+        }
+    }
+
     pub fn address(&self) -> H160 {
         let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &self.secp_secret_key);
         H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20])
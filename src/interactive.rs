@@ -1,5 +1,4 @@
 use std::fs;
-use std::io::Write;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
@@ -13,28 +12,44 @@ use rustyline::{Cmd, CompletionType, Config, EditMode, Editor, KeyPress};
 use serde_json::json;
 
 use crate::subcommands::{
-    AccountSubCommand, CliSubCommand, IndexController, IndexRequest, MockTxSubCommand,
-    RpcSubCommand, UtilSubCommand, WalletSubCommand,
+    AccountSubCommand, AliasSubCommand, AnalyzeSubCommand, ApprovalsSubCommand, AssetSubCommand,
+    ChainSubCommand, CliSubCommand, DevSubCommand, DoctorSubCommand, ExportSubCommand,
+    IndexController, IndexRequest, MockTxSubCommand, NodeSubCommand, PipelineSubCommand,
+    RpcSubCommand, ServeSubCommand, TestVectorSubCommand, UtilSubCommand, WalletSubCommand,
 };
 use crate::utils::{
+    alias::expand_alias,
+    arg_parser::{ArgParser, CapacityParser, FromStrParser},
     completer::CkbCompleter,
     config::GlobalConfig,
-    other::check_alerts,
+    i18n::{lang, tr},
+    other::{
+        check_alerts, get_genesis_info, get_key_store, lock_hash_hints, render_output,
+        resolve_account, write_private_file,
+    },
+    price::PriceFeed,
     printer::{ColorWhen, OutputFormat, Printable},
 };
-use ckb_sdk::{
-    wallet::{KeyStore, ScryptType},
-    GenesisInfo, HttpRpcClient,
-};
+use ckb_sdk::{wallet::KeyStore, GenesisInfo, HttpRpcClient};
 
 const ENV_PATTERN: &str = r"\$\{\s*(?P<key>\S+)\s*\}";
 
 /// Interactive command line
 pub struct InteractiveEnv {
+    ckb_cli_dir: PathBuf,
     config: GlobalConfig,
     config_file: PathBuf,
     history_file: PathBuf,
     index_dir: PathBuf,
+    headers_dir: PathBuf,
+    witness_templates_file: PathBuf,
+    lock_templates_file: PathBuf,
+    sign_modes_file: PathBuf,
+    serve_tokens_file: PathBuf,
+    serve_approvals_file: PathBuf,
+    serve_ceremonies_file: PathBuf,
+    mol_schemas_file: PathBuf,
+    price_cache_file: PathBuf,
     parser: clap::App<'static, 'static>,
     key_store: KeyStore,
     rpc_client: HttpRpcClient,
@@ -55,10 +70,25 @@ impl InteractiveEnv {
         history_file.push("history");
         let mut config_file = ckb_cli_dir.clone();
         config_file.push("config");
-        let mut index_dir = ckb_cli_dir.clone();
-        index_dir.push("index");
-        let mut keystore_dir = ckb_cli_dir.clone();
-        keystore_dir.push("keystore");
+        let index_dir = config.index_dir(&ckb_cli_dir);
+        let mut headers_dir = ckb_cli_dir.clone();
+        headers_dir.push("headers");
+        let mut witness_templates_file = ckb_cli_dir.clone();
+        witness_templates_file.push("witness-templates.json");
+        let mut lock_templates_file = ckb_cli_dir.clone();
+        lock_templates_file.push("lock-templates.json");
+        let mut sign_modes_file = ckb_cli_dir.clone();
+        sign_modes_file.push("sign-modes.json");
+        let mut serve_tokens_file = ckb_cli_dir.clone();
+        serve_tokens_file.push("serve-tokens.json");
+        let mut serve_approvals_file = ckb_cli_dir.clone();
+        serve_approvals_file.push("serve-approvals.json");
+        let mut serve_ceremonies_file = ckb_cli_dir.clone();
+        serve_ceremonies_file.push("serve-ceremonies.json");
+        let mut mol_schemas_file = ckb_cli_dir.clone();
+        mol_schemas_file.push("mol-schemas.json");
+        let mut price_cache_file = ckb_cli_dir.clone();
+        price_cache_file.push("price-cache.json");
 
         let mut env_file = ckb_cli_dir.clone();
         env_file.push("env_vars");
@@ -73,13 +103,21 @@ impl InteractiveEnv {
 
         let parser = crate::build_interactive();
         let rpc_client = HttpRpcClient::from_uri(config.get_url());
-        fs::create_dir_all(&keystore_dir).map_err(|err| err.to_string())?;
-        let key_store = KeyStore::from_dir(keystore_dir, ScryptType::default())
-            .map_err(|err| err.to_string())?;
+        let key_store = get_key_store(&ckb_cli_dir)?;
         Ok(InteractiveEnv {
+            ckb_cli_dir,
             config,
             config_file,
             index_dir,
+            headers_dir,
+            witness_templates_file,
+            lock_templates_file,
+            sign_modes_file,
+            serve_tokens_file,
+            serve_approvals_file,
+            serve_ceremonies_file,
+            mol_schemas_file,
+            price_cache_file,
             history_file,
             parser,
             rpc_client,
@@ -131,7 +169,7 @@ impl InteractiveEnv {
         rl.bind_sequence(KeyPress::Meta('N'), Cmd::HistorySearchForward);
         rl.bind_sequence(KeyPress::Meta('P'), Cmd::HistorySearchBackward);
         if rl.load_history(&self.history_file).is_err() {
-            eprintln!("No previous history.");
+            eprintln!("{}", tr("No previous history."));
         }
 
         Request::call(
@@ -156,6 +194,7 @@ impl InteractiveEnv {
                             eprintln!("{}", err.to_string());
                         }
                     }
+                    crate::print_trace_summary();
                     rl.add_history_entry(line.as_str());
                 }
                 Err(ReadlineError::Interrupted) => {
@@ -173,14 +212,14 @@ impl InteractiveEnv {
 
             if last_save_history.elapsed() >= Duration::from_secs(120) {
                 if let Err(err) = rl.save_history(&self.history_file) {
-                    eprintln!("Save command history failed: {}", err);
+                    eprintln!("{}: {}", tr("Save command history failed"), err);
                     break;
                 }
                 last_save_history = Instant::now();
             }
         }
         if let Err(err) = rl.save_history(&self.history_file) {
-            eprintln!("Save command history failed: {}", err);
+            eprintln!("{}: {}", tr("Save command history failed"), err);
         }
         Ok(())
     }
@@ -207,6 +246,21 @@ impl InteractiveEnv {
         );
     }
 
+    // Print the output of a command, lock hashes of known accounts are annotated
+    fn print_output(&mut self, output: &dyn Printable, format: OutputFormat, color: bool) {
+        let key_store = &mut self.key_store;
+        let genesis_info = &mut self.genesis_info;
+        let rpc_client = &mut self.rpc_client;
+        let content = render_output(output, format, color, || {
+            if ckb_sdk::is_offline() {
+                return None;
+            }
+            let genesis_info = get_genesis_info(genesis_info, rpc_client).ok()?;
+            lock_hash_hints(key_store, &genesis_info).ok()
+        });
+        println!("{}", content);
+    }
+
     fn genesis_info(&mut self) -> Result<GenesisInfo, String> {
         if self.genesis_info.is_none() {
             let genesis_block: BlockView = self
@@ -222,12 +276,33 @@ impl InteractiveEnv {
         Ok(self.genesis_info.clone().unwrap())
     }
 
+    // Index directory follows the db namespace of current profile
+    fn update_index_dir(&mut self) {
+        let index_dir = self.config.index_dir(&self.ckb_cli_dir);
+        if index_dir != self.index_dir {
+            let index_sender = self.index_controller.sender();
+            Request::call(
+                index_sender,
+                IndexRequest::UpdateIndexDir(index_dir.clone()),
+            );
+            self.index_dir = index_dir;
+        }
+    }
+
+    fn price_feed(&self) -> Option<PriceFeed> {
+        PriceFeed::from_config(self.config.price_feed_url(), self.price_cache_file.clone())
+    }
+
     fn handle_command(&mut self, line: &str, env_regex: &Regex) -> Result<bool, String> {
         let args = match shell_words::split(self.config.replace_cmd(&env_regex, line).as_str()) {
             Ok(args) => args,
             Err(e) => return Err(e.to_string()),
         };
+        let args = expand_alias(self.config.aliases(), args)?;
+        self.handle_args(args)
+    }
 
+    fn handle_args(&mut self, args: Vec<String>) -> Result<bool, String> {
         let format = self.config.output_format();
         let color = ColorWhen::new(self.config.color()).color();
         let debug = self.config.debug();
@@ -235,6 +310,20 @@ impl InteractiveEnv {
             Ok(matches) => {
                 match matches.subcommand() {
                     ("config", Some(m)) => {
+                        if let Some(name) = m.value_of("use-profile") {
+                            let name = if name == "default" {
+                                None
+                            } else {
+                                Some(name.to_owned())
+                            };
+                            self.config.use_profile(name)?;
+                            let url = self.config.get_url().to_string();
+                            let index_sender = self.index_controller.sender();
+                            Request::call(index_sender, IndexRequest::UpdateUrl(url.clone()));
+                            self.rpc_client = HttpRpcClient::from_uri(&url);
+                            self.genesis_info = None;
+                            self.update_index_dir();
+                        }
                         m.value_of("url").and_then(|url| {
                             let index_sender = self.index_controller.sender();
                             Request::call(index_sender, IndexRequest::UpdateUrl(url.to_string()));
@@ -265,20 +354,91 @@ impl InteractiveEnv {
                             self.config.switch_completion_style();
                         }
 
+                        if let Some(input) = m.value_of("default-account") {
+                            let account = resolve_account(&mut self.key_store, input)?;
+                            self.config.set_default_account(Some(account));
+                        }
+
+                        if let Some(urls) = m.value_of("broadcast-urls") {
+                            self.config.set_broadcast_urls(
+                                urls.split(',')
+                                    .filter(|url| !url.is_empty())
+                                    .map(ToOwned::to_owned)
+                                    .collect(),
+                            );
+                        }
+
+                        if let Some(namespace) = m.value_of("db-namespace") {
+                            self.config.set_db_namespace(
+                                Some(namespace.to_owned()).filter(|namespace| !namespace.is_empty()),
+                            )?;
+                            self.update_index_dir();
+                        }
+
+                        if let Some(input) = m.value_of("fee-rates") {
+                            self.config.set_fee_rates(crate::parse_fee_rates(input)?);
+                        }
+
+                        let max_tx_fee_opt: Option<u64> =
+                            CapacityParser.from_matches_opt(m, "max-tx-fee", false)?;
+                        if let Some(max_tx_fee) = max_tx_fee_opt {
+                            self.config.set_max_tx_fee(max_tx_fee);
+                        }
+
+                        let min_confirmations: Option<u64> = FromStrParser::<u64>::default()
+                            .from_matches_opt(m, "min-confirmations", false)?;
+                        if let Some(confirmations) = min_confirmations {
+                            self.config.set_min_confirmations(confirmations);
+                        }
+
+                        if let Some(input) = m.value_of("add-network") {
+                            self.config.add_network(crate::parse_network(input)?)?;
+                        }
+
+                        if let Some(name) = m.value_of("remove-network") {
+                            self.config.remove_network(name)?;
+                        }
+
+                        if let Some(url) = m.value_of("price-feed-url") {
+                            self.config.set_price_feed_url(
+                                Some(url.to_owned()).filter(|url| !url.is_empty()),
+                            );
+                        }
+
+                        if let Some(name) = m.value_of("save-profile") {
+                            if name == "default" {
+                                return Err("\"default\" is reserved for top level settings".to_owned());
+                            }
+                            let profile = self.config.current_profile();
+                            self.config.add_profile(name.to_owned(), profile);
+                        }
+
                         self.config.print();
-                        let mut file = fs::File::create(self.config_file.as_path())
-                            .map_err(|err| format!("open config error: {:?}", err))?;
+                        let (default_profile, profiles) = self.config.profiles();
                         let content = serde_json::to_string_pretty(&json!({
-                            "url": self.config.get_url().to_string(),
+                            "url": default_profile.url,
                             "color": self.config.color(),
                             "debug": self.config.debug(),
                             "output_format": self.config.output_format().to_string(),
                             "completion_style": self.config.completion_style(),
                             "edit_style": self.config.edit_style(),
+                            "default_account": default_profile.default_account.map(|account| format!("{:x}", account)),
+                            "broadcast_urls": default_profile.broadcast_urls,
+                            "fee_rates": default_profile.fee_rates,
+                            "max_tx_fee": default_profile.max_tx_fee,
+                            "db_namespace": default_profile.db_namespace,
+                            "price_feed_url": self.config.price_feed_url(),
+                            "min_confirmations": self.config.min_confirmations(),
+                            "networks": self.config.networks(),
+                            "aliases": self.config.aliases(),
+                            "profile": self.config.profile(),
+                            "profiles": profiles,
+                            "rpc_options": Some(ckb_sdk::rpc_client_options()).filter(|options| !options.is_empty()),
                         }))
                         .unwrap();
-                        file.write_all(content.as_bytes())
-                            .map_err(|err| format!("save config error: {:?}", err))?;
+                        // Holds rpc_options credentials
+                        write_private_file(self.config_file.as_path(), content.as_bytes(), true)
+                            .map_err(|err| format!("save config error: {}", err))?;
                         Ok(())
                     }
                     ("set", Some(m)) => {
@@ -298,13 +458,8 @@ impl InteractiveEnv {
                     }
                     ("rpc", Some(sub_matches)) => {
                         check_alerts(&mut self.rpc_client);
-                        let output = RpcSubCommand::new(&mut self.rpc_client).process(
-                            &sub_matches,
-                            format,
-                            color,
-                            debug,
-                        )?;
-                        println!("{}", output);
+                        let output = RpcSubCommand::new(&mut self.rpc_client).process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
                         Ok(())
                     }
                     ("account", Some(sub_matches)) => {
@@ -313,9 +468,11 @@ impl InteractiveEnv {
                             &mut self.rpc_client,
                             &mut self.key_store,
                             genesis_info,
+                            self.lock_templates_file.clone(),
+                            self.sign_modes_file.clone(),
                         )
-                        .process(&sub_matches, format, color, debug)?;
-                        println!("{}", output);
+                        .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
                         Ok(())
                     }
                     ("mock-tx", Some(sub_matches)) => {
@@ -324,16 +481,134 @@ impl InteractiveEnv {
                             &mut self.rpc_client,
                             &mut self.key_store,
                             genesis_info,
+                            self.witness_templates_file.clone(),
+                            self.sign_modes_file.clone(),
+                            color,
                         )
-                        .process(&sub_matches, format, color, debug)?;
-                        println!("{}", output);
+                        .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
                         Ok(())
                     }
                     ("util", Some(sub_matches)) => {
                         let genesis_info = self.genesis_info().ok();
-                        let output = UtilSubCommand::new(&mut self.rpc_client, genesis_info)
-                            .process(&sub_matches, format, color, debug)?;
-                        println!("{}", output);
+                        let output = UtilSubCommand::new(
+                            &mut self.rpc_client,
+                            genesis_info,
+                            self.mol_schemas_file.clone(),
+                        )
+                            .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
+                        Ok(())
+                    }
+                    ("export", Some(sub_matches)) => {
+                        let output = ExportSubCommand::new(&mut self.rpc_client)
+                            .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
+                        Ok(())
+                    }
+                    ("analyze", Some(sub_matches)) => {
+                        let genesis_info = self.genesis_info().ok();
+                        let output = AnalyzeSubCommand::new(&mut self.rpc_client, genesis_info)
+                            .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
+                        Ok(())
+                    }
+                    ("chain", Some(sub_matches)) => {
+                        let output =
+                            ChainSubCommand::new(&mut self.rpc_client, self.headers_dir.clone())
+                                .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
+                        Ok(())
+                    }
+                    ("dev", Some(sub_matches)) => {
+                        let genesis_info = self.genesis_info().ok();
+                        let output = DevSubCommand::new(&mut self.rpc_client, genesis_info)
+                            .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
+                        Ok(())
+                    }
+                    ("node", Some(sub_matches)) => {
+                        let output = NodeSubCommand::new(&mut self.rpc_client)
+                            .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
+                        Ok(())
+                    }
+                    ("pipeline", Some(sub_matches)) => {
+                        let mut global_args = vec![
+                            "--min-confirmations".to_owned(),
+                            self.config.min_confirmations().to_string(),
+                            "--lang".to_owned(),
+                            lang().as_str().to_owned(),
+                        ];
+                        if let Some(profile) = self.config.profile() {
+                            global_args.extend(vec!["--profile".to_owned(), profile.to_owned()]);
+                        }
+                        if ckb_sdk::trace::is_trace() {
+                            global_args.push("--trace".to_owned());
+                        }
+                        let output =
+                            PipelineSubCommand::new(self.config.get_url().to_owned(), global_args)
+                                .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
+                        Ok(())
+                    }
+                    ("doctor", Some(sub_matches)) => {
+                        let output = DoctorSubCommand::new(
+                            &mut self.rpc_client,
+                            self.ckb_cli_dir.clone(),
+                            self.index_dir.clone(),
+                            self.config.profile().map(ToOwned::to_owned),
+                        )
+                        .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
+                        Ok(())
+                    }
+                    ("alias", Some(sub_matches)) => {
+                        let output = AliasSubCommand::new(self.config.aliases())
+                            .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
+                        Ok(())
+                    }
+                    ("serve", Some(sub_matches)) => {
+                        let output = ServeSubCommand::new(
+                            self.serve_tokens_file.clone(),
+                            self.serve_approvals_file.clone(),
+                            self.serve_ceremonies_file.clone(),
+                            &mut self.key_store,
+                        )
+                        .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
+                        Ok(())
+                    }
+                    ("approvals", Some(sub_matches)) => {
+                        let mut approvals = ApprovalsSubCommand::new(
+                            self.serve_tokens_file.clone(),
+                            self.serve_approvals_file.clone(),
+                            self.price_feed(),
+                        );
+                        if let ("approve", Some(m)) = sub_matches.subcommand() {
+                            // Sign and send by `wallet transfer`, the password
+                            // prompt there is the last chance to cancel
+                            let (id, args) = approvals.approve(m)?;
+                            let result = self.handle_args(args).map(|_| ());
+                            approvals.finish(&id, &result)?;
+                            result
+                        } else {
+                            let output = approvals.process(&sub_matches, debug)?;
+                            self.print_output(&output, format, color);
+                            Ok(())
+                        }
+                    }
+                    ("test-vector", Some(sub_matches)) => {
+                        let output = TestVectorSubCommand::new()
+                            .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
+                        Ok(())
+                    }
+                    ("asset", Some(sub_matches)) => {
+                        let output = AssetSubCommand::new(&mut self.rpc_client, self.index_dir.clone())
+                            .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
                         Ok(())
                     }
                     ("wallet", Some(sub_matches)) => {
@@ -344,10 +619,19 @@ impl InteractiveEnv {
                             Some(genesis_info),
                             self.index_dir.clone(),
                             self.index_controller.clone(),
+                            self.config.default_account().cloned(),
+                            self.config.broadcast_urls().to_vec(),
+                            self.config.fee_rates().clone(),
+                            self.config.max_tx_fee(),
+                            self.config.min_confirmations(),
+                            self.price_feed(),
+                            self.lock_templates_file.clone(),
                             true,
+                            format,
+                            color,
                         )
-                        .process(&sub_matches, format, color, debug)?;
-                        println!("{}", output);
+                        .process(&sub_matches, debug)?;
+                        self.print_output(&output, format, color);
                         Ok(())
                     }
                     ("exit", _) => {
@@ -1,13 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
 use std::io::{self, Read};
 use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::sync::Arc;
+use std::time::Instant;
 
 use ckb_build_info::Version;
-use ckb_sdk::HttpRpcClient;
+use ckb_sdk::{CustomNetwork, HttpRpcClient};
+use ckb_types::H160;
 use ckb_util::RwLock;
 use clap::crate_version;
 use clap::{App, AppSettings, Arg, SubCommand};
@@ -16,14 +19,22 @@ use subcommands::TuiSubCommand;
 
 use interactive::InteractiveEnv;
 use subcommands::{
-    start_index_thread, AccountSubCommand, CliSubCommand, IndexThreadState, MockTxSubCommand,
-    RpcSubCommand, UtilSubCommand, WalletSubCommand,
+    start_index_thread, AccountSubCommand, AliasSubCommand, AnalyzeSubCommand, ApprovalsSubCommand,
+    AssetSubCommand, ChainSubCommand, CliSubCommand, DevSubCommand, DoctorSubCommand,
+    ExportSubCommand, IndexThreadState, MockTxSubCommand, NodeSubCommand, PipelineSubCommand,
+    RpcSubCommand, ServeSubCommand, TestVectorSubCommand, UtilSubCommand, WalletSubCommand,
 };
 use utils::{
-    arg_parser::{ArgParser, UrlParser},
-    config::GlobalConfig,
-    other::{check_alerts, get_key_store},
-    printer::{ColorWhen, OutputFormat},
+    alias::{expand_alias, load_aliases, parse_aliases},
+    arg_parser::{
+        read_arg_value, ArgParser, CapacityParser, FixedHashParser, FromStrParser, UrlParser,
+    },
+    config::{GlobalConfig, Profile},
+    i18n::{set_lang, tr, Lang},
+    invocation_log::{append_record, redact_args, redact_url, InvocationRecord, LogFormat},
+    other::{check_alerts, get_genesis_info, get_key_store, lock_hash_hints, render_output},
+    price::PriceFeed,
+    printer::{ColorWhen, OutputFormat, Printable, Text},
 };
 
 mod interactive;
@@ -41,7 +52,45 @@ fn main() -> Result<(), io::Error> {
     let version = get_version();
     let version_short = version.short();
     let version_long = version.long();
-    let matches = build_cli(&version_short, &version_long).get_matches();
+    let mut ckb_cli_dir = dirs::home_dir().unwrap();
+    ckb_cli_dir.push(".ckb-cli");
+    let mut config_file = ckb_cli_dir.clone();
+    config_file.push("config");
+
+    // Aliases are expanded before parsing, they are read from config file here
+    let mut args = env::args().collect::<Vec<_>>();
+    let rest_args = args.split_off(1);
+    match load_aliases(&config_file).and_then(|aliases| expand_alias(&aliases, rest_args)) {
+        Ok(rest_args) => args.extend(rest_args),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+    let started_at = (Instant::now(), chrono::Utc::now().to_rfc3339());
+    let log_args = redact_args(&args);
+    // Address arguments are validated while parsing, custom network prefixes
+    // must be known before that
+    ckb_sdk::set_custom_networks(load_networks(&config_file));
+    let matches = build_cli(&version_short, &version_long).get_matches_from(args);
+    set_lang(
+        matches
+            .value_of("lang")
+            .and_then(|lang| lang.parse().ok())
+            .unwrap_or_else(Lang::detect),
+    );
+    if matches.is_present("offline") {
+        ckb_sdk::set_offline(true);
+    }
+    if matches.is_present("trace") {
+        ckb_sdk::trace::set_trace(true);
+    }
+    if let Some(size) = matches
+        .value_of("rpc-cache-size")
+        .and_then(|input| input.parse::<usize>().ok())
+    {
+        ckb_sdk::set_rpc_cache_size(size);
+    }
 
     let mut env_map: HashMap<String, String> = HashMap::from_iter(env::vars());
     let api_uri_opt = matches
@@ -49,19 +98,32 @@ fn main() -> Result<(), io::Error> {
         .map(ToOwned::to_owned)
         .or_else(|| env_map.remove("API_URL"));
 
-    let mut ckb_cli_dir = dirs::home_dir().unwrap();
-    ckb_cli_dir.push(".ckb-cli");
     let mut resource_dir = ckb_cli_dir.clone();
     resource_dir.push("resource");
-    let mut index_dir = ckb_cli_dir.clone();
-    index_dir.push("index");
+    let mut headers_dir = ckb_cli_dir.clone();
+    headers_dir.push("headers");
+    let mut witness_templates_file = ckb_cli_dir.clone();
+    witness_templates_file.push("witness-templates.json");
+    let mut lock_templates_file = ckb_cli_dir.clone();
+    lock_templates_file.push("lock-templates.json");
+    let mut sign_modes_file = ckb_cli_dir.clone();
+    sign_modes_file.push("sign-modes.json");
+    let mut serve_tokens_file = ckb_cli_dir.clone();
+    serve_tokens_file.push("serve-tokens.json");
+    let mut serve_approvals_file = ckb_cli_dir.clone();
+    serve_approvals_file.push("serve-approvals.json");
+    let mut serve_ceremonies_file = ckb_cli_dir.clone();
+    serve_ceremonies_file.push("serve-ceremonies.json");
+    let mut mol_schemas_file = ckb_cli_dir.clone();
+    mol_schemas_file.push("mol-schemas.json");
+    let mut price_cache_file = ckb_cli_dir.clone();
+    price_cache_file.push("price-cache.json");
     let index_state = Arc::new(RwLock::new(IndexThreadState::default()));
 
     let mut config = GlobalConfig::new(api_uri_opt.clone(), Arc::clone(&index_state));
-    let mut config_file = ckb_cli_dir.clone();
-    config_file.push("config");
 
     let mut output_format = OutputFormat::Yaml;
+    let mut profile_opt = None;
     if config_file.as_path().exists() {
         let mut file = fs::File::open(&config_file)?;
         let mut content = String::new();
@@ -80,18 +142,122 @@ fn main() -> Result<(), io::Error> {
         config.set_output_format(output_format);
         config.set_completion_style(configs["completion_style"].as_bool().unwrap_or(true));
         config.set_edit_style(configs["edit_style"].as_bool().unwrap_or(true));
+        if let Some(value) = configs["default_account"].as_str() {
+            config.set_default_account(FixedHashParser::<H160>::default().parse(value).ok());
+        }
+        if let Some(urls) = configs["broadcast_urls"].as_array() {
+            config.set_broadcast_urls(
+                urls.iter()
+                    .filter_map(|url| url.as_str().map(ToOwned::to_owned))
+                    .collect(),
+            );
+        }
+        if let Some(fee_rates) = configs["fee_rates"].as_object() {
+            config.set_fee_rates(
+                fee_rates
+                    .iter()
+                    .filter_map(|(preset, rate)| rate.as_u64().map(|rate| (preset.clone(), rate)))
+                    .collect(),
+            );
+        }
+        if let Some(max_tx_fee) = configs["max_tx_fee"].as_u64() {
+            config.set_max_tx_fee(max_tx_fee);
+        }
+        if let Err(err) =
+            config.set_db_namespace(configs["db_namespace"].as_str().map(ToOwned::to_owned))
+        {
+            eprintln!("{}", err);
+        }
+        config.set_price_feed_url(configs["price_feed_url"].as_str().map(ToOwned::to_owned));
+        config.set_min_confirmations(configs["min_confirmations"].as_u64().unwrap_or(0));
+        if let Some(profiles) = configs["profiles"].as_object() {
+            for (name, value) in profiles {
+                match serde_json::from_value::<Profile>(value.clone()) {
+                    Ok(profile) => config.add_profile(name.clone(), profile),
+                    Err(err) => eprintln!("Invalid profile {}: {}", name, err),
+                }
+            }
+        }
+        if let Some(networks) = configs["networks"].as_array() {
+            let networks = networks
+                .iter()
+                .filter_map(|value| match parse_config_network(value) {
+                    Ok(network) => Some(network),
+                    Err(err) => {
+                        eprintln!("Invalid network {}: {}", value, err);
+                        None
+                    }
+                })
+                .collect();
+            config.set_networks(networks);
+        }
+        match parse_aliases(&configs["aliases"]) {
+            Ok(aliases) => config.set_aliases(aliases),
+            Err(err) => eprintln!("Invalid aliases: {}", err),
+        }
+        profile_opt = configs["profile"].as_str().map(ToOwned::to_owned);
+        if !configs["rpc_options"].is_null() {
+            match serde_json::from_value::<ckb_sdk::RpcClientOptions>(
+                configs["rpc_options"].clone(),
+            ) {
+                Ok(options) => ckb_sdk::set_rpc_client_options(options),
+                Err(err) => eprintln!("Invalid rpc_options: {}", err),
+            }
+        }
+    }
+    // Set by pipeline for its steps
+    if let Some(options) = env_map.remove("RPC_OPTIONS") {
+        match serde_json::from_str::<ckb_sdk::RpcClientOptions>(&options) {
+            Ok(options) => ckb_sdk::set_rpc_client_options(options),
+            Err(err) => eprintln!("Invalid RPC_OPTIONS: {}", err),
+        }
+    }
+    if let Some(name) = matches
+        .value_of("profile")
+        .map(ToOwned::to_owned)
+        .or(profile_opt)
+    {
+        if let Err(err) = config.use_profile(Some(name)) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        if let Some(url) = api_uri_opt {
+            config.set_url(url);
+        }
+    }
+
+    // Override the config for this run only
+    if let Some(confirmations) = matches
+        .value_of("min-confirmations")
+        .and_then(|input| input.parse::<u64>().ok())
+    {
+        config.set_min_confirmations(confirmations);
     }
 
     let api_uri = config.get_url().to_string();
+    let default_account = config.default_account().cloned();
+    let broadcast_urls = config.broadcast_urls().to_vec();
+    let fee_rates = config.fee_rates().clone();
+    let max_tx_fee = config.max_tx_fee();
+    let min_confirmations = config.min_confirmations();
+    let price_feed = PriceFeed::from_config(config.price_feed_url(), price_cache_file);
+    let profile = config.profile().map(ToOwned::to_owned);
+    let index_dir = config.index_dir(&ckb_cli_dir);
     let index_controller = start_index_thread(api_uri.as_str(), index_dir.clone(), index_state);
     let mut rpc_client = HttpRpcClient::from_uri(api_uri.as_str());
-    check_alerts(&mut rpc_client);
+    if !ckb_sdk::is_offline() {
+        check_alerts(&mut rpc_client);
+    }
 
     let color = ColorWhen::new(!matches.is_present("no-color")).color();
     let debug = matches.is_present("debug");
     if let Some(format) = matches.value_of("output-format") {
         output_format = OutputFormat::from_str(format).unwrap();
     }
+    let log_broadcast_urls = broadcast_urls
+        .iter()
+        .map(|url| redact_url(url))
+        .collect::<Vec<_>>();
     let result = match matches.subcommand() {
         #[cfg(unix)]
         ("tui", _) => TuiSubCommand::new(
@@ -99,32 +265,79 @@ fn main() -> Result<(), io::Error> {
             index_dir.clone(),
             index_controller.clone(),
         )
-        .start(),
+        .start()
+        .map(|message| Box::new(Text::from(message)) as Box<dyn Printable>),
         ("rpc", Some(sub_matches)) => {
-            RpcSubCommand::new(&mut rpc_client).process(&sub_matches, output_format, color, debug)
+            RpcSubCommand::new(&mut rpc_client).process(&sub_matches, debug)
         }
         ("account", Some(sub_matches)) => get_key_store(&ckb_cli_dir).and_then(|mut key_store| {
-            AccountSubCommand::new(&mut rpc_client, &mut key_store, None).process(
-                &sub_matches,
-                output_format,
-                color,
-                debug,
+            AccountSubCommand::new(
+                &mut rpc_client,
+                &mut key_store,
+                None,
+                lock_templates_file,
+                sign_modes_file,
             )
+            .process(&sub_matches, debug)
         }),
         ("mock-tx", Some(sub_matches)) => get_key_store(&ckb_cli_dir).and_then(|mut key_store| {
-            MockTxSubCommand::new(&mut rpc_client, &mut key_store, None).process(
-                &sub_matches,
-                output_format,
+            MockTxSubCommand::new(
+                &mut rpc_client,
+                &mut key_store,
+                None,
+                witness_templates_file,
+                sign_modes_file,
                 color,
-                debug,
             )
+            .process(&sub_matches, debug)
         }),
-        ("util", Some(sub_matches)) => UtilSubCommand::new(&mut rpc_client, None).process(
-            &sub_matches,
-            output_format,
-            color,
-            debug,
-        ),
+        ("util", Some(sub_matches)) => UtilSubCommand::new(&mut rpc_client, None, mol_schemas_file)
+            .process(&sub_matches, debug),
+        ("export", Some(sub_matches)) => {
+            ExportSubCommand::new(&mut rpc_client).process(&sub_matches, debug)
+        }
+        ("analyze", Some(sub_matches)) => {
+            AnalyzeSubCommand::new(&mut rpc_client, None).process(&sub_matches, debug)
+        }
+        ("chain", Some(sub_matches)) => {
+            ChainSubCommand::new(&mut rpc_client, headers_dir).process(&sub_matches, debug)
+        }
+        ("dev", Some(sub_matches)) => {
+            DevSubCommand::new(&mut rpc_client, None).process(&sub_matches, debug)
+        }
+        ("node", Some(sub_matches)) => {
+            NodeSubCommand::new(&mut rpc_client).process(&sub_matches, debug)
+        }
+        ("pipeline", Some(sub_matches)) => PipelineSubCommand::new(
+            api_uri.clone(),
+            subcommands::pipeline::global_args(&matches),
+        )
+        .process(&sub_matches, debug),
+        ("doctor", Some(sub_matches)) => DoctorSubCommand::new(
+            &mut rpc_client,
+            ckb_cli_dir.clone(),
+            index_dir.clone(),
+            profile,
+        )
+        .process(&sub_matches, debug),
+        ("alias", Some(sub_matches)) => {
+            AliasSubCommand::new(config.aliases()).process(&sub_matches, debug)
+        }
+        ("serve", Some(sub_matches)) => get_key_store(&ckb_cli_dir).and_then(|mut key_store| {
+            ServeSubCommand::new(
+                serve_tokens_file,
+                serve_approvals_file,
+                serve_ceremonies_file,
+                &mut key_store,
+            )
+            .process(&sub_matches, debug)
+        }),
+        ("test-vector", Some(sub_matches)) => {
+            TestVectorSubCommand::new().process(&sub_matches, debug)
+        }
+        ("asset", Some(sub_matches)) => {
+            AssetSubCommand::new(&mut rpc_client, index_dir.clone()).process(&sub_matches, debug)
+        }
         ("wallet", Some(sub_matches)) => get_key_store(&ckb_cli_dir).and_then(|mut key_store| {
             WalletSubCommand::new(
                 &mut rpc_client,
@@ -132,16 +345,25 @@ fn main() -> Result<(), io::Error> {
                 None,
                 index_dir.clone(),
                 index_controller.clone(),
+                default_account,
+                broadcast_urls,
+                fee_rates,
+                max_tx_fee,
+                min_confirmations,
+                price_feed,
+                lock_templates_file,
                 false,
+                output_format,
+                color,
             )
-            .process(&sub_matches, output_format, color, debug)
+            .process(&sub_matches, debug)
         }),
         _ => {
             if let Err(err) =
-                InteractiveEnv::from_config(ckb_cli_dir, config, index_controller.clone())
+                InteractiveEnv::from_config(ckb_cli_dir.clone(), config, index_controller.clone())
                     .and_then(|mut env| env.start())
             {
-                eprintln!("Process error: {}", err);
+                eprintln!("{}: {}", tr("Process error"), err);
                 index_controller.shutdown();
                 process::exit(1);
             }
@@ -150,9 +372,38 @@ fn main() -> Result<(), io::Error> {
         }
     };
 
+    print_trace_summary();
+    if let Some(log_file) = matches.value_of("log-file").map(PathBuf::from) {
+        let record = InvocationRecord {
+            time: started_at.1,
+            version: version_short.clone(),
+            pid: process::id(),
+            args: log_args,
+            subcommand: matches.subcommand_name().map(ToOwned::to_owned),
+            rpc_url: redact_url(&api_uri),
+            broadcast_urls: log_broadcast_urls,
+            offline: ckb_sdk::is_offline(),
+            duration_ms: ckb_sdk::trace::duration_ms(started_at.0.elapsed()),
+            success: result.is_ok(),
+            error: result.as_ref().err().cloned(),
+        };
+        let log_format = LogFormat::from_name(matches.value_of("log-format").unwrap())
+            .expect("checked by possible values");
+        if let Err(err) = append_record(&log_file, log_format, &record) {
+            eprintln!("{}", err);
+        }
+    }
     match result {
-        Ok(message) => {
-            println!("{}", message);
+        Ok(output) => {
+            let content = render_output(&output, output_format, color, || {
+                if ckb_sdk::is_offline() {
+                    return None;
+                }
+                let mut key_store = get_key_store(&ckb_cli_dir).ok()?;
+                let genesis_info = get_genesis_info(&mut None, &mut rpc_client).ok()?;
+                lock_hash_hints(&mut key_store, &genesis_info).ok()
+            });
+            println!("{}", content);
             index_controller.shutdown();
         }
         Err(err) => {
@@ -164,6 +415,91 @@ fn main() -> Result<(), io::Error> {
     Ok(())
 }
 
+/// Print (to stderr) and clear trace records of current thread
+pub fn print_trace_summary() {
+    if !ckb_sdk::trace::is_trace() {
+        return;
+    }
+    let records = ckb_sdk::trace::take_records();
+    let total = records
+        .iter()
+        .map(|record| record.duration)
+        .sum::<std::time::Duration>();
+    eprintln!(
+        "[TRACE] summary: {} operations, {:.3}ms",
+        records.len(),
+        ckb_sdk::trace::duration_ms(total)
+    );
+    let (hits, misses) = ckb_sdk::rpc_cache_stats();
+    eprintln!("[TRACE]   RPC cache: hits={}, misses={}", hits, misses);
+    for (kind, name, count, duration, size) in ckb_sdk::trace::summary(&records) {
+        eprintln!(
+            "[TRACE]   {:?} {}: count={}, total={:.3}ms, size={}",
+            kind,
+            name,
+            count,
+            ckb_sdk::trace::duration_ms(duration),
+            size
+        );
+    }
+}
+
+fn parse_config_network(value: &serde_json::Value) -> Result<CustomNetwork, String> {
+    let network: CustomNetwork =
+        serde_json::from_value(value.clone()).map_err(|err| err.to_string())?;
+    network.check()?;
+    Ok(network)
+}
+
+/// Valid custom networks in config file, invalid ones are reported when the
+/// config is loaded
+fn load_networks(config_file: &Path) -> Vec<CustomNetwork> {
+    fs::read_to_string(config_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|configs| {
+            configs["networks"].as_array().map(|networks| {
+                networks
+                    .iter()
+                    .filter_map(|value| parse_config_network(value).ok())
+                    .collect()
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Parse a custom network from json/yaml (or @file)
+pub fn parse_network(input: &str) -> Result<CustomNetwork, String> {
+    let content = read_arg_value(input)?;
+    let network: CustomNetwork = serde_yaml::from_str(&content)
+        .map_err(|err| err.to_string())
+        .or_else(|_| serde_json::from_str(&content).map_err(|err| err.to_string()))
+        .map_err(|err| format!("Invalid network: {}", err))?;
+    network.check()?;
+    Ok(network)
+}
+
+/// Parse fee rates of fee presets: `slow=1000,normal=2000,fast=5000`
+pub fn parse_fee_rates(input: &str) -> Result<BTreeMap<String, u64>, String> {
+    input
+        .split(',')
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let parts = item.split('=').collect::<Vec<_>>();
+            match parts.as_slice() {
+                [preset, rate] if ["slow", "normal", "fast"].contains(preset) => rate
+                    .parse::<u64>()
+                    .map(|rate| ((*preset).to_owned(), rate))
+                    .map_err(|err| format!("Invalid fee rate {}: {}", item, err)),
+                _ => Err(format!(
+                    "Invalid fee rate: {}, format: slow|normal|fast=<shannons/KB>",
+                    item
+                )),
+            }
+        })
+        .collect()
+}
+
 fn get_version() -> Version {
     let major = env!("CARGO_PKG_VERSION_MAJOR")
         .parse::<u8>()
@@ -208,6 +544,17 @@ pub fn build_cli<'a>(version_short: &'a str, version_long: &'a str) -> App<'a, '
         .subcommand(AccountSubCommand::subcommand("account"))
         .subcommand(MockTxSubCommand::subcommand("mock-tx"))
         .subcommand(UtilSubCommand::subcommand("util"))
+        .subcommand(ExportSubCommand::subcommand("export"))
+        .subcommand(AnalyzeSubCommand::subcommand("analyze"))
+        .subcommand(ChainSubCommand::subcommand("chain"))
+        .subcommand(NodeSubCommand::subcommand("node"))
+        .subcommand(DevSubCommand::subcommand("dev"))
+        .subcommand(PipelineSubCommand::subcommand("pipeline"))
+        .subcommand(ServeSubCommand::subcommand("serve"))
+        .subcommand(DoctorSubCommand::subcommand("doctor"))
+        .subcommand(AliasSubCommand::subcommand("alias"))
+        .subcommand(TestVectorSubCommand::subcommand("test-vector"))
+        .subcommand(AssetSubCommand::subcommand("asset"))
         .subcommand(WalletSubCommand::subcommand())
         .arg(
             Arg::with_name("url")
@@ -236,6 +583,60 @@ pub fn build_cli<'a>(version_short: &'a str, version_long: &'a str) -> App<'a, '
                 .long("debug")
                 .global(true)
                 .help("Display request parameters"),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .takes_value(true)
+                .help("Use a named profile in config file (url, default account, broadcast urls)"),
+        )
+        .arg(
+            Arg::with_name("lang")
+                .long("lang")
+                .global(true)
+                .takes_value(true)
+                .possible_values(&["en", "zh-CN"])
+                .help("Language of prompts and messages (default: detect from LANG), json/yaml output is never translated"),
+        )
+        .arg(
+            Arg::with_name("offline")
+                .long("offline")
+                .global(true)
+                .help("Forbid all network access (every RPC request fails immediately)"),
+        )
+        .arg(
+            Arg::with_name("min-confirmations")
+                .long("min-confirmations")
+                .takes_value(true)
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .help("Ignore live cells with less confirmations in balances, cell queries and input selection (overrides `config --min-confirmations`)"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .global(true)
+                .help("Print duration and size of each RPC call and index database operation to stderr, with a summary at the end of command"),
+        )
+        .arg(
+            Arg::with_name("rpc-cache-size")
+                .long("rpc-cache-size")
+                .takes_value(true)
+                .validator(|input| FromStrParser::<usize>::default().validate(input))
+                .help("Max number of cached RPC responses (headers, blocks, live cells), 0 disables the cache, cell entries are dropped on new tip [default: 1024]"),
+        )
+        .arg(
+            Arg::with_name("log-file")
+                .long("log-file")
+                .takes_value(true)
+                .help("Append a record of this command (arguments with secrets redacted, RPC endpoints, duration and outcome) to the file, interactive mode is not logged"),
+        )
+        .arg(
+            Arg::with_name("log-format")
+                .long("log-format")
+                .takes_value(true)
+                .possible_values(&["json", "text"])
+                .default_value("json")
+                .help("Format of --log-file records, json: one json object per line"),
         );
 
     #[cfg(unix)]
@@ -288,6 +689,88 @@ pub fn build_interactive() -> App<'static, 'static> {
                     Arg::with_name("edit_style")
                         .long("edit_style")
                         .help("Switch edit style"),
+                )
+                .arg(
+                    Arg::with_name("default-account")
+                        .long("default-account")
+                        .takes_value(true)
+                        .help("Set default account (lock-arg, address or unique lock-arg prefix in keystore)"),
+                )
+                .arg(
+                    Arg::with_name("use-profile")
+                        .long("use-profile")
+                        .takes_value(true)
+                        .help("Switch to a named profile (\"default\" for top level settings)"),
+                )
+                .arg(
+                    Arg::with_name("save-profile")
+                        .long("save-profile")
+                        .takes_value(true)
+                        .help("Save current url, default account, broadcast urls, fee settings and db namespace as a named profile"),
+                )
+                .arg(
+                    Arg::with_name("broadcast-urls")
+                        .long("broadcast-urls")
+                        .takes_value(true)
+                        .validator(|input| {
+                            input
+                                .split(',')
+                                .filter(|url| !url.is_empty())
+                                .try_for_each(|url| UrlParser.validate(url.to_owned()))
+                        })
+                        .help("Extra node urls (separated by ',', empty to clear) used by --broadcast-all"),
+                )
+                .arg(
+                    Arg::with_name("fee-rates")
+                        .long("fee-rates")
+                        .takes_value(true)
+                        .validator(|input| parse_fee_rates(&input).map(|_| ()))
+                        .help("Fee rates (shannons/KB) of `wallet transfer --fee` presets, example: slow=1000,normal=2000,fast=5000 (empty to clear)"),
+                )
+                .arg(
+                    Arg::with_name("max-tx-fee")
+                        .long("max-tx-fee")
+                        .takes_value(true)
+                        .validator(|input| CapacityParser.validate(input))
+                        .help("Max fee (unit: CKB) of a transaction sent by wallet, sending is refused above it"),
+                )
+                .arg(
+                    Arg::with_name("db-namespace")
+                        .long("db-namespace")
+                        .takes_value(true)
+                        .help("Use <ckb-cli-dir>/index/<namespace> for index database, pending transactions and other local wallet data (empty to use the index directory itself)"),
+                )
+                .arg(
+                    Arg::with_name("min-confirmations")
+                        .long("min-confirmations")
+                        .takes_value(true)
+                        .validator(|input| FromStrParser::<u64>::default().validate(input))
+                        .help("Ignore live cells with less confirmations (counted to the last indexed block) in balances, cell queries and input selection, 0 to disable"),
+                )
+                .arg(
+                    Arg::with_name("add-network")
+                        .long("add-network")
+                        .takes_value(true)
+                        .help("Register a custom network (json/yaml, @file to read from file): {name: <chain name of the node>, genesis_hash, address_prefix, secp_dep: {tx_hash, index} (optional), dao_dep: {tx_hash, index} (optional)}, replaces the one with the same name"),
+                )
+                .arg(
+                    Arg::with_name("remove-network")
+                        .long("remove-network")
+                        .takes_value(true)
+                        .help("Remove a custom network by name"),
+                )
+                .arg(
+                    Arg::with_name("price-feed-url")
+                        .long("price-feed-url")
+                        .takes_value(true)
+                        .validator(|input| {
+                            if input.is_empty() {
+                                Ok(())
+                            } else {
+                                UrlParser.validate(input)
+                            }
+                        })
+                        .help("Url of the fiat price feed (json: {\"currency\": \"USD\", \"price\": <price of 1 CKB>}), previews show fiat values of amounts and fees (disabled in offline mode, empty to clear)"),
                 ),
         )
         .subcommand(SubCommand::with_name("info").about("Display global variables"))
@@ -300,5 +783,17 @@ pub fn build_interactive() -> App<'static, 'static> {
         .subcommand(AccountSubCommand::subcommand("account"))
         .subcommand(MockTxSubCommand::subcommand("mock-tx"))
         .subcommand(UtilSubCommand::subcommand("util"))
+        .subcommand(ExportSubCommand::subcommand("export"))
+        .subcommand(AnalyzeSubCommand::subcommand("analyze"))
+        .subcommand(ChainSubCommand::subcommand("chain"))
+        .subcommand(NodeSubCommand::subcommand("node"))
+        .subcommand(DevSubCommand::subcommand("dev"))
+        .subcommand(PipelineSubCommand::subcommand("pipeline"))
+        .subcommand(ServeSubCommand::subcommand("serve"))
+        .subcommand(ApprovalsSubCommand::subcommand("approvals"))
+        .subcommand(DoctorSubCommand::subcommand("doctor"))
+        .subcommand(AliasSubCommand::subcommand("alias"))
+        .subcommand(TestVectorSubCommand::subcommand("test-vector"))
+        .subcommand(AssetSubCommand::subcommand("asset"))
         .subcommand(WalletSubCommand::subcommand())
 }
@@ -1,30 +1,70 @@
 use std::fs;
-use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use chrono::{TimeZone, Utc};
 use ckb_jsonrpc_types::BlockNumber;
 use ckb_sdk::{
-    wallet::{DerivationPath, Key, KeyStore, MasterPrivKey},
-    Address, GenesisInfo, HttpRpcClient, NetworkType,
+    eth_address,
+    wallet::{
+        zeroize_slice, Bip32Error, Crypto, DerivationPath, Key, KeyStore, MasterPrivKey, ScryptType,
+    },
+    Address, FullAddress, GenesisInfo, HttpRpcClient, NetworkType, OmniAuth, SECP256K1,
 };
 use ckb_types::{core::BlockView, prelude::*, H160, H256};
-use clap::{App, Arg, ArgMatches, SubCommand};
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+use rand::Rng;
 
 use super::CliSubCommand;
 use crate::utils::{
+    address_book::AddressBook,
+    arg,
     arg_parser::{
-        ArgParser, DurationParser, ExtendedPrivkeyPathParser, FixedHashParser, FromStrParser,
-        PrivkeyPathParser, PrivkeyWrapper,
+        AddressParser, ArgParser, DurationParser, ExtendedPrivkeyPathParser, FilePathParser,
+        FixedHashParser, FromStrParser, PrivkeyPathParser, PrivkeyWrapper,
+    },
+    i18n::tr,
+    key_usage::{now_secs, KeyUsages},
+    lock_template::{
+        all_lock_templates, builtin_lock_templates, load_lock_templates, save_lock_templates,
+        LockTemplate,
     },
-    other::read_password,
-    printer::{OutputFormat, Printable},
+    other::{get_lock_hash_accounts, read_password, write_private_file},
+    payment_request::PaymentRequest,
+    printer::{Printable, Text},
+    qr::render_qr,
+    sign_mode::{AccountSignMode, SignMode, SignModes},
 };
 
+const KEYS_ARCHIVE_VERSION: u64 = 1;
+// Neuron derives addresses from BIP-44 account 0 and shows the first
+// receiving address
+const NEURON_ACCOUNT_PATH: &str = "m/44'/309'/0'";
+const NEURON_FIRST_ADDRESS_PATH: &str = "m/44'/309'/0'/0/0";
+const BECH32_CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+// Data part of short addresses (format type 0x01, code hash index 0x00)
+// always starts with these characters
+const SHORT_ADDRESS_DATA_PREFIX: &str = "qyq";
+const VANITY_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+// Plain private keys, zeroized when dropped (also on early returns)
+struct SecretBytes(Vec<u8>);
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        zeroize_slice(&mut self.0);
+    }
+}
+
 pub struct AccountSubCommand<'a> {
     rpc_client: &'a mut HttpRpcClient,
     key_store: &'a mut KeyStore,
     genesis_info: Option<GenesisInfo>,
+    lock_templates_file: PathBuf,
+    sign_modes_file: PathBuf,
 }
 
 impl<'a> AccountSubCommand<'a> {
@@ -32,11 +72,15 @@ impl<'a> AccountSubCommand<'a> {
         rpc_client: &'a mut HttpRpcClient,
         key_store: &'a mut KeyStore,
         genesis_info: Option<GenesisInfo>,
+        lock_templates_file: PathBuf,
+        sign_modes_file: PathBuf,
     ) -> AccountSubCommand<'a> {
         AccountSubCommand {
             rpc_client,
             key_store,
             genesis_info,
+            lock_templates_file,
+            sign_modes_file,
         }
     }
 
@@ -72,7 +116,15 @@ impl<'a> AccountSubCommand<'a> {
         SubCommand::with_name(name)
             .about("Manage accounts")
             .subcommands(vec![
-                SubCommand::with_name("list").about("List all accounts"),
+                SubCommand::with_name("list")
+                    .about("List all accounts with signing usage (count, last signed time and transaction)")
+                    .arg(
+                        Arg::with_name("stale-days")
+                            .long("stale-days")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Only list accounts not used to sign in the last N days (or never used), candidates to retire"),
+                    ),
                 SubCommand::with_name("new").about("Create a new account and print related information."),
                 SubCommand::with_name("import")
                     .about("Import an unencrypted private key from <privkey-path> and create a new account.")
@@ -88,6 +140,16 @@ impl<'a> AccountSubCommand<'a> {
                          .required_unless("privkey-path")
                          .validator(|input| ExtendedPrivkeyPathParser.validate(input))
                     ),
+                SubCommand::with_name("import-keystore")
+                    .about("Import a keystore v3 JSON file exported by Neuron or other wallets and create a new account")
+                    .arg(
+                        Arg::with_name("path")
+                            .long("path")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Keystore file path"),
+                    ),
                 SubCommand::with_name("unlock")
                     .about("Unlock an account")
                     .arg(arg_lock_arg.clone())
@@ -110,6 +172,228 @@ impl<'a> AccountSubCommand<'a> {
                             .clone()
                             .required(true)
                             .help("Output extended private key path (PrivKey + ChainCode)")
+                    )
+                    .arg(arg::force()),
+                SubCommand::with_name("export-keystore")
+                    .about("Export an account as keystore v3 JSON file for Neuron or other wallets, with the BIP-44 account extended public key for watch-only wallets")
+                    .arg(arg_lock_arg.clone())
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output keystore file path"),
+                    )
+                    .arg(
+                        Arg::with_name("xpubkey-output")
+                            .long("xpubkey-output")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Also write the account extended public key to this file (Neuron watch-only wallet import format)"),
+                    )
+                    .arg(arg::force()),
+                SubCommand::with_name("export-all")
+                    .about("Export all accounts into one password encrypted archive (scrypt + aes-128-ctr, with MAC)")
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output archive file path"),
+                    )
+                    .arg(arg::force()),
+                SubCommand::with_name("import-all")
+                    .about("Import all accounts from an archive created by `account export-all` (existing accounts are skipped)")
+                    .arg(
+                        Arg::with_name("input")
+                            .long("input")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Archive file path"),
+                    ),
+                SubCommand::with_name("verify-backup")
+                    .about("Check a backup regenerates the lock-arg of an account, nothing is imported")
+                    .arg(arg_lock_arg.clone())
+                    .arg(
+                        Arg::with_name("keystore-path")
+                            .long("keystore-path")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Keystore v3 JSON file (see: account export-keystore)"),
+                    )
+                    .arg(
+                        arg_privkey_path
+                            .clone()
+                            .validator(|input| PrivkeyPathParser.validate(input))
+                            .help("Unencrypted private key file"),
+                    )
+                    .arg(
+                        arg_extended_privkey_path
+                            .clone()
+                            .validator(|input| ExtendedPrivkeyPathParser.validate(input)),
+                    )
+                    .arg(
+                        Arg::with_name("archive-path")
+                            .long("archive-path")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Keys archive file (see: account export-all)"),
+                    )
+                    .group(
+                        ArgGroup::with_name("backup")
+                            .args(&["keystore-path", "privkey-path", "extended-privkey-path", "archive-path"])
+                            .required(true),
+                    ),
+                SubCommand::with_name("lock-hash")
+                    .about("Get lock script hash of an account (by lock-arg)")
+                    .arg(arg_lock_arg.clone()),
+                SubCommand::with_name("lock-arg")
+                    .about("Resolve the account (lock-arg) of a lock script hash from keystore and address book")
+                    .arg(
+                        Arg::with_name("lock-hash")
+                            .long("lock-hash")
+                            .takes_value(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .required(true)
+                            .help("Lock script hash")
+                    ),
+                SubCommand::with_name("add-label")
+                    .about("Label an address in the address book, labels are accepted by --from-account and shown next to lock hashes of the address in outputs")
+                    .arg(
+                        Arg::with_name("label")
+                            .long("label")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Label (example: alice), replaces the old label of the address"),
+                    )
+                    .arg(arg::lock_arg())
+                    .arg(arg::address().help("Address to label"))
+                    .group(
+                        ArgGroup::with_name("target")
+                            .args(&["lock-arg", "address"])
+                            .required(true),
+                    ),
+                SubCommand::with_name("remove-label")
+                    .about("Remove a label from the address book")
+                    .arg(
+                        Arg::with_name("label")
+                            .long("label")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Label"),
+                    ),
+                SubCommand::with_name("list-labels").about("List labels in the address book"),
+                SubCommand::with_name("add-lock-template")
+                    .about("Register a custom lock script template, accounts derive lock scripts and addresses from it")
+                    .arg(
+                        Arg::with_name("name")
+                            .long("name")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Template name"),
+                    )
+                    .arg(
+                        Arg::with_name("code-hash")
+                            .long("code-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Lock script code hash"),
+                    )
+                    .arg(
+                        Arg::with_name("hash-type")
+                            .long("hash-type")
+                            .takes_value(true)
+                            .possible_values(&["data", "type"])
+                            .default_value("type")
+                            .help("Lock script hash type"),
+                    )
+                    .arg(
+                        Arg::with_name("args")
+                            .long("args")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Hex template of lock script args, {blake160} is replaced by the lock-arg of account (example: 0x00{blake160}00)"),
+                    ),
+                SubCommand::with_name("list-lock-templates").about("List custom lock script templates"),
+                SubCommand::with_name("remove-lock-template")
+                    .about("Remove a custom lock script template")
+                    .arg(
+                        Arg::with_name("name")
+                            .long("name")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Template name"),
+                    ),
+                SubCommand::with_name("sign-mode")
+                    .about("Set how the account signs: default, or eth-personal (ethereum personal_sign style, keccak256 with message prefix) for locks verifying ethereum signatures such as omnilock ethereum auth")
+                    .arg(arg_lock_arg.clone())
+                    .arg(
+                        Arg::with_name("mode")
+                            .long("mode")
+                            .takes_value(true)
+                            .possible_values(&["default", "eth-personal"])
+                            .required(true)
+                            .help("Sign mode"),
+                    ),
+                SubCommand::with_name("vanity")
+                    .about("Generate keys until the short address starts with <prefix>, then import the key into keystore")
+                    .arg(
+                        Arg::with_name("prefix")
+                            .long("prefix")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| parse_vanity_prefix(&input).map(|_| ()))
+                            .help("Address prefix, starts with ckb1qyq (mainnet) or ckt1qyq (testnet), example: ckt1qyqabc"),
+                    )
+                    .arg(
+                        Arg::with_name("threads")
+                            .long("threads")
+                            .takes_value(true)
+                            .default_value("4")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Number of threads generating keys"),
+                    ),
+                SubCommand::with_name("qr")
+                    .about("Show an address as QR code, or a payment request (ckb:<address>?amount=..&asset=..&memo=..) when --amount, --asset or --memo is given")
+                    .arg(
+                        Arg::with_name("address")
+                            .long("address")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| AddressParser.validate(input))
+                            .help("Receiving address"),
+                    )
+                    .arg(
+                        Arg::with_name("amount")
+                            .long("amount")
+                            .takes_value(true)
+                            .validator(|input| {
+                                if !input.is_empty()
+                                    && input.split('.').count() <= 2
+                                    && input.chars().all(|c| c.is_ascii_digit() || c == '.')
+                                {
+                                    Ok(())
+                                } else {
+                                    Err(format!("Invalid amount: {}", input))
+                                }
+                            })
+                            .help("Requested amount in unit of the asset (CKB by default), format: 123.45"),
+                    )
+                    .arg(
+                        Arg::with_name("asset")
+                            .long("asset")
+                            .takes_value(true)
+                            .help("Requested asset: ckb, a registered symbol or sUDT type script hash (see: asset add)"),
+                    )
+                    .arg(
+                        Arg::with_name("memo")
+                            .long("memo")
+                            .takes_value(true)
+                            .help("Memo of the payment request"),
                     ),
                 SubCommand::with_name("extended-address")
                     .about("Extended address (see: BIP-44)")
@@ -129,12 +413,15 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
     fn process(
         &mut self,
         matches: &ArgMatches,
-        format: OutputFormat,
-        color: bool,
         _debug: bool,
-    ) -> Result<String, String> {
+    ) -> Result<Box<dyn Printable>, String> {
         match matches.subcommand() {
-            ("list", _) => {
+            ("list", Some(m)) => {
+                let stale_days: Option<u64> =
+                    FromStrParser::<u64>::default().from_matches_opt(m, "stale-days", false)?;
+                let usages = KeyUsages::load(self.key_store.keys_dir())?;
+                let address_book = AddressBook::load(self.key_store.keys_dir())?;
+                let now = now_secs();
                 let mut accounts = self
                     .key_store
                     .get_accounts()
@@ -146,6 +433,11 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                 let resp = accounts
                     .into_iter()
                     .enumerate()
+                    .filter(|(_, (lock_arg, _))| {
+                        stale_days
+                            .map(|days| usages.is_stale(lock_arg, days, now))
+                            .unwrap_or(true)
+                    })
                     .map(|(idx, (lock_arg, filepath))| {
                         let address = Address::from_lock_arg(lock_arg.as_bytes()).unwrap();
                         let timeout = self.key_store.get_lock_timeout(&lock_arg);
@@ -161,6 +453,7 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                         serde_json::json!({
                             "#": idx,
                             "lock_arg": format!("{:x}", lock_arg),
+                            "label": address_book.label(&lock_arg),
                             "lock_hash": lock_hash_opt,
                             "address": {
                                 "mainnet": address.to_string(NetworkType::MainNet),
@@ -168,10 +461,17 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                             },
                             "path": filepath.to_string_lossy(),
                             "status": status,
+                            "usage": usages.get(&lock_arg).map(|usage| serde_json::json!({
+                                "sign_count": usage.sign_count,
+                                "last_signed_at": Utc
+                                    .timestamp(usage.last_signed_at as i64, 0)
+                                    .to_rfc3339(),
+                                "last_tx_hash": usage.last_tx_hash,
+                            })),
                         })
                     })
                     .collect::<Vec<_>>();
-                Ok(serde_json::json!(resp).render(format, color))
+                Ok(Box::new(serde_json::json!(resp)))
             }
             ("new", _) => {
                 println!("Your new account is locked with a password. Please give a password. Do not forget this password.");
@@ -197,7 +497,7 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                         "testnet": address.to_string(NetworkType::TestNet),
                     },
                 });
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("import", Some(m)) => {
                 let secp_key: Option<PrivkeyWrapper> =
@@ -223,7 +523,54 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                         "testnet": address.to_string(NetworkType::TestNet),
                     },
                 });
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
+            }
+            ("import-keystore", Some(m)) => {
+                let path: PathBuf = FilePathParser::new(true).from_matches(m, "path")?;
+                let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+                let data: serde_json::Value = serde_json::from_str(&content)
+                    .map_err(|err| format!("Invalid keystore file: {}", err))?;
+                let password = read_password(false, Some(tr("Keystore password")))?;
+                let (key, with_chain_code) = Key::from_keystore_v3(&data, password.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                let lock_arg = key.address().clone();
+                if self.key_store.has_account(&lock_arg) {
+                    return Err(format!("Account already exists: {:x}", lock_arg));
+                }
+                let new_password = read_password(true, Some(tr("Password of imported account")))?;
+                self.key_store
+                    .import_key(&key, new_password.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                let address = Address::from_lock_arg(lock_arg.as_bytes()).unwrap();
+                let mut resp = serde_json::json!({
+                    "lock_arg": format!("{:x}", lock_arg),
+                    "address": {
+                        "mainnet": address.to_string(NetworkType::MainNet),
+                        "testnet": address.to_string(NetworkType::TestNet),
+                    },
+                });
+                if with_chain_code {
+                    // Neuron receives to BIP-44 derived addresses, not the
+                    // address of the master key
+                    let path: DerivationPath = NEURON_FIRST_ADDRESS_PATH
+                        .parse()
+                        .map_err(|err: Bip32Error| err.to_string())?;
+                    let extended_pubkey = key.extended_pubkey(Some(&path))?;
+                    let first_address = Address::from_pubkey(&extended_pubkey.public_key)?;
+                    resp["first_receiving_address"] = serde_json::json!({
+                        "path": NEURON_FIRST_ADDRESS_PATH,
+                        "mainnet": first_address.to_string(NetworkType::MainNet),
+                        "testnet": first_address.to_string(NetworkType::TestNet),
+                    });
+                    resp["hint"] = serde_json::json!(
+                        "Balances of the source wallet are on derived addresses, run `wallet scan-accounts` to discover them"
+                    );
+                } else {
+                    resp["hint"] = serde_json::json!(
+                        "Keystore has no chain code, a new one is generated, derived addresses differ from the source wallet"
+                    );
+                }
+                Ok(Box::new(resp))
             }
             ("unlock", Some(m)) => {
                 let lock_arg: H160 =
@@ -238,13 +585,13 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                 let resp = serde_json::json!({
                     "status": lock_after,
                 });
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("update", Some(m)) => {
                 let lock_arg: H160 =
                     FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
-                let old_password = read_password(false, Some("Old password"))?;
-                let new_passsword = read_password(true, Some("New password"))?;
+                let old_password = read_password(false, Some(tr("Old password")))?;
+                let new_passsword = read_password(true, Some(tr("New password")))?;
                 self.key_store
                     .update(&lock_arg, old_password.as_bytes(), new_passsword.as_bytes())
                     .map_err(|err| err.to_string())?;
@@ -256,8 +603,12 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                 let key_path = m.value_of("extended-privkey-path").unwrap();
                 let password = read_password(false, None)?;
 
-                if Path::new(key_path).exists() {
-                    return Err(format!("File exists: {}", key_path));
+                let force = m.is_present("force");
+                if Path::new(key_path).exists() && !force {
+                    return Err(format!(
+                        "File exists: {} (use --force to overwrite)",
+                        key_path
+                    ));
                 }
                 let master_privkey = self
                     .key_store
@@ -266,15 +617,517 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                 let bytes = master_privkey.to_bytes();
                 let privkey = H256::from_slice(&bytes[0..32]).unwrap();
                 let chain_code = H256::from_slice(&bytes[32..64]).unwrap();
-                let mut file = fs::File::create(key_path).map_err(|err| err.to_string())?;
-                file.write(format!("{:x}\n", privkey).as_bytes())
-                    .map_err(|err| err.to_string())?;
-                file.write(format!("{:x}", chain_code).as_bytes())
-                    .map_err(|err| err.to_string())?;
-                Ok(format!(
+                let content = format!("{:x}\n{:x}", privkey, chain_code);
+                write_private_file(Path::new(key_path), content.as_bytes(), force)?;
+                Ok(Box::new(Text::from(format!(
                     "Success exported account as extended privkey to: \"{}\", please use this file carefully",
                     key_path
-                ))
+                ))))
+            }
+            ("export-keystore", Some(m)) => {
+                let lock_arg: H160 =
+                    FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
+                let output: PathBuf = FilePathParser::new(false).from_matches(m, "output")?;
+                let xpubkey_output: Option<PathBuf> =
+                    FilePathParser::new(false).from_matches_opt(m, "xpubkey-output", false)?;
+                let force = m.is_present("force");
+                for path in Some(&output).into_iter().chain(xpubkey_output.as_ref()) {
+                    if path.exists() && !force {
+                        return Err(format!(
+                            "File exists: {:?} (use --force to overwrite)",
+                            path
+                        ));
+                    }
+                }
+                let password = read_password(false, None)?;
+                let master_privkey = self
+                    .key_store
+                    .export_key(&lock_arg, password.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                let path: DerivationPath = NEURON_ACCOUNT_PATH
+                    .parse()
+                    .map_err(|err: Bip32Error| err.to_string())?;
+                let account_pubkey = master_privkey.extended_pubkey(Some(&path))?;
+                let mut xpubkey = account_pubkey.public_key.serialize().to_vec();
+                xpubkey.extend_from_slice(&account_pubkey.chain_code.0);
+                let xpubkey = faster_hex::hex_string(&xpubkey).unwrap();
+
+                let new_password = read_password(true, Some(tr("Keystore password")))?;
+                let keystore = Key::new(master_privkey)
+                    .to_json(new_password.as_bytes(), ScryptType::default());
+                let content =
+                    serde_json::to_string_pretty(&keystore).map_err(|err| err.to_string())?;
+                write_private_file(&output, content.as_bytes(), force)?;
+                if let Some(xpubkey_output) = xpubkey_output.as_ref() {
+                    let content = serde_json::to_string_pretty(&serde_json::json!({
+                        "xpubkey": xpubkey,
+                    }))
+                    .map_err(|err| err.to_string())?;
+                    write_private_file(xpubkey_output, content.as_bytes(), force)?;
+                }
+                let resp = serde_json::json!({
+                    "lock_arg": format!("{:x}", lock_arg),
+                    "output": output.to_string_lossy(),
+                    "derivation_path": NEURON_ACCOUNT_PATH,
+                    "xpubkey": xpubkey,
+                });
+                Ok(Box::new(resp))
+            }
+            ("export-all", Some(m)) => {
+                let output: PathBuf = FilePathParser::new(false).from_matches(m, "output")?;
+                let force = m.is_present("force");
+                if output.exists() && !force {
+                    return Err(format!(
+                        "File exists: {:?} (use --force to overwrite)",
+                        output
+                    ));
+                }
+                let mut lock_args = self
+                    .key_store
+                    .get_accounts()
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if lock_args.is_empty() {
+                    return Err("No account found".to_owned());
+                }
+                lock_args.sort();
+                let mut plaintext = SecretBytes(Vec::with_capacity(lock_args.len() * 64));
+                for lock_arg in &lock_args {
+                    let prompt = format!("{} {:x}", tr("Password of account"), lock_arg);
+                    let password = read_password(false, Some(prompt.as_str()))?;
+                    let master_privkey = self
+                        .key_store
+                        .export_key(lock_arg, password.as_bytes())
+                        .map_err(|err| {
+                        format!("Export account {:x} failed: {}", lock_arg, err)
+                    })?;
+                    let mut bytes = master_privkey.to_bytes();
+                    plaintext.0.extend_from_slice(&bytes[..]);
+                    zeroize_slice(&mut bytes);
+                }
+                let archive_password = read_password(true, Some(tr("Archive password")))?;
+                let crypto = Crypto::encrypt_key_scrypt(
+                    &plaintext.0,
+                    archive_password.as_bytes(),
+                    ScryptType::default(),
+                );
+                drop(plaintext);
+                let archive = serde_json::json!({
+                    "version": KEYS_ARCHIVE_VERSION,
+                    "accounts": lock_args
+                        .iter()
+                        .map(|lock_arg| format!("{:x}", lock_arg))
+                        .collect::<Vec<_>>(),
+                    "crypto": crypto.to_json(),
+                });
+                let content =
+                    serde_json::to_string_pretty(&archive).map_err(|err| err.to_string())?;
+                write_private_file(&output, content.as_bytes(), force)?;
+                let resp = serde_json::json!({
+                    "accounts": lock_args.len(),
+                    "output": output.to_string_lossy(),
+                });
+                Ok(Box::new(resp))
+            }
+            ("import-all", Some(m)) => {
+                let input: PathBuf = FilePathParser::new(true).from_matches(m, "input")?;
+                let keys = read_keys_archive(&input)?;
+                let password = read_password(true, Some(tr("Password of imported accounts")))?;
+                let mut imported = Vec::new();
+                let mut skipped = Vec::new();
+                for key in keys {
+                    let lock_arg = key.address().clone();
+                    if self.key_store.has_account(&lock_arg) {
+                        skipped.push(format!("{:x}", lock_arg));
+                        continue;
+                    }
+                    self.key_store
+                        .import_key(&key, password.as_bytes())
+                        .map_err(|err| err.to_string())?;
+                    imported.push(format!("{:x}", lock_arg));
+                }
+                let resp = serde_json::json!({
+                    "imported": imported,
+                    "skipped": skipped,
+                });
+                Ok(Box::new(resp))
+            }
+            ("verify-backup", Some(m)) => {
+                let lock_arg: H160 =
+                    FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
+                // Lock args in the backup, with chain code or not
+                let mut backup_keys: Vec<(H160, bool)> = Vec::new();
+                if let Some(path) = m.value_of("keystore-path") {
+                    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+                    let data: serde_json::Value = serde_json::from_str(&content)
+                        .map_err(|err| format!("Invalid keystore file: {}", err))?;
+                    let password = read_password(false, Some(tr("Keystore password")))?;
+                    let (key, with_chain_code) = Key::from_keystore_v3(&data, password.as_bytes())
+                        .map_err(|err| err.to_string())?;
+                    backup_keys.push((key.address().clone(), with_chain_code));
+                } else if m.is_present("privkey-path") {
+                    let privkey: PrivkeyWrapper =
+                        PrivkeyPathParser.from_matches(m, "privkey-path")?;
+                    let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+                    let address = Address::from_pubkey(&pubkey)?;
+                    backup_keys.push((address.hash().clone(), false));
+                } else if m.is_present("extended-privkey-path") {
+                    let master_privkey: MasterPrivKey =
+                        ExtendedPrivkeyPathParser.from_matches(m, "extended-privkey-path")?;
+                    backup_keys.push((Key::new(master_privkey).address().clone(), true));
+                } else {
+                    let path: PathBuf =
+                        FilePathParser::new(true).from_matches(m, "archive-path")?;
+                    for key in read_keys_archive(&path)? {
+                        backup_keys.push((key.address().clone(), true));
+                    }
+                }
+                let with_chain_code = match backup_keys
+                    .iter()
+                    .find(|(backup_lock_arg, _)| backup_lock_arg == &lock_arg)
+                {
+                    Some((_, with_chain_code)) => *with_chain_code,
+                    None => {
+                        return Err(format!(
+                            "Backup does not match account {:x}, lock-args in backup: {}",
+                            lock_arg,
+                            backup_keys
+                                .iter()
+                                .map(|(backup_lock_arg, _)| format!("{:x}", backup_lock_arg))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ));
+                    }
+                };
+                let address = Address::from_lock_arg(lock_arg.as_bytes()).unwrap();
+                let mut resp = serde_json::json!({
+                    "lock_arg": format!("{:x}", lock_arg),
+                    "address": {
+                        "mainnet": address.to_string(NetworkType::MainNet),
+                        "testnet": address.to_string(NetworkType::TestNet),
+                    },
+                    "matched": true,
+                    "in_keystore": self.key_store.has_account(&lock_arg),
+                    "with_chain_code": with_chain_code,
+                });
+                if !with_chain_code {
+                    resp["warning"] = serde_json::json!(
+                        "Backup has no chain code, balances on derived addresses (BIP-44) can not be recovered from it"
+                    );
+                }
+                Ok(Box::new(resp))
+            }
+            ("lock-hash", Some(m)) => {
+                let lock_arg: H160 =
+                    FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
+                let genesis_info = self.genesis_info()?;
+                let address = Address::from_lock_arg(lock_arg.as_bytes())?;
+                let lock_hash: H256 = address
+                    .lock_script(genesis_info.secp_type_hash().clone())
+                    .calc_script_hash()
+                    .unpack();
+                let mut custom_locks = Vec::new();
+                for template in all_lock_templates(&self.lock_templates_file)? {
+                    let script = template.script(&lock_arg)?;
+                    let lock_hash: H256 = script.calc_script_hash().unpack();
+                    let full_address = FullAddress::from_script(&script)?;
+                    custom_locks.push(serde_json::json!({
+                        "template": template.name,
+                        "lock_hash": lock_hash,
+                        "address": {
+                            "mainnet": full_address.to_string(NetworkType::MainNet),
+                            "testnet": full_address.to_string(NetworkType::TestNet),
+                        },
+                    }));
+                }
+                let sign_modes = SignModes::load(&self.sign_modes_file)?;
+                let eth_address_opt = sign_modes
+                    .get(&lock_arg)
+                    .and_then(|mode| mode.eth_address.clone());
+                if let Some(eth_address) = eth_address_opt.clone() {
+                    let auth = OmniAuth::Ethereum(eth_address);
+                    for network in &[NetworkType::MainNet, NetworkType::TestNet] {
+                        let script = auth.script(*network).expect("omnilock deployed");
+                        let lock_hash: H256 = script.calc_script_hash().unpack();
+                        custom_locks.push(serde_json::json!({
+                            "template": format!("omnilock-ethereum-{}", network),
+                            "lock_hash": lock_hash,
+                            "address": FullAddress::from_script(&script)?.to_string(*network),
+                        }));
+                    }
+                }
+                let resp = serde_json::json!({
+                    "lock_arg": format!("{:x}", lock_arg),
+                    "lock_hash": lock_hash,
+                    "address": {
+                        "mainnet": address.to_string(NetworkType::MainNet),
+                        "testnet": address.to_string(NetworkType::TestNet),
+                    },
+                    "eth_address": eth_address_opt,
+                    "custom_locks": custom_locks,
+                    "in_keystore": self.key_store.has_account(&lock_arg),
+                });
+                Ok(Box::new(resp))
+            }
+            ("lock-arg", Some(m)) => {
+                let lock_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "lock-hash")?;
+                let genesis_info = self.genesis_info()?;
+                let lock_templates = all_lock_templates(&self.lock_templates_file)?;
+                let address_book = AddressBook::load(self.key_store.keys_dir())?;
+                let mut accounts = get_lock_hash_accounts(
+                    self.key_store,
+                    genesis_info.secp_type_hash(),
+                    &lock_templates,
+                );
+                for entry in address_book.entries() {
+                    let entry_lock_hash: H256 = Address::new_default(entry.lock_arg.clone())
+                        .lock_script(genesis_info.secp_type_hash().clone())
+                        .calc_script_hash()
+                        .unpack();
+                    accounts
+                        .entry(entry_lock_hash)
+                        .or_insert_with(|| entry.lock_arg.clone());
+                }
+                let lock_arg = accounts
+                    .get(&lock_hash)
+                    .ok_or_else(|| format!("No account found for lock hash: {:#x}", lock_hash))?;
+                let address = Address::from_lock_arg(lock_arg.as_bytes())?;
+                let resp = serde_json::json!({
+                    "lock_arg": format!("{:x}", lock_arg),
+                    "label": address_book.label(lock_arg),
+                    "in_keystore": self.key_store.has_account(lock_arg),
+                    "lock_hash": lock_hash,
+                    "address": {
+                        "mainnet": address.to_string(NetworkType::MainNet),
+                        "testnet": address.to_string(NetworkType::TestNet),
+                    },
+                });
+                Ok(Box::new(resp))
+            }
+            ("add-label", Some(m)) => {
+                let label = m.value_of("label").unwrap();
+                let lock_arg = match FixedHashParser::<H160>::default()
+                    .from_matches_opt(m, "lock-arg", false)?
+                {
+                    Some(lock_arg) => lock_arg,
+                    None => AddressParser.from_matches(m, "address")?.hash().clone(),
+                };
+                let mut address_book = AddressBook::load(self.key_store.keys_dir())?;
+                address_book.add(label, lock_arg.clone())?;
+                address_book.save()?;
+                let resp = serde_json::json!({
+                    "label": label,
+                    "lock_arg": format!("{:x}", lock_arg),
+                    "in_keystore": self.key_store.has_account(&lock_arg),
+                });
+                Ok(Box::new(resp))
+            }
+            ("remove-label", Some(m)) => {
+                let mut address_book = AddressBook::load(self.key_store.keys_dir())?;
+                let entry = address_book.remove(m.value_of("label").unwrap())?;
+                address_book.save()?;
+                Ok(Box::new(serde_json::to_value(entry).unwrap()))
+            }
+            ("list-labels", _) => {
+                let address_book = AddressBook::load(self.key_store.keys_dir())?;
+                let resp = address_book
+                    .entries()
+                    .iter()
+                    .map(|entry| {
+                        let address = Address::new_default(entry.lock_arg.clone());
+                        serde_json::json!({
+                            "label": entry.label,
+                            "lock_arg": format!("{:x}", entry.lock_arg),
+                            "address": {
+                                "mainnet": address.to_string(NetworkType::MainNet),
+                                "testnet": address.to_string(NetworkType::TestNet),
+                            },
+                            "in_keystore": self.key_store.has_account(&entry.lock_arg),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Box::new(serde_json::json!(resp)))
+            }
+            ("add-lock-template", Some(m)) => {
+                let code_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "code-hash")?;
+                let name = m.value_of("name").unwrap();
+                if builtin_lock_templates()
+                    .iter()
+                    .any(|template| template.name == name)
+                {
+                    return Err(format!("Can not override builtin lock template: {}", name));
+                }
+                let template = LockTemplate {
+                    name: name.to_owned(),
+                    code_hash,
+                    hash_type: m.value_of("hash-type").unwrap().to_owned(),
+                    args: m.value_of("args").unwrap().to_owned(),
+                };
+                template.validate()?;
+                let mut templates = load_lock_templates(&self.lock_templates_file)?;
+                templates.retain(|item| item.name != template.name);
+                templates.push(template);
+                save_lock_templates(&self.lock_templates_file, &templates)?;
+                Ok(Box::new(Text::from("DONE")))
+            }
+            ("list-lock-templates", _) => {
+                let builtin = builtin_lock_templates()
+                    .into_iter()
+                    .map(|template| (template, true));
+                let registered = load_lock_templates(&self.lock_templates_file)?
+                    .into_iter()
+                    .map(|template| (template, false));
+                let resp = builtin
+                    .chain(registered)
+                    .map(|(template, builtin)| {
+                        let mut value = serde_json::to_value(template).unwrap();
+                        value["builtin"] = serde_json::json!(builtin);
+                        value
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Box::new(serde_json::json!(resp)))
+            }
+            ("remove-lock-template", Some(m)) => {
+                let name = m.value_of("name").unwrap();
+                let mut templates = load_lock_templates(&self.lock_templates_file)?;
+                let len = templates.len();
+                templates.retain(|template| template.name != name);
+                if templates.len() == len {
+                    return Err(format!("Lock template not found: {}", name));
+                }
+                save_lock_templates(&self.lock_templates_file, &templates)?;
+                Ok(Box::new(Text::from("DONE")))
+            }
+            ("sign-mode", Some(m)) => {
+                let lock_arg: H160 =
+                    FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
+                let mode = SignMode::from_name(m.value_of("mode").unwrap())?;
+                let eth_address_opt = if mode == SignMode::EthPersonal {
+                    let password = read_password(false, None)?;
+                    let master_privkey = self
+                        .key_store
+                        .export_key(&lock_arg, password.as_bytes())
+                        .map_err(|err| err.to_string())?;
+                    let pubkey = master_privkey.extended_pubkey(None)?.public_key;
+                    Some(eth_address(&pubkey))
+                } else {
+                    None
+                };
+                let mut sign_modes = SignModes::load(&self.sign_modes_file)?;
+                sign_modes.set(
+                    lock_arg.clone(),
+                    AccountSignMode {
+                        mode,
+                        eth_address: eth_address_opt.clone(),
+                    },
+                );
+                sign_modes.save()?;
+                let resp = serde_json::json!({
+                    "lock_arg": format!("{:x}", lock_arg),
+                    "mode": mode,
+                    "eth_address": eth_address_opt,
+                });
+                Ok(Box::new(resp))
+            }
+            ("vanity", Some(m)) => {
+                let prefix = m.value_of("prefix").unwrap().to_owned();
+                let (network, expected_tries) = parse_vanity_prefix(&prefix)?;
+                let threads: usize =
+                    FromStrParser::<usize>::default().from_matches(m, "threads")?;
+                if threads == 0 {
+                    return Err("<threads> can not be zero".to_owned());
+                }
+                // Ask password first, the search may take a long time
+                let password = read_password(true, None)?;
+
+                let tried = Arc::new(AtomicU64::new(0));
+                let found: Arc<Mutex<Option<secp256k1::SecretKey>>> = Arc::new(Mutex::new(None));
+                let stop = Arc::new(AtomicBool::new(false));
+                let handles = (0..threads)
+                    .map(|_| {
+                        let prefix = prefix.clone();
+                        let tried = Arc::clone(&tried);
+                        let found = Arc::clone(&found);
+                        let stop = Arc::clone(&stop);
+                        thread::spawn(move || {
+                            let mut rng = rand::thread_rng();
+                            while !stop.load(Ordering::Relaxed) {
+                                let data: [u8; 32] = rng.gen();
+                                let privkey = match secp256k1::SecretKey::from_slice(&data[..]) {
+                                    Ok(privkey) => privkey,
+                                    Err(_) => continue,
+                                };
+                                let pubkey =
+                                    secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+                                tried.fetch_add(1, Ordering::Relaxed);
+                                let address = Address::from_pubkey(&pubkey)
+                                    .expect("address of pubkey")
+                                    .to_string(network);
+                                if address.starts_with(&prefix) {
+                                    *found.lock().unwrap() = Some(privkey);
+                                    stop.store(true, Ordering::Relaxed);
+                                }
+                            }
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                let start_time = Instant::now();
+                let mut last_report = Instant::now();
+                while !stop.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(200));
+                    if last_report.elapsed() >= VANITY_REPORT_INTERVAL {
+                        last_report = Instant::now();
+                        let tried = tried.load(Ordering::Relaxed);
+                        let rate = tried as f64 / start_time.elapsed().as_secs_f64();
+                        let eta = (expected_tries - tried as f64).max(0.0) / rate.max(1.0);
+                        eprintln!(
+                            "[INFO]: {} keys tried, {:.0} keys/s, expected {:.0} keys, ETA {:.0}s",
+                            tried, rate, expected_tries, eta
+                        );
+                    }
+                }
+                for handle in handles {
+                    handle.join().expect("vanity thread panicked");
+                }
+                let privkey = found
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .expect("stopped only when found");
+                let lock_arg = self
+                    .key_store
+                    .import_secp_key(&privkey, password.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                let address = Address::from_lock_arg(lock_arg.as_bytes()).unwrap();
+                let resp = serde_json::json!({
+                    "lock_arg": format!("{:x}", lock_arg),
+                    "address": address.to_string(network),
+                    "tried": tried.load(Ordering::Relaxed),
+                    "elapsed_secs": start_time.elapsed().as_secs(),
+                });
+                Ok(Box::new(resp))
+            }
+            ("qr", Some(m)) => {
+                let request = PaymentRequest {
+                    address: m.value_of("address").unwrap().to_owned(),
+                    amount: m.value_of("amount").map(ToOwned::to_owned),
+                    asset: m.value_of("asset").map(ToOwned::to_owned),
+                    memo: m.value_of("memo").map(ToOwned::to_owned),
+                };
+                let text = if request.amount.is_none()
+                    && request.asset.is_none()
+                    && request.memo.is_none()
+                {
+                    request.address
+                } else {
+                    request.to_uri()
+                };
+                let qr = render_qr(&text)?;
+                Ok(Box::new(Text::from(format!("{}\n{}", qr, text))))
             }
             ("extended-address", Some(m)) => {
                 let lock_arg: H160 =
@@ -294,9 +1147,103 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                         "testnet": address.to_string(NetworkType::TestNet),
                     },
                 });
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             _ => Err(matches.usage().to_owned()),
         }
     }
 }
+
+// Decrypt keys of an archive created by `account export-all`
+fn read_keys_archive(path: &Path) -> Result<Vec<Key>, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let archive: serde_json::Value =
+        serde_json::from_str(&content).map_err(|err| format!("Invalid keys archive: {}", err))?;
+    if archive["version"] != serde_json::json!(KEYS_ARCHIVE_VERSION) {
+        return Err(format!(
+            "Unsupported keys archive version: {}",
+            archive["version"]
+        ));
+    }
+    let crypto = Crypto::from_json(&archive["crypto"]).map_err(|err| err.to_string())?;
+    let archive_password = read_password(false, Some(tr("Archive password")))?;
+    let plaintext = SecretBytes(
+        crypto
+            .decrypt(archive_password.as_bytes())
+            .map_err(|err| err.to_string())?,
+    );
+    if plaintext.0.is_empty() || plaintext.0.len() % 64 != 0 {
+        return Err("Invalid keys archive content".to_owned());
+    }
+    let mut keys = Vec::new();
+    for chunk in plaintext.0.chunks(64) {
+        let mut bytes = [0u8; 64];
+        bytes.copy_from_slice(chunk);
+        let master_privkey = MasterPrivKey::from_bytes(bytes);
+        // Zeroized before the error is returned
+        zeroize_slice(&mut bytes);
+        keys.push(Key::new(master_privkey.map_err(|err| err.to_string())?));
+    }
+    Ok(keys)
+}
+
+// Network of the vanity address prefix and expected number of keys to try
+fn parse_vanity_prefix(prefix: &str) -> Result<(NetworkType, f64), String> {
+    let separator = prefix
+        .find('1')
+        .ok_or_else(|| format!("Invalid address prefix: {}", prefix))?;
+    let network = NetworkType::from_prefix(&prefix[..separator])
+        .ok_or_else(|| format!("Invalid network of address prefix: {}", prefix))?;
+    let data = &prefix[separator + 1..];
+    let mut expected_tries = 1.0;
+    for (index, c) in data.chars().enumerate() {
+        let position = BECH32_CHARSET
+            .find(c)
+            .ok_or_else(|| format!("Invalid address character: {}", c))?;
+        if index < SHORT_ADDRESS_DATA_PREFIX.len() {
+            if SHORT_ADDRESS_DATA_PREFIX.chars().nth(index) != Some(c) {
+                return Err(format!(
+                    "Short address always starts with {}1{}",
+                    network.to_prefix(),
+                    SHORT_ADDRESS_DATA_PREFIX
+                ));
+            }
+        } else if index == SHORT_ADDRESS_DATA_PREFIX.len() {
+            // Only 4 bits of this character come from the lock arg, the
+            // highest bit is from the code hash index
+            if position >= 16 {
+                return Err(format!(
+                    "Character after {} must be one of: {}",
+                    SHORT_ADDRESS_DATA_PREFIX,
+                    &BECH32_CHARSET[..16]
+                ));
+            }
+            expected_tries *= 16.0;
+        } else {
+            expected_tries *= 32.0;
+        }
+    }
+    Ok((network, expected_tries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vanity_prefix() {
+        assert_eq!(
+            parse_vanity_prefix("ckt1qyq"),
+            Ok((NetworkType::TestNet, 1.0))
+        );
+        assert_eq!(
+            parse_vanity_prefix("ckb1qyqga"),
+            Ok((NetworkType::MainNet, 16.0 * 32.0))
+        );
+        // 's' is the 17th character of the charset
+        assert!(parse_vanity_prefix("ckt1qyqs").is_err());
+        assert!(parse_vanity_prefix("ckt1qxq").is_err());
+        assert!(parse_vanity_prefix("ckt1qyqb1").is_err());
+        assert!(parse_vanity_prefix("abc1qyq").is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use super::CliSubCommand;
+use crate::utils::printer::Printable;
+
+pub struct AliasSubCommand<'a> {
+    aliases: &'a BTreeMap<String, String>,
+}
+
+impl<'a> AliasSubCommand<'a> {
+    pub fn new(aliases: &'a BTreeMap<String, String>) -> AliasSubCommand<'a> {
+        AliasSubCommand { aliases }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        SubCommand::with_name(name)
+            .about("Command aliases defined in config file (\"aliases\": {\"<name>\": \"<command>\"}), $1..$9 in a command are replaced by the arguments after the alias")
+            .subcommands(vec![SubCommand::with_name("list")
+                .about("List aliases")
+                .arg(
+                    Arg::with_name("name")
+                        .long("name")
+                        .takes_value(true)
+                        .help("Only show this alias"),
+                )])
+    }
+}
+
+impl<'a> CliSubCommand for AliasSubCommand<'a> {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        _debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        match matches.subcommand() {
+            ("list", Some(m)) => {
+                let resp = match m.value_of("name") {
+                    Some(name) => {
+                        let command = self
+                            .aliases
+                            .get(name)
+                            .ok_or_else(|| format!("Alias not found: {}", name))?;
+                        serde_json::json!({ name: command })
+                    }
+                    None => serde_json::json!(self.aliases),
+                };
+                Ok(Box::new(resp))
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_jsonrpc_types::BlockNumber;
+use ckb_sdk::{Address, GenesisInfo, HttpRpcClient};
+use ckb_types::{
+    core::{BlockView, Capacity},
+    packed::{self, Byte32, CellOutput, OutPoint},
+    prelude::*,
+    H256,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use super::CliSubCommand;
+use crate::utils::{
+    arg_parser::{AddressParser, ArgParser, FilePathParser, FromStrParser},
+    other::get_genesis_info,
+    printer::Printable,
+};
+
+pub struct AnalyzeSubCommand<'a> {
+    rpc_client: &'a mut HttpRpcClient,
+    genesis_info: Option<GenesisInfo>,
+}
+
+impl<'a> AnalyzeSubCommand<'a> {
+    pub fn new(
+        rpc_client: &'a mut HttpRpcClient,
+        genesis_info: Option<GenesisInfo>,
+    ) -> AnalyzeSubCommand<'a> {
+        AnalyzeSubCommand {
+            rpc_client,
+            genesis_info,
+        }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        let arg_from_number = Arg::with_name("from")
+            .long("from")
+            .takes_value(true)
+            .required(true)
+            .validator(|input| FromStrParser::<u64>::default().validate(input))
+            .help("From block number (inclusive)");
+        let arg_to_number = Arg::with_name("to")
+            .long("to")
+            .takes_value(true)
+            .required(true)
+            .validator(|input| FromStrParser::<u64>::default().validate(input))
+            .help("To block number (inclusive)");
+        SubCommand::with_name(name)
+            .about("Analyze on-chain data over block ranges")
+            .subcommands(vec![SubCommand::with_name("capacity-flow")
+                .about("Aggregate capacity moved from one address set to another")
+                .arg(
+                    Arg::with_name("from-addresses")
+                        .long("from-addresses")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| FilePathParser::new(true).validate(input))
+                        .help("File of source addresses (one address per line, '#' for comment)"),
+                )
+                .arg(
+                    Arg::with_name("to-addresses")
+                        .long("to-addresses")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| FilePathParser::new(true).validate(input))
+                        .help("File of target addresses (one address per line, '#' for comment)"),
+                )
+                .arg(arg_from_number)
+                .arg(arg_to_number)])
+    }
+
+    fn read_lock_hashes(&mut self, m: &ArgMatches, name: &str) -> Result<HashSet<Byte32>, String> {
+        let path: PathBuf = FilePathParser::new(true).from_matches(m, name)?;
+        let secp_type_hash = get_genesis_info(&mut self.genesis_info, self.rpc_client)?
+            .secp_type_hash()
+            .clone();
+        let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let address: Address = AddressParser
+                    .parse(line)
+                    .map_err(|err| format!("Invalid address {} in {:?}: {}", line, path, err))?;
+                Ok(address
+                    .lock_script(secp_type_hash.clone())
+                    .calc_script_hash())
+            })
+            .collect()
+    }
+
+    fn capacity_flow(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let from_locks = self.read_lock_hashes(m, "from-addresses")?;
+        let to_locks = self.read_lock_hashes(m, "to-addresses")?;
+        let from_number: u64 = FromStrParser::<u64>::default().from_matches(m, "from")?;
+        let to_number: u64 = FromStrParser::<u64>::default().from_matches(m, "to")?;
+        if from_number > to_number {
+            return Err(format!(
+                "Invalid range: from({}) > to({})",
+                from_number, to_number
+            ));
+        }
+
+        let mut previous_txs: HashMap<Byte32, packed::Transaction> = HashMap::default();
+        let mut txs = Vec::new();
+        let mut total_flow: u64 = 0;
+        for number in from_number..=to_number {
+            let block: BlockView = self
+                .rpc_client
+                .get_block_by_number(BlockNumber::from(number))
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .ok_or_else(|| format!("Block not found: {}", number))?
+                .into();
+            // Skip cellbase
+            for tx in block.transactions().iter().skip(1) {
+                let to_capacity: u64 = tx
+                    .outputs()
+                    .into_iter()
+                    .filter(|output| to_locks.contains(&output.lock().calc_script_hash()))
+                    .map(|output| output_capacity(&output))
+                    .sum();
+                if to_capacity == 0 {
+                    continue;
+                }
+                let mut from_capacity: u64 = 0;
+                for input in tx.inputs().into_iter() {
+                    let output =
+                        self.previous_output(&mut previous_txs, &input.previous_output())?;
+                    if from_locks.contains(&output.lock().calc_script_hash()) {
+                        from_capacity += output_capacity(&output);
+                    }
+                }
+                if from_capacity == 0 {
+                    continue;
+                }
+                let flow = std::cmp::min(from_capacity, to_capacity);
+                total_flow += flow;
+                let tx_hash: H256 = tx.hash().unpack();
+                txs.push(serde_json::json!({
+                    "block_number": number,
+                    "tx_hash": tx_hash,
+                    "from_capacity": from_capacity,
+                    "to_capacity": to_capacity,
+                    "flow": flow,
+                }));
+            }
+        }
+        Ok(serde_json::json!({
+            "from": from_number,
+            "to": to_number,
+            "total_flow": total_flow,
+            "tx_count": txs.len(),
+            "transactions": txs,
+        }))
+    }
+
+    fn previous_output(
+        &mut self,
+        previous_txs: &mut HashMap<Byte32, packed::Transaction>,
+        out_point: &OutPoint,
+    ) -> Result<CellOutput, String> {
+        let tx_hash = out_point.tx_hash();
+        if !previous_txs.contains_key(&tx_hash) {
+            let tx: packed::Transaction = self
+                .rpc_client
+                .get_transaction(tx_hash.unpack())
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?
+                .transaction
+                .inner
+                .into();
+            previous_txs.insert(tx_hash.clone(), tx);
+        }
+        let index: u32 = out_point.index().unpack();
+        previous_txs[&tx_hash]
+            .raw()
+            .outputs()
+            .get(index as usize)
+            .ok_or_else(|| format!("Invalid out point index: {:#x}-{}", tx_hash, index))
+    }
+}
+
+impl<'a> CliSubCommand for AnalyzeSubCommand<'a> {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        _debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        match matches.subcommand() {
+            ("capacity-flow", Some(m)) => {
+                let resp = self.capacity_flow(m)?;
+                Ok(Box::new(resp))
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
+
+fn output_capacity(output: &CellOutput) -> u64 {
+    let capacity: Capacity = output.capacity().unpack();
+    capacity.as_u64()
+}
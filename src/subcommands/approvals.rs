@@ -0,0 +1,173 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use super::CliSubCommand;
+use crate::utils::{
+    price::PriceFeed,
+    printer::{format_capacity, Printable},
+    serve_approval::{ApprovalStatus, ServeApprovals, TransferProposal},
+    serve_token::ServeTokens,
+};
+
+/// Approve or reject transfers proposed by serve mode clients (interactive
+/// mode only)
+pub struct ApprovalsSubCommand {
+    serve_tokens_file: PathBuf,
+    serve_approvals_file: PathBuf,
+    price_feed: Option<PriceFeed>,
+}
+
+impl ApprovalsSubCommand {
+    pub fn new(
+        serve_tokens_file: PathBuf,
+        serve_approvals_file: PathBuf,
+        price_feed: Option<PriceFeed>,
+    ) -> ApprovalsSubCommand {
+        ApprovalsSubCommand {
+            serve_tokens_file,
+            serve_approvals_file,
+            price_feed,
+        }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        let arg_id = Arg::with_name("id")
+            .takes_value(true)
+            .required(true)
+            .help("Proposal id");
+        SubCommand::with_name(name)
+            .about("Approve or reject transfers proposed by serve mode clients")
+            .subcommands(vec![
+                SubCommand::with_name("list")
+                    .about("List pending proposals")
+                    .arg(
+                        Arg::with_name("all")
+                            .long("all")
+                            .help("Also list approved, rejected and expired proposals"),
+                    ),
+                SubCommand::with_name("approve")
+                    .about("Approve a proposal, then sign and send the transfer")
+                    .arg(arg_id.clone()),
+                SubCommand::with_name("reject")
+                    .about("Reject a proposal")
+                    .arg(arg_id),
+            ])
+    }
+
+    /// Check the proposal can be approved, returns the `wallet transfer`
+    /// command line to sign and send it. Call `finish` with the result of
+    /// the command.
+    pub fn approve(&mut self, matches: &ArgMatches) -> Result<(String, Vec<String>), String> {
+        let id = matches.value_of("id").unwrap();
+        let approvals = self.load_approvals()?;
+        let proposal = approvals.pending(id, now_millis())?;
+        // The spending limit may have been changed or used up since proposed
+        let tokens = ServeTokens::load(&self.serve_tokens_file)?;
+        let token = tokens
+            .tokens()
+            .iter()
+            .find(|token| token.name == proposal.token_name)
+            .ok_or_else(|| format!("Token {} is revoked", proposal.token_name))?;
+        if let Some(remaining) = token.remaining() {
+            if proposal.capacity > remaining {
+                return Err(format!(
+                    "Token {} exceeds spending limit: {} shannons left, {} requested",
+                    token.name, remaining, proposal.capacity
+                ));
+            }
+        }
+        Ok((id.to_owned(), transfer_args(proposal)))
+    }
+
+    pub fn finish(&mut self, id: &str, result: &Result<(), String>) -> Result<(), String> {
+        let mut approvals = self.load_approvals()?;
+        let (status, error) = match result {
+            Ok(()) => (ApprovalStatus::Sent, None),
+            Err(err) => (ApprovalStatus::Failed, Some(err.clone())),
+        };
+        let proposal = approvals.decide(id, status, now_millis(), error)?.clone();
+        approvals.save()?;
+        if status == ApprovalStatus::Sent {
+            let mut tokens = ServeTokens::load(&self.serve_tokens_file)?;
+            tokens.record_spend(&proposal.token_name, proposal.capacity)?;
+            tokens.save()?;
+        }
+        Ok(())
+    }
+
+    // Load approvals and expire the outdated ones
+    fn load_approvals(&self) -> Result<ServeApprovals, String> {
+        let mut approvals = ServeApprovals::load(&self.serve_approvals_file)?;
+        if approvals.expire(now_millis()) > 0 {
+            approvals.save()?;
+        }
+        Ok(approvals)
+    }
+}
+
+impl CliSubCommand for ApprovalsSubCommand {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        _debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        match matches.subcommand() {
+            ("list", Some(m)) => {
+                let approvals = self.load_approvals()?;
+                let resp = approvals
+                    .proposals()
+                    .iter()
+                    .filter(|proposal| {
+                        m.is_present("all") || proposal.status == ApprovalStatus::Pending
+                    })
+                    .map(|proposal| {
+                        let mut value = serde_json::to_value(proposal).unwrap();
+                        if let Some(feed) = self.price_feed.as_ref() {
+                            value["fiat"] = feed.fiat_values(&[
+                                ("capacity", proposal.capacity),
+                                ("tx_fee", proposal.tx_fee),
+                            ]);
+                        }
+                        value
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Box::new(serde_json::json!(resp)))
+            }
+            ("reject", Some(m)) => {
+                let id = m.value_of("id").unwrap();
+                let mut approvals = self.load_approvals()?;
+                approvals.pending(id, now_millis())?;
+                let proposal = approvals
+                    .decide(id, ApprovalStatus::Rejected, now_millis(), None)?
+                    .clone();
+                approvals.save()?;
+                Ok(Box::new(serde_json::to_value(proposal).unwrap()))
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
+
+fn transfer_args(proposal: &TransferProposal) -> Vec<String> {
+    let mut args = vec!["wallet".to_owned(), "transfer".to_owned()];
+    if let Some(from_account) = proposal.from_account.as_ref() {
+        args.push("--from-account".to_owned());
+        args.push(from_account.clone());
+    }
+    args.push("--to-address".to_owned());
+    args.push(proposal.to_address.clone());
+    args.push("--capacity".to_owned());
+    args.push(format_capacity(proposal.capacity));
+    args.push("--tx-fee".to_owned());
+    args.push(format_capacity(proposal.tx_fee));
+    args
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as u64
+}
@@ -0,0 +1,301 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ckb_jsonrpc_types::{CellDep as JsonCellDep, CellWithStatus, Script as JsonScript};
+use ckb_sdk::HttpRpcClient;
+use ckb_types::{
+    bytes::Bytes,
+    core::{DepType, ScriptHashType},
+    packed::{CellDep, OutPoint, Script},
+    prelude::*,
+    H160, H256,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use super::CliSubCommand;
+use crate::utils::{
+    arg,
+    arg_parser::{ArgParser, FixedHashParser, FromStrParser, HexParser, UrlParser},
+    asset::{verify_token_list, AssetInfo, AssetRegistry, RegistrySource, SyncedRegistry},
+    printer::{Printable, Text},
+};
+
+pub struct AssetSubCommand<'a> {
+    rpc_client: &'a mut HttpRpcClient,
+    index_dir: PathBuf,
+}
+
+impl<'a> AssetSubCommand<'a> {
+    pub fn new(rpc_client: &'a mut HttpRpcClient, index_dir: PathBuf) -> AssetSubCommand<'a> {
+        AssetSubCommand {
+            rpc_client,
+            index_dir,
+        }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        let arg_symbol = Arg::with_name("symbol")
+            .long("symbol")
+            .takes_value(true)
+            .required(true)
+            .help("Asset symbol");
+        SubCommand::with_name(name)
+            .about("Manage the asset (sUDT) registry used by wallet transfer and balance")
+            .subcommands(vec![
+                SubCommand::with_name("add")
+                    .about("Register a sUDT asset (or update the one with same symbol)")
+                    .arg(arg_symbol.clone())
+                    .arg(
+                        Arg::with_name("decimals")
+                            .long("decimals")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("Decimal places of the asset amount"),
+                    )
+                    .arg(
+                        arg::code_hash()
+                            .required(true)
+                            .help("Code hash of the sUDT type script"),
+                    )
+                    .arg(
+                        Arg::with_name("hash-type")
+                            .long("hash-type")
+                            .takes_value(true)
+                            .possible_values(&["data", "type"])
+                            .default_value("data")
+                            .help("Hash type of the sUDT type script"),
+                    )
+                    .arg(
+                        Arg::with_name("args")
+                            .long("args")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| HexParser.validate(input))
+                            .help("Args of the sUDT type script (owner lock hash)"),
+                    )
+                    .arg(
+                        Arg::with_name("dep-tx-hash")
+                            .long("dep-tx-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Transaction hash of the sUDT script cell dep"),
+                    )
+                    .arg(
+                        Arg::with_name("dep-index")
+                            .long("dep-index")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<u32>::default().validate(input))
+                            .help("Output index of the sUDT script cell dep"),
+                    )
+                    .arg(
+                        Arg::with_name("dep-group")
+                            .long("dep-group")
+                            .help("The cell dep is a dep group"),
+                    ),
+                SubCommand::with_name("list").about("List registered assets"),
+                SubCommand::with_name("remove")
+                    .about("Remove a registered asset")
+                    .arg(arg_symbol),
+                SubCommand::with_name("registry")
+                    .about("Signed token list synced from a URL or an on-chain cell")
+                    .subcommands(vec![
+                        SubCommand::with_name("update")
+                            .about("Fetch and verify the token list, reuse last source and signer if not given. Format: {\"assets\": [{symbol, decimals, type_script, cell_dep}], \"signature\": \"0x..\"}, signed over blake2b_256 of `assets` in compact JSON with sorted keys")
+                            .arg(
+                                Arg::with_name("url")
+                                    .long("url")
+                                    .takes_value(true)
+                                    .validator(|input| UrlParser.validate(input))
+                                    .help("Token list URL"),
+                            )
+                            .arg(
+                                Arg::with_name("tx-hash")
+                                    .long("tx-hash")
+                                    .takes_value(true)
+                                    .conflicts_with("url")
+                                    .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                                    .help("Transaction hash of the cell which data is the token list"),
+                            )
+                            .arg(
+                                Arg::with_name("index")
+                                    .long("index")
+                                    .takes_value(true)
+                                    .requires("tx-hash")
+                                    .default_value("0")
+                                    .validator(|input| FromStrParser::<u32>::default().validate(input))
+                                    .help("Output index of the token list cell"),
+                            )
+                            .arg(
+                                Arg::with_name("signer")
+                                    .long("signer")
+                                    .takes_value(true)
+                                    .validator(|input| FixedHashParser::<H160>::default().validate(input))
+                                    .help("Lock arg (blake160 of public key) of the token list signer"),
+                            ),
+                        SubCommand::with_name("show").about("Show source and status of the synced token list"),
+                    ]),
+            ])
+    }
+
+    fn registry_update(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let last = SyncedRegistry::load(&self.index_dir)?;
+        let source = if let Some(url) = m.value_of("url") {
+            RegistrySource::Url(url.to_owned())
+        } else if m.is_present("tx-hash") {
+            let tx_hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+            let index: u32 = FromStrParser::<u32>::default().from_matches(m, "index")?;
+            RegistrySource::Cell(OutPoint::new(tx_hash.pack(), index).into())
+        } else {
+            last.as_ref()
+                .map(|registry| registry.source.clone())
+                .ok_or_else(|| "<url> or <tx-hash> is required for the first update".to_owned())?
+        };
+        let signer_opt: Option<H160> =
+            FixedHashParser::<H160>::default().from_matches_opt(m, "signer", false)?;
+        let signer = signer_opt
+            .or_else(|| last.as_ref().map(|registry| registry.signer.clone()))
+            .ok_or_else(|| "<signer> is required for the first update".to_owned())?;
+
+        let content: Vec<u8> = match source {
+            RegistrySource::Url(ref url) => {
+                let mut response = ckb_sdk::http_client(None)?
+                    .get(url.as_str())
+                    .send()
+                    .map_err(|err| format!("Fetch token list error: {}", err))?;
+                if !response.status().is_success() {
+                    return Err(format!("Fetch token list error: {}", response.status()));
+                }
+                response
+                    .text()
+                    .map(String::into_bytes)
+                    .map_err(|err| err.to_string())?
+            }
+            RegistrySource::Cell(ref out_point) => {
+                let cell: CellWithStatus = self
+                    .rpc_client
+                    .get_live_cell(out_point.clone(), true)
+                    .call()
+                    .map_err(|err| err.to_string())?;
+                cell.cell
+                    .and_then(|info| info.data)
+                    .map(|data| data.content.into_bytes().to_vec())
+                    .ok_or_else(|| format!("Token list cell is not live: {}", cell.status))?
+            }
+        };
+        let assets = verify_token_list(&content, &signer)?;
+        let updated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let registry = SyncedRegistry {
+            source,
+            signer,
+            updated_at,
+            assets,
+        };
+        registry.save(&self.index_dir)?;
+        let resp = serde_json::json!({
+            "updated_at": updated_at,
+            "assets": registry.assets.iter().map(|info| info.symbol.clone()).collect::<Vec<_>>(),
+        });
+        Ok(Box::new(resp))
+    }
+}
+
+fn asset_json(info: &AssetInfo, synced: bool) -> serde_json::Value {
+    serde_json::json!({
+        "source": if synced { "registry" } else { "local" },
+        "symbol": info.symbol,
+        "decimals": info.decimals,
+        "type_hash": info.type_hash(),
+        "type_script": info.type_script,
+        "cell_dep": info.cell_dep,
+    })
+}
+
+impl<'a> CliSubCommand for AssetSubCommand<'a> {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        _debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        match matches.subcommand() {
+            ("add", Some(m)) => {
+                let code_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "code-hash")?;
+                let hash_type = match m.value_of("hash-type") {
+                    Some("type") => ScriptHashType::Type,
+                    _ => ScriptHashType::Data,
+                };
+                let args: Vec<u8> = HexParser.from_matches(m, "args")?;
+                let dep_tx_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "dep-tx-hash")?;
+                let dep_index: u32 =
+                    FromStrParser::<u32>::default().from_matches(m, "dep-index")?;
+                let dep_type = if m.is_present("dep-group") {
+                    DepType::DepGroup
+                } else {
+                    DepType::Code
+                };
+                let type_script = Script::new_builder()
+                    .code_hash(code_hash.pack())
+                    .hash_type(hash_type.into())
+                    .args(Bytes::from(args).pack())
+                    .build();
+                let cell_dep = CellDep::new_builder()
+                    .out_point(OutPoint::new(dep_tx_hash.pack(), dep_index))
+                    .dep_type(dep_type.into())
+                    .build();
+                let info = AssetInfo {
+                    symbol: m.value_of("symbol").unwrap().to_owned(),
+                    decimals: FromStrParser::<u8>::default().from_matches(m, "decimals")?,
+                    type_script: JsonScript::from(type_script),
+                    cell_dep: JsonCellDep::from(cell_dep),
+                };
+                let mut registry = AssetRegistry::load(&self.index_dir)?;
+                let resp = asset_json(&info, false);
+                registry.add(info)?;
+                registry.save()?;
+                Ok(Box::new(resp))
+            }
+            ("list", _) => {
+                let registry = AssetRegistry::load(&self.index_dir)?;
+                let resp = registry
+                    .list()
+                    .into_iter()
+                    .map(|(info, synced)| asset_json(info, synced))
+                    .collect::<Vec<_>>();
+                Ok(Box::new(serde_json::json!(resp)))
+            }
+            ("remove", Some(m)) => {
+                let symbol = m.value_of("symbol").unwrap();
+                let mut registry = AssetRegistry::load(&self.index_dir)?;
+                registry
+                    .remove(symbol)
+                    .ok_or_else(|| format!("Asset not found: {}", symbol))?;
+                registry.save()?;
+                Ok(Box::new(Text::from("DONE")))
+            }
+            ("registry", Some(registry_m)) => match registry_m.subcommand() {
+                ("update", Some(m)) => self.registry_update(m),
+                ("show", _) => {
+                    let resp = match SyncedRegistry::load(&self.index_dir)? {
+                        Some(registry) => serde_json::json!({
+                            "source": registry.source,
+                            "signer": registry.signer,
+                            "updated_at": registry.updated_at,
+                            "assets": registry.assets.len(),
+                        }),
+                        None => serde_json::json!(null),
+                    };
+                    Ok(Box::new(resp))
+                }
+                _ => Err(registry_m.usage().to_owned()),
+            },
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
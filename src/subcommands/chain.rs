@@ -0,0 +1,917 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use ckb_jsonrpc_types::{BlockNumber, EpochNumber, EpochView, Uint64};
+use ckb_pow::{EaglesongPowEngine, PowEngine};
+use ckb_sdk::{transactions_root_from_proof, HttpRpcClient};
+use ckb_types::{
+    core::HeaderView,
+    packed::{Header, Transaction},
+    prelude::*,
+    utilities::compact_to_difficulty,
+    H256, U256,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use super::CliSubCommand;
+use crate::utils::{
+    arg,
+    arg_parser::{ArgParser, FixedHashParser, FromStrParser},
+    printer::Printable,
+};
+
+pub struct ChainSubCommand<'a> {
+    rpc_client: &'a mut HttpRpcClient,
+    headers_dir: PathBuf,
+}
+
+impl<'a> ChainSubCommand<'a> {
+    pub fn new(rpc_client: &'a mut HttpRpcClient, headers_dir: PathBuf) -> ChainSubCommand<'a> {
+        ChainSubCommand {
+            rpc_client,
+            headers_dir,
+        }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        let arg_skip_pow = Arg::with_name("skip-pow")
+            .long("skip-pow")
+            .help("Skip PoW verification (for dev chain using dummy PoW)");
+        let arg_window = Arg::with_name("window")
+            .long("window")
+            .takes_value(true)
+            .default_value("1000")
+            .validator(|input| FromStrParser::<u64>::default().validate(input))
+            .help("Number of recent blocks used to estimate block time and hashrate");
+        let arg_checkpoint = Arg::with_name("checkpoint")
+            .long("checkpoint")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|input| parse_checkpoint(&input).map(|_| ()))
+            .help("Trusted block hash from a source other than the node, format: {number}:{hash}, the genesis checkpoint (0:{hash}) is checked when the local header chain is created, otherwise genesis is trusted from the node");
+        SubCommand::with_name(name)
+            .about("Verify chain data independently from the node")
+            .subcommands(vec![
+                SubCommand::with_name("sync-headers")
+                    .about("Sync and verify headers (parent link, epoch, compact target, difficulty adjustment, median time, PoW, checkpoints) into local header chain")
+                    .arg(arg_checkpoint.clone())
+                    .arg(
+                        Arg::with_name("to")
+                            .long("to")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Sync to block number (default: node tip)"),
+                    )
+                    .arg(arg_skip_pow.clone()),
+                SubCommand::with_name("verify-header-chain")
+                    .about("Audit headers over a range from the node: hash, parent link, epoch, difficulty adjustment, timestamp and PoW, report all anomalies")
+                    .arg(
+                        Arg::with_name("from")
+                            .long("from")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("From block number"),
+                    )
+                    .arg(
+                        Arg::with_name("to")
+                            .long("to")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("To block number (default: node tip)"),
+                    )
+                    .arg(
+                        Arg::with_name("max-anomalies")
+                            .long("max-anomalies")
+                            .takes_value(true)
+                            .default_value("100")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Stop after this many anomalies"),
+                    )
+                    .arg(arg_skip_pow.clone()),
+                SubCommand::with_name("verify-tx")
+                    .about("Check transaction inclusion claimed by the node against local header chain")
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .long("tx-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Transaction hash"),
+                    )
+                    .arg(arg_checkpoint.clone()),
+                SubCommand::with_name("verify-balance")
+                    .about("Check live cells of a lock hash claimed by the node (node indexer, see: rpc index_lock_hash) against local header chain: the creating transaction must be in a verified block and have the claimed output. Spent cells hidden or reported as live by the node can not be detected")
+                    .arg(arg::lock_hash().required(true))
+                    .arg(arg_checkpoint),
+                SubCommand::with_name("calc-epoch")
+                    .about("Show epoch boundaries and progress, and project time to a target block or epoch")
+                    .arg(
+                        Arg::with_name("epoch")
+                            .long("epoch")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Epoch number (default: current epoch)"),
+                    )
+                    .arg(
+                        Arg::with_name("to-block")
+                            .long("to-block")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Project time to this block number"),
+                    )
+                    .arg(
+                        Arg::with_name("to-epoch")
+                            .long("to-epoch")
+                            .takes_value(true)
+                            .conflicts_with("to-block")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Project time to start of this epoch (future epoch length is assumed same as current)"),
+                    )
+                    .arg(arg_window.clone()),
+                SubCommand::with_name("hashrate")
+                    .about("Estimate block time, difficulty and network hashrate over recent blocks")
+                    .arg(arg_window),
+            ])
+    }
+
+    fn epoch_view(&mut self, number: Option<u64>) -> Result<EpochView, String> {
+        match number {
+            Some(number) => self
+                .rpc_client
+                .get_epoch_by_number(EpochNumber::from(number))
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .ok_or_else(|| format!("Epoch not found: {}", number)),
+            None => self
+                .rpc_client
+                .get_current_epoch()
+                .call()
+                .map_err(|err| err.to_string()),
+        }
+    }
+
+    fn tip_header(&mut self) -> Result<HeaderView, String> {
+        Ok(self
+            .rpc_client
+            .get_tip_header()
+            .call()
+            .map_err(|err| err.to_string())?
+            .into())
+    }
+
+    // Average block time (ms) over `window` blocks before tip
+    fn block_time(&mut self, tip: &HeaderView, window: u64) -> Result<BlockTime, String> {
+        let start_number = tip.number().saturating_sub(window);
+        let blocks = tip.number() - start_number;
+        if blocks == 0 {
+            return Err("No block mined after genesis yet".to_owned());
+        }
+        let start = self.header_by_number(start_number)?;
+        let duration = tip.timestamp().saturating_sub(start.timestamp());
+        Ok(BlockTime {
+            start_number,
+            blocks,
+            duration,
+            average: duration / blocks,
+        })
+    }
+
+    fn calc_epoch(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let epoch_opt: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "epoch", false)?;
+        let to_block: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "to-block", false)?;
+        let to_epoch: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "to-epoch", false)?;
+        let window: u64 = FromStrParser::<u64>::default().from_matches(m, "window")?;
+
+        let tip = self.tip_header()?;
+        let current = self.epoch_view(None)?;
+        let epoch = match epoch_opt {
+            Some(number) if number > current.number.value() => {
+                return Err(format!(
+                    "Epoch {} is not started yet (current epoch: {}), use --to-epoch to project it",
+                    number,
+                    current.number.value()
+                ));
+            }
+            Some(number) => self.epoch_view(Some(number))?,
+            None => current.clone(),
+        };
+        let block_time = self.block_time(&tip, window)?;
+        let start_number = epoch.start_number.value();
+        let length = epoch.length.value();
+        let end_number = start_number + length - 1;
+        let compact_target = epoch.compact_target.value();
+
+        let progress = if epoch.number.value() == current.number.value() {
+            let index = tip.number() - start_number;
+            let remaining_blocks = end_number - tip.number();
+            serde_json::json!({
+                "index": index,
+                "percent": format!("{:.2}", (index + 1) as f64 * 100.0 / length as f64),
+                "remaining_blocks": remaining_blocks,
+                "estimated_end_timestamp": tip.timestamp() + remaining_blocks * block_time.average,
+            })
+        } else {
+            serde_json::json!({ "percent": "100.00", "remaining_blocks": 0 })
+        };
+
+        let target_number = match (to_block, to_epoch) {
+            (Some(number), _) => Some(number),
+            (None, Some(number)) if number <= current.number.value() => {
+                Some(self.epoch_view(Some(number))?.start_number.value())
+            }
+            (None, Some(number)) => {
+                let current_end = current.start_number.value() + current.length.value();
+                Some(current_end + (number - current.number.value() - 1) * current.length.value())
+            }
+            (None, None) => None,
+        };
+        let target = target_number.map(|number| {
+            let remaining_blocks = number.saturating_sub(tip.number());
+            let estimated_ms = remaining_blocks * block_time.average;
+            serde_json::json!({
+                "block_number": number,
+                "remaining_blocks": remaining_blocks,
+                "estimated_seconds": estimated_ms / 1000,
+                "estimated_timestamp": tip.timestamp() + estimated_ms,
+            })
+        });
+
+        Ok(serde_json::json!({
+            "epoch": epoch.number.value(),
+            "start_number": start_number,
+            "end_number": end_number,
+            "length": length,
+            "compact_target": format!("{:#x}", compact_target),
+            "difficulty": format!("{:#x}", compact_to_difficulty(compact_target)),
+            "tip_number": tip.number(),
+            "progress": progress,
+            "average_block_time_ms": block_time.average,
+            "target": target,
+        }))
+    }
+
+    fn hashrate(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let window: u64 = FromStrParser::<u64>::default().from_matches(m, "window")?;
+        let tip = self.tip_header()?;
+        let block_time = self.block_time(&tip, window)?;
+
+        // Sum difficulty of blocks in (start_number, tip] epoch by epoch, the
+        // difficulty only changes at epoch boundary.
+        let first_number = block_time.start_number + 1;
+        let mut total_difficulty = 0f64;
+        let mut epoch_number = self.header_by_number(first_number)?.epoch().number();
+        loop {
+            let epoch = self.epoch_view(Some(epoch_number))?;
+            let epoch_start = epoch.start_number.value();
+            let epoch_end = epoch_start + epoch.length.value() - 1;
+            let blocks = epoch_end.min(tip.number()) - epoch_start.max(first_number) + 1;
+            total_difficulty +=
+                u256_to_f64(&compact_to_difficulty(epoch.compact_target.value())) * blocks as f64;
+            if epoch_end >= tip.number() {
+                break;
+            }
+            epoch_number += 1;
+        }
+        let tip_difficulty = compact_to_difficulty(tip.compact_target());
+        let hashrate = if block_time.duration > 0 {
+            total_difficulty * 1000.0 / block_time.duration as f64
+        } else {
+            0.0
+        };
+        Ok(serde_json::json!({
+            "from_number": first_number,
+            "to_number": tip.number(),
+            "blocks": block_time.blocks,
+            "duration_ms": block_time.duration,
+            "average_block_time_ms": block_time.average,
+            "tip_difficulty": format!("{:#x}", tip_difficulty),
+            "hashrate": format!("{:.0}", hashrate),
+            "hashrate_readable": format_hashrate(hashrate),
+        }))
+    }
+
+    fn header_by_number(&mut self, number: u64) -> Result<HeaderView, String> {
+        Ok(self
+            .rpc_client
+            .get_header_by_number(BlockNumber::from(number))
+            .call()
+            .map_err(|err| err.to_string())?
+            .0
+            .ok_or_else(|| format!("Header not found: {}", number))?
+            .into())
+    }
+
+    // Genesis is checked against the genesis checkpoint, and synced headers
+    // against all checkpoints
+    fn header_store(&mut self, checkpoints: &[(u64, H256)]) -> Result<HeaderStore, String> {
+        let genesis = self.header_by_number(0)?;
+        let genesis_hash: H256 = genesis.hash().unpack();
+        match checkpoints.iter().find(|(number, _)| *number == 0) {
+            Some((_, hash)) if hash != &genesis_hash => {
+                return Err(format!(
+                    "Node genesis {:#x} does not match checkpoint {:#x}",
+                    genesis_hash, hash
+                ));
+            }
+            Some(_) => {}
+            None if !HeaderStore::exists(&self.headers_dir, &genesis) => {
+                eprintln!(
+                    "[WARNING]: No genesis checkpoint, trusting genesis {:#x} from the node",
+                    genesis_hash
+                );
+            }
+            None => {}
+        }
+        let mut store = HeaderStore::open(&self.headers_dir, genesis)?;
+        for (number, hash) in checkpoints {
+            if let Some(header) = store.get(*number)? {
+                let local_hash: H256 = header.hash().unpack();
+                if &local_hash != hash {
+                    return Err(format!(
+                        "Local header chain has {:#x} at block #{}, checkpoint is {:#x}, remove {} and sync again",
+                        local_hash,
+                        number,
+                        hash,
+                        store.path.display()
+                    ));
+                }
+            }
+        }
+        Ok(store)
+    }
+
+    // The node claims the transaction is in a block, check the block is in
+    // verified header chain and the transaction proof matches its header
+    fn verify_inclusion(
+        &mut self,
+        store: &mut HeaderStore,
+        tx_hash: &H256,
+    ) -> Result<(u64, H256, Vec<String>), String> {
+        let proof = self
+            .rpc_client
+            .get_transaction_proof(vec![tx_hash.clone()], None)
+            .call()
+            .map_err(|err| err.to_string())?;
+        let claimed: HeaderView = self
+            .rpc_client
+            .get_header(proof.block_hash.clone())
+            .call()
+            .map_err(|err| err.to_string())?
+            .0
+            .ok_or_else(|| format!("Header not found: {:#x}", proof.block_hash))?
+            .into();
+        let number = claimed.number();
+        let local = store.get(number)?.ok_or_else(|| {
+            format!(
+                "Block #{} is beyond local header chain, run `chain sync-headers` first",
+                number
+            )
+        })?;
+        let local_hash: H256 = local.hash().unpack();
+        let mut warnings = Vec::new();
+        if local_hash != proof.block_hash {
+            warnings.push(format!(
+                "Node claims block #{} is {:#x}, but verified header chain has {:#x}",
+                number, proof.block_hash, local_hash
+            ));
+        }
+        let transactions_root: H256 = local.transactions_root().unpack();
+        if transactions_root_from_proof(&proof, tx_hash).as_ref() != Some(&transactions_root) {
+            warnings.push(format!(
+                "Proof of transaction {:#x} does not match verified header",
+                tx_hash
+            ));
+        }
+        Ok((number, local_hash, warnings))
+    }
+
+    fn sync_headers(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let to_opt: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "to", false)?;
+        let skip_pow = m.is_present("skip-pow");
+        let checkpoints = checkpoints_from_matches(m)?;
+        let mut store = self.header_store(&checkpoints)?;
+        let to_number = match to_opt {
+            Some(number) => number,
+            None => self
+                .rpc_client
+                .get_tip_block_number()
+                .call()
+                .map_err(|err| err.to_string())?
+                .value(),
+        };
+
+        let mut parent = store.tip()?;
+        let from_number = parent.number() + 1;
+        let mut timestamps = VecDeque::with_capacity(MEDIAN_TIME_BLOCKS);
+        for number in from_number.saturating_sub(MEDIAN_TIME_BLOCKS as u64)..from_number {
+            if let Some(header) = store.get(number)? {
+                timestamps.push_back(header.timestamp());
+            }
+        }
+        for number in from_number..=to_number {
+            let header = self.header_by_number(number)?;
+            let hash: H256 = header.hash().unpack();
+            let median = median_time(&timestamps);
+            let result = verify_header(&parent, &header, skip_pow)
+                .and_then(|_| verify_difficulty_adjustment(&parent, &header))
+                .and_then(|_| {
+                    if header.timestamp() <= median {
+                        Err(format!(
+                            "timestamp {} is not after median time {} of previous {} blocks",
+                            header.timestamp(),
+                            median,
+                            timestamps.len()
+                        ))
+                    } else {
+                        Ok(())
+                    }
+                })
+                .and_then(|_| match checkpoints.iter().find(|(n, _)| *n == number) {
+                    Some((_, checkpoint)) if checkpoint != &hash => {
+                        Err(format!("checkpoint is {:#x}", checkpoint))
+                    }
+                    _ => Ok(()),
+                });
+            if let Err(err) = result {
+                return Err(format!(
+                    "Node returned invalid header #{} {:#x}: {}",
+                    number, hash, err
+                ));
+            }
+            store.append(&header)?;
+            if timestamps.len() == MEDIAN_TIME_BLOCKS {
+                timestamps.pop_front();
+            }
+            timestamps.push_back(header.timestamp());
+            parent = header;
+        }
+        let tip_hash: H256 = parent.hash().unpack();
+        Ok(serde_json::json!({
+            "synced": to_number.saturating_sub(from_number - 1),
+            "tip_number": parent.number(),
+            "tip_hash": tip_hash,
+        }))
+    }
+
+    fn verify_header_chain(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let from_number: u64 = FromStrParser::<u64>::default().from_matches(m, "from")?;
+        let to_opt: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "to", false)?;
+        let max_anomalies: usize =
+            FromStrParser::<usize>::default().from_matches(m, "max-anomalies")?;
+        let skip_pow = m.is_present("skip-pow");
+        let to_number = match to_opt {
+            Some(number) => number,
+            None => self.tip_header()?.number(),
+        };
+        if from_number > to_number {
+            return Err(format!(
+                "<from> {} is greater than <to> {}",
+                from_number, to_number
+            ));
+        }
+
+        // Timestamps of blocks before the range for the median time check
+        let mut timestamps = VecDeque::with_capacity(MEDIAN_TIME_BLOCKS);
+        let mut parent_opt = None;
+        for number in from_number.saturating_sub(MEDIAN_TIME_BLOCKS as u64)..from_number {
+            let header = self.header_by_number(number)?;
+            timestamps.push_back(header.timestamp());
+            parent_opt = Some(header);
+        }
+        let mut anomalies = Vec::new();
+        let mut checked = 0;
+        for number in from_number..=to_number {
+            let json_header = self
+                .rpc_client
+                .get_header_by_number(BlockNumber::from(number))
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .ok_or_else(|| format!("Header not found: {}", number))?;
+            // Calculate hash from header fields, not trusting the reported one
+            let header = Header::from(json_header.inner).into_view();
+            let claimed_hash = json_header.hash;
+            let hash: H256 = header.hash().unpack();
+            let mut errors = Vec::new();
+            if claimed_hash != hash {
+                errors.push(format!(
+                    "node reported hash {:#x}, calculated {:#x}",
+                    claimed_hash, hash
+                ));
+            }
+            if let Some(parent) = parent_opt.as_ref() {
+                if let Err(err) = verify_header(parent, &header, skip_pow) {
+                    errors.push(err);
+                }
+                if let Err(err) = verify_difficulty_adjustment(parent, &header) {
+                    errors.push(err);
+                }
+                let median = median_time(&timestamps);
+                if header.timestamp() <= median {
+                    errors.push(format!(
+                        "timestamp {} is not after median time {} of previous {} blocks",
+                        header.timestamp(),
+                        median,
+                        timestamps.len()
+                    ));
+                }
+            } else if header.number() != 0 {
+                errors.push("block number of first header is not 0".to_owned());
+            }
+            anomalies.extend(errors.into_iter().map(|error| {
+                serde_json::json!({
+                    "number": number,
+                    "hash": hash,
+                    "error": error,
+                })
+            }));
+            checked += 1;
+            if anomalies.len() >= max_anomalies {
+                eprintln!(
+                    "[WARNING]: Stopped at block #{}, reached max anomalies {}",
+                    number, max_anomalies
+                );
+                break;
+            }
+            if timestamps.len() == MEDIAN_TIME_BLOCKS {
+                timestamps.pop_front();
+            }
+            timestamps.push_back(header.timestamp());
+            parent_opt = Some(header);
+        }
+        Ok(serde_json::json!({
+            "from": from_number,
+            "to": to_number,
+            "checked": checked,
+            "valid": anomalies.is_empty(),
+            "anomalies": anomalies,
+        }))
+    }
+
+    fn verify_tx(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let tx_hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+        let mut store = self.header_store(&checkpoints_from_matches(m)?)?;
+        let (number, block_hash, warnings) = self.verify_inclusion(&mut store, &tx_hash)?;
+        for warning in &warnings {
+            eprintln!("[WARNING]: {}", warning);
+        }
+        Ok(serde_json::json!({
+            "tx_hash": tx_hash,
+            "block_number": number,
+            "block_hash": block_hash,
+            "verified": warnings.is_empty(),
+            "warnings": warnings,
+        }))
+    }
+
+    fn verify_balance(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let lock_hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "lock-hash")?;
+        let mut store = self.header_store(&checkpoints_from_matches(m)?)?;
+        let mut cells = Vec::new();
+        let mut page = 0;
+        loop {
+            let live_cells = self
+                .rpc_client
+                .get_live_cells_by_lock_hash(
+                    lock_hash.clone(),
+                    Uint64::from(page),
+                    Uint64::from(LIVE_CELLS_PAGE_SIZE),
+                    Some(false),
+                )
+                .call()
+                .map_err(|err| err.to_string())?
+                .0;
+            let count = live_cells.len() as u64;
+            cells.extend(live_cells);
+            if count < LIVE_CELLS_PAGE_SIZE {
+                break;
+            }
+            page += 1;
+        }
+
+        let mut claimed = 0u64;
+        let mut verified = 0u64;
+        let mut warnings = Vec::new();
+        // Transaction hash => verified output capacities
+        let mut verified_txs: HashMap<H256, Option<Vec<u64>>> = HashMap::new();
+        for cell in &cells {
+            let tx_hash = cell.created_by.tx_hash.clone();
+            let index = cell.created_by.index.value() as usize;
+            let capacity = cell.cell_output.capacity.value();
+            claimed = claimed.saturating_add(capacity);
+            if !verified_txs.contains_key(&tx_hash) {
+                let (_, _, tx_warnings) = self.verify_inclusion(&mut store, &tx_hash)?;
+                let outputs = if tx_warnings.is_empty() {
+                    self.verified_outputs(&tx_hash)?
+                } else {
+                    warnings.extend(tx_warnings);
+                    None
+                };
+                verified_txs.insert(tx_hash.clone(), outputs);
+            }
+            let outputs = match verified_txs[&tx_hash].as_ref() {
+                Some(outputs) => outputs,
+                None => continue,
+            };
+            match outputs.get(index) {
+                Some(output_capacity) if *output_capacity == capacity => verified += capacity,
+                Some(output_capacity) => warnings.push(format!(
+                    "Node claims cell {:#x}-{} has {} shannons, the transaction has {}",
+                    tx_hash, index, capacity, output_capacity
+                )),
+                None => warnings.push(format!(
+                    "Node claims cell {:#x}-{}, the transaction has no such output",
+                    tx_hash, index
+                )),
+            }
+        }
+        for warning in &warnings {
+            eprintln!("[WARNING]: {}", warning);
+        }
+        Ok(serde_json::json!({
+            "lock_hash": lock_hash,
+            "cells": cells.len(),
+            "claimed_capacity": claimed,
+            "verified_capacity": verified,
+            "verified": warnings.is_empty(),
+            "warnings": warnings,
+        }))
+    }
+
+    // Output capacities of the transaction, None if the node returns a
+    // transaction of another hash
+    fn verified_outputs(&mut self, tx_hash: &H256) -> Result<Option<Vec<u64>>, String> {
+        let tx = self
+            .rpc_client
+            .get_transaction(tx_hash.clone())
+            .call()
+            .map_err(|err| err.to_string())?
+            .0
+            .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?;
+        // Calculate hash from the transaction body, not trusting the reported one
+        let tx = Transaction::from(tx.transaction.inner).into_view();
+        let hash: H256 = tx.hash().unpack();
+        if &hash != tx_hash {
+            return Ok(None);
+        }
+        Ok(Some(
+            tx.outputs()
+                .into_iter()
+                .map(|output| output.capacity().unpack())
+                .collect(),
+        ))
+    }
+}
+
+impl<'a> CliSubCommand for ChainSubCommand<'a> {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        _debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        match matches.subcommand() {
+            ("sync-headers", Some(m)) => {
+                let resp = self.sync_headers(m)?;
+                Ok(Box::new(resp))
+            }
+            ("verify-header-chain", Some(m)) => {
+                let resp = self.verify_header_chain(m)?;
+                Ok(Box::new(resp))
+            }
+            ("verify-tx", Some(m)) => {
+                let resp = self.verify_tx(m)?;
+                Ok(Box::new(resp))
+            }
+            ("verify-balance", Some(m)) => {
+                let resp = self.verify_balance(m)?;
+                Ok(Box::new(resp))
+            }
+            ("calc-epoch", Some(m)) => {
+                let resp = self.calc_epoch(m)?;
+                Ok(Box::new(resp))
+            }
+            ("hashrate", Some(m)) => {
+                let resp = self.hashrate(m)?;
+                Ok(Box::new(resp))
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
+
+struct BlockTime {
+    start_number: u64,
+    blocks: u64,
+    // Milliseconds
+    duration: u64,
+    average: u64,
+}
+
+fn u256_to_f64(value: &U256) -> f64 {
+    format!("{:x}", value)
+        .chars()
+        .filter_map(|c| c.to_digit(16))
+        .fold(0f64, |acc, digit| acc * 16.0 + f64::from(digit))
+}
+
+fn format_hashrate(hashrate: f64) -> String {
+    let units = ["H/s", "KH/s", "MH/s", "GH/s", "TH/s", "PH/s", "EH/s"];
+    let mut value = hashrate;
+    let mut unit = 0;
+    while value >= 1000.0 && unit + 1 < units.len() {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.2} {}", value, units[unit])
+}
+
+/// Verify header against its parent: number, parent hash, epoch continuity,
+/// compact target can only change at epoch start, and PoW.
+pub fn verify_header(
+    parent: &HeaderView,
+    header: &HeaderView,
+    skip_pow: bool,
+) -> Result<(), String> {
+    if header.number() != parent.number() + 1 {
+        return Err(format!(
+            "number {} is not parent number {} + 1",
+            header.number(),
+            parent.number()
+        ));
+    }
+    if header.parent_hash() != parent.hash() {
+        return Err(format!(
+            "parent hash {:#x} != {:#x}",
+            header.parent_hash(),
+            parent.hash()
+        ));
+    }
+    let parent_epoch = parent.epoch();
+    let epoch = header.epoch();
+    let epoch_continued = if parent_epoch.index() + 1 == parent_epoch.length() {
+        epoch.number() == parent_epoch.number() + 1 && epoch.index() == 0
+    } else {
+        epoch.number() == parent_epoch.number()
+            && epoch.index() == parent_epoch.index() + 1
+            && epoch.length() == parent_epoch.length()
+    };
+    if !epoch_continued {
+        return Err(format!(
+            "epoch {}/{}/{} does not follow parent epoch {}/{}/{}",
+            epoch.number(),
+            epoch.index(),
+            epoch.length(),
+            parent_epoch.number(),
+            parent_epoch.index(),
+            parent_epoch.length()
+        ));
+    }
+    if epoch.number() == parent_epoch.number() && header.compact_target() != parent.compact_target()
+    {
+        return Err(format!(
+            "compact target changed inside epoch {}: {:#x} => {:#x}",
+            epoch.number(),
+            parent.compact_target(),
+            header.compact_target()
+        ));
+    }
+    if !skip_pow && !EaglesongPowEngine.verify(&header.data()) {
+        return Err("invalid PoW".to_owned());
+    }
+    Ok(())
+}
+
+/// Difficulty of a new epoch is adjusted from the last epoch by at most
+/// `DIFFICULTY_ADJUST_LIMIT` times up or down.
+pub fn verify_difficulty_adjustment(
+    parent: &HeaderView,
+    header: &HeaderView,
+) -> Result<(), String> {
+    if header.epoch().number() == parent.epoch().number() {
+        return Ok(());
+    }
+    let last = u256_to_f64(&compact_to_difficulty(parent.compact_target()));
+    let next = u256_to_f64(&compact_to_difficulty(header.compact_target()));
+    // Tolerance of compact target rounding
+    let limit = DIFFICULTY_ADJUST_LIMIT * 1.001;
+    if next > last * limit || next * limit < last {
+        return Err(format!(
+            "difficulty adjusted from {:.0} to {:.0} at epoch {}, more than {} times",
+            last,
+            next,
+            header.epoch().number(),
+            DIFFICULTY_ADJUST_LIMIT
+        ));
+    }
+    Ok(())
+}
+
+fn parse_checkpoint(input: &str) -> Result<(u64, H256), String> {
+    let mut parts = input.splitn(2, ':');
+    let number = parts
+        .next()
+        .unwrap()
+        .parse::<u64>()
+        .map_err(|err| format!("Invalid checkpoint block number {}: {}", input, err))?;
+    let hash = parts
+        .next()
+        .ok_or_else(|| format!("Invalid checkpoint {}, format: {{number}}:{{hash}}", input))
+        .and_then(|hash| FixedHashParser::<H256>::default().parse(hash))?;
+    Ok((number, hash))
+}
+
+fn checkpoints_from_matches(m: &ArgMatches) -> Result<Vec<(u64, H256)>, String> {
+    m.values_of("checkpoint")
+        .map(|values| values.map(parse_checkpoint).collect())
+        .unwrap_or_else(|| Ok(Vec::new()))
+}
+
+fn median_time(timestamps: &VecDeque<u64>) -> u64 {
+    let mut sorted = timestamps.iter().cloned().collect::<Vec<_>>();
+    sorted.sort();
+    sorted.get(sorted.len() / 2).cloned().unwrap_or(0)
+}
+
+// Block timestamp must be later than median time of this many previous blocks
+const MEDIAN_TIME_BLOCKS: usize = 37;
+// Max times of difficulty change between two epochs (TAU in consensus)
+const DIFFICULTY_ADJUST_LIMIT: f64 = 2.0;
+const LIVE_CELLS_PAGE_SIZE: u64 = 50;
+
+// Verified headers are saved in one file (per chain) as fixed size molecule
+// bytes, header of block number N is at offset N * Header::TOTAL_SIZE.
+struct HeaderStore {
+    path: PathBuf,
+    file: fs::File,
+}
+
+impl HeaderStore {
+    fn path(headers_dir: &PathBuf, genesis: &HeaderView) -> PathBuf {
+        let mut path = headers_dir.clone();
+        path.push(format!("{:x}", genesis.hash()));
+        path
+    }
+
+    fn exists(headers_dir: &PathBuf, genesis: &HeaderView) -> bool {
+        HeaderStore::path(headers_dir, genesis).exists()
+    }
+
+    fn open(headers_dir: &PathBuf, genesis: HeaderView) -> Result<HeaderStore, String> {
+        fs::create_dir_all(headers_dir).map_err(|err| err.to_string())?;
+        let path = HeaderStore::path(headers_dir, &genesis);
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&path)
+            .map_err(|err| err.to_string())?;
+        let mut store = HeaderStore { path, file };
+        if store.len()? == 0 {
+            store.append(&genesis)?;
+        }
+        Ok(store)
+    }
+
+    fn len(&self) -> Result<u64, String> {
+        let size = self.file.metadata().map_err(|err| err.to_string())?.len();
+        Ok(size / Header::TOTAL_SIZE as u64)
+    }
+
+    fn tip(&mut self) -> Result<HeaderView, String> {
+        let len = self.len()?;
+        self.get(len - 1)?
+            .ok_or_else(|| "Local header chain is empty".to_owned())
+    }
+
+    fn get(&mut self, number: u64) -> Result<Option<HeaderView>, String> {
+        if number >= self.len()? {
+            return Ok(None);
+        }
+        let mut data = vec![0u8; Header::TOTAL_SIZE];
+        self.file
+            .seek(SeekFrom::Start(number * Header::TOTAL_SIZE as u64))
+            .and_then(|_| self.file.read_exact(&mut data))
+            .map_err(|err| err.to_string())?;
+        Header::from_slice(&data)
+            .map(|header| Some(header.into_view()))
+            .map_err(|err| format!("Local header chain corrupted: {}", err))
+    }
+
+    fn append(&mut self, header: &HeaderView) -> Result<(), String> {
+        let offset = self.len()? * Header::TOTAL_SIZE as u64;
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| self.file.write_all(header.data().as_slice()))
+            .map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,412 @@
+use std::path::PathBuf;
+
+use ckb_hash::blake2b_256;
+use ckb_jsonrpc_types::{BlockNumber, BlockTemplate, CellWithStatus};
+use ckb_sdk::{
+    build_witness_with_key, Address, GenesisInfo, HttpRpcClient, NetworkType,
+    TransferTransactionBuilder, MIN_SECP_CELL_CAPACITY, SECP256K1,
+};
+use ckb_types::{
+    bytes::Bytes,
+    core::{BlockBuilder, BlockView, Capacity},
+    packed::{self, CellInput, OutPoint},
+    prelude::*,
+    H256,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use faster_hex::hex_string;
+use rand::Rng;
+
+use super::CliSubCommand;
+use crate::utils::{
+    arg,
+    arg_parser::{
+        ArgParser, CapacityParser, FilePathParser, FromStrParser, PrivkeyPathParser, PrivkeyWrapper,
+    },
+    other::{get_genesis_info, get_network_type, write_private_file},
+    printer::Printable,
+};
+
+// get_cells_by_lock_hash accepts at most 100 blocks per request
+const CELLS_SCAN_BLOCKS: u64 = 100;
+// Stop mining if the seeding transaction is still not committed
+const MAX_MINE_BLOCKS: u64 = 20;
+
+/// Helpers for dev chain (the chain spec `ckb_dev` with dummy PoW)
+pub struct DevSubCommand<'a> {
+    rpc_client: &'a mut HttpRpcClient,
+    genesis_info: Option<GenesisInfo>,
+}
+
+impl<'a> DevSubCommand<'a> {
+    pub fn new(
+        rpc_client: &'a mut HttpRpcClient,
+        genesis_info: Option<GenesisInfo>,
+    ) -> DevSubCommand<'a> {
+        DevSubCommand {
+            rpc_client,
+            genesis_info,
+        }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        SubCommand::with_name(name)
+            .about("Dev chain helpers (only work on ckb_dev chain)")
+            .subcommands(vec![
+                SubCommand::with_name("mine")
+                    .about("Mine blocks by get_block_template and submit_block (dummy PoW)")
+                    .arg(
+                        Arg::with_name("blocks")
+                            .long("blocks")
+                            .takes_value(true)
+                            .default_value("1")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Number of blocks to mine"),
+                    ),
+                SubCommand::with_name("seed")
+                    .about("Create funded accounts with cells from genesis issued cells, write the keys to a fixture file, then mine until the cells are committed")
+                    .arg(
+                        arg::privkey_path()
+                            .required(true)
+                            .help("Private key file of the genesis issued cells"),
+                    )
+                    .arg(
+                        Arg::with_name("accounts")
+                            .long("accounts")
+                            .takes_value(true)
+                            .default_value("10")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Number of accounts to create"),
+                    )
+                    .arg(
+                        Arg::with_name("cells-each")
+                            .long("cells-each")
+                            .takes_value(true)
+                            .default_value("5")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Number of cells of each account"),
+                    )
+                    .arg(
+                        Arg::with_name("cell-capacity")
+                            .long("cell-capacity")
+                            .takes_value(true)
+                            .default_value("1000")
+                            .validator(|input| CapacityParser.validate(input))
+                            .help("Capacity of each cell (unit: CKB, format: 123.335)"),
+                    )
+                    .arg(
+                        arg::tx_fee()
+                            .default_value("0.01")
+                            .help("Fee of the seeding transaction (unit: CKB, format: 0.01)"),
+                    )
+                    .arg(
+                        Arg::with_name("fixture")
+                            .long("fixture")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output JSON file of the created accounts (private keys, addresses and cells)"),
+                    )
+                    .arg(
+                        Arg::with_name("mine-blocks")
+                            .long("mine-blocks")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Extra blocks to mine after the seeding transaction is committed"),
+                    )
+                    .arg(arg::force()),
+            ])
+    }
+
+    fn check_dev_chain(&mut self) -> Result<(), String> {
+        match get_network_type(self.rpc_client) {
+            Ok(NetworkType::Dev) => Ok(()),
+            Ok(network_type) => Err(format!(
+                "Dev commands only work on ckb_dev chain, connected chain: {}",
+                network_type.to_str()
+            )),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Mine one block by the template of the node, dummy PoW accepts any nonce
+    fn mine_block(&mut self) -> Result<H256, String> {
+        let template: BlockTemplate = self
+            .rpc_client
+            .get_block_template(None, None, None)
+            .call()
+            .map_err(|err| format!("Send get_block_template error: {}", err))?;
+        let work_id = template.work_id.value().to_string();
+        let block = block_from_template(template);
+        self.rpc_client
+            .submit_block(work_id, block.data().into())
+            .call()
+            .map_err(|err| format!("Send submit_block error: {}", err))?
+            .0
+            .ok_or_else(|| "Block is not accepted by the node".to_owned())
+    }
+
+    // Live cells without type script and data of the lock
+    fn secp_cells(
+        &mut self,
+        lock_hash: H256,
+        capacity: u64,
+    ) -> Result<(Vec<CellInput>, u64), String> {
+        let tip_number = self
+            .rpc_client
+            .get_tip_block_number()
+            .call()
+            .map_err(|err| format!("Send get_tip_block_number error: {}", err))?
+            .value();
+        let mut inputs = Vec::new();
+        let mut total_capacity = 0;
+        let mut from = 0;
+        while from <= tip_number && total_capacity < capacity {
+            let to = (from + CELLS_SCAN_BLOCKS - 1).min(tip_number);
+            let cells = self
+                .rpc_client
+                .get_cells_by_lock_hash(
+                    lock_hash.clone(),
+                    BlockNumber::from(from),
+                    BlockNumber::from(to),
+                )
+                .call()
+                .map_err(|err| format!("Send get_cells_by_lock_hash error: {}", err))?
+                .0;
+            for cell in cells {
+                if total_capacity >= capacity {
+                    break;
+                }
+                let resp: CellWithStatus = self
+                    .rpc_client
+                    .get_live_cell(cell.out_point.clone(), true)
+                    .call()
+                    .map_err(|err| format!("Send get_live_cell error: {}", err))?;
+                let is_secp_cell = resp
+                    .cell
+                    .as_ref()
+                    .map(|info| {
+                        info.output.type_.is_none()
+                            && info
+                                .data
+                                .as_ref()
+                                .map(|data| data.content.as_bytes().is_empty())
+                                .unwrap_or(true)
+                    })
+                    .unwrap_or(false);
+                if resp.status != "live" || !is_secp_cell {
+                    continue;
+                }
+                total_capacity += cell.capacity.value();
+                let out_point: OutPoint = cell.out_point.into();
+                inputs.push(CellInput::new(out_point, 0));
+            }
+            from = to + 1;
+        }
+        Ok((inputs, total_capacity))
+    }
+
+    fn seed(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let privkey: PrivkeyWrapper = PrivkeyPathParser.from_matches(m, "privkey-path")?;
+        let accounts: usize = FromStrParser::<usize>::default().from_matches(m, "accounts")?;
+        let cells_each: usize = FromStrParser::<usize>::default().from_matches(m, "cells-each")?;
+        let cell_capacity: u64 = CapacityParser.from_matches(m, "cell-capacity")?;
+        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let fixture: PathBuf = FilePathParser::new(false).from_matches(m, "fixture")?;
+        let mine_blocks: u64 = FromStrParser::<u64>::default().from_matches(m, "mine-blocks")?;
+        if accounts == 0 || cells_each == 0 {
+            return Err("accounts and cells-each must be greater than 0".to_owned());
+        }
+        if cell_capacity < *MIN_SECP_CELL_CAPACITY {
+            return Err(format!(
+                "cell-capacity can not less than {} shannons",
+                *MIN_SECP_CELL_CAPACITY
+            ));
+        }
+        if fixture.exists() && !m.is_present("force") {
+            return Err(format!(
+                "Fixture file already exists: {}, use --force to overwrite",
+                fixture.to_string_lossy()
+            ));
+        }
+        self.check_dev_chain()?;
+        let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+        let secp_type_hash = genesis_info.secp_type_hash();
+
+        let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+        let from_address =
+            Address::from_lock_arg(&blake2b_256(&from_pubkey.serialize()[..])[0..20])?;
+        let from_lock_hash: H256 = from_address
+            .lock_script(secp_type_hash.clone())
+            .calc_script_hash()
+            .unpack();
+
+        let keys = (0..accounts)
+            .map(|_| loop {
+                let data: [u8; 32] = rand::thread_rng().gen();
+                if let Ok(privkey) = secp256k1::SecretKey::from_slice(&data[..]) {
+                    break privkey;
+                }
+            })
+            .collect::<Vec<_>>();
+        let addresses = keys
+            .iter()
+            .map(|key| {
+                let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, key);
+                Address::from_lock_arg(&blake2b_256(&pubkey.serialize()[..])[0..20])
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let mut outputs = Vec::with_capacity(accounts * cells_each);
+        for address in &addresses {
+            for _ in 0..cells_each {
+                let output = packed::CellOutput::new_builder()
+                    .capacity(Capacity::shannons(cell_capacity).pack())
+                    .lock(address.lock_script(secp_type_hash.clone()))
+                    .build();
+                outputs.push((output, Bytes::new()));
+            }
+        }
+        let capacity = cell_capacity * outputs.len() as u64;
+        let (inputs, total_capacity) = self.secp_cells(from_lock_hash, capacity + tx_fee)?;
+        if total_capacity < capacity + tx_fee {
+            return Err(format!(
+                "Capacity not enough: {} => {}",
+                from_address.to_string(NetworkType::Dev),
+                total_capacity
+            ));
+        }
+        let to_data = Bytes::new();
+        let mut tx_args = TransferTransactionBuilder::new(
+            &from_address,
+            total_capacity,
+            &to_data,
+            &from_address,
+            capacity,
+            tx_fee,
+            inputs,
+        );
+        tx_args.set_outputs(outputs);
+        let transaction = tx_args.transfer(&genesis_info, |args| {
+            Ok(build_witness_with_key(&privkey, args))
+        })?;
+        let tx_hash = self
+            .rpc_client
+            .send_transaction(transaction.data().into())
+            .call()
+            .map_err(|err| format!("Send transaction error: {}", err))?;
+
+        // Proposed in one block, committed after the proposal window
+        let mut mined = 0;
+        loop {
+            let status = self
+                .rpc_client
+                .get_transaction(tx_hash.clone())
+                .call()
+                .map_err(|err| format!("Send get_transaction error: {}", err))?
+                .0
+                .map(|tx| tx.tx_status.status);
+            if status == Some(ckb_jsonrpc_types::Status::Committed) {
+                break;
+            }
+            if mined >= MAX_MINE_BLOCKS {
+                return Err(format!(
+                    "Seeding transaction {:#x} is not committed after {} blocks",
+                    tx_hash, mined
+                ));
+            }
+            self.mine_block()?;
+            mined += 1;
+        }
+        for _ in 0..mine_blocks {
+            self.mine_block()?;
+            mined += 1;
+        }
+
+        let fixture_accounts = keys
+            .iter()
+            .zip(addresses.iter())
+            .enumerate()
+            .map(|(account_index, (key, address))| {
+                let cells = (0..cells_each)
+                    .map(|cell_index| {
+                        serde_json::json!({
+                            "tx_hash": tx_hash,
+                            "index": account_index * cells_each + cell_index,
+                            "capacity": cell_capacity,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::json!({
+                    "privkey": hex_string(&key[..]).expect("encode privkey failed"),
+                    "lock_arg": address.hash(),
+                    "address": address.to_string(NetworkType::Dev),
+                    "cells": cells,
+                })
+            })
+            .collect::<Vec<_>>();
+        let content = serde_json::to_string_pretty(&serde_json::json!({
+            "tx_hash": tx_hash,
+            "accounts": fixture_accounts,
+        }))
+        .map_err(|err| err.to_string())?;
+        write_private_file(&fixture, content.as_bytes(), true)?;
+
+        let resp = serde_json::json!({
+            "tx_hash": tx_hash,
+            "accounts": accounts,
+            "cells": accounts * cells_each,
+            "mined_blocks": mined,
+            "fixture": fixture.to_string_lossy(),
+        });
+        Ok(Box::new(resp))
+    }
+}
+
+impl<'a> CliSubCommand for DevSubCommand<'a> {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        _debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        match matches.subcommand() {
+            ("mine", Some(m)) => {
+                let blocks: u64 = FromStrParser::<u64>::default().from_matches(m, "blocks")?;
+                self.check_dev_chain()?;
+                let hashes = (0..blocks)
+                    .map(|_| self.mine_block())
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(Box::new(serde_json::json!({ "blocks": hashes })))
+            }
+            ("seed", Some(m)) => self.seed(m),
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
+
+// Uncles are not included, transactions root, proposals hash and uncles
+// hash are calculated by the builder
+fn block_from_template(template: BlockTemplate) -> BlockView {
+    let cellbase: packed::Transaction = template.cellbase.data.into();
+    let transactions = template.transactions.into_iter().map(|tx| {
+        let tx: packed::Transaction = tx.data.into();
+        tx.into_view()
+    });
+    let proposals = template
+        .proposals
+        .into_iter()
+        .map(packed::ProposalShortId::from)
+        .collect::<Vec<_>>();
+    BlockBuilder::default()
+        .version(template.version.value().pack())
+        .compact_target(template.compact_target.value().pack())
+        .timestamp(template.current_time.value().pack())
+        .number(template.number.value().pack())
+        .epoch(template.epoch.value().pack())
+        .parent_hash(template.parent_hash.pack())
+        .dao(template.dao.into())
+        .transaction(cellbase.into_view())
+        .transactions(transactions.collect::<Vec<_>>())
+        .proposals(proposals)
+        .build()
+}
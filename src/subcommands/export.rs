@@ -0,0 +1,368 @@
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use ckb_jsonrpc_types::BlockNumber;
+use ckb_sdk::HttpRpcClient;
+use ckb_types::{
+    core::{BlockView, Capacity},
+    prelude::*,
+    H256,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_derive::{Deserialize, Serialize};
+
+use super::CliSubCommand;
+use crate::utils::{
+    arg_parser::{ArgParser, FilePathParser, FromStrParser},
+    printer::Printable,
+};
+
+pub struct ExportSubCommand<'a> {
+    rpc_client: &'a mut HttpRpcClient,
+}
+
+impl<'a> ExportSubCommand<'a> {
+    pub fn new(rpc_client: &'a mut HttpRpcClient) -> ExportSubCommand<'a> {
+        ExportSubCommand { rpc_client }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        SubCommand::with_name(name)
+            .about("Export chain data (resumable)")
+            .subcommands(vec![SubCommand::with_name("blocks")
+                .about("Export headers, transactions or cell changes over a range of blocks")
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| FromStrParser::<u64>::default().validate(input))
+                        .help("From block number (inclusive)"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| FromStrParser::<u64>::default().validate(input))
+                        .help("To block number (inclusive)"),
+                )
+                .arg(
+                    Arg::with_name("data")
+                        .long("data")
+                        .takes_value(true)
+                        .possible_values(&["headers", "transactions", "cells"])
+                        .default_value("headers")
+                        .help("Which data to export"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["ndjson", "csv"])
+                        .default_value("ndjson")
+                        .help("Output file format"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| FilePathParser::new(false).validate(input))
+                        .help("Output file path (checkpoint is saved to <output>.checkpoint)"),
+                )
+                .arg(
+                    Arg::with_name("interval")
+                        .long("interval")
+                        .takes_value(true)
+                        .default_value("0")
+                        .validator(|input| FromStrParser::<u64>::default().validate(input))
+                        .help("Sleep milliseconds between block requests (throttle the node)"),
+                )])
+    }
+
+    fn export_blocks(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let from: u64 = FromStrParser::<u64>::default().from_matches(m, "from")?;
+        let to: u64 = FromStrParser::<u64>::default().from_matches(m, "to")?;
+        let data = m.value_of("data").unwrap().to_owned();
+        let format = m.value_of("format").unwrap().to_owned();
+        let output: PathBuf = FilePathParser::new(false).from_matches(m, "output")?;
+        let interval: u64 = FromStrParser::<u64>::default().from_matches(m, "interval")?;
+        if from > to {
+            return Err(format!("Invalid range: from({}) > to({})", from, to));
+        }
+
+        let mut checkpoint_path = output.clone().into_os_string();
+        checkpoint_path.push(".checkpoint");
+        let checkpoint_path = PathBuf::from(checkpoint_path);
+        let mut checkpoint = Checkpoint {
+            from,
+            to,
+            data,
+            format,
+            next: from,
+            offset: 0,
+        };
+        let mut file = if checkpoint_path.exists() {
+            let saved = Checkpoint::load(&checkpoint_path)?;
+            if !saved.same_task(&checkpoint) {
+                return Err(format!(
+                    "Checkpoint file {:?} belongs to another export task, remove it to start over",
+                    checkpoint_path
+                ));
+            }
+            checkpoint = saved;
+            // Drop anything written after last checkpoint
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .open(&output)
+                .map_err(|err| err.to_string())?;
+            file.set_len(checkpoint.offset)
+                .map_err(|err| err.to_string())?;
+            file
+        } else {
+            if output.exists() {
+                return Err(format!("Output file exists: {:?}", output));
+            }
+            let mut file = fs::File::create(&output).map_err(|err| err.to_string())?;
+            if checkpoint.format == "csv" {
+                writeln!(file, "{}", csv_header(&checkpoint.data))
+                    .map_err(|err| err.to_string())?;
+            }
+            checkpoint.offset = file_len(&file)?;
+            checkpoint.save(&checkpoint_path)?;
+            file
+        };
+        file.seek(SeekFrom::End(0)).map_err(|err| err.to_string())?;
+
+        let resumed_from = checkpoint.next;
+        let mut records = 0;
+        while checkpoint.next <= checkpoint.to {
+            let number = checkpoint.next;
+            let block: BlockView = self
+                .rpc_client
+                .get_block_by_number(BlockNumber::from(number))
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .ok_or_else(|| format!("Block not found: {}", number))?
+                .into();
+            for record in block_records(&block, &checkpoint.data) {
+                let line = if checkpoint.format == "csv" {
+                    csv_line(&record, &checkpoint.data)
+                } else {
+                    serde_json::to_string(&record).map_err(|err| err.to_string())?
+                };
+                writeln!(file, "{}", line).map_err(|err| err.to_string())?;
+                records += 1;
+            }
+            file.flush().map_err(|err| err.to_string())?;
+            checkpoint.next = number + 1;
+            checkpoint.offset = file_len(&file)?;
+            checkpoint.save(&checkpoint_path)?;
+            if interval > 0 && checkpoint.next <= checkpoint.to {
+                thread::sleep(Duration::from_millis(interval));
+            }
+        }
+        fs::remove_file(&checkpoint_path).map_err(|err| err.to_string())?;
+        Ok(serde_json::json!({
+            "output": output.to_string_lossy(),
+            "resumed_from": resumed_from,
+            "to": checkpoint.to,
+            "records": records,
+        }))
+    }
+}
+
+impl<'a> CliSubCommand for ExportSubCommand<'a> {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        _debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        match matches.subcommand() {
+            ("blocks", Some(m)) => {
+                let resp = self.export_blocks(m)?;
+                Ok(Box::new(resp))
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    from: u64,
+    to: u64,
+    data: String,
+    format: String,
+    // Next block number to export
+    next: u64,
+    // Output file length after last exported block
+    offset: u64,
+}
+
+impl Checkpoint {
+    fn load(path: &Path) -> Result<Checkpoint, String> {
+        let mut content = String::new();
+        fs::File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut content))
+            .map_err(|err| err.to_string())?;
+        serde_json::from_str(&content).map_err(|err| format!("Invalid checkpoint file: {}", err))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string(self).map_err(|err| err.to_string())?;
+        fs::write(path, content).map_err(|err| err.to_string())
+    }
+
+    fn same_task(&self, other: &Checkpoint) -> bool {
+        self.from == other.from
+            && self.to == other.to
+            && self.data == other.data
+            && self.format == other.format
+    }
+}
+
+fn file_len(file: &fs::File) -> Result<u64, String> {
+    file.metadata()
+        .map(|metadata| metadata.len())
+        .map_err(|err| err.to_string())
+}
+
+fn csv_columns(data: &str) -> &'static [&'static str] {
+    match data {
+        "headers" => &[
+            "number",
+            "hash",
+            "parent_hash",
+            "timestamp",
+            "epoch",
+            "transactions",
+        ],
+        "transactions" => &[
+            "block_number",
+            "index",
+            "tx_hash",
+            "inputs",
+            "outputs",
+            "outputs_capacity",
+        ],
+        "cells" => &[
+            "block_number",
+            "tx_hash",
+            "change",
+            "out_point_tx_hash",
+            "out_point_index",
+            "capacity",
+            "lock_hash",
+            "type_hash",
+        ],
+        _ => panic!("Invalid export data: {}", data),
+    }
+}
+
+fn csv_header(data: &str) -> String {
+    csv_columns(data).join(",")
+}
+
+fn csv_line(record: &serde_json::Value, data: &str) -> String {
+    csv_columns(data)
+        .iter()
+        .map(|column| match &record[*column] {
+            serde_json::Value::Null => String::new(),
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn block_records(block: &BlockView, data: &str) -> Vec<serde_json::Value> {
+    let header = block.header();
+    let number = header.number();
+    match data {
+        "headers" => {
+            let epoch = header.epoch();
+            let hash: H256 = header.hash().unpack();
+            let parent_hash: H256 = header.parent_hash().unpack();
+            vec![serde_json::json!({
+                "number": number,
+                "hash": hash,
+                "parent_hash": parent_hash,
+                "timestamp": header.timestamp(),
+                "epoch": format!("{}/{}/{}", epoch.number(), epoch.index(), epoch.length()),
+                "transactions": block.transactions().len(),
+            })]
+        }
+        "transactions" => block
+            .transactions()
+            .iter()
+            .enumerate()
+            .map(|(index, tx)| {
+                let tx_hash: H256 = tx.hash().unpack();
+                let outputs_capacity = tx
+                    .outputs()
+                    .into_iter()
+                    .map(|output| {
+                        let capacity: Capacity = output.capacity().unpack();
+                        capacity.as_u64()
+                    })
+                    .sum::<u64>();
+                serde_json::json!({
+                    "block_number": number,
+                    "index": index,
+                    "tx_hash": tx_hash,
+                    "inputs": tx.inputs().len(),
+                    "outputs": tx.outputs().len(),
+                    "outputs_capacity": outputs_capacity,
+                })
+            })
+            .collect(),
+        "cells" => {
+            let mut records = Vec::new();
+            for tx in block.transactions() {
+                let tx_hash: H256 = tx.hash().unpack();
+                for out_point in tx
+                    .inputs()
+                    .into_iter()
+                    .map(|input| input.previous_output())
+                    .filter(|out_point| !out_point.is_null())
+                {
+                    let out_point_tx_hash: H256 = out_point.tx_hash().unpack();
+                    let out_point_index: u32 = out_point.index().unpack();
+                    records.push(serde_json::json!({
+                        "block_number": number,
+                        "tx_hash": tx_hash,
+                        "change": "consumed",
+                        "out_point_tx_hash": out_point_tx_hash,
+                        "out_point_index": out_point_index,
+                    }));
+                }
+                for (index, output) in tx.outputs().into_iter().enumerate() {
+                    let capacity: Capacity = output.capacity().unpack();
+                    let lock_hash: H256 = output.lock().calc_script_hash().unpack();
+                    let type_hash: Option<H256> = output
+                        .type_()
+                        .to_opt()
+                        .map(|type_script| type_script.calc_script_hash().unpack());
+                    records.push(serde_json::json!({
+                        "block_number": number,
+                        "tx_hash": tx_hash,
+                        "change": "created",
+                        "out_point_tx_hash": tx_hash,
+                        "out_point_index": index,
+                        "capacity": capacity.as_u64(),
+                        "lock_hash": lock_hash,
+                        "type_hash": type_hash,
+                    }));
+                }
+            }
+            records
+        }
+        _ => panic!("Invalid export data: {}", data),
+    }
+}
@@ -1,34 +1,57 @@
 use std::fs;
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
+use ckb_jsonrpc_types::{CellWithStatus, Status};
 use ckb_sdk::{
-    wallet::KeyStore, GenesisInfo, HttpRpcClient, MockCellDep, MockInfo, MockInput,
-    MockResourceLoader, MockTransaction, MockTransactionHelper, ReprMockTransaction,
+    is_unsupported_method, multisig_config, omnilock_witness_templates, wallet::KeyStore, Address,
+    FeeEstimate, GenesisInfo, HttpRpcClient, MockCellDep, MockInfo, MockInput, MockResourceLoader,
+    MockTransaction, MockTransactionHelper, ReprMockTransaction, TxFinding, WitnessField,
+    WitnessTemplate,
 };
 use ckb_types::{
     bytes::Bytes,
     core::{
-        capacity_bytes, Capacity, HeaderBuilder, HeaderView, ScriptHashType, TransactionBuilder,
+        capacity_bytes, Capacity, DepType, HeaderBuilder, HeaderView, ScriptHashType,
+        TransactionBuilder,
     },
     h256,
-    packed::{CellDep, CellInput, CellOutput, OutPoint, Script},
+    packed::{self, CellDep, CellInput, CellOutput, OutPoint, OutPointVec, Script, WitnessArgs},
     prelude::*,
     H160, H256,
 };
-use clap::{App, Arg, ArgMatches, SubCommand};
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+use rand::Rng;
+use serde_derive::{Deserialize, Serialize};
 
 use super::CliSubCommand;
 use crate::utils::{
-    arg_parser::{ArgParser, FilePathParser, FixedHashParser},
+    arg_parser::{
+        AddressParser, ArgParser, CapacityParser, DirPathParser, FilePathParser, FixedHashParser,
+        FromStrParser, HexParser, OutPointParser,
+    },
+    finding::{count_errors, render_findings, FindingFormat},
+    i18n::tr,
+    key_usage::record_key_usage,
     other::{get_genesis_info, get_singer},
-    printer::{OutputFormat, Printable},
+    printer::{format_capacity, OutputFormat, Printable, Text},
+    serve_ceremony::recover_signer,
+    sign_mode::SignModes,
+    since::{ChainPoint, Since},
 };
 
+// Default max_tx_verify_cycles of the node tx pool
+const MAX_TX_VERIFY_CYCLES: u64 = 70_000_000;
+
 pub struct MockTxSubCommand<'a> {
     rpc_client: &'a mut HttpRpcClient,
     key_store: &'a mut KeyStore,
     genesis_info: Option<GenesisInfo>,
+    witness_templates_file: PathBuf,
+    sign_modes_file: PathBuf,
+    // Colorize the transaction printed to stdout
+    color: bool,
 }
 
 impl<'a> MockTxSubCommand<'a> {
@@ -36,11 +59,17 @@ impl<'a> MockTxSubCommand<'a> {
         rpc_client: &'a mut HttpRpcClient,
         key_store: &'a mut KeyStore,
         genesis_info: Option<GenesisInfo>,
+        witness_templates_file: PathBuf,
+        sign_modes_file: PathBuf,
+        color: bool,
     ) -> MockTxSubCommand<'a> {
         MockTxSubCommand {
             rpc_client,
             key_store,
             genesis_info,
+            witness_templates_file,
+            sign_modes_file,
+            color,
         }
     }
 
@@ -49,19 +78,39 @@ impl<'a> MockTxSubCommand<'a> {
             .long("tx-file")
             .takes_value(true)
             .required(true)
-            .validator(|input| FilePathParser::new(true).validate(input))
-            .help("Mock transaction data file (format: json)");
+            .validator(|input| resolve_tx_file(&input).map(|_| ()))
+            .help("Mock transaction data file (format: json), or the draft id or tx hash of a tx file in the current directory");
+        let arg_index = Arg::with_name("index")
+            .long("index")
+            .takes_value(true)
+            .required(true)
+            .validator(|input| FromStrParser::<usize>::default().validate(input));
+        let arg_remove = Arg::with_name("remove")
+            .long("remove")
+            .help("Remove it instead of replacing");
         let arg_output_file = Arg::with_name("output-file")
             .long("output-file")
             .takes_value(true)
             .validator(|input| FilePathParser::new(false).validate(input))
             .help("Completed mock transaction data file (format: json)");
+        let arg_findings_format = Arg::with_name("format")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&["report", "jsonl", "github"])
+            .default_value("report")
+            .help("Print findings as the normal report, one json object per line (jsonl) or GitHub Actions annotations (github), non-report formats exit with error when any error found");
         let arg_lock_arg = Arg::with_name("lock-arg")
             .long("lock-arg")
             .takes_value(true)
             .validator(|input| FixedHashParser::<H160>::default().validate(input))
             .required(true)
             .help("The lock_arg (identifier) of the account");
+        let arg_code_hash = Arg::with_name("code-hash")
+            .long("code-hash")
+            .takes_value(true)
+            .required(true)
+            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+            .help("Lock script code hash");
         SubCommand::with_name(name)
             .about("Handle mock transactions (verify/send)")
             .subcommands(vec![
@@ -69,20 +118,265 @@ impl<'a> MockTxSubCommand<'a> {
                     .about("Print mock transaction template")
                     .arg(arg_lock_arg.clone().required(false))
                     .arg(arg_output_file.clone().help("Save to a output file")),
+                SubCommand::with_name("from-rpc")
+                    .about("Import a committed or pending transaction from the node as a mock transaction, to use it as template or re-broadcast it")
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .long("tx-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Transaction hash"),
+                    )
+                    .arg(
+                        Arg::with_name("strip-witnesses")
+                            .long("strip-witnesses")
+                            .help("Remove witnesses (re-fill them by `mock-tx complete`)"),
+                    )
+                    .arg(arg_output_file.clone().help("Save to a output file")),
                 SubCommand::with_name("complete")
                     .about("Complete the mock transaction")
                     .arg(arg_tx_file.clone())
+                    .arg(
+                        Arg::with_name("fee-rate")
+                            .long("fee-rate")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Pay fee of this rate (shannons/KB) from the change output, size is estimated with final witnesses of sighash, multisig (the witness must contain the multisig config) and witness template locks"),
+                    )
                     .arg(
                         arg_output_file
                             .clone()
                             .help("Completed mock transaction data file (format: json)"),
                     ),
                 SubCommand::with_name("verify")
-                    .about("Verify a mock transaction in local")
-                    .arg(arg_tx_file.clone()),
+                    .about("Verify a mock transaction, cycles are estimated by the node (estimate_cycles RPC) or by local execution when the node lacks the method")
+                    .arg(arg_tx_file.clone())
+                    .arg(arg_findings_format.clone()),
                 SubCommand::with_name("send")
                     .about("Complete then send a transaction")
+                    .arg(arg_tx_file.clone())
+                    .arg(
+                        Arg::with_name("ignore-expiry")
+                            .long("ignore-expiry")
+                            .help("Send the transaction even if the draft is expired"),
+                    ),
+                SubCommand::with_name("set-draft-id")
+                    .about("Set the stable id of a mock transaction draft (saved in the tx file), it is kept when the content and tx hash change and can be used in place of --tx-file")
+                    .arg(arg_tx_file.clone())
+                    .arg(
+                        Arg::with_name("draft-id")
+                            .long("draft-id")
+                            .takes_value(true)
+                            .validator(|input| validate_draft_id(&input))
+                            .help("Draft id (label), a random one is generated if not given"),
+                    ),
+                SubCommand::with_name("set-expiry")
+                    .about("Attach an expiry to a mock transaction draft (saved in the tx file), expired drafts are refused by `mock-tx send`")
+                    .arg(arg_tx_file.clone())
+                    .arg(
+                        Arg::with_name("block-number")
+                            .long("block-number")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Expired when the tip block number reaches this number"),
+                    )
+                    .arg(
+                        Arg::with_name("time")
+                            .long("time")
+                            .takes_value(true)
+                            .validator(|input| parse_expiry_time(&input).map(|_| ()))
+                            .help("Expired when the tip block timestamp reaches this time (unix seconds or RFC 3339, example: 2020-01-31T08:00:00Z)"),
+                    )
+                    .arg(
+                        Arg::with_name("clear")
+                            .long("clear")
+                            .conflicts_with_all(&["block-number", "time"])
+                            .help("Remove the expiry"),
+                    )
+                    .group(
+                        ArgGroup::with_name("expiry")
+                            .args(&["block-number", "time", "clear"])
+                            .multiple(true)
+                            .required(true),
+                    ),
+                SubCommand::with_name("replace-input")
+                    .about("Replace or remove the input at index in place, signatures invalidated by the change are cleared and the keys which must sign again are reported")
+                    .arg(arg_tx_file.clone())
+                    .arg(arg_index.clone().help("Index of the input"))
+                    .arg(
+                        Arg::with_name("out-point")
+                            .long("out-point")
+                            .takes_value(true)
+                            .required_unless("remove")
+                            .validator(|input| OutPointParser.validate(input))
+                            .help("The new input cell (must be live), format: {tx-hash}-{index}"),
+                    )
+                    .arg(
+                        Arg::with_name("since")
+                            .long("since")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Since of the new input"),
+                    )
+                    .arg(arg_remove.clone().conflicts_with("out-point")),
+                SubCommand::with_name("replace-output")
+                    .about("Replace fields of the output at index (unspecified fields are kept) or remove it in place, signatures invalidated by the change are cleared and the keys which must sign again are reported")
+                    .arg(arg_tx_file.clone())
+                    .arg(arg_index.help("Index of the output"))
+                    .arg(
+                        Arg::with_name("capacity")
+                            .long("capacity")
+                            .takes_value(true)
+                            .validator(|input| CapacityParser.validate(input))
+                            .help("New capacity (unit: CKB, format: 123.335)"),
+                    )
+                    .arg(
+                        Arg::with_name("address")
+                            .long("address")
+                            .takes_value(true)
+                            .validator(|input| AddressParser.validate(input))
+                            .help("New lock script by address"),
+                    )
+                    .arg(
+                        Arg::with_name("data")
+                            .long("data")
+                            .takes_value(true)
+                            .validator(|input| HexParser.validate(input))
+                            .help("New output data (hex)"),
+                    )
+                    .arg(arg_remove.conflicts_with_all(&["capacity", "address", "data"]))
+                    .group(
+                        ArgGroup::with_name("change")
+                            .args(&["capacity", "address", "data", "remove"])
+                            .multiple(true)
+                            .required(true),
+                    ),
+                SubCommand::with_name("seal")
+                    .about("Check that all signable content is committed and save its digest in the tx file before sending it to co-signers, other commands refuse the file if the content was changed")
                     .arg(arg_tx_file.clone()),
+                SubCommand::with_name("add-multisig-config")
+                    .about("Save the multisig config of a lock group of the inputs in the tx file, signers see who must sign by `mock-tx multisig-info` (not signed content, the seal is kept)")
+                    .arg(arg_tx_file.clone())
+                    .arg(
+                        Arg::with_name("multisig-threshold")
+                            .long("multisig-threshold")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("Signatures required by the multisig lock"),
+                    )
+                    .arg(
+                        Arg::with_name("multisig-require-first-n")
+                            .long("multisig-require-first-n")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("The first n pubkeys must sign"),
+                    )
+                    .arg(
+                        Arg::with_name("multisig-pubkey-hash")
+                            .long("multisig-pubkey-hash")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H160>::default().validate(input))
+                            .help("Lock arg (blake160 of pubkey) of a multisig member, in order"),
+                    ),
+                SubCommand::with_name("multisig-info")
+                    .about("Show the multisig configs saved in the tx file without RPC: members, who signed and who else must sign, inputs must be in the mock info")
+                    .arg(arg_tx_file.clone())
+                    .arg(
+                        arg_lock_arg
+                            .clone()
+                            .required(false)
+                            .help("Also check the signature slot of this signer (lock arg)"),
+                    ),
+                SubCommand::with_name("list-cells")
+                    .about("List the cells defined in the mock info (inputs and cell deps), with --with-status check if each one is live, spent or not created yet on chain")
+                    .arg(arg_tx_file.clone())
+                    .arg(
+                        Arg::with_name("with-status")
+                            .long("with-status")
+                            .help("Resolve every cell by its out point on chain (needs RPC), also check the local definition matches the chain"),
+                    ),
+                SubCommand::with_name("validate-offline")
+                    .about("Check structure of a mock transaction without RPC (duplicated inputs/deps, witnesses count, occupied capacity, capacity balance)")
+                    .arg(arg_tx_file.clone())
+                    .arg(arg_findings_format),
+                SubCommand::with_name("add-witness-template")
+                    .about("Register placeholder witness of a custom lock, inputs of this lock will be signed by the key of 20 bytes of lock args at lock-arg-offset (omnilock is builtin)")
+                    .arg(arg_code_hash.clone())
+                    .arg(
+                        Arg::with_name("lock-size")
+                            .long("lock-size")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Size of WitnessArgs.lock"),
+                    )
+                    .arg(
+                        Arg::with_name("input-type-size")
+                            .long("input-type-size")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Size of WitnessArgs.input_type (default: none)"),
+                    )
+                    .arg(
+                        Arg::with_name("output-type-size")
+                            .long("output-type-size")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Size of WitnessArgs.output_type (default: none)"),
+                    )
+                    .arg(
+                        Arg::with_name("signature-field")
+                            .long("signature-field")
+                            .takes_value(true)
+                            .possible_values(&["lock", "input_type", "output_type"])
+                            .default_value("lock")
+                            .help("The WitnessArgs field signature goes into"),
+                    )
+                    .arg(
+                        Arg::with_name("signature-offset")
+                            .long("signature-offset")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Offset of the 65 bytes signature in signature field"),
+                    )
+                    .arg(
+                        Arg::with_name("lock-arg-offset")
+                            .long("lock-arg-offset")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Offset of the 20 bytes key identifier (blake160 of pubkey) in lock args"),
+                    ),
+                SubCommand::with_name("graph")
+                    .about("Print a graph of the mock transaction drafts in a directory, an edge means a draft spends outputs of another draft, cycles and outputs which do not exist are reported as comments")
+                    .arg(
+                        Arg::with_name("dir")
+                            .long("dir")
+                            .takes_value(true)
+                            .default_value(".")
+                            .validator(|input| DirPathParser::new(true).validate(input))
+                            .help("Directory of the tx files"),
+                    )
+                    .arg(
+                        Arg::with_name("format")
+                            .long("format")
+                            .takes_value(true)
+                            .possible_values(&["dot", "mermaid"])
+                            .default_value("dot")
+                            .help("Graph format"),
+                    ),
+                SubCommand::with_name("list-witness-templates").about("List witness templates"),
+                SubCommand::with_name("remove-witness-template")
+                    .about("Remove witness template")
+                    .arg(arg_code_hash),
             ])
     }
 }
@@ -91,66 +385,383 @@ impl<'a> CliSubCommand for MockTxSubCommand<'a> {
     fn process(
         &mut self,
         matches: &ArgMatches,
-        format: OutputFormat,
-        color: bool,
         _debug: bool,
-    ) -> Result<String, String> {
-        let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
-
-        let mut complete_tx = |m: &ArgMatches,
-                               complete: bool,
-                               verify: bool|
-         -> Result<(MockTransaction, u64), String> {
-            let path: PathBuf = FilePathParser::new(true).from_matches(m, "tx-file")?;
-            let mut content = String::new();
-            let mut file = fs::File::open(path).map_err(|err| err.to_string())?;
-            file.read_to_string(&mut content)
-                .map_err(|err| err.to_string())?;
-            let repr_tx: ReprMockTransaction = serde_yaml::from_str(content.as_str())
-                .map_err(|err| err.to_string())
-                .or_else(|_| {
-                    serde_json::from_str(content.as_str()).map_err(|err| err.to_string())
-                })?;
+    ) -> Result<Box<dyn Printable>, String> {
+        // No RPC call allowed, so handle it before loading genesis info
+        if let ("validate-offline", Some(m)) = matches.subcommand() {
+            let mut mock_tx = read_mock_tx(m)?;
+            let tx_hash: H256 = mock_tx.core_transaction().hash().unpack();
+            let helper = MockTransactionHelper::new(&mut mock_tx);
+            let mut findings = helper.check_structure();
+            findings.extend(helper.check_malleability());
+            let findings_format = FindingFormat::from_name(m.value_of("format").unwrap())?;
+            if findings_format != FindingFormat::Report {
+                return print_findings(m, findings_format, &findings);
+            }
+            let resp = serde_json::json!({
+                "tx-hash": tx_hash,
+                "valid": count_errors(&findings) == 0,
+                "errors": findings,
+            });
+            return Ok(Box::new(resp));
+        }
+        if let ("multisig-info", Some(m)) = matches.subcommand() {
+            let mut mock_tx = read_mock_tx(m)?;
+            let meta = read_tx_meta(m)?;
+            let signer: Option<H160> =
+                FixedHashParser::<H160>::default().from_matches_opt(m, "lock-arg", false)?;
+            if meta.multisig_configs.is_empty() {
+                return Err(
+                    "No multisig config in the tx file, see: mock-tx add-multisig-config"
+                        .to_owned(),
+                );
+            }
+            let mut configs = Vec::new();
+            for multisig in &meta.multisig_configs {
+                configs.push(multisig.describe(&mut mock_tx, signer.as_ref())?);
+            }
+            if let Some(signer) = signer.as_ref() {
+                if configs.iter().all(|config| config["slot"].is_null()) {
+                    return Err(format!(
+                        "{:#x} is not a member of any multisig config of the tx file",
+                        signer
+                    ));
+                }
+            }
+            return Ok(Box::new(serde_json::json!({ "multisig-configs": configs })));
+        }
+        // RPC is only needed with --with-status, genesis info is not
+        if let ("list-cells", Some(m)) = matches.subcommand() {
+            let mock_tx = read_mock_tx(m)?;
+            let cells = mock_tx
+                .mock_info
+                .inputs
+                .iter()
+                .map(|mock_input| {
+                    (
+                        "input",
+                        mock_input.input.previous_output(),
+                        &mock_input.output,
+                        &mock_input.data,
+                    )
+                })
+                .chain(mock_tx.mock_info.cell_deps.iter().map(|mock_cell_dep| {
+                    (
+                        "cell-dep",
+                        mock_cell_dep.cell_dep.out_point(),
+                        &mock_cell_dep.output,
+                        &mock_cell_dep.data,
+                    )
+                }));
+            let mut resp = Vec::new();
+            for (kind, out_point, output, data) in cells {
+                let capacity: u64 = output.capacity().unpack();
+                let lock_hash: H256 = output.lock().calc_script_hash().unpack();
+                let type_hash: Option<H256> = output
+                    .type_()
+                    .to_opt()
+                    .map(|script| script.calc_script_hash().unpack());
+                let mut item = serde_json::json!({
+                    "kind": kind,
+                    "out-point": out_point_name(&out_point),
+                    "capacity": format_capacity(capacity),
+                    "lock-hash": lock_hash,
+                    "type-hash": type_hash,
+                    "data-size": data.len(),
+                });
+                if m.is_present("with-status") {
+                    let (status, matches) = self.cell_status(&out_point, output, data)?;
+                    item["status"] = serde_json::json!(status);
+                    item["matches-chain"] = serde_json::json!(matches);
+                }
+                resp.push(item);
+            }
+            return Ok(Box::new(serde_json::json!(resp)));
+        }
+        if let ("seal", Some(m)) = matches.subcommand() {
+            // Sealing again is allowed, the content is not checked against the old digest
+            let content = read_tx_file(m)?;
+            let repr_tx: ReprMockTransaction = parse_tx_file(&content)?;
+            let mut meta: TxFileMeta =
+                parse_tx_file(&content).map_err(|err| format!("Invalid tx file: {}", err))?;
             let mut mock_tx: MockTransaction = repr_tx.into();
-
-            let signer = get_singer(self.key_store.clone());
-            let mut loader = Loader {
-                rpc_client: self.rpc_client,
+            let (findings, digest) = {
+                let helper = MockTransactionHelper::new(&mut mock_tx);
+                let mut findings = helper.check_structure();
+                findings.extend(helper.check_malleability());
+                (findings, helper.content_digest())
             };
-            let cycle = {
-                let mut helper = MockTransactionHelper::new(&mut mock_tx);
-                if complete {
-                    helper.complete_tx(None, &genesis_info, &signer, |out_point| {
-                        loader.get_live_cell(out_point)
-                    })?;
+            if count_errors(&findings) > 0 {
+                let resp = serde_json::json!({
+                    "sealed": false,
+                    "errors": findings,
+                });
+                return Ok(Box::new(resp));
+            }
+            meta.digest = Some(digest.clone());
+            let path: PathBuf = tx_file_path(m)?;
+            fs::write(path, render_mock_tx(&mock_tx, &meta, false)?)
+                .map_err(|err| err.to_string())?;
+            let resp = serde_json::json!({
+                "sealed": true,
+                "digest": digest,
+                "errors": findings,
+            });
+            return Ok(Box::new(resp));
+        }
+
+        let templates_file = self.witness_templates_file.clone();
+        let sign_modes_file = self.sign_modes_file.clone();
+        let color = self.color;
+        match matches.subcommand() {
+            ("add-witness-template", Some(m)) => {
+                let code_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "code-hash")?;
+                let signature_field = match m.value_of("signature-field") {
+                    Some("input_type") => WitnessField::InputType,
+                    Some("output_type") => WitnessField::OutputType,
+                    _ => WitnessField::Lock,
+                };
+                let template = WitnessTemplate {
+                    code_hash: code_hash.clone(),
+                    lock_size: FromStrParser::<usize>::default().from_matches(m, "lock-size")?,
+                    input_type_size: FromStrParser::<usize>::default().from_matches_opt(
+                        m,
+                        "input-type-size",
+                        false,
+                    )?,
+                    output_type_size: FromStrParser::<usize>::default().from_matches_opt(
+                        m,
+                        "output-type-size",
+                        false,
+                    )?,
+                    signature_field,
+                    signature_offset: FromStrParser::<usize>::default()
+                        .from_matches(m, "signature-offset")?,
+                    lock_arg_offset: FromStrParser::<usize>::default()
+                        .from_matches(m, "lock-arg-offset")?,
+                    signature_prefix: None,
+                };
+                template.validate()?;
+                let mut templates = load_witness_templates(&templates_file)?;
+                templates.retain(|template| template.code_hash != code_hash);
+                templates.push(template);
+                save_witness_templates(&templates_file, &templates)?;
+                return Ok(Box::new(Text::from("DONE")));
+            }
+            ("list-witness-templates", _) => {
+                let templates = load_witness_templates(&templates_file)?;
+                let resp = serde_json::to_value(templates).map_err(|err| err.to_string())?;
+                return Ok(Box::new(resp));
+            }
+            ("set-draft-id", Some(m)) => {
+                let path = tx_file_path(m)?;
+                let draft_id = m
+                    .value_of("draft-id")
+                    .map(ToOwned::to_owned)
+                    .unwrap_or_else(new_draft_id);
+                let dir = match path.parent() {
+                    Some(parent) if parent != Path::new("") => parent.to_path_buf(),
+                    _ => PathBuf::from("."),
+                };
+                if let Some((other, _, _)) =
+                    scan_tx_files(&dir).into_iter().find(|(other, _, meta)| {
+                        meta.draft_id.as_ref() == Some(&draft_id) && !same_file(other, &path)
+                    })
+                {
+                    return Err(format!(
+                        "Draft id {} is already used by {}",
+                        draft_id,
+                        other.display()
+                    ));
                 }
-                if verify {
-                    helper.verify(u64::max_value(), loader)?
+                let mock_tx = read_mock_tx(m)?;
+                // The draft id is not signed content, the digest is kept
+                let meta = TxFileMeta {
+                    draft_id: Some(draft_id.clone()),
+                    ..read_tx_meta(m)?
+                };
+                let content = render_mock_tx(&mock_tx, &meta, false)?;
+                fs::write(path, content).map_err(|err| err.to_string())?;
+                let tx_hash: H256 = mock_tx.core_transaction().hash().unpack();
+                let resp = serde_json::json!({
+                    "draft-id": draft_id,
+                    "tx-hash": tx_hash,
+                });
+                return Ok(Box::new(resp));
+            }
+            ("set-expiry", Some(m)) => {
+                let expiry = if m.is_present("clear") {
+                    None
                 } else {
-                    0
+                    Some(DraftExpiry {
+                        block_number: FromStrParser::<u64>::default().from_matches_opt(
+                            m,
+                            "block-number",
+                            false,
+                        )?,
+                        timestamp: m.value_of("time").map(parse_expiry_time).transpose()?,
+                    })
+                };
+                let mock_tx = read_mock_tx(m)?;
+                // The expiry is not signed content, the digest is kept
+                let meta = TxFileMeta {
+                    expiry,
+                    ..read_tx_meta(m)?
+                };
+                let path: PathBuf = tx_file_path(m)?;
+                let content = render_mock_tx(&mock_tx, &meta, false)?;
+                fs::write(path, content).map_err(|err| err.to_string())?;
+                let resp = serde_json::json!({ "expiry": expiry });
+                return Ok(Box::new(resp));
+            }
+            ("graph", Some(m)) => {
+                let dir: PathBuf = DirPathParser::new(true).from_matches(m, "dir")?;
+                let drafts = scan_tx_files(&dir);
+                let mermaid = m.value_of("format") == Some("mermaid");
+                return Ok(Box::new(Text::from(render_tx_graph(&drafts, mermaid))));
+            }
+            ("remove-witness-template", Some(m)) => {
+                let code_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "code-hash")?;
+                let mut templates = load_witness_templates(&templates_file)?;
+                let len = templates.len();
+                templates.retain(|template| template.code_hash != code_hash);
+                if templates.len() == len {
+                    return Err(format!("Witness template not found: {:#x}", code_hash));
                 }
+                save_witness_templates(&templates_file, &templates)?;
+                return Ok(Box::new(Text::from("DONE")));
+            }
+            _ => {}
+        }
+
+        let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+        // Registered templates override the builtin omnilock ones
+        let mut witness_templates = omnilock_witness_templates();
+        witness_templates.extend(load_witness_templates(&templates_file)?);
+
+        let keystore_dir = self.key_store.keys_dir().to_path_buf();
+        let mut complete_tx =
+            |m: &ArgMatches,
+             complete: bool,
+             verify: bool|
+             -> Result<(MockTransaction, u64, Option<FeeEstimate>), String> {
+                let mut mock_tx = read_mock_tx(m)?;
+
+                let sign_modes = SignModes::load(&sign_modes_file)?;
+                let key_signer =
+                    get_singer(self.key_store.clone(), SignModes::load(&sign_modes_file)?);
+                // Keys used to sign, recorded into key usage
+                let signed_keys: Mutex<Vec<H160>> = Mutex::new(Vec::new());
+                let signer = |identifier: &H160, message: &H256| -> Result<[u8; 65], String> {
+                    let signature = key_signer(identifier, message)?;
+                    let (lock_arg, _) = sign_modes.resolve(identifier);
+                    signed_keys.lock().unwrap().push(lock_arg);
+                    Ok(signature)
+                };
+                let mut loader = Loader {
+                    rpc_client: self.rpc_client,
+                };
+                let mut fee_estimate = None;
+                let cycle = {
+                    let mut helper = MockTransactionHelper::new(&mut mock_tx);
+                    helper.set_witness_templates(witness_templates.clone());
+                    helper.set_sign_progress(Box::new(|signed, total| {
+                        if total > 1 {
+                            eprint!("\rSigned: {}/{}", signed, total);
+                            if signed == total {
+                                eprintln!();
+                            }
+                        }
+                    }));
+                    let fee_rate: Option<u64> =
+                        FromStrParser::<u64>::default().from_matches_opt(m, "fee-rate", false)?;
+                    match fee_rate {
+                        Some(fee_rate) if complete => {
+                            fee_estimate = Some(helper.complete_tx_with_fee(
+                                None,
+                                fee_rate,
+                                &genesis_info,
+                                &signer,
+                                |out_point| loader.get_live_cell(out_point),
+                            )?);
+                        }
+                        _ if complete => {
+                            helper.complete_tx(None, &genesis_info, &signer, |out_point| {
+                                loader.get_live_cell(out_point)
+                            })?;
+                        }
+                        _ => {}
+                    }
+                    if verify {
+                        helper.verify(u64::max_value(), loader)?
+                    } else {
+                        0
+                    }
+                };
+                let mut signed_keys = signed_keys.into_inner().unwrap();
+                signed_keys.sort();
+                signed_keys.dedup();
+                let tx_hash: H256 = mock_tx.core_transaction().hash().unpack();
+                record_key_usage(&keystore_dir, &signed_keys, Some(tx_hash));
+                Ok((mock_tx, cycle, fee_estimate))
             };
-            Ok((mock_tx, cycle))
-        };
 
-        let output_tx = |m: &ArgMatches, mock_tx: &MockTransaction| -> Result<(), String> {
-            let output_opt: Option<PathBuf> =
-                FilePathParser::new(false).from_matches_opt(m, "output-file", false)?;
-            let output_color = output_opt.as_ref().map(|_| false).unwrap_or(color);
-            let output_content =
-                ReprMockTransaction::from(mock_tx.clone()).render(OutputFormat::Json, output_color);
-            if let Some(output) = output_opt {
-                let mut out_file = fs::File::create(output).map_err(|err| err.to_string())?;
-                out_file
-                    .write_all(output_content.as_bytes())
-                    .map_err(|err| err.to_string())?;
-            } else {
-                println!("{}", output_content);
-            }
-            Ok(())
-        };
+        let output_tx =
+            |m: &ArgMatches, mock_tx: &MockTransaction, meta: &TxFileMeta| -> Result<(), String> {
+                let output_opt: Option<PathBuf> =
+                    FilePathParser::new(false).from_matches_opt(m, "output-file", false)?;
+                let output_color = output_opt.as_ref().map(|_| false).unwrap_or(color);
+                let output_content = render_mock_tx(mock_tx, meta, output_color)?;
+                if let Some(output) = output_opt {
+                    let mut out_file = fs::File::create(output).map_err(|err| err.to_string())?;
+                    out_file
+                        .write_all(output_content.as_bytes())
+                        .map_err(|err| err.to_string())?;
+                } else {
+                    println!("{}", output_content);
+                }
+                Ok(())
+            };
 
         match matches.subcommand() {
+            ("add-multisig-config", Some(m)) => {
+                let threshold: u8 =
+                    FromStrParser::<u8>::default().from_matches(m, "multisig-threshold")?;
+                let require_first_n: u8 =
+                    FromStrParser::<u8>::default().from_matches(m, "multisig-require-first-n")?;
+                let pubkey_hashes: Vec<H160> = FixedHashParser::<H160>::default()
+                    .from_matches_vec(m, "multisig-pubkey-hash")?;
+                let config = multisig_config(require_first_n, threshold, &pubkey_hashes)?;
+                let mut mock_tx = read_mock_tx(m)?;
+                let (lock_hash, idxs) = {
+                    let mut loader = Loader {
+                        rpc_client: self.rpc_client,
+                    };
+                    MockTransactionHelper::new(&mut mock_tx)
+                        .multisig_group(&config, |out_point| loader.get_live_cell(out_point))?
+                };
+                let lock_hash: H256 = lock_hash.unpack();
+                // The config is not signed content, the digest is kept
+                let mut meta = read_tx_meta(m)?;
+                meta.multisig_configs
+                    .retain(|multisig| multisig.lock_hash != lock_hash);
+                meta.multisig_configs.push(TxMultisigConfig {
+                    lock_hash: lock_hash.clone(),
+                    require_first_n,
+                    threshold,
+                    pubkey_hashes,
+                });
+                let path: PathBuf = tx_file_path(m)?;
+                let content = render_mock_tx(&mock_tx, &meta, false)?;
+                fs::write(path, content).map_err(|err| err.to_string())?;
+                let resp = serde_json::json!({
+                    "lock-hash": lock_hash,
+                    "inputs": idxs,
+                });
+                Ok(Box::new(resp))
+            }
             ("template", Some(m)) => {
                 let lock_arg_opt: Option<H160> =
                     FixedHashParser::<H160>::default().from_matches_opt(m, "lock-arg", false)?;
@@ -205,42 +816,1036 @@ impl<'a> CliSubCommand for MockTxSubCommand<'a> {
                     let mut helper = MockTransactionHelper::new(&mut mock_tx);
                     helper.fill_deps(&genesis_info, |_| unreachable!())?;
                 }
-                output_tx(m, &mock_tx)?;
+                output_tx(m, &mock_tx, &TxFileMeta::new_draft())?;
 
-                Ok(String::new())
+                Ok(Box::new(Text::from("")))
+            }
+            ("from-rpc", Some(m)) => {
+                let tx_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+                let mut mock_tx = self.mock_tx_from_rpc(tx_hash.clone())?;
+                if m.is_present("strip-witnesses") {
+                    mock_tx.tx = mock_tx
+                        .tx
+                        .as_advanced_builder()
+                        .set_witnesses(Vec::new())
+                        .build()
+                        .data();
+                }
+                let meta = TxFileMeta::new_draft();
+                output_tx(m, &mock_tx, &meta)?;
+                let resp = serde_json::json!({
+                    "tx-hash": tx_hash,
+                    "draft-id": meta.draft_id,
+                });
+                Ok(Box::new(resp))
+            }
+            ("replace-input", Some(m)) => {
+                let index: usize = FromStrParser::<usize>::default().from_matches(m, "index")?;
+                let mut mock_tx = read_mock_tx(m)?;
+                let tx = mock_tx.core_transaction();
+                let mut inputs: Vec<CellInput> = tx.inputs().into_iter().collect();
+                let mut witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
+                if index >= inputs.len() {
+                    return Err(format!(
+                        "Input index {} out of range, inputs count: {}",
+                        index,
+                        inputs.len()
+                    ));
+                }
+                let old_input = inputs[index].clone();
+                let new_input = if m.is_present("remove") {
+                    inputs.remove(index);
+                    if index < witnesses.len() {
+                        witnesses.remove(index);
+                    }
+                    None
+                } else {
+                    let out_point: OutPoint = OutPointParser.from_matches(m, "out-point")?;
+                    let since: u64 = FromStrParser::<u64>::default().from_matches(m, "since")?;
+                    let input = CellInput::new(out_point, since);
+                    inputs[index] = input.clone();
+                    Some(input)
+                };
+                mock_tx.tx = tx
+                    .as_advanced_builder()
+                    .set_inputs(inputs.clone())
+                    .set_witnesses(witnesses)
+                    .build()
+                    .data();
+                // Mock inputs no longer referenced are dropped
+                mock_tx.mock_info.inputs.retain(|mock_input| {
+                    inputs.iter().any(|input| {
+                        input.previous_output().as_slice()
+                            == mock_input.input.previous_output().as_slice()
+                    })
+                });
+                if let Some(input) = new_input.as_ref() {
+                    let out_point = input.previous_output();
+                    if !mock_tx.mock_info.inputs.iter().any(|mock_input| {
+                        mock_input.input.previous_output().as_slice() == out_point.as_slice()
+                    }) {
+                        let mut loader = Loader {
+                            rpc_client: self.rpc_client,
+                        };
+                        let (output, data) =
+                            loader.get_live_cell(out_point.clone())?.ok_or_else(|| {
+                                format!("Input cell is not live: {}", out_point_name(&out_point))
+                            })?;
+                        mock_tx.mock_info.inputs.push(MockInput {
+                            input: input.clone(),
+                            output,
+                            data,
+                        });
+                    }
+                }
+                let change = serde_json::json!({
+                    "index": index,
+                    "old": out_point_name(&old_input.previous_output()),
+                    "new": new_input.map(|input| out_point_name(&input.previous_output())),
+                });
+                self.finish_edit(m, &genesis_info, mock_tx, change)
+            }
+            ("replace-output", Some(m)) => {
+                let index: usize = FromStrParser::<usize>::default().from_matches(m, "index")?;
+                let mut mock_tx = read_mock_tx(m)?;
+                let tx = mock_tx.core_transaction();
+                let mut outputs: Vec<CellOutput> = tx.outputs().into_iter().collect();
+                let mut outputs_data: Vec<packed::Bytes> = tx.outputs_data().into_iter().collect();
+                if index >= outputs.len() {
+                    return Err(format!(
+                        "Output index {} out of range, outputs count: {}",
+                        index,
+                        outputs.len()
+                    ));
+                }
+                let old_capacity: u64 = outputs[index].capacity().unpack();
+                let new_capacity = if m.is_present("remove") {
+                    outputs.remove(index);
+                    if index < outputs_data.len() {
+                        outputs_data.remove(index);
+                    }
+                    None
+                } else {
+                    let mut builder = outputs[index].clone().as_builder();
+                    if let Some(capacity) = CapacityParser.from_matches_opt(m, "capacity", false)? {
+                        builder = builder.capacity(capacity.pack());
+                    }
+                    let address: Option<Address> =
+                        AddressParser.from_matches_opt(m, "address", false)?;
+                    if let Some(address) = address {
+                        builder = builder
+                            .lock(address.lock_script(genesis_info.secp_type_hash().clone()));
+                    }
+                    let data: Option<Vec<u8>> = HexParser.from_matches_opt(m, "data", false)?;
+                    if let Some(data) = data {
+                        outputs_data.resize(outputs.len(), Default::default());
+                        outputs_data[index] = Bytes::from(data).pack();
+                    }
+                    outputs[index] = builder.build();
+                    let capacity: u64 = outputs[index].capacity().unpack();
+                    Some(capacity)
+                };
+                mock_tx.tx = tx
+                    .as_advanced_builder()
+                    .set_outputs(outputs)
+                    .set_outputs_data(outputs_data)
+                    .build()
+                    .data();
+                let change = serde_json::json!({
+                    "index": index,
+                    "old_capacity": old_capacity,
+                    "new_capacity": new_capacity,
+                });
+                self.finish_edit(m, &genesis_info, mock_tx, change)
             }
             ("complete", Some(m)) => {
-                let (mock_tx, _cycle) = complete_tx(m, true, false)?;
-                output_tx(m, &mock_tx)?;
+                let (mock_tx, _cycle, fee_estimate) = complete_tx(m, true, false)?;
+                // Keep the expiry and digest of the draft in completed transaction
+                let mut meta = read_tx_meta(m)?;
+                if let Some(digest) = meta.digest.as_ref() {
+                    let mut completed = mock_tx.clone();
+                    if &MockTransactionHelper::new(&mut completed).content_digest() != digest {
+                        return Err(
+                            "Completing changed the sealed transaction content, complete it before sealing"
+                                .to_owned(),
+                        );
+                    }
+                }
+                output_tx(m, &mock_tx, &meta)?;
                 let tx_hash: H256 = mock_tx.core_transaction().hash().unpack();
-                let resp = serde_json::json!({
+                let mut resp = serde_json::json!({
                     "tx-hash": tx_hash,
                 });
-                Ok(resp.render(format, color))
+                if let Some(fee_estimate) = fee_estimate {
+                    resp["fee"] = serde_json::to_value(fee_estimate).unwrap();
+                }
+                Ok(Box::new(resp))
             }
             ("verify", Some(m)) => {
-                let (mock_tx, cycle) = complete_tx(m, false, true)?;
+                let findings_format = FindingFormat::from_name(m.value_of("format").unwrap())?;
+                if findings_format != FindingFormat::Report {
+                    let mut findings = Vec::new();
+                    match complete_tx(m, false, true) {
+                        Ok((mock_tx, cycle, _)) => {
+                            if cycle > MAX_TX_VERIFY_CYCLES {
+                                findings.push(TxFinding::warning(
+                                    "cycles-exceed-limit",
+                                    "tx".to_owned(),
+                                    format!(
+                                        "Transaction takes {} cycles, the node accepts at most {}",
+                                        cycle, MAX_TX_VERIFY_CYCLES
+                                    ),
+                                ));
+                            }
+                            if let Some(expiry) = read_tx_meta(m)?.expiry {
+                                let tip = self.tip_point()?;
+                                if expiry.is_expired(&tip) {
+                                    findings.push(TxFinding::error(
+                                        "draft-expired",
+                                        "tx".to_owned(),
+                                        expiry.describe(&tip),
+                                    ));
+                                }
+                            }
+                            for item in self.since_report(&mock_tx)? {
+                                if item["satisfied"] != serde_json::json!(true) {
+                                    findings.push(since_finding(&item));
+                                }
+                            }
+                        }
+                        Err(err) => findings.push(TxFinding::error(
+                            "verification-failed",
+                            "tx".to_owned(),
+                            err,
+                        )),
+                    }
+                    return print_findings(m, findings_format, &findings);
+                }
+                let (mut mock_tx, _, _) = complete_tx(m, false, false)?;
+                let (cycle, cycle_source) = self.estimate_cycles(&mut mock_tx)?;
                 let tx_hash: H256 = mock_tx.core_transaction().hash().unpack();
-                let resp = serde_json::json!({
+                let since_report = self.since_report(&mock_tx)?;
+                let spendable = since_report
+                    .iter()
+                    .all(|item| item["satisfied"] == serde_json::json!(true));
+                let mut resp = serde_json::json!({
                     "tx-hash": tx_hash,
                     "cycle": cycle,
+                    "cycle-source": cycle_source,
+                    "max-cycles": MAX_TX_VERIFY_CYCLES,
+                    "within-limit": cycle <= MAX_TX_VERIFY_CYCLES,
+                    "spendable": spendable,
+                    "since": since_report,
                 });
-                Ok(resp.render(format, color))
+                if let Some(expiry) = read_tx_meta(m)?.expiry {
+                    let tip = self.tip_point()?;
+                    resp["expired"] = serde_json::json!(expiry.is_expired(&tip));
+                    resp["expiry"] = serde_json::json!(expiry.describe(&tip));
+                }
+                Ok(Box::new(resp))
             }
             ("send", Some(m)) => {
-                let (mock_tx, _cycle) = complete_tx(m, false, true)?;
+                if let Some(expiry) = read_tx_meta(m)?.expiry {
+                    let tip = self.tip_point()?;
+                    if expiry.is_expired(&tip) && !m.is_present("ignore-expiry") {
+                        return Err(format!(
+                            "{}, use --ignore-expiry to send it anyway",
+                            expiry.describe(&tip)
+                        ));
+                    }
+                }
+                let (mock_tx, _cycle, _) = complete_tx(m, false, true)?;
                 let resp = self
                     .rpc_client
                     .send_transaction(mock_tx.core_transaction().data().into())
                     .call()
-                    .map_err(|err| format!("Send transaction error: {}", err))?;
-                Ok(resp.render(format, color))
+                    .map_err(|err| format!("{}: {}", tr("Send transaction error"), err))?;
+                Ok(Box::new(resp))
             }
             _ => Err(matches.usage().to_owned()),
         }
     }
 }
 
+impl<'a> MockTxSubCommand<'a> {
+    // Status of a cell on chain: live, spent, pending (created by a tx not
+    // committed yet) or not-created, and whether its output and data match
+    // the local definition (None when not on chain)
+    fn cell_status(
+        &mut self,
+        out_point: &OutPoint,
+        output: &CellOutput,
+        data: &Bytes,
+    ) -> Result<(&'static str, Option<bool>), String> {
+        let resp: CellWithStatus = self
+            .rpc_client
+            .get_live_cell(out_point.clone().into(), true)
+            .call()
+            .map_err(|err| err.to_string())?;
+        if let Some(info) = resp.cell {
+            let chain_output: CellOutput = info.output.into();
+            let chain_data = info
+                .data
+                .map(|data| data.content.into_bytes())
+                .unwrap_or_default();
+            let matches = chain_output.as_slice() == output.as_slice() && &chain_data == data;
+            return Ok(("live", Some(matches)));
+        }
+        let tx_hash: H256 = out_point.tx_hash().unpack();
+        let index: u32 = out_point.index().unpack();
+        let tx_with_status = self
+            .rpc_client
+            .get_transaction(tx_hash)
+            .call()
+            .map_err(|err| err.to_string())?
+            .0;
+        let tx_with_status = match tx_with_status {
+            Some(tx_with_status) => tx_with_status,
+            None => return Ok(("not-created", None)),
+        };
+        let tx = tx_with_status.transaction.inner;
+        let matches = tx.outputs.get(index as usize).map(|chain_output| {
+            let chain_output: CellOutput = chain_output.clone().into();
+            chain_output.as_slice() == output.as_slice()
+                && tx.outputs_data[index as usize].as_bytes() == data.as_ref()
+        });
+        if matches.is_none() {
+            return Ok(("not-created", None));
+        }
+        match tx_with_status.tx_status.status {
+            Status::Committed => Ok(("spent", matches)),
+            _ => Ok(("pending", matches)),
+        }
+    }
+
+    // Output cell by transaction, works for spent cells too
+    fn committed_cell(&mut self, out_point: &OutPoint) -> Result<(CellOutput, Bytes), String> {
+        let tx_hash: H256 = out_point.tx_hash().unpack();
+        let index: u32 = out_point.index().unpack();
+        let tx = self
+            .rpc_client
+            .get_transaction(tx_hash.clone())
+            .call()
+            .map_err(|err| err.to_string())?
+            .0
+            .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?
+            .transaction
+            .inner;
+        let output = tx
+            .outputs
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| format!("Output not found: {:#x}-{}", tx_hash, index))?;
+        let data = tx.outputs_data[index as usize].clone().into_bytes();
+        Ok((output.into(), data))
+    }
+
+    fn mock_tx_from_rpc(&mut self, tx_hash: H256) -> Result<MockTransaction, String> {
+        let tx: packed::Transaction = self
+            .rpc_client
+            .get_transaction(tx_hash.clone())
+            .call()
+            .map_err(|err| err.to_string())?
+            .0
+            .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?
+            .transaction
+            .inner
+            .into();
+        let tx_view = tx.clone().into_view();
+
+        let mut inputs = Vec::new();
+        for input in tx_view.inputs().into_iter() {
+            let (output, data) = self.committed_cell(&input.previous_output())?;
+            inputs.push(MockInput {
+                input,
+                output,
+                data,
+            });
+        }
+        let mut cell_deps = Vec::new();
+        for cell_dep in tx_view.cell_deps().into_iter() {
+            let (output, data) = self.committed_cell(&cell_dep.out_point())?;
+            // Cells in dep group are needed by verification too
+            if cell_dep.dep_type() == DepType::DepGroup.into() {
+                let out_points = OutPointVec::from_slice(&data)
+                    .map_err(|err| format!("Parse dep group data error: {}", err))?;
+                for out_point in out_points.into_iter() {
+                    let (output, data) = self.committed_cell(&out_point)?;
+                    cell_deps.push(MockCellDep {
+                        cell_dep: CellDep::new_builder().out_point(out_point).build(),
+                        output,
+                        data,
+                    });
+                }
+            }
+            cell_deps.push(MockCellDep {
+                cell_dep,
+                output,
+                data,
+            });
+        }
+        let mut header_deps = Vec::new();
+        for block_hash in tx_view.header_deps().into_iter() {
+            let header: HeaderView = self
+                .rpc_client
+                .get_header(block_hash.unpack())
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .ok_or_else(|| format!("Header not found: {:#x}", block_hash))?
+                .into();
+            header_deps.push(header);
+        }
+        Ok(MockTransaction {
+            mock_info: MockInfo {
+                inputs,
+                cell_deps,
+                header_deps,
+            },
+            tx,
+        })
+    }
+
+    // Evaluate since of every input against current tip, script verification
+    // does not check them.
+    // Clear signatures invalidated by an edit and save the tx file in place,
+    // the expiry is kept and the seal (if any) is dropped
+    fn finish_edit(
+        &mut self,
+        m: &ArgMatches,
+        genesis_info: &GenesisInfo,
+        mut mock_tx: MockTransaction,
+        change: serde_json::Value,
+    ) -> Result<Box<dyn Printable>, String> {
+        let resign = {
+            let mut loader = Loader {
+                rpc_client: self.rpc_client,
+            };
+            MockTransactionHelper::new(&mut mock_tx)
+                .clear_signatures(genesis_info, |out_point| loader.get_live_cell(out_point))?
+        };
+        let meta = read_tx_meta(m)?;
+        let unsealed = meta.digest.is_some();
+        let meta = TxFileMeta {
+            digest: None,
+            ..meta
+        };
+        let path: PathBuf = tx_file_path(m)?;
+        let content = render_mock_tx(&mock_tx, &meta, false)?;
+        fs::write(path, content).map_err(|err| err.to_string())?;
+        let tx_hash: H256 = mock_tx.core_transaction().hash().unpack();
+        let resp = serde_json::json!({
+            "tx-hash": tx_hash,
+            "change": change,
+            "resign": resign,
+            "unsealed": unsealed,
+        });
+        Ok(Box::new(resp))
+    }
+
+    // Cycles and where they come from ("node" or "local")
+    fn estimate_cycles(
+        &mut self,
+        mock_tx: &mut MockTransaction,
+    ) -> Result<(u64, &'static str), String> {
+        match self
+            .rpc_client
+            .estimate_cycles(mock_tx.core_transaction().data().into())
+            .call()
+        {
+            Ok(result) => Ok((result.cycles.value(), "node")),
+            Err(ref err) if is_unsupported_method(err) => {
+                let loader = Loader {
+                    rpc_client: self.rpc_client,
+                };
+                let cycle = MockTransactionHelper::new(mock_tx).verify(u64::max_value(), loader)?;
+                Ok((cycle, "local"))
+            }
+            Err(err) => Err(format!("Estimate cycles error: {}", err)),
+        }
+    }
+
+    fn tip_point(&mut self) -> Result<ChainPoint, String> {
+        let tip_header: HeaderView = self
+            .rpc_client
+            .get_tip_header()
+            .call()
+            .map_err(|err| err.to_string())?
+            .into();
+        Ok(ChainPoint::from(&tip_header))
+    }
+
+    fn since_report(
+        &mut self,
+        mock_tx: &MockTransaction,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let mut report = Vec::new();
+        let mut tip_opt: Option<ChainPoint> = None;
+        for (index, input) in mock_tx.core_transaction().inputs().into_iter().enumerate() {
+            let value: u64 = input.since().unpack();
+            let since = match Since::parse(value)? {
+                Some(since) => since,
+                None => continue,
+            };
+            let tip = match tip_opt {
+                Some(tip) => tip,
+                None => {
+                    let tip_header: HeaderView = self
+                        .rpc_client
+                        .get_tip_header()
+                        .call()
+                        .map_err(|err| err.to_string())?
+                        .into();
+                    let tip = ChainPoint::from(&tip_header);
+                    tip_opt = Some(tip);
+                    tip
+                }
+            };
+            let base = if since.relative {
+                // Relative since counts from the block committing the input cell
+                let tx_hash: H256 = input.previous_output().tx_hash().unpack();
+                let block_hash = self
+                    .rpc_client
+                    .get_transaction(tx_hash.clone())
+                    .call()
+                    .map_err(|err| err.to_string())?
+                    .0
+                    .and_then(|tx_with_status| tx_with_status.tx_status.block_hash);
+                match block_hash {
+                    Some(block_hash) => self
+                        .rpc_client
+                        .get_header(block_hash)
+                        .call()
+                        .map_err(|err| err.to_string())?
+                        .0
+                        .map(|header| ChainPoint::from(&HeaderView::from(header))),
+                    None => None,
+                }
+            } else {
+                None
+            };
+            let mut item = serde_json::json!({
+                "index": index,
+                "since": format!("{:#x}", value),
+                "relative": since.relative,
+            });
+            match since.target(base.as_ref()) {
+                Ok(target) => {
+                    item["satisfied"] = serde_json::json!(target.is_reached(&tip));
+                    item["target"] = target.describe(&tip);
+                }
+                Err(_) => {
+                    item["satisfied"] = serde_json::json!(false);
+                    item["error"] = serde_json::json!("Input cell is not committed yet");
+                }
+            }
+            report.push(item);
+        }
+        Ok(report)
+    }
+}
+
+fn since_finding(item: &serde_json::Value) -> TxFinding {
+    let detail = item
+        .get("error")
+        .or_else(|| item.get("target"))
+        .map(|value| match value {
+            serde_json::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        })
+        .unwrap_or_default();
+    TxFinding::warning(
+        "since-not-satisfied",
+        format!("inputs[{}]", item["index"]),
+        format!(
+            "Since {} is not satisfied yet: {}",
+            item["since"].as_str().unwrap_or(""),
+            detail
+        ),
+    )
+}
+
+// Print findings one per line, failed when there is any error (for CI)
+fn print_findings(
+    m: &ArgMatches,
+    format: FindingFormat,
+    findings: &[TxFinding],
+) -> Result<Box<dyn Printable>, String> {
+    let file = m.value_of("tx-file").unwrap_or("-");
+    let lines = render_findings(format, file, findings);
+    match count_errors(findings) {
+        0 => Ok(Box::new(Text::from(lines.join("\n")))),
+        count => {
+            for line in lines {
+                println!("{}", line);
+            }
+            Err(format!("{} error(s) found", count))
+        }
+    }
+}
+
+fn load_witness_templates(path: &Path) -> Result<Vec<WitnessTemplate>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&content).map_err(|err| format!("Invalid witness templates file: {}", err))
+}
+
+fn save_witness_templates(path: &Path, templates: &[WitnessTemplate]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(templates).map_err(|err| err.to_string())?;
+    fs::write(path, content).map_err(|err| err.to_string())
+}
+
+/// Expiry of a mock transaction draft, saved as the `expiry` field of the tx
+/// file. The draft is expired when the tip reaches any of the limits.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct DraftExpiry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    block_number: Option<u64>,
+    // Unix timestamp, unit: second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<u64>,
+}
+
+impl DraftExpiry {
+    fn is_expired(&self, tip: &ChainPoint) -> bool {
+        self.block_number
+            .map(|number| tip.number >= number)
+            .unwrap_or(false)
+            || self
+                .timestamp
+                .map(|timestamp| tip.timestamp >= timestamp)
+                .unwrap_or(false)
+    }
+
+    fn describe(&self, tip: &ChainPoint) -> String {
+        let mut limits = Vec::new();
+        if let Some(number) = self.block_number {
+            limits.push(format!("block #{} (tip: #{})", number, tip.number));
+        }
+        if let Some(timestamp) = self.timestamp {
+            limits.push(format!("timestamp {} (tip: {})", timestamp, tip.timestamp));
+        }
+        let state = if self.is_expired(tip) {
+            "expired"
+        } else {
+            "expires"
+        };
+        format!("Draft {} at {}", state, limits.join(" or "))
+    }
+}
+
+fn parse_expiry_time(input: &str) -> Result<u64, String> {
+    input.parse::<u64>().or_else(|_| {
+        chrono::DateTime::parse_from_rfc3339(input)
+            .map_err(|err| format!("Invalid time {}: {}", input, err))
+            .and_then(|time| {
+                if time.timestamp() < 0 {
+                    Err(format!("Time before 1970 is not allowed: {}", input))
+                } else {
+                    Ok(time.timestamp() as u64)
+                }
+            })
+    })
+}
+
+/// Extra fields of a tx file besides the mock transaction
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct TxFileMeta {
+    // Stable id of the draft, kept when the tx hash changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    draft_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expiry: Option<DraftExpiry>,
+    // Content digest saved by `mock-tx seal`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    digest: Option<H256>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    multisig_configs: Vec<TxMultisigConfig>,
+}
+
+/// Multisig config of a lock group of the inputs, saved in the tx file by
+/// `mock-tx add-multisig-config`. It is checked against the input locks
+/// before use, so a changed config can not fool a signer.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct TxMultisigConfig {
+    lock_hash: H256,
+    require_first_n: u8,
+    threshold: u8,
+    // Lock args (blake160 of pubkey) of the members, in order
+    pubkey_hashes: Vec<H160>,
+}
+
+impl TxMultisigConfig {
+    // Members of the config and who already signed the witness of the lock
+    // group, inputs must be in the mock info
+    fn describe(
+        &self,
+        mock_tx: &mut MockTransaction,
+        signer: Option<&H160>,
+    ) -> Result<serde_json::Value, String> {
+        let config = multisig_config(self.require_first_n, self.threshold, &self.pubkey_hashes)?;
+        let (lock_hash, idxs, message) = {
+            let mut helper = MockTransactionHelper::new(mock_tx);
+            let (lock_hash, idxs) = helper.multisig_group(&config, |_| Ok(None))?;
+            let message = helper.multisig_message(&config, &idxs)?;
+            (lock_hash, idxs, message)
+        };
+        let lock_hash: H256 = lock_hash.unpack();
+        if lock_hash != self.lock_hash {
+            return Err(format!(
+                "Multisig config of {:#x} is used by inputs of lock hash {:#x}",
+                self.lock_hash, lock_hash
+            ));
+        }
+        // Witness lock is the config then `threshold` signatures, zero
+        // filled while collecting
+        let lock = mock_tx
+            .core_transaction()
+            .witnesses()
+            .get(idxs[0])
+            .and_then(|witness| WitnessArgs::from_slice(&witness.raw_data()).ok())
+            .and_then(|witness| witness.lock().to_opt())
+            .map(|lock| lock.raw_data())
+            .unwrap_or_default();
+        let mut signed = Vec::new();
+        if lock.len() == config.len() + 65 * self.threshold as usize
+            && lock.starts_with(&config[..])
+        {
+            for signature in lock[config.len()..].chunks(65) {
+                if signature.iter().any(|byte| *byte != 0) {
+                    signed.push(recover_signer(&message, signature)?);
+                }
+            }
+        }
+        let members = self
+            .pubkey_hashes
+            .iter()
+            .enumerate()
+            .map(|(idx, pubkey_hash)| {
+                serde_json::json!({
+                    "lock-arg": pubkey_hash,
+                    "required": idx < self.require_first_n as usize,
+                    "signed": signed.contains(pubkey_hash),
+                })
+            })
+            .collect::<Vec<_>>();
+        let slot = signer.and_then(|signer| {
+            self.pubkey_hashes
+                .iter()
+                .position(|pubkey_hash| pubkey_hash == signer)
+                .map(|position| {
+                    serde_json::json!({
+                        "position": position,
+                        "required": position < self.require_first_n as usize,
+                        "signed": signed.contains(signer),
+                    })
+                })
+        });
+        Ok(serde_json::json!({
+            "lock-hash": lock_hash,
+            "inputs": idxs,
+            "message": message,
+            "threshold": self.threshold,
+            "require-first-n": self.require_first_n,
+            "members": members,
+            "missing-signatures": (self.threshold as usize).saturating_sub(signed.len()),
+            "slot": slot,
+        }))
+    }
+}
+
+impl TxFileMeta {
+    fn new_draft() -> TxFileMeta {
+        TxFileMeta {
+            draft_id: Some(new_draft_id()),
+            ..Default::default()
+        }
+    }
+}
+
+fn new_draft_id() -> String {
+    let id: [u8; 8] = rand::thread_rng().gen();
+    faster_hex::hex_string(&id).unwrap()
+}
+
+// Tx hashes are also accepted in place of --tx-file, so a label must not
+// look like one
+fn validate_draft_id(input: &str) -> Result<(), String> {
+    if input.is_empty() {
+        return Err("Draft id is empty".to_owned());
+    }
+    if input.starts_with("0x") {
+        return Err(format!("Draft id can not start with 0x: {}", input));
+    }
+    if input.contains(|c: char| c.is_whitespace() || c == '/' || c == '\\') {
+        return Err(format!(
+            "Draft id can not contain whitespaces or path separators: {}",
+            input
+        ));
+    }
+    Ok(())
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// Parsable tx files (json/yaml) in a directory, others are skipped
+fn scan_tx_files(dir: &Path) -> Vec<(PathBuf, MockTransaction, TxFileMeta)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut files = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| ["json", "yaml", "yml"].contains(&ext))
+                    .unwrap_or(false)
+        })
+        .filter_map(|path| {
+            let content = fs::read_to_string(&path).ok()?;
+            let repr_tx: ReprMockTransaction = parse_tx_file(&content).ok()?;
+            let meta: TxFileMeta = parse_tx_file(&content).ok()?;
+            Some((path, repr_tx.into(), meta))
+        })
+        .collect::<Vec<_>>();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    files
+}
+
+// --tx-file is a path, or the draft id or tx hash of a tx file in the
+// current directory
+fn resolve_tx_file(input: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(input);
+    if path.exists() {
+        return Ok(path);
+    }
+    let tx_hash = input.trim_start_matches("0x").to_lowercase();
+    let mut matched = scan_tx_files(Path::new("."))
+        .into_iter()
+        .filter(|(_, mock_tx, meta)| {
+            let hash: H256 = mock_tx.core_transaction().hash().unpack();
+            meta.draft_id.as_ref().map(String::as_str) == Some(input)
+                || format!("{:x}", hash) == tx_hash
+        })
+        .map(|(path, _, _)| path)
+        .collect::<Vec<_>>();
+    match matched.len() {
+        0 => Err(format!(
+            "Tx file not found and no draft in the current directory has this draft id or tx hash: {}",
+            input
+        )),
+        1 => Ok(matched.remove(0)),
+        _ => Err(format!(
+            "{} matches more than one tx file: {}",
+            input,
+            matched
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+// Draft `from` spends output `index` of draft `to`
+struct TxGraphEdge {
+    from: usize,
+    to: usize,
+    index: u32,
+}
+
+fn render_tx_graph(drafts: &[(PathBuf, MockTransaction, TxFileMeta)], mermaid: bool) -> String {
+    let hashes = drafts
+        .iter()
+        .map(|(_, mock_tx, _)| {
+            let hash: H256 = mock_tx.core_transaction().hash().unpack();
+            hash
+        })
+        .collect::<Vec<_>>();
+    let mut edges = Vec::new();
+    let mut issues = Vec::new();
+    for (from, (path, mock_tx, _)) in drafts.iter().enumerate() {
+        for input in mock_tx.core_transaction().inputs().into_iter() {
+            let out_point = input.previous_output();
+            let tx_hash: H256 = out_point.tx_hash().unpack();
+            let index: u32 = out_point.index().unpack();
+            for (to, hash) in hashes.iter().enumerate() {
+                if hash != &tx_hash {
+                    continue;
+                }
+                if index as usize >= drafts[to].1.tx.raw().outputs().len() {
+                    issues.push(format!(
+                        "{} spends output {} which {} does not have",
+                        path.display(),
+                        out_point_name(&out_point),
+                        drafts[to].0.display()
+                    ));
+                }
+                edges.push(TxGraphEdge { from, to, index });
+            }
+        }
+    }
+    for cycle in find_cycles(drafts.len(), &edges) {
+        let names = cycle
+            .iter()
+            .map(|idx| drafts[*idx].0.display().to_string())
+            .collect::<Vec<_>>();
+        issues.push(format!("Cycle: {}", names.join(" -> ")));
+    }
+
+    let label = |idx: usize| {
+        let (path, _, meta) = &drafts[idx];
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let hash = format!("{:#x}", hashes[idx]);
+        match meta.draft_id.as_ref() {
+            Some(draft_id) => format!("{} ({})\\n{}..", draft_id, name, &hash[..10]),
+            None => format!("{}\\n{}..", name, &hash[..10]),
+        }
+    };
+    let mut lines = Vec::new();
+    if mermaid {
+        lines.push("graph LR".to_owned());
+        lines.extend(issues.iter().map(|issue| format!("    %% {}", issue)));
+        for idx in 0..drafts.len() {
+            lines.push(format!(
+                "    tx{}[\"{}\"]",
+                idx,
+                label(idx).replace("\\n", "<br/>")
+            ));
+        }
+        // Arrows follow the flow of cells: from the producer to the spender
+        for edge in &edges {
+            lines.push(format!(
+                "    tx{} -->|#{}| tx{}",
+                edge.to, edge.index, edge.from
+            ));
+        }
+    } else {
+        lines.push("digraph txs {".to_owned());
+        lines.extend(issues.iter().map(|issue| format!("    // {}", issue)));
+        lines.push("    rankdir=LR;".to_owned());
+        for idx in 0..drafts.len() {
+            lines.push(format!("    tx{} [label=\"{}\"];", idx, label(idx)));
+        }
+        for edge in &edges {
+            lines.push(format!(
+                "    tx{} -> tx{} [label=\"#{}\"];",
+                edge.to, edge.from, edge.index
+            ));
+        }
+        lines.push("}".to_owned());
+    }
+    lines.join("\n")
+}
+
+// Draft indexes of each cycle found by depth first search
+fn find_cycles(count: usize, edges: &[TxGraphEdge]) -> Vec<Vec<usize>> {
+    fn visit(
+        node: usize,
+        edges: &[TxGraphEdge],
+        states: &mut [u8],
+        stack: &mut Vec<usize>,
+        cycles: &mut Vec<Vec<usize>>,
+    ) {
+        // 0: not visited, 1: on the stack, 2: done
+        states[node] = 1;
+        stack.push(node);
+        for edge in edges.iter().filter(|edge| edge.to == node) {
+            match states[edge.from] {
+                0 => visit(edge.from, edges, states, stack, cycles),
+                1 => {
+                    let start = stack.iter().position(|idx| *idx == edge.from).unwrap();
+                    let mut cycle = stack[start..].to_vec();
+                    cycle.push(edge.from);
+                    cycles.push(cycle);
+                }
+                _ => {}
+            }
+        }
+        stack.pop();
+        states[node] = 2;
+    }
+
+    let mut states = vec![0u8; count];
+    let mut cycles = Vec::new();
+    while let Some(node) = states.iter().position(|state| *state == 0) {
+        visit(node, edges, &mut states, &mut Vec::new(), &mut cycles);
+    }
+    cycles
+}
+
+fn tx_file_path(m: &ArgMatches) -> Result<PathBuf, String> {
+    resolve_tx_file(m.value_of("tx-file").unwrap())
+}
+
+fn render_mock_tx(
+    mock_tx: &MockTransaction,
+    meta: &TxFileMeta,
+    color: bool,
+) -> Result<String, String> {
+    let mut value = serde_json::to_value(ReprMockTransaction::from(mock_tx.clone()))
+        .map_err(|err| err.to_string())?;
+    let meta_value = serde_json::to_value(meta).map_err(|err| err.to_string())?;
+    if let serde_json::Value::Object(fields) = meta_value {
+        for (key, field) in fields {
+            value[key] = field;
+        }
+    }
+    Ok(value.render(OutputFormat::Json, color))
+}
+
+fn read_tx_file(m: &ArgMatches) -> Result<String, String> {
+    let path: PathBuf = tx_file_path(m)?;
+    let mut content = String::new();
+    let mut file = fs::File::open(path).map_err(|err| err.to_string())?;
+    file.read_to_string(&mut content)
+        .map_err(|err| err.to_string())?;
+    Ok(content)
+}
+
+fn parse_tx_file<T: serde::de::DeserializeOwned>(content: &str) -> Result<T, String> {
+    serde_yaml::from_str(content)
+        .map_err(|err| err.to_string())
+        .or_else(|_| serde_json::from_str(content).map_err(|err| err.to_string()))
+}
+
+// The content of a sealed tx file is checked against its digest
+pub fn read_mock_tx(m: &ArgMatches) -> Result<MockTransaction, String> {
+    let content = read_tx_file(m)?;
+    let repr_tx: ReprMockTransaction = parse_tx_file(&content)?;
+    let mut mock_tx: MockTransaction = repr_tx.into();
+    let meta: TxFileMeta =
+        parse_tx_file(&content).map_err(|err| format!("Invalid tx file: {}", err))?;
+    if let Some(digest) = meta.digest {
+        let actual = MockTransactionHelper::new(&mut mock_tx).content_digest();
+        if actual != digest {
+            return Err(format!(
+                "Transaction content was changed after sealing (digest: {:#x}, sealed: {:#x}), ask the proposer for a new one",
+                actual, digest
+            ));
+        }
+    }
+    Ok(mock_tx)
+}
+
+fn read_tx_meta(m: &ArgMatches) -> Result<TxFileMeta, String> {
+    parse_tx_file(&read_tx_file(m)?).map_err(|err| format!("Invalid tx file: {}", err))
+}
+
 struct Loader<'a> {
     rpc_client: &'a mut HttpRpcClient,
 }
@@ -285,3 +1890,9 @@ impl<'a> MockResourceLoader for Loader<'a> {
         }
     }
 }
+
+fn out_point_name(out_point: &OutPoint) -> String {
+    let tx_hash: H256 = out_point.tx_hash().unpack();
+    let index: u32 = out_point.index().unpack();
+    format!("{:#x}-{}", tx_hash, index)
+}
@@ -1,6 +1,18 @@
 pub mod account;
+pub mod alias;
+pub mod analyze;
+pub mod approvals;
+pub mod asset;
+pub mod chain;
+pub mod dev;
+pub mod doctor;
+pub mod export;
 pub mod mock_tx;
+pub mod node;
+pub mod pipeline;
 pub mod rpc;
+pub mod serve;
+pub mod test_vector;
 #[cfg(unix)]
 pub mod tui;
 pub mod util;
@@ -10,8 +22,20 @@ pub mod wallet;
 pub use self::tui::TuiSubCommand;
 
 pub use account::AccountSubCommand;
+pub use alias::AliasSubCommand;
+pub use analyze::AnalyzeSubCommand;
+pub use approvals::ApprovalsSubCommand;
+pub use asset::AssetSubCommand;
+pub use chain::ChainSubCommand;
+pub use dev::DevSubCommand;
+pub use doctor::DoctorSubCommand;
+pub use export::ExportSubCommand;
 pub use mock_tx::MockTxSubCommand;
+pub use node::NodeSubCommand;
+pub use pipeline::PipelineSubCommand;
 pub use rpc::RpcSubCommand;
+pub use serve::ServeSubCommand;
+pub use test_vector::TestVectorSubCommand;
 pub use util::UtilSubCommand;
 pub use wallet::{
     start_index_thread, IndexController, IndexRequest, IndexResponse, IndexThreadState,
@@ -20,14 +44,8 @@ pub use wallet::{
 
 use clap::ArgMatches;
 
-use crate::utils::printer::OutputFormat;
+use crate::utils::printer::Printable;
 
 pub trait CliSubCommand {
-    fn process(
-        &mut self,
-        matches: &ArgMatches,
-        format: OutputFormat,
-        color: bool,
-        debug: bool,
-    ) -> Result<String, String>;
+    fn process(&mut self, matches: &ArgMatches, debug: bool) -> Result<Box<dyn Printable>, String>;
 }
@@ -0,0 +1,204 @@
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use ckb_jsonrpc_types::HeaderView;
+use ckb_sdk::HttpRpcClient;
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use super::CliSubCommand;
+use crate::utils::{
+    arg_parser::{ArgParser, DurationParser, FromStrParser, UrlParser},
+    printer::Printable,
+};
+
+pub struct NodeSubCommand<'a> {
+    rpc_client: &'a mut HttpRpcClient,
+}
+
+impl<'a> NodeSubCommand<'a> {
+    pub fn new(rpc_client: &'a mut HttpRpcClient) -> NodeSubCommand<'a> {
+        NodeSubCommand { rpc_client }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        SubCommand::with_name(name)
+            .about("Node status")
+            .subcommands(vec![SubCommand::with_name("sync-status")
+                .about("Show sync progress of the node, optionally wait until it is synced (exit with error on timeout)")
+                .arg(
+                    Arg::with_name("reference-url")
+                        .long("reference-url")
+                        .takes_value(true)
+                        .validator(|input| UrlParser.validate(input))
+                        .help("RPC url of another node, its tip is used as the best known block number (peers do not report their tips by RPC)"),
+                )
+                .arg(
+                    Arg::with_name("wait")
+                        .long("wait")
+                        .help("Wait until the node reaches --target-height, or is --within N blocks of the reference node, or leaves initial block download"),
+                )
+                .arg(
+                    Arg::with_name("target-height")
+                        .long("target-height")
+                        .takes_value(true)
+                        .validator(|input| FromStrParser::<u64>::default().validate(input))
+                        .help("Block number to wait for"),
+                )
+                .arg(
+                    Arg::with_name("within")
+                        .long("within")
+                        .takes_value(true)
+                        .requires("reference-url")
+                        .validator(|input| FromStrParser::<u64>::default().validate(input))
+                        .help("Max number of blocks behind the reference node to wait for"),
+                )
+                .arg(
+                    Arg::with_name("timeout")
+                        .long("timeout")
+                        .takes_value(true)
+                        .default_value("30m")
+                        .validator(|input| DurationParser.validate(input))
+                        .help("Max time to wait"),
+                )
+                .arg(
+                    Arg::with_name("interval")
+                        .long("interval")
+                        .takes_value(true)
+                        .default_value("5s")
+                        .validator(|input| DurationParser.validate(input))
+                        .help("Time between status checks"),
+                )])
+    }
+
+    fn sync_status(
+        &mut self,
+        reference_client: Option<&mut HttpRpcClient>,
+    ) -> Result<SyncStatus, String> {
+        let tip_header: HeaderView = self
+            .rpc_client
+            .get_tip_header()
+            .call()
+            .map_err(|err| format!("Send get_tip_header error: {}", err))?;
+        let chain_info = self
+            .rpc_client
+            .get_blockchain_info()
+            .call()
+            .map_err(|err| format!("Send get_blockchain_info error: {}", err))?;
+        let tx_pool_info = self
+            .rpc_client
+            .tx_pool_info()
+            .call()
+            .map_err(|err| format!("Send tx_pool_info error: {}", err))?;
+        let peers = self
+            .rpc_client
+            .get_peers()
+            .call()
+            .map_err(|err| format!("Send get_peers error: {}", err))?;
+        let best_known_number = match reference_client {
+            Some(client) => {
+                let header: HeaderView = client.get_tip_header().call().map_err(|err| {
+                    format!("Send get_tip_header to reference node error: {}", err)
+                })?;
+                Some(header.inner.number.value())
+            }
+            None => None,
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_millis() as u64;
+        let tip_number = tip_header.inner.number.value();
+        Ok(SyncStatus {
+            tip_number,
+            tip_timestamp: tip_header.inner.timestamp.value(),
+            tip_age_secs: now.saturating_sub(tip_header.inner.timestamp.value()) / 1000,
+            is_initial_block_download: chain_info.is_initial_block_download,
+            best_known_number,
+            behind: best_known_number.map(|number| number.saturating_sub(tip_number)),
+            peers: peers.0.len(),
+            orphan_txs: tx_pool_info.orphan.value(),
+        })
+    }
+}
+
+impl<'a> CliSubCommand for NodeSubCommand<'a> {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        _debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        match matches.subcommand() {
+            ("sync-status", Some(m)) => {
+                let mut reference_client = m.value_of("reference-url").map(HttpRpcClient::from_uri);
+                let target_height: Option<u64> =
+                    FromStrParser::<u64>::default().from_matches_opt(m, "target-height", false)?;
+                let within: Option<u64> =
+                    FromStrParser::<u64>::default().from_matches_opt(m, "within", false)?;
+                let timeout: Duration = DurationParser.from_matches(m, "timeout")?;
+                let interval: Duration = DurationParser.from_matches(m, "interval")?;
+
+                let mut status = self.sync_status(reference_client.as_mut())?;
+                if !m.is_present("wait") {
+                    return Ok(Box::new(status.to_json()));
+                }
+                let start_time = Instant::now();
+                while !status.is_ready(target_height, within) {
+                    if start_time.elapsed() >= timeout {
+                        return Err(format!(
+                            "Timeout after {}s, node is not synced: {}",
+                            timeout.as_secs(),
+                            status.to_json()
+                        ));
+                    }
+                    thread::sleep(interval);
+                    status = self.sync_status(reference_client.as_mut())?;
+                }
+                let mut resp = status.to_json();
+                resp["waited_secs"] = serde_json::json!(start_time.elapsed().as_secs());
+                Ok(Box::new(resp))
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
+
+struct SyncStatus {
+    tip_number: u64,
+    tip_timestamp: u64,
+    tip_age_secs: u64,
+    is_initial_block_download: bool,
+    // Tip of the reference node
+    best_known_number: Option<u64>,
+    behind: Option<u64>,
+    peers: usize,
+    orphan_txs: u64,
+}
+
+impl SyncStatus {
+    // Without target height or reference node, the node is ready when it
+    // leaves initial block download.
+    fn is_ready(&self, target_height: Option<u64>, within: Option<u64>) -> bool {
+        if let Some(target_height) = target_height {
+            if self.tip_number < target_height {
+                return false;
+            }
+        }
+        match (within, self.behind) {
+            (Some(within), Some(behind)) => behind <= within,
+            _ => target_height.is_some() || !self.is_initial_block_download,
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "tip_number": self.tip_number,
+            "tip_timestamp": self.tip_timestamp,
+            "tip_age_secs": self.tip_age_secs,
+            "is_initial_block_download": self.is_initial_block_download,
+            "best_known_number": self.best_known_number,
+            "behind": self.behind,
+            "peers": self.peers,
+            "orphan_txs": self.orphan_txs,
+        })
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use regex::{Captures, Regex};
+use serde_derive::{Deserialize, Serialize};
+
+use super::CliSubCommand;
+use crate::utils::{
+    arg_parser::{ArgParser, FilePathParser},
+    printer::Printable,
+};
+
+const VAR_PATTERN: &str = r"\$\{\s*(?P<key>[^\s}]+)\s*\}";
+// Top level options of the run which also apply to its steps
+const FORWARDED_OPTIONS: [&str; 6] = [
+    "profile",
+    "min-confirmations",
+    "lang",
+    "log-file",
+    "log-format",
+    "rpc-cache-size",
+];
+
+pub struct PipelineSubCommand {
+    url: String,
+    // Top level arguments put before every step command
+    global_args: Vec<String>,
+}
+
+impl PipelineSubCommand {
+    pub fn new(url: String, global_args: Vec<String>) -> PipelineSubCommand {
+        PipelineSubCommand { url, global_args }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        SubCommand::with_name(name)
+            .about("Run a sequence of dependent ckb-cli commands described in a yaml file")
+            .subcommands(vec![SubCommand::with_name("run")
+                .about("Run pipeline steps in order, ${step.path} in a command is replaced by the json output of an earlier step")
+                .arg(
+                    Arg::with_name("pipeline-path")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| FilePathParser::new(true).validate(input))
+                        .help("Pipeline file (yaml format: steps: [{name, command}])"),
+                )
+                .arg(
+                    Arg::with_name("resume")
+                        .long("resume")
+                        .help("Skip steps already finished in last run (saved in <pipeline-path>.state)"),
+                )])
+    }
+
+    fn run(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let path: PathBuf = FilePathParser::new(true).from_matches(m, "pipeline-path")?;
+        let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+        let pipeline: Pipeline = serde_yaml::from_str(&content)
+            .map_err(|err| format!("Invalid pipeline file: {}", err))?;
+        pipeline.check()?;
+
+        let mut state_path = path.clone().into_os_string();
+        state_path.push(".state");
+        let state_path = PathBuf::from(state_path);
+        let mut state = if m.is_present("resume") && state_path.exists() {
+            PipelineState::load(&state_path)?
+        } else {
+            PipelineState::default()
+        };
+
+        let var_regex = Regex::new(VAR_PATTERN).unwrap();
+        let mut executed = Vec::new();
+        for step in &pipeline.steps {
+            if state.outputs.contains_key(&step.name) {
+                continue;
+            }
+            // Split before replacing, so a value with spaces or quotes stays
+            // one argument
+            let command = shell_words::split(&step.command)
+                .map_err(|err| format!("Invalid command of step {}: {}", step.name, err))?
+                .iter()
+                .map(|arg| state.replace_vars(&var_regex, arg))
+                .collect::<Result<Vec<_>, String>>()?;
+            eprintln!("[{}]: {}", step.name, command.join(" "));
+            let output = self
+                .execute(command)
+                .map_err(|err| format!("Step {} failed: {}", step.name, err))?;
+            state.outputs.insert(step.name.clone(), output);
+            state.save(&state_path)?;
+            executed.push(step.name.clone());
+        }
+        if let Err(err) = fs::remove_file(&state_path) {
+            // No state file when there is no step to run
+            if err.kind() != io::ErrorKind::NotFound {
+                return Err(err.to_string());
+            }
+        }
+        Ok(serde_json::json!({
+            "executed": executed,
+            "outputs": state.outputs,
+        }))
+    }
+
+    fn execute(&self, command: Vec<String>) -> Result<serde_json::Value, String> {
+        let args = step_args(command, &self.global_args, ckb_sdk::is_offline());
+        let exe = env::current_exe().map_err(|err| err.to_string())?;
+        let mut process = Command::new(exe);
+        process.args(&args).env("API_URL", &self.url);
+        let rpc_options = ckb_sdk::rpc_client_options();
+        if rpc_options != ckb_sdk::RpcClientOptions::default() {
+            let rpc_options = serde_json::to_string(&rpc_options).map_err(|err| err.to_string())?;
+            process.env("RPC_OPTIONS", rpc_options);
+        }
+        let output = process
+            .stdin(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|err| err.to_string())?;
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+        if !output.status.success() {
+            return Err(format!("exit with {}: {}", output.status, stdout));
+        }
+        Ok(serde_json::from_str(&stdout).unwrap_or_else(|_| serde_json::Value::String(stdout)))
+    }
+}
+
+impl CliSubCommand for PipelineSubCommand {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        _debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        match matches.subcommand() {
+            ("run", Some(m)) => {
+                let resp = self.run(m)?;
+                Ok(Box::new(resp))
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
+
+/// Top level options and flags given to this run, to be forwarded to steps
+pub fn global_args(matches: &ArgMatches) -> Vec<String> {
+    let mut args = Vec::new();
+    for name in FORWARDED_OPTIONS.iter() {
+        if let Some(value) = matches.value_of(name) {
+            args.push(format!("--{}", name));
+            args.push(value.to_owned());
+        }
+    }
+    if matches.is_present("trace") {
+        args.push("--trace".to_owned());
+    }
+    args
+}
+
+// Top level flags go before the step command so they apply to any subcommand
+fn step_args(command: Vec<String>, global_args: &[String], offline: bool) -> Vec<String> {
+    let mut args: Vec<String> = vec!["--output-format", "json", "--no-color"]
+        .into_iter()
+        .map(ToOwned::to_owned)
+        .collect();
+    if offline {
+        args.push("--offline".to_owned());
+    }
+    args.extend(global_args.iter().cloned());
+    args.extend(command);
+    args
+}
+
+#[derive(Deserialize)]
+struct Pipeline {
+    steps: Vec<PipelineStep>,
+}
+
+#[derive(Deserialize)]
+struct PipelineStep {
+    name: String,
+    command: String,
+}
+
+impl Pipeline {
+    fn check(&self) -> Result<(), String> {
+        let mut names = Vec::new();
+        for step in &self.steps {
+            if step.name.is_empty() || step.name.contains('.') {
+                return Err(format!("Invalid step name: {:?}", step.name));
+            }
+            if names.contains(&&step.name) {
+                return Err(format!("Duplicated step name: {}", step.name));
+            }
+            names.push(&step.name);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct PipelineState {
+    // step name => json output
+    outputs: BTreeMap<String, serde_json::Value>,
+}
+
+impl PipelineState {
+    fn load(path: &Path) -> Result<PipelineState, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        serde_json::from_str(&content).map_err(|err| format!("Invalid state file: {}", err))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, content).map_err(|err| err.to_string())
+    }
+
+    // ${deploy.tx_hash} or ${deploy} (whole output), array index is also supported: ${step.cells.0}
+    fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        let mut parts = key.split('.');
+        let root = self.outputs.get(parts.next()?)?;
+        parts.try_fold(root, |value, part| match part.parse::<usize>() {
+            Ok(index) => value.get(index).or_else(|| value.get(part)),
+            Err(_) => value.get(part),
+        })
+    }
+
+    fn replace_vars(&self, regex: &Regex, line: &str) -> Result<String, String> {
+        let mut missing = Vec::new();
+        let result = regex
+            .replace_all(line, |caps: &Captures| {
+                let key = &caps["key"];
+                match self.get(key) {
+                    Some(serde_json::Value::String(s)) => s.to_owned(),
+                    Some(serde_json::Value::Number(n)) => n.to_string(),
+                    Some(serde_json::Value::Bool(b)) => b.to_string(),
+                    Some(value) => value.to_string(),
+                    None => {
+                        missing.push(key.to_owned());
+                        String::new()
+                    }
+                }
+            })
+            .into_owned();
+        if missing.is_empty() {
+            Ok(result)
+        } else {
+            Err(format!("Undefined variables: {}", missing.join(", ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(line: &str) -> Vec<String> {
+        shell_words::split(line).unwrap()
+    }
+
+    #[test]
+    fn test_step_args() {
+        let args = step_args(split("account list --stale-days 30"), &[], true);
+        let matches = crate::build_cli("", "")
+            .get_matches_from_safe(std::iter::once("ckb-cli".to_owned()).chain(args))
+            .unwrap();
+        assert!(matches.is_present("offline"));
+        assert_eq!(matches.value_of("output-format"), Some("json"));
+        let (name, sub_matches) = matches.subcommand();
+        assert_eq!(name, "account");
+        assert!(sub_matches.unwrap().subcommand_matches("list").is_some());
+
+        let args = step_args(split("account list"), &[], false);
+        assert!(!args.contains(&"--offline".to_owned()));
+
+        let run = crate::build_cli("", "")
+            .get_matches_from_safe(split(
+                "ckb-cli --profile testnet --min-confirmations 3 --trace --no-color pipeline run p.yaml",
+            ))
+            .unwrap();
+        let forwarded = global_args(&run);
+        assert_eq!(
+            forwarded,
+            split("--profile testnet --min-confirmations 3 --trace")
+        );
+        let args = step_args(split("account list"), &forwarded, false);
+        let matches = crate::build_cli("", "")
+            .get_matches_from_safe(std::iter::once("ckb-cli".to_owned()).chain(args))
+            .unwrap();
+        assert_eq!(matches.value_of("profile"), Some("testnet"));
+        assert_eq!(matches.value_of("min-confirmations"), Some("3"));
+    }
+
+    #[test]
+    fn test_replace_vars() {
+        let mut state = PipelineState::default();
+        state.outputs.insert(
+            "memo".to_owned(),
+            serde_json::json!({"text": "pay 'rent' now"}),
+        );
+        let regex = Regex::new(VAR_PATTERN).unwrap();
+        let command = split("wallet transfer --memo ${memo.text}")
+            .iter()
+            .map(|arg| state.replace_vars(&regex, arg))
+            .collect::<Result<Vec<_>, String>>()
+            .unwrap();
+        assert_eq!(command[3], "pay 'rent' now");
+        assert!(state.replace_vars(&regex, "${missing}").is_err());
+    }
+}
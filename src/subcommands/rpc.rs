@@ -1,8 +1,8 @@
 use ckb_jsonrpc_types::{
-    BlockNumber, EpochNumber, OutPoint, Timestamp, Transaction, Uint32, Uint64,
+    BlockNumber, EpochNumber, HeaderView, OutPoint, Timestamp, Transaction, Uint32, Uint64,
 };
-use ckb_sdk::HttpRpcClient;
-use ckb_types::H256;
+use ckb_sdk::{transactions_root_from_proof, HttpRpcClient, TransactionProof};
+use ckb_types::{core, prelude::*, H256};
 use clap::{App, Arg, ArgMatches, SubCommand};
 use ipnetwork::IpNetwork;
 use multiaddr::Multiaddr;
@@ -14,7 +14,7 @@ use super::CliSubCommand;
 use crate::utils::arg_parser::{
     ArgParser, DurationParser, FilePathParser, FixedHashParser, FromStrParser,
 };
-use crate::utils::printer::{OutputFormat, Printable};
+use crate::utils::printer::{Printable, Text};
 
 pub struct RpcSubCommand<'a> {
     rpc_client: &'a mut HttpRpcClient,
@@ -133,6 +133,48 @@ impl<'a> RpcSubCommand<'a> {
                 SubCommand::with_name("get_transaction")
                     .about("Get transaction content by transaction hash")
                     .arg(arg_hash.clone().help("Tx hash")),
+                SubCommand::with_name("get_transaction_proof")
+                    .about("Get merkle proof of transactions included in a block")
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .long("tx-hash")
+                            .takes_value(true)
+                            .multiple(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Transaction hash (all transactions must be in the same block)"),
+                    )
+                    .arg(
+                        Arg::with_name("block-hash")
+                            .long("block-hash")
+                            .takes_value(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Block hash looking for the transactions in"),
+                    ),
+                SubCommand::with_name("verify_transaction_proof")
+                    .about("Verify a transaction proof locally against a block header (not a rpc method)")
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .long("tx-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Transaction hash"),
+                    )
+                    .arg(
+                        Arg::with_name("proof-path")
+                            .long("proof-path")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Transaction proof file (output of get_transaction_proof), request the node if not given"),
+                    )
+                    .arg(
+                        Arg::with_name("header-path")
+                            .long("header-path")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Trusted block header file (output of get_header), request the node if not given"),
+                    ),
                 // [Indexer]
                 SubCommand::with_name("deindex_lock_hash")
                     .arg(arg_hash.clone().help("Lock script hash"))
@@ -233,10 +275,8 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
     fn process(
         &mut self,
         matches: &ArgMatches,
-        format: OutputFormat,
-        color: bool,
         _debug: bool,
-    ) -> Result<String, String> {
+    ) -> Result<Box<dyn Printable>, String> {
         match matches.subcommand() {
             // [Chain]
             ("get_block", Some(m)) => {
@@ -247,7 +287,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_block(hash)
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_block_by_number", Some(m)) => {
                 let number: u64 = FromStrParser::<u64>::default().from_matches(m, "number")?;
@@ -257,7 +297,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_block_by_number(BlockNumber::from(number))
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_block_hash", Some(m)) => {
                 let number: u64 = FromStrParser::<u64>::default().from_matches(m, "number")?;
@@ -267,7 +307,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_block_hash(BlockNumber::from(number))
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_cellbase_output_capacity_details", Some(m)) => {
                 let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
@@ -277,7 +317,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_cellbase_output_capacity_details(hash)
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_cells_by_lock_hash", Some(m)) => {
                 let lock_hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
@@ -293,7 +333,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     )
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_current_epoch", _) => {
                 let resp = self
@@ -301,7 +341,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_current_epoch()
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_epoch_by_number", Some(m)) => {
                 let number: u64 = FromStrParser::<u64>::default().from_matches(m, "number")?;
@@ -310,7 +350,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_epoch_by_number(EpochNumber::from(number))
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_header", Some(m)) => {
                 let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
@@ -320,7 +360,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_header(hash)
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_header_by_number", Some(m)) => {
                 let number: u64 = FromStrParser::<u64>::default().from_matches(m, "number")?;
@@ -330,7 +370,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_header_by_number(BlockNumber::from(number))
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_live_cell", Some(m)) => {
                 let tx_hash: H256 =
@@ -347,7 +387,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_live_cell(out_point, with_data)
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_tip_block_number", _) => {
                 let resp = self
@@ -355,7 +395,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_tip_block_number()
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_tip_header", _) => {
                 let resp = self
@@ -363,7 +403,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_tip_header()
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_transaction", Some(m)) => {
                 let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
@@ -373,7 +413,73 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_transaction(hash)
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
+            }
+            ("get_transaction_proof", Some(m)) => {
+                let tx_hashes: Vec<H256> =
+                    FixedHashParser::<H256>::default().from_matches_vec(m, "tx-hash")?;
+                let block_hash: Option<H256> =
+                    FixedHashParser::<H256>::default().from_matches_opt(m, "block-hash", false)?;
+
+                let resp = self
+                    .rpc_client
+                    .get_transaction_proof(tx_hashes, block_hash)
+                    .call()
+                    .map_err(|err| err.to_string())?;
+                Ok(Box::new(resp))
+            }
+            ("verify_transaction_proof", Some(m)) => {
+                let tx_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+                let proof_path: Option<PathBuf> =
+                    FilePathParser::new(true).from_matches_opt(m, "proof-path", false)?;
+                let header_path: Option<PathBuf> =
+                    FilePathParser::new(true).from_matches_opt(m, "header-path", false)?;
+
+                let proof: TransactionProof = match proof_path {
+                    Some(path) => {
+                        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+                        serde_yaml::from_str(&content).map_err(|err| err.to_string())?
+                    }
+                    None => self
+                        .rpc_client
+                        .get_transaction_proof(vec![tx_hash.clone()], None)
+                        .call()
+                        .map_err(|err| err.to_string())?,
+                };
+                let header: core::HeaderView = match header_path {
+                    Some(path) => {
+                        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+                        let header: HeaderView =
+                            serde_yaml::from_str(&content).map_err(|err| err.to_string())?;
+                        header.into()
+                    }
+                    None => self
+                        .rpc_client
+                        .get_header(proof.block_hash.clone())
+                        .call()
+                        .map_err(|err| err.to_string())?
+                        .0
+                        .ok_or_else(|| format!("Header not found: {:#x}", proof.block_hash))?
+                        .into(),
+                };
+                let header_hash: H256 = header.hash().unpack();
+                if header_hash != proof.block_hash {
+                    return Err(format!(
+                        "Block hash mismatch, proof: {:#x}, header: {:#x}",
+                        proof.block_hash, header_hash
+                    ));
+                }
+                let transactions_root: H256 = header.transactions_root().unpack();
+                let proof_root = transactions_root_from_proof(&proof, &tx_hash);
+                let resp = serde_json::json!({
+                    "block_hash": header_hash,
+                    "block_number": header.number(),
+                    "transactions_root": transactions_root,
+                    "proof_root": proof_root,
+                    "verified": proof_root.as_ref() == Some(&transactions_root),
+                });
+                Ok(Box::new(resp))
             }
             // [Indexer]
             ("deindex_lock_hash", Some(m)) => {
@@ -383,7 +489,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .deindex_lock_hash(hash)
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(String::from("DONE"))
+                Ok(Box::new(Text::from("DONE")))
             }
             ("get_live_cells_by_lock_hash", Some(m)) => {
                 let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
@@ -401,7 +507,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     )
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_transactions_by_lock_hash", Some(m)) => {
                 let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
@@ -419,7 +525,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     )
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("index_lock_hash", Some(m)) => {
                 let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
@@ -431,7 +537,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .index_lock_hash(hash, index_from.map(BlockNumber::from))
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             // [Net]
             ("get_banned_addresses", _) => {
@@ -440,7 +546,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_banned_addresses()
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get_peers", _) => {
                 let resp = self
@@ -448,7 +554,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_peers()
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("local_node_info", _) => {
                 let resp = self
@@ -456,7 +562,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .local_node_info()
                     .call()
                     .map_err(|err| err.description().to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("set_ban", Some(m)) => {
                 let address: IpNetwork =
@@ -471,7 +577,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .set_ban(address.to_string(), command, ban_time, absolute, reason)
                     .call()
                     .map_err(|err| err.description().to_string())?;
-                Ok(String::from("DONE"))
+                Ok(Box::new(Text::from("DONE")))
             }
             // [Pool]
             ("tx_pool_info", _) => {
@@ -480,7 +586,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .tx_pool_info()
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             // [Stats]
             ("get_blockchain_info", _) => {
@@ -489,7 +595,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .get_blockchain_info()
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             // [IntegrationTest]
             ("add_node", Some(m)) => {
@@ -501,7 +607,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .add_node(peer_id, address.to_string())
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(String::from("DONE"))
+                Ok(Box::new(Text::from("DONE")))
             }
             ("remove_node", Some(m)) => {
                 let peer_id = m.value_of("peer-id").map(|v| v.to_string()).unwrap();
@@ -510,7 +616,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .remove_node(peer_id)
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(String::from("DONE"))
+                Ok(Box::new(Text::from("DONE")))
             }
             ("broadcast_transaction", Some(m)) => {
                 let json_path: PathBuf = FilePathParser::new(true).from_matches(m, "json-path")?;
@@ -523,7 +629,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .broadcast_transaction(tx)
                     .call()
                     .map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             _ => Err(matches.usage().to_owned()),
         }
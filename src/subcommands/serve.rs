@@ -0,0 +1,1032 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ckb_jsonrpc_types::JsonBytes;
+use ckb_sdk::{
+    multisig_config, serialize_signature, wallet::KeyStore, MockTransaction, MockTransactionHelper,
+    ReprMockTransaction,
+};
+use ckb_types::{prelude::*, H160, H256};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use super::{mock_tx::read_mock_tx, CliSubCommand};
+use crate::utils::{
+    arg,
+    arg_parser::{
+        ArgParser, CapacityParser, DurationParser, FilePathParser, FixedHashParser, FromStrParser,
+        HexParser,
+    },
+    i18n::{tr, tr_fmt},
+    other::{read_password, resolve_account, write_private_file},
+    printer::Printable,
+    serve_approval::{ApprovalStatus, ServeApprovals},
+    serve_ceremony::{
+        CeremonyRequest, CeremonySignature, CeremonyStatus, MultisigCeremony, ServeCeremonies,
+    },
+    serve_token::{ServeTokens, TokenPermission},
+};
+
+pub struct ServeSubCommand<'a> {
+    serve_tokens_file: PathBuf,
+    serve_approvals_file: PathBuf,
+    serve_ceremonies_file: PathBuf,
+    // Signs multisig ceremonies with --from-account
+    key_store: &'a mut KeyStore,
+}
+
+impl<'a> ServeSubCommand<'a> {
+    pub fn new(
+        serve_tokens_file: PathBuf,
+        serve_approvals_file: PathBuf,
+        serve_ceremonies_file: PathBuf,
+        key_store: &'a mut KeyStore,
+    ) -> ServeSubCommand<'a> {
+        ServeSubCommand {
+            serve_tokens_file,
+            serve_approvals_file,
+            serve_ceremonies_file,
+            key_store,
+        }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        let arg_name = Arg::with_name("name")
+            .long("name")
+            .takes_value(true)
+            .required(true)
+            .help("Token name");
+        let arg_token = arg::token();
+        let arg_permission = Arg::with_name("permission")
+            .long("permission")
+            .takes_value(true)
+            .possible_values(&["read-only", "build-only", "sign-and-send"])
+            .required(true);
+        let arg_ceremony_id = Arg::with_name("id")
+            .long("id")
+            .takes_value(true)
+            .required(true)
+            .help("Ceremony id");
+        SubCommand::with_name(name)
+            .about("Manage API tokens, transfer proposals and multisig ceremonies of serve mode. There is no network listener (no HTTP gateway yet), every operation runs locally from this command and reads/writes files in the ckb-cli directory")
+            .subcommands(vec![
+                SubCommand::with_name("add-token")
+                    .about("Create an API token, the secret is only printed once (needs the admin password, set by the first add-token)")
+                    .arg(arg_name.clone())
+                    .arg(arg_permission.clone().help(
+                        "read-only: query, build-only: also build unsigned transactions, sign-and-send: also sign and send",
+                    ))
+                    .arg(
+                        Arg::with_name("spending-limit")
+                            .long("spending-limit")
+                            .takes_value(true)
+                            .validator(|input| CapacityParser.validate(input))
+                            .help("Max total capacity (unit: CKB) sent with this token, default is unlimited"),
+                    ),
+                SubCommand::with_name("list-tokens").about("List API tokens (needs the admin password)"),
+                SubCommand::with_name("revoke-token")
+                    .about("Revoke an API token (needs the admin password)")
+                    .arg(arg_name),
+                SubCommand::with_name("check-token")
+                    .about("Check if a token is allowed to do an operation")
+                    .arg(arg_token.clone())
+                    .arg(arg_permission.help("Required permission"))
+                    .arg(
+                        Arg::with_name("capacity")
+                            .long("capacity")
+                            .takes_value(true)
+                            .validator(|input| CapacityParser.validate(input))
+                            .help("Capacity to send (unit: CKB)"),
+                    ),
+                SubCommand::with_name("propose-transfer")
+                    .about("Propose a transfer, it is signed and sent only after approved by `approvals approve` in interactive mode")
+                    .arg(arg_token)
+                    .arg(arg::from_account())
+                    .arg(arg::to_address().required(true))
+                    .arg(arg::capacity().required(true))
+                    .arg(arg::tx_fee().required(true))
+                    .arg(
+                        Arg::with_name("expires-in")
+                            .long("expires-in")
+                            .takes_value(true)
+                            .default_value("1h")
+                            .validator(|input| DurationParser.validate(input))
+                            .help("The proposal expires if not approved in this duration (examples: 30m, 1h, 1d)"),
+                    ),
+                SubCommand::with_name("multisig-publish")
+                    .about("Publish an unsigned transaction spending multisig (secp256k1_blake160_multisig_all) inputs, signers submit signatures on this machine or sign the file of multisig-export elsewhere (needs build-only)")
+                    .arg(arg_token.clone())
+                    .arg(
+                        Arg::with_name("tx-file")
+                            .long("tx-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Mock transaction data file (format: json), see: mock-tx"),
+                    )
+                    .arg(
+                        Arg::with_name("multisig-threshold")
+                            .long("multisig-threshold")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("Signatures required by the multisig lock"),
+                    )
+                    .arg(
+                        Arg::with_name("multisig-require-first-n")
+                            .long("multisig-require-first-n")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("The first n pubkeys must sign"),
+                    )
+                    .arg(
+                        Arg::with_name("multisig-pubkey-hash")
+                            .long("multisig-pubkey-hash")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H160>::default().validate(input))
+                            .help("Lock arg (blake160 of pubkey) of a multisig member, in order"),
+                    )
+                    .arg(
+                        Arg::with_name("expires-in")
+                            .long("expires-in")
+                            .takes_value(true)
+                            .default_value("1d")
+                            .validator(|input| DurationParser.validate(input))
+                            .help("The ceremony expires if not assembled in this duration (examples: 30m, 1h, 1d)"),
+                    ),
+                SubCommand::with_name("multisig-fetch")
+                    .about("Fetch a multisig ceremony: the transaction, the message to sign and who signed (needs read-only)")
+                    .arg(arg_token.clone())
+                    .arg(arg_ceremony_id.clone()),
+                SubCommand::with_name("multisig-submit")
+                    .about("Submit a signature of the ceremony message, it must be signed by a member who has not signed yet (needs build-only)")
+                    .arg(arg_token.clone())
+                    .arg(arg_ceremony_id.clone())
+                    .arg(
+                        Arg::with_name("signature")
+                            .long("signature")
+                            .takes_value(true)
+                            .required_unless("from-account")
+                            .validator(|input| HexParser.validate(input))
+                            .help("Recoverable signature (65 bytes, hex) of the message, made by an external signer"),
+                    )
+                    .arg(
+                        Arg::with_name("from-account")
+                            .long("from-account")
+                            .takes_value(true)
+                            .conflicts_with("signature")
+                            .help("Sign the message with this keystore account (lock-arg, address or unique lock-arg prefix)"),
+                    ),
+                SubCommand::with_name("multisig-export")
+                    .about("Write the request file of a ceremony for signers on other machines, hand it over by any channel and sign it with multisig-sign (needs read-only)")
+                    .arg(arg_token.clone())
+                    .arg(arg_ceremony_id.clone())
+                    .arg(
+                        Arg::with_name("output-file")
+                            .long("output-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output file of the ceremony request"),
+                    )
+                    .arg(arg::force().help("Overwrite the output file if it exists")),
+                SubCommand::with_name("multisig-sign")
+                    .about("Check a ceremony request file (the message must be the signing message of its transaction) and sign it with a keystore account, no token or ceremony of this machine is used")
+                    .arg(
+                        Arg::with_name("request-file")
+                            .long("request-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Ceremony request file written by multisig-export"),
+                    )
+                    .arg(
+                        Arg::with_name("from-account")
+                            .long("from-account")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Sign with this keystore account (lock-arg, address or unique lock-arg prefix)"),
+                    )
+                    .arg(
+                        Arg::with_name("output-file")
+                            .long("output-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output file of the signature, send it back to the coordinator"),
+                    )
+                    .arg(arg::force().help("Overwrite the output file if it exists")),
+                SubCommand::with_name("multisig-import")
+                    .about("Submit a signature file written by multisig-sign (needs build-only)")
+                    .arg(arg_token.clone())
+                    .arg(
+                        Arg::with_name("signature-file")
+                            .long("signature-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Signature file written by multisig-sign"),
+                    ),
+                SubCommand::with_name("multisig-assemble")
+                    .about("Put the submitted signatures into the transaction when the threshold is reached, send it by `mock-tx send` (needs sign-and-send, only the publishing token)")
+                    .arg(arg_token)
+                    .arg(arg_ceremony_id)
+                    .arg(
+                        Arg::with_name("output-file")
+                            .long("output-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output file of the signed transaction"),
+                    )
+                    .arg(arg::force().help("Overwrite the output file if it exists")),
+                SubCommand::with_name("spec")
+                    .about("Print a machine readable description of the serve operations, their arguments and permissions"),
+            ])
+    }
+
+    // Load ceremonies and expire the outdated ones
+    fn load_ceremonies(&self) -> Result<ServeCeremonies, String> {
+        let mut ceremonies = ServeCeremonies::load(&self.serve_ceremonies_file)?;
+        if ceremonies.expire(now_millis()) > 0 {
+            ceremonies.save()?;
+        }
+        Ok(ceremonies)
+    }
+
+    fn multisig_publish(
+        &mut self,
+        m: &ArgMatches,
+        tokens: &ServeTokens,
+    ) -> Result<serde_json::Value, String> {
+        let token_name = tokens
+            .authorize(m.value_of("token").unwrap(), TokenPermission::BuildOnly, 0)?
+            .name
+            .clone();
+        let threshold: u8 = FromStrParser::<u8>::default().from_matches(m, "multisig-threshold")?;
+        let require_first_n: u8 =
+            FromStrParser::<u8>::default().from_matches(m, "multisig-require-first-n")?;
+        let pubkey_hashes: Vec<H160> =
+            FixedHashParser::<H160>::default().from_matches_vec(m, "multisig-pubkey-hash")?;
+        let config = multisig_config(require_first_n, threshold, &pubkey_hashes)?;
+        let expires_in: Duration = DurationParser.from_matches(m, "expires-in")?;
+
+        let mut mock_tx = read_mock_tx(m)?;
+        let (lock_hash, message) = {
+            let mut helper = MockTransactionHelper::new(&mut mock_tx);
+            // Inputs must be in mock info, signers see what is spent
+            let (lock_hash, idxs) = helper.multisig_group(&config, |_| Ok(None))?;
+            (lock_hash, helper.multisig_message(&config, &idxs)?)
+        };
+        let created_at = now_millis();
+        let mut ceremonies = self.load_ceremonies()?;
+        let ceremony = ceremonies
+            .publish(
+                &token_name,
+                mock_tx.into(),
+                JsonBytes::from_bytes(config),
+                lock_hash.unpack(),
+                message,
+                created_at,
+                created_at + expires_in.as_millis() as u64,
+            )
+            .clone();
+        ceremonies.save()?;
+        Ok(ceremony_summary(&ceremony))
+    }
+
+    fn multisig_submit(
+        &mut self,
+        m: &ArgMatches,
+        tokens: &ServeTokens,
+    ) -> Result<serde_json::Value, String> {
+        tokens.authorize(m.value_of("token").unwrap(), TokenPermission::BuildOnly, 0)?;
+        let mut ceremonies = self.load_ceremonies()?;
+        let ceremony = ceremonies.get_mut(m.value_of("id").unwrap())?;
+        let signature = match m.value_of("from-account") {
+            Some(input) => {
+                let lock_arg = resolve_account(self.key_store, input)?;
+                let prompt = tr_fmt("Password for [{}]", &[&format!("{:x}", lock_arg)]);
+                let password = read_password(false, Some(prompt.as_str()))?;
+                let signature = self
+                    .key_store
+                    .sign_recoverable_with_password(
+                        &lock_arg,
+                        &ceremony.message,
+                        password.as_bytes(),
+                    )
+                    .map_err(|err| err.to_string())?;
+                serialize_signature(&signature).to_vec()
+            }
+            None => HexParser.from_matches(m, "signature")?,
+        };
+        let signer = ceremony.submit(&signature)?;
+        let resp = serde_json::json!({
+            "signer": signer,
+            "signed": ceremony.signatures.len(),
+            "threshold": ceremony.threshold(),
+            "ready": ceremony.ready_signatures().is_ok(),
+        });
+        ceremonies.save()?;
+        Ok(resp)
+    }
+
+    fn multisig_export(
+        &mut self,
+        m: &ArgMatches,
+        tokens: &ServeTokens,
+    ) -> Result<serde_json::Value, String> {
+        tokens.authorize(m.value_of("token").unwrap(), TokenPermission::ReadOnly, 0)?;
+        let output_path: PathBuf = FilePathParser::new(false).from_matches(m, "output-file")?;
+        let ceremonies = self.load_ceremonies()?;
+        let ceremony = ceremonies.get(m.value_of("id").unwrap())?;
+        if ceremony.status != CeremonyStatus::Collecting {
+            return Err(format!(
+                "Ceremony {} is already {:?}",
+                ceremony.id, ceremony.status
+            ));
+        }
+        let request = CeremonyRequest::from(ceremony);
+        let content = serde_json::to_string_pretty(&request).map_err(|err| err.to_string())?;
+        write_private_file(&output_path, content.as_bytes(), m.is_present("force"))?;
+        Ok(serde_json::json!({
+            "id": ceremony.id,
+            "message": ceremony.message,
+            "output-file": output_path,
+        }))
+    }
+
+    fn multisig_sign(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let request_path: PathBuf = FilePathParser::new(true).from_matches(m, "request-file")?;
+        let output_path: PathBuf = FilePathParser::new(false).from_matches(m, "output-file")?;
+        let content = fs::read_to_string(&request_path).map_err(|err| err.to_string())?;
+        let request: CeremonyRequest = serde_json::from_str(&content)
+            .map_err(|err| format!("Invalid ceremony request file: {}", err))?;
+        if request.expires_at <= now_millis() {
+            return Err(format!("Ceremony {} is expired", request.id));
+        }
+        // Never trust the message in the file, it must sign this transaction
+        let config = request.multisig_config.clone().into_bytes();
+        let mut mock_tx: MockTransaction = request.tx.clone().into();
+        let (lock_hash, message) = {
+            let mut helper = MockTransactionHelper::new(&mut mock_tx);
+            let (lock_hash, idxs) = helper.multisig_group(&config, |_| Ok(None))?;
+            (lock_hash, helper.multisig_message(&config, &idxs)?)
+        };
+        let lock_hash: H256 = lock_hash.unpack();
+        if message != request.message || lock_hash != request.lock_hash {
+            return Err(format!(
+                "Message of ceremony {} does not match its transaction",
+                request.id
+            ));
+        }
+        let lock_arg = resolve_account(self.key_store, m.value_of("from-account").unwrap())?;
+        let prompt = tr_fmt("Password for [{}]", &[&format!("{:x}", lock_arg)]);
+        let password = read_password(false, Some(prompt.as_str()))?;
+        let signature = self
+            .key_store
+            .sign_recoverable_with_password(&lock_arg, &message, password.as_bytes())
+            .map_err(|err| err.to_string())?;
+        let signature = CeremonySignature {
+            id: request.id.clone(),
+            message,
+            signature: JsonBytes::from_vec(serialize_signature(&signature).to_vec()),
+        };
+        let content = serde_json::to_string_pretty(&signature).map_err(|err| err.to_string())?;
+        write_private_file(&output_path, content.as_bytes(), m.is_present("force"))?;
+        let tx_hash: H256 = mock_tx.core_transaction().hash().unpack();
+        Ok(serde_json::json!({
+            "id": request.id,
+            "signer": lock_arg,
+            "tx-hash": tx_hash,
+            "output-file": output_path,
+        }))
+    }
+
+    fn multisig_import(
+        &mut self,
+        m: &ArgMatches,
+        tokens: &ServeTokens,
+    ) -> Result<serde_json::Value, String> {
+        tokens.authorize(m.value_of("token").unwrap(), TokenPermission::BuildOnly, 0)?;
+        let path: PathBuf = FilePathParser::new(true).from_matches(m, "signature-file")?;
+        let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+        let signature: CeremonySignature = serde_json::from_str(&content)
+            .map_err(|err| format!("Invalid ceremony signature file: {}", err))?;
+        let mut ceremonies = self.load_ceremonies()?;
+        let ceremony = ceremonies.get_mut(&signature.id)?;
+        let signer = ceremony.import(&signature)?;
+        let resp = serde_json::json!({
+            "id": ceremony.id,
+            "signer": signer,
+            "signed": ceremony.signatures.len(),
+            "threshold": ceremony.threshold(),
+            "ready": ceremony.ready_signatures().is_ok(),
+        });
+        ceremonies.save()?;
+        Ok(resp)
+    }
+
+    fn multisig_assemble(
+        &mut self,
+        m: &ArgMatches,
+        tokens: &ServeTokens,
+    ) -> Result<serde_json::Value, String> {
+        let token_name = tokens
+            .authorize(
+                m.value_of("token").unwrap(),
+                TokenPermission::SignAndSend,
+                0,
+            )?
+            .name
+            .clone();
+        let output_path: PathBuf = FilePathParser::new(false).from_matches(m, "output-file")?;
+        let mut ceremonies = self.load_ceremonies()?;
+        let ceremony = ceremonies.get_mut(m.value_of("id").unwrap())?;
+        if ceremony.token_name != token_name {
+            return Err(format!(
+                "Ceremony {} is published by token {}",
+                ceremony.id, ceremony.token_name
+            ));
+        }
+        if ceremony.status != CeremonyStatus::Collecting {
+            return Err(format!(
+                "Ceremony {} is already {:?}",
+                ceremony.id, ceremony.status
+            ));
+        }
+        let signatures = ceremony.ready_signatures()?;
+        let config = ceremony.multisig_config.clone().into_bytes();
+        let mut mock_tx: MockTransaction = ceremony.tx.clone().into();
+        let tx_hash: H256 = {
+            let mut helper = MockTransactionHelper::new(&mut mock_tx);
+            let (_, idxs) = helper.multisig_group(&config, |_| Ok(None))?;
+            helper.fill_multisig_witness(&config, &signatures, &idxs)?;
+            helper.mock_tx.core_transaction().hash().unpack()
+        };
+        let content = serde_json::to_string_pretty(&ReprMockTransaction::from(mock_tx))
+            .map_err(|err| err.to_string())?;
+        write_private_file(&output_path, content.as_bytes(), m.is_present("force"))?;
+        ceremony.status = CeremonyStatus::Assembled;
+        let resp = serde_json::json!({
+            "id": ceremony.id,
+            "tx-hash": tx_hash,
+            "output-file": output_path,
+        });
+        ceremonies.save()?;
+        Ok(resp)
+    }
+}
+
+// Credential a serve operation requires
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Access {
+    // Admin password of the tokens file
+    Admin,
+    // Token with at least this permission
+    Token(TokenPermission),
+    // Password of the signing keystore account
+    Keystore,
+    // Nothing, only describes the operations
+    Public,
+}
+
+impl Access {
+    fn to_json(self) -> serde_json::Value {
+        match self {
+            Access::Admin => serde_json::json!("admin"),
+            Access::Token(permission) => serde_json::json!(permission),
+            Access::Keystore => serde_json::json!("keystore"),
+            Access::Public => serde_json::Value::Null,
+        }
+    }
+}
+
+// Value of a serve operation argument
+#[derive(Clone, Copy)]
+struct ArgSpec {
+    name: &'static str,
+    // Format of the value, "flag" for arguments without value
+    value: &'static str,
+    required: bool,
+    // Required if this other argument is not given
+    required_unless: Option<&'static str>,
+    multiple: bool,
+    default: Option<&'static str>,
+}
+
+const fn optional(name: &'static str, value: &'static str) -> ArgSpec {
+    ArgSpec {
+        name,
+        value,
+        required: false,
+        required_unless: None,
+        multiple: false,
+        default: None,
+    }
+}
+
+const fn required(name: &'static str, value: &'static str) -> ArgSpec {
+    ArgSpec {
+        required: true,
+        ..optional(name, value)
+    }
+}
+
+const fn with_default(name: &'static str, value: &'static str, default: &'static str) -> ArgSpec {
+    ArgSpec {
+        default: Some(default),
+        ..optional(name, value)
+    }
+}
+
+const TOKEN: ArgSpec = required("token", "secret");
+const CEREMONY_ID: ArgSpec = required("id", "string");
+const OUTPUT_FILE: ArgSpec = required("output-file", "file-path");
+const FORCE: ArgSpec = optional("force", "flag");
+
+struct OperationSpec {
+    name: &'static str,
+    description: &'static str,
+    access: Access,
+    args: &'static [ArgSpec],
+}
+
+// The serve operations as published by `serve spec`, kept in sync with
+// `ServeSubCommand::subcommand` by test_spec_matches_cli
+const OPERATIONS: &[OperationSpec] = &[
+    OperationSpec {
+        name: "add-token",
+        description: "Create an API token, the secret is only returned once",
+        access: Access::Admin,
+        args: &[
+            required("name", "string"),
+            required("permission", "permission"),
+            optional("spending-limit", "capacity"),
+        ],
+    },
+    OperationSpec {
+        name: "list-tokens",
+        description: "List API tokens",
+        access: Access::Admin,
+        args: &[],
+    },
+    OperationSpec {
+        name: "revoke-token",
+        description: "Revoke an API token",
+        access: Access::Admin,
+        args: &[required("name", "string")],
+    },
+    OperationSpec {
+        name: "check-token",
+        description: "Check if a token is allowed to do an operation",
+        access: Access::Token(TokenPermission::ReadOnly),
+        args: &[
+            TOKEN,
+            required("permission", "permission"),
+            optional("capacity", "capacity"),
+        ],
+    },
+    OperationSpec {
+        name: "propose-transfer",
+        description: "Propose a transfer, it is sent after approved in interactive mode",
+        access: Access::Token(TokenPermission::SignAndSend),
+        args: &[
+            TOKEN,
+            optional("from-account", "account"),
+            required("to-address", "address"),
+            required("capacity", "capacity"),
+            required("tx-fee", "capacity"),
+            with_default("expires-in", "duration", "1h"),
+        ],
+    },
+    OperationSpec {
+        name: "multisig-publish",
+        description: "Publish an unsigned transaction spending multisig inputs",
+        access: Access::Token(TokenPermission::BuildOnly),
+        args: &[
+            TOKEN,
+            required("tx-file", "file-path"),
+            required("multisig-threshold", "u8"),
+            with_default("multisig-require-first-n", "u8", "0"),
+            ArgSpec {
+                multiple: true,
+                ..required("multisig-pubkey-hash", "h160")
+            },
+            with_default("expires-in", "duration", "1d"),
+        ],
+    },
+    OperationSpec {
+        name: "multisig-fetch",
+        description: "Fetch a multisig ceremony, its transaction and signing message",
+        access: Access::Token(TokenPermission::ReadOnly),
+        args: &[TOKEN, CEREMONY_ID],
+    },
+    OperationSpec {
+        name: "multisig-submit",
+        description: "Submit a signature of the ceremony message",
+        access: Access::Token(TokenPermission::BuildOnly),
+        args: &[
+            TOKEN,
+            CEREMONY_ID,
+            ArgSpec {
+                required_unless: Some("from-account"),
+                ..optional("signature", "hex")
+            },
+            optional("from-account", "account"),
+        ],
+    },
+    OperationSpec {
+        name: "multisig-export",
+        description: "Write the request file of a ceremony for signers on other machines",
+        access: Access::Token(TokenPermission::ReadOnly),
+        args: &[TOKEN, CEREMONY_ID, OUTPUT_FILE, FORCE],
+    },
+    OperationSpec {
+        name: "multisig-sign",
+        description: "Check and sign a ceremony request file with a keystore account",
+        access: Access::Keystore,
+        args: &[
+            required("request-file", "file-path"),
+            required("from-account", "account"),
+            OUTPUT_FILE,
+            FORCE,
+        ],
+    },
+    OperationSpec {
+        name: "multisig-import",
+        description: "Submit a signature file written by multisig-sign",
+        access: Access::Token(TokenPermission::BuildOnly),
+        args: &[TOKEN, required("signature-file", "file-path")],
+    },
+    OperationSpec {
+        name: "multisig-assemble",
+        description: "Put the submitted signatures into the transaction",
+        access: Access::Token(TokenPermission::SignAndSend),
+        args: &[TOKEN, CEREMONY_ID, OUTPUT_FILE, FORCE],
+    },
+    OperationSpec {
+        name: "spec",
+        description: "Describe the serve operations",
+        access: Access::Public,
+        args: &[],
+    },
+];
+
+// Ask the admin password, the first admin operation sets it
+fn unlock_admin(tokens: &mut ServeTokens) -> Result<(), String> {
+    if tokens.has_admin_password() {
+        let password = read_password(false, Some(tr("Admin password of serve tokens")))?;
+        tokens.verify_admin_password(password.as_bytes())
+    } else {
+        let password = read_password(true, Some(tr("New admin password of serve tokens")))?;
+        tokens.set_admin_password(password.as_bytes())?;
+        tokens.save()
+    }
+}
+
+// Machine readable description of the serve operations, they are command
+// line operations (there is no HTTP endpoint)
+fn spec() -> serde_json::Value {
+    let operations = OPERATIONS
+        .iter()
+        .map(|operation| {
+            let args = operation
+                .args
+                .iter()
+                .map(|arg| {
+                    serde_json::json!({
+                        "name": arg.name,
+                        "value": arg.value,
+                        "required": arg.required,
+                        "required_unless": arg.required_unless,
+                        "multiple": arg.multiple,
+                        "default": arg.default,
+                    })
+                })
+                .collect::<Vec<_>>();
+            serde_json::json!({
+                "name": operation.name,
+                "description": operation.description,
+                "permission": operation.access.to_json(),
+                "args": args,
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({
+        "invocation": "ckb-cli --output-format json serve <operation> [--<arg> <value>]...",
+        "operations": operations,
+        "enums": {
+            "permission": [
+                TokenPermission::ReadOnly,
+                TokenPermission::BuildOnly,
+                TokenPermission::SignAndSend,
+            ],
+            "approval_status": [
+                ApprovalStatus::Pending,
+                ApprovalStatus::Sent,
+                ApprovalStatus::Failed,
+                ApprovalStatus::Rejected,
+                ApprovalStatus::Expired,
+            ],
+            "ceremony_status": [
+                CeremonyStatus::Collecting,
+                CeremonyStatus::Assembled,
+                CeremonyStatus::Expired,
+            ],
+        },
+    })
+}
+
+fn ceremony_summary(ceremony: &MultisigCeremony) -> serde_json::Value {
+    serde_json::json!({
+        "id": ceremony.id,
+        "lock_hash": ceremony.lock_hash,
+        "message": ceremony.message,
+        "threshold": ceremony.threshold(),
+        "require_first_n": ceremony.require_first_n(),
+        "members": ceremony.members(),
+        "signed": ceremony.signatures.keys().collect::<Vec<_>>(),
+        "status": ceremony.status,
+        "expires_at": ceremony.expires_at,
+    })
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as u64
+}
+
+impl<'a> CliSubCommand for ServeSubCommand<'a> {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        _debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        let mut tokens = ServeTokens::load(&self.serve_tokens_file)?;
+        match matches.subcommand() {
+            ("add-token", Some(m)) => {
+                unlock_admin(&mut tokens)?;
+                let name = m.value_of("name").unwrap();
+                let permission = TokenPermission::from_name(m.value_of("permission").unwrap())?;
+                let spending_limit: Option<u64> =
+                    CapacityParser.from_matches_opt(m, "spending-limit", false)?;
+                let created_at = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("Time went backwards")
+                    .as_millis() as u64;
+                let secret = tokens.add(name, permission, spending_limit, created_at)?;
+                tokens.save()?;
+                let resp = serde_json::json!({
+                    "name": name,
+                    "permission": permission,
+                    "token": secret,
+                });
+                Ok(Box::new(resp))
+            }
+            ("list-tokens", _) => {
+                unlock_admin(&mut tokens)?;
+                let resp = tokens
+                    .tokens()
+                    .iter()
+                    .map(|token| {
+                        serde_json::json!({
+                            "name": token.name,
+                            "permission": token.permission,
+                            "spending_limit": token.spending_limit,
+                            "spent": token.spent,
+                            "created_at": token.created_at,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Box::new(serde_json::json!(resp)))
+            }
+            ("revoke-token", Some(m)) => {
+                unlock_admin(&mut tokens)?;
+                let token = tokens.revoke(m.value_of("name").unwrap())?;
+                tokens.save()?;
+                Ok(Box::new(serde_json::to_value(token).unwrap()))
+            }
+            ("check-token", Some(m)) => {
+                let permission = TokenPermission::from_name(m.value_of("permission").unwrap())?;
+                let capacity: u64 = CapacityParser
+                    .from_matches_opt(m, "capacity", false)?
+                    .unwrap_or(0);
+                let token = tokens.authorize(m.value_of("token").unwrap(), permission, capacity)?;
+                let resp = serde_json::json!({
+                    "name": token.name,
+                    "permission": token.permission,
+                    "remaining": token.remaining(),
+                });
+                Ok(Box::new(resp))
+            }
+            ("propose-transfer", Some(m)) => {
+                let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
+                let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+                let expires_in: Duration = DurationParser.from_matches(m, "expires-in")?;
+                let token_name = tokens
+                    .authorize(
+                        m.value_of("token").unwrap(),
+                        TokenPermission::SignAndSend,
+                        capacity,
+                    )?
+                    .name
+                    .clone();
+                let created_at = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("Time went backwards")
+                    .as_millis() as u64;
+                let expires_at = created_at + expires_in.as_millis() as u64;
+                let mut approvals = ServeApprovals::load(&self.serve_approvals_file)?;
+                approvals.expire(created_at);
+                let proposal = approvals
+                    .propose(
+                        &token_name,
+                        m.value_of("from-account").map(ToOwned::to_owned),
+                        m.value_of("to-address").unwrap().to_owned(),
+                        capacity,
+                        tx_fee,
+                        created_at,
+                        expires_at,
+                    )
+                    .clone();
+                approvals.save()?;
+                Ok(Box::new(serde_json::to_value(proposal).unwrap()))
+            }
+            ("multisig-publish", Some(m)) => Ok(Box::new(self.multisig_publish(m, &tokens)?)),
+            ("multisig-fetch", Some(m)) => {
+                tokens.authorize(m.value_of("token").unwrap(), TokenPermission::ReadOnly, 0)?;
+                let ceremonies = self.load_ceremonies()?;
+                let ceremony = ceremonies.get(m.value_of("id").unwrap())?;
+                let mut resp = ceremony_summary(ceremony);
+                resp["tx"] = serde_json::to_value(&ceremony.tx).unwrap();
+                Ok(Box::new(resp))
+            }
+            ("multisig-submit", Some(m)) => Ok(Box::new(self.multisig_submit(m, &tokens)?)),
+            ("multisig-export", Some(m)) => Ok(Box::new(self.multisig_export(m, &tokens)?)),
+            ("multisig-sign", Some(m)) => Ok(Box::new(self.multisig_sign(m)?)),
+            ("multisig-import", Some(m)) => Ok(Box::new(self.multisig_import(m, &tokens)?)),
+            ("multisig-assemble", Some(m)) => Ok(Box::new(self.multisig_assemble(m, &tokens)?)),
+            ("spec", _) => Ok(Box::new(spec())),
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Help message of `serve <args>`
+    fn help(args: &[&str]) -> String {
+        let mut argv = vec!["serve"];
+        argv.extend_from_slice(args);
+        argv.push("--help");
+        let err = ServeSubCommand::subcommand("serve")
+            .get_matches_from_safe(argv)
+            .unwrap_err();
+        assert_eq!(err.kind, clap::ErrorKind::HelpDisplayed);
+        err.message
+    }
+
+    // First word of the lines of a help section, wrapped lines are skipped
+    fn section_items(help: &str, section: &str) -> Vec<String> {
+        help.lines()
+            .skip_while(|line| !line.starts_with(section))
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .filter(|line| line.starts_with("    ") && !line[4..].starts_with(' '))
+            .map(|line| line.trim().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_spec_matches_cli() {
+        let subcommands = section_items(&help(&[]), "SUBCOMMANDS:")
+            .into_iter()
+            .map(|line| line.split_whitespace().next().unwrap().to_owned())
+            .filter(|name| name != "help")
+            .collect::<Vec<_>>();
+        assert_eq!(
+            subcommands,
+            OPERATIONS
+                .iter()
+                .map(|operation| operation.name)
+                .collect::<Vec<_>>()
+        );
+
+        for operation in OPERATIONS {
+            let help = help(&[operation.name]);
+            let mut options = section_items(&help, "OPTIONS:");
+            options.extend(section_items(&help, "FLAGS:"));
+            let mut options = options
+                .into_iter()
+                .filter(|line| line.starts_with("--"))
+                .map(|line| {
+                    let mut words = line[2..].split_whitespace();
+                    let name = words.next().unwrap().to_owned();
+                    let value = words.next().unwrap_or_default();
+                    let value = if value.starts_with('<') {
+                        if value.ends_with("...") {
+                            "multiple"
+                        } else {
+                            "single"
+                        }
+                    } else {
+                        "flag"
+                    };
+                    (name, value)
+                })
+                .filter(|(name, _)| name != "help" && name != "version")
+                .collect::<Vec<_>>();
+            options.sort();
+            let mut args = operation
+                .args
+                .iter()
+                .map(|arg| {
+                    let value = if arg.value == "flag" {
+                        "flag"
+                    } else if arg.multiple {
+                        "multiple"
+                    } else {
+                        "single"
+                    };
+                    (arg.name.to_owned(), value)
+                })
+                .collect::<Vec<_>>();
+            args.sort();
+            assert_eq!(options, args, "args of {}", operation.name);
+
+            let words = help.split_whitespace().collect::<Vec<_>>().join(" ");
+            for arg in operation.args {
+                if let Some(default) = arg.default {
+                    assert!(
+                        words.contains(&format!("[default: {}]", default)),
+                        "default of {} {}",
+                        operation.name,
+                        arg.name
+                    );
+                }
+            }
+            assert_eq!(
+                words.matches("[default: ").count(),
+                operation
+                    .args
+                    .iter()
+                    .filter(|arg| arg.default.is_some())
+                    .count(),
+                "defaults of {}",
+                operation.name
+            );
+
+            // Missing required_unless arguments are reported after the
+            // required ones, only check the required here
+            let required = operation
+                .args
+                .iter()
+                .filter(|arg| arg.required)
+                .collect::<Vec<_>>();
+            match ServeSubCommand::subcommand("serve")
+                .get_matches_from_safe(vec!["serve", operation.name])
+            {
+                Ok(_) => assert!(
+                    required.is_empty()
+                        && operation
+                            .args
+                            .iter()
+                            .all(|arg| arg.required_unless.is_none()),
+                    "{} requires args",
+                    operation.name
+                ),
+                Err(err) => {
+                    assert_eq!(err.kind, clap::ErrorKind::MissingRequiredArgument);
+                    for arg in required {
+                        assert!(
+                            err.message.contains(&format!("--{} <", arg.name)),
+                            "{} of {} is not required",
+                            arg.name,
+                            operation.name
+                        );
+                    }
+                }
+            }
+        }
+
+        let spec = spec();
+        let propose = spec["operations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|operation| operation["name"] == "propose-transfer")
+            .unwrap();
+        assert_eq!(propose["permission"], "sign-and-send");
+        assert_eq!(propose["args"][5]["default"], "1h");
+        assert_eq!(spec["operations"][0]["permission"], "admin");
+    }
+}
@@ -0,0 +1,167 @@
+use ckb_hash::blake2b_256;
+use ckb_sdk::{build_witness_with_key, Address, NetworkType, SECP256K1};
+use ckb_types::{
+    bytes::Bytes,
+    core::{capacity_bytes, Capacity, DepType, ScriptHashType, TransactionBuilder},
+    h256,
+    packed::{CellDep, CellInput, CellOutput, OutPoint, Script, WitnessArgs},
+    prelude::*,
+    H160, H256,
+};
+use clap::{App, ArgMatches, SubCommand};
+use faster_hex::hex_string;
+
+use super::CliSubCommand;
+use crate::utils::printer::Printable;
+
+// Everything below is fixed, the output must never change between runs or
+// versions (unless the protocol changed). Other SDKs compare against it.
+const TEST_PRIVKEYS: [H256; 3] = [
+    h256!("0x0000000000000000000000000000000000000000000000000000000000000001"),
+    h256!("0x0101010101010101010101010101010101010101010101010101010101010101"),
+    h256!("0xd00c06bfd800d27397002dca6fb0993d5ba6399b4238b2f29ee9deb97593d2bc"),
+];
+// Type hash of secp256k1_blake160_sighash_all on mainnet and testnet (Aggron)
+const SECP_CODE_HASH: H256 =
+    h256!("0x9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8");
+const SECP_DEP_TX_HASH: H256 =
+    h256!("0x71a7ba8fc96349fea0ed3a5c47992e3b4084b031a42264a018e0072e8172e46c");
+const INPUT_TX_HASH: H256 =
+    h256!("0x1111111111111111111111111111111111111111111111111111111111111111");
+
+#[derive(Default)]
+pub struct TestVectorSubCommand {}
+
+impl TestVectorSubCommand {
+    pub fn new() -> TestVectorSubCommand {
+        TestVectorSubCommand {}
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        SubCommand::with_name(name).about(
+            "Print reproducible test vectors (keys, addresses, transaction hashes, signed witnesses) for SDK test suites, no RPC required",
+        )
+    }
+}
+
+struct TestKey {
+    privkey_bytes: H256,
+    privkey: secp256k1::SecretKey,
+    pubkey: secp256k1::PublicKey,
+    lock_arg: H160,
+}
+
+impl TestKey {
+    fn new(privkey_bytes: &H256) -> TestKey {
+        let privkey = secp256k1::SecretKey::from_slice(privkey_bytes.as_bytes())
+            .expect("Invalid test private key");
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+        let lock_arg = H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20])
+            .expect("Generate hash(H160) from pubkey failed");
+        TestKey {
+            privkey_bytes: privkey_bytes.clone(),
+            privkey,
+            pubkey,
+            lock_arg,
+        }
+    }
+
+    fn lock_script(&self) -> Script {
+        Script::new_builder()
+            .code_hash(SECP_CODE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(self.lock_arg.as_bytes()).pack())
+            .build()
+    }
+}
+
+fn key_vector(key: &TestKey) -> serde_json::Value {
+    let address = Address::new_default(key.lock_arg.clone());
+    let lock_hash: H256 = key.lock_script().calc_script_hash().unpack();
+    serde_json::json!({
+        "privkey": key.privkey_bytes,
+        "pubkey": format!("0x{}", hex_string(&key.pubkey.serialize()[..]).unwrap()),
+        "lock_arg": format!("{:#x}", key.lock_arg),
+        "lock_hash": lock_hash,
+        "address": {
+            "mainnet": address.to_string(NetworkType::MainNet),
+            "testnet": address.to_string(NetworkType::TestNet),
+        },
+    })
+}
+
+// Transfer 100 CKB from keys[0] to keys[1], 899 CKB change back to keys[0], signed
+// the same way as `wallet transfer`.
+fn transfer_vector(from: &TestKey, to: &TestKey) -> serde_json::Value {
+    let tx = TransactionBuilder::default()
+        .cell_dep(
+            CellDep::new_builder()
+                .out_point(OutPoint::new(SECP_DEP_TX_HASH.pack(), 0))
+                .dep_type(DepType::DepGroup.into())
+                .build(),
+        )
+        .input(CellInput::new(OutPoint::new(INPUT_TX_HASH.pack(), 0), 0))
+        .output(
+            CellOutput::new_builder()
+                .capacity(capacity_bytes!(100).pack())
+                .lock(to.lock_script())
+                .build(),
+        )
+        .output_data(Bytes::new().pack())
+        .output(
+            CellOutput::new_builder()
+                .capacity(capacity_bytes!(899).pack())
+                .lock(from.lock_script())
+                .build(),
+        )
+        .output_data(Bytes::new().pack())
+        .build();
+
+    let init_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let sign_args = vec![
+        tx.hash().raw_data().to_vec(),
+        (init_witness.as_bytes().len() as u64)
+            .to_le_bytes()
+            .to_vec(),
+        init_witness.as_bytes().to_vec(),
+    ];
+    let message = H256::from_slice(&ckb_sdk::blake2b_args(&sign_args)).unwrap();
+    // RFC6979 deterministic nonce, same key and message always give same signature
+    let signature = build_witness_with_key(&from.privkey, &sign_args);
+    let witness = init_witness
+        .as_builder()
+        .lock(Some(signature.clone()).pack())
+        .build();
+    let signed_tx = tx
+        .as_advanced_builder()
+        .witness(witness.as_bytes().pack())
+        .build();
+    let tx_hash: H256 = signed_tx.hash().unpack();
+    let witness_hash: H256 = signed_tx.witness_hash().unpack();
+    serde_json::json!({
+        "transaction": ckb_jsonrpc_types::Transaction::from(signed_tx.data()),
+        "tx_hash": tx_hash,
+        "witness_hash": witness_hash,
+        "signing_message": message,
+        "signature": format!("0x{}", hex_string(&signature[..]).unwrap()),
+        "serialized": format!("0x{}", hex_string(signed_tx.data().as_slice()).unwrap()),
+    })
+}
+
+impl CliSubCommand for TestVectorSubCommand {
+    fn process(
+        &mut self,
+        _matches: &ArgMatches,
+        _debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        let keys = TEST_PRIVKEYS.iter().map(TestKey::new).collect::<Vec<_>>();
+        let resp = serde_json::json!({
+            "secp_code_hash": SECP_CODE_HASH,
+            "keys": keys.iter().map(key_vector).collect::<Vec<_>>(),
+            "transactions": [transfer_vector(&keys[0], &keys[1])],
+        });
+        Ok(Box::new(resp))
+    }
+}
@@ -21,7 +21,7 @@ use tui::{Frame, Terminal};
 // use chrono::{Local, DateTime, TimeZone};
 use ckb_index::{with_index_db, IndexDatabase};
 use ckb_jsonrpc_types::BlockNumber;
-use ckb_sdk::{GenesisInfo, HttpRpcClient, NetworkType, ONE_CKB};
+use ckb_sdk::{GenesisInfo, HttpRpcClient, NetworkType};
 use ckb_types::{
     core::{service::Request, BlockView},
     prelude::*,
@@ -29,7 +29,7 @@ use ckb_types::{
 };
 
 use super::wallet::{IndexController, IndexRequest};
-use crate::utils::other::get_network_type;
+use crate::utils::{other::get_network_type, printer::format_capacity};
 use state::{start_rpc_thread, State, SummaryInfo};
 use util::{human_capacity, ts_now, App, Event, Events, TabsState};
 use widgets::List;
@@ -533,9 +533,8 @@ fn render_top_capacity<B: Backend>(
                                 .unwrap_or_else(|| "null".to_owned())
                         )),
                         Text::raw(format!(
-                            "  [capacity]: {}.{} ({})",
-                            capacity / ONE_CKB,
-                            capacity % ONE_CKB,
+                            "  [capacity]: {} ({})",
+                            format_capacity(*capacity),
                             human_capacity(*capacity)
                         )),
                     ]
@@ -1,8 +1,12 @@
 use ckb_crypto::secp::SECP256K1;
 use ckb_hash::blake2b_256;
 use ckb_jsonrpc_types::{Script as RpcScript, Transaction as RpcTransaction};
-use ckb_sdk::{Address, GenesisInfo, HttpRpcClient, NetworkType, OldAddress};
+use ckb_sdk::{
+    eth_address, Address, AddressPayloadFormat, FullAddress, GenesisInfo, HttpRpcClient,
+    NetworkType, OldAddress, OmniAuth, SECP_TYPE_HASH,
+};
 use ckb_types::{
+    bytes::Bytes,
     packed,
     prelude::*,
     utilities::{compact_to_difficulty, difficulty_to_compact},
@@ -10,32 +14,43 @@ use ckb_types::{
 };
 use clap::{App, Arg, ArgMatches, SubCommand};
 use faster_hex::hex_string;
+use rand::Rng;
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use super::CliSubCommand;
 use crate::utils::{
+    arg,
     arg_parser::{
-        AddressParser, ArgParser, FilePathParser, FixedHashParser, FromStrParser, HexParser,
-        PrivkeyPathParser, PrivkeyWrapper, PubkeyHexParser,
+        read_arg_value, AddressParser, ArgParser, FilePathParser, FixedHashParser, FromStrParser,
+        HexParser, OutPointParser, PrivkeyPathParser, PrivkeyWrapper, PubkeyHexParser,
     },
-    other::{get_address, get_genesis_info},
-    printer::{OutputFormat, Printable},
+    data_diff::{changed_ranges, diff_sections, elf_sections},
+    hash_type::{check_json_script, HashTypeInfo},
+    molecule::MolSchema,
+    other::{get_address, get_genesis_info, write_private_file},
+    printer::Printable,
+    smt::{parse_leaf, parse_pairs, smt_proof, smt_root, smt_verify},
 };
 
 pub struct UtilSubCommand<'a> {
     rpc_client: &'a mut HttpRpcClient,
     genesis_info: Option<GenesisInfo>,
+    // Registered molecule schemas: name => schema source
+    mol_schemas_file: PathBuf,
 }
 
 impl<'a> UtilSubCommand<'a> {
     pub fn new(
         rpc_client: &'a mut HttpRpcClient,
         genesis_info: Option<GenesisInfo>,
+        mol_schemas_file: PathBuf,
     ) -> UtilSubCommand<'a> {
         UtilSubCommand {
             rpc_client,
             genesis_info,
+            mol_schemas_file,
         }
     }
 
@@ -78,9 +93,35 @@ impl<'a> UtilSubCommand<'a> {
             .default_value("binary")
             .possible_values(&["binary", "hash"])
             .help("Serialize output type");
+        let arg_pairs_file = Arg::with_name("pairs-file")
+            .long("pairs-file")
+            .takes_value(true)
+            .required(true)
+            .validator(|input| FilePathParser::new(true).validate(input))
+            .help("Key/value pairs file, an object of 32 bytes hex keys to 32 bytes hex values (format: json/yaml)");
+        let arg_mol_schema = Arg::with_name("schema")
+            .long("schema")
+            .takes_value(true)
+            .required(true)
+            .help("Molecule schema file (.mol) or name of a registered schema");
+        let arg_mol_type = Arg::with_name("type")
+            .long("type")
+            .takes_value(true)
+            .required(true)
+            .help("Type name in the schema");
         SubCommand::with_name(name)
             .about("Utilities")
             .subcommands(vec![
+                SubCommand::with_name("generate-key")
+                    .about("Generate a random secp256k1 private key (without touching keystore)")
+                    .arg(
+                        Arg::with_name("privkey-path")
+                            .long("privkey-path")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output private key file path (the file must not exist unless --force)"),
+                    )
+                    .arg(arg::force()),
                 SubCommand::with_name("key-info")
                     .about(
                         "Show public information of a secp256k1 private key (from file) or public key",
@@ -89,6 +130,38 @@ impl<'a> UtilSubCommand<'a> {
                     .arg(arg_pubkey.clone().required(false))
                     .arg(arg_address.clone().required(false))
                     .arg(arg_lock_arg.clone()),
+                SubCommand::with_name("address-info")
+                    .about("Decode an address of any format (short, full), explain deprecated formats and convert to the full format")
+                    .arg(
+                        Arg::with_name("address")
+                            .long("address")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| {
+                                FullAddress::from_input(&input, &SECP_TYPE_HASH).map(|_| ())
+                            })
+                            .help("Address in any format"),
+                    ),
+                SubCommand::with_name("omnilock-address")
+                    .about("Derive omnilock lock script and addresses from a secp256k1 key or an ethereum address")
+                    .arg(arg_privkey.clone().conflicts_with_all(&["pubkey", "lock-arg", "eth-address"]))
+                    .arg(arg_pubkey.clone().conflicts_with_all(&["lock-arg", "eth-address"]))
+                    .arg(arg_lock_arg.clone().conflicts_with("eth-address"))
+                    .arg(
+                        Arg::with_name("eth-address")
+                            .long("eth-address")
+                            .takes_value(true)
+                            .validator(|input| FixedHashParser::<H160>::default().validate(input))
+                            .help("Ethereum address (ethereum auth)"),
+                    )
+                    .arg(
+                        Arg::with_name("auth")
+                            .long("auth")
+                            .takes_value(true)
+                            .possible_values(&["secp256k1", "ethereum"])
+                            .default_value("secp256k1")
+                            .help("Auth type when derived from private key or public key"),
+                    ),
                 SubCommand::with_name("serialize-tx")
                     .about("Serialize a transaction from json file to hex binary or hash")
                     .arg(json_path_arg.clone()
@@ -103,8 +176,190 @@ impl<'a> UtilSubCommand<'a> {
                          .help("Script content (json format, see rpc get_transaction)"))
                     .arg(serialize_output_type_arg.clone()),
                 SubCommand::with_name("deserialize-script")
-                    .about("Deserialize a script from hex binary to json")
+                    .about("Deserialize a script from hex binary to json, with the VM version it runs under")
                     .arg(binary_hex_arg.clone().help("Script binary hex")),
+                SubCommand::with_name("blake2b")
+                    .about("Hash binary with ckb-hash (blake2b-256 with personalization \"ckb-default-hash\"), also show blake160 (first 20 bytes)")
+                    .arg(
+                        Arg::with_name("binary-path")
+                            .long("binary-path")
+                            .takes_value(true)
+                            .required_unless_one(&["binary-hex", "script-path"])
+                            .conflicts_with_all(&["binary-hex", "script-path"])
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("File to hash (e.g. a script binary, the result is its data code hash)"),
+                    )
+                    .arg(
+                        Arg::with_name("binary-hex")
+                            .long("binary-hex")
+                            .takes_value(true)
+                            .conflicts_with("script-path")
+                            .validator(|input| HexParser.validate(input))
+                            .help("Hex string to hash (e.g. a public key, blake160 of it is the lock arg)"),
+                    )
+                    .arg(
+                        Arg::with_name("script-path")
+                            .long("script-path")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Script to hash (json format, the result is the script hash)"),
+                    ),
+                SubCommand::with_name("verify-code-hash")
+                    .about("Check data of a deployed script cell matches a local binary, and optionally that a dep group contains the cell")
+                    .arg(
+                        Arg::with_name("out-point")
+                            .long("out-point")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| OutPointParser.validate(input))
+                            .help("Out point of the script cell, format: {tx-hash}-{index}"),
+                    )
+                    .arg(
+                        Arg::with_name("binary-path")
+                            .long("binary-path")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Local build of the script binary"),
+                    )
+                    .arg(
+                        Arg::with_name("dep-group")
+                            .long("dep-group")
+                            .takes_value(true)
+                            .validator(|input| OutPointParser.validate(input))
+                            .help("Out point of a dep group cell which should contain the script cell, format: {tx-hash}-{index}"),
+                    ),
+                SubCommand::with_name("data-diff")
+                    .about("Compare two versions of cell data (e.g. a deployed script and its upgrade), byte ranges and ELF sections")
+                    .arg(
+                        Arg::with_name("out-point-old")
+                            .long("out-point-old")
+                            .takes_value(true)
+                            .required_unless("binary-path-old")
+                            .conflicts_with("binary-path-old")
+                            .validator(|input| OutPointParser.validate(input))
+                            .help("Out point of the old cell, format: {tx-hash}-{index}"),
+                    )
+                    .arg(
+                        Arg::with_name("binary-path-old")
+                            .long("binary-path-old")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Local file of the old data"),
+                    )
+                    .arg(
+                        Arg::with_name("out-point-new")
+                            .long("out-point-new")
+                            .takes_value(true)
+                            .required_unless("binary-path-new")
+                            .conflicts_with("binary-path-new")
+                            .validator(|input| OutPointParser.validate(input))
+                            .help("Out point of the new cell, format: {tx-hash}-{index}"),
+                    )
+                    .arg(
+                        Arg::with_name("binary-path-new")
+                            .long("binary-path-new")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Local file of the new data"),
+                    )
+                    .arg(
+                        Arg::with_name("max-ranges")
+                            .long("max-ranges")
+                            .takes_value(true)
+                            .default_value("32")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Max number of changed byte ranges to print"),
+                    ),
+                SubCommand::with_name("smt-root")
+                    .about("Root of the sparse merkle tree (as used by smt-based contracts) of key/value pairs")
+                    .arg(arg_pairs_file.clone()),
+                SubCommand::with_name("smt-proof")
+                    .about("Build compiled sparse merkle proof of keys, optionally as a witness for transactions (a key not in pairs is proved absent)")
+                    .arg(arg_pairs_file)
+                    .arg(
+                        Arg::with_name("key")
+                            .long("key")
+                            .takes_value(true)
+                            .multiple(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Key to prove (32 bytes hex)"),
+                    )
+                    .arg(
+                        Arg::with_name("witness-field")
+                            .long("witness-field")
+                            .takes_value(true)
+                            .possible_values(&["lock", "input_type", "output_type"])
+                            .help("Also output a WitnessArgs with the proof in this field"),
+                    ),
+                SubCommand::with_name("smt-verify")
+                    .about("Verify a compiled sparse merkle proof of leaves against a root")
+                    .arg(
+                        Arg::with_name("root")
+                            .long("root")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Root of the tree"),
+                    )
+                    .arg(
+                        Arg::with_name("proof")
+                            .long("proof")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| HexParser.validate(input))
+                            .help("Compiled proof (hex)"),
+                    )
+                    .arg(
+                        Arg::with_name("leaf")
+                            .long("leaf")
+                            .takes_value(true)
+                            .multiple(true)
+                            .required(true)
+                            .validator(|input| parse_leaf(&input).map(|_| ()))
+                            .help("Proved leaf, format: {key}:{value} (zero value for absent key)"),
+                    ),
+                SubCommand::with_name("mol-register")
+                    .about("Register a molecule schema (e.g. args/witness layout of a contract) by name, the name can be used as --schema of mol-encode/mol-decode")
+                    .arg(
+                        Arg::with_name("name")
+                            .long("name")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Schema name"),
+                    )
+                    .arg(
+                        Arg::with_name("schema")
+                            .long("schema")
+                            .takes_value(true)
+                            .required_unless("remove")
+                            .conflicts_with("remove")
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Schema file (.mol), `import` is skipped, common types of blockchain.mol (Uint64, Byte32, Bytes, Script ..) are builtin"),
+                    )
+                    .arg(
+                        Arg::with_name("remove")
+                            .long("remove")
+                            .help("Remove the registered schema"),
+                    ),
+                SubCommand::with_name("mol-schemas").about("List registered molecule schemas and their types"),
+                SubCommand::with_name("mol-encode")
+                    .about("Encode a json value to molecule bytes (e.g. script args or witness), byte arrays/vectors are hex strings, options are null or the value, unions are {\"type\": .., \"value\": ..}")
+                    .arg(arg_mol_schema.clone())
+                    .arg(arg_mol_type.clone())
+                    .arg(
+                        Arg::with_name("json")
+                            .long("json")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The json value (@<file> to read from a file, @- from stdin)"),
+                    ),
+                SubCommand::with_name("mol-decode")
+                    .about("Decode molecule bytes to a json value")
+                    .arg(arg_mol_schema)
+                    .arg(arg_mol_type)
+                    .arg(binary_hex_arg.clone().help("Molecule bytes (hex)")),
                 SubCommand::with_name("compact-to-difficulty")
                     .about("Convert compact target value to difficulty value")
                     .arg(Arg::with_name("compact-target")
@@ -145,15 +400,90 @@ impl<'a> UtilSubCommand<'a> {
     }
 }
 
+impl<'a> UtilSubCommand<'a> {
+    fn key_info(&mut self, pubkey: &secp256k1::PublicKey) -> serde_json::Value {
+        let pubkey_hash = blake2b_256(&pubkey.serialize()[..]);
+        let address = Address::new_default(H160::from_slice(&pubkey_hash[0..20]).unwrap());
+        let pubkey_string = hex_string(&pubkey.serialize()[..]).expect("encode pubkey failed");
+        self.address_info(&address, Some(pubkey_string))
+    }
+
+    // The lock hash depends on the genesis block, it is omitted when the node is not reachable.
+    // Schema of --schema, a registered name or a file
+    fn mol_schema(&self, m: &ArgMatches) -> Result<MolSchema, String> {
+        let schema = m.value_of("schema").unwrap();
+        let schemas = load_mol_schemas(&self.mol_schemas_file)?;
+        let source = match schemas.get(schema) {
+            Some(source) => source.clone(),
+            None => fs::read_to_string(schema).map_err(|err| {
+                format!(
+                    "Schema {} is neither a registered name nor a readable file: {}",
+                    schema, err
+                )
+            })?,
+        };
+        MolSchema::parse(&source)
+    }
+
+    fn address_info(&mut self, address: &Address, pubkey: Option<String>) -> serde_json::Value {
+        let old_address = OldAddress::new_default(address.hash().clone());
+        let secp_type_hash = get_genesis_info(&mut self.genesis_info, self.rpc_client)
+            .ok()
+            .map(|genesis_info| genesis_info.secp_type_hash().clone());
+        let lock_hash = secp_type_hash.as_ref().map(|secp_type_hash| {
+            let lock_hash: H256 = address
+                .lock_script(secp_type_hash.clone())
+                .calc_script_hash()
+                .unpack();
+            lock_hash
+        });
+        let full_address =
+            FullAddress::from_short(address, secp_type_hash.as_ref().unwrap_or(&SECP_TYPE_HASH));
+        serde_json::json!({
+            "pubkey": pubkey,
+            "address": {
+                "testnet": address.to_string(NetworkType::TestNet),
+                "mainnet": address.to_string(NetworkType::MainNet),
+            },
+            "full_address": {
+                "testnet": full_address.to_string(NetworkType::TestNet),
+                "mainnet": full_address.to_string(NetworkType::MainNet),
+            },
+            // NOTE: remove this later (after all testnet race reward received)
+            "old-testnet-address": old_address.to_string(NetworkType::TestNet),
+            "lock_arg": format!("{:x}", address.hash()),
+            "lock_hash": lock_hash,
+            "lock_code_hash": secp_type_hash.map(|code_hash| format!("{:#x}", code_hash)),
+        })
+    }
+}
+
 impl<'a> CliSubCommand for UtilSubCommand<'a> {
     fn process(
         &mut self,
         matches: &ArgMatches,
-        format: OutputFormat,
-        color: bool,
         _debug: bool,
-    ) -> Result<String, String> {
+    ) -> Result<Box<dyn Printable>, String> {
         match matches.subcommand() {
+            ("generate-key", Some(m)) => {
+                let privkey_path_opt: Option<PathBuf> =
+                    FilePathParser::new(false).from_matches_opt(m, "privkey-path", false)?;
+                let privkey = loop {
+                    let data: [u8; 32] = rand::thread_rng().gen();
+                    if let Ok(privkey) = secp256k1::SecretKey::from_slice(&data[..]) {
+                        break PrivkeyWrapper(privkey);
+                    }
+                };
+                let privkey_string = hex_string(&privkey[..]).expect("encode privkey failed");
+                if let Some(path) = privkey_path_opt {
+                    let content = format!("{}\n", privkey_string);
+                    write_private_file(&path, content.as_bytes(), m.is_present("force"))?;
+                }
+                let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+                let mut resp = self.key_info(&pubkey);
+                resp["privkey"] = serde_json::json!(privkey_string);
+                Ok(Box::new(resp))
+            }
             ("key-info", Some(m)) => {
                 let privkey_opt: Option<PrivkeyWrapper> =
                     PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
@@ -162,48 +492,105 @@ impl<'a> CliSubCommand for UtilSubCommand<'a> {
                 let pubkey_opt = privkey_opt
                     .map(|privkey| secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey))
                     .or_else(|| pubkey_opt);
-                let pubkey_string_opt = pubkey_opt.as_ref().map(|pubkey| {
-                    hex_string(&pubkey.serialize()[..]).expect("encode pubkey failed")
-                });
-                let address = match pubkey_opt {
-                    Some(pubkey) => {
-                        let pubkey_hash = blake2b_256(&pubkey.serialize()[..]);
-                        Address::from_lock_arg(&pubkey_hash[0..20])?
+                let resp = match pubkey_opt {
+                    Some(pubkey) => self.key_info(&pubkey),
+                    None => {
+                        let address = get_address(m)?;
+                        self.address_info(&address, None)
                     }
-                    None => get_address(m)?,
                 };
-                let old_address = OldAddress::new_default(address.hash().clone());
-
-                let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
-                let secp_type_hash = genesis_info.secp_type_hash();
-                println!(
-                    r#"Put this config in < ckb.toml >:
+                if let Some(code_hash) = resp["lock_code_hash"].as_str() {
+                    println!(
+                        r#"Put this config in < ckb.toml >:
 
 [block_assembler]
-code_hash = "{:#x}"
+code_hash = "{}"
 hash_type = "type"
-args = ["{:#x}"]
+args = ["0x{}"]
 "#,
-                    secp_type_hash,
-                    address.hash()
-                );
-
-                let lock_hash: H256 = address
-                    .lock_script(secp_type_hash.clone())
-                    .calc_script_hash()
-                    .unpack();
+                        code_hash,
+                        resp["lock_arg"].as_str().unwrap_or(""),
+                    );
+                }
+                Ok(Box::new(resp))
+            }
+            ("address-info", Some(m)) => {
+                // Short addresses of dev chains use the secp256k1 type hash of their genesis
+                let secp_type_hash = get_genesis_info(&mut self.genesis_info, self.rpc_client)
+                    .ok()
+                    .map(|genesis_info| genesis_info.secp_type_hash().clone())
+                    .unwrap_or(SECP_TYPE_HASH);
+                let input = m.value_of("address").unwrap();
+                let (network, address, format) = FullAddress::from_input(input, &secp_type_hash)?;
+                let note = match format {
+                    AddressPayloadFormat::Short => Some("Short format is deprecated, it only encodes a code hash index, use the full format instead"),
+                    AddressPayloadFormat::FullData | AddressPayloadFormat::FullType => Some("Full data/type format is deprecated (no hash type byte), use the full format instead"),
+                    AddressPayloadFormat::Full => None,
+                };
+                let script = address.script();
+                let lock_hash: H256 = script.calc_script_hash().unpack();
                 let resp = serde_json::json!({
-                    "pubkey": pubkey_string_opt,
-                    "address": {
-                        "testnet": address.to_string(NetworkType::TestNet),
-                        "mainnet": address.to_string(NetworkType::MainNet),
-                    },
-                    // NOTE: remove this later (after all testnet race reward received)
-                    "old-testnet-address": old_address.to_string(NetworkType::TestNet),
-                    "lock_arg": format!("{:x}", address.hash()),
+                    "network": network.to_string(),
+                    "format": format.name(),
+                    "deprecated": format.is_deprecated(),
+                    "note": note,
+                    "lock_script": RpcScript::from(script),
                     "lock_hash": lock_hash,
+                    "full_address": address.to_string(network),
+                    "short_address": address
+                        .to_short(&secp_type_hash)
+                        .map(|short| short.to_string(network)),
+                });
+                Ok(Box::new(resp))
+            }
+            ("omnilock-address", Some(m)) => {
+                let privkey_opt: Option<PrivkeyWrapper> =
+                    PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+                let pubkey_opt: Option<secp256k1::PublicKey> =
+                    PubkeyHexParser.from_matches_opt(m, "pubkey", false)?;
+                let lock_arg_opt: Option<H160> =
+                    FixedHashParser::<H160>::default().from_matches_opt(m, "lock-arg", false)?;
+                let eth_address_opt: Option<H160> =
+                    FixedHashParser::<H160>::default().from_matches_opt(m, "eth-address", false)?;
+                let pubkey_opt = privkey_opt
+                    .map(|privkey| secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey))
+                    .or_else(|| pubkey_opt);
+                let auth =
+                    match (pubkey_opt, lock_arg_opt, eth_address_opt) {
+                        (Some(pubkey), _, _) if m.value_of("auth") == Some("ethereum") => {
+                            OmniAuth::Ethereum(eth_address(&pubkey))
+                        }
+                        (Some(pubkey), _, _) => {
+                            OmniAuth::Secp256k1(Address::from_pubkey(&pubkey)?.hash().clone())
+                        }
+                        (None, Some(lock_arg), _) => OmniAuth::Secp256k1(lock_arg),
+                        (None, None, Some(eth_address)) => OmniAuth::Ethereum(eth_address),
+                        (None, None, None) => return Err(
+                            "<privkey-path> or <pubkey> or <lock-arg> or <eth-address> is required"
+                                .to_owned(),
+                        ),
+                    };
+                let network_json = |network: NetworkType| {
+                    let script = auth.script(network).expect("omnilock deployed");
+                    let lock_hash: H256 = script.calc_script_hash().unpack();
+                    let address = FullAddress::from_script(&script)
+                        .map(|address| address.to_string(network))
+                        .ok();
+                    serde_json::json!({
+                        "lock_script": RpcScript::from(script),
+                        "lock_hash": lock_hash,
+                        "address": address,
+                    })
+                };
+                let resp = serde_json::json!({
+                    "auth": {
+                        "type": auth.name(),
+                        "content": format!("{:#x}", auth.content()),
+                    },
+                    "mainnet": network_json(NetworkType::MainNet),
+                    "testnet": network_json(NetworkType::TestNet),
                 });
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("serialize-tx", Some(m)) => {
                 let json_path: PathBuf = FilePathParser::new(true).from_matches(m, "json-path")?;
@@ -226,13 +613,16 @@ args = ["{:#x}"]
                     .raw(raw_tx)
                     .build()
                     .into();
-                Ok(rpc_tx.render(format, color))
+                Ok(Box::new(rpc_tx))
             }
             ("serialize-script", Some(m)) => {
                 let json_path: PathBuf = FilePathParser::new(true).from_matches(m, "json-path")?;
                 let content = fs::read_to_string(json_path).map_err(|err| err.to_string())?;
-                let rpc_script: RpcScript =
+                let value: serde_json::Value =
                     serde_json::from_str(&content).map_err(|err| err.to_string())?;
+                check_json_script(&value)?;
+                let rpc_script: RpcScript =
+                    serde_json::from_value(value).map_err(|err| err.to_string())?;
                 let script: packed::Script = rpc_script.into();
                 let output = match m.value_of("output-type") {
                     Some("binary") => hex_string(script.as_slice()).unwrap(),
@@ -243,10 +633,227 @@ args = ["{:#x}"]
             }
             ("deserialize-script", Some(m)) => {
                 let binary: Vec<u8> = HexParser.from_matches(m, "binary-hex")?;
-                let rpc_script: RpcScript = packed::Script::from_slice(&binary)
+                let script = packed::Script::from_slice(&binary).map_err(|err| err.to_string())?;
+                let hash_type = HashTypeInfo::from_script(&script)?;
+                let resp = serde_json::json!({
+                    "script": RpcScript::from(script),
+                    "vm_version": hash_type.vm_version,
+                });
+                Ok(Box::new(resp))
+            }
+            ("blake2b", Some(m)) => {
+                let binary_path_opt: Option<PathBuf> =
+                    FilePathParser::new(true).from_matches_opt(m, "binary-path", false)?;
+                let script_path_opt: Option<PathBuf> =
+                    FilePathParser::new(true).from_matches_opt(m, "script-path", false)?;
+                let data = if let Some(path) = binary_path_opt {
+                    fs::read(path).map_err(|err| err.to_string())?
+                } else if let Some(path) = script_path_opt {
+                    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+                    let value: serde_json::Value =
+                        serde_json::from_str(&content).map_err(|err| err.to_string())?;
+                    check_json_script(&value)?;
+                    let rpc_script: RpcScript =
+                        serde_json::from_value(value).map_err(|err| err.to_string())?;
+                    packed::Script::from(rpc_script).as_slice().to_vec()
+                } else {
+                    HexParser.from_matches(m, "binary-hex")?
+                };
+                let hash = blake2b_256(&data);
+                let resp = serde_json::json!({
+                    "size": data.len(),
+                    "hash": format!("{:#x}", H256::from(hash)),
+                    "blake160": format!("{:#x}", H160::from_slice(&hash[0..20]).unwrap()),
+                });
+                Ok(Box::new(resp))
+            }
+            ("smt-root", Some(m)) => {
+                let pairs = read_smt_pairs(m)?;
+                let resp = serde_json::json!({
+                    "root": smt_root(&pairs)?,
+                    "count": pairs.len(),
+                });
+                Ok(Box::new(resp))
+            }
+            ("smt-proof", Some(m)) => {
+                let pairs = read_smt_pairs(m)?;
+                let keys: Vec<H256> =
+                    FixedHashParser::<H256>::default().from_matches_vec(m, "key")?;
+                let (root, leaves, proof) = smt_proof(&pairs, &keys)?;
+                let proof = Bytes::from(proof);
+                let witness = m.value_of("witness-field").map(|field| {
+                    let field_value = Some(proof.clone()).pack();
+                    let builder = packed::WitnessArgs::new_builder();
+                    let witness = match field {
+                        "input_type" => builder.input_type(field_value),
+                        "output_type" => builder.output_type(field_value),
+                        _ => builder.lock(field_value),
+                    }
+                    .build();
+                    format!("0x{}", hex_string(witness.as_slice()).unwrap())
+                });
+                let leaves = leaves
+                    .into_iter()
+                    .map(|(key, value)| {
+                        serde_json::json!({
+                            "key": key,
+                            "value": value,
+                            "included": value != H256::default(),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let resp = serde_json::json!({
+                    "root": root,
+                    "leaves": leaves,
+                    "proof": format!("0x{}", hex_string(&proof).unwrap()),
+                    "witness": witness,
+                });
+                Ok(Box::new(resp))
+            }
+            ("smt-verify", Some(m)) => {
+                let root: H256 = FixedHashParser::<H256>::default().from_matches(m, "root")?;
+                let proof: Vec<u8> = HexParser.from_matches(m, "proof")?;
+                let leaves = m
+                    .values_of("leaf")
+                    .unwrap()
+                    .map(parse_leaf)
+                    .collect::<Result<Vec<_>, String>>()?;
+                let resp = serde_json::json!({
+                    "valid": smt_verify(&root, proof, &leaves)?,
+                });
+                Ok(Box::new(resp))
+            }
+            ("mol-register", Some(m)) => {
+                let name = m.value_of("name").unwrap().to_owned();
+                let mut schemas = load_mol_schemas(&self.mol_schemas_file)?;
+                if m.is_present("remove") {
+                    if schemas.remove(&name).is_none() {
+                        return Err(format!("Schema not found: {}", name));
+                    }
+                    save_mol_schemas(&self.mol_schemas_file, &schemas)?;
+                    return Ok(Box::new(serde_json::json!({ "removed": name })));
+                }
+                let path: PathBuf = FilePathParser::new(true).from_matches(m, "schema")?;
+                let source = fs::read_to_string(path).map_err(|err| err.to_string())?;
+                MolSchema::parse(&source)?;
+                let types = MolSchema::type_names(&source)?;
+                schemas.insert(name.clone(), source);
+                save_mol_schemas(&self.mol_schemas_file, &schemas)?;
+                let resp = serde_json::json!({
+                    "name": name,
+                    "types": types,
+                });
+                Ok(Box::new(resp))
+            }
+            ("mol-schemas", _) => {
+                let schemas = load_mol_schemas(&self.mol_schemas_file)?;
+                let resp = schemas
+                    .iter()
+                    .map(|(name, source)| {
+                        Ok(serde_json::json!({
+                            "name": name,
+                            "types": MolSchema::type_names(source)?,
+                        }))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(Box::new(serde_json::json!(resp)))
+            }
+            ("mol-encode", Some(m)) => {
+                let schema = self.mol_schema(m)?;
+                let type_name = m.value_of("type").unwrap();
+                let content = read_arg_value(m.value_of("json").unwrap())?;
+                let value: serde_json::Value = serde_json::from_str(&content)
+                    .map_err(|err| format!("Invalid json value: {}", err))?;
+                let data = schema.encode(type_name, &value)?;
+                let resp = serde_json::json!({
+                    "size": data.len(),
+                    "hex": format!("0x{}", hex_string(&data).unwrap()),
+                });
+                Ok(Box::new(resp))
+            }
+            ("mol-decode", Some(m)) => {
+                let schema = self.mol_schema(m)?;
+                let data: Vec<u8> = HexParser.from_matches(m, "binary-hex")?;
+                let value = schema.decode(m.value_of("type").unwrap(), &data)?;
+                Ok(Box::new(value))
+            }
+            ("verify-code-hash", Some(m)) => {
+                let out_point: packed::OutPoint = OutPointParser.from_matches(m, "out-point")?;
+                let binary_path: PathBuf =
+                    FilePathParser::new(true).from_matches(m, "binary-path")?;
+                let dep_group_opt: Option<packed::OutPoint> =
+                    OutPointParser.from_matches_opt(m, "dep-group", false)?;
+
+                let binary = fs::read(binary_path).map_err(|err| err.to_string())?;
+                let binary_hash = H256::from(blake2b_256(&binary));
+                let (output, data) = committed_cell(self.rpc_client, &out_point)?;
+                let data_hash = H256::from(blake2b_256(&data));
+                let type_hash: Option<H256> = output
+                    .type_()
+                    .to_opt()
+                    .map(|script| script.calc_script_hash().unpack());
+                let live = self
+                    .rpc_client
+                    .get_live_cell(out_point.clone().into(), false)
+                    .call()
                     .map_err(|err| err.to_string())?
-                    .into();
-                Ok(rpc_script.render(format, color))
+                    .status
+                    == "live";
+                let dep_group = if let Some(dep_group) = dep_group_opt {
+                    let (_, group_data) = committed_cell(self.rpc_client, &dep_group)?;
+                    let out_points =
+                        packed::OutPointVec::from_slice(&group_data).map_err(|_| {
+                            "The dep group cell data is not an out point vector".to_owned()
+                        })?;
+                    let contains = out_points
+                        .into_iter()
+                        .any(|item| item.as_slice() == out_point.as_slice());
+                    Some(serde_json::json!({
+                        "out_point": ckb_jsonrpc_types::OutPoint::from(dep_group),
+                        "contains": contains,
+                    }))
+                } else {
+                    None
+                };
+                let resp = serde_json::json!({
+                    "matched": binary_hash == data_hash,
+                    "binary_hash": binary_hash,
+                    "data_hash": data_hash,
+                    "type_hash": type_hash,
+                    "live": live,
+                    "dep_group": dep_group,
+                });
+                Ok(Box::new(resp))
+            }
+            ("data-diff", Some(m)) => {
+                let max_ranges: usize =
+                    FromStrParser::<usize>::default().from_matches(m, "max-ranges")?;
+                let old = load_data(self.rpc_client, m, "out-point-old", "binary-path-old")?;
+                let new = load_data(self.rpc_client, m, "out-point-new", "binary-path-new")?;
+                let ranges = changed_ranges(&old, &new);
+                let changed_bytes: usize = ranges.iter().map(|range| range.end - range.start).sum();
+                let sections = match (elf_sections(&old), elf_sections(&new)) {
+                    (Some(old_sections), Some(new_sections)) => {
+                        Some(diff_sections(&old_sections, &new_sections))
+                    }
+                    _ => None,
+                };
+                let resp = serde_json::json!({
+                    "identical": old == new,
+                    "old": {
+                        "size": old.len(),
+                        "data_hash": H256::from(blake2b_256(&old)),
+                    },
+                    "new": {
+                        "size": new.len(),
+                        "data_hash": H256::from(blake2b_256(&new)),
+                    },
+                    "changed_bytes": changed_bytes,
+                    "changed_ranges_total": ranges.len(),
+                    "changed_ranges": ranges.iter().take(max_ranges).collect::<Vec<_>>(),
+                    "sections": sections,
+                });
+                Ok(Box::new(resp))
             }
             ("compact-to-difficulty", Some(m)) => {
                 let compact_target: u32 = FromStrParser::<u32>::default()
@@ -263,7 +870,7 @@ args = ["{:#x}"]
                 let resp = serde_json::json!({
                     "difficulty": format!("{:#x}", compact_to_difficulty(compact_target))
                 });
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("difficulty-to-compact", Some(m)) => {
                 let input = m.value_of("difficulty").unwrap();
@@ -276,9 +883,70 @@ args = ["{:#x}"]
                 let resp = serde_json::json!({
                     "compact-target": format!("{:#x}", difficulty_to_compact(difficulty)),
                 });
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             _ => Err(matches.usage().to_owned()),
         }
     }
 }
+
+// Cell data by out point, or content of a local file
+fn load_data(
+    rpc_client: &mut HttpRpcClient,
+    m: &ArgMatches,
+    out_point_name: &str,
+    path_name: &str,
+) -> Result<Bytes, String> {
+    match OutPointParser.from_matches_opt::<packed::OutPoint>(m, out_point_name, false)? {
+        Some(out_point) => committed_cell(rpc_client, &out_point).map(|(_, data)| data),
+        None => {
+            let path: PathBuf = FilePathParser::new(true).from_matches(m, path_name)?;
+            fs::read(path)
+                .map(Bytes::from)
+                .map_err(|err| err.to_string())
+        }
+    }
+}
+
+// Output and data of a committed cell, spent or not
+fn committed_cell(
+    rpc_client: &mut HttpRpcClient,
+    out_point: &packed::OutPoint,
+) -> Result<(packed::CellOutput, Bytes), String> {
+    let tx_hash: H256 = out_point.tx_hash().unpack();
+    let index: u32 = out_point.index().unpack();
+    let tx = rpc_client
+        .get_transaction(tx_hash.clone())
+        .call()
+        .map_err(|err| err.to_string())?
+        .0
+        .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?
+        .transaction
+        .inner;
+    let output = tx
+        .outputs
+        .get(index as usize)
+        .cloned()
+        .ok_or_else(|| format!("Output not found: {:#x}-{}", tx_hash, index))?;
+    let data = tx.outputs_data[index as usize].clone().into_bytes();
+    Ok((output.into(), data))
+}
+
+fn read_smt_pairs(m: &ArgMatches) -> Result<BTreeMap<H256, H256>, String> {
+    let path: PathBuf = FilePathParser::new(true).from_matches(m, "pairs-file")?;
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    parse_pairs(&content)
+}
+
+fn load_mol_schemas(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&content).map_err(|err| format!("Invalid molecule schemas file: {}", err))
+}
+
+fn save_mol_schemas(path: &Path, schemas: &BTreeMap<String, String>) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(schemas).map_err(|err| err.to_string())?;
+    fs::write(path, content).map_err(|err| err.to_string())
+}
@@ -0,0 +1,279 @@
+use std::collections::HashSet;
+use std::fs;
+
+use ckb_sdk::{Address, NetworkType};
+use ckb_types::{core::Capacity, prelude::*, H160, H256};
+use clap::ArgMatches;
+
+use super::job::{BatchJob, JobRecipient};
+use super::pending::{PendingTx, PendingTxStore};
+use super::udt::udt_cell;
+use super::{sender_address, WalletSubCommand};
+use crate::utils::{
+    arg_parser::{
+        AddressParser, AmountParser, ArgParser, FromStrParser, PrivkeyPathParser, PrivkeyWrapper,
+    },
+    asset::{Asset, AssetRegistry},
+    i18n::tr,
+    other::{get_network_type, read_password},
+    printer::Printable,
+};
+
+/// A recipient of a sUDT airdrop
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AirdropRecipient {
+    // The address as written in the recipients file
+    pub address: String,
+    // Base units of the asset
+    pub amount: u128,
+}
+
+/// Parse recipients CSV lines of `address,amount` (`#` starts a comment, an
+/// optional `address,amount` header line is skipped). Repeated addresses of
+/// the same lock are paid once: the first line is kept, returns the line
+/// numbers of the dropped duplicates.
+pub fn parse_recipients(
+    content: &str,
+    decimals: u8,
+    network_type: NetworkType,
+) -> Result<(Vec<AirdropRecipient>, Vec<usize>), String> {
+    let amount_parser = AmountParser::new(decimals);
+    let mut seen: HashSet<H160> = HashSet::new();
+    let mut recipients = Vec::new();
+    let mut duplicates = Vec::new();
+    for (number, line) in content
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+    {
+        let parts = line.split(',').map(str::trim).collect::<Vec<_>>();
+        if parts.len() != 2 {
+            return Err(format!(
+                "Invalid recipients csv line {}, format: address,amount",
+                number
+            ));
+        }
+        if number == 1 && parts[0] == "address" {
+            continue;
+        }
+        let err_with_line = |err: String| format!("line {}: {}", number, err);
+        let (address_network, address) = Address::from_input(parts[0]).map_err(err_with_line)?;
+        if address_network != network_type {
+            return Err(err_with_line(format!(
+                "Address {} is not for network {}",
+                parts[0], network_type
+            )));
+        }
+        let amount: u128 = amount_parser.parse(parts[1]).map_err(err_with_line)?;
+        if amount == 0 {
+            return Err(err_with_line("Amount can not be zero".to_owned()));
+        }
+        if !seen.insert(address.hash().clone()) {
+            duplicates.push(number);
+            continue;
+        }
+        recipients.push(AirdropRecipient {
+            address: parts[0].to_owned(),
+            amount,
+        });
+    }
+    Ok((recipients, duplicates))
+}
+
+impl<'a> WalletSubCommand<'a> {
+    // Distribute a sUDT to the recipients of a CSV file, `batch-size`
+    // recipients per transaction. Every transaction and the recipients it
+    // pays are recorded in the job before sending, so an interrupted run can
+    // be resumed without paying anyone twice.
+    pub fn udt_airdrop(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account = self.from_account(m, from_privkey.is_some())?;
+        let from_address = sender_address(from_privkey.as_ref(), from_account.as_ref())?;
+        let batch_size: usize = FromStrParser::<usize>::default().from_matches(m, "batch-size")?;
+        if batch_size == 0 {
+            return Err("<batch-size> can not be zero".to_owned());
+        }
+        let asset =
+            match AssetRegistry::load(&self.index_dir)?.resolve(m.value_of("asset").unwrap())? {
+                Asset::Udt(info) => info,
+                Asset::Ckb => {
+                    return Err(
+                        "Airdrop is for sUDT assets, use `wallet transfer --outputs-file` for CKB"
+                            .to_owned(),
+                    )
+                }
+            };
+        let network_type = get_network_type(self.rpc_client)?;
+        let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+        let genesis_hash: H256 = self.genesis_info()?.header().hash().unpack();
+        let from_lock_hash: H256 = from_address
+            .lock_script(secp_type_hash.clone())
+            .calc_script_hash()
+            .unpack();
+
+        let mut job = match m.value_of("resume") {
+            Some(job_id) => {
+                let mut job = BatchJob::load(&self.index_dir, job_id)?;
+                if job.kind != "udt-airdrop" {
+                    return Err(format!("Job {} is not a udt-airdrop job", job_id));
+                }
+                if job.type_hash.as_ref() != Some(&asset.type_hash()) {
+                    return Err(format!(
+                        "Job {} is not an airdrop of {}",
+                        job_id, asset.symbol
+                    ));
+                }
+                job.refresh(&mut self.rpc_client)?;
+                job
+            }
+            None => {
+                let path = m.value_of("recipients").unwrap();
+                let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+                let (recipients, duplicates) =
+                    parse_recipients(&content, asset.decimals, network_type)?;
+                if !duplicates.is_empty() {
+                    eprintln!(
+                        "[WARN]: {} duplicated recipients are skipped, lines: {:?}",
+                        duplicates.len(),
+                        duplicates
+                    );
+                }
+                let mut job = BatchJob::new("udt-airdrop");
+                job.type_hash = Some(asset.type_hash());
+                job.recipients = recipients
+                    .into_iter()
+                    .map(|recipient| JobRecipient {
+                        address: recipient.address,
+                        amount: recipient.amount.to_string(),
+                        tx_hash: None,
+                    })
+                    .collect();
+                job.save(&self.index_dir)?;
+                eprintln!("[job]: {} (resume with --resume {})", job.id, job.id);
+                job
+            }
+        };
+
+        let password = if m.is_present("with-password") {
+            Some(read_password(false, None)?)
+        } else {
+            None
+        };
+        let unpaid = job.unpaid_recipients();
+        let mut stopped = None;
+        for positions in unpaid.chunks(batch_size) {
+            let udt_outputs = positions
+                .iter()
+                .map(|position| {
+                    let recipient = &job.recipients[*position];
+                    let address: Address = AddressParser.parse(&recipient.address)?;
+                    let amount: u128 = recipient.amount.parse().map_err(|err| {
+                        format!("Invalid amount of {}: {}", recipient.address, err)
+                    })?;
+                    Ok(udt_cell(
+                        &asset.type_script(),
+                        address.lock_script(secp_type_hash.clone()),
+                        amount,
+                    ))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let capacity = udt_outputs
+                .iter()
+                .map(|(output, _)| {
+                    let capacity: Capacity = output.capacity().unpack();
+                    capacity.as_u64()
+                })
+                .sum::<u64>();
+            // Stop at the first batch can not be built (usually the sUDT
+            // cells are spent by pending transactions of this job)
+            let transaction = match self.build_udt_transaction(
+                m,
+                &asset,
+                from_privkey.as_ref(),
+                from_account.as_ref(),
+                &password,
+                udt_outputs,
+                &job.spent_out_points(),
+            ) {
+                Ok(transaction) => transaction,
+                Err(err) => {
+                    stopped = Some(err);
+                    break;
+                }
+            };
+            // Record before sending, so a resumed run never pays them again
+            let tx_hash: H256 = transaction.hash().unpack();
+            let position = job.add_signed(&transaction, capacity);
+            job.set_recipients_tx(positions, &tx_hash);
+            job.save(&self.index_dir)?;
+            let result = self
+                .rpc_client
+                .send_transaction(transaction.data().into())
+                .call()
+                .map_err(|err| format!("{}: {}", tr("Send transaction error"), err));
+            job.set_result(position, result.as_ref().map(|_| ()).map_err(Clone::clone));
+            job.save(&self.index_dir)?;
+            if let Err(err) = result {
+                stopped = Some(err);
+                break;
+            }
+            let mut pending_txs = PendingTxStore::load(&self.index_dir, &genesis_hash)?;
+            pending_txs.add(PendingTx::new(&transaction, from_lock_hash.clone()));
+            pending_txs.save()?;
+            eprintln!(
+                "[INFO]: sent {:#x} ({} recipients)",
+                tx_hash,
+                positions.len()
+            );
+        }
+
+        let mut resp = serde_json::json!({
+            "asset": asset.symbol,
+            "job": job.reconcile(&mut self.rpc_client)?,
+        });
+        if let Some(err) = stopped {
+            resp["stopped"] = serde_json::json!(format!(
+                "{} (resume with --resume {} when pending transactions are committed)",
+                err, job.id
+            ));
+        }
+        Ok(Box::new(resp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: &str = "ckt1qyqt8xaupvm8837nv3gtc9x0ekkj64vud3jqfwyw5v";
+    const SECOND: &str = "ckt1qyqywrwdchjyqeysjegpzw38fvandtktdhrs0zaxl4";
+
+    #[test]
+    fn test_parse_recipients() {
+        let content = format!(
+            "address,amount\n# team\n{},1.5\n\n{}, 2\n{},3\n",
+            FIRST, SECOND, FIRST
+        );
+        let (recipients, duplicates) = parse_recipients(&content, 8, NetworkType::Testnet).unwrap();
+        assert_eq!(
+            recipients,
+            vec![
+                AirdropRecipient {
+                    address: FIRST.to_owned(),
+                    amount: 150_000_000,
+                },
+                AirdropRecipient {
+                    address: SECOND.to_owned(),
+                    amount: 200_000_000,
+                },
+            ]
+        );
+        assert_eq!(duplicates, vec![6]);
+
+        assert!(parse_recipients(&format!("{},0", FIRST), 8, NetworkType::Testnet).is_err());
+        assert!(parse_recipients(&format!("{},1", FIRST), 8, NetworkType::Mainnet).is_err());
+        assert!(parse_recipients(FIRST, 8, NetworkType::Testnet).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use ckb_jsonrpc_types::{BlockNumber, HeaderView, TransactionWithStatus, Uint64};
+use ckb_sdk::Address;
+use ckb_types::{prelude::*, H160, H256};
+use clap::ArgMatches;
+
+use super::derived::DerivedAccounts;
+use super::WalletSubCommand;
+use crate::utils::{
+    arg_parser::{ArgParser, FixedHashParser, FromStrParser},
+    asset::{format_amount, Asset, AssetRegistry},
+    other::get_network_type,
+    printer::{format_capacity, Printable, Text},
+};
+
+// Page size of get_transactions_by_lock_hash
+const HISTORY_PAGE_SIZE: u64 = 50;
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn get_capacity(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let lock_hash = self.query_lock_hash(m)?;
+        if let Some(asset) = m.value_of("asset") {
+            if let Asset::Udt(info) = AssetRegistry::load(&self.index_dir)?.resolve(asset)? {
+                let (amount, cells) = self.udt_balance(lock_hash, &info)?;
+                let resp = serde_json::json!({
+                    "asset": info.symbol,
+                    "amount": format_amount(amount, info.decimals),
+                    "cells": cells,
+                });
+                return Ok(Box::new(resp));
+            }
+        }
+        if !m.is_present("with-derived") {
+            let capacity = self.with_db(|db| db.get_capacity(lock_hash))?;
+            let resp = serde_json::json!({
+                "capacity": capacity,
+            });
+            return Ok(Box::new(resp));
+        }
+        let lock_arg: H160 = match FixedHashParser::<H160>::default()
+            .from_matches_opt(m, "lock-arg", false)?
+        {
+            Some(lock_arg) => lock_arg,
+            None if ["lock-hash", "address", "pubkey"]
+                .iter()
+                .any(|name| m.is_present(name)) =>
+            {
+                return Err("<lock-arg> or <from-account> is required by --with-derived".to_owned());
+            }
+            None => self
+                .from_account(m, false)?
+                .expect("from_account always resolves without privkey"),
+        };
+        let derived_accounts = DerivedAccounts::load(&self.index_dir)?;
+        let derived = derived_accounts.get(&lock_arg).to_vec();
+        let (capacity, derived_capacities) = self.with_db(|db| {
+            let capacity = db.get_capacity(lock_hash).unwrap_or(0);
+            let derived_capacities = derived
+                .iter()
+                .map(|account| {
+                    let capacity = db.get_capacity(account.lock_hash.pack()).unwrap_or(0);
+                    (account.clone(), capacity)
+                })
+                .collect::<Vec<_>>();
+            (capacity, derived_capacities)
+        })?;
+        let total_capacity = capacity + derived_capacities.iter().map(|(_, c)| c).sum::<u64>();
+        let resp = serde_json::json!({
+            "capacity": capacity,
+            "derived": derived_capacities.into_iter().map(|(account, capacity)| {
+                serde_json::json!({
+                    "path": account.path,
+                    "lock_arg": account.lock_arg,
+                    "capacity": capacity,
+                })
+            }).collect::<Vec<_>>(),
+            "total_capacity": total_capacity,
+        });
+        Ok(Box::new(resp))
+    }
+
+    pub fn balance_at(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let number: u64 = FromStrParser::<u64>::default().from_matches(m, "block-number")?;
+        let network_type = get_network_type(self.rpc_client)?;
+        let balances = self.account_balances_at(number)?;
+        let total_capacity = balances.iter().map(|(_, capacity)| capacity).sum::<u64>();
+        let resp = serde_json::json!({
+            "block_number": number,
+            "accounts": balances.into_iter().map(|(lock_arg, capacity)| {
+                serde_json::json!({
+                    "lock_arg": lock_arg,
+                    "address": Address::new_default(lock_arg).to_string(network_type),
+                    "capacity": capacity,
+                })
+            }).collect::<Vec<_>>(),
+            "total_capacity": total_capacity,
+        });
+        Ok(Box::new(resp))
+    }
+
+    pub fn balance_snapshot(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let number_opt: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "block-number", false)?;
+        let header: HeaderView = match number_opt {
+            Some(number) => self
+                .rpc_client
+                .get_header_by_number(BlockNumber::from(number))
+                .call()
+                .map_err(|err| format!("Send get_header_by_number error: {}", err))?
+                .0
+                .ok_or_else(|| format!("Block not found: {}", number))?,
+            None => self
+                .rpc_client
+                .get_tip_header()
+                .call()
+                .map_err(|err| format!("Send get_tip_header error: {}", err))?,
+        };
+        let number = header.inner.number.value();
+        let network_type = get_network_type(self.rpc_client)?;
+        let balances = self.account_balances_at(number)?;
+        let total_capacity = balances.iter().map(|(_, capacity)| capacity).sum::<u64>();
+        if m.value_of("output") == Some("csv") {
+            let mut lines = vec![format!(
+                "# block_number: {}, block_hash: {:#x}, timestamp: {}",
+                number,
+                header.hash,
+                header.inner.timestamp.value()
+            )];
+            for (lock_arg, capacity) in balances {
+                lines.push(format!(
+                    "{:#x},{},{}",
+                    lock_arg,
+                    Address::new_default(lock_arg.clone()).to_string(network_type),
+                    format_capacity(capacity)
+                ));
+            }
+            lines.push(format!("total,,{}", format_capacity(total_capacity)));
+            return Ok(Box::new(Text::from(lines.join("\n"))));
+        }
+        let resp = serde_json::json!({
+            "block_number": number,
+            "block_hash": header.hash,
+            "timestamp": header.inner.timestamp,
+            "accounts": balances.into_iter().map(|(lock_arg, capacity)| {
+                serde_json::json!({
+                    "lock_arg": lock_arg,
+                    "address": Address::new_default(lock_arg).to_string(network_type),
+                    "capacity": capacity,
+                })
+            }).collect::<Vec<_>>(),
+            "total_capacity": total_capacity,
+        });
+        Ok(Box::new(resp))
+    }
+
+    // Capacity of every account (default secp256k1 lock) at the end of the
+    // block, from the cell history recorded by the node's indexer (see: rpc
+    // index_lock_hash).
+    fn account_balances_at(&mut self, number: u64) -> Result<Vec<(H160, u64)>, String> {
+        let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+        let mut lock_args = self
+            .key_store
+            .get_accounts()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        lock_args.sort();
+        let mut tx_outputs: HashMap<H256, Vec<u64>> = HashMap::new();
+        let mut balances = Vec::with_capacity(lock_args.len());
+        for lock_arg in lock_args {
+            let lock_hash: H256 = Address::new_default(lock_arg.clone())
+                .lock_script(secp_type_hash.clone())
+                .calc_script_hash()
+                .unpack();
+            let mut capacity = 0;
+            let mut page = 0;
+            loop {
+                let cell_txs = self
+                    .rpc_client
+                    .get_transactions_by_lock_hash(
+                        lock_hash.clone(),
+                        Uint64::from(page),
+                        Uint64::from(HISTORY_PAGE_SIZE),
+                        Some(false),
+                    )
+                    .call()
+                    .map_err(|err| format!("Send get_transactions_by_lock_hash error: {}", err))?
+                    .0;
+                for cell_tx in cell_txs.iter() {
+                    let created_by = &cell_tx.created_by;
+                    if created_by.block_number.value() > number {
+                        continue;
+                    }
+                    let consumed = cell_tx
+                        .consumed_by
+                        .as_ref()
+                        .map(|point| point.block_number.value() <= number)
+                        .unwrap_or(false);
+                    if consumed {
+                        continue;
+                    }
+                    if !tx_outputs.contains_key(&created_by.tx_hash) {
+                        let tx: TransactionWithStatus = self
+                            .rpc_client
+                            .get_transaction(created_by.tx_hash.clone())
+                            .call()
+                            .map_err(|err| format!("Send get_transaction error: {}", err))?
+                            .0
+                            .ok_or_else(|| {
+                                format!("Transaction not found: {:#x}", created_by.tx_hash)
+                            })?;
+                        let capacities = tx
+                            .transaction
+                            .inner
+                            .outputs
+                            .iter()
+                            .map(|output| output.capacity.value())
+                            .collect();
+                        tx_outputs.insert(created_by.tx_hash.clone(), capacities);
+                    }
+                    capacity += tx_outputs[&created_by.tx_hash]
+                        .get(created_by.index.value() as usize)
+                        .cloned()
+                        .unwrap_or(0);
+                }
+                if (cell_txs.len() as u64) < HISTORY_PAGE_SIZE {
+                    break;
+                }
+                page += 1;
+            }
+            balances.push((lock_arg, capacity));
+        }
+        Ok(balances)
+    }
+}
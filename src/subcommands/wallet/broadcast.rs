@@ -0,0 +1,82 @@
+use std::thread;
+
+use ckb_jsonrpc_types::Transaction;
+use ckb_sdk::HttpRpcClient;
+use ckb_types::{core::TransactionView, prelude::*, H256};
+
+use super::WalletSubCommand;
+use crate::utils::{i18n::tr, printer::Printable};
+
+impl<'a> WalletSubCommand<'a> {
+    // Send to current node and all broadcast urls in parallel, report per-node result
+    pub(super) fn broadcast_transaction(
+        &mut self,
+        transaction: TransactionView,
+    ) -> Result<Box<dyn Printable>, String> {
+        if self.broadcast_urls.is_empty() {
+            return Err("No broadcast urls configured (see: config --broadcast-urls)".to_owned());
+        }
+        let handles = self
+            .broadcast_urls
+            .iter()
+            .map(|url| {
+                let url = url.clone();
+                let tx: Transaction = transaction.data().into();
+                thread::spawn(move || {
+                    let result = HttpRpcClient::from_uri(&url)
+                        .send_transaction(tx)
+                        .call()
+                        .map_err(|err| err.to_string());
+                    (url, result)
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut results = vec![(
+            "default".to_owned(),
+            self.rpc_client
+                .send_transaction(transaction.data().into())
+                .call()
+                .map_err(|err| err.to_string()),
+        )];
+        for handle in handles {
+            results.push(
+                handle
+                    .join()
+                    .map_err(|_| "Broadcast thread panicked".to_owned())?,
+            );
+        }
+
+        if results.iter().all(|(_, result)| result.is_err()) {
+            let errors = results
+                .into_iter()
+                .map(|(url, result)| format!("{}: {}", url, result.unwrap_err()))
+                .collect::<Vec<_>>();
+            return Err(format!(
+                "{}: {}",
+                tr("Send transaction error"),
+                errors.join("; ")
+            ));
+        }
+        let nodes = results
+            .into_iter()
+            .map(|(url, result)| match result {
+                Ok(tx_hash) => serde_json::json!({
+                    "url": url,
+                    "accepted": true,
+                    "tx_hash": tx_hash,
+                }),
+                Err(err) => serde_json::json!({
+                    "url": url,
+                    "accepted": false,
+                    "error": err,
+                }),
+            })
+            .collect::<Vec<_>>();
+        let tx_hash: H256 = transaction.hash().unpack();
+        let resp = serde_json::json!({
+            "tx_hash": tx_hash,
+            "nodes": nodes,
+        });
+        Ok(Box::new(resp))
+    }
+}
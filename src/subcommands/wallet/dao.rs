@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+
+use ckb_hash::blake2b_256;
+use ckb_index::{with_index_db, IndexDatabase, LiveCellInfo};
+use ckb_jsonrpc_types::CellWithStatus;
+use ckb_sdk::{Address, TransferTransactionBuilder, MIN_SECP_CELL_CAPACITY, SECP256K1};
+use ckb_types::{bytes::Bytes, packed::CellInput, prelude::*, H256};
+use clap::ArgMatches;
+
+use super::job::BatchJob;
+use super::{
+    build_dao_inputs, build_dao_withdraw_hash, is_dao_cell, is_live_cell, WalletSubCommand,
+    DAO_MATURITY,
+};
+use crate::utils::{
+    arg_parser::{ArgParser, CapacityParser, PrivkeyPathParser, PrivkeyWrapper},
+    i18n::tr,
+    other::{get_network_type, read_password},
+    printer::Printable,
+};
+
+// Deposit headers plus the withdraw header
+const MAX_DAO_HEADER_DEPS: usize = 64;
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn withdraw_all_dao(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account = self.from_account(m, from_privkey.is_some())?;
+        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
+            let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
+            let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
+            Address::from_lock_arg(&pubkey_hash[0..20])?
+        } else {
+            Address::from_lock_arg(from_account.as_ref().unwrap().as_bytes())?
+        };
+        let password = if from_privkey.is_none() && m.is_present("with-password") {
+            Some(read_password(false, None)?)
+        } else {
+            None
+        };
+
+        let network_type = get_network_type(self.rpc_client)?;
+        let genesis_info = self.genesis_info()?;
+        let lock_hash = from_address
+            .lock_script(genesis_info.secp_type_hash().clone())
+            .calc_script_hash();
+        let mut job = match m.value_of("resume") {
+            Some(job_id) => {
+                let mut job = BatchJob::load(&self.index_dir, job_id)?;
+                if job.kind != "withdraw-all-dao" {
+                    return Err(format!("Job {} is not a withdraw-all-dao job", job_id));
+                }
+                job.refresh(&mut self.rpc_client)?;
+                job
+            }
+            None => {
+                let job = BatchJob::new("withdraw-all-dao");
+                eprintln!("[job]: {} (resume with --resume {})", job.id, job.id);
+                job
+            }
+        };
+        // Cells spent by transactions already sent in this job
+        let job_spent = job.spent_out_points();
+        // For check index database is ready
+        self.with_db(|_| ())?;
+        let index_dir = self.index_dir.clone();
+        let min_confirmations = self.min_confirmations;
+        let genesis_hash = genesis_info.header().hash();
+        let genesis_info_clone = genesis_info.clone();
+        let terminator = |_, info: &LiveCellInfo| {
+            let out_point = info.out_point();
+            if job_spent.contains(&(out_point.tx_hash().unpack(), out_point.index().unpack())) {
+                return (false, false);
+            }
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(out_point.into(), false)
+                .call()
+                .expect("get_live_cell by RPC call failed");
+            (
+                false,
+                is_live_cell(&resp) && is_dao_cell(&resp, genesis_info.dao_type_hash()),
+            )
+        };
+        let infos: Vec<LiveCellInfo> =
+            with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
+                let mut db =
+                    IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
+                db.set_min_confirmations(min_confirmations);
+                Ok(db.get_live_cells_by_lock(lock_hash, None, terminator))
+            })
+            .map_err(|_err| {
+                format!(
+                    "{}: {}",
+                    tr("index database may not ready, sync process"),
+                    self.index_controller.state().read().to_string()
+                )
+            })?;
+
+        let tip_number = self
+            .rpc_client
+            .get_tip_block_number()
+            .call()
+            .map_err(|err| err.to_string())?
+            .value();
+        let (matured, immature): (Vec<_>, Vec<_>) = infos
+            .into_iter()
+            .partition(|info| tip_number >= info.number + DAO_MATURITY);
+        let immature = immature
+            .into_iter()
+            .map(|info| {
+                let claimable_at = info.number + DAO_MATURITY;
+                serde_json::json!({
+                    "out_point": ckb_jsonrpc_types::OutPoint::from(info.out_point()),
+                    "capacity": info.capacity,
+                    "deposit_block": info.number,
+                    "claimable_at_block": claimable_at,
+                    "remaining_blocks": claimable_at - tip_number,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Split inputs so every transaction stays in header deps limit
+        let mut batches: Vec<Vec<(LiveCellInfo, CellInput, H256)>> = Vec::new();
+        let mut batch_headers: HashSet<H256> = HashSet::new();
+        let inputs = build_dao_inputs(&mut self.rpc_client, matured.clone())?;
+        for (info, (input, header_hash)) in matured.into_iter().zip(inputs.into_iter()) {
+            let new_header = !batch_headers.contains(&header_hash);
+            if batches.is_empty() || (new_header && batch_headers.len() + 1 >= MAX_DAO_HEADER_DEPS)
+            {
+                batches.push(Vec::new());
+                batch_headers.clear();
+            }
+            batch_headers.insert(header_hash.clone());
+            batches.last_mut().unwrap().push((info, input, header_hash));
+        }
+
+        // Withdraw header and deposit header deps (unique in transaction) of batches
+        let mut plans = Vec::with_capacity(batches.len());
+        for batch in batches.iter() {
+            let total_capacity = batch.iter().map(|(info, _, _)| info.capacity).sum::<u64>();
+            if total_capacity < tx_fee + *MIN_SECP_CELL_CAPACITY {
+                return Err(format!(
+                    "{}: {} < {} + {}",
+                    tr("Capacity not enough"),
+                    total_capacity,
+                    tx_fee,
+                    *MIN_SECP_CELL_CAPACITY
+                ));
+            }
+            let mut seen = HashSet::new();
+            let input_header_hashes = batch
+                .iter()
+                .map(|(_, _, header_hash)| header_hash.clone())
+                .filter(|hash| seen.insert(hash.clone()))
+                .collect::<Vec<_>>();
+            let withdraw_header_hash = build_dao_withdraw_hash(&mut self.rpc_client)?;
+            plans.push((total_capacity, withdraw_header_hash, input_header_hashes));
+        }
+
+        let to_data = Bytes::new();
+        let signed = self.sign_transactions(
+            batches.len(),
+            from_privkey.as_ref(),
+            from_account.as_ref(),
+            &password,
+            |index, build_witness| {
+                let (total_capacity, withdraw_header_hash, input_header_hashes) = &plans[index];
+                let inputs = batches[index]
+                    .iter()
+                    .map(|(_, input, _)| input.clone())
+                    .collect::<Vec<_>>();
+                let mut tx_args = TransferTransactionBuilder::new(
+                    &from_address,
+                    *total_capacity,
+                    &to_data,
+                    &from_address,
+                    total_capacity - tx_fee,
+                    tx_fee,
+                    inputs,
+                );
+                tx_args.withdraw_dao(
+                    withdraw_header_hash.clone(),
+                    input_header_hashes.clone(),
+                    &genesis_info,
+                    |args| build_witness(args),
+                )
+            },
+        )?;
+
+        let mut transactions = Vec::new();
+        for ((batch, (total_capacity, _, _)), transaction) in
+            batches.iter().zip(plans.iter()).zip(signed.into_iter())
+        {
+            // Record before sending, so a resumed run never spends the inputs again
+            let position = job.add_signed(&transaction, total_capacity - tx_fee);
+            job.save(&self.index_dir)?;
+            let result = self
+                .rpc_client
+                .send_transaction(transaction.data().into())
+                .call()
+                .map_err(|err| format!("{}: {}", tr("Send transaction error"), err));
+            job.set_result(position, result.as_ref().map(|_| ()).map_err(Clone::clone));
+            job.save(&self.index_dir)?;
+            let tx_hash =
+                result.map_err(|err| format!("{} (resume with --resume {})", err, job.id))?;
+            transactions.push(serde_json::json!({
+                "tx_hash": tx_hash,
+                "inputs": batch.len(),
+                "capacity": total_capacity - tx_fee,
+            }));
+        }
+
+        let resp = serde_json::json!({
+            "address": from_address.to_string(network_type),
+            "tip_number": tip_number,
+            "transactions": transactions,
+            "immature": immature,
+            "job": job.reconcile(&mut self.rpc_client)?,
+        });
+        Ok(Box::new(resp))
+    }
+}
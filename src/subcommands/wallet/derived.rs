@@ -0,0 +1,263 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_sdk::{
+    wallet::{Bip32Error, DerivationPath, KeyStoreError, MasterPrivKey},
+    Address,
+};
+use ckb_types::{prelude::*, H160, H256};
+use clap::ArgMatches;
+use serde_derive::{Deserialize, Serialize};
+
+use super::WalletSubCommand;
+use crate::utils::{
+    arg_parser::{ArgParser, FromStrParser, PrivkeyWrapper},
+    other::{get_network_type, read_password},
+    printer::Printable,
+};
+
+/// Used address derived from an account's master key (found by `wallet
+/// scan-accounts`, or taken by `--hd-change`)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DerivedAccount {
+    pub path: String,
+    pub lock_arg: H160,
+    pub lock_hash: H256,
+}
+
+/// Derived accounts saved in `<index-dir>/derived-accounts.json`, key is the
+/// lock_arg of the master account.
+pub struct DerivedAccounts {
+    path: PathBuf,
+    accounts: BTreeMap<H160, Vec<DerivedAccount>>,
+}
+
+impl DerivedAccounts {
+    pub fn load(index_dir: &Path) -> Result<DerivedAccounts, String> {
+        let path = index_dir.join("derived-accounts.json");
+        let accounts = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Invalid derived accounts file: {}", err))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(DerivedAccounts { path, accounts })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let content =
+            serde_json::to_string_pretty(&self.accounts).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+
+    pub fn get(&self, master: &H160) -> &[DerivedAccount] {
+        self.accounts
+            .get(master)
+            .map(|accounts| &accounts[..])
+            .unwrap_or(&[])
+    }
+
+    pub fn set(&mut self, master: H160, accounts: Vec<DerivedAccount>) {
+        self.accounts.insert(master, accounts);
+    }
+
+    /// Record one more derived account (e.g. a new HD change address)
+    pub fn add(&mut self, master: H160, account: DerivedAccount) {
+        let accounts = self.accounts.entry(master).or_insert_with(Vec::new);
+        if accounts.iter().all(|item| item.path != account.path) {
+            accounts.push(account);
+        }
+    }
+}
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn scan_accounts(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let lock_arg = self
+            .from_account(m, false)?
+            .expect("from_account always resolves without privkey");
+        let gap_limit: u32 = FromStrParser::<u32>::default().from_matches(m, "gap-limit")?;
+        if gap_limit == 0 {
+            return Err("gap-limit must be greater than 0".to_owned());
+        }
+        let network_type = get_network_type(self.rpc_client)?;
+        let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+        let master_key = self.hd_master_key(&lock_arg, None)?;
+
+        let mut found = Vec::new();
+        let mut result = Vec::new();
+        // 0: receiving, 1: change
+        for chain in 0..2 {
+            let mut next_index = 0;
+            let mut gap = 0;
+            while gap < gap_limit {
+                let mut batch = Vec::new();
+                for index in next_index..next_index + gap_limit {
+                    let path_str = format!("m/44'/309'/0'/{}/{}", chain, index);
+                    let address = self.derive_address(&lock_arg, master_key.as_ref(), &path_str)?;
+                    let lock_hash = address
+                        .lock_script(secp_type_hash.clone())
+                        .calc_script_hash();
+                    batch.push((path_str, address, lock_hash));
+                }
+                next_index += gap_limit;
+                let states = self.with_db(|db| {
+                    batch
+                        .iter()
+                        .map(|(_, _, lock_hash)| {
+                            let used = db.get_lock_script_by_hash(lock_hash.clone()).is_some();
+                            (used, db.get_capacity(lock_hash.clone()).unwrap_or(0))
+                        })
+                        .collect::<Vec<_>>()
+                })?;
+                for ((path, address, lock_hash), (used, capacity)) in
+                    batch.into_iter().zip(states.into_iter())
+                {
+                    if !used {
+                        gap += 1;
+                        if gap >= gap_limit {
+                            break;
+                        }
+                        continue;
+                    }
+                    gap = 0;
+                    result.push(serde_json::json!({
+                        "path": path,
+                        "address": address.to_string(network_type),
+                        "capacity": capacity,
+                    }));
+                    found.push(DerivedAccount {
+                        path,
+                        lock_arg: address.hash().clone(),
+                        lock_hash: lock_hash.unpack(),
+                    });
+                }
+            }
+        }
+
+        let mut derived_accounts = DerivedAccounts::load(&self.index_dir)?;
+        derived_accounts.set(lock_arg, found);
+        derived_accounts.save()?;
+        let total_capacity = result
+            .iter()
+            .map(|item| item["capacity"].as_u64().unwrap_or(0))
+            .sum::<u64>();
+        let resp = serde_json::json!({
+            "used": result,
+            "total_capacity": total_capacity,
+        });
+        Ok(Box::new(resp))
+    }
+
+    // Derive from unlocked key, or export the key by password (asked once if
+    // not given)
+    fn hd_master_key(
+        &mut self,
+        lock_arg: &H160,
+        password: Option<&str>,
+    ) -> Result<Option<MasterPrivKey>, String> {
+        match self.key_store.extended_pubkey(lock_arg, None) {
+            Ok(_) => Ok(None),
+            Err(KeyStoreError::AccountLocked(_)) => {
+                let password = match password {
+                    Some(password) => password.to_owned(),
+                    None => read_password(false, None)?,
+                };
+                self.key_store
+                    .export_key(lock_arg, password.as_bytes())
+                    .map(Some)
+                    .map_err(|err| err.to_string())
+            }
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    fn derive_address(
+        &mut self,
+        lock_arg: &H160,
+        master_key: Option<&MasterPrivKey>,
+        path: &str,
+    ) -> Result<Address, String> {
+        let path: DerivationPath = path.parse().map_err(|err: Bip32Error| err.to_string())?;
+        let extended_pubkey = match master_key {
+            Some(key) => key.extended_pubkey(Some(&path))?,
+            None => self
+                .key_store
+                .extended_pubkey(lock_arg, Some(&path))
+                .map_err(|err| err.to_string())?,
+        };
+        Address::from_pubkey(&extended_pubkey.public_key)
+    }
+
+    // The first change address (m/44'/309'/0'/1/{index}) of the account which
+    // is neither recorded as derived nor seen in the index, record it by
+    // `DerivedAccounts::add` after the transaction is sent.
+    pub(super) fn next_change_address(
+        &mut self,
+        lock_arg: &H160,
+        password: Option<&str>,
+    ) -> Result<(Address, DerivedAccount), String> {
+        let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+        let recorded = DerivedAccounts::load(&self.index_dir)?
+            .get(lock_arg)
+            .iter()
+            .map(|account| account.path.clone())
+            .collect::<HashSet<_>>();
+        let master_key = self.hd_master_key(lock_arg, password)?;
+        for index in 0.. {
+            let path = format!("m/44'/309'/0'/1/{}", index);
+            if recorded.contains(&path) {
+                continue;
+            }
+            let address = self.derive_address(lock_arg, master_key.as_ref(), &path)?;
+            let lock_hash = address
+                .lock_script(secp_type_hash.clone())
+                .calc_script_hash();
+            if self
+                .with_db(|db| db.get_lock_script_by_hash(lock_hash.clone()))?
+                .is_none()
+            {
+                let account = DerivedAccount {
+                    path,
+                    lock_arg: address.hash().clone(),
+                    lock_hash: lock_hash.unpack(),
+                };
+                return Ok((address, account));
+            }
+        }
+        unreachable!("change address index overflow")
+    }
+
+    pub(super) fn record_change_address(
+        &mut self,
+        lock_arg: H160,
+        account: DerivedAccount,
+    ) -> Result<(), String> {
+        let mut derived_accounts = DerivedAccounts::load(&self.index_dir)?;
+        derived_accounts.add(lock_arg, account);
+        derived_accounts.save()
+    }
+
+    // Private key of the derived address at `path` (see: --from-path)
+    pub(super) fn derived_privkey(
+        &mut self,
+        lock_arg: &H160,
+        path: &str,
+        password: Option<&str>,
+    ) -> Result<PrivkeyWrapper, String> {
+        let path: DerivationPath = path.parse().map_err(|err: Bip32Error| err.to_string())?;
+        let password = match password {
+            Some(password) => password.to_owned(),
+            None => read_password(false, None)?,
+        };
+        let master_key = self
+            .key_store
+            .export_key(lock_arg, password.as_bytes())
+            .map_err(|err| err.to_string())?;
+        master_key.derive_privkey(&path).map(PrivkeyWrapper)
+    }
+}
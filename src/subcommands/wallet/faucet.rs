@@ -0,0 +1,123 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ckb_jsonrpc_types::BlockNumber;
+use ckb_sdk::{Address, NetworkType};
+use ckb_types::{prelude::*, H256};
+use clap::ArgMatches;
+
+use super::WalletSubCommand;
+use crate::utils::{
+    arg_parser::{AddressParser, ArgParser, DurationParser},
+    other::get_network_type,
+    printer::Printable,
+};
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn faucet_claim(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let address: Address = AddressParser.from_matches(m, "address")?;
+        let faucet_url = m.value_of("faucet-url").unwrap();
+        let timeout: Duration = DurationParser.from_matches(m, "wait-timeout")?;
+        let network_type = get_network_type(self.rpc_client)?;
+        if network_type != NetworkType::TestNet {
+            return Err(format!(
+                "Faucet only works on testnet, current network: {:?}",
+                network_type
+            ));
+        }
+        let address_string = address.to_string(NetworkType::TestNet);
+        let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+        let lock_hash = address.lock_script(secp_type_hash).calc_script_hash();
+        let old_capacity = if self.interactive {
+            self.with_db(|db| db.get_capacity(lock_hash.clone()))?
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        let start_number = self
+            .rpc_client
+            .get_tip_block_number()
+            .call()
+            .map_err(|err| err.to_string())?
+            .value();
+
+        let mut response = ckb_sdk::http_client(None)?
+            .post(&format!(
+                "{}/claim_events",
+                faucet_url.trim_end_matches('/')
+            ))
+            .json(&serde_json::json!({
+                "claim_event": { "address_hash": address_string },
+            }))
+            .send()
+            .map_err(|err| format!("Send faucet request error: {}", err))?;
+        let body = response.text().map_err(|err| err.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Faucet claim failed, status: {}, response: {}",
+                response.status(),
+                body
+            ));
+        }
+        let faucet_response =
+            serde_json::from_str(&body).unwrap_or_else(|_| serde_json::Value::String(body));
+
+        let deadline = Instant::now() + timeout;
+        let mut granted = None;
+        while Instant::now() < deadline {
+            thread::sleep(Duration::from_secs(3));
+            granted = if self.interactive {
+                // Index database is updated by the index thread
+                let capacity = self
+                    .with_db(|db| db.get_capacity(lock_hash.clone()))?
+                    .unwrap_or(0);
+                Some(capacity.saturating_sub(old_capacity)).filter(|capacity| *capacity > 0)
+            } else {
+                self.granted_capacity(lock_hash.unpack(), start_number + 1)?
+            };
+            if granted.is_some() {
+                break;
+            }
+        }
+        let resp = serde_json::json!({
+            "address": address_string,
+            "faucet_response": faucet_response,
+            "granted_capacity": granted,
+        });
+        Ok(Box::new(resp))
+    }
+
+    // Sum capacity of cells belong to lock_hash created since from_number
+    fn granted_capacity(
+        &mut self,
+        lock_hash: H256,
+        from_number: u64,
+    ) -> Result<Option<u64>, String> {
+        let tip_number = self
+            .rpc_client
+            .get_tip_block_number()
+            .call()
+            .map_err(|err| err.to_string())?
+            .value();
+        let mut total_capacity = 0;
+        let mut number = from_number;
+        while number <= tip_number {
+            let to_number = std::cmp::min(number + 99, tip_number);
+            total_capacity += self
+                .rpc_client
+                .get_cells_by_lock_hash(
+                    lock_hash.clone(),
+                    BlockNumber::from(number),
+                    BlockNumber::from(to_number),
+                )
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .iter()
+                .map(|cell| cell.capacity.value())
+                .sum::<u64>();
+            number = to_number + 1;
+        }
+        Ok(Some(total_capacity).filter(|capacity| *capacity > 0))
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use ckb_jsonrpc_types::{BlockNumber, TransactionWithStatus};
+use ckb_types::{
+    bytes::Bytes,
+    core::{
+        BlockView, Capacity, HeaderView as CoreHeaderView, TransactionBuilder, TransactionView,
+    },
+    packed::{self, CellInput},
+    prelude::*,
+    H256,
+};
+
+use super::rent::dao_accumulated_rate;
+use super::WalletSubCommand;
+use crate::utils::{i18n::tr_fmt, printer::format_capacity};
+
+// Default min_fee_rate of node tx pool (shannons/KB)
+const MIN_FEE_RATE: u64 = 1000;
+const FEE_RATE_SAMPLE_BLOCKS: u64 = 20;
+
+impl<'a> WalletSubCommand<'a> {
+    pub(super) fn resolve_fee_rate(&mut self, preset: &str) -> Result<u64, String> {
+        if let Some(fee_rate) = self.fee_rates.get(preset) {
+            return Ok(*fee_rate);
+        }
+        let mut fee_rates = self.recent_fee_rates()?;
+        fee_rates.sort();
+        let percentile = match preset {
+            "slow" => 25,
+            "fast" => 90,
+            _ => 50,
+        };
+        let fee_rate = if fee_rates.is_empty() {
+            MIN_FEE_RATE
+        } else {
+            fee_rates[(fee_rates.len() - 1) * percentile / 100]
+        };
+        Ok(std::cmp::max(fee_rate, MIN_FEE_RATE))
+    }
+
+    // Fee rates (shannons/KB) of transactions in recent blocks
+    fn recent_fee_rates(&mut self) -> Result<Vec<u64>, String> {
+        let tip_number = self
+            .rpc_client
+            .get_tip_block_number()
+            .call()
+            .map_err(|err| err.to_string())?
+            .value();
+        let mut fee_rates = Vec::new();
+        let from_number = tip_number.saturating_sub(FEE_RATE_SAMPLE_BLOCKS - 1);
+        for number in from_number..=tip_number {
+            let block: BlockView = match self
+                .rpc_client
+                .get_block_by_number(BlockNumber::from(number))
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+            {
+                Some(block) => block.into(),
+                None => continue,
+            };
+            // Skip cellbase
+            'txs: for tx in block.transactions().into_iter().skip(1) {
+                let mut input_capacity = 0;
+                for input in tx.inputs().into_iter() {
+                    let out_point = input.previous_output();
+                    let index: u32 = out_point.index().unpack();
+                    let capacity = self
+                        .rpc_client
+                        .get_transaction(out_point.tx_hash().unpack())
+                        .call()
+                        .map_err(|err| err.to_string())?
+                        .0
+                        .and_then(|tx_with_status| {
+                            tx_with_status
+                                .transaction
+                                .inner
+                                .outputs
+                                .get(index as usize)
+                                .map(|output| output.capacity.value())
+                        });
+                    match capacity {
+                        Some(capacity) => input_capacity += capacity,
+                        None => continue 'txs,
+                    }
+                }
+                let output_capacity = tx
+                    .outputs()
+                    .into_iter()
+                    .map(|output| {
+                        let capacity: Capacity = output.capacity().unpack();
+                        capacity.as_u64()
+                    })
+                    .sum::<u64>();
+                // 4 bytes for the offset of transaction in block
+                let size = tx.data().as_slice().len() as u64 + 4;
+                if input_capacity > output_capacity {
+                    fee_rates.push((input_capacity - output_capacity) * 1000 / size);
+                }
+            }
+        }
+        Ok(fee_rates)
+    }
+
+    // Recompute the fee from resolved inputs, refuse to send when it is not
+    // positive or higher than the configured max tx fee.
+    // NervosDAO inputs are counted with their interest, the withdraw header is
+    // taken as the latest header dep (the one used by `withdraw-dao`), so the
+    // fee of a DAO withdraw transaction is checked as well.
+    pub(super) fn check_fee(&mut self, transaction: &TransactionView) -> Result<(), String> {
+        let dao_type_hash = self.genesis_info()?.dao_type_hash().clone();
+        let mut previous_txs: HashMap<H256, TransactionWithStatus> = HashMap::new();
+        let mut input_capacity: u64 = 0;
+        // (capacity, occupied capacity, block hash) of deposited cells
+        let mut dao_inputs = Vec::new();
+        for input in transaction.inputs().into_iter() {
+            let out_point = input.previous_output();
+            let tx_hash: H256 = out_point.tx_hash().unpack();
+            let index: u32 = out_point.index().unpack();
+            if !previous_txs.contains_key(&tx_hash) {
+                let tx_with_status = self
+                    .rpc_client
+                    .get_transaction(tx_hash.clone())
+                    .call()
+                    .map_err(|err| format!("Send get_transaction error: {}", err))?
+                    .0
+                    .ok_or_else(|| format!("Input transaction not found: {:#x}", tx_hash))?;
+                previous_txs.insert(tx_hash.clone(), tx_with_status);
+            }
+            let previous_tx = &previous_txs[&tx_hash];
+            let output: packed::CellOutput = previous_tx
+                .transaction
+                .inner
+                .outputs
+                .get(index as usize)
+                .cloned()
+                .ok_or_else(|| format!("Input cell not found: {:#x}-{}", tx_hash, index))?
+                .into();
+            let capacity: u64 = output.capacity().unpack();
+            let is_dao_input = output
+                .type_()
+                .to_opt()
+                .map_or(false, |script| script.calc_script_hash() == dao_type_hash);
+            if is_dao_input {
+                let data_len = previous_tx
+                    .transaction
+                    .inner
+                    .outputs_data
+                    .get(index as usize)
+                    .map(|data| data.len())
+                    .unwrap_or(0);
+                let occupied = output
+                    .occupied_capacity(Capacity::bytes(data_len).map_err(|err| err.to_string())?)
+                    .map_err(|err| err.to_string())?
+                    .as_u64();
+                let block_hash =
+                    previous_tx.tx_status.block_hash.clone().ok_or_else(|| {
+                        format!("Input transaction not committed: {:#x}", tx_hash)
+                    })?;
+                dao_inputs.push((capacity, occupied, block_hash));
+            } else {
+                input_capacity = input_capacity
+                    .checked_add(capacity)
+                    .ok_or_else(|| "Input capacity overflow".to_owned())?;
+            }
+        }
+        if !dao_inputs.is_empty() {
+            let mut withdraw_ar = 0;
+            for block_hash in transaction.header_deps().into_iter() {
+                withdraw_ar = withdraw_ar.max(self.accumulated_rate(block_hash.unpack())?);
+            }
+            for (capacity, occupied, block_hash) in dao_inputs {
+                let deposit_ar = self.accumulated_rate(block_hash)?;
+                if deposit_ar == 0 || withdraw_ar < deposit_ar {
+                    return Err(
+                        "Refuse to send transaction: withdraw header of NervosDAO input not found in header deps"
+                            .to_owned(),
+                    );
+                }
+                let counted = u128::from(capacity.saturating_sub(occupied))
+                    * u128::from(withdraw_ar)
+                    / u128::from(deposit_ar)
+                    + u128::from(occupied);
+                input_capacity = u64::try_from(counted)
+                    .ok()
+                    .and_then(|counted| input_capacity.checked_add(counted))
+                    .ok_or_else(|| "Input capacity overflow".to_owned())?;
+            }
+        }
+        let output_capacity = transaction
+            .outputs()
+            .into_iter()
+            .map(|output| -> u64 {
+                let capacity: Capacity = output.capacity().unpack();
+                capacity.as_u64()
+            })
+            .try_fold(0u64, u64::checked_add)
+            .ok_or_else(|| "Output capacity overflow".to_owned())?;
+        if input_capacity <= output_capacity {
+            return Err(format!(
+                "Refuse to send transaction: fee is not positive (inputs: {} shannons, outputs: {} shannons)",
+                input_capacity, output_capacity
+            ));
+        }
+        let fee = input_capacity - output_capacity;
+        if fee > self.max_tx_fee {
+            return Err(tr_fmt(
+                "Refuse to send transaction: fee {} CKB is higher than max tx fee {} CKB (see: config --max-tx-fee)",
+                &[
+                    &format_capacity(fee),
+                    &format_capacity(self.max_tx_fee),
+                ],
+            ));
+        }
+        // 4 bytes for the offset of transaction in block
+        let tx_size = transaction.data().as_slice().len() as u64 + 4;
+        let min_fee = (MIN_FEE_RATE * tx_size + 999) / 1000;
+        if fee < min_fee {
+            return Err(format!(
+                "Refuse to send transaction: fee {} shannons is lower than the min fee {} shannons of the node for {} bytes",
+                fee, min_fee, tx_size
+            ));
+        }
+        Ok(())
+    }
+
+    fn accumulated_rate(&mut self, block_hash: H256) -> Result<u64, String> {
+        let header: CoreHeaderView = self
+            .rpc_client
+            .get_header(block_hash.clone())
+            .call()
+            .map_err(|err| err.to_string())?
+            .0
+            .ok_or_else(|| format!("Header not found: {:#x}", block_hash))?
+            .into();
+        dao_accumulated_rate(&header.dao().raw_data())
+            .ok_or_else(|| format!("Invalid dao field of header: {:#x}", block_hash))
+    }
+}
+
+pub(super) fn estimate_tx_size(inputs: usize, outputs: &[(packed::CellOutput, Bytes)]) -> usize {
+    let witness = packed::WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let mut builder = TransactionBuilder::default()
+        .cell_dep(packed::CellDep::default())
+        .witness(witness.as_bytes().pack());
+    for index in 0..inputs {
+        builder = builder.input(CellInput::default());
+        if index > 0 {
+            builder = builder.witness(Bytes::new().pack());
+        }
+    }
+    for (output, data) in outputs {
+        builder = builder.output(output.clone()).output_data(data.pack());
+    }
+    // 4 bytes for the offset of transaction in block
+    builder.build().data().as_slice().len() + 4
+}
@@ -0,0 +1,281 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use ckb_sdk::{Address, GenesisInfo, NetworkType};
+use ckb_types::{
+    core::{BlockView, Capacity},
+    prelude::*,
+    H256,
+};
+use clap::ArgMatches;
+use serde_derive::{Deserialize, Serialize};
+
+use super::pending::PendingTxStore;
+use super::WalletSubCommand;
+use crate::utils::{
+    arg_parser::{AddressParser, ArgParser, FixedHashParser, FromStrParser},
+    other::{check_address_prefix, get_network_type},
+    printer::Printable,
+};
+
+// Webhooks are sent one by one, a slow receiver must not hold the rest
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookEvent {
+    // Watched address received capacity
+    Receive,
+    // Watched cell is spent
+    Spend,
+    // One of our pending transactions is committed
+    Commit,
+}
+
+impl FromStr for HookEvent {
+    type Err = String;
+    fn from_str(input: &str) -> Result<HookEvent, String> {
+        match input {
+            "receive" => Ok(HookEvent::Receive),
+            "spend" => Ok(HookEvent::Spend),
+            "commit" => Ok(HookEvent::Commit),
+            _ => Err(format!("Invalid hook event: {}", input)),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Hook {
+    pub event: HookEvent,
+    // For receive event
+    #[serde(default)]
+    pub address: Option<String>,
+    // For spend event: (tx_hash, index)
+    #[serde(default)]
+    pub out_point: Option<(H256, u32)>,
+    // Shell command, event payload is in CKB_CLI_EVENT environment variable
+    #[serde(default)]
+    pub command: Option<String>,
+    // Event payload is POSTed as json
+    #[serde(default)]
+    pub webhook: Option<String>,
+    // Only blocks after this number trigger the hook (no replay of history)
+    pub from_block: u64,
+}
+
+/// Hooks saved in `<index-dir>/hooks.json`, triggered by index thread
+pub struct Hooks {
+    path: PathBuf,
+    pub hooks: Vec<Hook>,
+}
+
+impl Hooks {
+    pub fn load(index_dir: &Path) -> Result<Hooks, String> {
+        let path = index_dir.join("hooks.json");
+        let hooks = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content).map_err(|err| format!("Invalid hooks file: {}", err))?
+        } else {
+            Vec::new()
+        };
+        Ok(Hooks { path, hooks })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(&self.hooks).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+
+    /// Collect events of the block and run hooks in background
+    pub fn trigger(
+        &self,
+        index_dir: &Path,
+        block: &BlockView,
+        genesis_info: &GenesisInfo,
+        network_type: NetworkType,
+    ) {
+        let hooks = self
+            .hooks
+            .iter()
+            .filter(|hook| block.number() > hook.from_block)
+            .collect::<Vec<_>>();
+        if hooks.is_empty() {
+            return;
+        }
+        let genesis_hash: H256 = genesis_info.header().hash().unpack();
+        let pending_txs = PendingTxStore::load(index_dir, &genesis_hash).ok();
+        let block_hash: H256 = block.hash().unpack();
+
+        let mut actions = Vec::new();
+        for hook in hooks {
+            for tx in block.transactions() {
+                let tx_hash: H256 = tx.hash().unpack();
+                let mut payload = match hook.event {
+                    HookEvent::Receive => {
+                        let address: Address = match hook
+                            .address
+                            .as_ref()
+                            .and_then(|address| AddressParser.parse(address).ok())
+                        {
+                            Some(address) => address,
+                            None => continue,
+                        };
+                        let lock_hash = address
+                            .lock_script(genesis_info.secp_type_hash().clone())
+                            .calc_script_hash();
+                        let capacity = tx
+                            .outputs()
+                            .into_iter()
+                            .filter(|output| output.lock().calc_script_hash() == lock_hash)
+                            .map(|output| {
+                                let capacity: Capacity = output.capacity().unpack();
+                                capacity.as_u64()
+                            })
+                            .sum::<u64>();
+                        if capacity == 0 {
+                            continue;
+                        }
+                        serde_json::json!({
+                            "address": address.to_string(network_type),
+                            "capacity": capacity,
+                        })
+                    }
+                    HookEvent::Spend => {
+                        let (out_point_tx_hash, index) = match hook.out_point {
+                            Some(ref out_point) => out_point.clone(),
+                            None => continue,
+                        };
+                        let spent = tx.inputs().into_iter().any(|input| {
+                            let out_point = input.previous_output();
+                            let input_tx_hash: H256 = out_point.tx_hash().unpack();
+                            let input_index: u32 = out_point.index().unpack();
+                            input_tx_hash == out_point_tx_hash && input_index == index
+                        });
+                        if !spent {
+                            continue;
+                        }
+                        serde_json::json!({
+                            "out_point": {
+                                "tx_hash": out_point_tx_hash,
+                                "index": index,
+                            },
+                        })
+                    }
+                    HookEvent::Commit => {
+                        let is_ours = pending_txs
+                            .as_ref()
+                            .map(|store| store.contains(&tx_hash))
+                            .unwrap_or(false);
+                        if !is_ours {
+                            continue;
+                        }
+                        serde_json::json!({})
+                    }
+                };
+                payload["event"] = serde_json::json!(hook.event);
+                payload["block_number"] = serde_json::json!(block.number());
+                payload["block_hash"] = serde_json::json!(block_hash);
+                payload["tx_hash"] = serde_json::json!(tx_hash);
+                actions.push((hook.command.clone(), hook.webhook.clone(), payload));
+            }
+        }
+        if actions.is_empty() {
+            return;
+        }
+        // Never block the index thread
+        thread::spawn(move || {
+            for (command, webhook, payload) in actions {
+                if let Some(command) = command {
+                    run_command(&command, &payload);
+                }
+                if let Some(url) = webhook {
+                    let result = ckb_sdk::http_client(Some(WEBHOOK_TIMEOUT)).and_then(|client| {
+                        client
+                            .post(&url)
+                            .json(&payload)
+                            .send()
+                            .map_err(|err| err.to_string())
+                    });
+                    if let Err(err) = result {
+                        log::warn!("Send webhook to {} failed: {}", url, err);
+                    }
+                }
+            }
+        });
+    }
+}
+
+fn run_command(command: &str, payload: &serde_json::Value) {
+    let mut process = if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.arg("/C");
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.arg("-c");
+        process
+    };
+    match process
+        .arg(command)
+        .env("CKB_CLI_EVENT", payload.to_string())
+        .status()
+    {
+        Ok(status) if !status.success() => {
+            log::warn!("Hook command `{}` exit with {}", command, status);
+        }
+        Err(err) => log::warn!("Run hook command `{}` failed: {}", command, err),
+        _ => {}
+    }
+}
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn add_hook(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let event: HookEvent = FromStrParser::<HookEvent>::new().from_matches(m, "event")?;
+        let address = match m.value_of("address") {
+            Some(address) if event == HookEvent::Receive => {
+                let network_type = get_network_type(self.rpc_client)?;
+                check_address_prefix(address, network_type)?;
+                Some(address.to_owned())
+            }
+            _ => None,
+        };
+        let out_point = if event == HookEvent::Spend {
+            let tx_hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+            let index: u32 = FromStrParser::<u32>::default().from_matches(m, "output-index")?;
+            Some((tx_hash, index))
+        } else {
+            None
+        };
+        let command = m.value_of("command").map(ToOwned::to_owned);
+        let webhook = m.value_of("webhook").map(ToOwned::to_owned);
+        let from_block = self
+            .rpc_client
+            .get_tip_block_number()
+            .call()
+            .map_err(|err| err.to_string())?
+            .value();
+
+        let mut hooks = Hooks::load(&self.index_dir)?;
+        hooks.hooks.push(Hook {
+            event,
+            address,
+            out_point,
+            command,
+            webhook,
+            from_block,
+        });
+        hooks.save()?;
+        let resp = serde_json::json!({
+            "index": hooks.hooks.len() - 1,
+            "from_block": from_block,
+        });
+        Ok(Box::new(resp))
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ckb_hash::blake2b_256;
+use ckb_types::{core::TransactionView, prelude::*, H256};
+use clap::ArgMatches;
+use faster_hex::hex_string;
+use serde_derive::{Deserialize, Serialize};
+
+use super::WalletSubCommand;
+use crate::utils::{i18n::tr_fmt, printer::Printable};
+
+/// Transaction sent for an idempotency key, `tx_hash` is empty while the
+/// transaction is being built
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IdempotentTx {
+    pub key: String,
+    pub tx_hash: Option<H256>,
+    // Unix timestamp in seconds
+    pub created_at: u64,
+}
+
+pub enum IdempotencyState {
+    // Already sent by an earlier run
+    Sent(H256),
+    Reserved(IdempotencyReservation),
+}
+
+/// Idempotency keys of transfers saved in `<index-dir>/idempotency-keys/`,
+/// one file per key (scoped by the lock hash of the sender). The file is
+/// created exclusively, so concurrent runs with the same key can not both
+/// send a transaction.
+pub struct IdempotencyKeys {
+    dir: PathBuf,
+}
+
+impl IdempotencyKeys {
+    pub fn new(index_dir: &Path) -> IdempotencyKeys {
+        IdempotencyKeys {
+            dir: index_dir.join("idempotency-keys"),
+        }
+    }
+
+    fn key_path(&self, lock_hash: &H256, key: &str) -> PathBuf {
+        let mut data = lock_hash.as_bytes().to_vec();
+        data.extend_from_slice(key.as_bytes());
+        let name = hex_string(&blake2b_256(&data)[0..20]).expect("encode key hash failed");
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// Reserve the key for a new transaction, or return the transaction
+    /// already sent for it
+    pub fn reserve(&self, lock_hash: &H256, key: &str) -> Result<IdempotencyState, String> {
+        fs::create_dir_all(&self.dir).map_err(|err| err.to_string())?;
+        let path = self.key_path(lock_hash, key);
+        let entry = IdempotentTx {
+            key: key.to_owned(),
+            tx_hash: None,
+            created_at: now_secs(),
+        };
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                let content = serde_json::to_string(&entry).map_err(|err| err.to_string())?;
+                file.write_all(content.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                Ok(IdempotencyState::Reserved(IdempotencyReservation {
+                    path,
+                    keep: false,
+                }))
+            }
+            Err(ref err) if err.kind() == ErrorKind::AlreadyExists => {
+                let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+                // Empty while the other run is writing the reservation
+                let existing: Option<IdempotentTx> = serde_json::from_str(&content).ok();
+                match existing.and_then(|existing| existing.tx_hash) {
+                    Some(tx_hash) => Ok(IdempotencyState::Sent(tx_hash)),
+                    None => Err(tr_fmt(
+                        "Idempotency key {} is reserved by another running command, remove {} if no such command is running",
+                        &[&key, &format!("{:?}", path)],
+                    )),
+                }
+            }
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+/// A reserved idempotency key, released on drop unless a transaction was
+/// recorded for it
+pub struct IdempotencyReservation {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl IdempotencyReservation {
+    /// Record the transaction before sending, so a retry after a timeout
+    /// never sends a second one
+    pub fn record(&mut self, key: &str, tx_hash: H256) -> Result<(), String> {
+        let entry = IdempotentTx {
+            key: key.to_owned(),
+            tx_hash: Some(tx_hash),
+            created_at: now_secs(),
+        };
+        let content = serde_json::to_string(&entry).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())?;
+        self.keep = true;
+        Ok(())
+    }
+
+    pub fn release(mut self) -> Result<(), String> {
+        // Removed here, not again on drop
+        self.keep = true;
+        fs::remove_file(&self.path).map_err(|err| err.to_string())
+    }
+}
+
+impl Drop for IdempotencyReservation {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+impl<'a> WalletSubCommand<'a> {
+    // Reserve the idempotency key of the sender before building the
+    // transaction, or the transaction already sent for it
+    pub(super) fn reserve_idempotency_key(
+        &self,
+        m: &ArgMatches,
+        lock_hash: &H256,
+    ) -> Result<Option<IdempotencyState>, String> {
+        let key = match m.value_of("idempotency-key") {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let state = IdempotencyKeys::new(&self.index_dir).reserve(lock_hash, key)?;
+        if let IdempotencyState::Sent(ref tx_hash) = state {
+            eprintln!(
+                "[INFO]: idempotency key {} was used by transaction {:#x}, not sending again",
+                key, tx_hash
+            );
+        }
+        Ok(Some(state))
+    }
+
+    // Record the transaction of the reserved key before sending, so a retry
+    // after a timeout never sends a second transaction. The key is released
+    // only when the node does not know the transaction after a failed send.
+    pub(super) fn send_with_idempotency_key(
+        &mut self,
+        m: &ArgMatches,
+        reservation: Option<IdempotencyReservation>,
+        transaction: TransactionView,
+        debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        let broadcast_all = m.is_present("broadcast-all");
+        let mut reservation = match reservation {
+            Some(reservation) => reservation,
+            None => return self.send_transaction(transaction, broadcast_all, debug),
+        };
+        let key = m.value_of("idempotency-key").unwrap();
+        let tx_hash: H256 = transaction.hash().unpack();
+        reservation.record(key, tx_hash.clone())?;
+        let result = self.send_transaction(transaction, broadcast_all, debug);
+        if result.is_err() {
+            let known = self
+                .rpc_client
+                .get_transaction(tx_hash)
+                .call()
+                .map(|resp| resp.0.is_some())
+                .unwrap_or(true);
+            if !known {
+                reservation.release()?;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_idempotency_key() {
+        let index_dir = std::env::temp_dir().join("ckb-cli-test-idempotency-keys");
+        let _ = fs::remove_dir_all(&index_dir);
+        let keys = IdempotencyKeys::new(&index_dir);
+        let lock_hash = H256::from([1u8; 32]);
+        let other_lock_hash = H256::from([2u8; 32]);
+
+        let reservation = match keys.reserve(&lock_hash, "order-1").unwrap() {
+            IdempotencyState::Reserved(reservation) => reservation,
+            IdempotencyState::Sent(_) => panic!("key not used yet"),
+        };
+        // A concurrent run with the same key is refused
+        assert!(keys.reserve(&lock_hash, "order-1").is_err());
+        // Other keys and other senders are independent
+        assert!(keys.reserve(&lock_hash, "order-2").is_ok());
+        assert!(keys.reserve(&other_lock_hash, "order-1").is_ok());
+
+        // Released (building or sending failed): the key can be used again
+        reservation.release().unwrap();
+        let mut reservation = match keys.reserve(&lock_hash, "order-1").unwrap() {
+            IdempotencyState::Reserved(reservation) => reservation,
+            IdempotencyState::Sent(_) => panic!("key was released"),
+        };
+
+        // Recorded: the transaction is replayed and kept after drop
+        let tx_hash = H256::from([3u8; 32]);
+        reservation.record("order-1", tx_hash.clone()).unwrap();
+        drop(reservation);
+        match keys.reserve(&lock_hash, "order-1").unwrap() {
+            IdempotencyState::Sent(sent) => assert_eq!(sent, tx_hash),
+            IdempotencyState::Reserved(_) => panic!("key was used"),
+        }
+        fs::remove_dir_all(&index_dir).unwrap();
+    }
+}
@@ -5,7 +5,7 @@ use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
-use ckb_index::{with_index_db, IndexDatabase};
+use ckb_index::{with_index_db, IndexDatabase, WatchList};
 use ckb_jsonrpc_types::BlockNumber;
 use ckb_sdk::GenesisInfo;
 use ckb_sdk::HttpRpcClient;
@@ -18,10 +18,13 @@ use ckb_util::RwLock;
 use crossbeam_channel::{Receiver, Sender};
 use serde_derive::{Deserialize, Serialize};
 
+use super::hooks::Hooks;
 use crate::utils::other::get_network_type;
 
 pub enum IndexRequest {
     UpdateUrl(String),
+    // Switched with the db namespace of a profile
+    UpdateIndexDir(PathBuf),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -182,7 +185,7 @@ impl IndexController {
 
 pub fn start_index_thread(
     url: &str,
-    index_dir: PathBuf,
+    mut index_dir: PathBuf,
     state: Arc<RwLock<IndexThreadState>>,
 ) -> IndexController {
     let mut rpc_url = url.to_owned();
@@ -196,7 +199,7 @@ pub fn start_index_thread(
         .spawn(move || {
             loop {
                 // Wait first request
-                match try_recv(&receiver, &mut rpc_url) {
+                match try_recv(&receiver, &mut rpc_url, &mut index_dir) {
                     Some(true) => {
                         state.write().stop();
                         log::info!("Index database thread stopped");
@@ -213,7 +216,7 @@ pub fn start_index_thread(
                     &receiver,
                     &mut rpc_url,
                     &mut rpc_client,
-                    &index_dir,
+                    &mut index_dir,
                     &state,
                     &shutdown_clone,
                 ) {
@@ -247,14 +250,16 @@ fn process(
     receiver: &Receiver<Request<IndexRequest, IndexResponse>>,
     rpc_url: &mut String,
     rpc_client: &mut HttpRpcClient,
-    index_dir: &PathBuf,
+    next_index_dir: &mut PathBuf,
     state: &Arc<RwLock<IndexThreadState>>,
     shutdown: &Arc<AtomicBool>,
 ) -> Result<bool, String> {
     let old_rpc_url = rpc_url.clone();
-    if let Some(exit) = try_recv(&receiver, rpc_url) {
+    if let Some(exit) = try_recv(&receiver, rpc_url, next_index_dir) {
         return Ok(exit);
     }
+    // A new index directory is used from next call
+    let index_dir = &next_index_dir.clone();
 
     state.write().start_init();
     if &old_rpc_url != rpc_url {
@@ -286,10 +291,20 @@ fn process(
         }
 
         if tip_header.number() >= next_number {
+            let hooks = Hooks::load(index_dir)
+                .map_err(|err| log::warn!("Load hooks failed: {}", err))
+                .ok();
+            let watch_list = WatchList::load(index_dir).map_err(|err| err.to_string())?;
             let exit_opt = with_index_db(index_dir, genesis_hash.clone(), |backend, cf| {
+                // The database directory depends on the watch list, retry if
+                // it changed while opening the database
+                if WatchList::load(index_dir)?.lock_hashes() != watch_list.lock_hashes() {
+                    return Ok(None);
+                }
                 let mut db =
                     IndexDatabase::from_db(backend, cf, network_type, genesis_info.clone(), false)
                         .unwrap();
+                db.set_watch_list(watch_list.filter());
                 if db.last_number().is_none() {
                     db.apply_next_block(genesis_block.clone())
                         .expect("Apply genesis block failed");
@@ -299,7 +314,7 @@ fn process(
                     if shutdown.load(Ordering::Relaxed) {
                         return Ok(Some(true));
                     }
-                    if let Some(exit) = try_recv(&receiver, rpc_url) {
+                    if let Some(exit) = try_recv(&receiver, rpc_url, next_index_dir) {
                         return Ok(Some(exit));
                     }
                     let next_block_number = BlockNumber::from(db.next_number().unwrap());
@@ -309,8 +324,11 @@ fn process(
                         .map_err(|err| err.to_string())?
                         .0
                     {
-                        db.apply_next_block(next_block.into())
-                            .expect("Add block failed");
+                        let next_block: BlockView = next_block.into();
+                        if let Some(hooks) = hooks.as_ref() {
+                            hooks.trigger(index_dir, &next_block, &genesis_info, network_type);
+                        }
+                        db.apply_next_block(next_block).expect("Add block failed");
                         state
                             .write()
                             .processing(db.last_header().cloned(), tip_header.number());
@@ -334,7 +352,7 @@ fn process(
         if shutdown.load(Ordering::Relaxed) {
             return Ok(true);
         }
-        if let Some(exit) = try_recv(&receiver, rpc_url) {
+        if let Some(exit) = try_recv(&receiver, rpc_url, next_index_dir) {
             return Ok(exit);
         }
         thread::sleep(Duration::from_millis(100));
@@ -344,9 +362,10 @@ fn process(
 fn try_recv(
     receiver: &Receiver<Request<IndexRequest, IndexResponse>>,
     rpc_url: &mut String,
+    index_dir: &mut PathBuf,
 ) -> Option<bool> {
     match receiver.try_recv() {
-        Ok(request) => Some(process_request(request, rpc_url)),
+        Ok(request) => Some(process_request(request, rpc_url, index_dir)),
         Err(err) => {
             if err.is_disconnected() {
                 log::info!("Sender dropped, exit index thread");
@@ -358,7 +377,11 @@ fn try_recv(
     }
 }
 
-fn process_request(request: Request<IndexRequest, IndexResponse>, rpc_url: &mut String) -> bool {
+fn process_request(
+    request: Request<IndexRequest, IndexResponse>,
+    rpc_url: &mut String,
+    index_dir: &mut PathBuf,
+) -> bool {
     let Request {
         responder,
         arguments,
@@ -368,5 +391,9 @@ fn process_request(request: Request<IndexRequest, IndexResponse>, rpc_url: &mut
             *rpc_url = url;
             responder.send(IndexResponse::Ok).is_err()
         }
+        IndexRequest::UpdateIndexDir(dir) => {
+            *index_dir = dir;
+            responder.send(IndexResponse::Ok).is_err()
+        }
     }
 }
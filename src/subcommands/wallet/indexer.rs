@@ -0,0 +1,228 @@
+use ckb_index::{IndexDatabase, LiveCellInfo};
+use ckb_jsonrpc_types::{BlockNumber, Script, Uint64};
+use ckb_types::{packed, prelude::*, H256};
+use clap::ArgMatches;
+use faster_hex::hex_string;
+use serde_derive::Deserialize;
+
+use super::WalletSubCommand;
+use crate::utils::{
+    arg_parser::{ArgParser, FromStrParser, HexParser},
+    printer::Printable,
+};
+
+// Search key and cursor follow ckb-indexer's `get_cells` RPC, so the same
+// json can be used against both.
+// See: https://github.com/nervosnetwork/ckb-indexer#get_cells
+
+#[derive(Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptType {
+    Lock,
+    Type,
+}
+
+#[derive(Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SearchKeyFilter {
+    #[serde(default)]
+    pub script: Option<Script>,
+    // [start, end)
+    #[serde(default)]
+    pub output_data_len_range: Option<[Uint64; 2]>,
+    #[serde(default)]
+    pub output_capacity_range: Option<[Uint64; 2]>,
+    #[serde(default)]
+    pub block_range: Option<[BlockNumber; 2]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SearchKey {
+    pub script: Script,
+    pub script_type: ScriptType,
+    #[serde(default)]
+    pub filter: Option<SearchKeyFilter>,
+}
+
+fn script_hash(script: &Script) -> H256 {
+    packed::Script::from(script.clone())
+        .calc_script_hash()
+        .unpack()
+}
+
+fn in_range(value: u64, range: &Option<[Uint64; 2]>) -> bool {
+    range
+        .as_ref()
+        .map(|[start, end]| value >= start.value() && value < end.value())
+        .unwrap_or(true)
+}
+
+impl SearchKey {
+    fn matches(&self, info: &LiveCellInfo) -> bool {
+        let filter = match self.filter {
+            Some(ref filter) => filter,
+            None => return true,
+        };
+        // Filter script is the other script of the cell
+        let script_matched = filter
+            .script
+            .as_ref()
+            .map(|script| {
+                let hash = script_hash(script);
+                match self.script_type {
+                    ScriptType::Lock => info
+                        .type_hashes
+                        .as_ref()
+                        .map(|(_, type_hash)| type_hash == &hash)
+                        .unwrap_or(false),
+                    ScriptType::Type => info.lock_hash == hash,
+                }
+            })
+            .unwrap_or(true);
+        let block_matched = filter
+            .block_range
+            .as_ref()
+            .map(|[start, end]| info.number >= start.value() && info.number < end.value())
+            .unwrap_or(true);
+        script_matched
+            && block_matched
+            && in_range(info.data_bytes, &filter.output_data_len_range)
+            && in_range(info.capacity, &filter.output_capacity_range)
+    }
+}
+
+/// Position of a live cell: (block_number, tx_index, output_index)
+pub type Cursor = (u64, u32, u32);
+
+pub fn cell_cursor(info: &LiveCellInfo) -> Cursor {
+    (info.number, info.index.tx_index, info.index.output_index)
+}
+
+pub fn encode_cursor(cursor: Cursor) -> String {
+    let mut bytes = cursor.0.to_be_bytes().to_vec();
+    bytes.extend(cursor.1.to_be_bytes().to_vec());
+    bytes.extend(cursor.2.to_be_bytes().to_vec());
+    format!("0x{}", hex_string(&bytes).unwrap())
+}
+
+pub fn decode_cursor(input: &str) -> Result<Cursor, String> {
+    let bytes = HexParser.parse(input)?;
+    if bytes.len() != 16 {
+        return Err(format!("Invalid cursor: {}", input));
+    }
+    let mut number = [0u8; 8];
+    let mut tx_index = [0u8; 4];
+    let mut output_index = [0u8; 4];
+    number.copy_from_slice(&bytes[0..8]);
+    tx_index.copy_from_slice(&bytes[8..12]);
+    output_index.copy_from_slice(&bytes[12..16]);
+    Ok((
+        u64::from_be_bytes(number),
+        u32::from_be_bytes(tx_index),
+        u32::from_be_bytes(output_index),
+    ))
+}
+
+/// Search live cells in local index, return at most `limit` cells and the
+/// cursor of the last one.
+pub fn search_cells(
+    db: &IndexDatabase,
+    search_key: &SearchKey,
+    order: Order,
+    limit: usize,
+    after_cursor: Option<Cursor>,
+) -> (Vec<LiveCellInfo>, Option<Cursor>) {
+    let hash = script_hash(&search_key.script).pack();
+    let from_number = match (order, after_cursor) {
+        (Order::Asc, Some(cursor)) => Some(cursor.0),
+        _ => None,
+    };
+    let mut matched = 0;
+    let terminator = |_idx, info: &LiveCellInfo| {
+        // Descending order need all cells
+        if order == Order::Desc {
+            return (false, search_key.matches(info));
+        }
+        let stop = matched >= limit;
+        let after = after_cursor
+            .map(|cursor| cell_cursor(info) > cursor)
+            .unwrap_or(true);
+        let push_info = !stop && after && search_key.matches(info);
+        if push_info {
+            matched += 1;
+        }
+        (stop, push_info)
+    };
+    let mut infos = match search_key.script_type {
+        ScriptType::Lock => db.get_live_cells_by_lock(hash, from_number, terminator),
+        ScriptType::Type => db.get_live_cells_by_type(hash, from_number, terminator),
+    };
+    if order == Order::Desc {
+        infos = infos
+            .into_iter()
+            .rev()
+            .filter(|info| {
+                after_cursor
+                    .map(|cursor| cell_cursor(info) < cursor)
+                    .unwrap_or(true)
+            })
+            .take(limit)
+            .collect();
+    }
+    let last_cursor = infos.last().map(cell_cursor);
+    (infos, last_cursor)
+}
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn get_cells(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let search_key: SearchKey = serde_json::from_str(m.value_of("search-key").unwrap())
+            .map_err(|err| format!("Invalid search key: {}", err))?;
+        let order = match m.value_of("order") {
+            Some("desc") => Order::Desc,
+            _ => Order::Asc,
+        };
+        let limit: usize = FromStrParser::<usize>::default().from_matches(m, "limit")?;
+        let after_cursor = match m.value_of("after-cursor") {
+            Some(input) => Some(decode_cursor(input)?),
+            None => None,
+        };
+        let (infos, last_cursor) =
+            self.with_db(|db| search_cells(&db, &search_key, order, limit, after_cursor))?;
+
+        let mut objects = Vec::new();
+        for info in infos {
+            let out_point = info.out_point();
+            let cell = self
+                .rpc_client
+                .get_live_cell(out_point.clone().into(), true)
+                .call()
+                .map_err(|err| err.to_string())?
+                .cell;
+            // Index may fall behind the node
+            let cell = match cell {
+                Some(cell) => cell,
+                None => continue,
+            };
+            objects.push(serde_json::json!({
+                "output": cell.output,
+                "output_data": cell.data.map(|data| data.content),
+                "out_point": ckb_jsonrpc_types::OutPoint::from(out_point),
+                "block_number": BlockNumber::from(info.number),
+                "tx_index": ckb_jsonrpc_types::Uint32::from(info.index.tx_index),
+            }));
+        }
+        let resp = serde_json::json!({
+            "objects": objects,
+            "last_cursor": last_cursor.map(encode_cursor).unwrap_or_else(|| "0x".to_owned()),
+        });
+        Ok(Box::new(resp))
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_jsonrpc_types::Status;
+use ckb_sdk::HttpRpcClient;
+use ckb_types::{core::TransactionView, prelude::*, H256};
+use rand::Rng;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobTxStatus {
+    // Recorded before sending, the run may be interrupted before it is sent
+    Signed,
+    Sent,
+    // Rejected by the node
+    Failed,
+    // Not known by the node when resumed, the inputs can be spent again
+    Dropped,
+}
+
+/// A transaction of a batch job
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JobTx {
+    pub tx_hash: H256,
+    // Consumed out points: (tx_hash, index)
+    pub inputs: Vec<(H256, u32)>,
+    pub capacity: u64,
+    pub status: JobTxStatus,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// A recipient of a distribution job, paid by the job transaction `tx_hash`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JobRecipient {
+    pub address: String,
+    // Base units of the asset, a string since JSON numbers can not hold u128
+    pub amount: String,
+    #[serde(default)]
+    pub tx_hash: Option<H256>,
+}
+
+/// A multi-transaction operation saved in `<index-dir>/jobs/<job-id>.json`,
+/// every transaction is recorded before it is sent so an interrupted run can
+/// be resumed without double-sending.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BatchJob {
+    pub id: String,
+    pub kind: String,
+    pub txs: Vec<JobTx>,
+    // Only for distribution jobs (udt-airdrop)
+    #[serde(default)]
+    pub recipients: Vec<JobRecipient>,
+    // Type script hash of the distributed sUDT
+    #[serde(default)]
+    pub type_hash: Option<H256>,
+}
+
+impl BatchJob {
+    pub fn new(kind: &str) -> BatchJob {
+        let suffix: u32 = rand::thread_rng().gen();
+        BatchJob {
+            id: format!("{}-{:08x}", kind, suffix),
+            kind: kind.to_owned(),
+            txs: Vec::new(),
+            recipients: Vec::new(),
+            type_hash: None,
+        }
+    }
+
+    fn path(index_dir: &Path, id: &str) -> PathBuf {
+        index_dir.join("jobs").join(format!("{}.json", id))
+    }
+
+    pub fn load(index_dir: &Path, id: &str) -> Result<BatchJob, String> {
+        let path = Self::path(index_dir, id);
+        if !path.exists() {
+            return Err(format!("Job not found: {}", id));
+        }
+        let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+        serde_json::from_str(&content).map_err(|err| format!("Invalid job file: {}", err))
+    }
+
+    pub fn save(&self, index_dir: &Path) -> Result<(), String> {
+        let path = Self::path(index_dir, &self.id);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(&path, content).map_err(|err| err.to_string())
+    }
+
+    /// Mark transactions known by the node as sent and the others as
+    /// dropped, called when resuming.
+    pub fn refresh(&mut self, rpc_client: &mut HttpRpcClient) -> Result<(), String> {
+        for tx in self
+            .txs
+            .iter_mut()
+            .filter(|tx| tx.status == JobTxStatus::Signed || tx.status == JobTxStatus::Sent)
+        {
+            tx.status = match tx_status(rpc_client, &tx.tx_hash)? {
+                Some(_) => JobTxStatus::Sent,
+                None => JobTxStatus::Dropped,
+            };
+        }
+        Ok(())
+    }
+
+    /// Out points consumed by signed or sent transactions, must not be used
+    /// as inputs again
+    pub fn spent_out_points(&self) -> HashSet<(H256, u32)> {
+        self.txs
+            .iter()
+            .filter(|tx| tx.status == JobTxStatus::Signed || tx.status == JobTxStatus::Sent)
+            .flat_map(|tx| tx.inputs.iter().cloned())
+            .collect()
+    }
+
+    /// Record the transaction before sending it, returns its position
+    pub fn add_signed(&mut self, transaction: &TransactionView, capacity: u64) -> usize {
+        let inputs = transaction
+            .inputs()
+            .into_iter()
+            .map(|input| {
+                let out_point = input.previous_output();
+                (out_point.tx_hash().unpack(), out_point.index().unpack())
+            })
+            .collect();
+        self.txs.push(JobTx {
+            tx_hash: transaction.hash().unpack(),
+            inputs,
+            capacity,
+            status: JobTxStatus::Signed,
+            error: None,
+        });
+        self.txs.len() - 1
+    }
+
+    /// Positions of recipients not paid yet: no transaction, or their
+    /// transaction failed or dropped
+    pub fn unpaid_recipients(&self) -> Vec<usize> {
+        let paid: HashSet<&H256> = self
+            .txs
+            .iter()
+            .filter(|tx| tx.status == JobTxStatus::Signed || tx.status == JobTxStatus::Sent)
+            .map(|tx| &tx.tx_hash)
+            .collect();
+        self.recipients
+            .iter()
+            .enumerate()
+            .filter(|(_, recipient)| match recipient.tx_hash {
+                Some(ref tx_hash) => !paid.contains(tx_hash),
+                None => true,
+            })
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    pub fn set_recipients_tx(&mut self, positions: &[usize], tx_hash: &H256) {
+        for position in positions {
+            self.recipients[*position].tx_hash = Some(tx_hash.clone());
+        }
+    }
+
+    pub fn set_result(&mut self, position: usize, result: Result<(), String>) {
+        let tx = &mut self.txs[position];
+        match result {
+            Ok(()) => tx.status = JobTxStatus::Sent,
+            Err(err) => {
+                tx.status = JobTxStatus::Failed;
+                tx.error = Some(err);
+            }
+        }
+    }
+
+    /// What landed on chain: status of every transaction of the job
+    pub fn reconcile(&self, rpc_client: &mut HttpRpcClient) -> Result<serde_json::Value, String> {
+        let mut committed = 0;
+        let mut txs = Vec::new();
+        for tx in &self.txs {
+            let chain_status = match tx.status {
+                JobTxStatus::Signed | JobTxStatus::Sent => {
+                    match tx_status(rpc_client, &tx.tx_hash)? {
+                        Some(Status::Committed) => "committed",
+                        Some(Status::Proposed) => "proposed",
+                        Some(Status::Pending) => "pending",
+                        None => "unknown",
+                    }
+                }
+                _ => "unknown",
+            };
+            if chain_status == "committed" {
+                committed += 1;
+            }
+            txs.push(serde_json::json!({
+                "tx_hash": tx.tx_hash,
+                "inputs": tx.inputs.len(),
+                "capacity": tx.capacity,
+                "status": tx.status,
+                "chain_status": chain_status,
+                "error": tx.error,
+            }));
+        }
+        let mut resp = serde_json::json!({
+            "job_id": self.id,
+            "kind": self.kind,
+            "committed": committed,
+            "total": self.txs.len(),
+            "transactions": txs,
+        });
+        if !self.recipients.is_empty() {
+            resp["recipients"] = serde_json::json!({
+                "total": self.recipients.len(),
+                "unpaid": self.unpaid_recipients().len(),
+            });
+        }
+        Ok(resp)
+    }
+}
+
+fn tx_status(rpc_client: &mut HttpRpcClient, tx_hash: &H256) -> Result<Option<Status>, String> {
+    rpc_client
+        .get_transaction(tx_hash.clone())
+        .call()
+        .map(|resp| resp.0.map(|tx_with_status| tx_with_status.tx_status.status))
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{
+        core::TransactionBuilder,
+        packed::{CellInput, OutPoint},
+    };
+
+    #[test]
+    fn test_batch_job() {
+        let mut job = BatchJob::new("withdraw-all-dao");
+        assert!(job.id.starts_with("withdraw-all-dao-"));
+        let tx_hash = H256::from([1u8; 32]);
+        let build_tx = |index: u32| {
+            TransactionBuilder::default()
+                .input(CellInput::new(OutPoint::new(tx_hash.pack(), index), 0))
+                .build()
+        };
+        let first = job.add_signed(&build_tx(0), 100);
+        let second = job.add_signed(&build_tx(1), 200);
+        let third = job.add_signed(&build_tx(2), 300);
+        job.set_result(first, Ok(()));
+        job.set_result(second, Err("rejected".to_owned()));
+        job.txs[third].status = JobTxStatus::Dropped;
+
+        let spent = job.spent_out_points();
+        assert_eq!(spent.len(), 1);
+        assert!(spent.contains(&(tx_hash.clone(), 0)));
+        assert_eq!(job.txs[second].error, Some("rejected".to_owned()));
+    }
+
+    #[test]
+    fn test_unpaid_recipients() {
+        let mut job = BatchJob::new("udt-airdrop");
+        job.recipients = (0..4)
+            .map(|index| JobRecipient {
+                address: format!("ckt1recipient{}", index),
+                amount: "100".to_owned(),
+                tx_hash: None,
+            })
+            .collect();
+        let build_tx = |index: u32| {
+            TransactionBuilder::default()
+                .input(CellInput::new(
+                    OutPoint::new(H256::default().pack(), index),
+                    0,
+                ))
+                .build()
+        };
+        let (first_tx, second_tx) = (build_tx(0), build_tx(1));
+        let first = job.add_signed(&first_tx, 100);
+        job.set_recipients_tx(&[0, 1], &first_tx.hash().unpack());
+        let second = job.add_signed(&second_tx, 100);
+        job.set_recipients_tx(&[2], &second_tx.hash().unpack());
+        assert_eq!(job.unpaid_recipients(), vec![3]);
+
+        job.set_result(first, Ok(()));
+        job.set_result(second, Err("rejected".to_owned()));
+        assert_eq!(job.unpaid_recipients(), vec![2, 3]);
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ckb_types::H256;
+use serde_derive::{Deserialize, Serialize};
+
+/// Local note attached to a transaction, never sent to the chain
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TxMemo {
+    pub memo: String,
+    // Unix timestamp in seconds
+    pub updated_at: u64,
+}
+
+/// Transaction memos saved in `<index-dir>/tx-memos.json`
+pub struct TxMemos {
+    path: PathBuf,
+    memos: BTreeMap<H256, TxMemo>,
+}
+
+impl TxMemos {
+    pub fn load(index_dir: &Path) -> Result<TxMemos, String> {
+        let path = index_dir.join("tx-memos.json");
+        let memos = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Invalid transaction memos file: {}", err))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(TxMemos { path, memos })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(&self.memos).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+
+    pub fn get(&self, tx_hash: &H256) -> Option<&TxMemo> {
+        self.memos.get(tx_hash)
+    }
+
+    /// Set memo of the transaction, empty memo removes it
+    pub fn set(&mut self, tx_hash: H256, memo: String) {
+        if memo.is_empty() {
+            self.memos.remove(&tx_hash);
+            return;
+        }
+        let updated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        self.memos.insert(tx_hash, TxMemo { memo, updated_at });
+    }
+
+    /// Case insensitive substring search, all memos if keyword is empty
+    pub fn search<'a>(&'a self, keyword: &str) -> Vec<(&'a H256, &'a TxMemo)> {
+        let keyword = keyword.to_lowercase();
+        self.memos
+            .iter()
+            .filter(|(_, memo)| memo.memo.to_lowercase().contains(&keyword))
+            .collect()
+    }
+}
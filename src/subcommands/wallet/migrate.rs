@@ -0,0 +1,406 @@
+use std::collections::BTreeMap;
+
+use ckb_hash::blake2b_256;
+use ckb_index::{with_index_db, IndexDatabase, LiveCellInfo};
+use ckb_jsonrpc_types::CellWithStatus;
+use ckb_sdk::{multisig_config, OmniAuth, TransferTransactionBuilder};
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, ScriptHashType},
+    packed::{self, CellInput, Script},
+    prelude::*,
+    H160, H256,
+};
+use clap::ArgMatches;
+
+use super::fee::estimate_tx_size;
+use super::job::BatchJob;
+use super::pending::{PendingTx, PendingTxStore};
+use super::{is_live_cell, sender_address, WalletSubCommand};
+use crate::utils::{
+    arg_parser::{
+        ArgParser, CapacityParser, FixedHashParser, FromStrParser, PrivkeyPathParser,
+        PrivkeyWrapper,
+    },
+    asset::{format_amount, udt_amount, AssetInfo, AssetRegistry},
+    i18n::tr,
+    other::{get_network_type, read_password},
+    printer::Printable,
+};
+
+/// Split cells to migrate into transactions of at most `max_inputs` inputs.
+/// Plain cells are spread over all transactions first since every
+/// transaction pays its fee from plain capacity, then the typed cells fill
+/// the rest.
+pub fn plan_batches<T>(
+    plain: Vec<T>,
+    typed: Vec<T>,
+    max_inputs: usize,
+) -> Result<Vec<Vec<T>>, String> {
+    if max_inputs == 0 {
+        return Err("<max-inputs> can not be zero".to_owned());
+    }
+    let total = plain.len() + typed.len();
+    let count = (total + max_inputs - 1) / max_inputs;
+    if plain.len() < count {
+        return Err(format!(
+            "{} transactions are needed but only {} plain cells to pay fees, transfer some CKB to the account first",
+            count,
+            plain.len()
+        ));
+    }
+    let mut batches: Vec<Vec<T>> = (0..count).map(|_| Vec::new()).collect();
+    for (index, cell) in plain.into_iter().enumerate() {
+        batches[index % count].push(cell);
+    }
+    let mut position = 0;
+    for cell in typed {
+        while batches[position].len() >= max_inputs {
+            position += 1;
+        }
+        batches[position].push(cell);
+    }
+    Ok(batches)
+}
+
+impl<'a> WalletSubCommand<'a> {
+    // Move plain and registered sUDT cells of the account to another lock.
+    // sUDT cells keep their type and data, plain cells are merged into one
+    // output per transaction which also pays the fee.
+    pub fn migrate_lock(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account = self.from_account(m, from_privkey.is_some())?;
+        let from_address = sender_address(from_privkey.as_ref(), from_account.as_ref())?;
+        let max_inputs: usize = FromStrParser::<usize>::default().from_matches(m, "max-inputs")?;
+        let tx_fee_opt: Option<u64> = CapacityParser.from_matches_opt(m, "tx-fee", false)?;
+        let fee_rate = match m.value_of("fee") {
+            Some(preset) => {
+                let fee_rate = self.resolve_fee_rate(preset)?;
+                eprintln!("[INFO]: fee {} => {} shannons/KB", preset, fee_rate);
+                fee_rate
+            }
+            None => 0,
+        };
+        let network_type = get_network_type(self.rpc_client)?;
+        let genesis_info = self.genesis_info()?;
+        let from_lock = from_address.lock_script(genesis_info.secp_type_hash().clone());
+        let from_lock_hash: H256 = from_lock.calc_script_hash().unpack();
+        let target_lock = match m.value_of("to") {
+            Some("multisig") => {
+                let threshold: u8 =
+                    FromStrParser::<u8>::default().from_matches(m, "multisig-threshold")?;
+                let require_first_n: u8 =
+                    FromStrParser::<u8>::default().from_matches(m, "multisig-require-first-n")?;
+                let pubkey_hashes: Vec<H160> = FixedHashParser::<H160>::default()
+                    .from_matches_vec(m, "multisig-pubkey-hash")?;
+                let config = multisig_config(require_first_n, threshold, &pubkey_hashes)?;
+                let code_hash = genesis_info
+                    .multisig_type_hash()
+                    .cloned()
+                    .ok_or_else(|| "Multisig script not found in genesis block".to_owned())?;
+                Script::new_builder()
+                    .code_hash(code_hash)
+                    .hash_type(ScriptHashType::Type.into())
+                    .args(Bytes::from(blake2b_256(&config)[0..20].to_vec()).pack())
+                    .build()
+            }
+            _ => OmniAuth::Secp256k1(from_address.hash().clone())
+                .script(network_type)
+                .ok_or_else(|| format!("Omnilock is not deployed on {}", network_type))?,
+        };
+
+        let mut job = match m.value_of("resume") {
+            Some(job_id) => {
+                let mut job = BatchJob::load(&self.index_dir, job_id)?;
+                if job.kind != "migrate-lock" {
+                    return Err(format!("Job {} is not a migrate-lock job", job_id));
+                }
+                job.refresh(&mut self.rpc_client)?;
+                job
+            }
+            None => BatchJob::new("migrate-lock"),
+        };
+        let mut excluded = job.spent_out_points();
+
+        // For check index database is ready
+        self.with_db(|_| ())?;
+        let index_dir = self.index_dir.clone();
+        let min_confirmations = self.min_confirmations;
+        let genesis_hash = genesis_info.header().hash();
+        let mut pending_txs = PendingTxStore::load(&index_dir, &genesis_hash.unpack())?;
+        pending_txs.refresh(self.rpc_client)?;
+        pending_txs.save()?;
+        excluded.extend(pending_txs.spent_out_points());
+        let genesis_info_clone = genesis_info.clone();
+        let infos: Vec<LiveCellInfo> =
+            with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
+                let mut db =
+                    IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
+                db.set_min_confirmations(min_confirmations);
+                Ok(
+                    db.get_live_cells_by_lock(from_lock.calc_script_hash(), None, |_, _| {
+                        (false, true)
+                    }),
+                )
+            })
+            .map_err(|_err| {
+                format!(
+                    "{}: {}",
+                    tr("index database may not ready, sync process"),
+                    self.index_controller.state().read().to_string()
+                )
+            })?;
+
+        // (input, capacity, sUDT output with the new lock)
+        type MigrateCell = (
+            CellInput,
+            u64,
+            Option<(packed::CellOutput, Bytes, AssetInfo)>,
+        );
+        let registry = AssetRegistry::load(&index_dir)?;
+        let mut plain: Vec<MigrateCell> = Vec::new();
+        let mut typed: Vec<MigrateCell> = Vec::new();
+        let mut skipped = Vec::new();
+        for info in infos {
+            if excluded.contains(&(info.tx_hash.clone(), info.tx_index)) {
+                continue;
+            }
+            let asset = match info.type_hashes {
+                None if info.data_bytes == 0 => None,
+                Some((_, ref type_hash)) => match registry.find_by_type_hash(type_hash) {
+                    Some(asset) => Some(asset.clone()),
+                    None => {
+                        skipped.push(serde_json::json!({
+                            "out_point": format!("{:#x}-{}", info.tx_hash, info.tx_index),
+                            "reason": "type script is not a registered sUDT",
+                        }));
+                        continue;
+                    }
+                },
+                None => {
+                    skipped.push(serde_json::json!({
+                        "out_point": format!("{:#x}-{}", info.tx_hash, info.tx_index),
+                        "reason": "cell has data",
+                    }));
+                    continue;
+                }
+            };
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(info.out_point().into(), asset.is_some())
+                .call()
+                .map_err(|err| err.to_string())?;
+            if !is_live_cell(&resp) {
+                continue;
+            }
+            match asset {
+                None => plain.push((info.input(), info.capacity, None)),
+                Some(asset) => {
+                    let cell = resp.cell.unwrap();
+                    let data = cell
+                        .data
+                        .map(|data| data.content.into_bytes())
+                        .unwrap_or_default();
+                    udt_amount(&data)?;
+                    let output: packed::CellOutput = cell.output.into();
+                    let output = output.as_builder().lock(target_lock.clone()).build();
+                    // The new lock may have longer args
+                    let occupied = output
+                        .occupied_capacity(
+                            Capacity::bytes(data.len()).map_err(|err| err.to_string())?,
+                        )
+                        .map_err(|err| err.to_string())?
+                        .as_u64();
+                    let output = output
+                        .as_builder()
+                        .capacity(Capacity::shannons(std::cmp::max(info.capacity, occupied)).pack())
+                        .build();
+                    typed.push((info.input(), info.capacity, Some((output, data, asset))));
+                }
+            }
+        }
+        let batches = plan_batches(plain, typed, max_inputs)?;
+
+        // Build the plan: outputs and fee of every transaction
+        let plain_occupied = packed::CellOutput::new_builder()
+            .lock(target_lock.clone())
+            .build()
+            .occupied_capacity(Capacity::zero())
+            .map_err(|err| err.to_string())?
+            .as_u64();
+        let mut plans = Vec::new();
+        for batch in &batches {
+            let inputs_capacity = batch.iter().map(|(_, capacity, _)| capacity).sum::<u64>();
+            let mut outputs = batch
+                .iter()
+                .filter_map(|(_, _, udt)| udt.as_ref())
+                .map(|(output, data, _)| (output.clone(), data.clone()))
+                .collect::<Vec<_>>();
+            let udt_capacity = outputs
+                .iter()
+                .map(|(output, _)| {
+                    let capacity: Capacity = output.capacity().unpack();
+                    capacity.as_u64()
+                })
+                .sum::<u64>();
+            outputs.push((
+                packed::CellOutput::new_builder()
+                    .lock(target_lock.clone())
+                    .build(),
+                Bytes::new(),
+            ));
+            let tx_fee = tx_fee_opt.unwrap_or_else(|| {
+                let size = estimate_tx_size(batch.len(), &outputs) as u64;
+                (fee_rate * size + 999) / 1000
+            });
+            let plain_capacity = inputs_capacity
+                .checked_sub(udt_capacity + tx_fee)
+                .filter(|capacity| *capacity >= plain_occupied)
+                .ok_or_else(|| {
+                    format!(
+                        "Plain capacity is not enough to pay fee {} and hold a cell of the new lock, transfer some CKB to the account first",
+                        tx_fee
+                    )
+                })?;
+            let last = outputs.len() - 1;
+            outputs[last].0 = outputs[last]
+                .0
+                .clone()
+                .as_builder()
+                .capacity(Capacity::shannons(plain_capacity).pack())
+                .build();
+            let mut cell_deps: Vec<packed::CellDep> = Vec::new();
+            for (_, _, udt) in batch {
+                if let Some((_, _, asset)) = udt {
+                    let cell_dep = asset.cell_dep();
+                    if cell_deps
+                        .iter()
+                        .all(|dep| dep.as_slice() != cell_dep.as_slice())
+                    {
+                        cell_deps.push(cell_dep);
+                    }
+                }
+            }
+            plans.push((inputs_capacity, outputs, tx_fee, cell_deps));
+        }
+        let total_fee = plans.iter().map(|(_, _, fee, _)| fee).sum::<u64>();
+        // symbol => (amount, decimals)
+        let mut assets: BTreeMap<String, (u128, u8)> = BTreeMap::new();
+        for (_, _, udt) in batches.iter().flatten() {
+            if let Some((_, data, asset)) = udt {
+                let entry = assets
+                    .entry(asset.symbol.clone())
+                    .or_insert((0, asset.decimals));
+                entry.0 += udt_amount(data)?;
+            }
+        }
+        let to_lock_hash: H256 = target_lock.calc_script_hash().unpack();
+        let mut resp = serde_json::json!({
+            "from": from_address.to_string(network_type),
+            "to_lock": ckb_jsonrpc_types::Script::from(target_lock.clone()),
+            "to_lock_hash": to_lock_hash,
+            "transactions": plans.len(),
+            "cells": batches.iter().map(Vec::len).sum::<usize>(),
+            "capacity": plans.iter().map(|(capacity, _, _, _)| capacity).sum::<u64>(),
+            "total_fee": total_fee,
+            "assets": assets
+                .iter()
+                .map(|(symbol, (amount, decimals))| {
+                    (symbol.clone(), serde_json::json!(format_amount(*amount, *decimals)))
+                })
+                .collect::<serde_json::Map<_, _>>(),
+            "skipped": skipped,
+        });
+        if let Some(feed) = self.price_feed.as_ref() {
+            resp["fiat"] = feed.fiat_values(&[
+                ("capacity", resp["capacity"].as_u64().unwrap_or_default()),
+                ("total_fee", total_fee),
+            ]);
+        }
+        if !m.is_present("execute") || plans.is_empty() {
+            return Ok(Box::new(resp));
+        }
+
+        eprintln!("[job]: {} (resume with --resume {})", job.id, job.id);
+        let password = if m.is_present("with-password") {
+            Some(read_password(false, None)?)
+        } else {
+            None
+        };
+        let no_data = Bytes::new();
+        let transactions = self.sign_transactions(
+            plans.len(),
+            from_privkey.as_ref(),
+            from_account.as_ref(),
+            &password,
+            |index, build_witness| {
+                let (inputs_capacity, outputs, tx_fee, cell_deps) = &plans[index];
+                let inputs = batches[index]
+                    .iter()
+                    .map(|(input, _, _)| input.clone())
+                    .collect::<Vec<_>>();
+                let mut tx_args = TransferTransactionBuilder::new(
+                    &from_address,
+                    *inputs_capacity,
+                    &no_data,
+                    // Not used, replaced by the outputs
+                    &from_address,
+                    inputs_capacity - tx_fee,
+                    *tx_fee,
+                    inputs,
+                );
+                tx_args.set_outputs(outputs.clone());
+                tx_args.add_cell_deps(cell_deps.clone());
+                tx_args.transfer(&genesis_info, |args| build_witness(args))
+            },
+        )?;
+        let total = transactions.len();
+        for (index, (transaction, (inputs_capacity, _, tx_fee, _))) in
+            transactions.into_iter().zip(plans.iter()).enumerate()
+        {
+            let outputs_capacity = inputs_capacity - tx_fee;
+            // Record before sending, so a resumed run never spends the inputs again
+            let position = job.add_signed(&transaction, outputs_capacity);
+            job.save(&self.index_dir)?;
+            let result = self
+                .rpc_client
+                .send_transaction(transaction.data().into())
+                .call()
+                .map_err(|err| format!("{}: {}", tr("Send transaction error"), err));
+            job.set_result(position, result.as_ref().map(|_| ()).map_err(Clone::clone));
+            job.save(&self.index_dir)?;
+            let tx_hash =
+                result.map_err(|err| format!("{} (resume with --resume {})", err, job.id))?;
+            pending_txs.add(PendingTx::new(&transaction, from_lock_hash.clone()));
+            pending_txs.save()?;
+            eprintln!("[{}/{}] sent {:#x}", index + 1, total, tx_hash);
+        }
+        resp["job"] = job.reconcile(&mut self.rpc_client)?;
+        Ok(Box::new(resp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_batches() {
+        let batches = plan_batches(vec![1, 2, 3], vec![10, 11, 12, 13], 3).unwrap();
+        assert_eq!(batches, vec![vec![1, 10, 11], vec![2, 12, 13], vec![3]]);
+        assert!(batches
+            .iter()
+            .all(|batch| batch.iter().any(|cell| *cell < 10)));
+
+        let batches = plan_batches(vec![1, 2], Vec::new(), 10).unwrap();
+        assert_eq!(batches, vec![vec![1, 2]]);
+        assert!(plan_batches::<u32>(Vec::new(), Vec::new(), 10)
+            .unwrap()
+            .is_empty());
+
+        // 2 transactions, only 1 plain cell
+        assert!(plan_batches(vec![1], vec![10, 11, 12], 2).is_err());
+        assert!(plan_batches(vec![1], Vec::new(), 0).is_err());
+    }
+}
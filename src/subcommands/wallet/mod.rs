@@ -1,43 +1,87 @@
+mod airdrop;
+mod balance;
+mod broadcast;
+mod dao;
+mod derived;
+mod faucet;
+mod fee;
+mod hooks;
+mod idempotency;
 mod index;
+mod indexer;
+mod job;
+mod memos;
+mod migrate;
+mod pending;
+mod rebase;
+mod rent;
+mod reshape;
+mod sweep;
+mod udt;
 
 use std::fs;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use ckb_hash::blake2b_256;
 use ckb_jsonrpc_types::{BlockNumber, CellWithStatus, HeaderView, TransactionWithStatus};
 use ckb_types::{
     bytes::Bytes,
-    core::{BlockView, TransactionView},
-    packed::{Byte32, CellInput, Script},
+    core::{BlockView, Capacity, ScriptHashType, TransactionView},
+    packed::{self, Byte32, CellInput, Script},
     prelude::*,
     H160, H256,
 };
-use clap::{App, ArgMatches, SubCommand};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use faster_hex::hex_string;
 
 use super::CliSubCommand;
 use crate::utils::{
+    address_book::AddressBook,
     arg,
     arg_parser::{
-        AddressParser, ArgParser, CapacityParser, FixedHashParser, FromStrParser, HexParser,
-        PrivkeyPathParser, PrivkeyWrapper,
+        AddressParser, AmountParser, ArgParser, CapacityParser, DurationParser, FilePathParser,
+        FixedHashParser, FromStrParser, HexParser, OutPointParser, PrivkeyPathParser,
+        PrivkeyWrapper, UrlParser,
     },
-    other::{check_address_prefix, get_address, get_network_type, read_password},
-    printer::{OutputFormat, Printable},
+    asset::{Asset, AssetRegistry},
+    cell_filter::CellFilter,
+    i18n::{tr, tr_fmt},
+    key_usage::record_key_usage,
+    lock_template::all_lock_templates,
+    other::{
+        check_address_prefix, confirm_input, dir_size, get_address, get_block_number_by_time,
+        get_header_deps, get_lock_hash_accounts, get_network_type, read_password, resolve_account,
+    },
+    output_spec::parse_outputs,
+    payment_request::PaymentRequest,
+    price::PriceFeed,
+    printer::{OutputFormat, Printable, Text},
 };
-use ckb_index::{with_index_db, IndexDatabase, LiveCellInfo};
+use ckb_index::{with_index_db, IndexDatabase, LiveCellInfo, WatchList};
 use ckb_sdk::{
-    blake2b_args, build_witness_with_key, serialize_signature,
-    wallet::{KeyStore, KeyStoreError},
-    Address, GenesisInfo, HttpRpcClient, TransferTransactionBuilder, MIN_SECP_CELL_CAPACITY,
-    ONE_CKB, SECP256K1,
+    blake2b_args, build_witness_with_key, serialize_signature, sign_in_parallel,
+    wallet::{DerivationPath, KeyStore, KeyStoreError},
+    Address, GenesisInfo, HttpRpcClient, SignProgress, TransferTransactionBuilder,
+    MIN_SECP_CELL_CAPACITY, ONE_CKB, SECP256K1,
 };
+use fee::estimate_tx_size;
+use hooks::Hooks;
+use idempotency::IdempotencyState;
 pub use index::{
     start_index_thread, CapacityResult, IndexController, IndexRequest, IndexResponse,
     IndexThreadState, SimpleBlockInfo,
 };
-use std::collections::HashSet;
+use job::BatchJob;
+use memos::TxMemos;
+use pending::{PendingTx, PendingTxStore};
+use std::collections::{BTreeMap, HashSet};
+
+const DEFAULT_FAUCET_URL: &str = "https://faucet.nervos.org";
+// Withdraw header is DAO_MATURITY blocks before tip (see: build_dao_withdraw_hash)
+const DAO_MATURITY: u64 = 10;
+const FEE_PRESETS: [&str; 3] = ["slow", "normal", "fast"];
 
 pub struct WalletSubCommand<'a> {
     rpc_client: &'a mut HttpRpcClient,
@@ -45,17 +89,42 @@ pub struct WalletSubCommand<'a> {
     genesis_info: Option<GenesisInfo>,
     index_dir: PathBuf,
     index_controller: IndexController,
+    default_account: Option<H160>,
+    broadcast_urls: Vec<String>,
+    // Fee rates (shannons/KB) of fee presets set by user
+    fee_rates: BTreeMap<String, u64>,
+    // Max fee (shannons) of a transaction to send
+    max_tx_fee: u64,
+    // Live cells with less confirmations are ignored by index queries, and
+    // pending changes are not chained
+    min_confirmations: u64,
+    // Fiat values in previews, None when not configured or offline
+    price_feed: Option<PriceFeed>,
+    lock_templates_file: PathBuf,
     interactive: bool,
+    // Format of the transaction printed by --debug
+    output_format: OutputFormat,
+    color: bool,
 }
 
 impl<'a> WalletSubCommand<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         rpc_client: &'a mut HttpRpcClient,
         key_store: &'a mut KeyStore,
         genesis_info: Option<GenesisInfo>,
         index_dir: PathBuf,
         index_controller: IndexController,
+        default_account: Option<H160>,
+        broadcast_urls: Vec<String>,
+        fee_rates: BTreeMap<String, u64>,
+        max_tx_fee: u64,
+        min_confirmations: u64,
+        price_feed: Option<PriceFeed>,
+        lock_templates_file: PathBuf,
         interactive: bool,
+        output_format: OutputFormat,
+        color: bool,
     ) -> WalletSubCommand<'a> {
         WalletSubCommand {
             rpc_client,
@@ -63,7 +132,16 @@ impl<'a> WalletSubCommand<'a> {
             genesis_info,
             index_dir,
             index_controller,
+            default_account,
+            broadcast_urls,
+            fee_rates,
+            max_tx_fee,
+            min_confirmations,
+            price_feed,
+            lock_templates_file,
             interactive,
+            output_format,
+            color,
         }
     }
 
@@ -93,64 +171,529 @@ impl<'a> WalletSubCommand<'a> {
         let network_type = get_network_type(self.rpc_client)?;
         let genesis_info = self.genesis_info()?;
         let genesis_hash: H256 = genesis_info.header().hash().unpack();
+        let min_confirmations = self.min_confirmations;
         with_index_db(&self.index_dir, genesis_hash, |backend, cf| {
-            let db = IndexDatabase::from_db(backend, cf, network_type, genesis_info, false)?;
+            let mut db = IndexDatabase::from_db(backend, cf, network_type, genesis_info, false)?;
+            db.set_min_confirmations(min_confirmations);
             Ok(func(db))
         })
         .map_err(|_err| {
             format!(
-                "index database may not ready, sync process: {}",
+                "{}: {}",
+                tr("index database may not ready, sync process"),
                 self.index_controller.state().read().to_string()
             )
         })
     }
 
+    // Lock hash to query by <lock-hash>, <address>, <pubkey> or <lock-arg>, or
+    // the default lock of <from-account> (default account if none is given)
+    fn query_lock_hash(&mut self, m: &ArgMatches) -> Result<Byte32, String> {
+        if let Some(lock_hash) =
+            FixedHashParser::<H256>::default().from_matches_opt(m, "lock-hash", false)?
+        {
+            return Ok(lock_hash.pack());
+        }
+        let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+        let address = if ["address", "pubkey", "lock-arg"]
+            .iter()
+            .any(|name| m.is_present(name))
+        {
+            get_address(m)?
+        } else {
+            let lock_arg = self
+                .from_account(m, false)?
+                .expect("from_account always resolves without privkey");
+            Address::new_default(lock_arg)
+        };
+        Ok(address.lock_script(secp_type_hash).calc_script_hash())
+    }
+
+    // Resolve the account to transfer from (ignored when privkey-path is given)
+    fn from_account(&mut self, m: &ArgMatches, has_privkey: bool) -> Result<Option<H160>, String> {
+        if has_privkey {
+            return Ok(None);
+        }
+        match m.value_of("from-account") {
+            Some(input) => resolve_account(self.key_store, input).map(Some),
+            None => self.default_account.clone().map(Some).ok_or_else(|| {
+                tr("<privkey-path> or <from-account> is required (or set default account by `config --default-account`)").to_owned()
+            }),
+        }
+    }
+
     pub fn subcommand() -> App<'static, 'static> {
         SubCommand::with_name("wallet")
             .about("Transfer / query balance (with local index) / key utils")
             .subcommands(vec![
                 SubCommand::with_name("transfer")
                     .about("Transfer capacity to an address (can have data)")
-                    .arg(arg::privkey_path().required_unless(arg::from_account().b.name))
-                    .arg(arg::from_account().required_unless(arg::privkey_path().b.name))
-                    .arg(arg::to_address().required(true))
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
+                    .arg(arg::to_address().required_unless_one(&["outputs-file", "payment-request"]))
                     .arg(arg::to_data())
                     .arg(arg::to_data_path())
+                    .arg(arg::capacity().required_unless_one(&["outputs-file", "amount", "payment-request"]))
+                    .arg(
+                        Arg::with_name("asset")
+                            .long("asset")
+                            .takes_value(true)
+                            .default_value("ckb")
+                            .help("Asset to transfer: ckb, a registered symbol or sUDT type script hash (see: asset add)"),
+                    )
+                    .arg(
+                        Arg::with_name("amount")
+                            .long("amount")
+                            .takes_value(true)
+                            .conflicts_with_all(&["outputs-file", "to-data", "to-data-path"])
+                            .help("sUDT amount (format: 123.45, scaled by decimals of the asset, more decimal places are rejected), <capacity> is the capacity of the sUDT cell (default: the minimal)"),
+                    )
+                    .arg(
+                        Arg::with_name("outputs-file")
+                            .long("outputs-file")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .conflicts_with_all(&["to-address", "to-data", "to-data-path", "capacity"])
+                            .help("Send to many outputs in one transaction, JSON array of {address|lock, capacity, type?, data?} or CSV lines of address,capacity[,data] (file ends with .csv)"),
+                    )
+                    .arg(
+                        Arg::with_name("payment-request")
+                            .long("payment-request")
+                            .takes_value(true)
+                            .validator(|input| input.parse::<PaymentRequest>().map(|_| ()))
+                            .conflicts_with_all(&["to-address", "to-data", "to-data-path", "capacity", "amount", "outputs-file"])
+                            .help("Pay a payment request URI: ckb:<address>?amount=<amount>&asset=<asset>&memo=<memo> (see: account qr)"),
+                    )
+                    .arg(arg::tx_fee().required_unless("fee"))
+                    .arg(
+                        Arg::with_name("fee")
+                            .long("fee")
+                            .takes_value(true)
+                            .possible_values(&FEE_PRESETS)
+                            .conflicts_with("tx-fee")
+                            .help("Fee preset, rate derived from recent blocks (override by `config --fee-rates`)"),
+                    )
+                    .arg(arg::with_password())
+                    .arg(arg::hd_change().conflicts_with("amount"))
+                    .arg(
+                        Arg::with_name("from-path")
+                            .long("from-path")
+                            .takes_value(true)
+                            .conflicts_with_all(&["privkey-path", "amount"])
+                            .validator(|input| {
+                                input
+                                    .parse::<DerivationPath>()
+                                    .map(|_| ())
+                                    .map_err(|err| err.to_string())
+                            })
+                            .help("Transfer from the address derived from <from-account> at this path (e.g. m/44'/309'/0'/1/0, see: wallet scan-accounts), password is required"),
+                    )
+                    .arg(arg::broadcast_all())
+                    .arg(
+                        Arg::with_name("no-chain-pending")
+                            .long("no-chain-pending")
+                            .help("Do not spend change outputs of our own pending transactions (wait them committed instead)"),
+                    )
+                    .arg(arg::memo())
+                    .arg(arg::idempotency_key())
+                    .arg(arg::header_dep())
+                    .arg(
+                        Arg::with_name("max-fee-ratio")
+                            .long("max-fee-ratio")
+                            .takes_value(true)
+                            .default_value("0.1")
+                            .validator(|input| FromStrParser::<f64>::default().validate(input))
+                            .help("Refuse if fee is more than this fraction of the transfer capacity"),
+                    )
+                    .arg(
+                        Arg::with_name("max-balance-ratio")
+                            .long("max-balance-ratio")
+                            .takes_value(true)
+                            .default_value("0.9")
+                            .validator(|input| FromStrParser::<f64>::default().validate(input))
+                            .help("Refuse if transfer capacity is more than this fraction of the account balance"),
+                    )
+                    .arg(
+                        Arg::with_name("force")
+                            .long("force")
+                            .help("Skip the safety checks (fee ratio, balance ratio, no change left)"),
+                    ),
+                SubCommand::with_name("burn")
+                    .about("Burn capacity: send it to a cell with an unspendable lock (code hash of all zeros), nobody can spend it again")
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
                     .arg(arg::capacity().required(true))
-                    .arg(arg::tx_fee().required(true))
-                    .arg(arg::with_password()),
+                    .arg(arg::tx_fee().required_unless("fee"))
+                    .arg(
+                        Arg::with_name("fee")
+                            .long("fee")
+                            .takes_value(true)
+                            .possible_values(&FEE_PRESETS)
+                            .conflicts_with("tx-fee")
+                            .help("Fee preset, rate derived from recent blocks (override by `config --fee-rates`)"),
+                    )
+                    .arg(
+                        Arg::with_name("confirm-burn")
+                            .long("confirm-burn")
+                            .required(true)
+                            .help("Confirm the capacity is destroyed forever, the capacity is also asked again before sending"),
+                    )
+                    .arg(arg::with_password())
+                    .arg(arg::broadcast_all())
+                    .arg(arg::memo())
+                    .arg(
+                        Arg::with_name("max-fee-ratio")
+                            .long("max-fee-ratio")
+                            .takes_value(true)
+                            .default_value("0.1")
+                            .validator(|input| FromStrParser::<f64>::default().validate(input))
+                            .help("Refuse if fee is more than this fraction of the burn capacity"),
+                    )
+                    .arg(
+                        Arg::with_name("max-balance-ratio")
+                            .long("max-balance-ratio")
+                            .takes_value(true)
+                            .default_value("0.9")
+                            .validator(|input| FromStrParser::<f64>::default().validate(input))
+                            .help("Refuse if burn capacity is more than this fraction of the account balance"),
+                    ),
+                SubCommand::with_name("rebroadcast-pending")
+                    .about("Send again pending transactions of this wallet missing in the node (lost after the node restarted), remove committed and dropped ones"),
                 SubCommand::with_name("deposit-dao")
                     .about("Deposit capacity into NervosDAO(can have data)")
-                    .arg(arg::privkey_path().required_unless(arg::from_account().b.name))
-                    .arg(arg::from_account().required_unless(arg::privkey_path().b.name))
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
                     .arg(arg::to_address())
                     .arg(arg::to_data())
                     .arg(arg::to_data_path())
                     .arg(arg::capacity().required(true))
                     .arg(arg::tx_fee().required(true))
-                    .arg(arg::with_password()),
+                    .arg(arg::with_password())
+                    .arg(arg::hd_change())
+                    .arg(arg::header_dep())
+                    .arg(arg::broadcast_all()),
                 SubCommand::with_name("withdraw-dao")
                     .about("Withdraw capacity from NervosDAO(can have data)")
-                    .arg(arg::privkey_path().required_unless(arg::from_account().b.name))
-                    .arg(arg::from_account().required_unless(arg::privkey_path().b.name))
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
                     .arg(arg::to_address())
                     .arg(arg::to_data())
                     .arg(arg::to_data_path())
                     .arg(arg::capacity().required(true))
                     .arg(arg::tx_fee().required(true))
+                    .arg(arg::with_password())
+                    .arg(arg::hd_change())
+                    .arg(arg::header_dep())
+                    .arg(arg::broadcast_all()),
+                SubCommand::with_name("withdraw-all-dao")
+                    .about("Withdraw all matured NervosDAO cells back to the account (one transaction per header deps limit), report immature ones")
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
+                    .arg(arg::tx_fee().required(true).help("Transaction fee of each transaction"))
+                    .arg(arg::with_password())
+                    .arg(arg::resume_job()),
+                SubCommand::with_name("udt-airdrop")
+                    .about("Distribute a sUDT to many recipients in multiple transactions, duplicated recipients are paid once and an interrupted run can be resumed")
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
+                    .arg(
+                        Arg::with_name("asset")
+                            .long("asset")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The sUDT: a registered symbol or type script hash (see: asset add)"),
+                    )
+                    .arg(
+                        Arg::with_name("recipients")
+                            .long("recipients")
+                            .takes_value(true)
+                            .required_unless("resume")
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("CSV file of address,amount lines (amount scaled by decimals of the asset)"),
+                    )
+                    .arg(
+                        Arg::with_name("batch-size")
+                            .long("batch-size")
+                            .takes_value(true)
+                            .default_value("100")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Recipients paid by one transaction"),
+                    )
+                    .arg(arg::tx_fee().required_unless("fee").help("Transaction fee of each transaction"))
+                    .arg(
+                        Arg::with_name("fee")
+                            .long("fee")
+                            .takes_value(true)
+                            .possible_values(&FEE_PRESETS)
+                            .conflicts_with("tx-fee")
+                            .help("Fee preset, rate derived from recent blocks (override by `config --fee-rates`)"),
+                    )
+                    .arg(arg::with_password())
+                    .arg(arg::resume_job().conflicts_with("recipients")),
+                SubCommand::with_name("migrate-lock")
+                    .about("Move all plain and registered sUDT cells of the account to the same key under omnilock, or to a multisig lock. Shows the plan and total fee, sends only with --execute")
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
+                    .arg(
+                        Arg::with_name("to")
+                            .long("to")
+                            .takes_value(true)
+                            .required(true)
+                            .possible_values(&["omnilock", "multisig"])
+                            .help("The new lock: omnilock (secp256k1 auth of the same key) or multisig (secp256k1_blake160_multisig_all)"),
+                    )
+                    .arg(
+                        Arg::with_name("multisig-threshold")
+                            .long("multisig-threshold")
+                            .takes_value(true)
+                            .required_if("to", "multisig")
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("Signatures required by the multisig lock"),
+                    )
+                    .arg(
+                        Arg::with_name("multisig-require-first-n")
+                            .long("multisig-require-first-n")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("The first n pubkeys must sign"),
+                    )
+                    .arg(
+                        Arg::with_name("multisig-pubkey-hash")
+                            .long("multisig-pubkey-hash")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .required_if("to", "multisig")
+                            .validator(|input| FixedHashParser::<H160>::default().validate(input))
+                            .help("Lock arg (blake160 of pubkey) of a multisig member, in order"),
+                    )
+                    .arg(
+                        Arg::with_name("max-inputs")
+                            .long("max-inputs")
+                            .takes_value(true)
+                            .default_value("100")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Max inputs of one transaction"),
+                    )
+                    .arg(arg::tx_fee().required_unless("fee").help("Transaction fee of each transaction"))
+                    .arg(
+                        Arg::with_name("fee")
+                            .long("fee")
+                            .takes_value(true)
+                            .possible_values(&FEE_PRESETS)
+                            .conflicts_with("tx-fee")
+                            .help("Fee preset, rate derived from recent blocks (override by `config --fee-rates`)"),
+                    )
+                    .arg(
+                        Arg::with_name("execute")
+                            .long("execute")
+                            .help("Sign and send the transactions of the plan"),
+                    )
+                    .arg(arg::with_password())
+                    .arg(arg::resume_job()),
+                SubCommand::with_name("split-cell")
+                    .about("Split a live plain cell of the account into N equal cells (the fee is paid from it). Shows the plan, sends only with --execute")
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
+                    .arg(
+                        Arg::with_name("out-point")
+                            .long("out-point")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| OutPointParser.validate(input))
+                            .help("The cell to split, format: {tx-hash}-{index}"),
+                    )
+                    .arg(
+                        Arg::with_name("count")
+                            .long("count")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Number of cells to create (at least 2)"),
+                    )
+                    .arg(arg::tx_fee().required(true))
+                    .arg(
+                        Arg::with_name("execute")
+                            .long("execute")
+                            .help("Sign and send the transaction"),
+                    )
+                    .arg(arg::with_password()),
+                SubCommand::with_name("merge-cells")
+                    .about("Merge live plain cells of the account into one cell (the fee is paid from it). Shows the plan, sends only with --execute")
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
+                    .arg(
+                        Arg::with_name("out-point")
+                            .long("out-point")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .required(true)
+                            .validator(|input| OutPointParser.validate(input))
+                            .help("A cell to merge (at least 2), format: {tx-hash}-{index}"),
+                    )
+                    .arg(arg::tx_fee().required(true))
+                    .arg(
+                        Arg::with_name("execute")
+                            .long("execute")
+                            .help("Sign and send the transaction"),
+                    )
                     .arg(arg::with_password()),
+                SubCommand::with_name("job-status")
+                    .about("Show what landed on chain of a multi-transaction job (see: --resume)")
+                    .arg(
+                        Arg::with_name("job-id")
+                            .long("job-id")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Job id printed by the job"),
+                    ),
+                SubCommand::with_name("rebase-tx")
+                    .about("Replace spent inputs of a stored (unsigned) transaction with live cells of the account, recompute change and clear witnesses, outputs and deps are kept")
+                    .arg(arg::from_account())
+                    .arg(
+                        Arg::with_name("tx-file")
+                            .long("tx-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Transaction json file (rewritten in place)"),
+                    )
+                    .arg(arg::tx_fee().required(true)),
+                SubCommand::with_name("presign-sweep")
+                    .about("Pre-sign a transaction sweeping all capacity to a recovery address, which can only be committed after unlock epoch/timestamp (saved encrypted)")
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
+                    .arg(
+                        Arg::with_name("recovery-address")
+                            .long("recovery-address")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| AddressParser.validate(input))
+                            .help("Address to receive all capacity"),
+                    )
+                    .arg(
+                        Arg::with_name("unlock-epoch")
+                            .long("unlock-epoch")
+                            .takes_value(true)
+                            .required_unless("unlock-timestamp")
+                            .conflicts_with("unlock-timestamp")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("The transaction can not be committed before this epoch number"),
+                    )
+                    .arg(
+                        Arg::with_name("unlock-timestamp")
+                            .long("unlock-timestamp")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("The transaction can not be committed before this unix timestamp (seconds, compared with median time of blocks)"),
+                    )
+                    .arg(arg::tx_fee().required(true))
+                    .arg(arg::with_password())
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output file of the encrypted transaction"),
+                    )
+                    .arg(arg::force()),
+                SubCommand::with_name("emergency-broadcast")
+                    .about("Decrypt and send a pre-signed sweep transaction (see: wallet presign-sweep)")
+                    .arg(
+                        Arg::with_name("sweep-path")
+                            .long("sweep-path")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("File saved by `wallet presign-sweep`"),
+                    )
+                    .arg(arg::broadcast_all()),
+                SubCommand::with_name("faucet-claim")
+                    .about("Claim testnet capacity from faucet and wait the granted cells")
+                    .arg(arg::address().required(true))
+                    .arg(
+                        Arg::with_name("faucet-url")
+                            .long("faucet-url")
+                            .takes_value(true)
+                            .default_value(DEFAULT_FAUCET_URL)
+                            .validator(|input| UrlParser.validate(input))
+                            .help("Testnet faucet endpoint"),
+                    )
+                    .arg(
+                        Arg::with_name("wait-timeout")
+                            .long("wait-timeout")
+                            .takes_value(true)
+                            .default_value("10m")
+                            .validator(|input| DurationParser.validate(input))
+                            .help("How long to wait the granted cells (0s for not wait)"),
+                    ),
                 SubCommand::with_name("get-capacity")
-                    .about("Get capacity by lock script hash or address or lock arg or pubkey")
+                    .about("Get capacity by lock script hash or address or lock arg or pubkey or account")
                     .arg(arg::lock_hash())
                     .arg(arg::address())
                     .arg(arg::pubkey())
-                    .arg(arg::lock_arg()),
+                    .arg(arg::lock_arg())
+                    .arg(
+                        arg::from_account()
+                            .conflicts_with_all(&["lock-hash", "address", "pubkey", "lock-arg"])
+                            .help("The account's lock-arg, address, label in address book or unique lock-arg prefix in keystore (default account will be used if no other argument given)"),
+                    )
+                    .arg(
+                        Arg::with_name("with-derived")
+                            .long("with-derived")
+                            .help("Include addresses derived from <lock-arg> or <from-account> account (see: wallet scan-accounts, --hd-change)"),
+                    )
+                    .arg(
+                        Arg::with_name("asset")
+                            .long("asset")
+                            .takes_value(true)
+                            .conflicts_with("with-derived")
+                            .help("Show balance of a sUDT asset: registered symbol or type script hash (see: asset add)"),
+                    ),
+                SubCommand::with_name("scan-accounts")
+                    .about("Discover used addresses derived from an account (BIP-44 receiving and change chains) in local index, and record them")
+                    .arg(arg::from_account())
+                    .arg(
+                        Arg::with_name("gap-limit")
+                            .long("gap-limit")
+                            .takes_value(true)
+                            .default_value("20")
+                            .validator(|input| FromStrParser::<u32>::default().validate(input))
+                            .help("Stop scanning a chain after this many consecutive unused addresses"),
+                    ),
                 SubCommand::with_name("get-dao-capacity")
-                    .about("Get NervosDAO deposited capacity by lock script hash or address or lock arg or pubkey")
+                    .about("Get NervosDAO deposited capacity by lock script hash or address or lock arg or pubkey or account")
+                    .arg(arg::lock_hash())
+                    .arg(arg::address())
+                    .arg(arg::pubkey())
+                    .arg(arg::lock_arg())
+                    .arg(
+                        arg::from_account()
+                            .conflicts_with_all(&["lock-hash", "address", "pubkey", "lock-arg"])
+                            .help("The account's lock-arg, address, label in address book or unique lock-arg prefix in keystore (default account will be used if no other argument given)"),
+                    ),
+                SubCommand::with_name("state-rent")
+                    .about("Report capacity locked by deployed cells (cells with data) of a lock, its NervosDAO opportunity cost, and cells not referenced as cell dep in recent blocks")
                     .arg(arg::lock_hash())
                     .arg(arg::address())
                     .arg(arg::pubkey())
-                    .arg(arg::lock_arg()),
+                    .arg(arg::lock_arg())
+                    .arg(
+                        arg::from_account()
+                            .conflicts_with_all(&["lock-hash", "address", "pubkey", "lock-arg"])
+                            .help("The account's lock-arg, address, label in address book or unique lock-arg prefix in keystore (default account will be used if no other argument given)"),
+                    )
+                    .arg(
+                        Arg::with_name("scan-blocks")
+                            .long("scan-blocks")
+                            .takes_value(true)
+                            .default_value("1000")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Number of recent blocks scanned for cell dep references, also used to estimate the NervosDAO rate"),
+                    ),
                 SubCommand::with_name("get-live-cells")
                     .about("Get live cells by lock/type/code  hash")
                     .arg(arg::lock_hash())
@@ -158,32 +701,278 @@ impl<'a> WalletSubCommand<'a> {
                     .arg(arg::code_hash())
                     .arg(arg::live_cells_limit())
                     .arg(arg::from_block_number())
-                    .arg(arg::to_block_number()),
+                    .arg(arg::to_block_number())
+                    .arg(arg::cell_filter()),
+                SubCommand::with_name("get-cells")
+                    .about("Get live cells by search key (same as ckb-indexer's get_cells, script args must match exactly)")
+                    .arg(
+                        Arg::with_name("search-key")
+                            .long("search-key")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Search key json, example: '{\"script\": {\"code_hash\": \"0x...\", \"hash_type\": \"type\", \"args\": \"0x...\"}, \"script_type\": \"lock\", \"filter\": {\"output_capacity_range\": [\"0x0\", \"0x174876e800\"]}}'"),
+                    )
+                    .arg(
+                        Arg::with_name("order")
+                            .long("order")
+                            .takes_value(true)
+                            .possible_values(&["asc", "desc"])
+                            .default_value("asc")
+                            .help("Order by block number"),
+                    )
+                    .arg(
+                        Arg::with_name("limit")
+                            .long("limit")
+                            .takes_value(true)
+                            .default_value("64")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Max number of cells in one page"),
+                    )
+                    .arg(
+                        Arg::with_name("after-cursor")
+                            .long("after-cursor")
+                            .takes_value(true)
+                            .validator(|input| HexParser.validate(input))
+                            .help("Return cells after this cursor (`last_cursor` of previous page)"),
+                    ),
                 // Move to index subcommand
                 SubCommand::with_name("get-lock-by-address")
                     .about("Get lock script (include hash) by address")
                     .arg(arg::address().required(true)),
                 // Move to index subcommand
                 SubCommand::with_name("db-metrics").about("Show index database metrics"),
+                SubCommand::with_name("db-prune")
+                    .about("Remove history data from index database and compact it, live cells are kept")
+                    .arg(
+                        Arg::with_name("keep-days")
+                            .long("keep-days")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .required_unless("live-cells-only")
+                            .help("Keep history of the last N days"),
+                    )
+                    .arg(
+                        Arg::with_name("live-cells-only")
+                            .long("live-cells-only")
+                            .conflicts_with("keep-days")
+                            .help("Remove all history, only keep live cells"),
+                    ),
+                SubCommand::with_name("db-watch-add")
+                    .about("Only index cells of watched lock scripts (all locks are indexed when the watch list is empty), the index syncs from genesis again after the watch list changed")
+                    .arg(arg::address().multiple(true).number_of_values(1).required_unless("lock-hash"))
+                    .arg(arg::lock_hash().multiple(true).number_of_values(1)),
+                SubCommand::with_name("db-watch-remove")
+                    .about("Remove lock scripts from the watch list, the index syncs from genesis again after the watch list changed")
+                    .arg(arg::address().multiple(true).number_of_values(1).required_unless("lock-hash"))
+                    .arg(arg::lock_hash().multiple(true).number_of_values(1)),
+                SubCommand::with_name("db-watch-list").about("List watched lock scripts of the index"),
+                SubCommand::with_name("balance-at")
+                    .about("Show capacity of all accounts at the end of a block, from the node's indexer history (see: rpc index_lock_hash)")
+                    .arg(
+                        Arg::with_name("block-number")
+                            .long("block-number")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Block number"),
+                    ),
+                SubCommand::with_name("balance-snapshot")
+                    .about("Snapshot capacity of all accounts for accounting, from the node's indexer history (see: rpc index_lock_hash)")
+                    .arg(
+                        Arg::with_name("block-number")
+                            .long("block-number")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Block number [default: tip block number]"),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .takes_value(true)
+                            .possible_values(&["json", "csv"])
+                            .default_value("json")
+                            .help("Output format, csv lines are: lock_arg,address,capacity(CKB)"),
+                    ),
                 SubCommand::with_name("top-capacity")
                     .about("Show top n capacity owned by lock script hash")
                     .arg(arg::top_n()),
+                SubCommand::with_name("add-hook")
+                    .about("Run a command or call a webhook when index thread sees an event (only new blocks)")
+                    .arg(
+                        Arg::with_name("event")
+                            .long("event")
+                            .takes_value(true)
+                            .required(true)
+                            .possible_values(&["receive", "spend", "commit"])
+                            .help("receive: address received capacity, spend: out point is spent, commit: transaction sent by this wallet is committed"),
+                    )
+                    .arg(arg::address().required_if("event", "receive"))
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .long("tx-hash")
+                            .takes_value(true)
+                            .required_if("event", "spend")
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Transaction hash of the watched out point"),
+                    )
+                    .arg(
+                        Arg::with_name("output-index")
+                            .long("output-index")
+                            .takes_value(true)
+                            .required_if("event", "spend")
+                            .validator(|input| FromStrParser::<u32>::default().validate(input))
+                            .help("Output index of the watched out point"),
+                    )
+                    .arg(
+                        Arg::with_name("command")
+                            .long("command")
+                            .takes_value(true)
+                            .required_unless("webhook")
+                            .help("Shell command to run, event json is in CKB_CLI_EVENT environment variable"),
+                    )
+                    .arg(
+                        Arg::with_name("webhook")
+                            .long("webhook")
+                            .takes_value(true)
+                            .validator(|input| UrlParser.validate(input))
+                            .help("URL to POST event json to"),
+                    ),
+                SubCommand::with_name("annotate")
+                    .about("Attach a local memo to a transaction (empty memo removes it)")
+                    .arg(arg::tx_hash().required(true))
+                    .arg(arg::memo().required(true)),
+                SubCommand::with_name("search-memos")
+                    .about("Search transaction memos (list all if keyword is not given)")
+                    .arg(
+                        Arg::with_name("memo")
+                            .long("memo")
+                            .takes_value(true)
+                            .help("Keyword in memo (case insensitive)"),
+                    ),
+                SubCommand::with_name("list-hooks").about("List event hooks"),
+                SubCommand::with_name("remove-hook")
+                    .about("Remove event hook")
+                    .arg(
+                        Arg::with_name("index")
+                            .long("index")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Hook index (see: wallet list-hooks)"),
+                    ),
             ])
     }
 
-    pub fn transfer(
+    pub fn transfer(&mut self, m: &ArgMatches, debug: bool) -> Result<Box<dyn Printable>, String> {
+        if let Some(input) = m.value_of("payment-request") {
+            let request: PaymentRequest = input.parse()?;
+            return self.pay_request(m, request, debug);
+        }
+        let asset = AssetRegistry::load(&self.index_dir)?.resolve(m.value_of("asset").unwrap())?;
+        if let Asset::Udt(info) = asset {
+            if !m.is_present("amount") {
+                return Err(format!("<amount> is required to transfer {}", info.symbol));
+            }
+            let amount: u128 = AmountParser::new(info.decimals).from_matches(m, "amount")?;
+            let to_address = m.value_of("to-address").unwrap().to_owned();
+            let memo = m.value_of("memo").map(ToOwned::to_owned);
+            return self.transfer_udt(m, info, &to_address, amount, memo, debug);
+        }
+        if m.is_present("amount") {
+            return Err("<amount> is for sUDT assets, use <capacity> to transfer CKB".to_owned());
+        }
+        let outputs_opt = if let Some(path) = m.value_of("outputs-file") {
+            let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+            let network_type = get_network_type(self.rpc_client)?;
+            let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+            let specs = parse_outputs(Path::new(path), &content, network_type)?;
+            let outputs = specs
+                .iter()
+                .map(|spec| spec.to_output(&secp_type_hash))
+                .collect::<Result<Vec<_>, String>>()?;
+            Some(outputs)
+        } else {
+            None
+        };
+        let memo = m.value_of("memo").map(ToOwned::to_owned);
+        self.transfer_ckb(m, outputs_opt, memo, debug)
+    }
+
+    // Transfer the asset and amount of a payment request, --memo overrides
+    // the memo of the request.
+    fn pay_request(
         &mut self,
         m: &ArgMatches,
-        format: OutputFormat,
-        color: bool,
+        request: PaymentRequest,
+        debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        if request.asset.is_some() && m.occurrences_of("asset") > 0 {
+            return Err("The asset is given by the payment request, remove --asset".to_owned());
+        }
+        let asset_name = request.asset.clone().unwrap_or_else(|| "ckb".to_owned());
+        let asset = AssetRegistry::load(&self.index_dir)?.resolve(&asset_name)?;
+        let amount = request
+            .amount
+            .clone()
+            .ok_or_else(|| "Payment request has no amount".to_owned())?;
+        let network_type = get_network_type(self.rpc_client)?;
+        check_address_prefix(&request.address, network_type)?;
+        let memo = m
+            .value_of("memo")
+            .map(ToOwned::to_owned)
+            .or_else(|| request.memo.clone());
+        eprintln!(
+            "[INFO]: pay {} {} to {}",
+            amount,
+            asset.symbol(),
+            request.address
+        );
+        match asset {
+            Asset::Udt(info) => {
+                let amount: u128 = AmountParser::new(info.decimals).parse(&amount)?;
+                self.transfer_udt(m, info, &request.address, amount, memo, debug)
+            }
+            Asset::Ckb => {
+                let capacity: u64 = CapacityParser.parse(&amount)?;
+                check_capacity(capacity, 0)?;
+                let to_address: Address = AddressParser.parse(&request.address)?;
+                let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+                let output = packed::CellOutput::new_builder()
+                    .lock(to_address.lock_script(secp_type_hash))
+                    .capacity(Capacity::shannons(capacity).pack())
+                    .build();
+                self.transfer_ckb(m, Some(vec![(output, Bytes::new())]), memo, debug)
+            }
+        }
+    }
+
+    // Transfer CKB to <to-address>, or to the given outputs instead
+    fn transfer_ckb(
+        &mut self,
+        m: &ArgMatches,
+        outputs_opt: Option<Vec<(packed::CellOutput, Bytes)>>,
+        memo: Option<String>,
         debug: bool,
-    ) -> Result<String, String> {
+    ) -> Result<Box<dyn Printable>, String> {
         let from_privkey: Option<PrivkeyWrapper> =
             PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
-        let from_account: Option<H160> =
-            FixedHashParser::<H160>::default().from_matches_opt(m, "from-account", false)?;
-        let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
-        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let from_account = self.from_account(m, from_privkey.is_some())?;
+        let with_password = m.is_present("with-password");
+        let password = if with_password && from_privkey.is_none() {
+            Some(read_password(false, None)?)
+        } else {
+            None
+        };
+        // Spend from a derived address (e.g. a HD change address) of the account
+        let from_privkey = match m.value_of("from-path") {
+            Some(path) => Some(self.derived_privkey(
+                from_account.as_ref().unwrap(),
+                path,
+                password.as_ref().map(String::as_str),
+            )?),
+            None => from_privkey,
+        };
+        let tx_fee_opt: Option<u64> = CapacityParser.from_matches_opt(m, "tx-fee", false)?;
         let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
             let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
             let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
@@ -191,23 +980,113 @@ impl<'a> WalletSubCommand<'a> {
         } else {
             Address::from_lock_arg(from_account.as_ref().unwrap().as_bytes())?
         };
-        let to_address: Address = AddressParser.from_matches(m, "to-address")?;
-        let to_data = to_data(m)?;
-        let with_password = m.is_present("with-password");
-
-        check_capacity(capacity, to_data.len())?;
         let network_type = get_network_type(self.rpc_client)?;
         let genesis_info = self.genesis_info()?;
         let secp_type_hash = genesis_info.secp_type_hash();
 
-        check_address_prefix(m.value_of("to-address").unwrap(), network_type)?;
+        let (to_address, to_data, capacity) = if let Some(outputs) = outputs_opt.as_ref() {
+            let capacity = outputs
+                .iter()
+                .map(|(output, _)| {
+                    let capacity: Capacity = output.capacity().unpack();
+                    capacity.as_u64()
+                })
+                .try_fold(0u64, u64::checked_add)
+                .ok_or_else(|| "Total capacity of outputs overflow".to_owned())?;
+            (from_address.clone(), Bytes::new(), capacity)
+        } else {
+            let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
+            let to_address: Address = AddressParser.from_matches(m, "to-address")?;
+            let to_data = to_data(m)?;
+            check_capacity(capacity, to_data.len())?;
+            check_address_prefix(m.value_of("to-address").unwrap(), network_type)?;
+            (to_address, to_data, capacity)
+        };
+        let from_lock_hash = from_address
+            .lock_script(secp_type_hash.clone())
+            .calc_script_hash();
+        let hd_change = if m.is_present("hd-change") {
+            let (address, account) = self.next_change_address(
+                from_account.as_ref().unwrap(),
+                password.as_ref().map(String::as_str),
+            )?;
+            eprintln!(
+                "[INFO]: change to {} ({})",
+                address.to_string(network_type),
+                account.path
+            );
+            Some((address, account))
+        } else {
+            None
+        };
+        let reservation = match self.reserve_idempotency_key(m, &from_lock_hash.unpack())? {
+            Some(IdempotencyState::Sent(tx_hash)) => return Ok(Box::new(tx_hash)),
+            Some(IdempotencyState::Reserved(reservation)) => Some(reservation),
+            None => None,
+        };
+        let fee_rate = match m.value_of("fee") {
+            Some(preset) => {
+                let fee_rate = self.resolve_fee_rate(preset)?;
+                eprintln!("[INFO]: fee {} => {} shannons/KB", preset, fee_rate);
+                fee_rate
+            }
+            None => 0,
+        };
+        // Outputs (include change) used to estimate transaction size for fee rate
+        let mut estimate_outputs = outputs_opt.clone().unwrap_or_else(|| {
+            let output = packed::CellOutput::new_builder()
+                .capacity(Capacity::shannons(capacity).pack())
+                .lock(to_address.lock_script(secp_type_hash.clone()))
+                .build();
+            vec![(output, to_data.clone())]
+        });
+        estimate_outputs.push((
+            packed::CellOutput::new_builder()
+                .lock(from_address.lock_script(secp_type_hash.clone()))
+                .build(),
+            Bytes::new(),
+        ));
+        let fee_for = |inputs: usize| -> u64 {
+            tx_fee_opt.unwrap_or_else(|| {
+                let size = estimate_tx_size(inputs, &estimate_outputs) as u64;
+                (fee_rate * size + 999) / 1000
+            })
+        };
+        // Capacities of an outputs file are not bounded by the parser
+        capacity
+            .checked_add(fee_for(1))
+            .ok_or_else(|| "Capacity overflow".to_owned())?;
         // For check index database is ready
-        self.with_db(|_| ())?;
+        let balance = self
+            .with_db(|db| db.get_capacity(from_lock_hash.clone()))?
+            .unwrap_or(0);
+        if !m.is_present("force") {
+            let max_fee_ratio: f64 =
+                FromStrParser::<f64>::default().from_matches(m, "max-fee-ratio")?;
+            let max_balance_ratio: f64 =
+                FromStrParser::<f64>::default().from_matches(m, "max-balance-ratio")?;
+            check_transfer_safety(
+                capacity,
+                fee_for(1),
+                balance,
+                max_fee_ratio,
+                max_balance_ratio,
+            )?;
+        }
         let index_dir = self.index_dir.clone();
+        let min_confirmations = self.min_confirmations;
         let genesis_hash = genesis_info.header().hash();
         let genesis_info_clone = genesis_info.clone();
+        let mut pending_txs = PendingTxStore::load(&index_dir, &genesis_hash.unpack())?;
+        pending_txs.refresh(self.rpc_client)?;
+        // Cells consumed by our pending transactions are still live on chain
+        let pending_spent = pending_txs.spent_out_points();
         let mut total_capacity = 0;
+        let mut inputs_count = 0;
         let terminator = |_, info: &LiveCellInfo| {
+            if pending_spent.contains(&(info.tx_hash.clone(), info.tx_index)) {
+                return (false, false);
+            }
             let out_point = info.out_point();
             let resp: CellWithStatus = self
                 .rpc_client
@@ -216,38 +1095,64 @@ impl<'a> WalletSubCommand<'a> {
                 .expect("get_live_cell by RPC call failed");
             if is_live_cell(&resp) && is_secp_cell(&resp) {
                 total_capacity += info.capacity;
-                (total_capacity >= capacity + tx_fee, true)
+                inputs_count += 1;
+                let fee = fee_for(inputs_count);
+                (total_capacity >= capacity.saturating_add(fee), true)
             } else {
                 (false, false)
             }
         };
         let infos: Vec<LiveCellInfo> =
             with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
-                let db =
+                let mut db =
                     IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
-                Ok(db.get_live_cells_by_lock(
-                    from_address
-                        .lock_script(secp_type_hash.clone())
-                        .calc_script_hash(),
-                    None,
-                    terminator,
-                ))
+                db.set_min_confirmations(min_confirmations);
+                Ok(db.get_live_cells_by_lock(from_lock_hash.clone(), None, terminator))
             })
             .map_err(|_err| {
                 format!(
-                    "index database may not ready, sync process: {}",
+                    "{}: {}",
+                    tr("index database may not ready, sync process"),
                     self.index_controller.state().read().to_string()
                 )
             })?;
 
-        if total_capacity < capacity + tx_fee {
+        // Committed cells are not enough, chain on change of pending transactions
+        // (never confirmed, so not when confirmations are required)
+        let mut chained = Vec::new();
+        if !m.is_present("no-chain-pending") && self.min_confirmations == 0 {
+            for change in pending_txs.available_changes(&from_lock_hash.unpack()) {
+                let fee = fee_for(infos.len() + chained.len());
+                if total_capacity >= capacity.saturating_add(fee) {
+                    break;
+                }
+                total_capacity += change.capacity;
+                chained.push(change);
+            }
+        }
+        let tx_fee = fee_for(infos.len() + chained.len());
+        let required = capacity
+            .checked_add(tx_fee)
+            .ok_or_else(|| "Capacity overflow".to_owned())?;
+        if total_capacity < required {
             return Err(format!(
-                "Capacity not enough: {} => {}",
+                "{}: {} => {}",
+                tr("Capacity not enough"),
                 from_address.to_string(network_type),
                 total_capacity,
             ));
         }
-        let inputs = infos.iter().map(LiveCellInfo::input).collect::<Vec<_>>();
+        let inputs = infos
+            .iter()
+            .map(LiveCellInfo::input)
+            .chain(chained.iter().map(|change| change.input()))
+            .collect::<Vec<_>>();
+        for change in &chained {
+            eprintln!(
+                "[INFO]: spend pending change {:#x}-{} (chain depth: {})",
+                change.tx_hash, change.index, change.depth
+            );
+        }
         let mut tx_args = TransferTransactionBuilder::new(
             &from_address,
             total_capacity,
@@ -257,35 +1162,84 @@ impl<'a> WalletSubCommand<'a> {
             tx_fee,
             inputs,
         );
+        if let Some(outputs) = outputs_opt {
+            tx_args.set_outputs(outputs);
+        }
+        if let Some((address, _)) = hd_change.as_ref() {
+            tx_args.set_change_address(address);
+        }
+        tx_args.add_header_deps(get_header_deps(self.rpc_client, m)?);
         let transaction = if let Some(privkey) = from_privkey.as_ref() {
             tx_args.transfer(&genesis_info, |args| {
                 Ok(build_witness_with_key(privkey, args))
             })
         } else {
             let lock_arg = from_account.as_ref().unwrap();
-            let password = if with_password {
-                Some(read_password(false, None)?)
-            } else {
-                None
-            };
             tx_args.transfer(&genesis_info, |args| {
                 self.build_witness_with_keystore(lock_arg, args, &password)
             })
         }?;
-        self.send_transaction(transaction, format, color, debug)
+        let pending_tx = PendingTx::new(&transaction, from_lock_hash.unpack());
+        let tx_hash: H256 = transaction.hash().unpack();
+        let resp = self.send_with_idempotency_key(m, reservation, transaction, debug)?;
+        pending_txs.add(pending_tx);
+        pending_txs.save()?;
+        if let Some((_, account)) = hd_change {
+            self.record_change_address(from_account.unwrap(), account)?;
+        }
+        if let Some(memo) = memo {
+            let mut memos = TxMemos::load(&self.index_dir)?;
+            memos.set(tx_hash, memo);
+            memos.save()?;
+        }
+        Ok(resp)
+    }
+
+    pub fn burn(&mut self, m: &ArgMatches, debug: bool) -> Result<Box<dyn Printable>, String> {
+        let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
+        // The burn pattern: no cell can have data hash of all zeros, so the
+        // lock script can never be loaded and the cell is never unlocked.
+        let burn_lock = Script::new_builder()
+            .code_hash(Byte32::zero())
+            .hash_type(ScriptHashType::Data.into())
+            .build();
+        let output = packed::CellOutput::new_builder()
+            .capacity(Capacity::shannons(capacity).pack())
+            .lock(burn_lock)
+            .build();
+        let occupied_capacity = output
+            .occupied_capacity(Capacity::zero())
+            .map_err(|err| err.to_string())?
+            .as_u64();
+        if capacity < occupied_capacity {
+            return Err(format!(
+                "Capacity can not less than {} shannons",
+                occupied_capacity
+            ));
+        }
+        let capacity_string = m.value_of("capacity").unwrap();
+        confirm_input(
+            &tr_fmt(
+                "Burn {} CKB, it can never be spent again",
+                &[&capacity_string],
+            ),
+            capacity_string,
+        )?;
+        let memo = match m.value_of("memo") {
+            Some(memo) => format!("[burn {} CKB] {}", capacity_string, memo),
+            None => format!("[burn {} CKB]", capacity_string),
+        };
+        self.transfer_ckb(m, Some(vec![(output, Bytes::new())]), Some(memo), debug)
     }
 
     pub fn deposit_dao(
         &mut self,
         m: &ArgMatches,
-        format: OutputFormat,
-        color: bool,
         debug: bool,
-    ) -> Result<String, String> {
+    ) -> Result<Box<dyn Printable>, String> {
         let from_privkey: Option<PrivkeyWrapper> =
             PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
-        let from_account: Option<H160> =
-            FixedHashParser::<H160>::default().from_matches_opt(m, "from-account", false)?;
+        let from_account = self.from_account(m, from_privkey.is_some())?;
         let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
         let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
         let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
@@ -300,6 +1254,11 @@ impl<'a> WalletSubCommand<'a> {
             .unwrap_or_else(|| from_address.clone());
         let to_data = to_data(m)?;
         let with_password = m.is_present("with-password");
+        let password = if with_password && from_privkey.is_none() {
+            Some(read_password(false, None)?)
+        } else {
+            None
+        };
 
         check_capacity(capacity, to_data.len())?;
         let network_type = get_network_type(self.rpc_client)?;
@@ -309,9 +1268,24 @@ impl<'a> WalletSubCommand<'a> {
         if let Some(address) = m.value_of("to-address") {
             check_address_prefix(address, network_type)?;
         }
+        let hd_change = if m.is_present("hd-change") {
+            let (address, account) = self.next_change_address(
+                from_account.as_ref().unwrap(),
+                password.as_ref().map(String::as_str),
+            )?;
+            eprintln!(
+                "[INFO]: change to {} ({})",
+                address.to_string(network_type),
+                account.path
+            );
+            Some((address, account))
+        } else {
+            None
+        };
         // For check index database is ready
         self.with_db(|_| ())?;
         let index_dir = self.index_dir.clone();
+        let min_confirmations = self.min_confirmations;
         let genesis_hash = genesis_info.header().hash();
         let genesis_info_clone = genesis_info.clone();
         let mut total_capacity = 0;
@@ -331,8 +1305,9 @@ impl<'a> WalletSubCommand<'a> {
         };
         let infos: Vec<LiveCellInfo> =
             with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
-                let db =
+                let mut db =
                     IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
+                db.set_min_confirmations(min_confirmations);
 
                 Ok(db.get_live_cells_by_lock(
                     from_address
@@ -344,14 +1319,16 @@ impl<'a> WalletSubCommand<'a> {
             })
             .map_err(|_err| {
                 format!(
-                    "index database may not ready, sync process: {}",
+                    "{}: {}",
+                    tr("index database may not ready, sync process"),
                     self.index_controller.state().read().to_string()
                 )
             })?;
 
         if total_capacity < capacity + tx_fee {
             return Err(format!(
-                "Capacity not enough: {} => {}",
+                "{}: {} => {}",
+                tr("Capacity not enough"),
                 from_address.to_string(network_type),
                 total_capacity,
             ));
@@ -367,35 +1344,35 @@ impl<'a> WalletSubCommand<'a> {
             tx_fee,
             inputs,
         );
+        if let Some((address, _)) = hd_change.as_ref() {
+            tx_args.set_change_address(address);
+        }
+        tx_args.add_header_deps(get_header_deps(self.rpc_client, m)?);
         let transaction = if let Some(privkey) = from_privkey.as_ref() {
             tx_args.deposit_dao(&genesis_info, |args| {
                 Ok(build_witness_with_key(privkey, args))
             })
         } else {
             let lock_arg = from_account.as_ref().unwrap();
-            let password = if with_password {
-                Some(read_password(false, None)?)
-            } else {
-                None
-            };
             tx_args.deposit_dao(&genesis_info, |args| {
                 self.build_witness_with_keystore(lock_arg, args, &password)
             })
         }?;
-        self.send_transaction(transaction, format, color, debug)
+        let resp = self.send_transaction(transaction, m.is_present("broadcast-all"), debug)?;
+        if let Some((_, account)) = hd_change {
+            self.record_change_address(from_account.unwrap(), account)?;
+        }
+        Ok(resp)
     }
 
     pub fn withdraw_dao(
         &mut self,
         m: &ArgMatches,
-        format: OutputFormat,
-        color: bool,
         debug: bool,
-    ) -> Result<String, String> {
+    ) -> Result<Box<dyn Printable>, String> {
         let from_privkey: Option<PrivkeyWrapper> =
             PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
-        let from_account: Option<H160> =
-            FixedHashParser::<H160>::default().from_matches_opt(m, "from-account", false)?;
+        let from_account = self.from_account(m, from_privkey.is_some())?;
         let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
         let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
         let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
@@ -410,6 +1387,11 @@ impl<'a> WalletSubCommand<'a> {
             .unwrap_or_else(|| from_address.clone());
         let to_data = to_data(m)?;
         let with_password = m.is_present("with-password");
+        let password = if with_password && from_privkey.is_none() {
+            Some(read_password(false, None)?)
+        } else {
+            None
+        };
 
         check_capacity(capacity, to_data.len())?;
         let network_type = get_network_type(self.rpc_client)?;
@@ -419,9 +1401,24 @@ impl<'a> WalletSubCommand<'a> {
         if let Some(address) = m.value_of("to-address") {
             check_address_prefix(address, network_type)?;
         }
+        let hd_change = if m.is_present("hd-change") {
+            let (address, account) = self.next_change_address(
+                from_account.as_ref().unwrap(),
+                password.as_ref().map(String::as_str),
+            )?;
+            eprintln!(
+                "[INFO]: change to {} ({})",
+                address.to_string(network_type),
+                account.path
+            );
+            Some((address, account))
+        } else {
+            None
+        };
         // For check index database is ready
         self.with_db(|_| ())?;
         let index_dir = self.index_dir.clone();
+        let min_confirmations = self.min_confirmations;
         let genesis_hash = genesis_info.header().hash();
         let genesis_info_clone = genesis_info.clone();
         let mut total_capacity = 0;
@@ -441,8 +1438,9 @@ impl<'a> WalletSubCommand<'a> {
         };
         let infos: Vec<LiveCellInfo> =
             with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
-                let db =
+                let mut db =
                     IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
+                db.set_min_confirmations(min_confirmations);
                 Ok(db.get_live_cells_by_lock(
                     from_address
                         .lock_script(secp_type_hash.clone())
@@ -453,14 +1451,16 @@ impl<'a> WalletSubCommand<'a> {
             })
             .map_err(|_err| {
                 format!(
-                    "index database may not ready, sync process: {}",
+                    "{}: {}",
+                    tr("index database may not ready, sync process"),
                     self.index_controller.state().read().to_string()
                 )
             })?;
 
         if total_capacity < capacity + tx_fee {
             return Err(format!(
-                "Capacity not enough: {} => {}",
+                "{}: {} => {}",
+                tr("Capacity not enough"),
                 from_address.to_string(network_type),
                 total_capacity,
             ));
@@ -478,6 +1478,10 @@ impl<'a> WalletSubCommand<'a> {
             tx_fee,
             inputs,
         );
+        if let Some((address, _)) = hd_change.as_ref() {
+            tx_args.set_change_address(address);
+        }
+        tx_args.add_header_deps(get_header_deps(self.rpc_client, m)?);
         let transaction = if let Some(privkey) = from_privkey.as_ref() {
             tx_args.withdraw_dao(
                 withdraw_header_hash,
@@ -487,11 +1491,6 @@ impl<'a> WalletSubCommand<'a> {
             )
         } else {
             let lock_arg = from_account.as_ref().unwrap();
-            let password = if with_password {
-                Some(read_password(false, None)?)
-            } else {
-                None
-            };
             tx_args.withdraw_dao(
                 withdraw_header_hash,
                 input_header_hashes,
@@ -499,7 +1498,11 @@ impl<'a> WalletSubCommand<'a> {
                 |args| self.build_witness_with_keystore(lock_arg, args, &password),
             )
         }?;
-        self.send_transaction(transaction, format, color, debug)
+        let resp = self.send_transaction(transaction, m.is_present("broadcast-all"), debug)?;
+        if let Some((_, account)) = hd_change {
+            self.record_change_address(from_account.unwrap(), account)?;
+        }
+        Ok(resp)
     }
 
     fn build_witness_with_keystore(
@@ -526,74 +1529,168 @@ impl<'a> WalletSubCommand<'a> {
                 .sign_recoverable_with_password(lock_arg, &sign_hash, password.as_bytes())
                 .map_err(|err| err.to_string())
         } else {
-            return Err("Password required to unlock the keystore".to_owned());
+            return Err(tr("Password required to unlock the keystore").to_owned());
+        };
+        let signature = signature_result?;
+        // The first sign arg is the transaction hash
+        let tx_hash = args.first().and_then(|arg| H256::from_slice(arg).ok());
+        record_key_usage(self.key_store.keys_dir(), &[lock_arg.clone()], tx_hash);
+        Ok(serialize_signature(&signature))
+    }
+
+    /// Build and sign `total` transactions of one sender by `build(index,
+    /// build_witness)`. Signing with a password runs scrypt for every
+    /// transaction, those are signed over all cores.
+    pub(super) fn sign_transactions<B>(
+        &mut self,
+        total: usize,
+        from_privkey: Option<&PrivkeyWrapper>,
+        from_account: Option<&H160>,
+        password: &Option<String>,
+        build: B,
+    ) -> Result<Vec<TransactionView>, String>
+    where
+        B: Fn(
+                usize,
+                &mut dyn FnMut(&Vec<Vec<u8>>) -> Result<Bytes, String>,
+            ) -> Result<TransactionView, String>
+            + Sync,
+    {
+        let progress: SignProgress = Box::new(|signed, total| {
+            if total > 1 {
+                eprint!("\rSigned: {}/{}", signed, total);
+                if signed == total {
+                    eprintln!();
+                }
+            }
+        });
+        if let Some(privkey) = from_privkey {
+            return sign_in_parallel(
+                total,
+                |index| build(index, &mut |args| Ok(build_witness_with_key(privkey, args))),
+                Some(&progress),
+            );
+        }
+        let lock_arg = from_account.ok_or_else(|| "No sender account".to_owned())?;
+        let password = match password {
+            Some(password) => password,
+            // Signed by the unlocked key of interactive mode, no scrypt
+            None => {
+                return (0..total)
+                    .map(|index| {
+                        build(index, &mut |args| {
+                            self.build_witness_with_keystore(lock_arg, args, password)
+                        })
+                    })
+                    .collect();
+            }
         };
-        signature_result.map(|signature| serialize_signature(&signature))
+        let key_store = &*self.key_store;
+        let transactions = sign_in_parallel(
+            total,
+            |index| {
+                build(index, &mut |args| {
+                    let sign_hash = H256::from_slice(&blake2b_args(args))
+                        .expect("converting digest of [u8; 32] to H256 should be ok");
+                    key_store
+                        .sign_recoverable_with_password(lock_arg, &sign_hash, password.as_bytes())
+                        .map(|signature| serialize_signature(&signature))
+                        .map_err(|err| err.to_string())
+                })
+            },
+            Some(&progress),
+        )?;
+        for transaction in &transactions {
+            record_key_usage(
+                self.key_store.keys_dir(),
+                &[lock_arg.clone()],
+                Some(transaction.hash().unpack()),
+            );
+        }
+        Ok(transactions)
     }
 
     fn send_transaction(
         &mut self,
         transaction: TransactionView,
-        format: OutputFormat,
-        color: bool,
+        broadcast_all: bool,
         debug: bool,
-    ) -> Result<String, String> {
+    ) -> Result<Box<dyn Printable>, String> {
         let transaction_view: ckb_jsonrpc_types::TransactionView = transaction.clone().into();
         if debug {
             println!(
                 "[Send Transaction]:\n{}",
-                transaction_view.render(format, color)
+                transaction_view.render(self.output_format, self.color)
             );
         }
+        self.check_fee(&transaction)?;
+        self.check_conflicts(&transaction)?;
 
+        if broadcast_all {
+            return self.broadcast_transaction(transaction);
+        }
         let resp = self
             .rpc_client
             .send_transaction(transaction.data().into())
             .call()
-            .map_err(|err| format!("Send transaction error: {}", err))?;
-        Ok(resp.render(format, color))
+            .map_err(|err| format!("{}: {}", tr("Send transaction error"), err))?;
+        Ok(Box::new(resp))
+    }
+
+    // Lock hashes of --address (default secp256k1 lock) and --lock-hash
+    fn watch_lock_hashes(&mut self, m: &ArgMatches) -> Result<Vec<H256>, String> {
+        let mut lock_hashes: Vec<H256> =
+            FixedHashParser::<H256>::default().from_matches_vec(m, "lock-hash")?;
+        let addresses: Vec<Address> = AddressParser.from_matches_vec(m, "address")?;
+        if !addresses.is_empty() {
+            let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+            for address in addresses {
+                lock_hashes.push(
+                    address
+                        .lock_script(secp_type_hash.clone())
+                        .calc_script_hash()
+                        .unpack(),
+                );
+            }
+        }
+        Ok(lock_hashes)
+    }
+
+    fn watch_list_info(&mut self, watch_list: &WatchList) -> Result<Box<dyn Printable>, String> {
+        let genesis_hash: H256 = self.genesis_info()?.header().hash().unpack();
+        let resp = serde_json::json!({
+            "lock_hashes": watch_list.lock_hashes(),
+            "index_all_locks": watch_list.is_empty(),
+            "db_dir": self.index_dir.join(watch_list.db_dir_name(&genesis_hash)),
+        });
+        Ok(Box::new(resp))
     }
 }
 
 impl<'a> CliSubCommand for WalletSubCommand<'a> {
-    fn process(
-        &mut self,
-        matches: &ArgMatches,
-        format: OutputFormat,
-        color: bool,
-        debug: bool,
-    ) -> Result<String, String> {
+    fn process(&mut self, matches: &ArgMatches, debug: bool) -> Result<Box<dyn Printable>, String> {
         match matches.subcommand() {
-            ("transfer", Some(m)) => self.transfer(m, format, color, debug),
-            ("deposit-dao", Some(m)) => self.deposit_dao(m, format, color, debug),
-            ("withdraw-dao", Some(m)) => self.withdraw_dao(m, format, color, debug),
-            ("get-capacity", Some(m)) => {
-                let lock_hash_opt: Option<H256> =
-                    FixedHashParser::<H256>::default().from_matches_opt(m, "lock-hash", false)?;
-                let lock_hash = if let Some(lock_hash) = lock_hash_opt {
-                    lock_hash.pack()
-                } else {
-                    let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
-                    let address = get_address(m)?;
-                    address.lock_script(secp_type_hash).calc_script_hash()
-                };
-                let capacity = self.with_db(|db| db.get_capacity(lock_hash))?;
-                let resp = serde_json::json!({
-                    "capacity": capacity,
-                });
-                Ok(resp.render(format, color))
+            ("transfer", Some(m)) => self.transfer(m, debug),
+            ("burn", Some(m)) => self.burn(m, debug),
+            ("deposit-dao", Some(m)) => self.deposit_dao(m, debug),
+            ("withdraw-dao", Some(m)) => self.withdraw_dao(m, debug),
+            ("withdraw-all-dao", Some(m)) => self.withdraw_all_dao(m),
+            ("udt-airdrop", Some(m)) => self.udt_airdrop(m),
+            ("migrate-lock", Some(m)) => self.migrate_lock(m),
+            ("split-cell", Some(m)) => self.reshape_cells(m, true),
+            ("merge-cells", Some(m)) => self.reshape_cells(m, false),
+            ("job-status", Some(m)) => {
+                let job = BatchJob::load(&self.index_dir, m.value_of("job-id").unwrap())?;
+                Ok(Box::new(job.reconcile(&mut self.rpc_client)?))
             }
+            ("rebase-tx", Some(m)) => self.rebase_tx(m),
+            ("presign-sweep", Some(m)) => self.presign_sweep(m),
+            ("emergency-broadcast", Some(m)) => self.emergency_broadcast(m, debug),
+            ("faucet-claim", Some(m)) => self.faucet_claim(m),
+            ("get-capacity", Some(m)) => self.get_capacity(m),
             ("get-dao-capacity", Some(m)) => {
-                let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
                 let dao_type_hash = self.genesis_info()?.dao_type_hash().clone();
-                let lock_hash_opt: Option<H256> =
-                    FixedHashParser::<H256>::default().from_matches_opt(m, "lock-hash", false)?;
-                let lock_hash = if let Some(lock_hash) = lock_hash_opt {
-                    lock_hash.pack()
-                } else {
-                    let address = get_address(m)?;
-                    address.lock_script(secp_type_hash).calc_script_hash()
-                };
+                let lock_hash = self.query_lock_hash(m)?;
                 let capacity = self.with_db(|db| {
                     let infos_by_lock = db
                         .get_live_cells_by_lock(lock_hash, Some(0), |_, _| (false, true))
@@ -611,8 +1708,9 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                 let resp = serde_json::json!({
                     "capacity": capacity,
                 });
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
+            ("state-rent", Some(m)) => self.state_rent(m),
             ("get-live-cells", Some(m)) => {
                 let lock_hash_opt: Option<H256> =
                     FixedHashParser::<H256>::default().from_matches_opt(m, "lock-hash", false)?;
@@ -626,17 +1724,33 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                 let to_number_opt: Option<u64> =
                     FromStrParser::<u64>::default().from_matches_opt(m, "to", false)?;
 
+                let filter_opt: Option<CellFilter> =
+                    FromStrParser::<CellFilter>::new().from_matches_opt(m, "filter", false)?;
+
                 if lock_hash_opt.is_none() && type_hash_opt.is_none() && code_hash_opt.is_none() {
                     return Err("lock-hash or type-hash or code-hash is required".to_owned());
                 }
 
                 let to_number = to_number_opt.unwrap_or(std::u64::MAX);
+                let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+                let lock_templates = all_lock_templates(&self.lock_templates_file)?;
+                let accounts =
+                    get_lock_hash_accounts(self.key_store, &secp_type_hash, &lock_templates);
+                let address_book = AddressBook::load(self.key_store.keys_dir())?;
+                let memos = TxMemos::load(&self.index_dir)?;
+                let assets = AssetRegistry::load(&self.index_dir)?;
                 let (infos, total_capacity) = self.with_db(|db| {
                     let mut total_capacity = 0;
-                    let terminator = |idx, info: &LiveCellInfo| {
-                        let stop = idx >= limit || info.number > to_number;
-                        let push_info = !stop;
+                    let mut matched = 0;
+                    let terminator = |_idx, info: &LiveCellInfo| {
+                        let stop = matched >= limit || info.number > to_number;
+                        let push_info = !stop
+                            && filter_opt
+                                .as_ref()
+                                .map(|filter| filter.matches(info))
+                                .unwrap_or(true);
                         if push_info {
+                            matched += 1;
                             total_capacity += info.capacity;
                         }
                         (stop, push_info)
@@ -664,11 +1778,23 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                 })?;
                 let resp = serde_json::json!({
                     "live_cells": infos.into_iter().map(|info| {
-                        serde_json::to_value(&info).unwrap()
+                        let mut value = serde_json::to_value(&info).unwrap();
+                        value["account"] = serde_json::json!(accounts
+                            .get(&info.lock_hash)
+                            .map(|lock_arg| address_book.hint(lock_arg)));
+                        if let Some(memo) = memos.get(&info.tx_hash) {
+                            value["memo"] = serde_json::json!(memo.memo);
+                        }
+                        if let Some((_, ref type_hash)) = info.type_hashes {
+                            if let Some(asset) = assets.find_by_type_hash(type_hash) {
+                                value["asset"] = serde_json::json!(asset.symbol);
+                            }
+                        }
+                        value
                     }).collect::<Vec<_>>(),
                     "total_capacity": total_capacity,
                 });
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
             }
             ("get-lock-by-address", Some(m)) => {
                 let address: Address = AddressParser.from_matches(m, "address")?;
@@ -688,7 +1814,7 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                             })
                         })
                 })?;
-                Ok(lock_script.render(format, color))
+                Ok(Box::new(lock_script))
             }
             ("top-capacity", Some(m)) => {
                 let n: usize = m
@@ -696,30 +1822,173 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                     .map(|n_str| n_str.parse().unwrap())
                     .unwrap();
                 let network_type = get_network_type(self.rpc_client)?;
+                let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+                let lock_templates = all_lock_templates(&self.lock_templates_file)?;
+                let accounts =
+                    get_lock_hash_accounts(self.key_store, &secp_type_hash, &lock_templates);
+                let address_book = AddressBook::load(self.key_store.keys_dir())?;
                 let resp = self.with_db(|db| {
                     db.get_top_n(n)
                         .into_iter()
                         .map(|(lock_hash, address, capacity)| {
+                            let lock_hash_h256: H256 = lock_hash.unpack();
+                            let account = accounts
+                                .get(&lock_hash_h256)
+                                .map(|lock_arg| address_book.hint(lock_arg));
                             serde_json::json!({
                                 "lock_hash": format!("{:#x}", lock_hash),
+                                "account": account,
                                 "address": address.map(|addr| addr.to_string(network_type)),
                                 "capacity": capacity,
                             })
                         })
                         .collect::<Vec<_>>()
                 })?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
+            }
+            ("get-cells", Some(m)) => self.get_cells(m),
+            ("scan-accounts", Some(m)) => self.scan_accounts(m),
+            ("add-hook", Some(m)) => self.add_hook(m),
+            ("annotate", Some(m)) => {
+                let tx_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+                let memo = m.value_of("memo").unwrap().to_owned();
+                let mut memos = TxMemos::load(&self.index_dir)?;
+                memos.set(tx_hash, memo);
+                memos.save()?;
+                Ok(Box::new(Text::from("DONE")))
+            }
+            ("search-memos", Some(m)) => {
+                let memos = TxMemos::load(&self.index_dir)?;
+                let resp = memos
+                    .search(m.value_of("memo").unwrap_or(""))
+                    .into_iter()
+                    .map(|(tx_hash, memo)| {
+                        serde_json::json!({
+                            "tx_hash": tx_hash,
+                            "memo": memo.memo,
+                            "updated_at": memo.updated_at,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Box::new(serde_json::json!(resp)))
+            }
+            ("list-hooks", _) => {
+                let hooks = Hooks::load(&self.index_dir)?;
+                let resp = hooks
+                    .hooks
+                    .iter()
+                    .enumerate()
+                    .map(|(index, hook)| {
+                        let mut value = serde_json::to_value(hook).unwrap();
+                        value["index"] = serde_json::json!(index);
+                        value
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Box::new(serde_json::json!(resp)))
             }
+            ("remove-hook", Some(m)) => {
+                let index: usize = FromStrParser::<usize>::default().from_matches(m, "index")?;
+                let mut hooks = Hooks::load(&self.index_dir)?;
+                if index >= hooks.hooks.len() {
+                    return Err(format!("Hook not found: {}", index));
+                }
+                hooks.hooks.remove(index);
+                hooks.save()?;
+                Ok(Box::new(Text::from("DONE")))
+            }
+            ("balance-at", Some(m)) => self.balance_at(m),
+            ("balance-snapshot", Some(m)) => self.balance_snapshot(m),
             ("db-metrics", _) => {
                 let metrcis = self.with_db(|db| db.get_metrics(None))?;
                 let resp = serde_json::to_value(metrcis).map_err(|err| err.to_string())?;
-                Ok(resp.render(format, color))
+                Ok(Box::new(resp))
+            }
+            ("rebroadcast-pending", _) => {
+                let genesis_hash: H256 = self.genesis_info()?.header().hash().unpack();
+                let mut pending_txs = PendingTxStore::load(&self.index_dir, &genesis_hash)?;
+                let results = pending_txs.rebroadcast(self.rpc_client)?;
+                let resp = serde_json::to_value(results).map_err(|err| err.to_string())?;
+                Ok(Box::new(resp))
+            }
+            ("db-watch-add", Some(m)) => {
+                let lock_hashes = self.watch_lock_hashes(m)?;
+                let mut watch_list =
+                    WatchList::load(&self.index_dir).map_err(|err| err.to_string())?;
+                for lock_hash in lock_hashes {
+                    watch_list.add(lock_hash);
+                }
+                watch_list.save().map_err(|err| err.to_string())?;
+                self.watch_list_info(&watch_list)
+            }
+            ("db-watch-remove", Some(m)) => {
+                let lock_hashes = self.watch_lock_hashes(m)?;
+                let mut watch_list =
+                    WatchList::load(&self.index_dir).map_err(|err| err.to_string())?;
+                for lock_hash in &lock_hashes {
+                    if !watch_list.remove(lock_hash) {
+                        return Err(format!("Lock hash is not watched: {:#x}", lock_hash));
+                    }
+                }
+                watch_list.save().map_err(|err| err.to_string())?;
+                self.watch_list_info(&watch_list)
+            }
+            ("db-watch-list", _) => {
+                let watch_list = WatchList::load(&self.index_dir).map_err(|err| err.to_string())?;
+                self.watch_list_info(&watch_list)
+            }
+            ("db-prune", Some(m)) => {
+                let keep_days: Option<u64> =
+                    FromStrParser::<u64>::default().from_matches_opt(m, "keep-days", false)?;
+                let keep_from_number = match keep_days {
+                    Some(days) => {
+                        let now = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .expect("Time went backwards")
+                            .as_millis() as u64;
+                        let since = now.saturating_sub(days * 24 * 3600 * 1000);
+                        Some(get_block_number_by_time(self.rpc_client, since)?)
+                    }
+                    None => None,
+                };
+                let genesis_hash: H256 = self.genesis_info()?.header().hash().unpack();
+                let db_dir = WatchList::load(&self.index_dir)
+                    .map(|watch_list| self.index_dir.join(watch_list.db_dir_name(&genesis_hash)))
+                    .map_err(|err| err.to_string())?;
+                let disk_size_before = dir_size(&db_dir)?;
+                let removed = self
+                    .with_db(|db| db.prune(keep_from_number))?
+                    .map_err(|err| err.to_string())?;
+                let disk_size_after = dir_size(&db_dir)?;
+                let resp = serde_json::json!({
+                    "keep_from_number": keep_from_number,
+                    "removed": removed,
+                    "disk_size_before": disk_size_before,
+                    "disk_size_after": disk_size_after,
+                });
+                Ok(Box::new(resp))
             }
             _ => Err(matches.usage().to_owned()),
         }
     }
 }
 
+// Serialized size (in block) of a secp256k1 transfer transaction, the
+// signature is a placeholder of the same size.
+// Address of the sender given by --privkey-path or --from-account
+fn sender_address(
+    from_privkey: Option<&PrivkeyWrapper>,
+    from_account: Option<&H160>,
+) -> Result<Address, String> {
+    if let Some(from_privkey) = from_privkey {
+        let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
+        let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
+        Address::from_lock_arg(&pubkey_hash[0..20])
+    } else {
+        Address::from_lock_arg(from_account.unwrap().as_bytes())
+    }
+}
+
 fn check_capacity(capacity: u64, to_data_len: usize) -> Result<(), String> {
     if capacity < *MIN_SECP_CELL_CAPACITY {
         return Err(format!(
@@ -736,6 +2005,36 @@ fn check_capacity(capacity: u64, to_data_len: usize) -> Result<(), String> {
     Ok(())
 }
 
+// Catch common mistakes (wrong unit, extra digits), skipped by `--force`
+fn check_transfer_safety(
+    capacity: u64,
+    tx_fee: u64,
+    balance: u64,
+    max_fee_ratio: f64,
+    max_balance_ratio: f64,
+) -> Result<(), String> {
+    if tx_fee as f64 > capacity as f64 * max_fee_ratio {
+        return Err(tr_fmt(
+            "Fee {} is more than {} of transfer capacity {}, use --force if it is intended",
+            &[&tx_fee, &max_fee_ratio, &capacity],
+        ));
+    }
+    if capacity + tx_fee == balance {
+        return Err(tr(
+            "Transfer spends the entire balance and leaves no change, use --force if it is intended",
+        )
+        .to_owned());
+    }
+    // Not enough balance is reported when collecting inputs
+    if balance > capacity + tx_fee && capacity as f64 > balance as f64 * max_balance_ratio {
+        return Err(tr_fmt(
+            "Transfer capacity {} is more than {} of balance {}, use --force if it is intended",
+            &[&capacity, &max_balance_ratio, &balance],
+        ));
+    }
+    Ok(())
+}
+
 fn is_live_cell(cell: &CellWithStatus) -> bool {
     if cell.status != "live" {
         eprintln!(
@@ -829,8 +2128,6 @@ fn build_dao_inputs(
 }
 
 fn build_dao_withdraw_hash(rpc_client: &mut HttpRpcClient) -> Result<H256, String> {
-    const DAO_MATURITY: u64 = 10;
-
     let tip_header: HeaderView = rpc_client
         .get_tip_header()
         .call()
@@ -0,0 +1,319 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_jsonrpc_types::{Status, Transaction};
+use ckb_sdk::HttpRpcClient;
+use ckb_types::{
+    core::{Capacity, TransactionView},
+    packed::{CellInput, OutPoint},
+    prelude::*,
+    H256,
+};
+use serde_derive::{Deserialize, Serialize};
+
+use super::WalletSubCommand;
+use crate::utils::i18n::tr_fmt;
+
+// Node's tx pool rejects transactions with too many in-pool ancestors, keep
+// the chain well below that.
+pub const MAX_CHAIN_DEPTH: usize = 16;
+
+/// A transaction sent by this wallet but not committed yet
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PendingTx {
+    pub tx_hash: H256,
+    pub lock_hash: H256,
+    // Consumed out points: (tx_hash, index)
+    pub inputs: Vec<(H256, u32)>,
+    // Plain outputs back to `lock_hash`: (index, capacity)
+    pub changes: Vec<(u32, u64)>,
+    // The signed transaction, to send again when the node lost it (missing
+    // in files of old versions)
+    #[serde(default)]
+    pub transaction: Option<Transaction>,
+}
+
+impl PendingTx {
+    pub fn new(transaction: &TransactionView, lock_hash: H256) -> PendingTx {
+        let inputs = transaction
+            .inputs()
+            .into_iter()
+            .map(|input| {
+                let out_point = input.previous_output();
+                (out_point.tx_hash().unpack(), out_point.index().unpack())
+            })
+            .collect();
+        // Only plain (no type, no data) outputs, spending a sUDT change as
+        // capacity would burn the tokens
+        let changes = transaction
+            .outputs()
+            .into_iter()
+            .zip(transaction.outputs_data().into_iter())
+            .enumerate()
+            .filter(|(_, (output, data))| {
+                let output_lock_hash: H256 = output.lock().calc_script_hash().unpack();
+                output_lock_hash == lock_hash
+                    && output.type_().to_opt().is_none()
+                    && data.raw_data().is_empty()
+            })
+            .map(|(index, (output, _))| {
+                let capacity: Capacity = output.capacity().unpack();
+                (index as u32, capacity.as_u64())
+            })
+            .collect();
+        PendingTx {
+            tx_hash: transaction.hash().unpack(),
+            lock_hash,
+            inputs,
+            changes,
+            transaction: Some(transaction.data().into()),
+        }
+    }
+}
+
+/// Status of a pending transaction after `PendingTxStore::rebroadcast`:
+/// committed, pending, rebroadcast or dropped
+#[derive(Serialize)]
+pub struct RebroadcastResult {
+    pub tx_hash: H256,
+    pub lock_hash: H256,
+    pub status: &'static str,
+    pub error: Option<String>,
+}
+
+impl RebroadcastResult {
+    fn new(tx: &PendingTx, status: &'static str, error: Option<&str>) -> RebroadcastResult {
+        RebroadcastResult {
+            tx_hash: tx.tx_hash.clone(),
+            lock_hash: tx.lock_hash.clone(),
+            status,
+            error: error.map(ToOwned::to_owned),
+        }
+    }
+}
+
+/// An unspent change output of a pending transaction
+pub struct PendingChange {
+    pub tx_hash: H256,
+    pub index: u32,
+    pub capacity: u64,
+    // Number of pending transactions in the chain, include the one creating this cell
+    pub depth: usize,
+}
+
+impl PendingChange {
+    pub fn input(&self) -> CellInput {
+        CellInput::new(OutPoint::new(self.tx_hash.clone().pack(), self.index), 0)
+    }
+}
+
+/// Pending transactions saved in `<index-dir>/pending-<genesis-hash>.json`
+pub struct PendingTxStore {
+    path: PathBuf,
+    txs: Vec<PendingTx>,
+}
+
+impl PendingTxStore {
+    pub fn load(index_dir: &Path, genesis_hash: &H256) -> Result<PendingTxStore, String> {
+        let path = index_dir.join(format!("pending-{:x}.json", genesis_hash));
+        let txs = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Invalid pending transactions file: {}", err))?
+        } else {
+            Vec::new()
+        };
+        Ok(PendingTxStore { path, txs })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(&self.txs).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+
+    /// Remove committed transactions, send again transactions no longer known
+    /// by the node (lost after the node restarted), remove the ones can not be
+    /// sent again (together with all transactions spending their outputs).
+    pub fn refresh(&mut self, rpc_client: &mut HttpRpcClient) -> Result<(), String> {
+        self.rebroadcast(rpc_client).map(|_| ())
+    }
+
+    /// Same as `refresh`, returns status of every pending transaction
+    pub fn rebroadcast(
+        &mut self,
+        rpc_client: &mut HttpRpcClient,
+    ) -> Result<Vec<RebroadcastResult>, String> {
+        let mut results = Vec::new();
+        let mut committed = HashSet::new();
+        let mut dropped = HashSet::new();
+        // Parents are always before children, so they are sent first
+        for tx in &self.txs {
+            if tx.inputs.iter().any(|(hash, _)| dropped.contains(hash)) {
+                // Descendants of a dropped transaction can never be committed
+                dropped.insert(tx.tx_hash.clone());
+                results.push(RebroadcastResult::new(
+                    tx,
+                    "dropped",
+                    Some("parent dropped"),
+                ));
+                continue;
+            }
+            let status = rpc_client
+                .get_transaction(tx.tx_hash.clone())
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .map(|tx_with_status| tx_with_status.tx_status.status);
+            match status {
+                Some(Status::Committed) => {
+                    committed.insert(tx.tx_hash.clone());
+                    results.push(RebroadcastResult::new(tx, "committed", None));
+                }
+                Some(_) => results.push(RebroadcastResult::new(tx, "pending", None)),
+                None => {
+                    let result = match tx.transaction.clone() {
+                        Some(transaction) => rpc_client
+                            .send_transaction(transaction)
+                            .call()
+                            .map(|_| ())
+                            .map_err(|err| err.to_string()),
+                        None => Err("transaction not saved".to_owned()),
+                    };
+                    match result {
+                        Ok(()) => {
+                            eprintln!(
+                                "[INFO]: pending transaction {:#x} is missing in node, sent again",
+                                tx.tx_hash
+                            );
+                            results.push(RebroadcastResult::new(tx, "rebroadcast", None));
+                        }
+                        Err(err) => {
+                            eprintln!(
+                                "[WARNING]: pending transaction {:#x} is dropped by node: {}",
+                                tx.tx_hash, err
+                            );
+                            dropped.insert(tx.tx_hash.clone());
+                            results.push(RebroadcastResult::new(tx, "dropped", Some(&err)));
+                        }
+                    }
+                }
+            }
+        }
+        let len = self.txs.len();
+        self.txs
+            .retain(|tx| !committed.contains(&tx.tx_hash) && !dropped.contains(&tx.tx_hash));
+        if self.txs.len() != len {
+            self.save()?;
+        }
+        Ok(results)
+    }
+
+    pub fn contains(&self, tx_hash: &H256) -> bool {
+        self.txs.iter().any(|tx| &tx.tx_hash == tx_hash)
+    }
+
+    pub fn add(&mut self, tx: PendingTx) {
+        self.txs.push(tx);
+    }
+
+    /// Out points consumed by pending transactions, must not be used as inputs again
+    pub fn spent_out_points(&self) -> HashSet<(H256, u32)> {
+        self.txs
+            .iter()
+            .flat_map(|tx| tx.inputs.iter().cloned())
+            .collect()
+    }
+
+    /// Inputs of the transaction already consumed by other pending
+    /// transactions: (out point, pending tx hash)
+    pub fn conflicts(&self, transaction: &TransactionView) -> Vec<((H256, u32), H256)> {
+        let tx_hash: H256 = transaction.hash().unpack();
+        transaction
+            .inputs()
+            .into_iter()
+            .filter_map(|input| {
+                let out_point = input.previous_output();
+                let out_point: (H256, u32) =
+                    (out_point.tx_hash().unpack(), out_point.index().unpack());
+                self.txs
+                    .iter()
+                    .find(|tx| tx.tx_hash != tx_hash && tx.inputs.contains(&out_point))
+                    .map(|tx| (out_point, tx.tx_hash.clone()))
+            })
+            .collect()
+    }
+
+    /// Unspent change outputs of pending transactions owned by `lock_hash`,
+    /// skip those already at max chain depth.
+    pub fn available_changes(&self, lock_hash: &H256) -> Vec<PendingChange> {
+        let spent = self.spent_out_points();
+        let mut depths: HashMap<H256, usize> = HashMap::default();
+        // Parents are always added before children
+        for tx in &self.txs {
+            let parent_depth = tx
+                .inputs
+                .iter()
+                .filter_map(|(hash, _)| depths.get(hash))
+                .max()
+                .cloned()
+                .unwrap_or(0);
+            depths.insert(tx.tx_hash.clone(), parent_depth + 1);
+        }
+        self.txs
+            .iter()
+            .filter(|tx| &tx.lock_hash == lock_hash && depths[&tx.tx_hash] < MAX_CHAIN_DEPTH)
+            .flat_map(|tx| {
+                let depth = depths[&tx.tx_hash];
+                tx.changes
+                    .iter()
+                    .map(move |(index, capacity)| PendingChange {
+                        tx_hash: tx.tx_hash.clone(),
+                        index: *index,
+                        capacity: *capacity,
+                        depth,
+                    })
+            })
+            .filter(|change| !spent.contains(&(change.tx_hash.clone(), change.index)))
+            .collect()
+    }
+}
+
+impl<'a> WalletSubCommand<'a> {
+    // Inputs already spent by transactions this wallet sent but not yet
+    // committed would be rejected by the node as a double spend, report the
+    // conflicting transaction instead. Conflicts the node no longer knows
+    // (dropped) are ignored.
+    pub(super) fn check_conflicts(&mut self, transaction: &TransactionView) -> Result<(), String> {
+        let genesis_hash: H256 = self.genesis_info()?.header().hash().unpack();
+        let pending_txs = PendingTxStore::load(&self.index_dir, &genesis_hash)?;
+        let mut errors = Vec::new();
+        for ((tx_hash, index), conflict_hash) in pending_txs.conflicts(transaction) {
+            let status = self
+                .rpc_client
+                .get_transaction(conflict_hash.clone())
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .map(|tx_with_status| tx_with_status.tx_status.status);
+            if let Some(status) = status {
+                errors.push(tr_fmt(
+                    "input {} is already spent by transaction {} ({})",
+                    &[
+                        &format!("{:#x}-{}", tx_hash, index),
+                        &format!("{:#x}", conflict_hash),
+                        &format!("{:?}", status),
+                    ],
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(tr_fmt("Conflicting transaction: {}", &[&errors.join(", ")]))
+        }
+    }
+}
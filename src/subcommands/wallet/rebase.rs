@@ -0,0 +1,161 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_index::{with_index_db, IndexDatabase, LiveCellInfo};
+use ckb_jsonrpc_types::{CellWithStatus, Transaction};
+use ckb_sdk::{Address, MIN_SECP_CELL_CAPACITY};
+use ckb_types::{bytes::Bytes, core::Capacity, packed, prelude::*, H256};
+use clap::ArgMatches;
+
+use super::pending::PendingTxStore;
+use super::{is_live_cell, is_secp_cell, WalletSubCommand};
+use crate::utils::{
+    arg_parser::{ArgParser, CapacityParser, FilePathParser},
+    i18n::tr,
+    other::get_network_type,
+    printer::Printable,
+};
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn rebase_tx(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let from_account = self.from_account(m, false)?.unwrap();
+        let tx_file: PathBuf = FilePathParser::new(true).from_matches(m, "tx-file")?;
+        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let content = fs::read_to_string(&tx_file).map_err(|err| err.to_string())?;
+        let tx: Transaction = serde_json::from_str(&content)
+            .map_err(|err| format!("Invalid transaction file: {}", err))?;
+        let tx = packed::Transaction::from(tx).into_view();
+        let from_address = Address::from_lock_arg(from_account.as_bytes())?;
+
+        let mut stale_inputs = Vec::new();
+        for input in tx.inputs().into_iter() {
+            let out_point = input.previous_output();
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(out_point.clone().into(), false)
+                .call()
+                .map_err(|err| err.to_string())?;
+            if resp.status != "live" {
+                stale_inputs.push(ckb_jsonrpc_types::OutPoint::from(out_point));
+            }
+        }
+        if stale_inputs.is_empty() {
+            let resp = serde_json::json!({
+                "stale_inputs": stale_inputs,
+                "rebased": false,
+            });
+            return Ok(Box::new(resp));
+        }
+
+        let network_type = get_network_type(self.rpc_client)?;
+        let genesis_info = self.genesis_info()?;
+        let secp_type_hash = genesis_info.secp_type_hash();
+        let from_lock_hash = from_address
+            .lock_script(secp_type_hash.clone())
+            .calc_script_hash();
+        // Plain cells back to the account are change, will be recomputed
+        let outputs = tx
+            .outputs()
+            .into_iter()
+            .zip(tx.outputs_data().into_iter())
+            .filter(|(output, data)| {
+                output.lock().calc_script_hash() != from_lock_hash
+                    || output.type_().to_opt().is_some()
+                    || !data.raw_data().is_empty()
+            })
+            .collect::<Vec<_>>();
+        let outputs_capacity = outputs
+            .iter()
+            .map(|(output, _)| {
+                let capacity: Capacity = output.capacity().unpack();
+                capacity.as_u64()
+            })
+            .sum::<u64>();
+        let required_capacity = outputs_capacity + tx_fee;
+
+        // For check index database is ready
+        self.with_db(|_| ())?;
+        let index_dir = self.index_dir.clone();
+        let min_confirmations = self.min_confirmations;
+        let genesis_hash = genesis_info.header().hash();
+        let genesis_info_clone = genesis_info.clone();
+        let mut pending_txs = PendingTxStore::load(&index_dir, &genesis_hash.unpack())?;
+        pending_txs.refresh(self.rpc_client)?;
+        let pending_spent = pending_txs.spent_out_points();
+        let mut total_capacity = 0;
+        let terminator = |_, info: &LiveCellInfo| {
+            if pending_spent.contains(&(info.tx_hash.clone(), info.tx_index)) {
+                return (false, false);
+            }
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(info.out_point().into(), true)
+                .call()
+                .expect("get_live_cell by RPC call failed");
+            if is_live_cell(&resp) && is_secp_cell(&resp) {
+                total_capacity += info.capacity;
+                (total_capacity >= required_capacity, true)
+            } else {
+                (false, false)
+            }
+        };
+        let infos: Vec<LiveCellInfo> =
+            with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
+                let mut db =
+                    IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
+                db.set_min_confirmations(min_confirmations);
+                Ok(db.get_live_cells_by_lock(from_lock_hash.clone(), None, terminator))
+            })
+            .map_err(|_err| {
+                format!(
+                    "{}: {}",
+                    tr("index database may not ready, sync process"),
+                    self.index_controller.state().read().to_string()
+                )
+            })?;
+        if total_capacity < required_capacity {
+            return Err(format!(
+                "{}: {} => {}",
+                tr("Capacity not enough"),
+                from_address.to_string(network_type),
+                total_capacity,
+            ));
+        }
+
+        let inputs = infos.iter().map(LiveCellInfo::input).collect::<Vec<_>>();
+        let (mut outputs, mut outputs_data): (Vec<_>, Vec<_>) = outputs.into_iter().unzip();
+        let rest_capacity = total_capacity - required_capacity;
+        if rest_capacity >= *MIN_SECP_CELL_CAPACITY {
+            let change = packed::CellOutput::new_builder()
+                .capacity(Capacity::shannons(rest_capacity).pack())
+                .lock(from_address.lock_script(secp_type_hash.clone()))
+                .build();
+            outputs.push(change);
+            outputs_data.push(Bytes::new().pack());
+        }
+        let witnesses = inputs
+            .iter()
+            .map(|_| Bytes::new().pack())
+            .collect::<Vec<_>>();
+        let new_tx = tx
+            .as_advanced_builder()
+            .set_inputs(inputs)
+            .set_outputs(outputs)
+            .set_outputs_data(outputs_data)
+            .set_witnesses(witnesses)
+            .build();
+        let content = serde_json::to_string_pretty(&Transaction::from(new_tx.data()))
+            .map_err(|err| err.to_string())?;
+        fs::write(&tx_file, content).map_err(|err| err.to_string())?;
+
+        let tx_hash: H256 = new_tx.hash().unpack();
+        let resp = serde_json::json!({
+            "stale_inputs": stale_inputs,
+            "rebased": true,
+            "tx_hash": tx_hash,
+            "inputs": new_tx.inputs().len(),
+            "fee": tx_fee + if rest_capacity >= *MIN_SECP_CELL_CAPACITY { 0 } else { rest_capacity },
+        });
+        Ok(Box::new(resp))
+    }
+}
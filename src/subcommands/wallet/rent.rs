@@ -0,0 +1,222 @@
+use std::collections::{HashMap, HashSet};
+
+use ckb_index::LiveCellInfo;
+use ckb_jsonrpc_types::{BlockNumber, CellWithStatus};
+use ckb_types::{
+    core::{BlockView, Capacity, DepType, HeaderView as CoreHeaderView},
+    packed::{self, OutPoint, OutPointVec},
+    prelude::*,
+    H256,
+};
+use clap::ArgMatches;
+
+use super::WalletSubCommand;
+use crate::utils::{
+    arg_parser::{ArgParser, FromStrParser},
+    printer::Printable,
+};
+
+const YEAR_MILLIS: f64 = 365.0 * 24.0 * 3600.0 * 1000.0;
+
+/// Accumulated rate (AR) of NervosDAO from the `dao` field of a header
+/// See: https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0023-dao-deposit-withdraw/0023-dao-deposit-withdraw.md
+pub fn dao_accumulated_rate(dao: &[u8]) -> Option<u64> {
+    if dao.len() != 32 {
+        return None;
+    }
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&dao[8..16]);
+    Some(u64::from_le_bytes(buf))
+}
+
+/// Annualized NervosDAO compensation rate from the growth of AR over a period
+pub fn annual_dao_rate(start_ar: u64, end_ar: u64, duration_ms: u64) -> f64 {
+    if start_ar == 0 || duration_ms == 0 {
+        return 0.0;
+    }
+    let growth = end_ar as f64 / start_ar as f64;
+    growth.powf(YEAR_MILLIS / duration_ms as f64) - 1.0
+}
+
+/// Out points in a dep group cell, None if the data is not a dep group
+pub fn parse_dep_group(data: &[u8]) -> Option<Vec<OutPoint>> {
+    OutPointVec::from_slice(data)
+        .ok()
+        .filter(|out_points| !out_points.is_empty())
+        .map(|out_points| out_points.into_iter().collect())
+}
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn state_rent(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let lock_hash = self.query_lock_hash(m)?;
+        let scan_blocks: u64 = FromStrParser::<u64>::default().from_matches(m, "scan-blocks")?;
+
+        let infos = self.with_db(|db| {
+            db.get_live_cells_by_lock(lock_hash, None, |_, info: &LiveCellInfo| {
+                (false, info.data_bytes > 0)
+            })
+        })?;
+        // (info, occupied capacity, type hash, dep group members)
+        let mut cells = Vec::new();
+        for info in infos {
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(info.out_point().into(), true)
+                .call()
+                .map_err(|err| err.to_string())?;
+            let cell = match resp.cell {
+                Some(cell) => cell,
+                None => continue,
+            };
+            let data = cell
+                .data
+                .map(|data| data.content.into_bytes())
+                .unwrap_or_default();
+            let output: packed::CellOutput = cell.output.into();
+            let occupied: u64 = output
+                .occupied_capacity(Capacity::bytes(data.len()).map_err(|err| err.to_string())?)
+                .map_err(|err| err.to_string())?
+                .as_u64();
+            let type_hash: Option<H256> = output
+                .type_()
+                .to_opt()
+                .map(|script| script.calc_script_hash().unpack());
+            cells.push((info, occupied, type_hash, parse_dep_group(&data)));
+        }
+
+        let tip: CoreHeaderView = self
+            .rpc_client
+            .get_tip_header()
+            .call()
+            .map_err(|err| err.to_string())?
+            .into();
+        let from_number = tip.number().saturating_sub(scan_blocks) + 1;
+        let start: CoreHeaderView = self
+            .rpc_client
+            .get_header_by_number(BlockNumber::from(from_number - 1))
+            .call()
+            .map_err(|err| err.to_string())?
+            .0
+            .ok_or_else(|| format!("Header not found: {}", from_number - 1))?
+            .into();
+        let dao_rate = match (
+            dao_accumulated_rate(&start.dao().raw_data()),
+            dao_accumulated_rate(&tip.dao().raw_data()),
+        ) {
+            (Some(start_ar), Some(end_ar)) => annual_dao_rate(
+                start_ar,
+                end_ar,
+                tip.timestamp().saturating_sub(start.timestamp()),
+            ),
+            _ => 0.0,
+        };
+
+        // Cell deps of recent blocks, dep groups are expanded
+        let mut dep_groups: HashMap<OutPoint, Vec<OutPoint>> = cells
+            .iter()
+            .filter_map(|(info, _, _, members)| {
+                members.clone().map(|members| (info.out_point(), members))
+            })
+            .collect();
+        let mut referenced: HashSet<OutPoint> = HashSet::new();
+        for number in from_number..=tip.number() {
+            let block: BlockView = self
+                .rpc_client
+                .get_block_by_number(BlockNumber::from(number))
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .ok_or_else(|| format!("Block not found: {}", number))?
+                .into();
+            for tx in block.transactions() {
+                for cell_dep in tx.cell_deps().into_iter() {
+                    let out_point = cell_dep.out_point();
+                    if cell_dep.dep_type() == DepType::DepGroup.into() {
+                        if !dep_groups.contains_key(&out_point) {
+                            let resp: CellWithStatus = self
+                                .rpc_client
+                                .get_live_cell(out_point.clone().into(), true)
+                                .call()
+                                .map_err(|err| err.to_string())?;
+                            let members = resp
+                                .cell
+                                .and_then(|cell| cell.data)
+                                .and_then(|data| parse_dep_group(&data.content.into_bytes()))
+                                .unwrap_or_default();
+                            dep_groups.insert(out_point.clone(), members);
+                        }
+                        referenced.extend(dep_groups[&out_point].iter().cloned());
+                    }
+                    referenced.insert(out_point);
+                }
+            }
+        }
+
+        let mut total_capacity = 0;
+        let mut total_occupied = 0;
+        let mut reclaimable_capacity = 0;
+        let cells_json = cells
+            .into_iter()
+            .map(|(info, occupied, type_hash, members)| {
+                let is_referenced = referenced.contains(&info.out_point());
+                total_capacity += info.capacity;
+                total_occupied += occupied;
+                if !is_referenced {
+                    reclaimable_capacity += info.capacity;
+                }
+                serde_json::json!({
+                    "tx_hash": info.tx_hash,
+                    "index": info.tx_index,
+                    "kind": if members.is_some() { "dep_group" } else { "code" },
+                    "capacity": info.capacity,
+                    "occupied_capacity": occupied,
+                    "data_bytes": info.data_bytes,
+                    "type_hash": type_hash,
+                    "referenced": is_referenced,
+                })
+            })
+            .collect::<Vec<_>>();
+        let resp = serde_json::json!({
+            "cells": cells_json,
+            "total_capacity": total_capacity,
+            "total_occupied_capacity": total_occupied,
+            "dao_annual_rate": format!("{:.4}%", dao_rate * 100.0),
+            "annual_opportunity_cost": (total_capacity as f64 * dao_rate) as u64,
+            "reclaimable_capacity": reclaimable_capacity,
+            "scanned_blocks": {
+                "from": from_number,
+                "to": tip.number(),
+            },
+        });
+        Ok(Box::new(resp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_rent() {
+        let mut dao = [0u8; 32];
+        dao[8..16].copy_from_slice(&10_000_000_000_000_000u64.to_le_bytes());
+        assert_eq!(dao_accumulated_rate(&dao), Some(10_000_000_000_000_000));
+        assert_eq!(dao_accumulated_rate(&dao[..16]), None);
+
+        // 1% in half a year is about 2.01% a year
+        let rate = annual_dao_rate(1_000_000, 1_010_000, (YEAR_MILLIS / 2.0) as u64);
+        assert!((rate - 0.0201).abs() < 1e-6);
+        assert_eq!(annual_dao_rate(0, 1, 1), 0.0);
+
+        let out_point = OutPoint::new(Default::default(), 1);
+        let group = OutPointVec::new_builder()
+            .push(out_point.clone())
+            .push(out_point.clone())
+            .build();
+        assert_eq!(
+            parse_dep_group(group.as_slice()),
+            Some(vec![out_point.clone(), out_point])
+        );
+        assert_eq!(parse_dep_group(&[1, 2, 3]), None);
+    }
+}
@@ -0,0 +1,222 @@
+use ckb_jsonrpc_types::CellWithStatus;
+use ckb_sdk::{build_witness_with_key, TransferTransactionBuilder, MIN_SECP_CELL_CAPACITY};
+use ckb_types::{
+    bytes::Bytes,
+    core::Capacity,
+    packed::{self, CellInput},
+    prelude::*,
+    H256,
+};
+use clap::ArgMatches;
+
+use super::pending::{PendingTx, PendingTxStore};
+use super::{is_live_cell, sender_address, WalletSubCommand};
+use crate::utils::{
+    arg_parser::{
+        ArgParser, CapacityParser, FromStrParser, OutPointParser, PrivkeyPathParser, PrivkeyWrapper,
+    },
+    i18n::tr,
+    other::{get_network_type, read_password},
+    printer::Printable,
+};
+
+/// Capacity of each cell when `total` (after paying `tx_fee`) is split into
+/// `count` equal cells, the remainder goes to the first cell. Every cell must
+/// hold at least `min_capacity`.
+pub fn split_capacity(
+    total: u64,
+    tx_fee: u64,
+    count: u64,
+    min_capacity: u64,
+) -> Result<Vec<u64>, String> {
+    if count < 2 {
+        return Err("<count> must be at least 2".to_owned());
+    }
+    let rest = total
+        .checked_sub(tx_fee)
+        .ok_or_else(|| format!("Capacity {} is not enough to pay fee {}", total, tx_fee))?;
+    let each = rest / count;
+    if each < min_capacity {
+        return Err(format!(
+            "Each cell would have {} shannons, less than the minimal capacity {}, use a smaller count (max: {})",
+            each,
+            min_capacity,
+            rest / min_capacity
+        ));
+    }
+    let mut capacities = vec![each; count as usize];
+    capacities[0] += rest % count;
+    Ok(capacities)
+}
+
+impl<'a> WalletSubCommand<'a> {
+    // split-cell (split = true) and merge-cells
+    pub fn reshape_cells(
+        &mut self,
+        m: &ArgMatches,
+        split: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account = self.from_account(m, from_privkey.is_some())?;
+        let from_address = sender_address(from_privkey.as_ref(), from_account.as_ref())?;
+        let out_points: Vec<packed::OutPoint> = OutPointParser.from_matches_vec(m, "out-point")?;
+        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        if !split && out_points.len() < 2 {
+            return Err("At least 2 cells are required to merge".to_owned());
+        }
+        let network_type = get_network_type(self.rpc_client)?;
+        let genesis_info = self.genesis_info()?;
+        let from_lock = from_address.lock_script(genesis_info.secp_type_hash().clone());
+        let from_lock_hash: H256 = from_lock.calc_script_hash().unpack();
+
+        let genesis_hash: H256 = genesis_info.header().hash().unpack();
+        let mut pending_txs = PendingTxStore::load(&self.index_dir, &genesis_hash)?;
+        pending_txs.refresh(self.rpc_client)?;
+        pending_txs.save()?;
+        let pending_spent = pending_txs.spent_out_points();
+
+        let mut inputs = Vec::new();
+        let mut inputs_capacity = 0;
+        for out_point in &out_points {
+            let tx_hash: H256 = out_point.tx_hash().unpack();
+            let index: u32 = out_point.index().unpack();
+            let name = format!("{:#x}-{}", tx_hash, index);
+            if inputs
+                .iter()
+                .any(|input: &CellInput| input.previous_output().as_slice() == out_point.as_slice())
+            {
+                return Err(format!("Duplicated cell: {}", name));
+            }
+            if pending_spent.contains(&(tx_hash, index)) {
+                return Err(format!("Cell {} is spent by a pending transaction", name));
+            }
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(out_point.clone().into(), true)
+                .call()
+                .map_err(|err| err.to_string())?;
+            if !is_live_cell(&resp) {
+                return Err(format!("Cell {} is not live", name));
+            }
+            let cell = resp.cell.unwrap();
+            let output: packed::CellOutput = cell.output.into();
+            if output.lock().as_slice() != from_lock.as_slice() {
+                return Err(format!("Cell {} is not owned by the account", name));
+            }
+            let has_data = cell
+                .data
+                .map(|data| !data.content.as_bytes().is_empty())
+                .unwrap_or(false);
+            if output.type_().to_opt().is_some() || has_data {
+                return Err(format!(
+                    "Cell {} is not a plain cell (has type script or data)",
+                    name
+                ));
+            }
+            let capacity: Capacity = output.capacity().unpack();
+            inputs_capacity += capacity.as_u64();
+            inputs.push(CellInput::new(out_point.clone(), 0));
+        }
+
+        let capacities = if split {
+            let count: u64 = FromStrParser::<u64>::default().from_matches(m, "count")?;
+            split_capacity(inputs_capacity, tx_fee, count, *MIN_SECP_CELL_CAPACITY)?
+        } else {
+            vec![inputs_capacity
+                .checked_sub(tx_fee)
+                .filter(|capacity| *capacity >= *MIN_SECP_CELL_CAPACITY)
+                .ok_or_else(|| {
+                    format!(
+                        "Capacity {} is not enough to pay fee {} and hold a cell",
+                        inputs_capacity, tx_fee
+                    )
+                })?]
+        };
+        let outputs = capacities
+            .iter()
+            .map(|capacity| {
+                let output = packed::CellOutput::new_builder()
+                    .capacity(Capacity::shannons(*capacity).pack())
+                    .lock(from_lock.clone())
+                    .build();
+                (output, Bytes::new())
+            })
+            .collect::<Vec<_>>();
+        let mut resp = serde_json::json!({
+            "from": from_address.to_string(network_type),
+            "inputs": out_points
+                .iter()
+                .map(|out_point| {
+                    let tx_hash: H256 = out_point.tx_hash().unpack();
+                    let index: u32 = out_point.index().unpack();
+                    format!("{:#x}-{}", tx_hash, index)
+                })
+                .collect::<Vec<_>>(),
+            "capacity": inputs_capacity,
+            "outputs": capacities,
+            "fee": tx_fee,
+        });
+        if let Some(feed) = self.price_feed.as_ref() {
+            resp["fiat"] = feed.fiat_values(&[("capacity", inputs_capacity), ("fee", tx_fee)]);
+        }
+        if !m.is_present("execute") {
+            return Ok(Box::new(resp));
+        }
+
+        let password = if m.is_present("with-password") {
+            Some(read_password(false, None)?)
+        } else {
+            None
+        };
+        let no_data = Bytes::new();
+        let mut tx_args = TransferTransactionBuilder::new(
+            &from_address,
+            inputs_capacity,
+            &no_data,
+            // Not used, replaced by the outputs
+            &from_address,
+            inputs_capacity - tx_fee,
+            tx_fee,
+            inputs,
+        );
+        tx_args.set_outputs(outputs);
+        let transaction = if let Some(privkey) = from_privkey.as_ref() {
+            tx_args.transfer(&genesis_info, |args| {
+                Ok(build_witness_with_key(privkey, args))
+            })
+        } else {
+            let lock_arg = from_account.as_ref().unwrap();
+            tx_args.transfer(&genesis_info, |args| {
+                self.build_witness_with_keystore(lock_arg, args, &password)
+            })
+        }?;
+        let tx_hash = self
+            .rpc_client
+            .send_transaction(transaction.data().into())
+            .call()
+            .map_err(|err| format!("{}: {}", tr("Send transaction error"), err))?;
+        pending_txs.add(PendingTx::new(&transaction, from_lock_hash));
+        pending_txs.save()?;
+        resp["tx_hash"] = serde_json::json!(tx_hash);
+        Ok(Box::new(resp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_capacity() {
+        assert_eq!(
+            split_capacity(1000, 1, 3, 100).unwrap(),
+            vec![333, 333, 333]
+        );
+        assert_eq!(split_capacity(1001, 0, 2, 100).unwrap(), vec![501, 500]);
+        // Each cell 99
+        assert!(split_capacity(1000, 10, 10, 100).is_err());
+        assert!(split_capacity(1000, 1001, 2, 1).is_err());
+        assert!(split_capacity(1000, 0, 1, 1).is_err());
+    }
+}
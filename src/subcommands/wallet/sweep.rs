@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_hash::blake2b_256;
+use ckb_index::{with_index_db, IndexDatabase, LiveCellInfo};
+use ckb_jsonrpc_types::{CellWithStatus, Transaction};
+use ckb_sdk::{
+    build_witness_with_key,
+    wallet::{Crypto, ScryptType},
+    Address, TransferTransactionBuilder, MIN_SECP_CELL_CAPACITY, SECP256K1,
+};
+use ckb_types::{
+    bytes::Bytes,
+    core::{EpochNumberWithFraction, HeaderView as CoreHeaderView},
+    packed::{self, OutPoint},
+    prelude::*,
+    H256,
+};
+use clap::ArgMatches;
+
+use super::{is_live_cell, is_secp_cell, WalletSubCommand};
+use crate::utils::{
+    arg_parser::{
+        AddressParser, ArgParser, CapacityParser, FilePathParser, FixedHashParser, FromStrParser,
+        PrivkeyPathParser, PrivkeyWrapper,
+    },
+    i18n::tr,
+    other::{check_address_prefix, get_network_type, read_password, write_private_file},
+    printer::Printable,
+};
+
+// See: https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0017-tx-valid-since/0017-tx-valid-since.md
+const SINCE_ABSOLUTE_EPOCH_FLAG: u64 = 0x2000_0000_0000_0000;
+const SINCE_ABSOLUTE_TIMESTAMP_FLAG: u64 = 0x4000_0000_0000_0000;
+const SWEEP_FILE_VERSION: u64 = 1;
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn presign_sweep(&mut self, m: &ArgMatches) -> Result<Box<dyn Printable>, String> {
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account = self.from_account(m, from_privkey.is_some())?;
+        let recovery_address: Address = AddressParser.from_matches(m, "recovery-address")?;
+        let unlock_epoch: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "unlock-epoch", false)?;
+        let unlock_timestamp: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "unlock-timestamp", false)?;
+        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let output: PathBuf = FilePathParser::new(false).from_matches(m, "output")?;
+        let with_password = m.is_present("with-password");
+        let force = m.is_present("force");
+        if output.exists() && !force {
+            return Err(format!(
+                "Output file exists: {:?} (use --force to overwrite)",
+                output
+            ));
+        }
+        let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
+            let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
+            let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
+            Address::from_lock_arg(&pubkey_hash[0..20])?
+        } else {
+            Address::from_lock_arg(from_account.as_ref().unwrap().as_bytes())?
+        };
+
+        // The unlock point must be in the future, or it is just a normal transfer
+        let tip_header: CoreHeaderView = self
+            .rpc_client
+            .get_tip_header()
+            .call()
+            .map_err(|err| format!("Send get_tip_header error: {}", err))?
+            .into();
+        let since = match (unlock_epoch, unlock_timestamp) {
+            (Some(epoch), None) => {
+                if epoch <= tip_header.epoch().number() {
+                    return Err(format!(
+                        "Unlock epoch must be greater than current epoch {}",
+                        tip_header.epoch().number()
+                    ));
+                }
+                SINCE_ABSOLUTE_EPOCH_FLAG | EpochNumberWithFraction::new(epoch, 0, 1).full_value()
+            }
+            (None, Some(timestamp)) => {
+                if timestamp <= tip_header.timestamp() / 1000 {
+                    return Err("Unlock timestamp must be in the future".to_owned());
+                }
+                if timestamp >= 1 << 56 {
+                    return Err(format!("Unlock timestamp too large: {}", timestamp));
+                }
+                SINCE_ABSOLUTE_TIMESTAMP_FLAG | timestamp
+            }
+            _ => return Err("<unlock-epoch> or <unlock-timestamp> is required".to_owned()),
+        };
+
+        let network_type = get_network_type(self.rpc_client)?;
+        let genesis_info = self.genesis_info()?;
+        let secp_type_hash = genesis_info.secp_type_hash();
+        check_address_prefix(m.value_of("recovery-address").unwrap(), network_type)?;
+        // For check index database is ready
+        self.with_db(|_| ())?;
+        let index_dir = self.index_dir.clone();
+        let min_confirmations = self.min_confirmations;
+        let genesis_hash = genesis_info.header().hash();
+        let genesis_info_clone = genesis_info.clone();
+        let lock_hash = from_address
+            .lock_script(secp_type_hash.clone())
+            .calc_script_hash();
+        let mut total_capacity = 0;
+        let terminator = |_, info: &LiveCellInfo| {
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(info.out_point().into(), true)
+                .call()
+                .expect("get_live_cell by RPC call failed");
+            if is_live_cell(&resp) && is_secp_cell(&resp) {
+                total_capacity += info.capacity;
+                (false, true)
+            } else {
+                (false, false)
+            }
+        };
+        let infos: Vec<LiveCellInfo> =
+            with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
+                let mut db =
+                    IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
+                db.set_min_confirmations(min_confirmations);
+                Ok(db.get_live_cells_by_lock(lock_hash.clone(), None, terminator))
+            })
+            .map_err(|_err| {
+                format!(
+                    "{}: {}",
+                    tr("index database may not ready, sync process"),
+                    self.index_controller.state().read().to_string()
+                )
+            })?;
+        if total_capacity < tx_fee + *MIN_SECP_CELL_CAPACITY {
+            return Err(format!(
+                "{}: {} => {}",
+                tr("Capacity not enough"),
+                from_address.to_string(network_type),
+                total_capacity,
+            ));
+        }
+
+        let inputs = infos
+            .iter()
+            .map(|info| {
+                LiveCellInfo::input(info)
+                    .as_builder()
+                    .since(since.pack())
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let to_data = Bytes::default();
+        let mut tx_args = TransferTransactionBuilder::new(
+            &from_address,
+            total_capacity,
+            &to_data,
+            &recovery_address,
+            total_capacity - tx_fee,
+            tx_fee,
+            inputs,
+        );
+        let transaction = if let Some(privkey) = from_privkey.as_ref() {
+            tx_args.transfer(&genesis_info, |args| {
+                Ok(build_witness_with_key(privkey, args))
+            })
+        } else {
+            let lock_arg = from_account.as_ref().unwrap();
+            let password = if with_password {
+                Some(read_password(false, None)?)
+            } else {
+                None
+            };
+            tx_args.transfer(&genesis_info, |args| {
+                self.build_witness_with_keystore(lock_arg, args, &password)
+            })
+        }?;
+
+        let password = read_password(true, Some(tr("Password to encrypt the sweep transaction")))?;
+        let tx: Transaction = transaction.data().into();
+        let plaintext = serde_json::to_vec(&tx).map_err(|err| err.to_string())?;
+        let crypto =
+            Crypto::encrypt_key_scrypt(&plaintext, password.as_bytes(), ScryptType::default());
+        let tx_hash: H256 = transaction.hash().unpack();
+        let lock_hash: H256 = lock_hash.unpack();
+        // Inputs are saved in plain text, so they can be checked without the password
+        let inputs = infos
+            .iter()
+            .map(|info| {
+                serde_json::json!({
+                    "tx_hash": info.tx_hash,
+                    "index": info.tx_index,
+                })
+            })
+            .collect::<Vec<_>>();
+        let content = serde_json::json!({
+            "version": SWEEP_FILE_VERSION,
+            "tx_hash": tx_hash,
+            "lock_hash": lock_hash,
+            "recovery_address": recovery_address.to_string(network_type),
+            "since": since,
+            "capacity": total_capacity,
+            "inputs": inputs,
+            "crypto": crypto.to_json(),
+        });
+        let content = serde_json::to_string_pretty(&content).map_err(|err| err.to_string())?;
+        write_private_file(&output, content.as_bytes(), force)?;
+        let resp = serde_json::json!({
+            "output": output.to_string_lossy(),
+            "tx_hash": tx_hash,
+            "inputs": infos.len(),
+            "capacity": total_capacity,
+            "since": since,
+            "notice": "Re-generate the sweep transaction after any input is spent or new capacity is received",
+        });
+        Ok(Box::new(resp))
+    }
+
+    pub fn emergency_broadcast(
+        &mut self,
+        m: &ArgMatches,
+        debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        let path: PathBuf = FilePathParser::new(true).from_matches(m, "sweep-path")?;
+        let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+        let sweep: serde_json::Value =
+            serde_json::from_str(&content).map_err(|err| format!("Invalid sweep file: {}", err))?;
+        if sweep["version"].as_u64() != Some(SWEEP_FILE_VERSION) {
+            return Err(format!(
+                "Unsupported sweep file version: {}",
+                sweep["version"]
+            ));
+        }
+        let inputs = sweep["inputs"]
+            .as_array()
+            .ok_or_else(|| "Invalid sweep file: inputs missing".to_owned())?;
+        for input in inputs {
+            let tx_hash: H256 = FixedHashParser::<H256>::default()
+                .parse(input["tx_hash"].as_str().unwrap_or(""))?;
+            let index = input["index"]
+                .as_u64()
+                .ok_or_else(|| "Invalid sweep file: input index missing".to_owned())?;
+            let out_point = OutPoint::new(tx_hash.pack(), index as u32);
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(out_point.into(), false)
+                .call()
+                .map_err(|err| err.to_string())?;
+            if resp.status != "live" {
+                return Err(format!(
+                    "Input {:#x}-{} is {}, the sweep transaction must be re-generated by `wallet presign-sweep`",
+                    tx_hash, index, resp.status
+                ));
+            }
+        }
+
+        let crypto = Crypto::from_json(&sweep["crypto"]).map_err(|err| err.to_string())?;
+        let password = read_password(false, Some(tr("Password of the sweep transaction")))?;
+        let plaintext = crypto
+            .decrypt(password.as_bytes())
+            .map_err(|err| err.to_string())?;
+        let tx: Transaction = serde_json::from_slice(&plaintext)
+            .map_err(|err| format!("Invalid sweep transaction: {}", err))?;
+        let transaction = packed::Transaction::from(tx).into_view();
+        let tx_hash: H256 = transaction.hash().unpack();
+        if sweep["tx_hash"].as_str() != Some(format!("{:#x}", tx_hash).as_str()) {
+            return Err("Sweep file corrupted: transaction hash mismatch".to_owned());
+        }
+        self.send_transaction(transaction, m.is_present("broadcast-all"), debug)
+    }
+}
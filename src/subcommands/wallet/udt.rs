@@ -0,0 +1,470 @@
+use std::collections::HashSet;
+
+use ckb_index::{with_index_db, IndexDatabase, LiveCellInfo};
+use ckb_jsonrpc_types::CellWithStatus;
+use ckb_sdk::{build_witness_with_key, Address, TransferTransactionBuilder};
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, TransactionView},
+    packed::{self, Byte32, Script},
+    prelude::*,
+    H160, H256,
+};
+use clap::ArgMatches;
+
+use super::fee::estimate_tx_size;
+use super::idempotency::IdempotencyState;
+use super::memos::TxMemos;
+use super::pending::{PendingTx, PendingTxStore};
+use super::{is_live_cell, is_secp_cell, sender_address, WalletSubCommand};
+use crate::utils::{
+    arg_parser::{
+        AddressParser, ArgParser, CapacityParser, FromStrParser, PrivkeyPathParser, PrivkeyWrapper,
+    },
+    asset::{format_amount, udt_amount, AssetInfo},
+    i18n::{tr, tr_fmt},
+    other::{check_address_prefix, get_header_deps, get_network_type, read_password},
+    printer::Printable,
+};
+
+impl<'a> WalletSubCommand<'a> {
+    // Transfer sUDT, the sUDT change goes back to sender and capacity is paid by
+    // plain (no type, no data) cells of sender.
+    #[allow(clippy::too_many_arguments)]
+    pub fn transfer_udt(
+        &mut self,
+        m: &ArgMatches,
+        asset: AssetInfo,
+        to_address: &str,
+        amount: u128,
+        memo: Option<String>,
+        debug: bool,
+    ) -> Result<Box<dyn Printable>, String> {
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account = self.from_account(m, from_privkey.is_some())?;
+        let from_address = sender_address(from_privkey.as_ref(), from_account.as_ref())?;
+        if amount == 0 {
+            return Err(tr("Transfer amount can not be zero").to_owned());
+        }
+        let network_type = get_network_type(self.rpc_client)?;
+        check_address_prefix(to_address, network_type)?;
+        let to_address: Address = AddressParser.parse(to_address)?;
+        let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+        let from_lock_hash = from_address
+            .lock_script(secp_type_hash.clone())
+            .calc_script_hash();
+        let reservation = match self.reserve_idempotency_key(m, &from_lock_hash.unpack())? {
+            Some(IdempotencyState::Sent(tx_hash)) => return Ok(Box::new(tx_hash)),
+            Some(IdempotencyState::Reserved(reservation)) => Some(reservation),
+            None => None,
+        };
+
+        let (mut to_output, to_data) = udt_cell(
+            &asset.type_script(),
+            to_address.lock_script(secp_type_hash),
+            amount,
+        );
+        let to_capacity_opt: Option<u64> = CapacityParser.from_matches_opt(m, "capacity", false)?;
+        if let Some(capacity) = to_capacity_opt {
+            let occupied: Capacity = to_output.capacity().unpack();
+            if capacity < occupied.as_u64() {
+                return Err(format!(
+                    "Capacity can not hold the sUDT cell, at least: {}",
+                    occupied.as_u64()
+                ));
+            }
+            to_output = to_output
+                .as_builder()
+                .capacity(Capacity::shannons(capacity).pack())
+                .build();
+        }
+        if !m.is_present("force") {
+            let max_fee_ratio: f64 =
+                FromStrParser::<f64>::default().from_matches(m, "max-fee-ratio")?;
+            let max_balance_ratio: f64 =
+                FromStrParser::<f64>::default().from_matches(m, "max-balance-ratio")?;
+            let tx_fee_opt: Option<u64> = CapacityParser.from_matches_opt(m, "tx-fee", false)?;
+            let capacity: Capacity = to_output.capacity().unpack();
+            let (balance, _) = self.udt_balance(from_lock_hash.clone(), &asset)?;
+            check_udt_transfer_safety(
+                amount,
+                capacity.as_u64(),
+                tx_fee_opt,
+                balance,
+                max_fee_ratio,
+                max_balance_ratio,
+            )?;
+        }
+        let password = if m.is_present("with-password") {
+            Some(read_password(false, None)?)
+        } else {
+            None
+        };
+        let transaction = self.build_udt_transaction(
+            m,
+            &asset,
+            from_privkey.as_ref(),
+            from_account.as_ref(),
+            &password,
+            vec![(to_output, to_data)],
+            &HashSet::new(),
+        )?;
+        let pending_tx = PendingTx::new(&transaction, from_lock_hash.unpack());
+        let tx_hash: H256 = transaction.hash().unpack();
+        let resp = self.send_with_idempotency_key(m, reservation, transaction, debug)?;
+        let genesis_hash: H256 = self.genesis_info()?.header().hash().unpack();
+        let mut pending_txs = PendingTxStore::load(&self.index_dir, &genesis_hash)?;
+        pending_txs.add(pending_tx);
+        pending_txs.save()?;
+        if let Some(memo) = memo {
+            let mut memos = TxMemos::load(&self.index_dir)?;
+            memos.set(tx_hash, memo);
+            memos.save()?;
+        }
+        Ok(resp)
+    }
+
+    // Build and sign a transaction sending sUDT cells `udt_outputs`: collect
+    // sUDT cells of sender first, then plain cells for the rest of capacity.
+    // Cells spent by pending transactions or in `excluded` are skipped.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn build_udt_transaction(
+        &mut self,
+        m: &ArgMatches,
+        asset: &AssetInfo,
+        from_privkey: Option<&PrivkeyWrapper>,
+        from_account: Option<&H160>,
+        password: &Option<String>,
+        udt_outputs: Vec<(packed::CellOutput, Bytes)>,
+        excluded: &HashSet<(H256, u32)>,
+    ) -> Result<TransactionView, String> {
+        let from_address = sender_address(from_privkey, from_account)?;
+        let amount = udt_outputs
+            .iter()
+            .map(|(_, data)| udt_amount(data).expect("sUDT output data"))
+            .try_fold(0u128, u128::checked_add)
+            .ok_or_else(|| "Total sUDT amount of outputs overflows".to_owned())?;
+        let tx_fee_opt: Option<u64> = CapacityParser.from_matches_opt(m, "tx-fee", false)?;
+        let network_type = get_network_type(self.rpc_client)?;
+        let genesis_info = self.genesis_info()?;
+        let secp_type_hash = genesis_info.secp_type_hash().clone();
+        let from_lock = from_address.lock_script(secp_type_hash);
+        let from_lock_hash = from_lock.calc_script_hash();
+        let type_script = asset.type_script();
+        let type_hash = asset.type_hash();
+        let fee_rate = match m.value_of("fee") {
+            Some(preset) => {
+                let fee_rate = self.resolve_fee_rate(preset)?;
+                eprintln!("[INFO]: fee {} => {} shannons/KB", preset, fee_rate);
+                fee_rate
+            }
+            None => 0,
+        };
+
+        // For check index database is ready
+        self.with_db(|_| ())?;
+        let index_dir = self.index_dir.clone();
+        let min_confirmations = self.min_confirmations;
+        let genesis_hash = genesis_info.header().hash();
+        let genesis_info_clone = genesis_info.clone();
+        let mut pending_txs = PendingTxStore::load(&index_dir, &genesis_hash.unpack())?;
+        pending_txs.refresh(self.rpc_client)?;
+        pending_txs.save()?;
+        let mut pending_spent = pending_txs.spent_out_points();
+        pending_spent.extend(excluded.iter().cloned());
+
+        // Collect sUDT cells first, then plain cells for the rest of capacity
+        let mut udt_total: u128 = 0;
+        let mut udt_overflow = false;
+        let mut udt_capacity: u64 = 0;
+        let udt_terminator = |_, info: &LiveCellInfo| {
+            if pending_spent.contains(&(info.tx_hash.clone(), info.tx_index)) {
+                return (false, false);
+            }
+            match info.type_hashes {
+                Some((_, ref script_hash)) if script_hash == &type_hash => {}
+                _ => return (false, false),
+            }
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(info.out_point().into(), true)
+                .call()
+                .expect("get_live_cell by RPC call failed");
+            if !is_live_cell(&resp) {
+                return (false, false);
+            }
+            let cell_amount = resp
+                .cell
+                .as_ref()
+                .and_then(|cell| cell.data.as_ref())
+                .map(|data| udt_amount(data.content.as_bytes()));
+            match cell_amount {
+                Some(Ok(cell_amount)) => match udt_total.checked_add(cell_amount) {
+                    Some(total) => {
+                        udt_total = total;
+                        udt_capacity += info.capacity;
+                        (udt_total >= amount, true)
+                    }
+                    None => {
+                        udt_overflow = true;
+                        (true, false)
+                    }
+                },
+                _ => (false, false),
+            }
+        };
+        let udt_infos: Vec<LiveCellInfo> =
+            with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
+                let mut db =
+                    IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
+                db.set_min_confirmations(min_confirmations);
+                Ok(db.get_live_cells_by_lock(from_lock_hash.clone(), None, udt_terminator))
+            })
+            .map_err(|_err| {
+                format!(
+                    "{}: {}",
+                    tr("index database may not ready, sync process"),
+                    self.index_controller.state().read().to_string()
+                )
+            })?;
+        if udt_overflow {
+            return Err(format!(
+                "Total {} amount of sUDT cells overflows",
+                asset.symbol
+            ));
+        }
+        if udt_total < amount {
+            return Err(format!(
+                "{} ({}): {} => {}",
+                tr("Amount not enough"),
+                asset.symbol,
+                from_address.to_string(network_type),
+                format_amount(udt_total, asset.decimals),
+            ));
+        }
+        let mut outputs = udt_outputs;
+        if udt_total > amount {
+            outputs.push(udt_cell(
+                &type_script,
+                from_lock.clone(),
+                udt_total - amount,
+            ));
+        }
+        let outputs_capacity = outputs
+            .iter()
+            .map(|(output, _)| {
+                let capacity: Capacity = output.capacity().unpack();
+                capacity.as_u64()
+            })
+            .sum::<u64>();
+        let mut estimate_outputs = outputs.clone();
+        estimate_outputs.push((
+            packed::CellOutput::new_builder()
+                .lock(from_lock.clone())
+                .build(),
+            Bytes::new(),
+        ));
+        let fee_for = |inputs: usize| -> u64 {
+            tx_fee_opt.unwrap_or_else(|| {
+                let size = estimate_tx_size(inputs, &estimate_outputs) as u64;
+                (fee_rate * size + 999) / 1000
+            })
+        };
+        let mut plain_capacity: u64 = 0;
+        let mut plain_count = 0;
+        let plain_infos: Vec<LiveCellInfo> = if udt_capacity
+            >= outputs_capacity + fee_for(udt_infos.len())
+        {
+            Vec::new()
+        } else {
+            let genesis_info_clone = genesis_info.clone();
+            let udt_inputs_count = udt_infos.len();
+            let plain_terminator = |_, info: &LiveCellInfo| {
+                if pending_spent.contains(&(info.tx_hash.clone(), info.tx_index))
+                    || info.type_hashes.is_some()
+                    || info.data_bytes > 0
+                {
+                    return (false, false);
+                }
+                let resp: CellWithStatus = self
+                    .rpc_client
+                    .get_live_cell(info.out_point().into(), false)
+                    .call()
+                    .expect("get_live_cell by RPC call failed");
+                if is_live_cell(&resp) && is_secp_cell(&resp) {
+                    plain_capacity += info.capacity;
+                    plain_count += 1;
+                    let inputs_count = udt_inputs_count + plain_count;
+                    let stop =
+                        udt_capacity + plain_capacity >= outputs_capacity + fee_for(inputs_count);
+                    (stop, true)
+                } else {
+                    (false, false)
+                }
+            };
+            with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
+                let mut db =
+                    IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
+                db.set_min_confirmations(min_confirmations);
+                Ok(db.get_live_cells_by_lock(from_lock_hash.clone(), None, plain_terminator))
+            })
+            .map_err(|err| err.to_string())?
+        };
+        let total_capacity = udt_capacity + plain_capacity;
+        let tx_fee = fee_for(udt_infos.len() + plain_infos.len());
+        if total_capacity < outputs_capacity + tx_fee {
+            return Err(format!(
+                "{}: {} => {}",
+                tr("Capacity not enough"),
+                from_address.to_string(network_type),
+                total_capacity,
+            ));
+        }
+        let inputs = udt_infos
+            .iter()
+            .chain(plain_infos.iter())
+            .map(LiveCellInfo::input)
+            .collect::<Vec<_>>();
+        let no_data = Bytes::new();
+        let mut tx_args = TransferTransactionBuilder::new(
+            &from_address,
+            total_capacity,
+            &no_data,
+            // Not used, replaced by the outputs
+            &from_address,
+            outputs_capacity,
+            tx_fee,
+            inputs,
+        );
+        tx_args.set_outputs(outputs);
+        tx_args.add_cell_deps(vec![asset.cell_dep()]);
+        tx_args.add_header_deps(get_header_deps(self.rpc_client, m)?);
+        if let Some(privkey) = from_privkey {
+            tx_args.transfer(&genesis_info, |args| {
+                Ok(build_witness_with_key(privkey, args))
+            })
+        } else {
+            let lock_arg = from_account.unwrap();
+            tx_args.transfer(&genesis_info, |args| {
+                self.build_witness_with_keystore(lock_arg, args, password)
+            })
+        }
+    }
+
+    // Total sUDT amount and number of sUDT cells of the lock
+    pub(super) fn udt_balance(
+        &mut self,
+        lock_hash: Byte32,
+        asset: &AssetInfo,
+    ) -> Result<(u128, usize), String> {
+        // For check index database is ready
+        self.with_db(|_| ())?;
+        let network_type = get_network_type(self.rpc_client)?;
+        let genesis_info = self.genesis_info()?;
+        let genesis_hash = genesis_info.header().hash();
+        let index_dir = self.index_dir.clone();
+        let min_confirmations = self.min_confirmations;
+        let type_hash = asset.type_hash();
+        let mut amount: u128 = 0;
+        let mut overflow = false;
+        let terminator = |_, info: &LiveCellInfo| {
+            match info.type_hashes {
+                Some((_, ref script_hash)) if script_hash == &type_hash => {}
+                _ => return (false, false),
+            }
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(info.out_point().into(), true)
+                .call()
+                .expect("get_live_cell by RPC call failed");
+            let cell_amount = resp
+                .cell
+                .as_ref()
+                .and_then(|cell| cell.data.as_ref())
+                .map(|data| udt_amount(data.content.as_bytes()));
+            match cell_amount {
+                Some(Ok(cell_amount)) => match amount.checked_add(cell_amount) {
+                    Some(total) => {
+                        amount = total;
+                        (false, true)
+                    }
+                    None => {
+                        overflow = true;
+                        (true, false)
+                    }
+                },
+                _ => (false, false),
+            }
+        };
+        let infos = with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
+            let mut db = IndexDatabase::from_db(backend, cf, network_type, genesis_info, false)?;
+            db.set_min_confirmations(min_confirmations);
+            Ok(db.get_live_cells_by_lock(lock_hash, None, terminator))
+        })
+        .map_err(|err| err.to_string())?;
+        if overflow {
+            return Err(format!(
+                "Total {} amount of sUDT cells overflows",
+                asset.symbol
+            ));
+        }
+        Ok((amount, infos.len()))
+    }
+}
+
+// sUDT cell of the minimal capacity
+pub(super) fn udt_cell(
+    type_script: &Script,
+    lock: Script,
+    amount: u128,
+) -> (packed::CellOutput, Bytes) {
+    let data = Bytes::from(amount.to_le_bytes().to_vec());
+    let output = packed::CellOutput::new_builder()
+        .lock(lock)
+        .type_(
+            packed::ScriptOpt::new_builder()
+                .set(Some(type_script.clone()))
+                .build(),
+        )
+        .build();
+    let occupied = output
+        .occupied_capacity(Capacity::bytes(data.len()).expect("data capacity"))
+        .expect("occupied capacity");
+    (output.as_builder().capacity(occupied.pack()).build(), data)
+}
+
+// Same guards as `check_transfer_safety` for sUDT: the fee is compared with
+// the capacity of the sUDT output, the balance checks use sUDT amounts. Fee
+// presets are derived from recent blocks, only an explicit fee is checked.
+fn check_udt_transfer_safety(
+    amount: u128,
+    capacity: u64,
+    tx_fee_opt: Option<u64>,
+    balance: u128,
+    max_fee_ratio: f64,
+    max_balance_ratio: f64,
+) -> Result<(), String> {
+    if let Some(tx_fee) = tx_fee_opt {
+        if tx_fee as f64 > capacity as f64 * max_fee_ratio {
+            return Err(tr_fmt(
+                "Fee {} is more than {} of the sUDT cell capacity {}, use --force if it is intended",
+                &[&tx_fee, &max_fee_ratio, &capacity],
+            ));
+        }
+    }
+    if amount == balance {
+        return Err(tr(
+            "Transfer spends the entire sUDT balance and leaves no change, use --force if it is intended",
+        )
+        .to_owned());
+    }
+    // Not enough balance is reported when collecting inputs
+    if balance > amount && amount as f64 > balance as f64 * max_balance_ratio {
+        return Err(tr_fmt(
+            "Transfer amount {} is more than {} of sUDT balance {}, use --force if it is intended",
+            &[&amount, &max_balance_ratio, &balance],
+        ));
+    }
+    Ok(())
+}
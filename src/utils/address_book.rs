@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_sdk::Address;
+use ckb_types::{
+    packed::{Byte32, Script},
+    prelude::*,
+    H160, H256,
+};
+use serde_derive::{Deserialize, Serialize};
+
+/// A named address, it does not need to be an account in keystore
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct AddressBookEntry {
+    pub label: String,
+    pub lock_arg: H160,
+}
+
+/// Labels of addresses saved in `<keystore-dir>/address-book.json`, used to
+/// resolve accounts by label and to annotate printed lock hashes.
+pub struct AddressBook {
+    path: PathBuf,
+    entries: Vec<AddressBookEntry>,
+}
+
+impl AddressBook {
+    pub fn load(keystore_dir: &Path) -> Result<AddressBook, String> {
+        let path = keystore_dir.join("address-book.json");
+        let entries = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Invalid address book file: {}", err))?
+        } else {
+            Vec::new()
+        };
+        Ok(AddressBook { path, entries })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.entries).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+
+    pub fn entries(&self) -> &[AddressBookEntry] {
+        &self.entries
+    }
+
+    /// Add a label, an address has at most one label so the old label of
+    /// the address is replaced
+    pub fn add(&mut self, label: &str, lock_arg: H160) -> Result<(), String> {
+        validate_label(label)?;
+        if let Some(entry) = self.entries.iter().find(|entry| entry.label == label) {
+            if entry.lock_arg != lock_arg {
+                return Err(format!(
+                    "Label {} is already used by {:#x}",
+                    label, entry.lock_arg
+                ));
+            }
+        }
+        self.entries.retain(|entry| entry.lock_arg != lock_arg);
+        self.entries.push(AddressBookEntry {
+            label: label.to_owned(),
+            lock_arg,
+        });
+        self.entries.sort_by(|a, b| a.label.cmp(&b.label));
+        Ok(())
+    }
+
+    pub fn remove(&mut self, label: &str) -> Result<AddressBookEntry, String> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.label == label)
+            .ok_or_else(|| format!("Label not found: {}", label))?;
+        Ok(self.entries.remove(index))
+    }
+
+    pub fn resolve(&self, label: &str) -> Option<&H160> {
+        self.entries
+            .iter()
+            .find(|entry| entry.label == label)
+            .map(|entry| &entry.lock_arg)
+    }
+
+    pub fn label(&self, lock_arg: &H160) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| &entry.lock_arg == lock_arg)
+            .map(|entry| entry.label.as_str())
+    }
+
+    /// The label, or the lock arg of an unlabeled account
+    pub fn hint(&self, lock_arg: &H160) -> String {
+        self.label(lock_arg)
+            .map(ToOwned::to_owned)
+            .unwrap_or_else(|| format!("{:x}", lock_arg))
+    }
+}
+
+// Labels are accepted wherever an account is, so they must not look like a
+// lock arg or an address
+fn validate_label(label: &str) -> Result<(), String> {
+    if label.is_empty() {
+        return Err("Label is empty".to_owned());
+    }
+    if label.starts_with("0x")
+        || (label.len() == 40 && label.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        return Err(format!("Label looks like a lock arg: {}", label));
+    }
+    if label.starts_with("ckb") || label.starts_with("ckt") {
+        return Err(format!("Label looks like an address: {}", label));
+    }
+    if label.contains(char::is_whitespace) {
+        return Err(format!("Label can not contain whitespaces: {}", label));
+    }
+    Ok(())
+}
+
+/// Map default lock hash of keystore accounts and labeled addresses to their
+/// hints
+pub fn account_hints<'a>(
+    lock_args: impl Iterator<Item = &'a H160>,
+    address_book: &AddressBook,
+    secp_type_hash: &Byte32,
+) -> HashMap<H256, String> {
+    let lock_hash = |lock_arg: &H160| -> H256 {
+        Address::new_default(lock_arg.clone())
+            .lock_script(secp_type_hash.clone())
+            .calc_script_hash()
+            .unpack()
+    };
+    let mut hints = lock_args
+        .map(|lock_arg| (lock_hash(lock_arg), address_book.hint(lock_arg)))
+        .collect::<HashMap<_, _>>();
+    for entry in address_book.entries() {
+        hints.insert(lock_hash(&entry.lock_arg), entry.label.clone());
+    }
+    hints
+}
+
+/// The value has lock hashes or lock scripts to annotate
+pub fn has_locks(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Array(values) => values.iter().any(has_locks),
+        serde_json::Value::Object(map) => map.iter().any(|(key, field)| {
+            is_lock_hash_key(key) || (key == "lock" && field.is_object()) || has_locks(field)
+        }),
+        _ => false,
+    }
+}
+
+/// Add an account field next to every lock hash (`lock_hash` => `account`,
+/// `from_lock_hash` => `from_account`) and lock script (`lock` =>
+/// `lock_account`) of a known account, existing fields are kept.
+pub fn annotate_accounts(value: &mut serde_json::Value, hints: &HashMap<H256, String>) {
+    match value {
+        serde_json::Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| annotate_accounts(value, hints)),
+        serde_json::Value::Object(map) => {
+            let mut annotations = Vec::new();
+            for (key, field) in map.iter() {
+                let lock_hash = if is_lock_hash_key(key) {
+                    field
+                        .as_str()
+                        .and_then(|hash| serde_json::from_value::<H256>(hash.into()).ok())
+                } else if key == "lock" {
+                    script_hash(field)
+                } else {
+                    None
+                };
+                if let Some(hint) = lock_hash.and_then(|hash| hints.get(&hash)) {
+                    let name = if key == "lock" {
+                        "lock_account".to_owned()
+                    } else {
+                        key.replace("lock_hash", "account")
+                    };
+                    annotations.push((name, hint.clone()));
+                }
+            }
+            for (name, hint) in annotations {
+                map.entry(name).or_insert_with(|| hint.into());
+            }
+            map.values_mut()
+                .for_each(|value| annotate_accounts(value, hints));
+        }
+        _ => {}
+    }
+}
+
+fn is_lock_hash_key(key: &str) -> bool {
+    key == "lock_hash" || key.ends_with("_lock_hash")
+}
+
+// Hash of a json script ({code_hash, hash_type, args})
+fn script_hash(value: &serde_json::Value) -> Option<H256> {
+    let script: ckb_jsonrpc_types::Script = serde_json::from_value(value.clone()).ok()?;
+    Some(Script::from(script).calc_script_hash().unpack())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_accounts() {
+        let secp_type_hash = Byte32::new([1u8; 32]);
+        let alice = H160::from([2u8; 20]);
+        let bob = H160::from([3u8; 20]);
+        let mut address_book = AddressBook {
+            path: PathBuf::new(),
+            entries: Vec::new(),
+        };
+        address_book.add("alice", alice.clone()).unwrap();
+        assert!(address_book.add("alice", bob.clone()).is_err());
+        assert!(address_book.add("0xabc", bob.clone()).is_err());
+        assert_eq!(address_book.resolve("alice"), Some(&alice));
+        let hints = account_hints(vec![bob.clone()].iter(), &address_book, &secp_type_hash);
+
+        let alice_lock = Address::new_default(alice).lock_script(secp_type_hash.clone());
+        let alice_hash: H256 = alice_lock.calc_script_hash().unpack();
+        let bob_hash: H256 = Address::new_default(bob.clone())
+            .lock_script(secp_type_hash)
+            .calc_script_hash()
+            .unpack();
+        let mut value = serde_json::json!({
+            "lock_hash": alice_hash,
+            "txs": [{ "from_lock_hash": bob_hash, "from_account": "kept" }],
+            "output": { "lock": ckb_jsonrpc_types::Script::from(alice_lock) },
+        });
+        assert!(has_locks(&value));
+        annotate_accounts(&mut value, &hints);
+        assert_eq!(value["account"], "alice");
+        assert_eq!(value["txs"][0]["from_account"], "kept");
+        assert_eq!(value["output"]["lock_account"], "alice");
+        assert!(!has_locks(&serde_json::json!({ "tx_hash": bob_hash })));
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// Aliases may refer to other aliases, stop at this depth (cycles)
+const MAX_ALIAS_DEPTH: usize = 8;
+// Top level options taking a value, they may come before the alias
+const VALUE_OPTIONS: [&str; 8] = [
+    "--url",
+    "--output-format",
+    "--profile",
+    "--lang",
+    "--min-confirmations",
+    "--rpc-cache-size",
+    "--log-file",
+    "--log-format",
+];
+/// Names of the commands in command line and interactive mode, an alias can
+/// not take one of them
+pub const BUILTIN_COMMANDS: [&str; 25] = [
+    "rpc",
+    "account",
+    "mock-tx",
+    "util",
+    "export",
+    "analyze",
+    "chain",
+    "node",
+    "dev",
+    "pipeline",
+    "serve",
+    "approvals",
+    "doctor",
+    "alias",
+    "test-vector",
+    "asset",
+    "wallet",
+    "tui",
+    "config",
+    "set",
+    "get",
+    "info",
+    "exit",
+    "quit",
+    "help",
+];
+
+/// Read `aliases` from config file, an alias maps a name to a command line:
+///
+/// ```json
+/// "aliases": {
+///     "pay": "wallet transfer --from-account main --fee normal",
+///     "tip": "rpc get_tip_header"
+/// }
+/// ```
+pub fn load_aliases(config_file: &Path) -> Result<BTreeMap<String, String>, String> {
+    fs::read_to_string(config_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .map(|configs| parse_aliases(&configs["aliases"]))
+        .unwrap_or_else(|| Ok(BTreeMap::new()))
+}
+
+pub fn parse_aliases(value: &serde_json::Value) -> Result<BTreeMap<String, String>, String> {
+    let aliases: BTreeMap<String, String> = value
+        .as_object()
+        .map(|aliases| {
+            aliases
+                .iter()
+                .filter_map(|(name, command)| {
+                    command
+                        .as_str()
+                        .map(|command| (name.clone(), command.to_owned()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if let Some(name) = aliases
+        .keys()
+        .find(|name| BUILTIN_COMMANDS.contains(&name.as_str()))
+    {
+        return Err(format!("Alias {} shadows the built-in command", name));
+    }
+    Ok(aliases)
+}
+
+/// Expand the alias at the first argument after the top level options.
+/// `$1`..`$9` in the alias are replaced by the following arguments, the rest
+/// of the arguments are appended.
+pub fn expand_alias(
+    aliases: &BTreeMap<String, String>,
+    mut args: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let mut options = Vec::new();
+    while let Some(arg) = args.first() {
+        if !arg.starts_with('-') {
+            break;
+        }
+        let with_value = VALUE_OPTIONS.contains(&arg.as_str());
+        options.push(args.remove(0));
+        if with_value && !args.is_empty() {
+            options.push(args.remove(0));
+        }
+    }
+    let mut args = expand_command(aliases, args)?;
+    options.append(&mut args);
+    Ok(options)
+}
+
+fn expand_command(
+    aliases: &BTreeMap<String, String>,
+    mut args: Vec<String>,
+) -> Result<Vec<String>, String> {
+    for _ in 0..MAX_ALIAS_DEPTH {
+        let command = match args.first().and_then(|name| aliases.get(name)) {
+            Some(command) => command,
+            None => return Ok(args),
+        };
+        let rest = args.split_off(1);
+        let mut used = vec![false; rest.len()];
+        let mut expanded = Vec::new();
+        for word in shell_words::split(command)
+            .map_err(|err| format!("Invalid alias {}: {}", args[0], err))?
+        {
+            match placeholder(&word) {
+                Some(index) => {
+                    let value = rest
+                        .get(index - 1)
+                        .ok_or_else(|| format!("Alias {} requires argument ${}", args[0], index))?;
+                    used[index - 1] = true;
+                    expanded.push(value.clone());
+                }
+                None => expanded.push(word),
+            }
+        }
+        expanded.extend(
+            rest.into_iter()
+                .zip(used)
+                .filter(|(_, used)| !used)
+                .map(|(arg, _)| arg),
+        );
+        args = expanded;
+    }
+    Err(format!(
+        "Alias expansion is deeper than {}, aliases may refer to each other",
+        MAX_ALIAS_DEPTH
+    ))
+}
+
+// "$1" => Some(1)
+fn placeholder(word: &str) -> Option<usize> {
+    if word.len() == 2 && word.starts_with('$') {
+        word[1..].parse::<usize>().ok().filter(|index| *index > 0)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(input: &str) -> Vec<String> {
+        shell_words::split(input).unwrap()
+    }
+
+    #[test]
+    fn test_expand_alias() {
+        let aliases = parse_aliases(&serde_json::json!({
+            "pay": "wallet transfer --from-account main --fee normal",
+            "send-to": "pay --to-address $1 --capacity $2",
+            "loop": "loop",
+            "ignored": 1,
+        }))
+        .unwrap();
+        assert_eq!(aliases.len(), 3);
+
+        assert_eq!(
+            expand_alias(&aliases, args("pay --capacity 100")).unwrap(),
+            args("wallet transfer --from-account main --fee normal --capacity 100")
+        );
+        assert_eq!(
+            expand_alias(&aliases, args("send-to ckt1xyz 100 --tx-fee 0.001")).unwrap(),
+            args("wallet transfer --from-account main --fee normal --to-address ckt1xyz --capacity 100 --tx-fee 0.001")
+        );
+        assert_eq!(
+            expand_alias(&aliases, args("wallet get-capacity")).unwrap(),
+            args("wallet get-capacity")
+        );
+        assert!(expand_alias(&aliases, args("send-to ckt1xyz")).is_err());
+        assert!(expand_alias(&aliases, args("loop")).is_err());
+        assert!(expand_alias(&aliases, Vec::new()).unwrap().is_empty());
+
+        assert_eq!(
+            expand_alias(&aliases, args("--url http://127.0.0.1:8114 --no-color pay")).unwrap(),
+            args("--url http://127.0.0.1:8114 --no-color wallet transfer --from-account main --fee normal")
+        );
+        assert_eq!(
+            expand_alias(&aliases, args("--profile=testnet send-to ckt1xyz 100")).unwrap(),
+            args("--profile=testnet wallet transfer --from-account main --fee normal --to-address ckt1xyz --capacity 100")
+        );
+        assert_eq!(
+            expand_alias(&aliases, args("--profile pay")).unwrap(),
+            args("--profile pay")
+        );
+    }
+
+    #[test]
+    fn test_builtin_alias() {
+        assert!(parse_aliases(&serde_json::json!({ "wallet": "account list" })).is_err());
+        assert!(parse_aliases(&serde_json::json!({ "quit": "exit" })).is_err());
+        assert!(parse_aliases(&serde_json::json!({ "wallets": "wallet" })).is_ok());
+    }
+}
@@ -2,9 +2,14 @@ use crate::utils::arg_parser::{
     AddressParser, ArgParser, CapacityParser, FilePathParser, FixedHashParser, FromStrParser,
     HexParser, PrivkeyPathParser, PubkeyHexParser,
 };
+use crate::utils::cell_filter::CellFilter;
 use ckb_types::{H160, H256};
 use clap::Arg;
 
+/// Long names of options whose value is a secret, they are redacted in the
+/// invocation log (see: --log-file). Add new secret options here.
+pub const SECRET_ARGS: &[&str] = &["token"];
+
 pub fn privkey_path<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("privkey-path")
         .long("privkey-path")
@@ -51,8 +56,7 @@ pub fn from_account<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("from-account")
         .long("from-account")
         .takes_value(true)
-        .validator(|input| FixedHashParser::<H160>::default().validate(input))
-        .help("The account's lock-arg (transfer from this account)")
+        .help("The account's lock-arg, address, label in address book (see: account add-label) or unique lock-arg prefix in keystore (transfer from this account, default account will be used if not given)")
 }
 
 pub fn to_address<'a, 'b>() -> Arg<'a, 'b> {
@@ -101,6 +105,33 @@ pub fn with_password<'a, 'b>() -> Arg<'a, 'b> {
         .help("Input password to unlock keystore account just for current transfer transaction")
 }
 
+pub fn hd_change<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("hd-change")
+        .long("hd-change")
+        .conflicts_with("privkey-path")
+        .help("Send the change to the next unused HD change address (m/44'/309'/0'/1/*) of the account instead of the account itself, it is recorded for `wallet get-capacity --with-derived` and spendable by `wallet transfer --from-path`")
+}
+
+pub fn broadcast_all<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("broadcast-all")
+        .long("broadcast-all")
+        .help("Also send the transaction to all broadcast urls (see: config --broadcast-urls) in parallel")
+}
+
+pub fn force<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("force")
+        .long("force")
+        .help("Overwrite the output file if it exists")
+}
+
+pub fn resume_job<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("resume")
+        .long("resume")
+        .takes_value(true)
+        .value_name("job-id")
+        .help("Resume an interrupted run, transactions already sent in the job are not sent again")
+}
+
 pub fn type_hash<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("type-hash")
         .long("type-hash")
@@ -151,3 +182,55 @@ pub fn top_n<'a, 'b>() -> Arg<'a, 'b> {
         .default_value("10")
         .help("Get top n capacity addresses")
 }
+
+pub fn cell_filter<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("filter")
+        .long("filter")
+        .takes_value(true)
+        .validator(|input| FromStrParser::<CellFilter>::new().validate(input))
+        .help("Filter expression, fields: capacity, data.len, block_number, tx_index, tx_hash, lock_hash, type.code_hash, type.hash (example: 'capacity > 1000 and type.code_hash == 0x... and data.len == 16')")
+}
+
+pub fn tx_hash<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("tx-hash")
+        .long("tx-hash")
+        .takes_value(true)
+        .validator(|input| FixedHashParser::<H256>::default().validate(input))
+        .help("Transaction hash")
+}
+
+pub fn header_dep<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("header-dep")
+        .long("header-dep")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .validator(|input| {
+            FixedHashParser::<H256>::default()
+                .validate(input.clone())
+                .or_else(|_| FromStrParser::<u64>::default().validate(input))
+        })
+        .help("Block hash or block number of a header dep (can be repeated), the block must be on the canonical chain")
+}
+
+pub fn idempotency_key<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("idempotency-key")
+        .long("idempotency-key")
+        .takes_value(true)
+        .help("Unique key of this request (per sender), submitting the same key again returns the transaction already sent instead of sending a new one")
+}
+
+pub fn token<'a, 'b>() -> Arg<'a, 'b> {
+    // Listed in SECRET_ARGS
+    Arg::with_name("token")
+        .long("token")
+        .takes_value(true)
+        .required(true)
+        .help("Token secret")
+}
+
+pub fn memo<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("memo").long("memo").takes_value(true).help(
+        "Local memo of the transaction, only saved in index directory (see: wallet search-memos)",
+    )
+}
@@ -1,20 +1,24 @@
 use std::fmt::Display;
 use std::fs;
-use std::io::Read;
+use std::io::{self, Read};
 use std::marker::PhantomData;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use ckb_sdk::{
     wallet::{zeroize_privkey, MasterPrivKey},
-    Address, NetworkType, OldAddress, ONE_CKB,
+    Address, FullAddress, NetworkType, OldAddress, ONE_CKB, SECP_TYPE_HASH,
 };
 use ckb_types::{packed::OutPoint, prelude::*, H160, H256};
 use clap::ArgMatches;
 use faster_hex::hex_decode;
+use lazy_static::lazy_static;
 use url::Url;
 
+use super::asset::parse_amount;
+
 pub trait ArgParser<T> {
     fn parse(&self, input: &str) -> Result<T, String>;
 
@@ -66,13 +70,11 @@ impl ArgParser<String> for NullParser {
     }
 }
 
-#[allow(dead_code)]
 pub enum EitherValue<TA, TB> {
     A(TA),
     B(TB),
 }
 
-#[allow(dead_code)]
 pub struct EitherParser<TA, TB, A, B> {
     a: A,
     b: B,
@@ -85,7 +87,6 @@ where
     A: ArgParser<TA>,
     B: ArgParser<TB>,
 {
-    #[allow(dead_code)]
     pub fn new(a: A, b: B) -> Self {
         EitherParser {
             a,
@@ -138,10 +139,50 @@ impl ArgParser<Url> for UrlParser {
     }
 }
 
+lazy_static! {
+    // Stdin can only be read once, keep it for every `@-` argument
+    static ref STDIN_CONTENT: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// Resolve `@path/to/file` and `@-` (stdin) into the content, other input is
+/// returned as is.
+pub fn read_arg_value(input: &str) -> Result<String, String> {
+    if !input.starts_with('@') {
+        return Ok(input.to_owned());
+    }
+    let content = match &input[1..] {
+        "-" => {
+            let mut stdin_content = STDIN_CONTENT.lock().unwrap();
+            if stdin_content.is_none() {
+                let mut content = String::new();
+                io::stdin()
+                    .read_to_string(&mut content)
+                    .map_err(|err| format!("Read stdin failed: {}", err))?;
+                *stdin_content = Some(content);
+            }
+            stdin_content.clone().unwrap()
+        }
+        path => fs::read_to_string(path).map_err(|err| format!("Read {} failed: {}", path, err))?,
+    };
+    Ok(content.trim().to_owned())
+}
+
+/// Hex string, or `@path/to/file` / `@-` (stdin) containing the hex string
 pub struct HexParser;
 
 impl ArgParser<Vec<u8>> for HexParser {
-    fn parse(&self, mut input: &str) -> Result<Vec<u8>, String> {
+    // Do not consume stdin when validating
+    fn validate(&self, input: String) -> Result<(), String> {
+        if input == "@-" {
+            Ok(())
+        } else {
+            self.parse(&input).map(|_| ())
+        }
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<u8>, String> {
+        let content = read_arg_value(input)?;
+        let mut input = content.as_str();
         if input.starts_with("0x") || input.starts_with("0X") {
             input = &input[2..];
         }
@@ -219,11 +260,13 @@ pub struct DirPathParser {
     path_parser: PathParser,
 }
 
-// impl DirPathParser {
-//     pub fn new(should_exists: bool) -> DirPathParser {
-//         DirPathParser { path_parser: PathParser { should_exists } }
-//     }
-// }
+impl DirPathParser {
+    pub fn new(should_exists: bool) -> DirPathParser {
+        DirPathParser {
+            path_parser: PathParser { should_exists },
+        }
+    }
+}
 
 impl ArgParser<PathBuf> for DirPathParser {
     fn parse(&self, input: &str) -> Result<PathBuf, String> {
@@ -318,9 +361,20 @@ impl ArgParser<Address> for AddressParser {
         if let Ok((_network, address)) = Address::from_input(input) {
             return Ok(address);
         }
+        if let Ok((_network, full_address, _format)) =
+            FullAddress::from_input(input, &SECP_TYPE_HASH)
+        {
+            return full_address.to_short(&SECP_TYPE_HASH).ok_or_else(|| {
+                "Only addresses of the default secp256k1 lock are supported here".to_owned()
+            });
+        }
 
-        let prefix = input.chars().take(3).collect::<String>();
-        let network = NetworkType::from_prefix(prefix.as_str())
+        // Bech32 data never contains '1', the last one is the separator
+        let prefix = input
+            .rfind('1')
+            .map(|index| &input[..index])
+            .unwrap_or(input);
+        let network = NetworkType::from_prefix(prefix)
             .ok_or_else(|| format!("Invalid address prefix: {}", prefix))?;
         let old_address = OldAddress::from_input(network, input)?;
         Ok(Address::new_default(old_address.hash().clone()))
@@ -354,7 +408,25 @@ impl ArgParser<u64> for CapacityParser {
     }
 }
 
-#[allow(dead_code)]
+/// sUDT amount scaled by decimals of the asset: "12.5" is 1250000000 with 8
+/// decimals. More decimal places than the asset has is an error, never
+/// rounded.
+pub struct AmountParser {
+    decimals: u8,
+}
+
+impl AmountParser {
+    pub fn new(decimals: u8) -> AmountParser {
+        AmountParser { decimals }
+    }
+}
+
+impl ArgParser<u128> for AmountParser {
+    fn parse(&self, input: &str) -> Result<u128, String> {
+        parse_amount(input, self.decimals)
+    }
+}
+
 pub struct OutPointParser;
 
 impl ArgParser<OutPoint> for OutPointParser {
@@ -425,6 +497,16 @@ mod tests {
         assert_eq!(HexParser.parse("3a6665"), Ok(vec![0x3a, 0x66, 0x65]));
         assert!(HexParser.parse("0x3a665").is_err());
         assert!(HexParser.parse("abcdefghi").is_err());
+
+        let path = std::env::temp_dir().join("ckb-cli-test-hex-arg");
+        fs::write(&path, "0x3a6665\n").unwrap();
+        assert_eq!(
+            HexParser.parse(&format!("@{}", path.to_str().unwrap())),
+            Ok(vec![0x3a, 0x66, 0x65])
+        );
+        fs::remove_file(&path).unwrap();
+        assert!(HexParser.parse("@/not/exists").is_err());
+        assert!(HexParser.validate("@-".to_owned()).is_ok());
     }
 
     #[test]
@@ -468,6 +550,18 @@ mod tests {
             )))
         );
 
+        // Full address of the same lock
+        let full_address = FullAddress::from_short(
+            &Address::new_default(h160!("0x13e41d6F9292555916f17B4882a5477C01270142")),
+            &SECP_TYPE_HASH,
+        );
+        assert_eq!(
+            AddressParser.parse(&full_address.to_string(NetworkType::MainNet)),
+            Ok(Address::new_default(h160!(
+                "0x13e41d6F9292555916f17B4882a5477C01270142"
+            )))
+        );
+
         // Old address
         assert!(AddressParser
             .parse("kt1q9gry5zgzkfc6rznfaequqlcmdeh4fhta4uwn4qajhqxyc")
@@ -502,4 +596,13 @@ mod tests {
         assert!(CapacityParser.parse("-234").is_err());
         assert!(CapacityParser.parse("-234.3").is_err());
     }
+
+    #[test]
+    fn test_amount() {
+        assert_eq!(AmountParser::new(8).parse("12.5"), Ok(1_250_000_000));
+        assert_eq!(AmountParser::new(0).parse("12"), Ok(12));
+        assert!(AmountParser::new(2).parse("12.345").is_err());
+        assert!(AmountParser::new(0).parse("12.5").is_err());
+        assert!(AmountParser::new(8).parse("-1").is_err());
+    }
 }
@@ -0,0 +1,436 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_hash::blake2b_256;
+use ckb_jsonrpc_types::{CellDep as JsonCellDep, OutPoint as JsonOutPoint, Script as JsonScript};
+use ckb_sdk::SECP256K1;
+use ckb_types::{
+    packed::{CellDep, Script},
+    prelude::*,
+    H160, H256,
+};
+use secp256k1::recovery::{RecoverableSignature, RecoveryId};
+use serde_derive::{Deserialize, Serialize};
+
+use super::arg_parser::{ArgParser, FixedHashParser, HexParser};
+
+pub const CKB_SYMBOL: &str = "CKB";
+pub const CKB_DECIMALS: u8 = 8;
+// u128::MAX has 39 digits
+const MAX_DECIMALS: u8 = 38;
+
+/// A user defined token (sUDT), identified by its type script
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AssetInfo {
+    pub symbol: String,
+    pub decimals: u8,
+    pub type_script: JsonScript,
+    // Where the sUDT script code lives
+    pub cell_dep: JsonCellDep,
+}
+
+impl AssetInfo {
+    pub fn type_script(&self) -> Script {
+        self.type_script.clone().into()
+    }
+
+    pub fn type_hash(&self) -> H256 {
+        self.type_script().calc_script_hash().unpack()
+    }
+
+    pub fn cell_dep(&self) -> CellDep {
+        self.cell_dep.clone().into()
+    }
+}
+
+pub enum Asset {
+    Ckb,
+    Udt(AssetInfo),
+}
+
+impl Asset {
+    pub fn symbol(&self) -> &str {
+        match self {
+            Asset::Ckb => CKB_SYMBOL,
+            Asset::Udt(info) => &info.symbol,
+        }
+    }
+
+    pub fn decimals(&self) -> u8 {
+        match self {
+            Asset::Ckb => CKB_DECIMALS,
+            Asset::Udt(info) => info.decimals,
+        }
+    }
+}
+
+/// Where the signed token list is fetched from
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrySource {
+    Url(String),
+    // Token list is the data of the cell
+    Cell(JsonOutPoint),
+}
+
+/// Token list synced by `asset registry update`, saved in
+/// `<index-dir>/asset-registry.json`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SyncedRegistry {
+    pub source: RegistrySource,
+    // blake160 of the public key which signed the token list
+    pub signer: H160,
+    // Unix timestamp in seconds
+    pub updated_at: u64,
+    pub assets: Vec<AssetInfo>,
+}
+
+impl SyncedRegistry {
+    pub fn load(index_dir: &Path) -> Result<Option<SyncedRegistry>, String> {
+        let path = index_dir.join("asset-registry.json");
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|err| format!("Invalid synced asset registry file: {}", err))
+    }
+
+    // Written to a temporary file then renamed, an interrupted update keeps
+    // the last synced list
+    pub fn save(&self, index_dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(index_dir).map_err(|err| err.to_string())?;
+        let content = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        let path = index_dir.join("asset-registry.json");
+        let tmp_path = index_dir.join("asset-registry.json.tmp");
+        fs::write(&tmp_path, content).map_err(|err| err.to_string())?;
+        fs::rename(&tmp_path, &path).map_err(|err| err.to_string())
+    }
+}
+
+/// Verify a signed token list and return the assets in it.
+///
+/// Format: `{"assets": [<asset>...], "signature": "0x<65 bytes>"}`, the
+/// signature is a recoverable secp256k1 signature of blake2b_256 of `assets`
+/// serialized as compact JSON with sorted keys.
+pub fn verify_token_list(content: &[u8], signer: &H160) -> Result<Vec<AssetInfo>, String> {
+    let value: serde_json::Value =
+        serde_json::from_slice(content).map_err(|err| format!("Invalid token list: {}", err))?;
+    let assets_value = value
+        .get("assets")
+        .ok_or_else(|| "Missing assets in token list".to_owned())?;
+    let signature = value
+        .get("signature")
+        .and_then(serde_json::Value::as_str)
+        .ok_or_else(|| "Missing signature in token list".to_owned())?;
+    let signature = HexParser.parse(signature)?;
+    if signature.len() != 65 {
+        return Err(format!("Invalid signature length: {}", signature.len()));
+    }
+    let recovery_id =
+        RecoveryId::from_i32(i32::from(signature[64])).map_err(|err| err.to_string())?;
+    let signature = RecoverableSignature::from_compact(&signature[0..64], recovery_id)
+        .map_err(|err| err.to_string())?;
+    let message = secp256k1::Message::from_slice(&token_list_hash(assets_value))
+        .map_err(|err| err.to_string())?;
+    let pubkey = SECP256K1
+        .recover(&message, &signature)
+        .map_err(|err| format!("Invalid token list signature: {}", err))?;
+    let pubkey_hash = blake2b_256(&pubkey.serialize()[..]);
+    if &pubkey_hash[0..20] != signer.as_bytes() {
+        return Err(format!(
+            "Token list is not signed by {:#x}, signed by: 0x{}",
+            signer,
+            faster_hex::hex_string(&pubkey_hash[0..20]).unwrap()
+        ));
+    }
+    let assets: Vec<AssetInfo> = serde_json::from_value(assets_value.clone())
+        .map_err(|err| format!("Invalid assets in token list: {}", err))?;
+    let mut symbols = HashSet::new();
+    let mut type_hashes = HashSet::new();
+    for info in &assets {
+        check_asset(info)
+            .map_err(|err| format!("Invalid asset {} in token list: {}", info.symbol, err))?;
+        if !symbols.insert(info.symbol.clone()) {
+            return Err(format!("Duplicated symbol in token list: {}", info.symbol));
+        }
+        let type_hash = info.type_hash();
+        if !type_hashes.insert(type_hash.clone()) {
+            return Err(format!(
+                "Duplicated type script in token list: {:#x}",
+                type_hash
+            ));
+        }
+    }
+    Ok(assets)
+}
+
+// Rules for both user added and synced assets
+fn check_asset(info: &AssetInfo) -> Result<(), String> {
+    if info.symbol.eq_ignore_ascii_case(CKB_SYMBOL) {
+        return Err(format!("Symbol {} is reserved", CKB_SYMBOL));
+    }
+    if info.decimals > MAX_DECIMALS {
+        return Err(format!("Decimals can not be more than {}", MAX_DECIMALS));
+    }
+    Ok(())
+}
+
+// serde_json::Map is a BTreeMap, keys are sorted when serialized
+fn token_list_hash(assets: &serde_json::Value) -> [u8; 32] {
+    blake2b_256(serde_json::to_vec(assets).expect("serialize token list"))
+}
+
+/// Known assets: added by user (saved in `<index-dir>/assets.json`, key is
+/// the symbol) and synced from the token list, added ones take precedence.
+pub struct AssetRegistry {
+    path: PathBuf,
+    assets: BTreeMap<String, AssetInfo>,
+    synced: BTreeMap<String, AssetInfo>,
+}
+
+impl AssetRegistry {
+    pub fn load(index_dir: &Path) -> Result<AssetRegistry, String> {
+        let path = index_dir.join("assets.json");
+        let assets: BTreeMap<String, AssetInfo> = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Invalid asset registry file: {}", err))?
+        } else {
+            BTreeMap::new()
+        };
+        // A synced asset with the type script of an added one under another
+        // symbol is ignored, otherwise the type hash resolves to either
+        let added_type_hashes = assets
+            .values()
+            .map(|info| (info.type_hash(), info.symbol.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let synced = SyncedRegistry::load(index_dir)?
+            .map(|registry| {
+                registry
+                    .assets
+                    .into_iter()
+                    .filter(|info| {
+                        check_asset(info).is_ok()
+                            && added_type_hashes
+                                .get(&info.type_hash())
+                                .map(|symbol| symbol == &info.symbol)
+                                .unwrap_or(true)
+                    })
+                    .map(|info| (info.symbol.clone(), info))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(AssetRegistry {
+            path,
+            assets,
+            synced,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(&self.assets).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+
+    /// All known assets, with whether it is from the synced token list
+    pub fn list(&self) -> Vec<(&AssetInfo, bool)> {
+        let mut assets = self
+            .assets
+            .values()
+            .map(|info| (info, false))
+            .collect::<Vec<_>>();
+        assets.extend(
+            self.synced
+                .values()
+                .filter(|info| !self.assets.contains_key(&info.symbol))
+                .map(|info| (info, true)),
+        );
+        assets
+    }
+
+    pub fn add(&mut self, info: AssetInfo) -> Result<(), String> {
+        check_asset(&info)?;
+        let type_hash = info.type_hash();
+        if let Some(other) = self.find_by_type_hash(&type_hash) {
+            if other.symbol != info.symbol {
+                return Err(format!(
+                    "Type script {:#x} is already registered as {}",
+                    type_hash, other.symbol
+                ));
+            }
+        }
+        self.assets.insert(info.symbol.clone(), info);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, symbol: &str) -> Option<AssetInfo> {
+        self.assets.remove(symbol)
+    }
+
+    pub fn find_by_type_hash(&self, type_hash: &H256) -> Option<&AssetInfo> {
+        self.assets
+            .values()
+            .chain(self.synced.values())
+            .find(|info| &info.type_hash() == type_hash)
+    }
+
+    /// Resolve `ckb`, a symbol or a type script hash
+    pub fn resolve(&self, input: &str) -> Result<Asset, String> {
+        if input.eq_ignore_ascii_case(CKB_SYMBOL) {
+            return Ok(Asset::Ckb);
+        }
+        if let Some(info) = self.assets.get(input).or_else(|| self.synced.get(input)) {
+            return Ok(Asset::Udt(info.clone()));
+        }
+        let type_hash: H256 = FixedHashParser::<H256>::default()
+            .parse(input)
+            .map_err(|_| format!("Unknown asset: {}", input))?;
+        self.find_by_type_hash(&type_hash)
+            .cloned()
+            .map(Asset::Udt)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown asset type hash {:#x}, run `asset registry update` or register it by `asset add` first (decimals are required)",
+                    type_hash
+                )
+            })
+    }
+}
+
+/// Parse decimal amount ("12.5") to the smallest unit
+pub fn parse_amount(input: &str, decimals: u8) -> Result<u128, String> {
+    let parts = input.trim().split('.').collect::<Vec<_>>();
+    // u128::from_str also accepts a leading '+'
+    if parts.len() > 2
+        || parts
+            .iter()
+            .any(|part| part.is_empty() || !part.bytes().all(|byte| byte.is_ascii_digit()))
+    {
+        return Err(format!("Invalid amount: {}", input));
+    }
+    let unit = 10u128.pow(u32::from(decimals));
+    let integer = parts[0]
+        .parse::<u128>()
+        .map_err(|err| err.to_string())?
+        .checked_mul(unit)
+        .ok_or_else(|| "Amount overflow".to_owned())?;
+    let fraction = match parts.get(1) {
+        Some(fraction) => {
+            if fraction.len() > usize::from(decimals) {
+                return Err(format!(
+                    "Too many decimal places: {}, at most {}",
+                    fraction.len(),
+                    decimals
+                ));
+            }
+            let value = fraction.parse::<u128>().map_err(|err| err.to_string())?;
+            value * 10u128.pow(u32::from(decimals) - fraction.len() as u32)
+        }
+        None => 0,
+    };
+    integer
+        .checked_add(fraction)
+        .ok_or_else(|| "Amount overflow".to_owned())
+}
+
+/// Format amount in the smallest unit with decimals, trailing zeros removed
+pub fn format_amount(amount: u128, decimals: u8) -> String {
+    let unit = 10u128.pow(u32::from(decimals));
+    let fraction = amount % unit;
+    if fraction == 0 {
+        return (amount / unit).to_string();
+    }
+    let fraction = format!("{:0width$}", fraction, width = usize::from(decimals));
+    format!("{}.{}", amount / unit, fraction.trim_end_matches('0'))
+}
+
+/// Amount of a sUDT cell, the first 16 bytes of data (little endian)
+pub fn udt_amount(data: &[u8]) -> Result<u128, String> {
+    if data.len() < 16 {
+        return Err(format!("Invalid sUDT cell data length: {}", data.len()));
+    }
+    let mut buf = [0u8; 16];
+    buf.copy_from_slice(&data[0..16]);
+    Ok(u128::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amount() {
+        assert_eq!(parse_amount("12.5", 2), Ok(1250));
+        assert_eq!(parse_amount("12", 0), Ok(12));
+        assert_eq!(parse_amount("0.001", 3), Ok(1));
+        assert!(parse_amount("0.001", 2).is_err());
+        assert!(parse_amount("1.2.3", 8).is_err());
+        assert!(parse_amount(".5", 8).is_err());
+        assert!(parse_amount("5.", 8).is_err());
+        assert!(parse_amount("+5", 8).is_err());
+        assert!(parse_amount("1.+5", 8).is_err());
+        assert!(parse_amount("340282366920938463463374607431768211456", 0).is_err());
+
+        assert_eq!(format_amount(1250, 2), "12.5");
+        assert_eq!(format_amount(1200, 2), "12");
+        assert_eq!(format_amount(1, 8), "0.00000001");
+        assert_eq!(format_amount(7, 0), "7");
+
+        let mut data = 1000u128.to_le_bytes().to_vec();
+        assert_eq!(udt_amount(&data), Ok(1000));
+        data.truncate(15);
+        assert!(udt_amount(&data).is_err());
+    }
+
+    #[test]
+    fn test_verify_token_list() {
+        let privkey = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+        let signer = H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20]).unwrap();
+        let asset = |symbol: &str, decimals: u8, args: &str| {
+            serde_json::json!({
+                "symbol": symbol,
+                "decimals": decimals,
+                "type_script": {"code_hash": format!("{:#x}", H256::default()), "hash_type": "data", "args": args},
+                "cell_dep": {"out_point": {"tx_hash": format!("{:#x}", H256::default()), "index": "0x0"}, "dep_type": "code"},
+            })
+        };
+        let sign = |assets: serde_json::Value| {
+            let message = secp256k1::Message::from_slice(&token_list_hash(&assets)).unwrap();
+            let signature =
+                ckb_sdk::serialize_signature(&SECP256K1.sign_recoverable(&message, &privkey));
+            serde_json::json!({
+                "assets": assets,
+                "signature": format!("0x{}", faster_hex::hex_string(&signature).unwrap()),
+            })
+        };
+        let token_list = sign(serde_json::json!([asset("TT", 8, "0x")]));
+        let content = serde_json::to_vec_pretty(&token_list).unwrap();
+        let verified = verify_token_list(&content, &signer).unwrap();
+        assert_eq!(verified.len(), 1);
+        assert_eq!(verified[0].symbol, "TT");
+        assert!(verify_token_list(&content, &H160::default()).is_err());
+
+        let mut tampered = token_list.clone();
+        tampered["assets"][0]["decimals"] = serde_json::json!(2);
+        let content = serde_json::to_vec(&tampered).unwrap();
+        assert!(verify_token_list(&content, &signer).is_err());
+
+        // Signed but invalid lists
+        for assets in vec![
+            serde_json::json!([asset("TT", 39, "0x")]),
+            serde_json::json!([asset("ckb", 8, "0x")]),
+            serde_json::json!([asset("TT", 8, "0x"), asset("TT", 8, "0x01")]),
+            serde_json::json!([asset("TT", 8, "0x"), asset("UU", 8, "0x")]),
+        ] {
+            let content = serde_json::to_vec(&sign(assets)).unwrap();
+            assert!(verify_token_list(&content, &signer).is_err());
+        }
+    }
+}
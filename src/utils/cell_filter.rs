@@ -0,0 +1,369 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ckb_index::LiveCellInfo;
+use ckb_types::H256;
+
+/// Filter expression over indexed live cells, example:
+///
+///   capacity > 1000 and type.code_hash == 0x... and data.len == 16
+///
+/// Fields:
+///   capacity (shannons), data.len, block_number, tx_index, tx_hash,
+///   lock_hash, type.code_hash, type.hash
+///
+/// Operators: `==`, `!=`, `>`, `>=`, `<`, `<=` (hash fields only support
+/// `==` and `!=`), combined with `and`, `or`, `not` and parentheses. Hash
+/// fields can be compared with `null` (for cells without type script).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CellFilter {
+    And(Box<CellFilter>, Box<CellFilter>),
+    Or(Box<CellFilter>, Box<CellFilter>),
+    Not(Box<CellFilter>),
+    Compare(Field, CmpOp, Value),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Field {
+    Capacity,
+    DataLen,
+    BlockNumber,
+    TxIndex,
+    TxHash,
+    LockHash,
+    TypeCodeHash,
+    TypeHash,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Value {
+    Number(u64),
+    Hash(H256),
+    Null,
+}
+
+impl Field {
+    fn is_hash(self) -> bool {
+        match self {
+            Field::TxHash | Field::LockHash | Field::TypeCodeHash | Field::TypeHash => true,
+            _ => false,
+        }
+    }
+
+    fn value(self, info: &LiveCellInfo) -> Value {
+        match self {
+            Field::Capacity => Value::Number(info.capacity),
+            Field::DataLen => Value::Number(info.data_bytes),
+            Field::BlockNumber => Value::Number(info.number),
+            Field::TxIndex => Value::Number(u64::from(info.tx_index)),
+            Field::TxHash => Value::Hash(info.tx_hash.clone()),
+            Field::LockHash => Value::Hash(info.lock_hash.clone()),
+            Field::TypeCodeHash => info
+                .type_hashes
+                .as_ref()
+                .map(|(code_hash, _)| Value::Hash(code_hash.clone()))
+                .unwrap_or(Value::Null),
+            Field::TypeHash => info
+                .type_hashes
+                .as_ref()
+                .map(|(_, script_hash)| Value::Hash(script_hash.clone()))
+                .unwrap_or(Value::Null),
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+    fn from_str(input: &str) -> Result<Field, String> {
+        match input {
+            "capacity" => Ok(Field::Capacity),
+            "data.len" => Ok(Field::DataLen),
+            "block_number" | "number" => Ok(Field::BlockNumber),
+            "tx_index" => Ok(Field::TxIndex),
+            "tx_hash" => Ok(Field::TxHash),
+            "lock_hash" | "lock.hash" => Ok(Field::LockHash),
+            "type.code_hash" => Ok(Field::TypeCodeHash),
+            "type.hash" | "type_hash" => Ok(Field::TypeHash),
+            _ => Err(format!("Unknown field: {}", input)),
+        }
+    }
+}
+
+impl CmpOp {
+    fn compare<T: Ord>(self, left: &T, right: &T) -> bool {
+        match self {
+            CmpOp::Eq => left == right,
+            CmpOp::Ne => left != right,
+            CmpOp::Gt => left > right,
+            CmpOp::Ge => left >= right,
+            CmpOp::Lt => left < right,
+            CmpOp::Le => left <= right,
+        }
+    }
+}
+
+impl CellFilter {
+    pub fn matches(&self, info: &LiveCellInfo) -> bool {
+        match self {
+            CellFilter::And(left, right) => left.matches(info) && right.matches(info),
+            CellFilter::Or(left, right) => left.matches(info) || right.matches(info),
+            CellFilter::Not(inner) => !inner.matches(info),
+            CellFilter::Compare(field, op, expected) => {
+                match (field.value(info), expected) {
+                    (Value::Number(actual), Value::Number(expected)) => {
+                        op.compare(&actual, expected)
+                    }
+                    (Value::Hash(actual), Value::Hash(expected)) => op.compare(&actual, expected),
+                    // Only `==` / `!=` allowed for null (checked when parsing)
+                    (actual, expected) => op.compare(&(actual == *expected), &true),
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for CellFilter {
+    type Err = String;
+    fn from_str(input: &str) -> Result<CellFilter, String> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let filter = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("Unexpected token in filter: {}", token));
+        }
+        Ok(filter)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Hash(H256),
+    Null,
+    Op(CmpOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Hash(hash) => write!(f, "{:#x}", hash),
+            Token::Null => write!(f, "null"),
+            Token::Op(op) => write!(f, "{:?}", op),
+            Token::And => write!(f, "and"),
+            Token::Or => write!(f, "or"),
+            Token::Not => write!(f, "not"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let c = chars[pos];
+        if c.is_whitespace() {
+            pos += 1;
+            continue;
+        }
+        match c {
+            '(' => {
+                tokens.push(Token::LParen);
+                pos += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                pos += 1;
+            }
+            '=' | '!' | '>' | '<' => {
+                let next_eq = chars.get(pos + 1) == Some(&'=');
+                let op = match (c, next_eq) {
+                    ('=', true) => CmpOp::Eq,
+                    ('!', true) => CmpOp::Ne,
+                    ('>', true) => CmpOp::Ge,
+                    ('<', true) => CmpOp::Le,
+                    ('>', false) => CmpOp::Gt,
+                    ('<', false) => CmpOp::Lt,
+                    _ => return Err(format!("Invalid operator at position {}", pos)),
+                };
+                tokens.push(Token::Op(op));
+                pos += if next_eq { 2 } else { 1 };
+            }
+            _ if c.is_ascii_alphanumeric() || c == '_' || c == '.' => {
+                let start = pos;
+                while pos < chars.len()
+                    && (chars[pos].is_ascii_alphanumeric()
+                        || chars[pos] == '_'
+                        || chars[pos] == '.')
+                {
+                    pos += 1;
+                }
+                let word = chars[start..pos].iter().collect::<String>();
+                let token = if word.starts_with("0x") {
+                    H256::from_str(&word[2..])
+                        .map(Token::Hash)
+                        .map_err(|err| format!("Invalid hash {}: {}", word, err))?
+                } else if c.is_ascii_digit() {
+                    word.parse::<u64>()
+                        .map(Token::Number)
+                        .map_err(|err| format!("Invalid number {}: {}", word, err))?
+                } else {
+                    match word.to_lowercase().as_str() {
+                        "and" => Token::And,
+                        "or" => Token::Or,
+                        "not" => Token::Not,
+                        "null" => Token::Null,
+                        _ => Token::Ident(word),
+                    }
+                };
+                tokens.push(token);
+            }
+            _ => return Err(format!("Unexpected character in filter: {}", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| "Unexpected end of filter".to_owned())?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn parse_or(&mut self) -> Result<CellFilter, String> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = CellFilter::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<CellFilter, String> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = CellFilter::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<CellFilter, String> {
+        match self.next()? {
+            Token::Not => Ok(CellFilter::Not(Box::new(self.parse_unary()?))),
+            Token::LParen => {
+                let inner = self.parse_or()?;
+                match self.next()? {
+                    Token::RParen => Ok(inner),
+                    token => Err(format!("Expected `)`, got: {}", token)),
+                }
+            }
+            Token::Ident(name) => {
+                let field: Field = name.parse()?;
+                let op = match self.next()? {
+                    Token::Op(op) => op,
+                    token => {
+                        return Err(format!("Expected operator after {}, got: {}", name, token))
+                    }
+                };
+                let value = match self.next()? {
+                    Token::Number(number) if !field.is_hash() => Value::Number(number),
+                    Token::Hash(hash) if field.is_hash() => Value::Hash(hash),
+                    Token::Null if field.is_hash() => Value::Null,
+                    token => return Err(format!("Invalid value for {}: {}", name, token)),
+                };
+                if field.is_hash() && op != CmpOp::Eq && op != CmpOp::Ne {
+                    return Err(format!("Only == and != are allowed for {}", name));
+                }
+                Ok(CellFilter::Compare(field, op, value))
+            }
+            token => Err(format!("Unexpected token in filter: {}", token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_index::CellIndex;
+    use ckb_types::h256;
+
+    fn cell(capacity: u64, data_bytes: u64, type_hashes: Option<(H256, H256)>) -> LiveCellInfo {
+        LiveCellInfo {
+            tx_hash: h256!("0x1"),
+            tx_index: 0,
+            data_bytes,
+            lock_hash: h256!("0x2"),
+            type_hashes,
+            capacity,
+            number: 10,
+            index: CellIndex {
+                tx_index: 0,
+                output_index: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_cell_filter() {
+        let code_hash = h256!("0x3");
+        let filter: CellFilter = format!(
+            "capacity > 1000 and type.code_hash == {:#x} and data.len == 16",
+            code_hash
+        )
+        .parse()
+        .unwrap();
+        let typed = Some((code_hash.clone(), h256!("0x4")));
+        assert!(filter.matches(&cell(1001, 16, typed.clone())));
+        assert!(!filter.matches(&cell(1000, 16, typed.clone())));
+        assert!(!filter.matches(&cell(1001, 15, typed.clone())));
+        assert!(!filter.matches(&cell(1001, 16, None)));
+
+        let filter: CellFilter = "not (type.hash == null or capacity <= 10)".parse().unwrap();
+        assert!(filter.matches(&cell(11, 0, typed.clone())));
+        assert!(!filter.matches(&cell(11, 0, None)));
+        assert!(!filter.matches(&cell(10, 0, typed)));
+
+        assert!("capacity > 0x01".parse::<CellFilter>().is_err());
+        assert!("lock_hash > null".parse::<CellFilter>().is_err());
+        assert!("unknown == 1".parse::<CellFilter>().is_err());
+        assert!("(capacity == 1".parse::<CellFilter>().is_err());
+        assert!("capacity == 1 capacity".parse::<CellFilter>().is_err());
+    }
+}
@@ -1,17 +1,56 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use ansi_term::Colour::Yellow;
+use ckb_sdk::CustomNetwork;
+use ckb_types::H160;
 use ckb_util::RwLock;
 use regex::{Captures, Regex};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::subcommands::wallet::IndexThreadState;
-use crate::utils::printer::{OutputFormat, Printable};
+use crate::utils::printer::{format_capacity, OutputFormat, Printable};
 
 const DEFAULT_JSONRPC_URL: &str = "http://127.0.0.1:8114";
+// Wallet refuses to send transactions paying more than this (shannons)
+pub const DEFAULT_MAX_TX_FEE: u64 = ckb_sdk::ONE_CKB;
+
+/// Per environment settings, switched by `--profile` or `config --use-profile`
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub default_account: Option<H160>,
+    #[serde(default)]
+    pub broadcast_urls: Vec<String>,
+    #[serde(default)]
+    pub fee_rates: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub max_tx_fee: Option<u64>,
+    // Sub directory of the index directory (index database, pending
+    // transactions, memos, ...), None uses the index directory itself
+    #[serde(default)]
+    pub db_namespace: Option<String>,
+}
+
+fn check_db_namespace(namespace: &str) -> Result<(), String> {
+    let valid = !namespace.is_empty()
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid db namespace (letters, digits, '-' and '_' only): {}",
+            namespace
+        ))
+    }
+}
 
 pub struct GlobalConfig {
     url: Option<String>,
@@ -21,6 +60,25 @@ pub struct GlobalConfig {
     path: PathBuf,
     completion_style: bool,
     edit_style: bool,
+    default_account: Option<H160>,
+    broadcast_urls: Vec<String>,
+    // Fee rates (shannons/KB) of `wallet transfer --fee <preset>`
+    fee_rates: BTreeMap<String, u64>,
+    // Max fee (shannons) of a transaction sent by wallet
+    max_tx_fee: u64,
+    db_namespace: Option<String>,
+    // Url of the fiat price feed shown in previews
+    price_feed_url: Option<String>,
+    // Live cells with less confirmations are ignored by balances and input selection
+    min_confirmations: u64,
+    // Chains other than mainnet/testnet
+    networks: Vec<CustomNetwork>,
+    // Command aliases: name => command line
+    aliases: BTreeMap<String, String>,
+    // None means the default profile (top level settings)
+    profile: Option<String>,
+    default_profile: Profile,
+    profiles: BTreeMap<String, Profile>,
     env_variable: HashMap<String, serde_json::Value>,
     index_state: Arc<RwLock<IndexThreadState>>,
 }
@@ -35,6 +93,18 @@ impl GlobalConfig {
             path: env::current_dir().unwrap(),
             completion_style: true,
             edit_style: true,
+            default_account: None,
+            broadcast_urls: Vec::new(),
+            fee_rates: BTreeMap::new(),
+            max_tx_fee: DEFAULT_MAX_TX_FEE,
+            db_namespace: None,
+            price_feed_url: None,
+            min_confirmations: 0,
+            networks: Vec::new(),
+            aliases: BTreeMap::new(),
+            profile: None,
+            default_profile: Profile::default(),
+            profiles: BTreeMap::new(),
             env_variable: HashMap::new(),
             index_state,
         }
@@ -154,6 +224,152 @@ impl GlobalConfig {
         self.edit_style = value;
     }
 
+    pub fn set_default_account(&mut self, value: Option<H160>) {
+        self.default_account = value;
+    }
+
+    pub fn set_broadcast_urls(&mut self, value: Vec<String>) {
+        self.broadcast_urls = value
+            .into_iter()
+            .map(|url| {
+                if url.starts_with("http://") || url.starts_with("https://") {
+                    url
+                } else {
+                    "http://".to_owned() + &url
+                }
+            })
+            .collect();
+    }
+
+    pub fn set_fee_rates(&mut self, value: BTreeMap<String, u64>) {
+        self.fee_rates = value;
+    }
+
+    pub fn set_max_tx_fee(&mut self, value: u64) {
+        self.max_tx_fee = value;
+    }
+
+    pub fn set_db_namespace(&mut self, value: Option<String>) -> Result<(), String> {
+        if let Some(namespace) = value.as_ref() {
+            check_db_namespace(namespace)?;
+        }
+        self.db_namespace = value;
+        Ok(())
+    }
+
+    pub fn set_price_feed_url(&mut self, value: Option<String>) {
+        self.price_feed_url = value;
+    }
+
+    pub fn set_min_confirmations(&mut self, value: u64) {
+        self.min_confirmations = value;
+    }
+
+    pub fn set_networks(&mut self, value: Vec<CustomNetwork>) {
+        ckb_sdk::set_custom_networks(value.clone());
+        self.networks = value;
+    }
+
+    /// Add or replace (by name) a custom network
+    pub fn add_network(&mut self, network: CustomNetwork) -> Result<(), String> {
+        network.check()?;
+        let mut networks = self.networks.clone();
+        networks.retain(|item| item.name != network.name);
+        if let Some(other) = networks
+            .iter()
+            .find(|item| item.genesis_hash == network.genesis_hash)
+        {
+            return Err(format!(
+                "Genesis hash {:#x} is used by network {}",
+                network.genesis_hash, other.name
+            ));
+        }
+        networks.push(network);
+        self.set_networks(networks);
+        Ok(())
+    }
+
+    pub fn remove_network(&mut self, name: &str) -> Result<(), String> {
+        let mut networks = self.networks.clone();
+        let len = networks.len();
+        networks.retain(|item| item.name != name);
+        if networks.len() == len {
+            return Err(format!("Network not found: {}", name));
+        }
+        self.set_networks(networks);
+        Ok(())
+    }
+
+    pub fn set_aliases(&mut self, value: BTreeMap<String, String>) {
+        self.aliases = value;
+    }
+
+    pub fn add_profile(&mut self, name: String, profile: Profile) {
+        self.profiles.insert(name, profile);
+    }
+
+    /// Save current settings into current profile, then load settings from
+    /// target profile (None for default profile).
+    pub fn use_profile(&mut self, name: Option<String>) -> Result<(), String> {
+        let target = match name {
+            Some(ref name) => self
+                .profiles
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("Profile not found: {}", name))?,
+            None => self.default_profile.clone(),
+        };
+        if let Some(namespace) = target.db_namespace.as_ref() {
+            check_db_namespace(namespace)?;
+        }
+        let current = self.current_profile();
+        match self.profile {
+            Some(ref name) => {
+                self.profiles.insert(name.clone(), current);
+            }
+            None => self.default_profile = current,
+        }
+        self.url = None;
+        if let Some(url) = target.url {
+            self.set_url(url);
+        }
+        self.default_account = target.default_account;
+        self.set_broadcast_urls(target.broadcast_urls);
+        self.fee_rates = target.fee_rates;
+        self.max_tx_fee = target.max_tx_fee.unwrap_or(DEFAULT_MAX_TX_FEE);
+        self.db_namespace = target.db_namespace;
+        self.profile = name;
+        Ok(())
+    }
+
+    pub fn current_profile(&self) -> Profile {
+        Profile {
+            url: self.url.clone(),
+            default_account: self.default_account.clone(),
+            broadcast_urls: self.broadcast_urls.clone(),
+            fee_rates: self.fee_rates.clone(),
+            max_tx_fee: Some(self.max_tx_fee),
+            db_namespace: self.db_namespace.clone(),
+        }
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_ref().map(String::as_str)
+    }
+
+    /// Default profile and named profiles, current settings are saved into current profile
+    pub fn profiles(&self) -> (Profile, BTreeMap<String, Profile>) {
+        let mut default_profile = self.default_profile.clone();
+        let mut profiles = self.profiles.clone();
+        match self.profile {
+            Some(ref name) => {
+                profiles.insert(name.clone(), self.current_profile());
+            }
+            None => default_profile = self.current_profile(),
+        }
+        (default_profile, profiles)
+    }
+
     pub fn color(&self) -> bool {
         self.color
     }
@@ -174,6 +390,51 @@ impl GlobalConfig {
         self.edit_style
     }
 
+    pub fn default_account(&self) -> Option<&H160> {
+        self.default_account.as_ref()
+    }
+
+    pub fn broadcast_urls(&self) -> &[String] {
+        &self.broadcast_urls
+    }
+
+    pub fn fee_rates(&self) -> &BTreeMap<String, u64> {
+        &self.fee_rates
+    }
+
+    pub fn max_tx_fee(&self) -> u64 {
+        self.max_tx_fee
+    }
+
+    pub fn db_namespace(&self) -> Option<&str> {
+        self.db_namespace.as_ref().map(String::as_str)
+    }
+
+    /// Index directory of current profile
+    pub fn index_dir(&self, ckb_cli_dir: &Path) -> PathBuf {
+        let index_dir = ckb_cli_dir.join("index");
+        match self.db_namespace {
+            Some(ref namespace) => index_dir.join(namespace),
+            None => index_dir,
+        }
+    }
+
+    pub fn price_feed_url(&self) -> Option<&str> {
+        self.price_feed_url.as_ref().map(String::as_str)
+    }
+
+    pub fn min_confirmations(&self) -> u64 {
+        self.min_confirmations
+    }
+
+    pub fn networks(&self) -> &[CustomNetwork] {
+        &self.networks
+    }
+
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
     pub fn print(&self) {
         let path = self.path.to_string_lossy();
         let color = self.color.to_string();
@@ -185,18 +446,60 @@ impl GlobalConfig {
             "Circular"
         };
         let edit_style = if self.edit_style { "Emacs" } else { "Vi" };
+        let default_account = self
+            .default_account
+            .as_ref()
+            .map(|lock_arg| format!("{:x}", lock_arg))
+            .unwrap_or_else(|| "None".to_owned());
+        let broadcast_urls = if self.broadcast_urls.is_empty() {
+            "None".to_owned()
+        } else {
+            self.broadcast_urls.join(", ")
+        };
+        let fee_rates = if self.fee_rates.is_empty() {
+            "None".to_owned()
+        } else {
+            self.fee_rates
+                .iter()
+                .map(|(preset, rate)| format!("{}={}", preset, rate))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let max_tx_fee = format!("{} CKB", format_capacity(self.max_tx_fee));
+        let price_feed_url = self.price_feed_url().unwrap_or("None");
+        let min_confirmations = self.min_confirmations.to_string();
+        let networks = if self.networks.is_empty() {
+            "None".to_owned()
+        } else {
+            self.networks
+                .iter()
+                .map(|network| format!("{} ({})", network.name, network.address_prefix))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let offline = ckb_sdk::is_offline().to_string();
         let index_state = self.index_state.read().to_string();
         let version = crate::get_version();
         let version_long = version.long();
         let values = [
             ("ckb-cli version", version_long.as_str()),
+            ("profile", self.profile().unwrap_or("default")),
             ("url", self.get_url()),
+            ("offline", offline.as_str()),
             ("pwd", path.deref()),
             ("color", color.as_str()),
             ("debug", debug.as_str()),
             ("output format", output_format.as_str()),
             ("completion style", completion_style),
             ("edit style", edit_style),
+            ("default account", default_account.as_str()),
+            ("broadcast urls", broadcast_urls.as_str()),
+            ("fee rates", fee_rates.as_str()),
+            ("max tx fee", max_tx_fee.as_str()),
+            ("db namespace", self.db_namespace().unwrap_or("None")),
+            ("price feed url", price_feed_url),
+            ("min confirmations", min_confirmations.as_str()),
+            ("custom networks", networks.as_str()),
             ("index db state", index_state.as_str()),
         ];
 
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+use ckb_hash::blake2b_256;
+use ckb_types::H256;
+use serde_derive::Serialize;
+
+// Changed ranges closer than this are reported as one range
+const RANGE_MERGE_GAP: usize = 16;
+// Section without content in file (.bss)
+const SHT_NOBITS: u32 = 8;
+
+/// Byte range [start, end) which differs between old and new data
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct ChangedRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Ranges of differing bytes, compared by offset. The tail of the longer
+/// data is one changed range.
+pub fn changed_ranges(old: &[u8], new: &[u8]) -> Vec<ChangedRange> {
+    let mut ranges: Vec<ChangedRange> = Vec::new();
+    let max_len = old.len().max(new.len());
+    for offset in 0..max_len {
+        if old.get(offset) == new.get(offset) {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(range) if offset <= range.end + RANGE_MERGE_GAP => range.end = offset + 1,
+            _ => ranges.push(ChangedRange {
+                start: offset,
+                end: offset + 1,
+            }),
+        }
+    }
+    ranges
+}
+
+/// A section of an ELF file
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ElfSection {
+    pub name: String,
+    pub size: u64,
+    // None for sections without content in file (.bss)
+    pub hash: Option<H256>,
+}
+
+/// Sections of a 64-bit little endian ELF file (RISC-V scripts), None if
+/// the data is not such an ELF file.
+pub fn elf_sections(data: &[u8]) -> Option<Vec<ElfSection>> {
+    // ELFCLASS64, ELFDATA2LSB
+    if data.get(0..4)? != b"\x7fELF" || data.get(4)? != &2 || data.get(5)? != &1 {
+        return None;
+    }
+    let shoff = read_u64(data, 0x28)? as usize;
+    let shentsize = read_u16(data, 0x3a)? as usize;
+    let shnum = read_u16(data, 0x3c)? as usize;
+    let shstrndx = read_u16(data, 0x3e)? as usize;
+    if shentsize < 0x40 {
+        return None;
+    }
+    let header = |index: usize| -> Option<(u32, u32, u64, u64)> {
+        let base = shoff.checked_add(index.checked_mul(shentsize)?)?;
+        Some((
+            read_u32(data, base)?,
+            read_u32(data, base + 4)?,
+            read_u64(data, base + 24)?,
+            read_u64(data, base + 32)?,
+        ))
+    };
+    let (_, _, strtab_offset, strtab_size) = header(shstrndx)?;
+    let strtab =
+        data.get(strtab_offset as usize..(strtab_offset.checked_add(strtab_size)?) as usize)?;
+    let mut sections = Vec::with_capacity(shnum);
+    for index in 1..shnum {
+        let (name_offset, section_type, offset, size) = header(index)?;
+        let name = strtab
+            .get(name_offset as usize..)
+            .and_then(|bytes| bytes.split(|byte| *byte == 0).next())
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+            .unwrap_or_default();
+        let hash = if section_type == SHT_NOBITS {
+            None
+        } else {
+            let content = data.get(offset as usize..(offset.checked_add(size)?) as usize)?;
+            Some(H256::from(blake2b_256(content)))
+        };
+        sections.push(ElfSection { name, size, hash });
+    }
+    Some(sections)
+}
+
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct SectionDiff {
+    pub name: String,
+    // same, changed, added or removed
+    pub status: &'static str,
+    pub old_size: Option<u64>,
+    pub new_size: Option<u64>,
+}
+
+/// Compare sections by name
+pub fn diff_sections(old: &[ElfSection], new: &[ElfSection]) -> Vec<SectionDiff> {
+    let mut names: BTreeMap<&str, (Option<&ElfSection>, Option<&ElfSection>)> = BTreeMap::new();
+    for section in old {
+        names.entry(section.name.as_str()).or_default().0 = Some(section);
+    }
+    for section in new {
+        names.entry(section.name.as_str()).or_default().1 = Some(section);
+    }
+    names
+        .into_iter()
+        .map(|(name, (old, new))| {
+            let status = match (old, new) {
+                (Some(old), Some(new)) if old == new => "same",
+                (Some(_), Some(_)) => "changed",
+                (None, Some(_)) => "added",
+                _ => "removed",
+            };
+            SectionDiff {
+                name: name.to_owned(),
+                status,
+                old_size: old.map(|section| section.size),
+                new_size: new.map(|section| section.size),
+            }
+        })
+        .collect()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ELF header + ".text" content + shstrtab, then 3 section headers
+    // (null, .text, .shstrtab)
+    fn build_elf(text: &[u8]) -> Vec<u8> {
+        let strtab = b"\0.text\0.shstrtab\0";
+        let text_offset = 0x40;
+        let strtab_offset = text_offset + text.len();
+        let shoff = strtab_offset + strtab.len();
+        let mut data = vec![0u8; 0x40];
+        data[0..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2;
+        data[5] = 1;
+        data[0x28..0x30].copy_from_slice(&(shoff as u64).to_le_bytes());
+        data[0x3a..0x3c].copy_from_slice(&0x40u16.to_le_bytes());
+        data[0x3c..0x3e].copy_from_slice(&3u16.to_le_bytes());
+        data[0x3e..0x40].copy_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(text);
+        data.extend_from_slice(strtab);
+        let section = |name: u32, section_type: u32, offset: usize, size: usize| {
+            let mut header = vec![0u8; 0x40];
+            header[0..4].copy_from_slice(&name.to_le_bytes());
+            header[4..8].copy_from_slice(&section_type.to_le_bytes());
+            header[24..32].copy_from_slice(&(offset as u64).to_le_bytes());
+            header[32..40].copy_from_slice(&(size as u64).to_le_bytes());
+            header
+        };
+        data.extend(section(0, 0, 0, 0));
+        data.extend(section(1, 1, text_offset, text.len()));
+        data.extend(section(7, 3, strtab_offset, strtab.len()));
+        data
+    }
+
+    #[test]
+    fn test_changed_ranges() {
+        assert!(changed_ranges(b"abc", b"abc").is_empty());
+        assert_eq!(
+            changed_ranges(b"abcdef", b"abXdeY"),
+            vec![ChangedRange { start: 2, end: 6 }]
+        );
+        let mut new = vec![0u8; 64];
+        new[0] = 1;
+        new[40] = 1;
+        assert_eq!(
+            changed_ranges(&[0u8; 32], &new),
+            vec![
+                ChangedRange { start: 0, end: 1 },
+                ChangedRange { start: 32, end: 64 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_elf_sections() {
+        let old = elf_sections(&build_elf(b"\x13\x00\x00\x00")).unwrap();
+        assert_eq!(old.len(), 2);
+        assert_eq!(old[0].name, ".text");
+        assert_eq!(old[0].size, 4);
+        assert_eq!(old[1].name, ".shstrtab");
+        assert!(elf_sections(b"not an elf file").is_none());
+
+        let new = elf_sections(&build_elf(b"\x73\x00\x00\x00\x13\x00\x00\x00")).unwrap();
+        let diffs = diff_sections(&old, &new);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].name, ".shstrtab");
+        assert_eq!(diffs[0].status, "same");
+        assert_eq!(diffs[1].name, ".text");
+        assert_eq!(diffs[1].status, "changed");
+        assert_eq!(diffs[1].new_size, Some(8));
+    }
+}
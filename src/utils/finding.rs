@@ -0,0 +1,115 @@
+use ckb_sdk::{Severity, TxFinding};
+
+/// How findings of a transaction check are printed
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FindingFormat {
+    // The normal command output (json/yaml)
+    Report,
+    // One json object per line
+    Jsonl,
+    // GitHub Actions workflow commands (annotations)
+    Github,
+}
+
+impl FindingFormat {
+    pub fn from_name(name: &str) -> Result<FindingFormat, String> {
+        match name {
+            "report" => Ok(FindingFormat::Report),
+            "jsonl" => Ok(FindingFormat::Jsonl),
+            "github" => Ok(FindingFormat::Github),
+            _ => Err(format!("Invalid findings format: {}", name)),
+        }
+    }
+}
+
+/// Render findings one per line, `file` is the checked transaction file
+/// (used as annotation file in github format).
+pub fn render_findings(format: FindingFormat, file: &str, findings: &[TxFinding]) -> Vec<String> {
+    findings
+        .iter()
+        .map(|finding| match format {
+            FindingFormat::Github => {
+                let command = match finding.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                format!(
+                    "::{} file={},title={}::{}: {}",
+                    command,
+                    escape_property(file),
+                    escape_property(&finding.code),
+                    escape_data(&finding.location),
+                    escape_data(&finding.message),
+                )
+            }
+            _ => {
+                let mut value = serde_json::to_value(finding).expect("serialize finding");
+                value["file"] = serde_json::json!(file);
+                value.to_string()
+            }
+        })
+        .collect()
+}
+
+pub fn count_errors(findings: &[TxFinding]) -> usize {
+    findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count()
+}
+
+// See: https://github.com/actions/toolkit/blob/main/packages/core/src/command.ts
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_findings() {
+        let findings = vec![
+            TxFinding::error(
+                "duplicated-input",
+                "inputs[1]".to_owned(),
+                "Duplicated input: 0xff-0".to_owned(),
+            ),
+            TxFinding::warning(
+                "since-not-satisfied",
+                "inputs[0]".to_owned(),
+                "100% of\nthe epoch".to_owned(),
+            ),
+        ];
+        assert_eq!(count_errors(&findings), 1);
+        assert_eq!(
+            render_findings(FindingFormat::Github, "tx,1.json", &findings),
+            vec![
+                "::error file=tx%2C1.json,title=duplicated-input::inputs[1]: Duplicated input: 0xff-0"
+                    .to_owned(),
+                "::warning file=tx%2C1.json,title=since-not-satisfied::inputs[0]: 100%25 of%0Athe epoch"
+                    .to_owned(),
+            ]
+        );
+
+        let lines = render_findings(FindingFormat::Jsonl, "tx.json", &findings[..1]);
+        let value: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "severity": "error",
+                "code": "duplicated-input",
+                "location": "inputs[1]",
+                "message": "Duplicated input: 0xff-0",
+                "file": "tx.json",
+            })
+        );
+    }
+}
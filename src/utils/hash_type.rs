@@ -0,0 +1,97 @@
+use ckb_types::{packed::Script, prelude::*};
+
+// Raw value of `Script.hash_type`
+// See: https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0032-ckb-vm-version-selection/0032-ckb-vm-version-selection.md
+const HASH_TYPE_DATA: u8 = 0;
+const HASH_TYPE_TYPE: u8 = 1;
+const HASH_TYPE_DATA1: u8 = 2;
+const HASH_TYPE_DATA2: u8 = 4;
+
+/// The highest VM version the connected chain version runs scripts under
+pub const MAX_VM_VERSION: u8 = 0;
+
+/// Hash type of a script together with the VM version it selects
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct HashTypeInfo {
+    pub name: &'static str,
+    pub vm_version: u8,
+}
+
+impl HashTypeInfo {
+    pub fn from_u8(value: u8) -> Result<HashTypeInfo, String> {
+        let (name, vm_version) = match value {
+            HASH_TYPE_DATA => ("data", 0),
+            // `type` always runs under the latest VM version of the chain
+            HASH_TYPE_TYPE => ("type", MAX_VM_VERSION),
+            HASH_TYPE_DATA1 => ("data1", 1),
+            HASH_TYPE_DATA2 => ("data2", 2),
+            _ => return Err(format!("Invalid script hash type: {}", value)),
+        };
+        if vm_version > MAX_VM_VERSION {
+            return Err(format!(
+                "Script hash type {} requires VM version {}, not supported by this chain version (max VM version: {})",
+                name, vm_version, MAX_VM_VERSION
+            ));
+        }
+        Ok(HashTypeInfo { name, vm_version })
+    }
+
+    pub fn from_name(name: &str) -> Result<HashTypeInfo, String> {
+        let value = match name {
+            "data" => HASH_TYPE_DATA,
+            "type" => HASH_TYPE_TYPE,
+            "data1" => HASH_TYPE_DATA1,
+            "data2" => HASH_TYPE_DATA2,
+            _ => {
+                return Err(format!(
+                    "Invalid script hash type: {}, expected one of: data, type, data1, data2",
+                    name
+                ))
+            }
+        };
+        HashTypeInfo::from_u8(value)
+    }
+
+    pub fn from_script(script: &Script) -> Result<HashTypeInfo, String> {
+        HashTypeInfo::from_u8(script.hash_type().as_slice()[0])
+    }
+}
+
+/// Check `hash_type` of a json script before it is deserialized, so newer hash
+/// types get a readable error instead of a serde error.
+pub fn check_json_script(value: &serde_json::Value) -> Result<(), String> {
+    match value.get("hash_type").and_then(serde_json::Value::as_str) {
+        Some(name) => HashTypeInfo::from_name(name).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_type() {
+        assert_eq!(
+            HashTypeInfo::from_u8(0),
+            Ok(HashTypeInfo {
+                name: "data",
+                vm_version: 0
+            })
+        );
+        assert_eq!(HashTypeInfo::from_name("type").unwrap().name, "type");
+        assert!(HashTypeInfo::from_u8(2)
+            .unwrap_err()
+            .contains("VM version 1"));
+        assert!(HashTypeInfo::from_name("data2")
+            .unwrap_err()
+            .contains("VM version 2"));
+        assert!(HashTypeInfo::from_u8(3).is_err());
+        assert!(HashTypeInfo::from_name("Data").is_err());
+
+        let script = serde_json::json!({"code_hash": "0x00", "hash_type": "data1", "args": "0x"});
+        assert!(check_json_script(&script).is_err());
+        let script = serde_json::json!({"code_hash": "0x00", "hash_type": "data", "args": "0x"});
+        assert!(check_json_script(&script).is_ok());
+    }
+}
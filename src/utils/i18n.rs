@@ -0,0 +1,152 @@
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static LANG: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Lang {
+    En,
+    ZhCn,
+}
+
+impl Lang {
+    /// Detect language from LC_ALL / LC_MESSAGES / LANG environment variables
+    pub fn detect() -> Lang {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| value.split('.').next().and_then(|s| s.parse().ok()))
+            .unwrap_or(Lang::En)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::ZhCn => "zh-CN",
+        }
+    }
+}
+
+impl FromStr for Lang {
+    type Err = String;
+    fn from_str(input: &str) -> Result<Lang, String> {
+        match input.to_lowercase().replace('_', "-").as_str() {
+            "en" | "en-us" | "en-gb" | "c" | "posix" => Ok(Lang::En),
+            "zh" | "zh-cn" | "zh-hans" => Ok(Lang::ZhCn),
+            _ => Err(format!("Unsupported language: {}", input)),
+        }
+    }
+}
+
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as usize, Ordering::SeqCst);
+}
+
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::SeqCst) {
+        1 => Lang::ZhCn,
+        _ => Lang::En,
+    }
+}
+
+/// Translate a user facing message (prompts, confirmations, error explanations).
+///
+/// Machine readable output (json/yaml results) must never go through here.
+pub fn tr(msg: &'static str) -> &'static str {
+    match lang() {
+        Lang::En => msg,
+        Lang::ZhCn => zh_cn(msg).unwrap_or(msg),
+    }
+}
+
+/// Translate a message template, then fill its `{}` placeholders with `args`
+/// in order (translations keep the order of placeholders).
+pub fn tr_fmt(template: &'static str, args: &[&dyn fmt::Display]) -> String {
+    fill(tr(template), args)
+}
+
+fn fill(template: &str, args: &[&dyn fmt::Display]) -> String {
+    let mut parts = template.split("{}");
+    let mut result = parts.next().unwrap_or_default().to_owned();
+    for (index, part) in parts.enumerate() {
+        if let Some(arg) = args.get(index) {
+            result.push_str(&arg.to_string());
+        }
+        result.push_str(part);
+    }
+    result
+}
+
+fn zh_cn(msg: &str) -> Option<&'static str> {
+    let translated = match msg {
+        "Password" => "密码",
+        "Repeat password" => "重复密码",
+        "Passwords do not match" => "两次输入的密码不一致",
+        "Old password" => "旧密码",
+        "New password" => "新密码",
+        "Password required to unlock the keystore" => "需要输入密码来解锁 keystore",
+        "Password to encrypt the sweep transaction" => "用于加密清扫交易的密码",
+        "Password of the sweep transaction" => "清扫交易的密码",
+        "Capacity not enough" => "余额不足",
+        "Amount not enough" => "资产余额不足",
+        "Send transaction error" => "发送交易失败",
+        "index database may not ready, sync process" => "索引数据库尚未就绪，同步进度",
+        "<privkey-path> or <from-account> is required (or set default account by `config --default-account`)" => {
+            "需要 <privkey-path> 或 <from-account> 参数（或通过 `config --default-account` 设置默认账户）"
+        }
+        "No previous history." => "没有历史命令记录。",
+        "Save command history failed" => "保存命令历史失败",
+        "Process error" => "处理出错",
+        "Password for [{}]" => "账户 [{}] 的密码",
+        "{} (type \"{}\" to confirm): " => "{}（输入 \"{}\" 以确认）：",
+        "Not confirmed, aborted" => "未确认，已取消",
+        "Burn {} CKB, it can never be spent again" => "销毁 {} CKB，销毁后将永远无法花费",
+        "File exists: {} (use --force to overwrite)" => "文件已存在：{}（使用 --force 覆盖）",
+        "Transfer amount can not be zero" => "转账金额不能为零",
+        "Fee {} is more than {} of transfer capacity {}, use --force if it is intended" => {
+            "手续费 {} 超过转账金额的 {}（转账金额 {}），如确认无误请使用 --force"
+        }
+        "Transfer spends the entire balance and leaves no change, use --force if it is intended" => {
+            "本次转账将花光全部余额且没有找零，如确认无误请使用 --force"
+        }
+        "Transfer capacity {} is more than {} of balance {}, use --force if it is intended" => {
+            "转账金额 {} 超过余额的 {}（余额 {}），如确认无误请使用 --force"
+        }
+        "Fee {} is more than {} of the sUDT cell capacity {}, use --force if it is intended" => {
+            "手续费 {} 超过 sUDT cell 容量的 {}（容量 {}），如确认无误请使用 --force"
+        }
+        "Transfer spends the entire sUDT balance and leaves no change, use --force if it is intended" => {
+            "本次转账将花光全部 sUDT 余额且没有找零，如确认无误请使用 --force"
+        }
+        "Transfer amount {} is more than {} of sUDT balance {}, use --force if it is intended" => {
+            "转账数量 {} 超过 sUDT 余额的 {}（余额 {}），如确认无误请使用 --force"
+        }
+        "Refuse to send transaction: fee {} CKB is higher than max tx fee {} CKB (see: config --max-tx-fee)" => {
+            "拒绝发送交易：手续费 {} CKB 高于最大手续费 {} CKB（参见：config --max-tx-fee）"
+        }
+        "Conflicting transaction: {}" => "交易冲突：{}",
+        "input {} is already spent by transaction {} ({})" => "输入 {} 已被交易 {} 花费（{}）",
+        "Idempotency key {} is reserved by another running command, remove {} if no such command is running" => {
+            "幂等键 {} 正被另一个运行中的命令占用，如没有这样的命令在运行，请删除 {}"
+        }
+        _ => return None,
+    };
+    Some(translated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(fill("Fee {} of {}", &[&1, &"2"]), "Fee 1 of 2");
+        assert_eq!(fill("{} (missing {})", &[&"a"]), "a (missing )");
+        assert_eq!(fill("No placeholder", &[&1]), "No placeholder");
+        let template = "Conflicting transaction: {}";
+        assert_eq!(fill(zh_cn(template).unwrap(), &[&"0x01"]), "交易冲突：0x01");
+    }
+}
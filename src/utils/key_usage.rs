@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ckb_types::{H160, H256};
+use serde_derive::{Deserialize, Serialize};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Signing history of a key
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct KeyUsage {
+    pub sign_count: u64,
+    // Unix timestamp in seconds
+    pub last_signed_at: u64,
+    pub last_tx_hash: Option<H256>,
+}
+
+/// Usage of keystore accounts saved in `<keystore-dir>/key-usage.json`,
+/// keyed by lock arg.
+pub struct KeyUsages {
+    path: PathBuf,
+    usages: BTreeMap<H160, KeyUsage>,
+}
+
+impl KeyUsages {
+    pub fn load(keystore_dir: &Path) -> Result<KeyUsages, String> {
+        let path = keystore_dir.join("key-usage.json");
+        let usages = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Invalid key usage file: {}", err))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(KeyUsages { path, usages })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.usages).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+
+    pub fn get(&self, lock_arg: &H160) -> Option<&KeyUsage> {
+        self.usages.get(lock_arg)
+    }
+
+    pub fn record(&mut self, lock_arg: H160, tx_hash: Option<H256>, now: u64) {
+        let usage = self
+            .usages
+            .entry(lock_arg)
+            .or_insert_with(KeyUsage::default);
+        usage.sign_count += 1;
+        usage.last_signed_at = now;
+        if tx_hash.is_some() {
+            usage.last_tx_hash = tx_hash;
+        }
+    }
+
+    /// Never used or not used in last `days` days
+    pub fn is_stale(&self, lock_arg: &H160, days: u64, now: u64) -> bool {
+        self.get(lock_arg)
+            .map(|usage| now.saturating_sub(usage.last_signed_at) > days * SECS_PER_DAY)
+            .unwrap_or(true)
+    }
+}
+
+/// Record signings of keys into the usage file of the keystore, errors are
+/// printed since the signing itself succeeded.
+pub fn record_key_usage(keystore_dir: &Path, lock_args: &[H160], tx_hash: Option<H256>) {
+    if lock_args.is_empty() {
+        return;
+    }
+    let result = KeyUsages::load(keystore_dir).and_then(|mut usages| {
+        let now = now_secs();
+        for lock_arg in lock_args {
+            usages.record(lock_arg.clone(), tx_hash.clone(), now);
+        }
+        usages.save()
+    });
+    if let Err(err) = result {
+        eprintln!("[WARN]: record key usage failed: {}", err);
+    }
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{h160, h256};
+
+    #[test]
+    fn test_key_usages() {
+        let mut usages = KeyUsages {
+            path: PathBuf::new(),
+            usages: BTreeMap::new(),
+        };
+        let key = h160!("0x1");
+        let now = 100 * SECS_PER_DAY;
+        assert!(usages.is_stale(&key, 30, now));
+        usages.record(key.clone(), Some(h256!("0xa")), now - 40 * SECS_PER_DAY);
+        usages.record(key.clone(), None, now - 31 * SECS_PER_DAY);
+        assert_eq!(
+            usages.get(&key),
+            Some(&KeyUsage {
+                sign_count: 2,
+                last_signed_at: now - 31 * SECS_PER_DAY,
+                last_tx_hash: Some(h256!("0xa")),
+            })
+        );
+        assert!(usages.is_stale(&key, 30, now));
+        assert!(!usages.is_stale(&key, 31, now));
+    }
+}
@@ -0,0 +1,150 @@
+use std::fs;
+use std::path::Path;
+
+use ckb_sdk::{OMNILOCK_MAINNET_TYPE_HASH, OMNILOCK_TESTNET_TYPE_HASH};
+use ckb_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{Byte32, Script},
+    prelude::*,
+    H160, H256,
+};
+use serde_derive::{Deserialize, Serialize};
+
+const BLAKE160_PLACEHOLDER: &str = "{blake160}";
+
+/// Lock script of a non-standard lock derived from an account. `args` is a
+/// hex template, `{blake160}` is replaced by the lock arg of the account, for
+/// example omnilock-style args: `0x00{blake160}00`.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct LockTemplate {
+    pub name: String,
+    pub code_hash: H256,
+    // "data" or "type"
+    pub hash_type: String,
+    pub args: String,
+}
+
+impl LockTemplate {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("Lock template name is empty".to_owned());
+        }
+        if self.hash_type != "data" && self.hash_type != "type" {
+            return Err(format!(
+                "Invalid hash type: {}, expected data or type",
+                self.hash_type
+            ));
+        }
+        self.derive_args(&H160::default()).map(|_| ())
+    }
+
+    pub fn derive_args(&self, lock_arg: &H160) -> Result<Bytes, String> {
+        let args_hex = self
+            .args
+            .trim_start_matches("0x")
+            .replace(BLAKE160_PLACEHOLDER, &format!("{:x}", lock_arg));
+        if args_hex.len() % 2 != 0 {
+            return Err(format!("Odd length of args template: {}", self.args));
+        }
+        let mut args = vec![0u8; args_hex.len() / 2];
+        faster_hex::hex_decode(args_hex.as_bytes(), &mut args)
+            .map_err(|err| format!("Invalid args template {}: {:?}", self.args, err))?;
+        Ok(Bytes::from(args))
+    }
+
+    pub fn script(&self, lock_arg: &H160) -> Result<Script, String> {
+        let hash_type = if self.hash_type == "type" {
+            ScriptHashType::Type
+        } else {
+            ScriptHashType::Data
+        };
+        Ok(Script::new_builder()
+            .code_hash(self.code_hash.pack())
+            .hash_type(hash_type.into())
+            .args(self.derive_args(lock_arg)?.pack())
+            .build())
+    }
+
+    pub fn lock_hash(&self, lock_arg: &H160) -> Result<Byte32, String> {
+        self.script(lock_arg)
+            .map(|script| script.calc_script_hash())
+    }
+}
+
+/// Omnilock with secp256k1 auth of the account (mainnet and testnet)
+pub fn builtin_lock_templates() -> Vec<LockTemplate> {
+    vec![
+        ("omnilock-mainnet", OMNILOCK_MAINNET_TYPE_HASH),
+        ("omnilock-testnet", OMNILOCK_TESTNET_TYPE_HASH),
+    ]
+    .into_iter()
+    .map(|(name, code_hash)| LockTemplate {
+        name: name.to_owned(),
+        code_hash,
+        hash_type: "type".to_owned(),
+        args: format!("0x00{}00", BLAKE160_PLACEHOLDER),
+    })
+    .collect()
+}
+
+/// Builtin templates and the registered ones
+pub fn all_lock_templates(path: &Path) -> Result<Vec<LockTemplate>, String> {
+    let mut templates = builtin_lock_templates();
+    templates.extend(load_lock_templates(path)?);
+    Ok(templates)
+}
+
+/// Lock templates saved in `<ckb-cli-dir>/lock-templates.json`
+pub fn load_lock_templates(path: &Path) -> Result<Vec<LockTemplate>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&content).map_err(|err| format!("Invalid lock templates file: {}", err))
+}
+
+pub fn save_lock_templates(path: &Path, templates: &[LockTemplate]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(templates).map_err(|err| err.to_string())?;
+    fs::write(path, content).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_template() {
+        let template = LockTemplate {
+            name: "omnilock".to_owned(),
+            code_hash: H256::default(),
+            hash_type: "type".to_owned(),
+            args: "0x00{blake160}00".to_owned(),
+        };
+        assert!(template.validate().is_ok());
+        let lock_arg = H160::from_slice(&[0x11; 20]).unwrap();
+        let args = template.derive_args(&lock_arg).unwrap();
+        assert_eq!(args.len(), 22);
+        assert_eq!(args[0], 0x00);
+        assert_eq!(&args[1..21], lock_arg.as_bytes());
+        assert_eq!(args[21], 0x00);
+        assert_ne!(
+            template.lock_hash(&lock_arg).unwrap(),
+            template.lock_hash(&H160::default()).unwrap()
+        );
+
+        let mut invalid = template.clone();
+        invalid.args = "0x0{blake160}".to_owned();
+        assert!(invalid.validate().is_err());
+        invalid.args = "0xzz".to_owned();
+        assert!(invalid.validate().is_err());
+        let mut invalid = template;
+        invalid.hash_type = "data1".to_owned();
+        assert!(invalid.validate().is_err());
+
+        for template in builtin_lock_templates() {
+            assert!(template.validate().is_ok());
+            assert_eq!(template.derive_args(&lock_arg).unwrap(), args);
+        }
+    }
+}
@@ -1,10 +1,32 @@
+pub mod address_book;
+pub mod alias;
 pub mod arg;
 pub mod arg_parser;
+pub mod asset;
+pub mod cell_filter;
 pub mod completer;
 pub mod config;
+pub mod data_diff;
+pub mod finding;
+pub mod hash_type;
+pub mod i18n;
+pub mod invocation_log;
 pub mod json_color;
+pub mod key_usage;
+pub mod lock_template;
+pub mod molecule;
 pub mod other;
+pub mod output_spec;
+pub mod payment_request;
+pub mod price;
 pub mod printer;
+pub mod qr;
+pub mod serve_approval;
+pub mod serve_ceremony;
+pub mod serve_token;
+pub mod sign_mode;
+pub mod since;
+pub mod smt;
 
 #[allow(clippy::cast_lossless)]
 pub mod yaml_ser;
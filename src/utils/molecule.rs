@@ -0,0 +1,750 @@
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+use faster_hex::{hex_decode, hex_string};
+use serde_json::{json, Map, Value};
+
+// Common types of blockchain.mol, `import` statements are skipped and these
+// are always available (a schema may define them again).
+const PRELUDE: &str = "
+array Uint32 [byte; 4];
+array Uint64 [byte; 8];
+array Uint128 [byte; 16];
+array Byte32 [byte; 32];
+array Uint256 [byte; 32];
+vector Bytes <byte>;
+option BytesOpt (Bytes);
+vector BytesVec <Bytes>;
+vector Byte32Vec <Byte32>;
+option ScriptOpt (Script);
+table Script {
+    code_hash: Byte32,
+    hash_type: byte,
+    args: Bytes,
+}
+struct OutPoint {
+    tx_hash: Byte32,
+    index: Uint32,
+}
+table WitnessArgs {
+    lock: BytesOpt,
+    input_type: BytesOpt,
+    output_type: BytesOpt,
+}
+";
+
+const BYTE: &str = "byte";
+const HEADER_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum MolType {
+    Array { item: String, size: usize },
+    Struct { fields: Vec<(String, String)> },
+    Vector { item: String },
+    Table { fields: Vec<(String, String)> },
+    Option { item: String },
+    // Item name and its union id
+    Union { items: Vec<(String, u32)> },
+}
+
+/// Types of a molecule schema file, used to encode json values to molecule
+/// bytes (e.g. script args and witnesses of custom contracts) and back.
+///
+/// Json format of values:
+///   * byte: number or 1 byte hex
+///   * byte array / byte vector: hex string (byte array of at most 8 bytes also accepts a number, little endian)
+///   * array / vector: json array
+///   * struct / table: object of all fields
+///   * option: null or the item value
+///   * union: {"type": <item type>, "value": <item value>}
+#[derive(Debug, Clone)]
+pub struct MolSchema {
+    types: BTreeMap<String, MolType>,
+}
+
+impl MolSchema {
+    pub fn parse(source: &str) -> Result<MolSchema, String> {
+        let mut types = BTreeMap::new();
+        parse_definitions(PRELUDE, &mut types)?;
+        let prelude_names = types.keys().cloned().collect::<Vec<_>>();
+        let mut defined = BTreeMap::new();
+        parse_definitions(source, &mut defined)?;
+        for name in prelude_names {
+            if !defined.contains_key(&name) {
+                defined.insert(name.clone(), types.remove(&name).unwrap());
+            }
+        }
+        let schema = MolSchema { types: defined };
+        schema.check()?;
+        Ok(schema)
+    }
+
+    /// Types defined in the schema (prelude types not included)
+    pub fn type_names(source: &str) -> Result<Vec<String>, String> {
+        let mut types = BTreeMap::new();
+        parse_definitions(source, &mut types)?;
+        Ok(types.into_iter().map(|(name, _)| name).collect())
+    }
+
+    pub fn encode(&self, name: &str, value: &Value) -> Result<Vec<u8>, String> {
+        self.encode_inner(name, value)
+            .map_err(|err| format!("Encode {} failed: {}", name, err))
+    }
+
+    pub fn decode(&self, name: &str, data: &[u8]) -> Result<Value, String> {
+        self.decode_inner(name, data)
+            .map_err(|err| format!("Decode {} failed: {}", name, err))
+    }
+
+    fn get(&self, name: &str) -> Result<&MolType, String> {
+        self.types
+            .get(name)
+            .ok_or_else(|| format!("Unknown type: {}", name))
+    }
+
+    fn check(&self) -> Result<(), String> {
+        for (name, mol_type) in &self.types {
+            let items: Vec<&String> = match mol_type {
+                MolType::Array { item, size } => {
+                    if *size == 0 {
+                        return Err(format!("Array {} can not be empty", name));
+                    }
+                    vec![item]
+                }
+                MolType::Struct { fields } | MolType::Table { fields } => {
+                    fields.iter().map(|(_, item)| item).collect()
+                }
+                MolType::Vector { item } | MolType::Option { item } => vec![item],
+                MolType::Union { items } => items.iter().map(|(item, _)| item).collect(),
+            };
+            for item in items {
+                if item != BYTE && !self.types.contains_key(item) {
+                    return Err(format!("Unknown type {} used by {}", item, name));
+                }
+            }
+            match mol_type {
+                MolType::Array { item, .. } if self.fixed_size(item).is_none() => {
+                    return Err(format!("Item of array {} must be fixed size", name));
+                }
+                MolType::Struct { fields }
+                    if fields
+                        .iter()
+                        .any(|(_, item)| self.fixed_size(item).is_none()) =>
+                {
+                    return Err(format!("Fields of struct {} must be fixed size", name));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn fixed_size(&self, name: &str) -> Option<usize> {
+        if name == BYTE {
+            return Some(1);
+        }
+        match self.types.get(name)? {
+            MolType::Array { item, size } => self.fixed_size(item).map(|item| item * size),
+            MolType::Struct { fields } => fields
+                .iter()
+                .map(|(_, item)| self.fixed_size(item))
+                .sum::<Option<usize>>(),
+            _ => None,
+        }
+    }
+
+    fn encode_inner(&self, name: &str, value: &Value) -> Result<Vec<u8>, String> {
+        if name == BYTE {
+            return encode_byte(value);
+        }
+        match self.get(name)? {
+            MolType::Array { item, size } => {
+                if item == BYTE {
+                    return encode_byte_array(value, *size);
+                }
+                let values = as_array(value, Some(*size))?;
+                let mut data = Vec::new();
+                for value in values {
+                    data.extend(self.encode_inner(item, value)?);
+                }
+                Ok(data)
+            }
+            MolType::Struct { fields } => {
+                let mut data = Vec::new();
+                for field in self.field_values(fields, value)? {
+                    data.extend(field);
+                }
+                Ok(data)
+            }
+            MolType::Vector { item } => {
+                let items = if item == BYTE {
+                    decode_hex(value)?
+                        .into_iter()
+                        .map(|byte| vec![byte])
+                        .collect()
+                } else {
+                    as_array(value, None)?
+                        .iter()
+                        .map(|value| self.encode_inner(item, value))
+                        .collect::<Result<Vec<_>, String>>()?
+                };
+                if self.fixed_size(item).is_some() {
+                    let mut data = (items.len() as u32).to_le_bytes().to_vec();
+                    for item in items {
+                        data.extend(item);
+                    }
+                    Ok(data)
+                } else {
+                    Ok(encode_dynamic(items))
+                }
+            }
+            MolType::Table { fields } => Ok(encode_dynamic(self.field_values(fields, value)?)),
+            MolType::Option { item } => {
+                if value.is_null() {
+                    Ok(Vec::new())
+                } else {
+                    self.encode_inner(item, value)
+                }
+            }
+            MolType::Union { items } => {
+                let item_name = value["type"].as_str().ok_or_else(|| {
+                    "Union value must be {\"type\": .., \"value\": ..}".to_owned()
+                })?;
+                let (item, id) = items
+                    .iter()
+                    .find(|(item, _)| item == item_name)
+                    .ok_or_else(|| format!("{} is not an item of the union", item_name))?;
+                let mut data = id.to_le_bytes().to_vec();
+                data.extend(self.encode_inner(item, &value["value"])?);
+                Ok(data)
+            }
+        }
+    }
+
+    fn field_values(
+        &self,
+        fields: &[(String, String)],
+        value: &Value,
+    ) -> Result<Vec<Vec<u8>>, String> {
+        let object = value
+            .as_object()
+            .ok_or_else(|| "Expected an object".to_owned())?;
+        if let Some(unknown) = object
+            .keys()
+            .find(|key| fields.iter().all(|(field, _)| field != *key))
+        {
+            return Err(format!("Unknown field: {}", unknown));
+        }
+        fields
+            .iter()
+            .map(|(field, item)| {
+                let value = object
+                    .get(field)
+                    .ok_or_else(|| format!("Missing field: {}", field))?;
+                self.encode_inner(item, value)
+                    .map_err(|err| format!("{}: {}", field, err))
+            })
+            .collect()
+    }
+
+    fn decode_inner(&self, name: &str, data: &[u8]) -> Result<Value, String> {
+        if let Some(size) = self.fixed_size(name) {
+            if data.len() != size {
+                return Err(format!("Expected {} bytes, got {}", size, data.len()));
+            }
+        }
+        if name == BYTE {
+            return Ok(json!(data[0]));
+        }
+        match self.get(name)? {
+            MolType::Array { item, size } => {
+                if item == BYTE {
+                    return Ok(json!(format!("0x{}", hex_string(data).unwrap())));
+                }
+                let item_size = data.len() / size;
+                data.chunks(item_size)
+                    .map(|chunk| self.decode_inner(item, chunk))
+                    .collect::<Result<Vec<_>, String>>()
+                    .map(Value::Array)
+            }
+            MolType::Struct { fields } => {
+                let mut object = Map::new();
+                let mut offset = 0;
+                for (field, item) in fields {
+                    let size = self.fixed_size(item).unwrap();
+                    object.insert(
+                        field.clone(),
+                        self.decode_inner(item, &data[offset..offset + size])?,
+                    );
+                    offset += size;
+                }
+                Ok(Value::Object(object))
+            }
+            MolType::Vector { item } => {
+                let items = match self.fixed_size(item) {
+                    Some(item_size) => {
+                        let count = read_u32(data, 0)? as usize;
+                        if data.len() != HEADER_SIZE + count * item_size {
+                            return Err(format!(
+                                "Invalid fixvec size {} for {} items",
+                                data.len(),
+                                count
+                            ));
+                        }
+                        data[HEADER_SIZE..].chunks(item_size).collect()
+                    }
+                    None => decode_dynamic(data)?,
+                };
+                if item == BYTE {
+                    return Ok(json!(format!(
+                        "0x{}",
+                        hex_string(&data[HEADER_SIZE..]).unwrap()
+                    )));
+                }
+                items
+                    .into_iter()
+                    .map(|item_data| self.decode_inner(item, item_data))
+                    .collect::<Result<Vec<_>, String>>()
+                    .map(Value::Array)
+            }
+            MolType::Table { fields } => {
+                let items = decode_dynamic(data)?;
+                if items.len() != fields.len() {
+                    return Err(format!(
+                        "Expected {} fields, got {}",
+                        fields.len(),
+                        items.len()
+                    ));
+                }
+                let mut object = Map::new();
+                for ((field, item), item_data) in fields.iter().zip(items) {
+                    object.insert(field.clone(), self.decode_inner(item, item_data)?);
+                }
+                Ok(Value::Object(object))
+            }
+            MolType::Option { item } => {
+                if data.is_empty() {
+                    Ok(Value::Null)
+                } else {
+                    self.decode_inner(item, data)
+                }
+            }
+            MolType::Union { items } => {
+                let id = read_u32(data, 0)?;
+                let (item, _) = items
+                    .iter()
+                    .find(|(_, item_id)| *item_id == id)
+                    .ok_or_else(|| format!("Unknown union id: {}", id))?;
+                Ok(json!({
+                    "type": item,
+                    "value": self.decode_inner(item, &data[HEADER_SIZE..])?,
+                }))
+            }
+        }
+    }
+}
+
+// Header of dynvec and table: full size, then offset of each item
+fn encode_dynamic(items: Vec<Vec<u8>>) -> Vec<u8> {
+    let header_size = HEADER_SIZE * (items.len() + 1);
+    let full_size = header_size + items.iter().map(Vec::len).sum::<usize>();
+    let mut data = (full_size as u32).to_le_bytes().to_vec();
+    let mut offset = header_size;
+    for item in &items {
+        data.extend(&(offset as u32).to_le_bytes());
+        offset += item.len();
+    }
+    for item in items {
+        data.extend(item);
+    }
+    data
+}
+
+fn decode_dynamic(data: &[u8]) -> Result<Vec<&[u8]>, String> {
+    let full_size = read_u32(data, 0)? as usize;
+    if full_size != data.len() {
+        return Err(format!(
+            "Full size in header is {}, got {} bytes",
+            full_size,
+            data.len()
+        ));
+    }
+    if full_size == HEADER_SIZE {
+        return Ok(Vec::new());
+    }
+    let first_offset = read_u32(data, HEADER_SIZE)? as usize;
+    if first_offset % HEADER_SIZE != 0 || first_offset < HEADER_SIZE * 2 {
+        return Err(format!("Invalid first offset: {}", first_offset));
+    }
+    let count = first_offset / HEADER_SIZE - 1;
+    let mut offsets = (0..count)
+        .map(|index| read_u32(data, HEADER_SIZE * (index + 1)).map(|offset| offset as usize))
+        .collect::<Result<Vec<_>, String>>()?;
+    offsets.push(full_size);
+    offsets
+        .windows(2)
+        .map(|pair| {
+            if pair[0] > pair[1] || pair[1] > full_size {
+                Err(format!("Invalid offsets: {} {}", pair[0], pair[1]))
+            } else {
+                Ok(&data[pair[0]..pair[1]])
+            }
+        })
+        .collect()
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + HEADER_SIZE)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| format!("Not enough data, size: {}", data.len()))
+}
+
+fn as_array(value: &Value, size: Option<usize>) -> Result<&Vec<Value>, String> {
+    let values = value
+        .as_array()
+        .ok_or_else(|| "Expected an array".to_owned())?;
+    match size {
+        Some(size) if values.len() != size => {
+            Err(format!("Expected {} items, got {}", size, values.len()))
+        }
+        _ => Ok(values),
+    }
+}
+
+fn decode_hex(value: &Value) -> Result<Vec<u8>, String> {
+    let input = value
+        .as_str()
+        .ok_or_else(|| "Expected a hex string".to_owned())?;
+    let hex = input.trim_start_matches("0x");
+    if hex.len() % 2 != 0 {
+        return Err(format!("Invalid hex string: {}", input));
+    }
+    let mut bytes = vec![0u8; hex.len() / 2];
+    hex_decode(hex.as_bytes(), &mut bytes)
+        .map_err(|err| format!("Invalid hex string {}: {:?}", input, err))?;
+    Ok(bytes)
+}
+
+fn encode_byte(value: &Value) -> Result<Vec<u8>, String> {
+    match value.as_u64() {
+        Some(number) if number <= 0xff => Ok(vec![number as u8]),
+        Some(number) => Err(format!("Byte out of range: {}", number)),
+        None => encode_byte_array(value, 1),
+    }
+}
+
+fn encode_byte_array(value: &Value, size: usize) -> Result<Vec<u8>, String> {
+    if let Some(number) = value.as_u64() {
+        let bytes = number.to_le_bytes();
+        if size < bytes.len() && bytes[size..].iter().any(|byte| *byte != 0) {
+            return Err(format!("Number {} does not fit in {} bytes", number, size));
+        }
+        let mut data = bytes.to_vec();
+        data.resize(size, 0);
+        return Ok(data);
+    }
+    let data = decode_hex(value)?;
+    if data.len() != size {
+        return Err(format!("Expected {} bytes, got {}", size, data.len()));
+    }
+    Ok(data)
+}
+
+fn parse_definitions(source: &str, types: &mut BTreeMap<String, MolType>) -> Result<(), String> {
+    let tokens = tokenize(source)?;
+    let mut tokens = Tokens {
+        tokens: &tokens,
+        position: 0,
+    };
+    while let Some(keyword) = tokens.next_opt() {
+        if keyword == "import" {
+            while tokens.next()? != ";" {}
+            continue;
+        }
+        let name = tokens.ident()?;
+        let mol_type = match keyword {
+            "array" => {
+                tokens.expect("[")?;
+                let item = tokens.ident()?;
+                tokens.expect(";")?;
+                let size = tokens
+                    .next()?
+                    .parse::<usize>()
+                    .map_err(|err| format!("Invalid size of array {}: {}", name, err))?;
+                tokens.expect("]")?;
+                tokens.expect(";")?;
+                MolType::Array { item, size }
+            }
+            "struct" => MolType::Struct {
+                fields: tokens.fields()?,
+            },
+            "table" => MolType::Table {
+                fields: tokens.fields()?,
+            },
+            "vector" => {
+                tokens.expect("<")?;
+                let item = tokens.ident()?;
+                tokens.expect(">")?;
+                tokens.expect(";")?;
+                MolType::Vector { item }
+            }
+            "option" => {
+                tokens.expect("(")?;
+                let item = tokens.ident()?;
+                tokens.expect(")")?;
+                tokens.expect(";")?;
+                MolType::Option { item }
+            }
+            "union" => {
+                tokens.expect("{")?;
+                let mut items = Vec::new();
+                loop {
+                    let token = tokens.next()?;
+                    if token == "}" {
+                        break;
+                    }
+                    let item = tokens.ident_from(token)?;
+                    let mut id = items.last().map(|(_, id)| id + 1).unwrap_or(0);
+                    if tokens.peek() == Some(":") {
+                        tokens.next()?;
+                        id = tokens
+                            .next()?
+                            .parse::<u32>()
+                            .map_err(|err| format!("Invalid id of union {}: {}", name, err))?;
+                    }
+                    items.push((item, id));
+                    if tokens.peek() == Some(",") {
+                        tokens.next()?;
+                    }
+                }
+                MolType::Union { items }
+            }
+            _ => return Err(format!("Unknown keyword: {}", keyword)),
+        };
+        if name == BYTE || types.insert(name.clone(), mol_type).is_some() {
+            return Err(format!("Type {} is defined more than once", name));
+        }
+    }
+    Ok(())
+}
+
+fn tokenize(source: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        if c.is_whitespace() {
+            index += 1;
+        } else if c == '/' && chars.get(index + 1) == Some(&'/') {
+            while index < chars.len() && chars[index] != '\n' {
+                index += 1;
+            }
+        } else if c == '/' && chars.get(index + 1) == Some(&'*') {
+            index += 2;
+            while index < chars.len()
+                && !(chars[index] == '*' && chars.get(index + 1) == Some(&'/'))
+            {
+                index += 1;
+            }
+            if index >= chars.len() {
+                return Err("Unterminated comment".to_owned());
+            }
+            index += 2;
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let start = index;
+            while index < chars.len()
+                && (chars[index].is_ascii_alphanumeric() || chars[index] == '_')
+            {
+                index += 1;
+            }
+            tokens.push(chars[start..index].iter().collect());
+        } else if "[]{}<>();:,".contains(c) {
+            tokens.push(c.to_string());
+            index += 1;
+        } else {
+            return Err(format!("Unexpected character in schema: {}", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Tokens<'a> {
+    tokens: &'a [String],
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn next_opt(&mut self) -> Option<&'a str> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+        Some(token.as_str())
+    }
+
+    fn next(&mut self) -> Result<&'a str, String> {
+        self.next_opt()
+            .ok_or_else(|| "Unexpected end of schema".to_owned())
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(format!("Expected \"{}\", found \"{}\"", expected, token));
+        }
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        let token = self.next()?;
+        self.ident_from(token)
+    }
+
+    fn ident_from(&self, token: &str) -> Result<String, String> {
+        if token
+            .chars()
+            .next()
+            .map(|c| c.is_ascii_alphabetic() || c == '_')
+            .unwrap_or(false)
+        {
+            Ok(token.to_owned())
+        } else {
+            Err(format!("Expected a name, found \"{}\"", token))
+        }
+    }
+
+    fn fields(&mut self) -> Result<Vec<(String, String)>, String> {
+        self.expect("{")?;
+        let mut fields = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token == "}" {
+                break;
+            }
+            let field = self.ident_from(token)?;
+            self.expect(":")?;
+            let item = self.ident()?;
+            if fields.iter().any(|(name, _)| *name == field) {
+                return Err(format!("Field {} is defined more than once", field));
+            }
+            fields.push((field, item));
+            if self.peek() == Some(",") {
+                self.next()?;
+            }
+        }
+        Ok(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{bytes::Bytes, core::ScriptHashType, h256, packed, prelude::*};
+
+    const SCHEMA: &str = "
+        import blockchain;
+        // Args of a sample contract
+        struct Limit { amount: Uint64, epoch: Uint32, }
+        vector Limits <Limit>;
+        union Auth { Byte32, Script, }
+        table MyArgs {
+            owner: Script,
+            limits: Limits,
+            auth: Auth,
+            memo: BytesOpt,
+            flag: byte,
+        }
+    ";
+
+    #[test]
+    fn test_encode_script() {
+        let schema = MolSchema::parse(SCHEMA).unwrap();
+        let script = packed::Script::new_builder()
+            .code_hash(h256!("0x1234").pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![1u8, 2, 3]).pack())
+            .build();
+        let value = json!({
+            "code_hash": format!("{:#x}", h256!("0x1234")),
+            "hash_type": 1,
+            "args": "0x010203",
+        });
+        let data = schema.encode("Script", &value).unwrap();
+        assert_eq!(data, script.as_slice().to_vec());
+        assert_eq!(schema.decode("Script", &data).unwrap(), value);
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let schema = MolSchema::parse(SCHEMA).unwrap();
+        assert_eq!(
+            MolSchema::type_names(SCHEMA).unwrap(),
+            vec!["Auth", "Limit", "Limits", "MyArgs"]
+        );
+        let value = json!({
+            "owner": {
+                "code_hash": format!("{:#x}", h256!("0x1")),
+                "hash_type": 0,
+                "args": "0x",
+            },
+            "limits": [{"amount": 100, "epoch": 2}],
+            "auth": {"type": "Byte32", "value": format!("{:#x}", h256!("0x2"))},
+            "memo": null,
+            "flag": 255,
+        });
+        let data = schema.encode("MyArgs", &value).unwrap();
+        let mut expected = value.clone();
+        expected["limits"] = json!([{"amount": "0x6400000000000000", "epoch": "0x02000000"}]);
+        assert_eq!(schema.decode("MyArgs", &data).unwrap(), expected);
+        assert_eq!(schema.encode("MyArgs", &expected).unwrap(), data);
+
+        let limit = schema
+            .encode("Limit", &json!({"amount": 1, "epoch": 1}))
+            .unwrap();
+        assert_eq!(limit, vec![1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(
+            schema.encode("Limits", &json!([])).unwrap(),
+            vec![0, 0, 0, 0]
+        );
+        assert_eq!(
+            schema.encode("BytesVec", &json!([])).unwrap(),
+            vec![4, 0, 0, 0]
+        );
+
+        // Missing field, unknown field, wrong size and bad data
+        let mut missing = value.clone();
+        missing.as_object_mut().unwrap().remove("flag");
+        assert!(schema.encode("MyArgs", &missing).is_err());
+        let mut unknown = value.clone();
+        unknown["other"] = json!(1);
+        assert!(schema.encode("MyArgs", &unknown).is_err());
+        assert!(schema.encode("Uint32", &json!("0x01")).is_err());
+        assert!(schema.encode("Uint32", &json!(0x1_0000_0000u64)).is_err());
+        assert!(schema.decode("MyArgs", &data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_parse_schema() {
+        let schema =
+            MolSchema::parse("union Message { Uint32: 5, Uint64, } /* ids */ table Empty {}")
+                .unwrap();
+        let data = schema
+            .encode("Message", &json!({"type": "Uint64", "value": 1}))
+            .unwrap();
+        assert_eq!(&data[..4], &[6, 0, 0, 0]);
+        assert_eq!(
+            schema.encode("Empty", &json!({})).unwrap(),
+            vec![4, 0, 0, 0]
+        );
+
+        assert!(MolSchema::parse("table A { b: Unknown }").is_err());
+        assert!(MolSchema::parse("struct A { b: Bytes }").is_err());
+        assert!(MolSchema::parse("array A [byte; 2]; array A [byte; 3];").is_err());
+        assert!(MolSchema::parse("array A [byte 2];").is_err());
+        // Types of the prelude can be defined again
+        assert!(MolSchema::parse("array Byte32 [byte; 32];").is_ok());
+    }
+}
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use ckb_jsonrpc_types::{AlertMessage, BlockNumber};
@@ -7,35 +10,107 @@ use ckb_sdk::{
     wallet::{KeyStore, ScryptType},
     Address, GenesisInfo, HttpRpcClient, NetworkType,
 };
-use ckb_types::{core::BlockView, H160, H256};
+use ckb_types::{core::BlockView, packed::Byte32, prelude::*, H160, H256};
 use clap::ArgMatches;
 use colored::Colorize;
 use rpassword::prompt_password_stdout;
 
-use super::arg_parser::{AddressParser, ArgParser, FixedHashParser, PubkeyHexParser};
+use super::address_book::{account_hints, annotate_accounts, has_locks, AddressBook};
+use super::arg_parser::{
+    AddressParser, ArgParser, EitherParser, EitherValue, FixedHashParser, FromStrParser,
+    PubkeyHexParser,
+};
+use super::i18n::{tr, tr_fmt};
+use super::lock_template::LockTemplate;
+use super::printer::{OutputFormat, Printable};
+use super::sign_mode::SignModes;
 
 pub fn read_password(repeat: bool, prompt: Option<&str>) -> Result<String, String> {
-    let prompt = prompt.unwrap_or("Password");
+    let prompt = prompt.unwrap_or_else(|| tr("Password"));
     let pass =
         prompt_password_stdout(format!("{}: ", prompt).as_str()).map_err(|err| err.to_string())?;
     if repeat {
-        let repeat_pass =
-            prompt_password_stdout("Repeat password: ").map_err(|err| err.to_string())?;
+        let repeat_pass = prompt_password_stdout(format!("{}: ", tr("Repeat password")).as_str())
+            .map_err(|err| err.to_string())?;
         if pass != repeat_pass {
-            return Err("Passwords do not match".to_owned());
+            return Err(tr("Passwords do not match").to_owned());
         }
     }
     Ok(pass)
 }
 
+/// Ask the user to type `expected` to confirm a dangerous action
+pub fn confirm_input(prompt: &str, expected: &str) -> Result<(), String> {
+    print!(
+        "{}",
+        tr_fmt("{} (type \"{}\" to confirm): ", &[&prompt, &expected])
+    );
+    io::stdout().flush().map_err(|err| err.to_string())?;
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .map_err(|err| err.to_string())?;
+    if input.trim() != expected {
+        return Err(tr("Not confirmed, aborted").to_owned());
+    }
+    Ok(())
+}
+
+/// Write a file containing sensitive data (keys, encrypted archives), only
+/// readable by current user (0600 on unix). Existing file is only overwritten
+/// when `force` is set.
+pub fn write_private_file(path: &Path, content: &[u8], force: bool) -> Result<(), String> {
+    if path.exists() && !force {
+        return Err(tr_fmt(
+            "File exists: {} (use --force to overwrite)",
+            &[&format!("{:?}", path)],
+        ));
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    let mut file = open_private_file(path, options)?;
+    file.write_all(content).map_err(|err| err.to_string())
+}
+
+/// Append to a file containing sensitive data (logs), created only readable
+/// by current user like `write_private_file`.
+pub fn append_private_file(path: &Path, content: &[u8]) -> Result<(), String> {
+    let mut options = fs::OpenOptions::new();
+    options.append(true).create(true);
+    let mut file = open_private_file(path, options)?;
+    file.write_all(content).map_err(|err| err.to_string())
+}
+
+fn open_private_file(path: &Path, mut options: fs::OpenOptions) -> Result<fs::File, String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(path).map_err(|err| err.to_string())?;
+    // Mode only applies to newly created files
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(file)
+}
+
 pub fn get_key_store(ckb_cli_dir: &PathBuf) -> Result<KeyStore, String> {
     let mut keystore_dir = ckb_cli_dir.clone();
     keystore_dir.push("keystore");
-    fs::create_dir_all(&keystore_dir)
-        .map_err(|err| err.to_string())
-        .and_then(|_| {
-            KeyStore::from_dir(keystore_dir, ScryptType::default()).map_err(|err| err.to_string())
-        })
+    if !keystore_dir.exists() {
+        fs::create_dir_all(&keystore_dir).map_err(|err| err.to_string())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&keystore_dir, fs::Permissions::from_mode(0o700))
+                .map_err(|err| err.to_string())?;
+        }
+    }
+    KeyStore::from_dir(keystore_dir, ScryptType::default()).map_err(|err| err.to_string())
 }
 
 pub fn get_address(m: &ArgMatches) -> Result<Address, String> {
@@ -51,15 +126,96 @@ pub fn get_address(m: &ArgMatches) -> Result<Address, String> {
     Ok(address)
 }
 
+/// Map lock hash (secp256k1 default lock and locks derived by lock templates)
+/// to lock_arg of all accounts in keystore
+pub fn get_lock_hash_accounts(
+    key_store: &mut KeyStore,
+    secp_type_hash: &Byte32,
+    lock_templates: &[LockTemplate],
+) -> HashMap<H256, H160> {
+    let mut accounts = HashMap::new();
+    for lock_arg in key_store.get_accounts().keys() {
+        let lock_hash: H256 = Address::new_default(lock_arg.clone())
+            .lock_script(secp_type_hash.clone())
+            .calc_script_hash()
+            .unpack();
+        accounts.insert(lock_hash, lock_arg.clone());
+        for template in lock_templates {
+            if let Ok(lock_hash) = template.lock_hash(lock_arg) {
+                accounts.insert(lock_hash.unpack(), lock_arg.clone());
+            }
+        }
+    }
+    accounts
+}
+
+/// Resolve account from full lock-arg, address, label in address book or
+/// unique lock-arg prefix in keystore
+pub fn resolve_account(key_store: &mut KeyStore, input: &str) -> Result<H160, String> {
+    if let Ok(lock_arg) = FixedHashParser::<H160>::default().parse(input) {
+        return Ok(lock_arg);
+    }
+    if let Ok(address) = AddressParser.parse(input) {
+        return Ok(address.hash().clone());
+    }
+    if let Some(lock_arg) = AddressBook::load(key_store.keys_dir())?.resolve(input) {
+        return Ok(lock_arg.clone());
+    }
+    let prefix = input.trim_start_matches("0x").to_lowercase();
+    if prefix.is_empty() {
+        return Err("Empty account input".to_owned());
+    }
+    let matched = key_store
+        .get_accounts()
+        .keys()
+        .filter(|lock_arg| format!("{:x}", lock_arg).starts_with(&prefix))
+        .cloned()
+        .collect::<Vec<_>>();
+    match matched.len() {
+        0 => Err(format!("No account matched in keystore: {}", input)),
+        1 => Ok(matched[0].clone()),
+        n => Err(format!(
+            "Ambiguous account: {}, {} accounts matched in keystore",
+            input, n
+        )),
+    }
+}
+
+/// Signer of accounts in keystore, the identifier is the lock arg or the
+/// ethereum address (of accounts in eth-personal sign mode). The password
+/// of each account is asked once, signing may run in multiple threads.
 pub fn get_singer(
     key_store: KeyStore,
-) -> impl Fn(&H160, &H256) -> Result<[u8; 65], String> + 'static {
-    move |lock_arg: &H160, tx_hash_hash: &H256| {
-        let prompt = format!("Password for [{:x}]", lock_arg);
-        let password = read_password(false, Some(prompt.as_str()))?;
-        let signature = key_store
-            .sign_recoverable_with_password(lock_arg, tx_hash_hash, password.as_bytes())
-            .map_err(|err| err.to_string())?;
+    sign_modes: SignModes,
+) -> impl Fn(&H160, &H256) -> Result<[u8; 65], String> + Sync + 'static {
+    let passwords: Mutex<HashMap<H160, String>> = Mutex::new(HashMap::new());
+    move |identifier: &H160, tx_hash_hash: &H256| {
+        let (lock_arg, sign_mode) = sign_modes.resolve(identifier);
+        let message = sign_mode.message(tx_hash_hash);
+        let cached = passwords.lock().unwrap().get(&lock_arg).cloned();
+        let signature = match cached {
+            Some(password) => key_store
+                .sign_recoverable_with_password(&lock_arg, &message, password.as_bytes())
+                .map_err(|err| err.to_string())?,
+            None => {
+                // Hold the lock while prompting, other groups of the account
+                // wait for this password instead of asking again
+                let mut passwords = passwords.lock().unwrap();
+                let password = match passwords.get(&lock_arg) {
+                    Some(password) => password.clone(),
+                    None => {
+                        let prompt = tr_fmt("Password for [{}]", &[&format!("{:x}", lock_arg)]);
+                        read_password(false, Some(prompt.as_str()))?
+                    }
+                };
+                let signature = key_store
+                    .sign_recoverable_with_password(&lock_arg, &message, password.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                // Only a password which unlocked the key is kept
+                passwords.insert(lock_arg.clone(), password);
+                signature
+            }
+        };
         let (recov_id, data) = signature.serialize_compact();
         let mut signature_bytes = [0u8; 65];
         signature_bytes[0..64].copy_from_slice(&data[0..64]);
@@ -68,6 +224,101 @@ pub fn get_singer(
     }
 }
 
+/// Resolve `--header-dep` values (block hash or block number) into block
+/// hashes, every block must be on the canonical chain
+/// Number of the first block with timestamp (milliseconds) not before
+/// `timestamp`, tip number + 1 if there is no such block
+pub fn get_block_number_by_time(
+    rpc_client: &mut HttpRpcClient,
+    timestamp: u64,
+) -> Result<u64, String> {
+    let tip_number = rpc_client
+        .get_tip_header()
+        .call()
+        .map_err(|err| err.to_string())?
+        .inner
+        .number
+        .value();
+    let (mut low, mut high) = (0, tip_number + 1);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let header = rpc_client
+            .get_header_by_number(BlockNumber::from(middle))
+            .call()
+            .map_err(|err| err.to_string())?
+            .0
+            .ok_or_else(|| format!("Block not found: #{}", middle))?;
+        if header.inner.timestamp.value() < timestamp {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(low)
+}
+
+/// Total size of files in the directory (recursive)
+pub fn dir_size(path: &Path) -> Result<u64, String> {
+    let mut size = 0;
+    for entry in fs::read_dir(path).map_err(|err| err.to_string())? {
+        let entry = entry.map_err(|err| err.to_string())?;
+        let metadata = entry.metadata().map_err(|err| err.to_string())?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+pub fn get_header_deps(
+    rpc_client: &mut HttpRpcClient,
+    m: &ArgMatches,
+) -> Result<Vec<H256>, String> {
+    let parser = EitherParser::new(
+        FixedHashParser::<H256>::default(),
+        FromStrParser::<u64>::new(),
+    );
+    let mut header_deps = Vec::new();
+    for value in parser.from_matches_vec::<EitherValue<H256, u64>>(m, "header-dep")? {
+        let block_hash = match value {
+            EitherValue::A(block_hash) => {
+                let number = rpc_client
+                    .get_header(block_hash.clone())
+                    .call()
+                    .map_err(|err| err.to_string())?
+                    .0
+                    .ok_or_else(|| format!("Header dep not found: {:#x}", block_hash))?
+                    .inner
+                    .number;
+                let canonical_hash = rpc_client
+                    .get_block_hash(number.clone())
+                    .call()
+                    .map_err(|err| err.to_string())?
+                    .0;
+                if canonical_hash.as_ref() != Some(&block_hash) {
+                    return Err(format!(
+                        "Header dep {:#x} (#{}) is not on the canonical chain",
+                        block_hash,
+                        number.value()
+                    ));
+                }
+                block_hash
+            }
+            // Hash by number is always on the canonical chain
+            EitherValue::B(number) => rpc_client
+                .get_block_hash(BlockNumber::from(number))
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .ok_or_else(|| format!("Header dep not found: block #{}", number))?,
+        };
+        header_deps.push(block_hash);
+    }
+    Ok(header_deps)
+}
+
 pub fn check_alerts(rpc_client: &mut HttpRpcClient) {
     if let Some(alerts) = rpc_client
         .get_blockchain_info()
@@ -101,6 +352,42 @@ pub fn check_alerts(rpc_client: &mut HttpRpcClient) {
     }
 }
 
+/// Render the output of a command, lock hashes and lock scripts of known
+/// accounts are annotated. Hints are only loaded when the output has any lock.
+pub fn render_output<F>(
+    output: &dyn Printable,
+    format: OutputFormat,
+    color: bool,
+    hints: F,
+) -> String
+where
+    F: FnOnce() -> Option<HashMap<H256, String>>,
+{
+    match output.json_value() {
+        Some(mut value) if has_locks(&value) => match hints() {
+            Some(hints) => {
+                annotate_accounts(&mut value, &hints);
+                value.render(format, color)
+            }
+            None => output.render(format, color),
+        },
+        _ => output.render(format, color),
+    }
+}
+
+/// Hints of default lock hashes of keystore accounts and labeled addresses
+pub fn lock_hash_hints(
+    key_store: &mut KeyStore,
+    genesis_info: &GenesisInfo,
+) -> Result<HashMap<H256, String>, String> {
+    let address_book = AddressBook::load(key_store.keys_dir())?;
+    Ok(account_hints(
+        key_store.get_accounts().keys(),
+        &address_book,
+        genesis_info.secp_type_hash(),
+    ))
+}
+
 pub fn get_genesis_info(
     genesis_info: &mut Option<GenesisInfo>,
     rpc_client: &mut HttpRpcClient,
@@ -125,15 +412,25 @@ pub fn get_network_type(rpc_client: &mut HttpRpcClient) -> Result<NetworkType, S
         .get_blockchain_info()
         .call()
         .map_err(|err| err.to_string())?;
-    NetworkType::from_raw_str(chain_info.chain.as_str())
-        .ok_or_else(|| format!("Unexpected network type: {}", chain_info.chain))
+    NetworkType::from_raw_str(chain_info.chain.as_str()).ok_or_else(|| {
+        format!(
+            "Unexpected network type: {} (register it by `config --add-network`)",
+            chain_info.chain
+        )
+    })
 }
 
 pub fn check_address_prefix(address: &str, network_type: NetworkType) -> Result<(), String> {
     if address.len() < 3 {
         Err(format!("Invalid address length: {}", address))
-    } else if &address[..3] != network_type.to_prefix() {
-        Err(format!("Invalid address prefix: {}", &address[..3]))
+    } else if !address.starts_with(&format!("{}1", network_type.to_prefix())) {
+        Err(format!(
+            "Invalid address prefix: {}",
+            address
+                .rfind('1')
+                .map(|index| &address[..index])
+                .unwrap_or(address)
+        ))
     } else {
         Ok(())
     }
@@ -0,0 +1,203 @@
+use std::path::Path;
+
+use ckb_jsonrpc_types::Script as JsonScript;
+use ckb_sdk::{Address, NetworkType};
+use ckb_types::{
+    bytes::Bytes,
+    core::Capacity,
+    packed::{Byte32, CellOutput, Script, ScriptOpt},
+    prelude::*,
+};
+use serde_derive::Deserialize;
+
+use super::arg_parser::{AddressParser, ArgParser, CapacityParser, HexParser};
+use super::other::check_address_prefix;
+
+/// One output of a batch transfer, read from a JSON or CSV spec file.
+///
+/// JSON (array of objects, `address` or `lock` is required):
+///
+///   [{"address": "ckt1...", "capacity": "100.5", "type": {...}, "data": "0x..."}]
+///
+/// CSV (one output per line, `#` starts a comment):
+///
+///   address,capacity[,data]
+#[derive(Debug, Clone)]
+pub struct OutputSpec {
+    pub lock: OutputLock,
+    // Unit: shannon
+    pub capacity: u64,
+    pub type_: Option<Script>,
+    pub data: Bytes,
+}
+
+#[derive(Debug, Clone)]
+pub enum OutputLock {
+    // Default secp256k1 lock
+    Address(Address),
+    Script(Script),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonOutputSpec {
+    #[serde(default)]
+    address: Option<String>,
+    #[serde(default)]
+    lock: Option<JsonScript>,
+    // Unit: CKB
+    capacity: String,
+    #[serde(default, rename = "type")]
+    type_: Option<JsonScript>,
+    #[serde(default)]
+    data: Option<String>,
+}
+
+impl OutputSpec {
+    pub fn to_output(&self, secp_type_hash: &Byte32) -> Result<(CellOutput, Bytes), String> {
+        let lock = match self.lock {
+            OutputLock::Address(ref address) => address.lock_script(secp_type_hash.clone()),
+            OutputLock::Script(ref script) => script.clone(),
+        };
+        let output = CellOutput::new_builder()
+            .capacity(Capacity::shannons(self.capacity).pack())
+            .lock(lock)
+            .type_(ScriptOpt::new_builder().set(self.type_.clone()).build())
+            .build();
+        let occupied = output
+            .occupied_capacity(Capacity::bytes(self.data.len()).map_err(|err| err.to_string())?)
+            .map_err(|err| err.to_string())?;
+        if occupied.as_u64() > self.capacity {
+            return Err(format!(
+                "Output capacity {} can not hold the cell, at least: {}",
+                self.capacity,
+                occupied.as_u64()
+            ));
+        }
+        Ok((output, self.data.clone()))
+    }
+}
+
+// Address of the output, which must be of the current network
+fn parse_address(address: &str, network_type: NetworkType) -> Result<Address, String> {
+    check_address_prefix(address, network_type)?;
+    AddressParser.parse(address)
+}
+
+fn parse_json(content: &str, network_type: NetworkType) -> Result<Vec<OutputSpec>, String> {
+    let items: Vec<JsonOutputSpec> =
+        serde_json::from_str(content).map_err(|err| format!("Invalid outputs json: {}", err))?;
+    items
+        .into_iter()
+        .map(|item| {
+            let lock = match (item.address, item.lock) {
+                (Some(address), None) => {
+                    OutputLock::Address(parse_address(&address, network_type)?)
+                }
+                (None, Some(lock)) => OutputLock::Script(lock.into()),
+                _ => return Err("One of address or lock is required in output".to_owned()),
+            };
+            Ok(OutputSpec {
+                lock,
+                capacity: CapacityParser.parse(&item.capacity)?,
+                type_: item.type_.map(Into::into),
+                data: item
+                    .data
+                    .map(|data| HexParser.parse(&data).map(Bytes::from))
+                    .transpose()?
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+fn parse_csv(content: &str, network_type: NetworkType) -> Result<Vec<OutputSpec>, String> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| {
+            let parts = line.split(',').map(str::trim).collect::<Vec<_>>();
+            if parts.len() < 2 || parts.len() > 3 {
+                return Err(format!(
+                    "Invalid outputs csv line {}, format: address,capacity[,data]",
+                    number
+                ));
+            }
+            let err_with_line = |err: String| format!("line {}: {}", number, err);
+            Ok(OutputSpec {
+                lock: OutputLock::Address(
+                    parse_address(parts[0], network_type).map_err(err_with_line)?,
+                ),
+                capacity: CapacityParser.parse(parts[1]).map_err(err_with_line)?,
+                type_: None,
+                data: parts
+                    .get(2)
+                    .map(|data| HexParser.parse(data).map(Bytes::from))
+                    .transpose()
+                    .map_err(err_with_line)?
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Parse outputs spec, CSV if the file extension is `.csv`, otherwise JSON.
+/// Every address must be of `network_type`.
+pub fn parse_outputs(
+    path: &Path,
+    content: &str,
+    network_type: NetworkType,
+) -> Result<Vec<OutputSpec>, String> {
+    let is_csv = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("csv"))
+        .unwrap_or(false);
+    let outputs = if is_csv {
+        parse_csv(content, network_type)?
+    } else {
+        parse_json(content, network_type)?
+    };
+    if outputs.is_empty() {
+        return Err("No output found in outputs file".to_owned());
+    }
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::h256;
+
+    #[test]
+    fn test_parse_outputs() {
+        let address = "ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p";
+        let csv = format!("# payroll\n{},100\n\n{},200.5,0x0102\n", address, address);
+        let outputs = parse_outputs(Path::new("outputs.csv"), &csv, NetworkType::MainNet).unwrap();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].capacity, 100 * 100_000_000);
+        assert_eq!(outputs[1].capacity, 20_050_000_000);
+        assert_eq!(outputs[1].data, Bytes::from(vec![1u8, 2]));
+        assert!(parse_outputs(Path::new("outputs.csv"), "abc", NetworkType::MainNet).is_err());
+        // Address of another network
+        assert!(parse_outputs(Path::new("outputs.csv"), &csv, NetworkType::TestNet).is_err());
+
+        let json = format!(
+            r#"[{{"address": "{}", "capacity": "61", "data": "0x00"}}]"#,
+            address
+        );
+        let outputs =
+            parse_outputs(Path::new("outputs.json"), &json, NetworkType::MainNet).unwrap();
+        let secp_type_hash = h256!("0x1").pack();
+        // 61 CKB can not hold 1 byte of data
+        assert!(outputs[0].to_output(&secp_type_hash).is_err());
+        assert!(parse_outputs(
+            Path::new("outputs.json"),
+            r#"[{"capacity": "61"}]"#,
+            NetworkType::MainNet
+        )
+        .is_err());
+        assert!(parse_outputs(Path::new("outputs.json"), "[]", NetworkType::MainNet).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use std::str::FromStr;
+
+use url::{form_urlencoded, Url};
+
+const SCHEME: &str = "ckb";
+
+/// Payment request URI: `ckb:<address>?amount=<amount>&asset=<asset>&memo=<memo>`
+///
+/// `amount` is in the unit of the asset (CKB for capacity), `asset` is `ckb`
+/// (the default), a registered symbol or a sUDT type script hash.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PaymentRequest {
+    pub address: String,
+    pub amount: Option<String>,
+    pub asset: Option<String>,
+    pub memo: Option<String>,
+}
+
+impl PaymentRequest {
+    pub fn to_uri(&self) -> String {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        if let Some(amount) = self.amount.as_ref() {
+            query.append_pair("amount", amount);
+        }
+        if let Some(asset) = self.asset.as_ref() {
+            query.append_pair("asset", asset);
+        }
+        if let Some(memo) = self.memo.as_ref() {
+            query.append_pair("memo", memo);
+        }
+        let query = query.finish();
+        if query.is_empty() {
+            format!("{}:{}", SCHEME, self.address)
+        } else {
+            format!("{}:{}?{}", SCHEME, self.address, query)
+        }
+    }
+}
+
+impl FromStr for PaymentRequest {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<PaymentRequest, String> {
+        let url = Url::parse(input).map_err(|err| format!("Invalid payment request: {}", err))?;
+        if url.scheme() != SCHEME {
+            return Err(format!(
+                "Invalid payment request scheme: {}, expected: {}",
+                url.scheme(),
+                SCHEME
+            ));
+        }
+        let address = url.path().to_owned();
+        if address.is_empty() {
+            return Err("Payment request has no address".to_owned());
+        }
+        let mut request = PaymentRequest {
+            address,
+            ..Default::default()
+        };
+        for (key, value) in url.query_pairs() {
+            let field = match key.as_ref() {
+                "amount" => &mut request.amount,
+                "asset" => &mut request.asset,
+                "memo" => &mut request.memo,
+                _ => return Err(format!("Unknown payment request parameter: {}", key)),
+            };
+            if field.is_some() {
+                return Err(format!("Duplicated payment request parameter: {}", key));
+            }
+            *field = Some(value.into_owned());
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "ckt1qyqt8xaupvm8837nv3gtc9x0ekkj64vud3jqfwyw5v";
+
+    #[test]
+    fn test_payment_request() {
+        let request = PaymentRequest {
+            address: ADDRESS.to_owned(),
+            amount: Some("100.5".to_owned()),
+            asset: Some("USDT".to_owned()),
+            memo: Some("order #12 & more".to_owned()),
+        };
+        let uri = request.to_uri();
+        assert_eq!(
+            uri,
+            format!(
+                "ckb:{}?amount=100.5&asset=USDT&memo=order+%2312+%26+more",
+                ADDRESS
+            )
+        );
+        assert_eq!(uri.parse::<PaymentRequest>(), Ok(request));
+
+        let request = PaymentRequest {
+            address: ADDRESS.to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(request.to_uri(), format!("ckb:{}", ADDRESS));
+        assert_eq!(request.to_uri().parse::<PaymentRequest>(), Ok(request));
+    }
+
+    #[test]
+    fn test_invalid_payment_request() {
+        assert!(format!("bitcoin:{}", ADDRESS)
+            .parse::<PaymentRequest>()
+            .is_err());
+        assert!("ckb:?amount=1".parse::<PaymentRequest>().is_err());
+        assert!(format!("ckb:{}?amount=1&amount=2", ADDRESS)
+            .parse::<PaymentRequest>()
+            .is_err());
+        assert!(format!("ckb:{}?label=shop", ADDRESS)
+            .parse::<PaymentRequest>()
+            .is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ckb_sdk::ONE_CKB;
+use serde_derive::{Deserialize, Serialize};
+
+// Cached quote older than this is marked stale
+const STALE_SECS: u64 = 10 * 60;
+// A new quote moving more than this factor from the cached one is rejected
+const MAX_PRICE_FACTOR: f64 = 10.0;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Price of one CKB in fiat, the feed url must return a json object like
+/// `{"currency": "USD", "price": 0.0123}`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PriceQuote {
+    pub currency: String,
+    pub price: f64,
+    // Unix time (seconds) the quote was fetched
+    #[serde(default)]
+    pub fetched_at: u64,
+}
+
+impl PriceQuote {
+    /// Check the quote is sane, `last` is the cached quote if any
+    pub fn check(&self, last: Option<&PriceQuote>) -> Result<(), String> {
+        if !self.price.is_finite() || self.price <= 0.0 {
+            return Err(format!("Invalid price: {}", self.price));
+        }
+        if self.currency.is_empty() {
+            return Err("Empty currency".to_owned());
+        }
+        if let Some(last) = last.filter(|last| last.currency == self.currency) {
+            let factor = self.price / last.price;
+            if factor > MAX_PRICE_FACTOR || factor < 1.0 / MAX_PRICE_FACTOR {
+                return Err(format!(
+                    "Price {} {} is out of bounds of the cached price {} {}",
+                    self.price, self.currency, last.price, last.currency
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fiat value of shannons
+    pub fn value_of(&self, shannons: u64) -> f64 {
+        shannons as f64 / ONE_CKB as f64 * self.price
+    }
+}
+
+/// Price feed of the configured url (`config --price-feed-url`), the last
+/// good quote is cached in a local file. Never used in offline mode.
+pub struct PriceFeed {
+    url: String,
+    cache_file: PathBuf,
+}
+
+impl PriceFeed {
+    /// None when no url configured or in offline mode
+    pub fn from_config(url: Option<&str>, cache_file: PathBuf) -> Option<PriceFeed> {
+        if ckb_sdk::is_offline() {
+            return None;
+        }
+        url.map(|url| PriceFeed {
+            url: url.to_owned(),
+            cache_file,
+        })
+    }
+
+    fn load_cache(&self) -> Option<PriceQuote> {
+        fs::read_to_string(&self.cache_file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+    }
+
+    fn fetch(&self, last: Option<&PriceQuote>) -> Result<PriceQuote, String> {
+        let mut response = ckb_sdk::http_client(Some(FETCH_TIMEOUT))?
+            .get(self.url.as_str())
+            .send()
+            .map_err(|err| format!("Fetch price error: {}", err))?;
+        if !response.status().is_success() {
+            return Err(format!("Fetch price error: {}", response.status()));
+        }
+        let mut quote: PriceQuote = response
+            .json()
+            .map_err(|err| format!("Invalid price feed response: {}", err))?;
+        quote.fetched_at = now_secs();
+        quote.check(last)?;
+        Ok(quote)
+    }
+
+    /// Fetch a fresh quote, the cached one is used when fetching fails
+    /// (the error is kept in the result).
+    pub fn quote(&self) -> (Option<PriceQuote>, Option<String>) {
+        let cached = self.load_cache();
+        match self.fetch(cached.as_ref()) {
+            Ok(quote) => {
+                if let Ok(content) = serde_json::to_string(&quote) {
+                    if let Err(err) = fs::write(&self.cache_file, content) {
+                        eprintln!("Save price cache failed: {}", err);
+                    }
+                }
+                (Some(quote), None)
+            }
+            Err(err) => (cached, Some(err)),
+        }
+    }
+
+    /// Fiat values of named amounts (shannons) for a preview, with the price,
+    /// its age and whether it is stale.
+    pub fn fiat_values(&self, amounts: &[(&str, u64)]) -> serde_json::Value {
+        let (quote, error) = self.quote();
+        fiat_values(quote.as_ref(), error, amounts, now_secs())
+    }
+}
+
+pub fn fiat_values(
+    quote: Option<&PriceQuote>,
+    error: Option<String>,
+    amounts: &[(&str, u64)],
+    now: u64,
+) -> serde_json::Value {
+    match quote {
+        Some(quote) => {
+            let age = now.saturating_sub(quote.fetched_at);
+            let values = amounts
+                .iter()
+                .map(|(name, shannons)| {
+                    let value = format!("{:.2}", quote.value_of(*shannons));
+                    ((*name).to_owned(), serde_json::json!(value))
+                })
+                .collect::<serde_json::Map<_, _>>();
+            serde_json::json!({
+                "currency": quote.currency,
+                "price": quote.price,
+                "age_secs": age,
+                "stale": age > STALE_SECS,
+                "values": values,
+                "error": error,
+            })
+        }
+        None => serde_json::json!({
+            "error": error.unwrap_or_else(|| "No price available".to_owned()),
+        }),
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(currency: &str, price: f64, fetched_at: u64) -> PriceQuote {
+        PriceQuote {
+            currency: currency.to_owned(),
+            price,
+            fetched_at,
+        }
+    }
+
+    #[test]
+    fn test_check_quote() {
+        let last = quote("USD", 0.01, 0);
+        assert!(quote("USD", 0.012, 0).check(Some(&last)).is_ok());
+        assert!(quote("USD", 0.2, 0).check(Some(&last)).is_err());
+        assert!(quote("USD", 0.0005, 0).check(Some(&last)).is_err());
+        // Other currency is not compared
+        assert!(quote("EUR", 0.2, 0).check(Some(&last)).is_ok());
+        assert!(quote("USD", 0.0, 0).check(None).is_err());
+        assert!(quote("USD", std::f64::NAN, 0).check(None).is_err());
+        assert!(quote("", 0.01, 0).check(None).is_err());
+    }
+
+    #[test]
+    fn test_fiat_values() {
+        let quote = quote("USD", 0.5, 1000);
+        let value = fiat_values(Some(&quote), None, &[("fee", 3 * ONE_CKB)], 1010);
+        assert_eq!(value["values"]["fee"], serde_json::json!("1.50"));
+        assert_eq!(value["stale"], serde_json::json!(false));
+        let value = fiat_values(
+            Some(&quote),
+            Some("timeout".to_owned()),
+            &[("fee", ONE_CKB)],
+            1000 + STALE_SECS + 1,
+        );
+        assert_eq!(value["stale"], serde_json::json!(true));
+        assert_eq!(value["error"], serde_json::json!("timeout"));
+        let value = fiat_values(None, None, &[("fee", ONE_CKB)], 0);
+        assert_eq!(value["error"], serde_json::json!("No price available"));
+    }
+}
@@ -2,6 +2,7 @@ use std::env;
 use std::fmt;
 
 use atty;
+use ckb_sdk::ONE_CKB;
 use colored::Colorize;
 
 use crate::utils::json_color::Colorizer;
@@ -86,12 +87,42 @@ impl ColorWhen {
 
 pub trait Printable {
     fn render(&self, format: OutputFormat, color: bool) -> String;
+
+    // Json value of the output, None for plain text
+    fn json_value(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+/// Plain text output, rendered as is in every format
+pub struct Text(String);
+
+impl From<String> for Text {
+    fn from(text: String) -> Text {
+        Text(text)
+    }
+}
+
+impl From<&str> for Text {
+    fn from(text: &str) -> Text {
+        Text(text.to_owned())
+    }
+}
+
+impl Printable for Text {
+    fn render(&self, _format: OutputFormat, _color: bool) -> String {
+        self.0.clone()
+    }
 }
 
 impl Printable for Box<dyn Printable> {
     fn render(&self, format: OutputFormat, color: bool) -> String {
         self.as_ref().render(format, color)
     }
+
+    fn json_value(&self) -> Option<serde_json::Value> {
+        self.as_ref().json_value()
+    }
 }
 
 impl<T: ?Sized> Printable for T
@@ -99,10 +130,11 @@ where
     T: serde::ser::Serialize,
 {
     fn render(&self, format: OutputFormat, color: bool) -> String {
+        let mut value = serde_json::to_value(self).unwrap();
+        canonicalize(&mut value, false);
         match format {
-            OutputFormat::Yaml => yaml_ser::to_string(self, color).unwrap(),
+            OutputFormat::Yaml => yaml_ser::to_string(&value, color).unwrap(),
             OutputFormat::Json => {
-                let value = serde_json::to_value(self).unwrap();
                 if color {
                     Colorizer::arbitrary().colorize_json_value(&value).unwrap()
                 } else {
@@ -111,6 +143,66 @@ where
             }
         }
     }
+
+    fn json_value(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self).ok()
+    }
+}
+
+// Fields of binary data besides `*hash` / `*hashes`, other strings (memos,
+// labels, user input) are never changed
+const HEX_FIELDS: [&str; 10] = [
+    "args",
+    "lock_arg",
+    "data",
+    "outputs_data",
+    "witnesses",
+    "header_deps",
+    "signature",
+    "pubkey",
+    "message",
+    "proposals",
+];
+
+/// Capacity in CKB with all 8 decimals, the one format of capacities shown
+/// as CKB
+pub fn format_capacity(shannons: u64) -> String {
+    format!("{}.{:08}", shannons / ONE_CKB, shannons % ONE_CKB)
+}
+
+fn is_hex_field(key: &str) -> bool {
+    key.ends_with("hash") || key.ends_with("hashes") || HEX_FIELDS.contains(&key)
+}
+
+// Make output deterministic: object keys are sorted, hex strings of binary
+// fields are lowercased.
+fn canonicalize(value: &mut serde_json::Value, hex: bool) {
+    match value {
+        serde_json::Value::String(content) => {
+            if hex
+                && (content.starts_with("0x") || content.starts_with("0X"))
+                && content.len() > 2
+                && content[2..].chars().all(|c| c.is_ascii_hexdigit())
+            {
+                *content = content.to_ascii_lowercase();
+            }
+        }
+        serde_json::Value::Array(values) => {
+            values.iter_mut().for_each(|value| canonicalize(value, hex))
+        }
+        serde_json::Value::Object(map) => {
+            // Sorted here, serde_json keeps insertion order with `preserve_order`
+            let mut entries = std::mem::replace(map, serde_json::Map::new())
+                .into_iter()
+                .collect::<Vec<_>>();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            for (key, mut value) in entries {
+                canonicalize(&mut value, is_hex_field(&key));
+                map.insert(key, value);
+            }
+        }
+        _ => {}
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -158,3 +250,42 @@ impl<'a> TypedStr<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        let mut value = serde_json::json!({
+            "tx_hash": "0xABCD",
+            "memo": "0xABCD",
+            "lock": {"code_hash": "0xEF", "args": "0xAA"},
+            "witnesses": ["0xBB"],
+            "capacity": 100,
+        });
+        canonicalize(&mut value, false);
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "tx_hash": "0xabcd",
+                "memo": "0xABCD",
+                "lock": {"code_hash": "0xef", "args": "0xaa"},
+                "witnesses": ["0xbb"],
+                "capacity": 100,
+            })
+        );
+        let keys = value.as_object().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec!["capacity", "lock", "memo", "tx_hash", "witnesses"]
+        );
+    }
+
+    #[test]
+    fn test_format_capacity() {
+        assert_eq!(format_capacity(0), "0.00000000");
+        assert_eq!(format_capacity(1), "0.00000001");
+        assert_eq!(format_capacity(12_350_000_000), "123.50000000");
+    }
+}
@@ -0,0 +1,35 @@
+use qrcode::{Color, QrCode};
+
+// Light modules around the code, scanners need it to find the code
+const QUIET_ZONE: usize = 2;
+
+/// Render a QR code of the text with unicode half blocks, one character
+/// holds two rows of modules. Light modules are drawn (for dark terminals).
+pub fn render_qr(text: &str) -> Result<String, String> {
+    let code = QrCode::new(text.as_bytes()).map_err(|err| format!("QR code error: {}", err))?;
+    let width = code.width();
+    let is_light = |x: usize, y: usize| -> bool {
+        if x < QUIET_ZONE || y < QUIET_ZONE || x >= width + QUIET_ZONE || y >= width + QUIET_ZONE {
+            return true;
+        }
+        code[(x - QUIET_ZONE, y - QUIET_ZONE)] == Color::Light
+    };
+    let size = width + QUIET_ZONE * 2;
+    let mut lines = Vec::with_capacity((size + 1) / 2);
+    for y in (0..size).step_by(2) {
+        let line = (0..size)
+            .map(|x| {
+                let upper = is_light(x, y);
+                let lower = y + 1 >= size || is_light(x, y + 1);
+                match (upper, lower) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                }
+            })
+            .collect::<String>();
+        lines.push(line);
+    }
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,186 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rand::Rng;
+use serde_derive::{Deserialize, Serialize};
+
+use super::other::write_private_file;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApprovalStatus {
+    // Waiting for a human to approve or reject
+    Pending,
+    // Approved, signed and sent
+    Sent,
+    // Approved, but signing or sending failed
+    Failed,
+    Rejected,
+    // Not approved before `expires_at`
+    Expired,
+}
+
+/// A transfer proposed by a serve mode client, it is only signed and sent
+/// after approved in interactive mode.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct TransferProposal {
+    pub id: String,
+    // Name of the API token which proposed this transfer
+    pub token_name: String,
+    // None means the default account
+    pub from_account: Option<String>,
+    pub to_address: String,
+    // Unit: shannons
+    pub capacity: u64,
+    pub tx_fee: u64,
+    pub status: ApprovalStatus,
+    pub created_at: u64,
+    pub expires_at: u64,
+    #[serde(default)]
+    pub decided_at: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Transfer proposals of serve mode saved in `<ckb-cli-dir>/serve-approvals.json`
+pub struct ServeApprovals {
+    path: PathBuf,
+    proposals: Vec<TransferProposal>,
+}
+
+impl ServeApprovals {
+    pub fn load(path: &Path) -> Result<ServeApprovals, String> {
+        let proposals = if path.exists() {
+            let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Invalid serve approvals file: {}", err))?
+        } else {
+            Vec::new()
+        };
+        Ok(ServeApprovals {
+            path: path.to_path_buf(),
+            proposals,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let content =
+            serde_json::to_string_pretty(&self.proposals).map_err(|err| err.to_string())?;
+        write_private_file(&self.path, content.as_bytes(), true)
+    }
+
+    pub fn proposals(&self) -> &[TransferProposal] {
+        &self.proposals
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn propose(
+        &mut self,
+        token_name: &str,
+        from_account: Option<String>,
+        to_address: String,
+        capacity: u64,
+        tx_fee: u64,
+        created_at: u64,
+        expires_at: u64,
+    ) -> &TransferProposal {
+        let id: [u8; 8] = rand::thread_rng().gen();
+        self.proposals.push(TransferProposal {
+            id: faster_hex::hex_string(&id).unwrap(),
+            token_name: token_name.to_owned(),
+            from_account,
+            to_address,
+            capacity,
+            tx_fee,
+            status: ApprovalStatus::Pending,
+            created_at,
+            expires_at,
+            decided_at: None,
+            error: None,
+        });
+        self.proposals.last().unwrap()
+    }
+
+    /// Mark pending proposals past their expiry time as expired, returns how
+    /// many are expired.
+    pub fn expire(&mut self, now: u64) -> usize {
+        let mut count = 0;
+        for proposal in &mut self.proposals {
+            if proposal.status == ApprovalStatus::Pending && proposal.expires_at <= now {
+                proposal.status = ApprovalStatus::Expired;
+                proposal.decided_at = Some(proposal.expires_at);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// The pending proposal which is about to be approved or rejected
+    pub fn pending(&self, id: &str, now: u64) -> Result<&TransferProposal, String> {
+        let proposal = self
+            .proposals
+            .iter()
+            .find(|proposal| proposal.id == id)
+            .ok_or_else(|| format!("Proposal not found: {}", id))?;
+        if proposal.status != ApprovalStatus::Pending {
+            return Err(format!("Proposal {} is already {:?}", id, proposal.status));
+        }
+        if proposal.expires_at <= now {
+            return Err(format!("Proposal {} is expired", id));
+        }
+        Ok(proposal)
+    }
+
+    pub fn decide(
+        &mut self,
+        id: &str,
+        status: ApprovalStatus,
+        decided_at: u64,
+        error: Option<String>,
+    ) -> Result<&TransferProposal, String> {
+        let proposal = self
+            .proposals
+            .iter_mut()
+            .find(|proposal| proposal.id == id)
+            .ok_or_else(|| format!("Proposal not found: {}", id))?;
+        proposal.status = status;
+        proposal.decided_at = Some(decided_at);
+        proposal.error = error;
+        Ok(proposal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serve_approvals() {
+        let mut approvals = ServeApprovals {
+            path: PathBuf::new(),
+            proposals: Vec::new(),
+        };
+        let first = approvals
+            .propose("payout", None, "ckt1first".to_owned(), 100, 1, 0, 1000)
+            .id
+            .clone();
+        let second = approvals
+            .propose("payout", None, "ckt1second".to_owned(), 200, 1, 0, 5000)
+            .id
+            .clone();
+        assert!(approvals.pending(&first, 500).is_ok());
+        assert!(approvals.pending(&first, 1000).is_err());
+        assert!(approvals.pending("unknown", 0).is_err());
+
+        assert_eq!(approvals.expire(2000), 1);
+        assert_eq!(approvals.proposals()[0].status, ApprovalStatus::Expired);
+        assert_eq!(approvals.expire(2000), 0);
+
+        approvals
+            .decide(&second, ApprovalStatus::Rejected, 3000, None)
+            .unwrap();
+        assert!(approvals.pending(&second, 3000).is_err());
+        assert_eq!(approvals.expire(6000), 0);
+        assert_eq!(approvals.proposals()[1].decided_at, Some(3000));
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_hash::blake2b_256;
+use ckb_jsonrpc_types::JsonBytes;
+use ckb_sdk::{ReprMockTransaction, SECP256K1};
+use ckb_types::{H160, H256};
+use rand::Rng;
+use secp256k1::recovery::{RecoverableSignature, RecoveryId};
+use serde_derive::{Deserialize, Serialize};
+
+use super::other::write_private_file;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CeremonyStatus {
+    // Waiting for signatures
+    Collecting,
+    // Signatures put into the transaction by the coordinator
+    Assembled,
+    // Not assembled before `expires_at`
+    Expired,
+}
+
+/// An unsigned multisig transaction published by a coordinator, signers
+/// submit their signatures of `message` until the threshold is reached.
+/// Signers on other machines sign the exported `CeremonyRequest` file and
+/// send back a `CeremonySignature` file, ckb-cli does not transfer them.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MultisigCeremony {
+    pub id: String,
+    // Name of the API token which published the ceremony
+    pub token_name: String,
+    pub tx: ReprMockTransaction,
+    pub multisig_config: JsonBytes,
+    // Lock hash of the multisig inputs
+    pub lock_hash: H256,
+    // Signing message of the multisig lock group
+    pub message: H256,
+    // Signer key (blake160 of pubkey) => signature
+    #[serde(default)]
+    pub signatures: BTreeMap<H160, JsonBytes>,
+    pub status: CeremonyStatus,
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+impl MultisigCeremony {
+    /// Keys (blake160 of pubkey) in the multisig config, in order
+    pub fn members(&self) -> Vec<H160> {
+        self.multisig_config.as_bytes()[4..]
+            .chunks(20)
+            .map(|hash| H160::from_slice(hash).expect("Convert to H160 failed"))
+            .collect()
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.multisig_config.as_bytes()[2] as usize
+    }
+
+    pub fn require_first_n(&self) -> usize {
+        self.multisig_config.as_bytes()[1] as usize
+    }
+
+    /// Check the signature is from a member who has not signed yet and save
+    /// it, returns the key of the signer
+    pub fn submit(&mut self, signature: &[u8]) -> Result<H160, String> {
+        if self.status != CeremonyStatus::Collecting {
+            return Err(format!("Ceremony {} is already {:?}", self.id, self.status));
+        }
+        let signer = recover_signer(&self.message, signature)?;
+        if !self.members().contains(&signer) {
+            return Err(format!(
+                "Signed by {:#x}, which is not a member of the multisig config",
+                signer
+            ));
+        }
+        if self.signatures.contains_key(&signer) {
+            return Err(format!("{:#x} already signed", signer));
+        }
+        self.signatures
+            .insert(signer.clone(), JsonBytes::from_vec(signature.to_vec()));
+        Ok(signer)
+    }
+
+    /// Submit the signature file of a signer, it must be made for this
+    /// ceremony and message
+    pub fn import(&mut self, signature: &CeremonySignature) -> Result<H160, String> {
+        if signature.id != self.id {
+            return Err(format!(
+                "Signature is made for ceremony {}, not {}",
+                signature.id, self.id
+            ));
+        }
+        if signature.message != self.message {
+            return Err(format!(
+                "Signature is made for message {:#x}, not {:#x}",
+                signature.message, self.message
+            ));
+        }
+        self.submit(signature.signature.as_bytes())
+    }
+
+    /// `threshold` signatures in the order of their keys in the config, the
+    /// first `require_first_n` members must all have signed
+    pub fn ready_signatures(&self) -> Result<Vec<[u8; 65]>, String> {
+        let members = self.members();
+        if let Some(member) = members
+            .iter()
+            .take(self.require_first_n())
+            .find(|member| !self.signatures.contains_key(member))
+        {
+            return Err(format!(
+                "Waiting for signature of {:#x}, the first {} members must sign",
+                member,
+                self.require_first_n()
+            ));
+        }
+        let signatures = members
+            .iter()
+            .filter_map(|member| self.signatures.get(member))
+            .take(self.threshold())
+            .map(|signature| {
+                let mut bytes = [0u8; 65];
+                bytes.copy_from_slice(signature.as_bytes());
+                bytes
+            })
+            .collect::<Vec<_>>();
+        if signatures.len() < self.threshold() {
+            return Err(format!(
+                "Waiting for signatures: {} of {} submitted",
+                signatures.len(),
+                self.threshold()
+            ));
+        }
+        Ok(signatures)
+    }
+}
+
+/// Everything a signer needs to check and sign a ceremony, written by
+/// `serve multisig-export` and read by `serve multisig-sign`
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CeremonyRequest {
+    pub id: String,
+    pub tx: ReprMockTransaction,
+    pub multisig_config: JsonBytes,
+    pub lock_hash: H256,
+    pub message: H256,
+    pub expires_at: u64,
+}
+
+impl From<&MultisigCeremony> for CeremonyRequest {
+    fn from(ceremony: &MultisigCeremony) -> CeremonyRequest {
+        CeremonyRequest {
+            id: ceremony.id.clone(),
+            tx: ceremony.tx.clone(),
+            multisig_config: ceremony.multisig_config.clone(),
+            lock_hash: ceremony.lock_hash.clone(),
+            message: ceremony.message.clone(),
+            expires_at: ceremony.expires_at,
+        }
+    }
+}
+
+/// Signature of a signer, written by `serve multisig-sign` and read by
+/// `serve multisig-import`
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CeremonySignature {
+    pub id: String,
+    pub message: H256,
+    pub signature: JsonBytes,
+}
+
+/// Multisig ceremonies of serve mode saved in `<ckb-cli-dir>/serve-ceremonies.json`
+pub struct ServeCeremonies {
+    path: PathBuf,
+    ceremonies: Vec<MultisigCeremony>,
+}
+
+impl ServeCeremonies {
+    pub fn load(path: &Path) -> Result<ServeCeremonies, String> {
+        let ceremonies = if path.exists() {
+            let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Invalid serve ceremonies file: {}", err))?
+        } else {
+            Vec::new()
+        };
+        Ok(ServeCeremonies {
+            path: path.to_path_buf(),
+            ceremonies,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let content =
+            serde_json::to_string_pretty(&self.ceremonies).map_err(|err| err.to_string())?;
+        write_private_file(&self.path, content.as_bytes(), true)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn publish(
+        &mut self,
+        token_name: &str,
+        tx: ReprMockTransaction,
+        multisig_config: JsonBytes,
+        lock_hash: H256,
+        message: H256,
+        created_at: u64,
+        expires_at: u64,
+    ) -> &MultisigCeremony {
+        let id: [u8; 8] = rand::thread_rng().gen();
+        self.ceremonies.push(MultisigCeremony {
+            id: faster_hex::hex_string(&id).unwrap(),
+            token_name: token_name.to_owned(),
+            tx,
+            multisig_config,
+            lock_hash,
+            message,
+            signatures: BTreeMap::new(),
+            status: CeremonyStatus::Collecting,
+            created_at,
+            expires_at,
+        });
+        self.ceremonies.last().unwrap()
+    }
+
+    /// Mark collecting ceremonies past their expiry time as expired, returns
+    /// how many are expired.
+    pub fn expire(&mut self, now: u64) -> usize {
+        let mut count = 0;
+        for ceremony in &mut self.ceremonies {
+            if ceremony.status == CeremonyStatus::Collecting && ceremony.expires_at <= now {
+                ceremony.status = CeremonyStatus::Expired;
+                count += 1;
+            }
+        }
+        count
+    }
+
+    pub fn get(&self, id: &str) -> Result<&MultisigCeremony, String> {
+        self.ceremonies
+            .iter()
+            .find(|ceremony| ceremony.id == id)
+            .ok_or_else(|| format!("Ceremony not found: {}", id))
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Result<&mut MultisigCeremony, String> {
+        self.ceremonies
+            .iter_mut()
+            .find(|ceremony| ceremony.id == id)
+            .ok_or_else(|| format!("Ceremony not found: {}", id))
+    }
+}
+
+/// Key (blake160 of pubkey) of a recoverable signature (65 bytes) of `message`
+pub fn recover_signer(message: &H256, signature: &[u8]) -> Result<H160, String> {
+    if signature.len() != 65 {
+        return Err(format!("Invalid signature length: {}", signature.len()));
+    }
+    let recovery_id =
+        RecoveryId::from_i32(i32::from(signature[64])).map_err(|err| err.to_string())?;
+    let signature = RecoverableSignature::from_compact(&signature[0..64], recovery_id)
+        .map_err(|err| err.to_string())?;
+    let message =
+        secp256k1::Message::from_slice(message.as_bytes()).map_err(|err| err.to_string())?;
+    let pubkey = SECP256K1
+        .recover(&message, &signature)
+        .map_err(|err| format!("Invalid signature: {}", err))?;
+    Ok(
+        H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20])
+            .expect("Convert to H160 failed"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_sdk::{multisig_config, MockTransaction};
+
+    fn sign(privkey: &secp256k1::SecretKey, message: &H256) -> Vec<u8> {
+        let signature = SECP256K1.sign_recoverable(
+            &secp256k1::Message::from_slice(message.as_bytes()).unwrap(),
+            privkey,
+        );
+        let (recov_id, data) = signature.serialize_compact();
+        let mut bytes = data.to_vec();
+        bytes.push(recov_id.to_i32() as u8);
+        bytes
+    }
+
+    #[test]
+    fn test_multisig_ceremony() {
+        let privkeys = (1..=3u8)
+            .map(|index| secp256k1::SecretKey::from_slice(&[index; 32]).unwrap())
+            .collect::<Vec<_>>();
+        let members = privkeys
+            .iter()
+            .map(|privkey| {
+                let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, privkey);
+                H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20]).unwrap()
+            })
+            .collect::<Vec<_>>();
+        // 2 of 3, the first member must sign
+        let config = multisig_config(1, 2, &members).unwrap();
+        let mut ceremonies = ServeCeremonies {
+            path: PathBuf::new(),
+            ceremonies: Vec::new(),
+        };
+        let message = H256::from([7u8; 32]);
+        let id = ceremonies
+            .publish(
+                "coordinator",
+                MockTransaction::default().into(),
+                JsonBytes::from_bytes(config),
+                H256::default(),
+                message.clone(),
+                0,
+                1000,
+            )
+            .id
+            .clone();
+        let ceremony = ceremonies.get_mut(&id).unwrap();
+        assert_eq!(ceremony.members(), members);
+
+        assert_eq!(
+            ceremony.submit(&sign(&privkeys[2], &message)).unwrap(),
+            members[2]
+        );
+        assert!(ceremony.submit(&sign(&privkeys[2], &message)).is_err());
+        let outsider = secp256k1::SecretKey::from_slice(&[9u8; 32]).unwrap();
+        assert!(ceremony.submit(&sign(&outsider, &message)).is_err());
+        assert!(ceremony
+            .submit(&sign(&privkeys[1], &H256::from([8u8; 32])))
+            .is_err());
+        // Threshold is not enough without the first member
+        assert!(ceremony.submit(&sign(&privkeys[1], &message)).is_ok());
+        assert!(ceremony.ready_signatures().is_err());
+
+        let first = sign(&privkeys[0], &message);
+        let mut imported = CeremonySignature {
+            id: "other".to_owned(),
+            message: message.clone(),
+            signature: JsonBytes::from_vec(first.clone()),
+        };
+        assert!(ceremony.import(&imported).is_err());
+        imported.id = id.clone();
+        imported.message = H256::from([8u8; 32]);
+        assert!(ceremony.import(&imported).is_err());
+        imported.message = message.clone();
+        assert_eq!(ceremony.import(&imported).unwrap(), members[0]);
+        let signatures = ceremony.ready_signatures().unwrap();
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(&signatures[0][..], &first[..]);
+
+        assert_eq!(ceremonies.expire(2000), 1);
+        assert!(ceremonies
+            .get_mut(&id)
+            .unwrap()
+            .submit(&sign(&privkeys[2], &message))
+            .is_err());
+    }
+}
@@ -0,0 +1,307 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_hash::blake2b_256;
+use ckb_sdk::wallet::{Crypto, ScryptType};
+use ckb_types::H256;
+use rand::Rng;
+use serde_derive::{Deserialize, Serialize};
+
+use super::other::write_private_file;
+
+// Encrypted with the admin password, decrypting it checks the password
+const ADMIN_CHECK: &[u8] = b"ckb-cli serve admin";
+
+/// What a serve mode API token is allowed to do, each level includes the
+/// ones before it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenPermission {
+    // Query chain state and balances
+    ReadOnly,
+    // Build unsigned transactions
+    BuildOnly,
+    // Sign and send transactions, subject to the spending limit
+    SignAndSend,
+}
+
+impl TokenPermission {
+    pub fn from_name(name: &str) -> Result<TokenPermission, String> {
+        match name {
+            "read-only" => Ok(TokenPermission::ReadOnly),
+            "build-only" => Ok(TokenPermission::BuildOnly),
+            "sign-and-send" => Ok(TokenPermission::SignAndSend),
+            _ => Err(format!(
+                "Invalid permission: {}, expected read-only, build-only or sign-and-send",
+                name
+            )),
+        }
+    }
+
+    pub fn allows(self, required: TokenPermission) -> bool {
+        self >= required
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ServeToken {
+    pub name: String,
+    // blake2b_256 of the token secret, the secret itself is never saved
+    pub token_hash: H256,
+    pub permission: TokenPermission,
+    // Max total capacity (in shannons) sent with this token, None is unlimited
+    #[serde(default)]
+    pub spending_limit: Option<u64>,
+    #[serde(default)]
+    pub spent: u64,
+    pub created_at: u64,
+}
+
+impl ServeToken {
+    pub fn remaining(&self) -> Option<u64> {
+        self.spending_limit
+            .map(|limit| limit.saturating_sub(self.spent))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum TokensFile {
+    Current {
+        // Crypto json of ADMIN_CHECK
+        admin: Option<serde_json::Value>,
+        tokens: Vec<ServeToken>,
+    },
+    // Tokens only, before the admin password
+    Legacy(Vec<ServeToken>),
+}
+
+/// API tokens of serve mode saved in `<ckb-cli-dir>/serve-tokens.json`,
+/// managing them requires the admin password
+pub struct ServeTokens {
+    path: PathBuf,
+    admin: Option<serde_json::Value>,
+    tokens: Vec<ServeToken>,
+}
+
+impl ServeTokens {
+    pub fn load(path: &Path) -> Result<ServeTokens, String> {
+        let file = if path.exists() {
+            let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Invalid serve tokens file: {}", err))?
+        } else {
+            TokensFile::Legacy(Vec::new())
+        };
+        let (admin, tokens) = match file {
+            TokensFile::Current { admin, tokens } => (admin, tokens),
+            TokensFile::Legacy(tokens) => (None, tokens),
+        };
+        Ok(ServeTokens {
+            path: path.to_path_buf(),
+            admin,
+            tokens,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = TokensFile::Current {
+            admin: self.admin.clone(),
+            tokens: self.tokens.clone(),
+        };
+        let content = serde_json::to_string_pretty(&file).map_err(|err| err.to_string())?;
+        write_private_file(&self.path, content.as_bytes(), true)
+    }
+
+    pub fn has_admin_password(&self) -> bool {
+        self.admin.is_some()
+    }
+
+    /// Set the admin password, only allowed once (no tokens file or a file
+    /// written before admin passwords)
+    pub fn set_admin_password(&mut self, password: &[u8]) -> Result<(), String> {
+        if self.admin.is_some() {
+            return Err("Admin password is already set".to_owned());
+        }
+        let crypto = Crypto::encrypt_key_scrypt(ADMIN_CHECK, password, ScryptType::default());
+        self.admin = Some(crypto.to_json());
+        Ok(())
+    }
+
+    pub fn verify_admin_password(&self, password: &[u8]) -> Result<(), String> {
+        let admin = self
+            .admin
+            .as_ref()
+            .ok_or_else(|| "Admin password is not set".to_owned())?;
+        let crypto = Crypto::from_json(admin).map_err(|err| err.to_string())?;
+        match crypto.decrypt(password) {
+            Ok(ref plaintext) if plaintext.as_slice() == ADMIN_CHECK => Ok(()),
+            _ => Err("Invalid admin password".to_owned()),
+        }
+    }
+
+    pub fn tokens(&self) -> &[ServeToken] {
+        &self.tokens
+    }
+
+    /// Create a token, returns the secret (hex), it is only shown once
+    pub fn add(
+        &mut self,
+        name: &str,
+        permission: TokenPermission,
+        spending_limit: Option<u64>,
+        created_at: u64,
+    ) -> Result<String, String> {
+        if name.is_empty() {
+            return Err("Token name is empty".to_owned());
+        }
+        if self.tokens.iter().any(|token| token.name == name) {
+            return Err(format!("Token already exists: {}", name));
+        }
+        let secret: [u8; 32] = rand::thread_rng().gen();
+        let secret = faster_hex::hex_string(&secret).unwrap();
+        self.tokens.push(ServeToken {
+            name: name.to_owned(),
+            token_hash: hash_secret(&secret),
+            permission,
+            spending_limit,
+            spent: 0,
+            created_at,
+        });
+        Ok(secret)
+    }
+
+    pub fn revoke(&mut self, name: &str) -> Result<ServeToken, String> {
+        let index = self
+            .tokens
+            .iter()
+            .position(|token| token.name == name)
+            .ok_or_else(|| format!("Token not found: {}", name))?;
+        Ok(self.tokens.remove(index))
+    }
+
+    /// Check the token secret has the permission and enough spending limit
+    /// left for `amount` shannons
+    pub fn authorize(
+        &self,
+        secret: &str,
+        required: TokenPermission,
+        amount: u64,
+    ) -> Result<&ServeToken, String> {
+        let token_hash = hash_secret(secret);
+        let token = self
+            .tokens
+            .iter()
+            .find(|token| token.token_hash == token_hash)
+            .ok_or_else(|| "Invalid token".to_owned())?;
+        if !token.permission.allows(required) {
+            return Err(format!(
+                "Token {} has {:?} permission, {:?} required",
+                token.name, token.permission, required
+            ));
+        }
+        if let Some(remaining) = token.remaining() {
+            if amount > remaining {
+                return Err(format!(
+                    "Token {} exceeds spending limit: {} shannons left, {} requested",
+                    token.name, remaining, amount
+                ));
+            }
+        }
+        Ok(token)
+    }
+
+    /// Add `amount` shannons sent with the token, fails if it exceeds the
+    /// spending limit left
+    pub fn record_spend(&mut self, name: &str, amount: u64) -> Result<(), String> {
+        let token = self
+            .tokens
+            .iter_mut()
+            .find(|token| token.name == name)
+            .ok_or_else(|| format!("Token not found: {}", name))?;
+        if let Some(remaining) = token.remaining() {
+            if amount > remaining {
+                return Err(format!(
+                    "Token {} exceeds spending limit: {} shannons left, {} requested",
+                    token.name, remaining, amount
+                ));
+            }
+        }
+        token.spent += amount;
+        Ok(())
+    }
+}
+
+fn hash_secret(secret: &str) -> H256 {
+    H256::from(blake2b_256(secret.trim().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serve_tokens() {
+        let mut tokens = ServeTokens {
+            path: PathBuf::new(),
+            admin: None,
+            tokens: Vec::new(),
+        };
+        let reader = tokens
+            .add("dashboard", TokenPermission::ReadOnly, None, 0)
+            .unwrap();
+        let sender = tokens
+            .add("payout", TokenPermission::SignAndSend, Some(1000), 0)
+            .unwrap();
+        assert!(tokens
+            .add("payout", TokenPermission::ReadOnly, None, 0)
+            .is_err());
+
+        assert!(tokens
+            .authorize(&reader, TokenPermission::ReadOnly, 0)
+            .is_ok());
+        assert!(tokens
+            .authorize(&reader, TokenPermission::BuildOnly, 0)
+            .is_err());
+        assert!(tokens
+            .authorize("00", TokenPermission::ReadOnly, 0)
+            .is_err());
+
+        assert!(tokens
+            .authorize(&sender, TokenPermission::SignAndSend, 1000)
+            .is_ok());
+        tokens.tokens[1].spent = 600;
+        assert!(tokens
+            .authorize(&sender, TokenPermission::SignAndSend, 500)
+            .is_err());
+        assert_eq!(tokens.tokens()[1].remaining(), Some(400));
+        assert!(tokens.record_spend("payout", 500).is_err());
+        tokens.record_spend("payout", 400).unwrap();
+        assert_eq!(tokens.tokens()[1].remaining(), Some(0));
+
+        tokens.revoke("payout").unwrap();
+        assert!(tokens
+            .authorize(&sender, TokenPermission::ReadOnly, 0)
+            .is_err());
+        assert!(TokenPermission::from_name("admin").is_err());
+    }
+
+    #[test]
+    fn test_admin_password() {
+        let mut tokens = ServeTokens {
+            path: PathBuf::new(),
+            admin: None,
+            tokens: Vec::new(),
+        };
+        assert!(tokens.verify_admin_password(b"abc").is_err());
+        tokens.set_admin_password(b"abc").unwrap();
+        assert!(tokens.set_admin_password(b"def").is_err());
+        tokens.verify_admin_password(b"abc").unwrap();
+        assert!(tokens.verify_admin_password(b"abd").is_err());
+
+        match serde_json::from_str("[]").unwrap() {
+            TokensFile::Legacy(tokens) => assert!(tokens.is_empty()),
+            TokensFile::Current { .. } => panic!("expect legacy tokens file"),
+        }
+    }
+}
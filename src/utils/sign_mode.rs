@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_sdk::eth_personal_message;
+use ckb_types::{H160, H256};
+use serde_derive::{Deserialize, Serialize};
+
+/// How the signing message of an account is hashed before signing
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignMode {
+    // Sign the message directly (secp256k1_blake160 style)
+    Default,
+    // Sign keccak256("\x19Ethereum Signed Message:\n32" + message)
+    EthPersonal,
+}
+
+impl SignMode {
+    pub fn from_name(name: &str) -> Result<SignMode, String> {
+        match name {
+            "default" => Ok(SignMode::Default),
+            "eth-personal" => Ok(SignMode::EthPersonal),
+            _ => Err(format!(
+                "Invalid sign mode: {}, expected default or eth-personal",
+                name
+            )),
+        }
+    }
+
+    pub fn message(self, message: &H256) -> H256 {
+        match self {
+            SignMode::Default => message.clone(),
+            SignMode::EthPersonal => eth_personal_message(message),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct AccountSignMode {
+    pub mode: SignMode,
+    // Ethereum address of the account key, set for eth-personal mode
+    #[serde(default)]
+    pub eth_address: Option<H160>,
+}
+
+/// Sign modes of accounts (by lock arg) saved in `<ckb-cli-dir>/sign-modes.json`,
+/// accounts not in it use the default mode.
+pub struct SignModes {
+    path: PathBuf,
+    accounts: BTreeMap<H160, AccountSignMode>,
+}
+
+impl SignModes {
+    pub fn load(path: &Path) -> Result<SignModes, String> {
+        let accounts = if path.exists() {
+            let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Invalid sign modes file: {}", err))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(SignModes {
+            path: path.to_path_buf(),
+            accounts,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let content =
+            serde_json::to_string_pretty(&self.accounts).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+
+    pub fn set(&mut self, lock_arg: H160, mode: AccountSignMode) {
+        if mode.mode == SignMode::Default {
+            self.accounts.remove(&lock_arg);
+        } else {
+            self.accounts.insert(lock_arg, mode);
+        }
+    }
+
+    pub fn get(&self, lock_arg: &H160) -> Option<&AccountSignMode> {
+        self.accounts.get(lock_arg)
+    }
+
+    /// Resolve the key identifier in lock args (lock arg or ethereum address)
+    /// to the account and its sign mode. Only an ethereum address identifier
+    /// signs in eth-personal mode, a lock arg identifier is always from a
+    /// blake160 lock (secp256k1_blake160, omnilock secp256k1).
+    pub fn resolve(&self, identifier: &H160) -> (H160, SignMode) {
+        self.accounts
+            .iter()
+            .find(|(_, mode)| mode.eth_address.as_ref() == Some(identifier))
+            .map(|(lock_arg, mode)| (lock_arg.clone(), mode.mode))
+            .unwrap_or_else(|| (identifier.clone(), SignMode::Default))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_modes() {
+        let lock_arg = H160::from_slice(&[1u8; 20]).unwrap();
+        let eth_address = H160::from_slice(&[2u8; 20]).unwrap();
+        let other = H160::from_slice(&[3u8; 20]).unwrap();
+        let mut modes = SignModes {
+            path: PathBuf::new(),
+            accounts: BTreeMap::new(),
+        };
+        modes.set(
+            lock_arg.clone(),
+            AccountSignMode {
+                mode: SignMode::EthPersonal,
+                eth_address: Some(eth_address.clone()),
+            },
+        );
+        assert_eq!(
+            modes.resolve(&lock_arg),
+            (lock_arg.clone(), SignMode::Default)
+        );
+        assert_eq!(
+            modes.resolve(&eth_address),
+            (lock_arg.clone(), SignMode::EthPersonal)
+        );
+        assert_eq!(modes.resolve(&other), (other, SignMode::Default));
+
+        let message = H256::from([5u8; 32]);
+        assert_eq!(SignMode::Default.message(&message), message);
+        assert_ne!(SignMode::EthPersonal.message(&message), message);
+
+        modes.set(
+            lock_arg.clone(),
+            AccountSignMode {
+                mode: SignMode::Default,
+                eth_address: None,
+            },
+        );
+        assert!(modes.get(&lock_arg).is_none());
+        assert!(SignMode::from_name("eth").is_err());
+    }
+}
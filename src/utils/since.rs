@@ -0,0 +1,194 @@
+use ckb_types::core::{EpochNumberWithFraction, HeaderView};
+
+// See: https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0017-tx-valid-since/0017-tx-valid-since.md
+const SINCE_RELATIVE_FLAG: u64 = 0x8000_0000_0000_0000;
+const SINCE_METRIC_MASK: u64 = 0x6000_0000_0000_0000;
+const SINCE_RESERVED_MASK: u64 = 0x1f00_0000_0000_0000;
+const SINCE_VALUE_MASK: u64 = 0x00ff_ffff_ffff_ffff;
+const SINCE_METRIC_BLOCK_NUMBER: u64 = 0x0000_0000_0000_0000;
+const SINCE_METRIC_EPOCH: u64 = 0x2000_0000_0000_0000;
+const SINCE_METRIC_TIMESTAMP: u64 = 0x4000_0000_0000_0000;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SinceMetric {
+    BlockNumber(u64),
+    Epoch(EpochNumberWithFraction),
+    // Unit: second
+    Timestamp(u64),
+}
+
+/// Decoded `since` field of a transaction input
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Since {
+    pub relative: bool,
+    pub metric: SinceMetric,
+}
+
+/// The chain state a since is compared with: the tip for absolute since, the
+/// block of input cell for relative since.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ChainPoint {
+    pub number: u64,
+    pub epoch: EpochNumberWithFraction,
+    // Unit: second
+    pub timestamp: u64,
+}
+
+impl From<&HeaderView> for ChainPoint {
+    fn from(header: &HeaderView) -> ChainPoint {
+        ChainPoint {
+            number: header.number(),
+            epoch: header.epoch(),
+            timestamp: header.timestamp() / 1000,
+        }
+    }
+}
+
+// Epoch with fraction as a rational number: (numerator, denominator)
+fn epoch_rational(epoch: EpochNumberWithFraction) -> (u128, u128) {
+    let length = u128::from(epoch.length().max(1));
+    (
+        u128::from(epoch.number()) * length + u128::from(epoch.index()),
+        length,
+    )
+}
+
+fn epoch_cmp(a: EpochNumberWithFraction, b: EpochNumberWithFraction) -> std::cmp::Ordering {
+    let (a_num, a_den) = epoch_rational(a);
+    let (b_num, b_den) = epoch_rational(b);
+    (a_num * b_den).cmp(&(b_num * a_den))
+}
+
+// Fraction of `b` is converted to the length of `a` (rounded up), so the
+// result is never earlier than the exact sum.
+fn epoch_add(a: EpochNumberWithFraction, b: EpochNumberWithFraction) -> EpochNumberWithFraction {
+    let length = a.length().max(1);
+    let b_length = b.length().max(1);
+    let index = a.index() + (b.index() * length + b_length - 1) / b_length;
+    EpochNumberWithFraction::new(
+        a.number() + b.number() + index / length,
+        index % length,
+        length,
+    )
+}
+
+impl Since {
+    /// Return `None` for zero since (no constraint)
+    pub fn parse(value: u64) -> Result<Option<Since>, String> {
+        if value == 0 {
+            return Ok(None);
+        }
+        if value & SINCE_RESERVED_MASK != 0 {
+            return Err(format!(
+                "Invalid since {:#x}: reserved bits are not zero",
+                value
+            ));
+        }
+        let metric_value = value & SINCE_VALUE_MASK;
+        let metric = match value & SINCE_METRIC_MASK {
+            SINCE_METRIC_BLOCK_NUMBER => SinceMetric::BlockNumber(metric_value),
+            SINCE_METRIC_EPOCH => {
+                SinceMetric::Epoch(EpochNumberWithFraction::from_full_value(metric_value))
+            }
+            SINCE_METRIC_TIMESTAMP => SinceMetric::Timestamp(metric_value),
+            _ => return Err(format!("Invalid since {:#x}: unknown metric flag", value)),
+        };
+        Ok(Some(Since {
+            relative: value & SINCE_RELATIVE_FLAG != 0,
+            metric,
+        }))
+    }
+
+    /// The absolute point the chain must reach, `base` is required for relative since
+    pub fn target(&self, base: Option<&ChainPoint>) -> Result<SinceMetric, String> {
+        if !self.relative {
+            return Ok(self.metric);
+        }
+        let base = base.ok_or_else(|| "Relative since requires the input block".to_owned())?;
+        Ok(match self.metric {
+            SinceMetric::BlockNumber(number) => SinceMetric::BlockNumber(base.number + number),
+            SinceMetric::Epoch(epoch) => SinceMetric::Epoch(epoch_add(base.epoch, epoch)),
+            SinceMetric::Timestamp(seconds) => SinceMetric::Timestamp(base.timestamp + seconds),
+        })
+    }
+}
+
+impl SinceMetric {
+    pub fn is_reached(&self, tip: &ChainPoint) -> bool {
+        match self {
+            SinceMetric::BlockNumber(number) => tip.number >= *number,
+            SinceMetric::Epoch(epoch) => epoch_cmp(tip.epoch, *epoch) != std::cmp::Ordering::Less,
+            SinceMetric::Timestamp(seconds) => tip.timestamp >= *seconds,
+        }
+    }
+
+    /// Json description of the target and how far it is from `tip`
+    pub fn describe(&self, tip: &ChainPoint) -> serde_json::Value {
+        match self {
+            SinceMetric::BlockNumber(number) => serde_json::json!({
+                "block_number": number,
+                "remaining_blocks": number.saturating_sub(tip.number),
+            }),
+            SinceMetric::Epoch(epoch) => serde_json::json!({
+                "epoch": format!("{}({}/{})", epoch.number(), epoch.index(), epoch.length()),
+                "remaining_epochs": epoch.number().saturating_sub(tip.epoch.number()),
+            }),
+            SinceMetric::Timestamp(seconds) => serde_json::json!({
+                "timestamp": seconds,
+                "remaining_seconds": seconds.saturating_sub(tip.timestamp),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_since() {
+        assert_eq!(Since::parse(0), Ok(None));
+        assert!(Since::parse(0x0100_0000_0000_0000).is_err());
+        assert!(Since::parse(0x6000_0000_0000_0001).is_err());
+
+        let tip = ChainPoint {
+            number: 100,
+            epoch: EpochNumberWithFraction::new(5, 50, 100),
+            timestamp: 1000,
+        };
+        let since = Since::parse(100).unwrap().unwrap();
+        assert!(!since.relative);
+        assert!(since.target(None).unwrap().is_reached(&tip));
+        assert!(!Since::parse(101)
+            .unwrap()
+            .unwrap()
+            .target(None)
+            .unwrap()
+            .is_reached(&tip));
+
+        // Relative 10 blocks after block 95
+        let base = ChainPoint {
+            number: 95,
+            epoch: EpochNumberWithFraction::new(5, 0, 100),
+            timestamp: 900,
+        };
+        let since = Since::parse(SINCE_RELATIVE_FLAG | 10).unwrap().unwrap();
+        assert!(since.target(None).is_err());
+        let target = since.target(Some(&base)).unwrap();
+        assert_eq!(target, SinceMetric::BlockNumber(105));
+        assert!(!target.is_reached(&tip));
+
+        // Relative half epoch: 5(0/100) + 0(1/2) = 5(50/100)
+        let half = EpochNumberWithFraction::new(0, 1, 2).full_value();
+        let since = Since::parse(SINCE_RELATIVE_FLAG | SINCE_METRIC_EPOCH | half)
+            .unwrap()
+            .unwrap();
+        let target = since.target(Some(&base)).unwrap();
+        assert!(target.is_reached(&tip));
+
+        let since = Since::parse(SINCE_METRIC_TIMESTAMP | 1001)
+            .unwrap()
+            .unwrap();
+        assert!(!since.target(None).unwrap().is_reached(&tip));
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use ckb_types::H256;
+use sparse_merkle_tree::{
+    blake2b::Blake2bHasher, default_store::DefaultStore, CompiledMerkleProof, SparseMerkleTree,
+    H256 as SmtH256,
+};
+
+use super::arg_parser::{ArgParser, FixedHashParser};
+
+// The tree used by CKB contracts: blake2b with ckb-default-hash personalization
+type Smt = SparseMerkleTree<Blake2bHasher, SmtH256, DefaultStore<SmtH256>>;
+
+/// Key/value pairs of a pairs file: a json/yaml object of 32 bytes hex keys
+/// to 32 bytes hex values. Zero values are absent keys.
+pub fn parse_pairs(content: &str) -> Result<BTreeMap<H256, H256>, String> {
+    serde_yaml::from_str(content)
+        .map_err(|err| err.to_string())
+        .or_else(|_| serde_json::from_str(content).map_err(|err| err.to_string()))
+        .map_err(|err| format!("Invalid pairs file: {}", err))
+}
+
+/// Parse a leaf of format: {key}:{value}
+pub fn parse_leaf(input: &str) -> Result<(H256, H256), String> {
+    let parts = input.split(':').collect::<Vec<_>>();
+    if parts.len() != 2 {
+        return Err(format!("Invalid leaf {}, format: {{key}}:{{value}}", input));
+    }
+    let parser = FixedHashParser::<H256>::default();
+    Ok((parser.parse(parts[0])?, parser.parse(parts[1])?))
+}
+
+fn to_smt(hash: &H256) -> SmtH256 {
+    SmtH256::from(hash.0)
+}
+
+fn from_smt(hash: &SmtH256) -> H256 {
+    H256::from_slice(hash.as_slice()).expect("smt hash is 32 bytes")
+}
+
+fn build_tree(pairs: &BTreeMap<H256, H256>) -> Result<Smt, String> {
+    let mut tree = Smt::default();
+    for (key, value) in pairs {
+        tree.update(to_smt(key), to_smt(value))
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(tree)
+}
+
+pub fn smt_root(pairs: &BTreeMap<H256, H256>) -> Result<H256, String> {
+    Ok(from_smt(build_tree(pairs)?.root()))
+}
+
+/// Root, proved leaves (zero value proves the key is absent) and the compiled
+/// proof of the keys, the format smt-based contracts read from witnesses.
+pub fn smt_proof(
+    pairs: &BTreeMap<H256, H256>,
+    keys: &[H256],
+) -> Result<(H256, Vec<(H256, H256)>, Vec<u8>), String> {
+    if keys.is_empty() {
+        return Err("At least one key is required".to_owned());
+    }
+    let tree = build_tree(pairs)?;
+    let leaves = keys
+        .iter()
+        .map(|key| {
+            let value = pairs.get(key).cloned().unwrap_or_default();
+            (key.clone(), value)
+        })
+        .collect::<Vec<_>>();
+    let proof = tree
+        .merkle_proof(keys.iter().map(to_smt).collect())
+        .map_err(|err| err.to_string())?
+        .compile(
+            leaves
+                .iter()
+                .map(|(key, value)| (to_smt(key), to_smt(value)))
+                .collect(),
+        )
+        .map_err(|err| err.to_string())?;
+    Ok((from_smt(tree.root()), leaves, proof.0))
+}
+
+pub fn smt_verify(root: &H256, proof: Vec<u8>, leaves: &[(H256, H256)]) -> Result<bool, String> {
+    CompiledMerkleProof(proof)
+        .verify::<Blake2bHasher>(
+            &to_smt(root),
+            leaves
+                .iter()
+                .map(|(key, value)| (to_smt(key), to_smt(value)))
+                .collect(),
+        )
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::h256;
+
+    #[test]
+    fn test_smt_proof() {
+        let mut pairs = BTreeMap::new();
+        assert_eq!(smt_root(&pairs).unwrap(), H256::default());
+        pairs.insert(h256!("0x1"), h256!("0xaa"));
+        pairs.insert(h256!("0x2"), h256!("0xbb"));
+        pairs.insert(h256!("0x3"), h256!("0xcc"));
+        let root = smt_root(&pairs).unwrap();
+        assert_ne!(root, H256::default());
+
+        // Inclusion of 0x1 and non-inclusion of 0x4
+        let keys = vec![h256!("0x1"), h256!("0x4")];
+        let (proof_root, leaves, proof) = smt_proof(&pairs, &keys).unwrap();
+        assert_eq!(proof_root, root);
+        assert_eq!(
+            leaves,
+            vec![
+                (h256!("0x1"), h256!("0xaa")),
+                (h256!("0x4"), H256::default())
+            ]
+        );
+        assert!(smt_verify(&root, proof.clone(), &leaves).unwrap());
+
+        let tampered = vec![
+            (h256!("0x1"), h256!("0xab")),
+            (h256!("0x4"), H256::default()),
+        ];
+        assert_ne!(smt_verify(&root, proof, &tampered), Ok(true));
+        assert!(smt_proof(&pairs, &[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_leaf() {
+        let key = format!("{:#x}", h256!("0x1"));
+        let value = format!("{:x}", h256!("0xaa"));
+        assert_eq!(
+            parse_leaf(&format!("{}:{}", key, value)).unwrap(),
+            (h256!("0x1"), h256!("0xaa"))
+        );
+        assert!(parse_leaf(&key).is_err());
+        assert!(parse_leaf(&format!("{}:0x12", key)).is_err());
+    }
+}